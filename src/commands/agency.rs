@@ -0,0 +1,251 @@
+use crate::commands;
+use crate::commands::gtfs::GtfsNode;
+use crate::commands::CommandInterpreter;
+use crate::commands::filter::{Filter, FilterError};
+use crate::commands::gtfs::GTFSCommandInterpreterError;
+use crate::commands::names::{self, NameError, Segment};
+use crate::commands::output::Output;
+use crate::commands::table::{SortKey, Table};
+use crate::gtfs::agency::{Agency, AGENCY_FIELDS};
+use crate::gtfs::view::ScheduleFilter;
+use crate::style::Colorize;
+use std::collections::{HashMap, HashSet};
+
+pub struct AgencyCommandInterpreter<'a>(pub &'a GtfsNode);
+
+#[derive(Debug)]
+pub enum AgencyCommandError {
+    InvalidCommand(String),
+    NoSuchAgency(String),
+    ErrorExecutingCommandForAgency(String, Box<GTFSCommandInterpreterError>),
+    InvalidFilter(FilterError),
+    NameError(NameError),
+}
+
+impl std::fmt::Display for AgencyCommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AgencyCommandError::InvalidCommand(command) => write!(f, "Invalid command: {}", command),
+            AgencyCommandError::NoSuchAgency(agency_id) => write!(f, "No such agency: {}", agency_id),
+            AgencyCommandError::ErrorExecutingCommandForAgency(agency_id, cause) => write!(f, "Error executing command for agency {}: {}", agency_id, **cause),
+            AgencyCommandError::InvalidFilter(e) => write!(f, "{}", e),
+            AgencyCommandError::NameError(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for AgencyCommandError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AgencyCommandError::ErrorExecutingCommandForAgency(_, cause) => Some(cause.as_ref()),
+            AgencyCommandError::InvalidFilter(e) => Some(e),
+            AgencyCommandError::NameError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> CommandInterpreter for AgencyCommandInterpreter<'a> {
+    type CommandResult = Output;
+    type CommandError = AgencyCommandError;
+
+    fn interpret(&self, command: &str) -> Result<Self::CommandResult, Self::CommandError> {
+        if let Some((agencies_command, args)) = commands::find_command(AGENCIES_COMMANDS, command) {
+            return (agencies_command.run)(self, args);
+        }
+        let (segment, rest) = names::split_segment(command);
+        let agency_id = self.agency_id(segment)?;
+        let Some(agency) = self.0.gtfs.agencies.agencies.get(&agency_id) else {
+            return Err(AgencyCommandError::InvalidCommand(command.to_string()));
+        };
+        // commands about the agency itself are run here; anything else is run on the agency's node.
+        if let Some((agency_command, args)) = commands::find_command(AGENCY_COMMANDS, rest) {
+            return (agency_command.run)(self, agency, args);
+        }
+        // help at an agency lists the commands about the agency before those of its node.
+        if commands::is_help(rest) {
+            commands::print_help(&commands::help_lines(AGENCY_COMMANDS, ""));
+        }
+        self.agency(&agency_id)?
+            .interpret(rest)
+            .map_err(|e| AgencyCommandError::ErrorExecutingCommandForAgency(agency_id, Box::new(e)))
+    }
+
+    fn complete(&self, partial: &str) -> Vec<String> {
+        let Some((agency_id, rest)) = partial.split_once('.') else {
+            let mut completions = commands::complete_commands(AGENCIES_COMMANDS, partial);
+            let agency_ids = self.agencies().into_iter()
+                .filter_map(|agency| agency.agency_id.as_deref());
+            completions.extend(commands::complete_ids(agency_ids, partial));
+            completions.sort();
+            return completions;
+        };
+        let Ok(node) = self.agency(agency_id) else {
+            return Vec::new();
+        };
+        let mut completions = commands::complete_commands(AGENCY_COMMANDS, rest);
+        completions.extend(node.complete(rest));
+        completions.sort();
+        completions.dedup();
+        completions.into_iter().map(|completion| format!("{}.{}", agency_id, completion)).collect()
+    }
+}
+
+type AgenciesCommand = fn(&AgencyCommandInterpreter, &str) -> Result<Output, AgencyCommandError>;
+type AgencyCommand = fn(&AgencyCommandInterpreter, &Agency, &str) -> Result<Output, AgencyCommandError>;
+
+// AGENCIES_COMMANDS are the commands about the agencies as a whole.
+const AGENCIES_COMMANDS: &[commands::Command<AgenciesCommand>] = &[
+    commands::Command {
+        name: "help",
+        args: "",
+        description: "List the commands valid here (also ?)",
+        run: |_, _| {
+            commands::print_collection_help(AGENCIES_COMMANDS, AGENCY_COMMANDS, "agency", "<agency_id>");
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "list",
+        args: "[<conditions>] [fields=<fields>] [sort=<fields>] [limit=<n>] [offset=<n>]",
+        description: "List the agencies with the number of routes each runs here, meeting any conditions given, e.g. agency_lang=en",
+        run: |agencies, args| agencies.list(args).map(Output::Table),
+    },
+    commands::Command {
+        name: "info",
+        args: "",
+        description: "Count the agencies",
+        run: |agencies, _| Ok(Output::Counts(vec![("Agencies", agencies.agencies().len())])),
+    },
+    commands::Command {
+        name: "count",
+        args: "[<conditions>]",
+        description: "Count the agencies meeting any conditions given, e.g. agency_lang=en",
+        run: |agencies, args| agencies.list(args).map(|table| Output::Counts(vec![("Agencies", table.rows().len())])),
+    },
+];
+
+// AGENCY_COMMANDS are the commands about a single agency, given as <agency_id>.<command>.
+const AGENCY_COMMANDS: &[commands::Command<AgencyCommand>] = &[
+    // info prints the agency's fields before the counts the agency node prints.
+    commands::Command {
+        name: "info",
+        args: "",
+        description: "Print every field of the agency, and count the stops, routes and trips it runs",
+        run: |agencies, agency, _| {
+            info_of(agency);
+            let node = agencies.agency(agency.agency_id.as_deref().unwrap_or_default())?;
+            println!("{}", node.view());
+            Ok(Output::Printed)
+        },
+    },
+];
+
+// info_of prints every field of an agency given in agency.txt.
+fn info_of(agency: &Agency) {
+    commands::print_fields(&[
+        ("ID", agency.agency_id.clone()),
+        ("Name", Some(agency.agency_name.bold().to_string())),
+        ("URL", Some(agency.agency_url.clone())),
+        ("Timezone", Some(agency.agency_timezone.name().to_string())),
+        ("Language", agency.agency_lang.clone()),
+        ("Phone", agency.agency_phone.clone()),
+        ("Fare URL", agency.agency_fare_url.clone()),
+        ("Email", agency.agency_email.clone()),
+    ]);
+}
+
+impl AgencyCommandInterpreter<'_> {
+    // agencies gives the agencies of the feed, in order of ID. Below the root, only those running a route of the
+    // node are given, so that e.g. routes.Red.agency.list names the agency running the Red Line.
+    fn agencies(&self) -> Vec<&Agency> {
+        let schedule = &self.0.gtfs;
+        let mut agencies = match self.0.filter {
+            None => schedule.agencies.agencies.values().collect::<Vec<_>>(),
+            Some(_) => self.0.view().routes().filter_map(|route| schedule.agencies.agency_of(route)).collect(),
+        };
+        agencies.sort_by_key(|agency| agency.agency_id.as_deref());
+        agencies.dedup_by_key(|agency| agency.agency_id.as_deref());
+        agencies
+    }
+
+    // list lists the agencies meeting the conditions of a Filter given as args, with every field of agency.txt along
+    // with the number of routes each runs here. Their IDs, names and routes are shown unless other fields are chosen.
+    fn list(&self, args: &str) -> Result<Table, AgencyCommandError> {
+        let filter = Filter::parse(args).map_err(AgencyCommandError::InvalidFilter)?;
+        let view = self.0.view();
+        let mut table = Table::new(&[&AGENCY_FIELDS[..], &["routes"]].concat())
+            .with_default_fields(&["agency_id", "agency_name", "routes"])
+            .with_empty_message("No agencies here; the feed may have no agency.txt");
+        for agency in self.agencies() {
+            let mut record = HashMap::from(agency);
+            let routes = view.routes()
+                .filter(|route| self.0.gtfs.agencies.agency_of(route).is_some_and(|runner| runner.agency_id == agency.agency_id))
+                .count();
+            record.insert("routes".to_string(), routes.to_string());
+            table.push_record(&record);
+        }
+        table.sort(&[SortKey::from("agency_id")]).expect("agency_id is a field of agency.txt");
+        filter.apply(table).map_err(AgencyCommandError::InvalidFilter)
+    }
+
+    // navigate returns the node at path, given as <agency_id> or <agency_id>.<path from the agency's node>, the agency
+    // possibly being given by name, e.g. "MBTA".
+    pub fn navigate(&self, path: &str) -> Result<GtfsNode, AgencyCommandError> {
+        let (segment, rest) = names::split_segment(path);
+        let agency_id = self.agency_id(segment)?;
+        self.agency(&agency_id)?
+            .navigate(rest)
+            .map_err(|e| AgencyCommandError::ErrorExecutingCommandForAgency(agency_id, Box::new(e)))
+    }
+
+    // agency_id gives the ID of the agency a segment of a path picks out, by its agency_name if it's quoted. A feed's
+    // only agency may have no ID, in which case it can only be given by name.
+    fn agency_id(&self, segment: Segment) -> Result<String, AgencyCommandError> {
+        let name = match segment {
+            Segment::Id(agency_id) => return Ok(agency_id.to_string()),
+            Segment::Name(name) => name,
+        };
+        let candidates = self.agencies().into_iter()
+            .map(|agency| (agency.agency_id.as_deref().unwrap_or_default(), agency.agency_name.as_str()));
+        names::one("agency", name, names::matching(name, candidates)).map_err(AgencyCommandError::NameError)
+    }
+
+    // agency creates a node for the given agency, along with the routes it runs here, their trips and the stops they
+    // visit. The node shares this node's schedule rather than copying it.
+    fn agency(&self, agency_id: &str) -> Result<GtfsNode, AgencyCommandError> {
+        let view = self.0.view();
+        let raw_agency = self.0.gtfs.agencies.agencies.get(agency_id)
+            .ok_or(AgencyCommandError::NoSuchAgency(agency_id.to_string()))?;
+
+        let route_ids = view.routes()
+            .filter(|route| self.0.gtfs.agencies.agency_of(route).is_some_and(|runner| runner.agency_id == raw_agency.agency_id))
+            .map(|route| route.route_id.clone())
+            .collect::<HashSet<_>>();
+
+        let trip_ids = route_ids.iter()
+            .flat_map(|route_id| view.trips_of_route(route_id))
+            .map(|trip| trip.trip_id.clone())
+            .collect::<HashSet<_>>();
+
+        let stop_ids = trip_ids.iter()
+            .flat_map(|trip_id| view.stop_times_of_trip(trip_id))
+            .filter_map(|stop_time| stop_time.stop_id.clone())
+            .collect::<HashSet<_>>();
+
+        // an agency without an ID is reached by its name, so its node is named for that.
+        let node_id = match agency_id {
+            "" => format!("agency.\"{}\"", raw_agency.agency_name),
+            agency_id => format!("agency.{}", agency_id),
+        };
+        Ok(self.0.child(
+            &node_id,
+            Some(raw_agency.agency_name.clone()),
+            ScheduleFilter {
+                stop_ids,
+                route_ids,
+                trip_ids,
+            }
+        ))
+    }
+}
@@ -0,0 +1,154 @@
+use crate::commands::output::Output;
+use crate::commands::progress;
+use crate::commands::table::Table;
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::stop_times::GtfsTime;
+use crate::style::Paint;
+use std::time::{Duration, Instant};
+
+// SAMPLES is how many stops or routes each query is run for by default, spread evenly through the feed, so that a
+// benchmark takes about as long whatever the size of the feed.
+pub const SAMPLES: usize = 100;
+
+// Benchmark is what bench measures of a feed: how long loading it took, table by table, how long a set of typical
+// queries take on it, and how much memory the process held at its peak after each.
+pub struct Benchmark {
+    // load gives how long each table took to parse, in the order they were parsed, followed by linking.
+    pub load: Vec<(String, Duration)>,
+    pub loaded: Duration,
+    pub queries: Vec<QueryTiming>,
+    // peak_memory gives the peak resident memory of the process after loading and after querying, where it's known.
+    pub peak_memory: Vec<(&'static str, Option<usize>)>,
+}
+
+// QueryTiming is how long a query took over its runs. The first run is given apart from the others, since it builds
+// the indexes the schedule keeps for later queries.
+pub struct QueryTiming {
+    pub query: &'static str,
+    pub runs: usize,
+    pub first: Duration,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl QueryTiming {
+    pub fn mean(&self) -> Duration {
+        self.total / self.runs.max(1) as u32
+    }
+}
+
+// run_queries times departures, nearest stops and the stops of routes, each for samples of the feed's stops or routes,
+// spread evenly through them in order of ID. Departures are looked up from 08:00 on a date the feed has service: today
+// if it runs any trips, else the first date it does.
+pub fn run_queries(schedule: &GtfsSchedule, samples: usize) -> Vec<QueryTiming> {
+    let view = schedule.view();
+    let stop_ids = sample(schedule.stops.stops.keys().map(String::as_str).collect(), samples);
+    let route_ids = sample(schedule.routes.routes.keys().map(String::as_str).collect(), samples);
+    let date = service_date(schedule);
+    let morning = GtfsTime::from_hms(8, 0, 0).expect("08:00:00 is a valid time");
+    let points = stop_ids.iter()
+        .filter_map(|stop_id| {
+            let stop = view.stop(stop_id)?;
+            Some((stop.stop_lat()?, stop.stop_lon()?))
+        })
+        .collect::<Vec<_>>();
+    vec![
+        time("departures", &stop_ids, |stop_id| view.departures_at(stop_id, date, morning, 10).len()),
+        time("nearest", &points, |(lat, lon)| view.nearest(*lat, *lon, 5).len()),
+        time("route stops", &route_ids, |route_id| view.stops_on_route(route_id, None).len()),
+    ]
+}
+
+// time runs query for each of inputs in turn, timing each run. What query returns is kept from the optimizer.
+fn time<T>(name: &'static str, inputs: &[T], query: impl Fn(&T) -> usize) -> QueryTiming {
+    let mut timing = QueryTiming { query: name, runs: inputs.len(), first: Duration::ZERO, total: Duration::ZERO, max: Duration::ZERO };
+    for (run, input) in inputs.iter().enumerate() {
+        let started = Instant::now();
+        std::hint::black_box(query(input));
+        let elapsed = started.elapsed();
+        if run == 0 {
+            timing.first = elapsed;
+        }
+        timing.total += elapsed;
+        timing.max = timing.max.max(elapsed);
+    }
+    timing
+}
+
+// sample picks up to n of ids, spread evenly through them in order.
+fn sample(mut ids: Vec<&str>, n: usize) -> Vec<&str> {
+    ids.sort();
+    let step = ids.len().div_ceil(n.max(1)).max(1);
+    ids.into_iter().step_by(step).take(n).collect()
+}
+
+// service_date gives today if the schedule runs any trips today, else the first date any of its trips run.
+fn service_date(schedule: &GtfsSchedule) -> chrono::NaiveDate {
+    let today = chrono::Local::now().date_naive();
+    if schedule.trips_on(today).next().is_some() {
+        return today;
+    }
+    schedule.trips.trips.values()
+        .map(|trip| trip.service_id.as_str())
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|service_id| schedule.service_dates(service_id).first().copied())
+        .min()
+        .unwrap_or(today)
+}
+
+// peak_memory gives the most memory the process has held resident so far, in bytes.
+#[cfg(unix)]
+pub fn peak_memory() -> Option<usize> {
+    // SAFETY: getrusage only writes to the rusage it's given, which is zeroed, so fully initialized.
+    let mut usage = unsafe { std::mem::zeroed::<libc::rusage>() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+    // macOS gives the peak in bytes, and other systems in kilobytes.
+    let unit = if cfg!(target_os = "macos") { 1 } else { 1024 };
+    usize::try_from(usage.ru_maxrss).ok().map(|peak| peak * unit)
+}
+
+#[cfg(not(unix))]
+pub fn peak_memory() -> Option<usize> {
+    None
+}
+
+impl Benchmark {
+    // output gives the benchmark as tables of loading, queries and memory, so that it can be shown as JSON or CSV and
+    // compared between runs to catch regressions. Times are given in milliseconds.
+    pub fn output(&self) -> Output {
+        let millis = |duration: Duration| format!("{:.3}", duration.as_secs_f64() * 1000.0);
+
+        let mut load = Table::new(&["step", "ms"]);
+        for (step, elapsed) in &self.load {
+            load.push(vec![step.clone(), millis(*elapsed)]);
+        }
+        load.push(vec!["total".to_string(), millis(self.loaded)]);
+
+        let mut queries = Table::new(&["query", "runs", "first_ms", "mean_ms", "max_ms", "total_ms"])
+            .with_style(2, |text| text.muted().to_string());
+        for timing in &self.queries {
+            queries.push(vec![
+                timing.query.to_string(),
+                timing.runs.to_string(),
+                millis(timing.first),
+                millis(timing.mean()),
+                millis(timing.max),
+                millis(timing.total),
+            ]);
+        }
+
+        let mut memory = Table::new(&["after", "peak"]);
+        for (after, peak) in &self.peak_memory {
+            memory.push(vec![after.to_string(), peak.map_or("unknown".to_string(), progress::bytes)]);
+        }
+
+        Output::Sections(vec![
+            ("load".to_string(), Output::Table(load)),
+            ("queries".to_string(), Output::Table(queries)),
+            ("memory".to_string(), Output::Table(memory)),
+        ])
+    }
+}
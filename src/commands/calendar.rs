@@ -0,0 +1,358 @@
+use crate::commands;
+use crate::commands::gtfs::GtfsNode;
+use crate::commands::CommandInterpreter;
+use crate::commands::filter::{Filter, FilterError};
+use crate::commands::gtfs::GTFSCommandInterpreterError;
+use crate::commands::output::Output;
+use crate::commands::table::{SortKey, Table};
+use crate::gtfs::calendar::{ExceptionType, SERVICE_FIELDS};
+use crate::gtfs::view::ScheduleFilter;
+use crate::style::{Colorize, Paint};
+use std::collections::{HashMap, HashSet};
+
+pub struct CalendarCommandInterpreter<'a>(pub &'a GtfsNode);
+
+#[derive(Debug)]
+pub enum CalendarCommandError {
+    InvalidCommand(String),
+    NoSuchService(String),
+    ErrorExecutingCommandForService(String, Box<GTFSCommandInterpreterError>),
+    InvalidDate(String),
+    InvalidFilter(FilterError),
+}
+
+impl std::fmt::Display for CalendarCommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CalendarCommandError::InvalidCommand(command) => write!(f, "Invalid command: {}", command),
+            CalendarCommandError::NoSuchService(service_id) => write!(f, "No such service: {}", service_id),
+            CalendarCommandError::ErrorExecutingCommandForService(service_id, cause) => write!(f, "Error executing command for service {}: {}", service_id, **cause),
+            CalendarCommandError::InvalidDate(date) => write!(f, "Invalid date: {} (expected a date like 2025-01-31)", date),
+            CalendarCommandError::InvalidFilter(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CalendarCommandError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CalendarCommandError::ErrorExecutingCommandForService(_, cause) => Some(cause.as_ref()),
+            CalendarCommandError::InvalidFilter(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> CommandInterpreter for CalendarCommandInterpreter<'a> {
+    type CommandResult = Output;
+    type CommandError = CalendarCommandError;
+
+    fn interpret(&self, command: &str) -> Result<Self::CommandResult, Self::CommandError> {
+        if let Some((calendar_command, args)) = commands::find_command(CALENDAR_COMMANDS, command) {
+            return (calendar_command.run)(self, args);
+        }
+        let (service_id, rest) = command.split_once('.').unwrap_or((command, ""));
+        if !self.0.gtfs.service_ids().contains(service_id) {
+            return Err(CalendarCommandError::InvalidCommand(command.to_string()));
+        }
+        // commands about the service itself are run here; anything else is run on the service's node.
+        if let Some((service_command, args)) = commands::find_command(SERVICE_COMMANDS, rest) {
+            return (service_command.run)(self, service_id, args);
+        }
+        // help at a service lists the commands about the service before those of its node.
+        if commands::is_help(rest) {
+            commands::print_help(&commands::help_lines(SERVICE_COMMANDS, ""));
+        }
+        self.service(service_id)?
+            .interpret(rest)
+            .map_err(|e| CalendarCommandError::ErrorExecutingCommandForService(service_id.to_string(), Box::new(e)))
+    }
+
+    fn complete(&self, partial: &str) -> Vec<String> {
+        let Some((service_id, rest)) = partial.split_once('.') else {
+            let mut completions = commands::complete_commands(CALENDAR_COMMANDS, partial);
+            completions.extend(commands::complete_ids(self.service_ids().into_iter(), partial));
+            completions.sort();
+            return completions;
+        };
+        let Ok(node) = self.service(service_id) else {
+            return Vec::new();
+        };
+        let mut completions = commands::complete_commands(SERVICE_COMMANDS, rest);
+        completions.extend(node.complete(rest));
+        completions.sort();
+        completions.dedup();
+        completions.into_iter().map(|completion| format!("{}.{}", service_id, completion)).collect()
+    }
+}
+
+type CalendarCommand = fn(&CalendarCommandInterpreter, &str) -> Result<Output, CalendarCommandError>;
+type ServiceCommand = fn(&CalendarCommandInterpreter, &str, &str) -> Result<Output, CalendarCommandError>;
+
+// CALENDAR_COMMANDS are the commands about the services as a whole.
+const CALENDAR_COMMANDS: &[commands::Command<CalendarCommand>] = &[
+    commands::Command {
+        name: "help",
+        args: "",
+        description: "List the commands valid here (also ?)",
+        run: |_, _| {
+            let mut lines = commands::help_lines(CALENDAR_COMMANDS, "");
+            lines.extend(commands::help_lines(SERVICE_COMMANDS, "<service_id>."));
+            lines.push(("<service_id>.<command>".to_string(), "Run a command on the service's node; see <service_id>.help"));
+            commands::print_help(&lines);
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "list",
+        args: "[<conditions>] [fields=<fields>] [sort=<fields>] [limit=<n>] [offset=<n>]",
+        description: "List the services with their days, dates, exceptions and trips, meeting any conditions given, e.g. saturday=1",
+        run: |calendar, args| calendar.list(args).map(Output::Table),
+    },
+    commands::Command {
+        name: "info",
+        args: "",
+        description: "Count the services",
+        run: |calendar, _| Ok(Output::Counts(vec![("Services", calendar.service_ids().len())])),
+    },
+    commands::Command {
+        name: "count",
+        args: "[<conditions>]",
+        description: "Count the services meeting any conditions given, e.g. saturday=1",
+        run: |calendar, args| calendar.list(args).map(|table| Output::Counts(vec![("Services", table.rows().len())])),
+    },
+    commands::Command {
+        name: "on",
+        args: "[date]",
+        description: "List the services running on a date, today by default, and the trips here they run",
+        run: |calendar, args| calendar.on(args),
+    },
+];
+
+// SERVICE_COMMANDS are the commands about a single service, given as <service_id>.<command>.
+const SERVICE_COMMANDS: &[commands::Command<ServiceCommand>] = &[
+    // info prints the service's definition before the counts the service node prints.
+    commands::Command {
+        name: "info",
+        args: "",
+        description: "Print the service's days and dates, its exceptions, and count the trips it runs",
+        run: |calendar, service_id, _| {
+            calendar.info_of(service_id);
+            println!("{}", calendar.service(service_id)?.view());
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "dates",
+        args: "",
+        description: "List every date the service runs, with the exception which adds it if there is one",
+        run: |calendar, service_id, _| Ok(Output::Table(calendar.dates(service_id))),
+    },
+    commands::Command {
+        name: "exceptions",
+        args: "",
+        description: "List the dates calendar_dates.txt adds to or removes from the service",
+        run: |calendar, service_id, _| Ok(Output::Table(calendar.exceptions(service_id))),
+    },
+];
+
+impl CalendarCommandInterpreter<'_> {
+    // service_ids gives the IDs of the services of the feed, in order. Below the root, only those running a trip of
+    // the node are given, so that e.g. routes.Red.calendar.list gives the services of the Red Line.
+    fn service_ids(&self) -> Vec<&str> {
+        let service_ids = self.0.gtfs.service_ids();
+        match self.0.filter {
+            None => service_ids.into_iter().collect(),
+            Some(_) => {
+                let running = self.0.view().trips().map(|trip| trip.service_id.as_str()).collect::<HashSet<_>>();
+                service_ids.into_iter().filter(|service_id| running.contains(service_id)).collect()
+            },
+        }
+    }
+
+    // trips_of_service counts the trips here run by a service.
+    fn trips_of_service(&self, service_id: &str) -> usize {
+        let view = self.0.view();
+        self.0.gtfs.trips_of_service(service_id)
+            .filter(|trip| view.trip(&trip.trip_id).is_some())
+            .count()
+    }
+
+    // list lists the services meeting the conditions of a Filter given as args, in order of ID, with every field of
+    // calendar.txt along with their days, how many dates they run, the exceptions made to them and how many trips
+    // here they run. Services only given in calendar_dates.txt have no fields of calendar.txt.
+    fn list(&self, args: &str) -> Result<Table, CalendarCommandError> {
+        let filter = Filter::parse(args).map_err(CalendarCommandError::InvalidFilter)?;
+        let schedule = &self.0.gtfs;
+        let mut table = Table::new(&[&SERVICE_FIELDS[..], &["days", "dates", "added", "removed", "trips"]].concat())
+            .with_default_fields(&["service_id", "days", "start_date", "end_date", "added", "removed", "trips"])
+            .with_empty_message("No services here; the feed may have no calendar.txt or calendar_dates.txt");
+        for service_id in self.service_ids() {
+            let mut record = match schedule.calendar.services.get(service_id) {
+                Some(service) => {
+                    let mut record = HashMap::from(service);
+                    record.insert("days".to_string(), service.weekdays().iter().map(|weekday| weekday.to_string()).collect::<Vec<_>>().join(", "));
+                    record
+                },
+                None => HashMap::from([("service_id".to_string(), service_id.to_string())]),
+            };
+            let exceptions = schedule.calendar_dates.calendar_dates.get(service_id).into_iter().flatten();
+            let (added, removed) = exceptions.partition::<Vec<_>, _>(|calendar_date| calendar_date.exception_type == ExceptionType::Added);
+            record.insert("dates".to_string(), schedule.service_dates(service_id).len().to_string());
+            record.insert("added".to_string(), added.len().to_string());
+            record.insert("removed".to_string(), removed.len().to_string());
+            record.insert("trips".to_string(), self.trips_of_service(service_id).to_string());
+            table.push_record(&record);
+        }
+        table.sort(&[SortKey::from("service_id")]).expect("service_id is a field of calendar.txt");
+        filter.apply(table).map_err(CalendarCommandError::InvalidFilter)
+    }
+
+    // on lists the services running on a date, today by default, saying whether each runs by its weekly pattern or
+    // is added that day, along with the trips here which they run, in order of the time each leaves its first stop.
+    fn on(&self, date: &str) -> Result<Output, CalendarCommandError> {
+        let date = match date {
+            "" | "today" => chrono::Local::now().date_naive(),
+            date => chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| CalendarCommandError::InvalidDate(date.to_string()))?,
+        };
+        let schedule = &self.0.gtfs;
+        let view = self.0.view();
+        let running = schedule.services_on(date);
+
+        let mut services = Table::new(&["service_id", "runs_by", "trips"])
+            .with_style(1, |text| text.muted().to_string())
+            .with_empty_message(&format!("No services run on {}", date));
+        for service_id in self.service_ids().into_iter().filter(|service_id| running.contains(service_id)) {
+            let runs_by = match schedule.calendar_dates.exception(service_id, date) {
+                Some(ExceptionType::Added) => "calendar_dates.txt",
+                _ => "calendar.txt",
+            };
+            services.push(vec![service_id.to_string(), runs_by.to_string(), self.trips_of_service(service_id).to_string()]);
+        }
+
+        let mut trips = Table::new(&["trip_id", "route", "service_id", "trip_headsign", "departure_time"])
+            .with_empty_message(&format!("No trips here run on {}", date));
+        let mut trips_on = view.trips()
+            .filter(|trip| running.contains(trip.service_id.as_str()))
+            .map(|trip| {
+                let departure_time = view.stop_times_of_trip(&trip.trip_id)
+                    .min_by_key(|stop_time| stop_time.stop_sequence)
+                    .and_then(|stop_time| stop_time.departure_time);
+                (departure_time, trip)
+            })
+            .collect::<Vec<_>>();
+        trips_on.sort_by(|(a_time, a), (b_time, b)| (a_time, &a.trip_id).cmp(&(b_time, &b.trip_id)));
+        for (departure_time, trip) in trips_on {
+            trips.push(vec![
+                trip.trip_id.clone(),
+                view.route(&trip.route_id).map_or(trip.route_id.clone(), |route| route.name()),
+                trip.service_id.clone(),
+                trip.trip_headsign.clone().unwrap_or_default(),
+                departure_time.map_or(String::new(), |time| time.to_string()),
+            ]);
+        }
+
+        Ok(Output::Sections(vec![
+            ("services".to_string(), Output::Table(services)),
+            ("trips".to_string(), Output::Table(trips)),
+        ]))
+    }
+
+    // info_of prints a service's weekly pattern and the period it holds for, as calendar.txt gives them, along with
+    // the number of dates it runs, the first and last of them, and the exceptions calendar_dates.txt makes to it.
+    fn info_of(&self, service_id: &str) {
+        let schedule = &self.0.gtfs;
+        let service = schedule.calendar.services.get(service_id);
+        let dates = schedule.service_dates(service_id);
+        let exceptions = |exception_type: ExceptionType| {
+            let dates = schedule.calendar_dates.calendar_dates.get(service_id).into_iter()
+                .flatten()
+                .filter(|calendar_date| calendar_date.exception_type == exception_type)
+                .map(|calendar_date| calendar_date.date.to_string())
+                .collect::<Vec<_>>();
+            (!dates.is_empty()).then(|| dates.join(", "))
+        };
+        commands::print_fields(&[
+            ("ID", Some(service_id.bold().to_string())),
+            ("Days", service.map(|service| service.weekdays().iter().map(|weekday| weekday.to_string()).collect::<Vec<_>>().join(", "))),
+            ("Period", service.map(|service| format!("{} to {}", service.start_date, service.end_date))),
+            ("Dates", Some(dates.len().to_string())),
+            ("First", dates.first().map(|date| date.to_string())),
+            ("Last", dates.last().map(|date| date.to_string())),
+            ("Added", exceptions(ExceptionType::Added)),
+            ("Removed", exceptions(ExceptionType::Removed)),
+        ]);
+    }
+
+    // dates lists every date a service runs, in order, with its day of the week, marking those only added by
+    // calendar_dates.txt.
+    fn dates(&self, service_id: &str) -> Table {
+        let schedule = &self.0.gtfs;
+        let mut table = Table::new(&["date", "weekday", "exception"])
+            .with_style(2, |text| text.yellow().to_string())
+            .with_empty_message(&format!("{} never runs", service_id));
+        for date in schedule.service_dates(service_id) {
+            let exception = match schedule.calendar_dates.exception(service_id, date) {
+                Some(ExceptionType::Added) => "added",
+                _ => "",
+            };
+            table.push(vec![date.to_string(), chrono::Datelike::weekday(&date).to_string(), exception.to_string()]);
+        }
+        table
+    }
+
+    // exceptions lists the exceptions calendar_dates.txt makes to a service, in order of date.
+    fn exceptions(&self, service_id: &str) -> Table {
+        let mut exceptions = self.0.gtfs.calendar_dates.calendar_dates.get(service_id).into_iter().flatten().collect::<Vec<_>>();
+        exceptions.sort_by_key(|calendar_date| calendar_date.date);
+        let mut table = Table::new(&["date", "weekday", "exception"])
+            .with_empty_message(&format!("calendar_dates.txt makes no exceptions to {}", service_id));
+        for calendar_date in exceptions {
+            table.push(vec![
+                calendar_date.date.to_string(),
+                chrono::Datelike::weekday(&calendar_date.date).to_string(),
+                match calendar_date.exception_type {
+                    ExceptionType::Added => "added",
+                    ExceptionType::Removed => "removed",
+                }.to_string(),
+            ]);
+        }
+        table
+    }
+
+    // navigate returns the node at path, given as <service_id> or <service_id>.<path from the service's node>.
+    pub fn navigate(&self, path: &str) -> Result<GtfsNode, CalendarCommandError> {
+        let (service_id, rest) = path.split_once('.').unwrap_or((path, ""));
+        self.service(service_id)?
+            .navigate(rest)
+            .map_err(|e| CalendarCommandError::ErrorExecutingCommandForService(service_id.to_string(), Box::new(e)))
+    }
+
+    // service creates a node for the given service, along with the trips here it runs, their routes and the stops
+    // they visit. The node shares this node's schedule rather than copying it.
+    fn service(&self, service_id: &str) -> Result<GtfsNode, CalendarCommandError> {
+        if !self.0.gtfs.service_ids().contains(service_id) {
+            return Err(CalendarCommandError::NoSuchService(service_id.to_string()));
+        }
+        let view = self.0.view();
+
+        let trips = self.0.gtfs.trips_of_service(service_id)
+            .filter(|trip| view.trip(&trip.trip_id).is_some())
+            .collect::<Vec<_>>();
+
+        let stop_ids = trips.iter()
+            .flat_map(|trip| view.stop_times_of_trip(&trip.trip_id))
+            .filter_map(|stop_time| stop_time.stop_id.clone())
+            .collect::<HashSet<_>>();
+
+        Ok(self.0.child(
+            &format!("calendar.{}", service_id),
+            None,
+            ScheduleFilter {
+                stop_ids,
+                route_ids: trips.iter().map(|trip| trip.route_id.clone()).collect(),
+                trip_ids: trips.iter().map(|trip| trip.trip_id.clone()).collect(),
+            }
+        ))
+    }
+}
@@ -0,0 +1,148 @@
+use crate::commands::output::OutputFormat;
+use crate::style::ColorChoice;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path;
+
+// Config is read from config.toml in the rust-gtfs configuration directory, e.g. ~/.config/rust-gtfs/config.toml.
+// It names the feeds the tool knows of, each given as a URL, an archive or a directory of .txt files, along with the
+// one loaded when no other is chosen, and may choose how results are shown and when they're colored:
+//
+//     default_feed = "mbta"
+//     output = "json"
+//     color = "never"
+//
+//     [feeds]
+//     mbta = "https://cdn.mbta.com/MBTA_GTFS.zip"
+//     local = "/home/me/gtfs"
+//
+//     [aliases]
+//     rd = "routes.Red.departures"
+//
+// Aliases are defined with the alias command, which saves them here, and settings may be changed with config set.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_feed: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub feeds: BTreeMap<String, String>,
+    // output is the format results are shown in unless --json is given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<OutputFormat>,
+    // color is when output is colored unless --color is given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<ColorChoice>,
+    // aliases maps the names of aliases to the commands they stand for.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    ReadError(path::PathBuf, io::Error),
+    ParseError(path::PathBuf, toml::de::Error),
+    UnknownFeed(String),
+    NoConfigDirectory,
+    SerializeError(toml::ser::Error),
+    WriteError(path::PathBuf, io::Error),
+    UnknownSetting(String),
+    // InvalidValue holds the setting and the value it can't be given.
+    InvalidValue(String, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::ReadError(path, e) => write!(f, "Failed to read {}: {}", path.display(), e),
+            ConfigError::ParseError(path, e) => write!(f, "Failed to parse {}: {}", path.display(), e),
+            ConfigError::UnknownFeed(name) => write!(f, "No feed named {} in the config file", name),
+            ConfigError::NoConfigDirectory => write!(f, "Neither XDG_CONFIG_HOME nor HOME is set, so there is nowhere to save the config file"),
+            ConfigError::SerializeError(e) => write!(f, "Failed to encode the config file: {}", e),
+            ConfigError::WriteError(path, e) => write!(f, "Failed to write {}: {}", path.display(), e),
+            ConfigError::UnknownSetting(key) => write!(f, "Unknown setting {}: expected one of {}", key, SETTINGS.join(", ")),
+            ConfigError::InvalidValue(key, value) => write!(f, "Invalid {} '{}': expected {}", key, value, expected(key)),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::ReadError(_, e) => Some(e),
+            ConfigError::ParseError(_, e) => Some(e),
+            ConfigError::UnknownFeed(_) => None,
+            ConfigError::NoConfigDirectory => None,
+            ConfigError::SerializeError(e) => Some(e),
+            ConfigError::WriteError(_, e) => Some(e),
+            ConfigError::UnknownSetting(_) => None,
+            ConfigError::InvalidValue(_, _) => None,
+        }
+    }
+}
+
+// SETTINGS are the settings config set changes.
+pub const SETTINGS: [&str; 3] = ["default_feed", "output", "color"];
+
+// expected describes the values a setting may be given.
+fn expected(key: &str) -> &'static str {
+    match key {
+        "default_feed" => "the name of one of the feeds in the config file",
+        "output" => "text, json or csv",
+        _ => "auto, always or never",
+    }
+}
+
+impl Config {
+    // path gives where the config file is kept: in $XDG_CONFIG_HOME, or ~/.config if that isn't set.
+    pub fn path() -> Option<path::PathBuf> {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(path::PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| path::Path::new(&home).join(".config")))
+            .map(|dir| dir.join("rust-gtfs").join("config.toml"))
+    }
+
+    // load reads the config file, if there is one; without one, no feeds are named.
+    pub fn load() -> Result<Config, ConfigError> {
+        let Some(path) = Config::path().filter(|path| path.exists()) else {
+            return Ok(Config::default());
+        };
+        let text = std::fs::read_to_string(&path).map_err(|e| ConfigError::ReadError(path.clone(), e))?;
+        toml::from_str(&text).map_err(|e| ConfigError::ParseError(path, e))
+    }
+
+    // save writes the config file, creating the directory it's kept in if need be. Comments in the file are lost.
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let path = Config::path().ok_or(ConfigError::NoConfigDirectory)?;
+        let text = self.to_toml()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| ConfigError::WriteError(dir.to_path_buf(), e))?;
+        }
+        std::fs::write(&path, text).map_err(|e| ConfigError::WriteError(path.clone(), e))
+    }
+
+    // to_toml gives the config as it's written to the config file.
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        toml::to_string_pretty(self).map_err(ConfigError::SerializeError)
+    }
+
+    // set changes one of SETTINGS, given its value as typed: the default feed must be one of the feeds named.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let invalid = || ConfigError::InvalidValue(key.to_string(), value.to_string());
+        match key {
+            "default_feed" if self.feeds.contains_key(value) => self.default_feed = Some(value.to_string()),
+            "default_feed" => return Err(invalid()),
+            "output" => self.output = Some(value.parse().map_err(|_| invalid())?),
+            "color" => self.color = Some(value.parse().map_err(|_| invalid())?),
+            _ => return Err(ConfigError::UnknownSetting(key.to_string())),
+        }
+        Ok(())
+    }
+
+    // feed gives the URL or path of the feed with the given name.
+    pub fn feed(&self, name: &str) -> Result<&str, ConfigError> {
+        self.feeds.get(name).map(String::as_str).ok_or_else(|| ConfigError::UnknownFeed(name.to_string()))
+    }
+}
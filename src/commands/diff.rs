@@ -0,0 +1,33 @@
+use crate::gtfs::diff::{DiffTable, FeedDiff};
+use crate::style::Paint;
+
+// DRILL_DOWN describes the arguments which may follow the feeds given to diff, choosing which changes to show.
+pub const DRILL_DOWN: &str = "[stops|routes|trips|stop_times [<id>]]";
+
+// DrillDown picks out the changes to show: those to a table, or only to one record of it.
+pub type DrillDown<'a> = Option<(DiffTable, Option<&'a str>)>;
+
+// drill_down reads the arguments following the feeds given to diff: nothing, a table's name, or a table's name and a
+// record's ID, e.g. trips 12345. Invalid arguments are given back.
+pub fn drill_down<'a>(args: &[&'a str]) -> Result<DrillDown<'a>, String> {
+    let table = |name: &str| name.parse::<DiffTable>().map_err(|_| args.join(" "));
+    match args {
+        [] => Ok(None),
+        [name] => Ok(Some((table(name)?, None))),
+        [name, id] => Ok(Some((table(name)?, Some(*id)))),
+        _ => Err(args.join(" ")),
+    }
+}
+
+// print_diff prints how many records of each table changed, or the changes drill_down picks out, field by field.
+pub fn print_diff(diff: &FeedDiff, drill_down: DrillDown) {
+    match drill_down {
+        None => {
+            print!("{}", diff.summary());
+            if !diff.is_empty() {
+                println!("{}", "Give a table, e.g. stops, to list its changes, and an ID to show one record's".muted());
+            }
+        },
+        Some((table, id)) => print!("{}", diff.only(table, id)),
+    }
+}
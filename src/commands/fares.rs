@@ -0,0 +1,239 @@
+use crate::commands;
+use crate::commands::gtfs::GtfsNode;
+use crate::commands::CommandInterpreter;
+use crate::commands::filter::{Filter, FilterError};
+use crate::commands::names;
+use crate::commands::output::Output;
+use crate::commands::stops::{StopsCommandError, StopsCommandInterpreter};
+use crate::commands::table::{SortKey, Table};
+use crate::gtfs::fares::Fare;
+use crate::gtfs::view::ScheduleView;
+use crate::style::Colorize;
+use std::collections::{HashMap, HashSet};
+
+pub struct FaresCommandInterpreter<'a>(pub &'a GtfsNode);
+
+#[derive(Debug)]
+pub enum FaresCommandError {
+    InvalidCommand(String),
+    InvalidFilter(FilterError),
+    InvalidFareArguments(String),
+    InvalidStop(Box<StopsCommandError>),
+    NoSuchStop(String),
+}
+
+impl std::fmt::Display for FaresCommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FaresCommandError::InvalidCommand(command) => write!(f, "Invalid command: {}", command),
+            FaresCommandError::InvalidFilter(e) => write!(f, "{}", e),
+            FaresCommandError::InvalidFareArguments(args) => write!(
+                f, "Invalid fare arguments '{}': expected from <stop> to <stop>, each a stop ID or a quoted name", args
+            ),
+            FaresCommandError::InvalidStop(e) => write!(f, "{}", e),
+            FaresCommandError::NoSuchStop(stop_id) => write!(f, "No such stop: {}", stop_id),
+        }
+    }
+}
+
+impl std::error::Error for FaresCommandError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FaresCommandError::InvalidFilter(e) => Some(e),
+            FaresCommandError::InvalidStop(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> CommandInterpreter for FaresCommandInterpreter<'a> {
+    type CommandResult = Output;
+    type CommandError = FaresCommandError;
+
+    fn interpret(&self, command: &str) -> Result<Self::CommandResult, Self::CommandError> {
+        match commands::find_command(FARES_COMMANDS, command) {
+            Some((fares_command, args)) => (fares_command.run)(self, args),
+            None => Err(FaresCommandError::InvalidCommand(command.to_string())),
+        }
+    }
+
+    fn complete(&self, partial: &str) -> Vec<String> {
+        commands::complete_commands(FARES_COMMANDS, partial)
+    }
+}
+
+type FaresCommand = fn(&FaresCommandInterpreter, &str) -> Result<Output, FaresCommandError>;
+
+// FARES_COMMANDS are the commands about the fares of fare_attributes.txt and fare products of fare_products.txt.
+const FARES_COMMANDS: &[commands::Command<FaresCommand>] = &[
+    commands::Command {
+        name: "help",
+        args: "",
+        description: "List the commands valid here (also ?)",
+        run: |_, _| {
+            commands::print_help(&commands::help_lines(FARES_COMMANDS, ""));
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "list",
+        args: "[<conditions>] [fields=<fields>] [sort=<fields>] [limit=<n>] [offset=<n>]",
+        description: "List the fares and fare products with their prices and the number of rules for each, meeting any conditions given, e.g. price<3",
+        run: |fares, args| fares.list(args).map(Output::Table),
+    },
+    commands::Command {
+        name: "info",
+        args: "",
+        description: "Count the fares and fare products",
+        run: |fares, _| fares.list("").map(|table| Output::Counts(vec![("Fares", table.rows().len())])),
+    },
+    commands::Command {
+        name: "count",
+        args: "[<conditions>]",
+        description: "Count the fares and fare products meeting any conditions given, e.g. currency=EUR",
+        run: |fares, args| fares.list(args).map(|table| Output::Counts(vec![("Fares", table.rows().len())])),
+    },
+];
+
+// FARE_FIELDS are the fields of a fare listed by fares.list: those of fare_attributes.txt and fare_products.txt, with
+// the price and currency of either, and the number of rules of fare_rules.txt or fare_leg_rules.txt giving its use.
+const FARE_FIELDS: [&str; 11] = [
+    "fare_id", "fare_name", "price", "currency", "payment_method", "transfers", "transfer_duration", "agency_id",
+    "rider_category_id", "fare_media_id", "rules",
+];
+
+// RIDE_FARE_FIELDS are the fields of a fare charged for a ride, as routes.<id>.fares and fare list them: where the
+// rule it's charged under applies from and to, the zone it requires a ride to pass through, and who pays it how.
+const RIDE_FARE_FIELDS: [&str; 9] = [
+    "fare_id", "fare_name", "price", "currency", "from", "to", "contains", "rider_category_id", "fare_media_id",
+];
+
+impl FaresCommandInterpreter<'_> {
+    // list lists the fares of fare_attributes.txt and the fare products of fare_products.txt meeting the conditions of
+    // a Filter given as args. Below the root, only those charged for riding a route of the node are listed.
+    fn list(&self, args: &str) -> Result<Table, FaresCommandError> {
+        let filter = Filter::parse(args).map_err(FaresCommandError::InvalidFilter)?;
+        let schedule = &self.0.gtfs;
+        let view = self.0.view();
+        let fare_ids = self.0.filter.as_ref().map(|_| view.routes()
+            .flat_map(|route| view.fares_of_route(&route.route_id))
+            .map(|fare| fare.fare_id())
+            .collect::<HashSet<_>>());
+        let listed = |fare_id: &str| fare_ids.as_ref().is_none_or(|fare_ids| fare_ids.contains(fare_id));
+
+        let mut table = Table::new(&FARE_FIELDS)
+            .with_style(2, |text| text.bold().to_string())
+            .with_default_fields(&["fare_id", "fare_name", "price", "currency", "rider_category_id", "rules"])
+            .with_empty_message("No fares here; the feed may have no fare_attributes.txt or fare_products.txt");
+        for fare_attribute in schedule.fare_attributes.fare_attributes.values().filter(|fare_attribute| listed(&fare_attribute.fare_id)) {
+            table.push_record(&HashMap::from([
+                ("fare_id".to_string(), fare_attribute.fare_id.clone()),
+                ("price".to_string(), price(fare_attribute.price)),
+                ("currency".to_string(), fare_attribute.currency_type.clone()),
+                ("payment_method".to_string(), fare_attribute.payment_method.name().to_string()),
+                ("transfers".to_string(), fare_attribute.transfers.map_or("unlimited".to_string(), |transfers| transfers.to_string())),
+                ("transfer_duration".to_string(), fare_attribute.transfer_duration.map(|seconds| seconds.to_string()).unwrap_or_default()),
+                ("agency_id".to_string(), fare_attribute.agency_id.clone().unwrap_or_default()),
+                ("rules".to_string(), schedule.fare_rules.of_fare(&fare_attribute.fare_id).count().to_string()),
+            ]));
+        }
+        for fare_product in schedule.fare_products.iter().filter(|fare_product| listed(&fare_product.fare_product_id)) {
+            let rules = schedule.fare_leg_rules.iter()
+                .filter(|fare_leg_rule| fare_leg_rule.fare_product_id == fare_product.fare_product_id)
+                .count();
+            table.push_record(&HashMap::from([
+                ("fare_id".to_string(), fare_product.fare_product_id.clone()),
+                ("fare_name".to_string(), fare_product.fare_product_name.clone().unwrap_or_default()),
+                ("price".to_string(), price(fare_product.amount)),
+                ("currency".to_string(), fare_product.currency.clone()),
+                ("rider_category_id".to_string(), fare_product.rider_category_id.clone().unwrap_or_default()),
+                ("fare_media_id".to_string(), fare_product.fare_media_id.clone().unwrap_or_default()),
+                ("rules".to_string(), rules.to_string()),
+            ]));
+        }
+        table.sort(&[SortKey::from("fare_id"), SortKey::from("rider_category_id"), SortKey::from("fare_media_id")])
+            .expect("all are fields of a fare");
+        filter.apply(table).map_err(FaresCommandError::InvalidFilter)
+    }
+
+    // fare lists the fares which may be charged for riding between two stops without changing, given as from <stop>
+    // to <stop>, each by ID or by name in quotes, cheapest first on each route running between them.
+    pub fn fare(&self, args: &str) -> Result<Table, FaresCommandError> {
+        let invalid = || FaresCommandError::InvalidFareArguments(args.to_string());
+        let rest = args.trim().strip_prefix("from ").ok_or_else(invalid)?.trim_start();
+        let (from, rest) = rest.split_at(names::path_end(rest));
+        let to = rest.trim_start().strip_prefix("to ").ok_or_else(invalid)?.trim();
+        if from.is_empty() || to.is_empty() {
+            return Err(invalid());
+        }
+        let (from_stop_id, to_stop_id) = (self.stop_id(from)?, self.stop_id(to)?);
+
+        let view = self.0.view();
+        let route_name = |route_id: &str| view.route(route_id).map_or(route_id.to_string(), |route| route.name());
+        let mut table = Table::new(&[&["route"], &RIDE_FARE_FIELDS[..]].concat())
+            .with_style(3, |text| text.bold().to_string())
+            .with_default_fields(&["route", "fare_id", "fare_name", "price", "currency", "rider_category_id"])
+            .with_empty_message(&format!(
+                "No fares found from {} to {}; no trip runs directly between them, or the feed has no fares for it",
+                from_stop_id, to_stop_id
+            ));
+        for route_fare in view.fares_between(&from_stop_id, &to_stop_id) {
+            let mut record = ride_fare_record(&route_fare.fare);
+            record.insert("route".to_string(), route_name(route_fare.route_id));
+            table.push_record(&record);
+        }
+        Ok(table)
+    }
+
+    // stop_id gives the ID of a stop in the view, given by ID or by name in quotes.
+    fn stop_id(&self, stop: &str) -> Result<String, FaresCommandError> {
+        let stop_id = StopsCommandInterpreter(self.0).stop_id(names::split_segment(stop).0)
+            .map_err(|e| FaresCommandError::InvalidStop(Box::new(e)))?;
+        match self.0.view().stop(&stop_id) {
+            Some(_) => Ok(stop_id),
+            None => Err(FaresCommandError::NoSuchStop(stop_id)),
+        }
+    }
+}
+
+// route_fares lists the fares which may be charged for riding a route, cheapest first, with the zones or areas each
+// is charged between.
+pub fn route_fares(view: &ScheduleView, route_id: &str) -> Table {
+    let mut fares = view.fares_of_route(route_id);
+    fares.sort_by(|a, b| a.price().total_cmp(&b.price()).then(a.fare_id().cmp(b.fare_id())));
+    let mut table = Table::new(&RIDE_FARE_FIELDS)
+        .with_style(2, |text| text.bold().to_string())
+        .with_empty_message("No fares for this route; the feed may have no fare rules for it");
+    for fare in fares {
+        table.push_record(&ride_fare_record(&fare));
+    }
+    table
+}
+
+// ride_fare_record gives the fields of RIDE_FARE_FIELDS for a fare.
+fn ride_fare_record(fare: &Fare) -> HashMap<String, String> {
+    let (rider_category_id, fare_media_id) = match fare {
+        Fare::Product(fare_product, _) => (fare_product.rider_category_id.as_deref(), fare_product.fare_media_id.as_deref()),
+        Fare::Attribute(..) => (None, None),
+    };
+    let amount = price(fare.price());
+    [
+        ("fare_id", Some(fare.fare_id())),
+        ("fare_name", fare.name()),
+        ("price", Some(amount.as_str())),
+        ("currency", Some(fare.currency())),
+        ("from", fare.from()),
+        ("to", fare.to()),
+        ("contains", fare.contains()),
+        ("rider_category_id", rider_category_id),
+        ("fare_media_id", fare_media_id),
+    ]
+        .into_iter()
+        .map(|(field, value)| (field.to_string(), value.unwrap_or_default().to_string()))
+        .collect()
+}
+
+// price gives an amount of money to the cent, as fares are usually shown.
+fn price(amount: f64) -> String {
+    format!("{:.2}", amount)
+}
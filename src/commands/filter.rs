@@ -0,0 +1,212 @@
+use crate::commands::table::Table;
+
+// Filter selects the rows of a table by conditions on their fields, all of which must hold, such as
+// name~"Harvard" wheelchair=true. It's shared by the list commands, which take conditions as arguments.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Filter {
+    conditions: Vec<Condition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Condition {
+    field: String,
+    operator: Operator,
+    value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    // Equals and NotEquals compare text regardless of case. The values true and false stand for GTFS's 1 for yes
+    // and anything else for no, so that wheelchair=true matches wheelchair_boarding 1.
+    Equals,
+    NotEquals,
+    // Contains matches text containing the value, regardless of case.
+    Contains,
+    // the comparisons compare numbers; fields which aren't numbers never match.
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+// OPERATORS are tried in order, so that != and <= are found before = and <.
+const OPERATORS: [(&str, Operator); 7] = [
+    ("!=", Operator::NotEquals),
+    ("<=", Operator::LessOrEqual),
+    (">=", Operator::GreaterOrEqual),
+    ("=", Operator::Equals),
+    ("~", Operator::Contains),
+    ("<", Operator::Less),
+    (">", Operator::Greater),
+];
+
+#[derive(Debug)]
+pub enum FilterError {
+    InvalidCondition(String),
+    UnterminatedQuote(String),
+    UnknownField(String, Vec<String>),
+}
+
+impl std::fmt::Display for FilterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterError::InvalidCondition(condition) => write!(
+                f, "Invalid condition {} (expected a field, one of =, !=, ~, <, <=, > and >=, and a value, e.g. name~\"Harvard\")", condition
+            ),
+            FilterError::UnterminatedQuote(args) => write!(f, "Unterminated quote in {}", args),
+            FilterError::UnknownField(field, fields) => write!(f, "Unknown field {} (expected one of {})", field, fields.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+impl Filter {
+    // parse parses conditions separated by whitespace. Values containing whitespace may be quoted.
+    pub fn parse(args: &str) -> Result<Self, FilterError> {
+        Filter::from_args(&split_args(args)?)
+    }
+
+    // from_args parses conditions which have already been split by split_args, such as those left once a command
+    // has taken the arguments it handles itself.
+    pub fn from_args(args: &[String]) -> Result<Self, FilterError> {
+        let conditions = args.iter()
+            .map(|arg| {
+                let (position, symbol, operator) = OPERATORS.iter()
+                    .filter_map(|(symbol, operator)| Some((arg.find(symbol)?, *symbol, *operator)))
+                    .min_by_key(|(position, symbol, _)| (*position, std::cmp::Reverse(symbol.len())))
+                    .ok_or_else(|| FilterError::InvalidCondition(arg.clone()))?;
+                let field = &arg[..position];
+                if field.is_empty() || !field.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    return Err(FilterError::InvalidCondition(arg.clone()));
+                }
+                Ok(Condition {
+                    field: field.to_string(),
+                    operator,
+                    value: arg[position + symbol.len()..].to_string(),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Filter { conditions })
+    }
+
+    // apply keeps the rows of table meeting every condition. Fields are found as Table::column finds them, so
+    // name stands for stop_name in a table of stops.
+    pub fn apply(&self, mut table: Table) -> Result<Table, FilterError> {
+        let columns = self.conditions.iter()
+            .map(|condition| table.column(&condition.field)
+                .ok_or_else(|| FilterError::UnknownField(condition.field.clone(), table.headers().to_vec())))
+            .collect::<Result<Vec<_>, _>>()?;
+        table.retain(|row| self.conditions.iter()
+            .zip(&columns)
+            .all(|(condition, column)| condition.matches(row.get(*column).map_or("", String::as_str))));
+        Ok(table)
+    }
+}
+
+impl Condition {
+    fn matches(&self, text: &str) -> bool {
+        let compare = |test: fn(f64, f64) -> bool| match (text.parse::<f64>(), self.value.parse::<f64>()) {
+            (Ok(number), Ok(value)) => test(number, value),
+            _ => false,
+        };
+        match self.operator {
+            Operator::Equals => self.equals(text),
+            Operator::NotEquals => !self.equals(text),
+            Operator::Contains => text.to_lowercase().contains(&self.value.to_lowercase()),
+            Operator::Less => compare(|number, value| number < value),
+            Operator::LessOrEqual => compare(|number, value| number <= value),
+            Operator::Greater => compare(|number, value| number > value),
+            Operator::GreaterOrEqual => compare(|number, value| number >= value),
+        }
+    }
+
+    fn equals(&self, text: &str) -> bool {
+        match self.value.as_str() {
+            "true" => text == "1",
+            "false" => text != "1",
+            value => text.eq_ignore_ascii_case(value),
+        }
+    }
+}
+
+// split_args splits arguments at whitespace outside double quotes, removing the quotes.
+pub fn split_args(args: &str) -> Result<Vec<String>, FilterError> {
+    let mut split = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in args.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    split.push(std::mem::take(&mut current));
+                }
+            },
+            c => current.push(c),
+        }
+    }
+    if quoted {
+        return Err(FilterError::UnterminatedQuote(args.to_string()));
+    }
+    if !current.is_empty() {
+        split.push(current);
+    }
+    Ok(split)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stops() -> Table {
+        let mut table = Table::new(&["stop_id", "stop_name", "wheelchair_boarding", "stop_lat"]);
+        table.push(vec!["X".to_string(), "Harvard Square".to_string(), "1".to_string(), "42.37".to_string()]);
+        table.push(vec!["Y".to_string(), "Central".to_string(), "2".to_string(), "42.36".to_string()]);
+        table.push(vec!["Z".to_string(), "Porter".to_string(), "".to_string(), "".to_string()]);
+        table
+    }
+
+    // matching lists the IDs of the stops meeting the conditions.
+    fn matching(conditions: &str) -> Vec<String> {
+        Filter::parse(conditions).unwrap().apply(stops()).unwrap().rows().iter().map(|row| row[0].clone()).collect()
+    }
+
+    #[test]
+    fn conditions_match_text() {
+        assert_eq!(matching("stop_id=x"), vec!["X"]);
+        assert_eq!(matching("stop_id!=x"), vec!["Y", "Z"]);
+        assert_eq!(matching("name~\"harvard sq\""), vec!["X"]);
+        assert_eq!(matching("name~r stop_id!=Z"), vec!["X", "Y"]);
+        assert_eq!(matching(""), vec!["X", "Y", "Z"]);
+    }
+
+    #[test]
+    fn true_and_false_stand_for_gtfs_yes_and_no() {
+        assert_eq!(matching("wheelchair=true"), vec!["X"]);
+        assert_eq!(matching("wheelchair=false"), vec!["Y", "Z"]);
+    }
+
+    #[test]
+    fn comparisons_match_only_numbers() {
+        assert_eq!(matching("stop_lat<42.37"), vec!["Y"]);
+        assert_eq!(matching("stop_lat<=42.37"), vec!["X", "Y"]);
+        assert_eq!(matching("stop_lat>42.36"), vec!["X"]);
+        assert_eq!(matching("stop_lat>=42.36"), vec!["X", "Y"]);
+        assert_eq!(matching("stop_name>0"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn invalid_conditions_are_errors() {
+        assert!(matches!(Filter::parse("name"), Err(FilterError::InvalidCondition(condition)) if condition == "name"));
+        assert!(matches!(Filter::parse("=X"), Err(FilterError::InvalidCondition(_))));
+        assert!(matches!(Filter::parse("name~\"Harvard"), Err(FilterError::UnterminatedQuote(_))));
+        let unknown = Filter::parse("route=1").unwrap().apply(stops());
+        assert!(matches!(unknown, Err(FilterError::UnknownField(field, _)) if field == "route"));
+    }
+
+    #[test]
+    fn split_args_keeps_quoted_whitespace() {
+        assert_eq!(split_args("  a \"b c\"  d\"e f\" ").unwrap(), vec!["a", "b c", "de f"]);
+    }
+}
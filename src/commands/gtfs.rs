@@ -1,25 +1,103 @@
 use crate::commands;
+use crate::commands::CommandInterpreter;
+use crate::commands::config::ConfigError;
+use crate::commands::diff;
+use crate::commands::map;
+use crate::commands::names;
+use crate::commands::output::{Output, OutputError};
+use crate::commands::redirect::RedirectError;
+use crate::commands::table::Table;
 use crate::gtfs::GtfsSchedule;
+use crate::gtfs::fields;
+use crate::gtfs::{routes as route_records, stop_times, stops as stop_records, trips as trip_records};
+use crate::gtfs::accessibility::AccessibilityFilter;
+use crate::gtfs::analysis::corridors;
+use crate::gtfs::analysis::stats::Stats;
+use crate::gtfs::analysis::travel_times;
+use crate::gtfs::geometry::{GeoJsonError, Polygon};
+use crate::gtfs::gtfs_rt::overlay::RealtimeOverlay;
+use crate::gtfs::view::{ScheduleFilter, ScheduleView};
+use crate::gtfs::error::GtfsError;
+use crate::gtfs::loaders::multi_feed_loader::FeedSource;
+use crate::gtfs::store::sqlite::{SqliteStore, SqliteStoreError};
+use crate::gtfs::writers::json_writer;
+use crate::gtfs::writers::zip_writer::{ZipWriter, ZipWriterError};
+use crate::commands::agency;
+use crate::commands::calendar;
+use crate::commands::fares;
+use crate::commands::shapes;
 use crate::commands::stops;
 use crate::commands::routes;
 use crate::commands::trips;
+use crate::commands::validate;
+use crate::commands::window;
+use crate::style::{Colorize, Paint};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
+// GtfsNode is a position in the REPL's navigation of a schedule, e.g. the root, a stop or a route.
+// Every node shares the same schedule; nodes below the root hold a filter selecting their part of it,
+// so navigating never copies the schedule's records.
 #[derive(Debug, Clone)]
 pub struct GtfsNode {
-    pub gtfs: GtfsSchedule,
+    pub gtfs: Arc<GtfsSchedule>,
+    pub filter: Option<Arc<ScheduleFilter>>,
     pub parent: Option<Box<GtfsNode>>,
+    // node_id is the path from the parent to the node, e.g. routes.Red or accessible; the root's is empty.
     pub node_id: String,
     pub node_name: Option<String>,
-
+    // realtime is laid over the schedule where commands show what's expected, such as departures, if the feed has one.
+    pub realtime: Option<Arc<RealtimeOverlay>>,
 }
 
 #[derive(Debug)]
 pub enum GTFSCommandInterpreterError {
     InvalidCommand(String),
     StopsSubcommandRequired,
+    AccessibleSubcommandRequired,
     StopsSubcommandError(Box<stops::StopsCommandError>),
     RoutesCommandError(routes::RoutesCommandError),
     TripsCommandError(trips::TripsCommandError),
+    AgencyCommandError(agency::AgencyCommandError),
+    CalendarCommandError(calendar::CalendarCommandError),
+    ShapesCommandError(shapes::ShapesCommandError),
+    FaresCommandError(fares::FaresCommandError),
+    DiffSourceRequired,
+    DiffLoadError(String, GtfsError),
+    InvalidDiffArguments(String),
+    InvalidTravelTimesArguments(String),
+    InvalidNearestArguments(String),
+    InvalidThroughArguments(String),
+    AreaReadError(String, std::io::Error),
+    AreaParseError(String, GeoJsonError),
+    InvalidExportArguments(String),
+    ExportWriteError(String, std::io::Error),
+    ExportZipError(String, ZipWriterError),
+    ExportStoreError(String, SqliteStoreError),
+    NotANode(String),
+    InvalidSetting(String),
+    InvalidConfigCommand(String),
+    InvalidDescribeTable(String),
+    OutputError(OutputError),
+    GrepPatternRequired,
+    InvalidPattern(String, regex::Error),
+    InvalidFeedCommand(String),
+    UnknownFeed(String),
+    InvalidRealtimeCommand(String),
+    NoRealtimeFeed,
+    FeedLoadError(String, GtfsError),
+    InvalidAlias(String),
+    UnknownAlias(String),
+    ConfigError(ConfigError),
+    NoMatches(String),
+    NoHistoryEntry(String),
+    InvalidHistoryArgument(String),
+    InvalidValidateArgument(String),
+    InvalidForeach(String),
+    NotAListing(String),
+    // UnknownPlaceholder holds the placeholder and the fields of the listing, which may be used in its place.
+    UnknownPlaceholder(String, Vec<String>),
+    RedirectError(RedirectError),
 }
 
 impl std::fmt::Display for GTFSCommandInterpreterError {
@@ -28,44 +106,745 @@ impl std::fmt::Display for GTFSCommandInterpreterError {
             GTFSCommandInterpreterError::InvalidCommand(command) => write!(f, "Invalid command: {}", command),
             GTFSCommandInterpreterError::StopsSubcommandError(e) => write!(f, "Error interpreting stops subcommand: {}", e),
             GTFSCommandInterpreterError::StopsSubcommandRequired => write!(f, "Stops subcommand required"),
+            GTFSCommandInterpreterError::AccessibleSubcommandRequired => write!(f, "accessible requires a command to run, e.g. accessible.stops.list"),
             GTFSCommandInterpreterError::RoutesCommandError(e) => write!(f, "Error interpreting routes command: {}", e),
             GTFSCommandInterpreterError::TripsCommandError(e) => write!(f, "Error interpreting trips command: {}", e),
+            GTFSCommandInterpreterError::AgencyCommandError(e) => write!(f, "Error interpreting agency command: {}", e),
+            GTFSCommandInterpreterError::CalendarCommandError(e) => write!(f, "Error interpreting calendar command: {}", e),
+            GTFSCommandInterpreterError::ShapesCommandError(e) => write!(f, "Error interpreting shapes command: {}", e),
+            GTFSCommandInterpreterError::FaresCommandError(e) => write!(f, "Error interpreting fares command: {}", e),
+            GTFSCommandInterpreterError::DiffSourceRequired => write!(f, "diff requires a feed to compare against, e.g. diff path/to/gtfs.zip"),
+            GTFSCommandInterpreterError::DiffLoadError(source, e) => write!(f, "Error loading {}: {}", source, e),
+            GTFSCommandInterpreterError::InvalidDiffArguments(args) => write!(
+                f, "Invalid diff arguments '{}': expected {} after the feed", args, diff::DRILL_DOWN
+            ),
+            GTFSCommandInterpreterError::InvalidThroughArguments(args) => write!(
+                f, "Invalid through arguments '{}': expected a GeoJSON file and optionally date=<YYYY-MM-DD>", args
+            ),
+            GTFSCommandInterpreterError::AreaReadError(path, e) => write!(f, "Error reading {}: {}", path, e),
+            GTFSCommandInterpreterError::AreaParseError(path, e) => write!(f, "Error parsing {}: {}", path, e),
+            GTFSCommandInterpreterError::InvalidExportArguments(args) => write!(
+                f, "Invalid export arguments '{}': expected a format, one of gtfs, sqlite, geojson and json, and a path to write to", args
+            ),
+            GTFSCommandInterpreterError::ExportWriteError(path, e) => write!(f, "Error writing {}: {}", path, e),
+            GTFSCommandInterpreterError::ExportZipError(path, e) => write!(f, "Error writing {}: {}", path, e),
+            GTFSCommandInterpreterError::ExportStoreError(path, e) => write!(f, "Error writing {}: {}", path, e),
+            GTFSCommandInterpreterError::InvalidSetting(setting) => write!(
+                f, "Invalid setting '{}': expected output text, output json or output csv", setting
+            ),
+            GTFSCommandInterpreterError::OutputError(e) => write!(f, "{}", e),
+            GTFSCommandInterpreterError::GrepPatternRequired => write!(f, "grep requires a pattern to search for, e.g. grep Harvard"),
+            GTFSCommandInterpreterError::InvalidPattern(pattern, e) => write!(f, "Invalid pattern {}: {}", pattern, e),
+            GTFSCommandInterpreterError::InvalidFeedCommand(args) => write!(
+                f, "Invalid feed command '{}': expected add <name> <url or path>, use <name>, diff <name> or list", args
+            ),
+            GTFSCommandInterpreterError::UnknownFeed(name) => write!(f, "No feed named {} is loaded; see feed list", name),
+            GTFSCommandInterpreterError::InvalidRealtimeCommand(args) => write!(
+                f, "Invalid rt command '{}': expected status, alerts or alerts all", args
+            ),
+            GTFSCommandInterpreterError::NoRealtimeFeed => write!(f, "No realtime feed is loaded; give one with --realtime"),
+            GTFSCommandInterpreterError::FeedLoadError(source, e) => write!(f, "Error loading {}: {}", source, e),
+            GTFSCommandInterpreterError::InvalidAlias(args) => write!(
+                f, "Invalid alias '{}': expected a name which isn't a command, =, and the command it stands for, e.g. rd = routes.Red.departures", args
+            ),
+            GTFSCommandInterpreterError::UnknownAlias(name) => write!(f, "No alias named {}; see alias", name),
+            GTFSCommandInterpreterError::ConfigError(e) => write!(f, "{}", e),
+            GTFSCommandInterpreterError::InvalidDescribeTable(table) => write!(
+                f, "Invalid describe argument '{}': expected stops, routes, trips or stop_times", table
+            ),
+            GTFSCommandInterpreterError::InvalidConfigCommand(args) => write!(
+                f, "Invalid config command '{}': expected show, set <key> <value> or path", args
+            ),
+            GTFSCommandInterpreterError::NoMatches(pattern) => write!(f, "No stops, routes or trips match {}", pattern),
+            GTFSCommandInterpreterError::NoHistoryEntry(number) => write!(f, "No line numbered {} in the history; see history", number),
+            GTFSCommandInterpreterError::InvalidHistoryArgument(n) => write!(f, "Invalid history argument '{}': expected a number of lines", n),
+            GTFSCommandInterpreterError::InvalidForeach(args) => write!(
+                f, "Invalid foreach '{}': expected <listing> : <command>, e.g. foreach routes.list : {{id}}.info", args
+            ),
+            GTFSCommandInterpreterError::NotAListing(command) => write!(f, "{} doesn't list anything for foreach to run a command on", command),
+            GTFSCommandInterpreterError::UnknownPlaceholder(field, fields) => write!(
+                f, "Unknown placeholder {{{}}}: expected {{id}} or one of the listing's fields, {}", field, fields.join(", ")
+            ),
+            GTFSCommandInterpreterError::RedirectError(e) => write!(f, "{}", e),
+            GTFSCommandInterpreterError::InvalidValidateArgument(argument) => write!(f, "Invalid validate argument: {} (expected {} or nothing)", argument, validate::STRICT),
+            GTFSCommandInterpreterError::NotANode(path) => write!(
+                f, "{} is not a node: expected a path such as routes.<route_id>, stops.<stop_id>.accessible or trips.<trip_id>", path
+            ),
+            GTFSCommandInterpreterError::InvalidTravelTimesArguments(args) => write!(
+                f, "Invalid travel_times arguments '{}': expected two stop IDs and optionally date=<YYYY-MM-DD>", args
+            ),
+            GTFSCommandInterpreterError::InvalidNearestArguments(args) => write!(
+                f, "Invalid nearest arguments '{}': expected a latitude, a longitude and optionally n=<number of stops>", args
+            ),
         }
     }
 }
 
-impl std::error::Error for GTFSCommandInterpreterError {}
+impl std::error::Error for GTFSCommandInterpreterError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GTFSCommandInterpreterError::StopsSubcommandError(e) => Some(e.as_ref()),
+            GTFSCommandInterpreterError::RoutesCommandError(e) => Some(e),
+            GTFSCommandInterpreterError::TripsCommandError(e) => Some(e),
+            GTFSCommandInterpreterError::AgencyCommandError(e) => Some(e),
+            GTFSCommandInterpreterError::CalendarCommandError(e) => Some(e),
+            GTFSCommandInterpreterError::ShapesCommandError(e) => Some(e),
+            GTFSCommandInterpreterError::FaresCommandError(e) => Some(e),
+            GTFSCommandInterpreterError::DiffLoadError(_, e) => e.source(),
+            GTFSCommandInterpreterError::AreaReadError(_, e) => Some(e),
+            GTFSCommandInterpreterError::AreaParseError(_, e) => Some(e),
+            GTFSCommandInterpreterError::ExportWriteError(_, e) => Some(e),
+            GTFSCommandInterpreterError::ExportZipError(_, e) => Some(e),
+            GTFSCommandInterpreterError::ExportStoreError(_, e) => Some(e),
+            GTFSCommandInterpreterError::OutputError(e) => Some(e),
+            GTFSCommandInterpreterError::RedirectError(e) => Some(e),
+            GTFSCommandInterpreterError::InvalidPattern(_, e) => Some(e),
+            GTFSCommandInterpreterError::FeedLoadError(_, e) => e.source(),
+            GTFSCommandInterpreterError::ConfigError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
-impl commands::CommandInterpreter for GtfsNode {
-    type CommandResult = ();
+impl CommandInterpreter for GtfsNode {
+    type CommandResult = Output;
     type CommandError = GTFSCommandInterpreterError;
 
     fn interpret(&self, command: &str) -> Result<Self::CommandResult, Self::CommandError> {
-        let (first, rest) = command.find(".").and_then(|i| command.split_at_checked(i)).unwrap_or((command, ""));
+        match commands::find_command(NODE_COMMANDS, command) {
+            Some((node_command, args)) => (node_command.run)(self, args),
+            None => Err(GTFSCommandInterpreterError::InvalidCommand(command.to_string())),
+        }
+    }
+
+    fn complete(&self, partial: &str) -> Vec<String> {
+        let Some((first, rest)) = partial.split_once('.') else {
+            return commands::complete_commands(NODE_COMMANDS, partial);
+        };
+        let completions = match first {
+            "accessible" => self.accessible().complete(rest),
+            "stops" => stops::StopsCommandInterpreter(self).complete(rest),
+            "routes" => routes::RoutesCommandInterpreter(self).complete(rest),
+            "trips" => trips::TripsCommandInterpreter(self).complete(rest),
+            "agency" => agency::AgencyCommandInterpreter(self).complete(rest),
+            "calendar" => calendar::CalendarCommandInterpreter(self).complete(rest),
+            "shapes" => shapes::ShapesCommandInterpreter(self).complete(rest),
+            "fares" => fares::FaresCommandInterpreter(self).complete(rest),
+            _ => Vec::new(),
+        };
+        completions.into_iter().map(|completion| format!("{}.{}", first, completion)).collect()
+    }
+}
+
+pub type NodeCommand = fn(&GtfsNode, &str) -> Result<Output, GTFSCommandInterpreterError>;
+
+// NODE_COMMANDS are the commands every node accepts, whether the root or a node reached by drilling into a stop,
+// route or trip.
+pub const NODE_COMMANDS: &[commands::Command<NodeCommand>] = &[
+    commands::Command {
+        name: "help",
+        args: "",
+        description: "List the commands valid here (also ?)",
+        run: |_, _| {
+            commands::print_help(&commands::help_lines(NODE_COMMANDS, ""));
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "info",
+        args: "",
+        description: "Count the stops, routes and trips here",
+        run: |node, _| Ok(node.info()),
+    },
+    commands::Command {
+        name: "grep",
+        args: "<pattern>",
+        description: "Search the IDs, names, descriptions and headsigns of the stops, routes and trips here",
+        run: |node, args| node.grep(args).map(Output::Table),
+    },
+    commands::Command {
+        name: "map",
+        args: "",
+        description: "Draw the stops here, and the shapes of the trips if the feed has them, fitted to the terminal",
+        run: |node, _| {
+            node.map();
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "nearest",
+        args: "<lat> <lon> [n=10]",
+        description: "List the stops closest to a point, with their distances and the routes serving them",
+        run: |node, args| node.nearest(args).map(Output::Table),
+    },
+    commands::Command {
+        name: "describe",
+        args: "<stops|routes|trips|stop_times>",
+        description: "List the fields of a table, for fields= and filters, with their types and fill rates",
+        run: |node, args| node.describe(args).map(Output::Table),
+    },
+    commands::Command {
+        name: "stats",
+        args: "",
+        description: "Print totals, the busiest stops and routes, the longest dwells and trips by hour",
+        run: |node, _| {
+            node.stats();
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "corridors",
+        args: "",
+        description: "List runs of stops shared by more than one route",
+        run: |node, _| {
+            node.corridors();
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "travel_times",
+        args: "<from_stop_id> <to_stop_id> [date=<YYYY-MM-DD>]",
+        description: "Summarize scheduled travel times between two stops, over the day and by hour",
+        run: |node, args| node.travel_times(args).map(|_| Output::Printed),
+    },
+    commands::Command {
+        name: "fare",
+        args: "from <stop> to <stop>",
+        description: "List the fares for riding between two stops without changing, cheapest first on each route",
+        run: |node, args| fares::FaresCommandInterpreter(node)
+            .fare(args)
+            .map(Output::Table)
+            .map_err(GTFSCommandInterpreterError::FaresCommandError),
+    },
+    commands::Command {
+        name: "through",
+        args: "<path.geojson> [date=<YYYY-MM-DD>]",
+        description: "List the trips passing through the polygons of a GeoJSON file",
+        run: |node, args| node.through(args).map(|_| Output::Printed),
+    },
+    commands::Command {
+        name: "diff",
+        args: "<path or URL> [stops|routes|trips|stop_times [<id>]]",
+        description: "Count what another feed adds, removes and changes here, or list the changes to a table or record",
+        run: |node, args| node.diff(args).map(|_| Output::Printed),
+    },
+    commands::Command {
+        name: "validate",
+        args: "[--strict]",
+        description: "Check the whole feed against the GTFS reference and for common mistakes; --strict fails it for warnings too",
+        run: |node, args| {
+            let strict = validate::strict(args).map_err(GTFSCommandInterpreterError::InvalidValidateArgument)?;
+            validate::print_report(&node.gtfs.validate(), strict);
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "export",
+        args: "<gtfs|sqlite|geojson|json> <path>",
+        description: "Write the stops, routes and trips here as a GTFS zip, a SQLite store, GeoJSON or JSON",
+        run: |node, args| node.export(args).map(|_| Output::Printed),
+    },
+    commands::Command {
+        name: "accessible",
+        args: ".<command>",
+        description: "Run a command on the wheelchair-accessible trips and stops, as accessible=true after it does",
+        run: |node, args| match args {
+            "" => Err(GTFSCommandInterpreterError::AccessibleSubcommandRequired),
+            args => node.accessible().interpret(args),
+        },
+    },
+    commands::Command {
+        name: "stops",
+        args: ".<command>",
+        description: "Run a stops command; see stops.help",
+        run: |node, args| match args {
+            "" => Err(GTFSCommandInterpreterError::StopsSubcommandRequired),
+            args => stops::StopsCommandInterpreter(node)
+                .interpret(args)
+                .map_err(|e| GTFSCommandInterpreterError::StopsSubcommandError(Box::new(e))),
+        },
+    },
+    commands::Command {
+        name: "routes",
+        args: ".<command>",
+        description: "Run a routes command; see routes.help",
+        run: |node, args| routes::RoutesCommandInterpreter(node)
+            .interpret(args)
+            .map_err(GTFSCommandInterpreterError::RoutesCommandError),
+    },
+    commands::Command {
+        name: "trips",
+        args: ".<command>",
+        description: "Run a trips command; see trips.help",
+        run: |node, args| trips::TripsCommandInterpreter(node)
+            .interpret(args)
+            .map_err(GTFSCommandInterpreterError::TripsCommandError),
+    },
+    commands::Command {
+        name: "agency",
+        args: ".<command>",
+        description: "Run an agency command; see agency.help",
+        run: |node, args| agency::AgencyCommandInterpreter(node)
+            .interpret(args)
+            .map_err(GTFSCommandInterpreterError::AgencyCommandError),
+    },
+    commands::Command {
+        name: "calendar",
+        args: ".<command>",
+        description: "Run a calendar command; see calendar.help",
+        run: |node, args| calendar::CalendarCommandInterpreter(node)
+            .interpret(args)
+            .map_err(GTFSCommandInterpreterError::CalendarCommandError),
+    },
+    commands::Command {
+        name: "shapes",
+        args: ".<command>",
+        description: "Run a shapes command; see shapes.help",
+        run: |node, args| shapes::ShapesCommandInterpreter(node)
+            .interpret(args)
+            .map_err(GTFSCommandInterpreterError::ShapesCommandError),
+    },
+    commands::Command {
+        name: "fares",
+        args: ".<command>",
+        description: "Run a fares command; see fares.help",
+        run: |node, args| fares::FaresCommandInterpreter(node)
+            .interpret(args)
+            .map_err(GTFSCommandInterpreterError::FaresCommandError),
+    },
+];
+
+impl GtfsNode {
+    // root creates the node for a whole schedule.
+    pub fn root(gtfs: Arc<GtfsSchedule>) -> Self {
+        GtfsNode {
+            gtfs,
+            filter: None,
+            parent: None,
+            node_id: "".to_string(),
+            node_name: None,
+            realtime: None,
+        }
+    }
+
+    // with_realtime lays a realtime overlay over the node's schedule, and that of the nodes below it.
+    pub fn with_realtime(self, realtime: Option<Arc<RealtimeOverlay>>) -> Self {
+        GtfsNode { realtime, ..self }
+    }
+
+    // child creates a node for the part of the schedule selected by filter, with this node as its parent.
+    pub fn child(&self, node_id: &str, node_name: Option<String>, filter: ScheduleFilter) -> Self {
+        GtfsNode {
+            gtfs: Arc::clone(&self.gtfs),
+            filter: Some(Arc::new(filter)),
+            parent: Some(Box::new(self.clone())),
+            node_id: node_id.to_string(),
+            node_name,
+            realtime: self.realtime.clone(),
+        }
+    }
+
+    // path gives the node's place in the navigation, as the paths to it from the root, e.g. gtfs/routes/Red.
+    pub fn path(&self) -> String {
+        match &self.parent {
+            None => "gtfs".to_string(),
+            Some(parent) => format!("{}/{}", parent.path(), self.node_id.replace('.', "/")),
+        }
+    }
+
+    // glob expands a command whose path has IDs given as patterns, e.g. routes.CR-*.list, into a command for each ID
+    // matched, e.g. routes.CR-Fitchburg.list, along with the path to the last ID matched, which labels its output;
+    // commands without patterns are left as they are, unlabeled.
+    // A * matches any run of characters. IDs are found as they are for tab completion, so that they may be given as
+    // patterns wherever they may be completed, including more than once in a path. Where nothing follows a
+    // pattern, e.g. stops.place-*, the info of each match is shown.
+    pub fn glob(&self, command: &str) -> Vec<(String, String)> {
+        let path_end = names::path_end(command);
+        let Some(star) = command[..path_end].find('*') else {
+            return vec![(String::new(), command.to_string())];
+        };
+        let start = command[..star].rfind('.').map_or(0, |dot| dot + 1);
+        let end = command[star..path_end].find('.').map_or(path_end, |dot| star + dot);
+        let pattern = &command[start..end];
+        let rest = match &command[end..] {
+            "" => ".info",
+            rest => rest,
+        };
+        let mut ids = self.complete(&command[..star]).into_iter()
+            .filter_map(|completion| Some(completion.strip_suffix('.')?[start..].to_string()))
+            .filter(|id| matches_glob(pattern, id))
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        ids.into_iter()
+            .flat_map(|id| {
+                let path = format!("{}{}", &command[..start], id);
+                self.glob(&format!("{}{}", path, rest)).into_iter()
+                    .map(move |(label, command)| (if label.is_empty() { path.clone() } else { label }, command))
+            })
+            .collect()
+    }
+
+    // navigate returns the node reached from this one by path, e.g. routes.Red or stops.<stop_id>.accessible.
+    // The empty path leads to this node.
+    pub fn navigate(&self, path: &str) -> Result<GtfsNode, GTFSCommandInterpreterError> {
+        if path.is_empty() {
+            return Ok(self.clone());
+        }
+        let (first, rest) = path.split_once('.').unwrap_or((path, ""));
         match first {
-            "info" => Ok(println!("{}", &self.gtfs)),
-            "stops" => match try_tail(rest) {
-                Some(tail) => stops::StopsCommandInterpreter(&self.gtfs)
-                    .interpret(tail.as_str())
-                    .map_err(|e| GTFSCommandInterpreterError::StopsSubcommandError(Box::new(e))),
-                None => Err(GTFSCommandInterpreterError::StopsSubcommandRequired),
-            },
-            "routes" => routes::RoutesCommandInterpreter(&self)
-                .interpret(String::from(&rest[1..]).as_str())
+            "accessible" => self.accessible().navigate(rest),
+            "stops" if !rest.is_empty() => stops::StopsCommandInterpreter(self)
+                .navigate(rest)
+                .map_err(|e| GTFSCommandInterpreterError::StopsSubcommandError(Box::new(e))),
+            "routes" if !rest.is_empty() => routes::RoutesCommandInterpreter(self)
+                .navigate(rest)
                 .map_err(GTFSCommandInterpreterError::RoutesCommandError),
-            "trips" => trips::TripsCommandInterpreter(&self.gtfs)
-                .interpret(String::from(&rest[1..]).as_str())
+            "trips" if !rest.is_empty() => trips::TripsCommandInterpreter(self)
+                .navigate(rest)
                 .map_err(GTFSCommandInterpreterError::TripsCommandError),
-            _ => Err(GTFSCommandInterpreterError::InvalidCommand(command.to_string())),
+            "agency" if !rest.is_empty() => agency::AgencyCommandInterpreter(self)
+                .navigate(rest)
+                .map_err(GTFSCommandInterpreterError::AgencyCommandError),
+            "calendar" if !rest.is_empty() => calendar::CalendarCommandInterpreter(self)
+                .navigate(rest)
+                .map_err(GTFSCommandInterpreterError::CalendarCommandError),
+            "shapes" if !rest.is_empty() => shapes::ShapesCommandInterpreter(self)
+                .navigate(rest)
+                .map_err(GTFSCommandInterpreterError::ShapesCommandError),
+            _ => Err(GTFSCommandInterpreterError::NotANode(path.to_string())),
+        }
+    }
+
+    pub fn view(&self) -> ScheduleView<'_> {
+        ScheduleView::new(&self.gtfs, self.filter.as_deref())
+    }
+
+    // accessible creates a node for the trips and stops of this node which are wheelchair accessible.
+    pub fn accessible(&self) -> Self {
+        let filter = AccessibilityFilter::default().apply(&self.view());
+        self.child("accessible", None, filter)
+    }
+
+    // info counts the stops, routes and trips of the node.
+    fn info(&self) -> Output {
+        let view = self.view();
+        Output::Counts(vec![("Stops", view.stops().count()), ("Routes", view.routes().count()), ("Trips", view.trips().count())])
+    }
+
+    // grep finds the stops, routes and trips whose IDs, names, descriptions or headsigns match a regular expression,
+    // regardless of case, so that plain text matches wherever it appears. Matches are grouped by type and given in
+    // order of ID, with the fields which matched.
+    fn grep(&self, args: &str) -> Result<Table, GTFSCommandInterpreterError> {
+        let args = args.trim();
+        let pattern = args.strip_prefix('"').and_then(|args| args.strip_suffix('"')).unwrap_or(args);
+        if pattern.is_empty() {
+            return Err(GTFSCommandInterpreterError::GrepPatternRequired);
+        }
+        let regex = regex::RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| GTFSCommandInterpreterError::InvalidPattern(pattern.to_string(), e))?;
+
+        let view = self.view();
+        let mut table = Table::new(&["type", "id", "name", "matched"])
+            .with_style(0, |text| text.muted().to_string())
+            .with_empty_message(&format!("Nothing here matches {}", pattern));
+        let mut push_matches = |kind: &str, mut records: Vec<(String, String, HashMap<String, String>)>, fields: &[&str]| {
+            records.sort_by(|a, b| a.0.cmp(&b.0));
+            for (id, name, record) in records {
+                let matched = fields.iter()
+                    .filter(|field| record.get(**field).is_some_and(|value| regex.is_match(value)))
+                    .copied()
+                    .collect::<Vec<_>>();
+                if !matched.is_empty() {
+                    table.push(vec![kind.to_string(), id, name, matched.join(",")]);
+                }
+            }
+        };
+        push_matches(
+            "stop",
+            view.stops()
+                .map(|stop| (stop.stop_id.clone(), stop.get_stop_name().unwrap_or("").to_string(), HashMap::from(stop)))
+                .collect(),
+            &["stop_id", "stop_code", "stop_name", "tts_stop_name", "stop_desc", "platform_code"],
+        );
+        push_matches(
+            "route",
+            view.routes().map(|route| (route.route_id.clone(), route.name(), HashMap::from(route))).collect(),
+            &["route_id", "route_short_name", "route_long_name", "route_desc"],
+        );
+        push_matches(
+            "trip",
+            view.trips()
+                .map(|trip| (trip.trip_id.clone(), trip.trip_headsign.clone().unwrap_or_default(), HashMap::from(trip)))
+                .collect(),
+            &["trip_id", "trip_short_name", "trip_headsign"],
+        );
+        Ok(table)
+    }
+
+    // map draws the node's stops, highlighted, over the shapes its trips follow, filling the terminal but for a line
+    // beneath for the prompt. North is up.
+    fn map(&self) {
+        let view = self.view();
+        let stops = view.stops()
+            .filter_map(|stop| Some((stop.stop_lat()?, stop.stop_lon()?)))
+            .collect::<Vec<_>>();
+        let shapes = view.trips()
+            .filter_map(|trip| trip.shape_id.as_deref())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .filter_map(|shape_id| self.gtfs.shapes.shapes.get(shape_id))
+            .map(|points| points.iter().map(|point| (point.shape_pt_lat, point.shape_pt_lon)).collect())
+            .collect::<Vec<_>>();
+        let (width, height) = terminal_size::terminal_size()
+            .map_or((80, 24), |(terminal_size::Width(width), terminal_size::Height(height))| (width as usize, height as usize));
+        let Some(canvas) = map::draw(&stops, &shapes, width, height.saturating_sub(2).max(1)) else {
+            println!("No stops here have coordinates");
+            return;
+        };
+        for line in canvas.lines() {
+            println!("{}", line);
+        }
+        println!("{}", format!("{} stops, {} shapes", stops.len(), shapes.len()).muted());
+    }
+
+    // nearest lists the n stops closest to a point, 10 by default, with their distances in meters and the names of
+    // the routes calling there.
+    fn nearest(&self, args: &str) -> Result<Table, GTFSCommandInterpreterError> {
+        let invalid = || GTFSCommandInterpreterError::InvalidNearestArguments(args.to_string());
+        let (lat, lon, n) = match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [lat, lon] => (*lat, *lon, 10),
+            [lat, lon, n] => (*lat, *lon, n.strip_prefix("n=").and_then(|n| n.parse().ok()).ok_or_else(invalid)?),
+            _ => return Err(invalid()),
+        };
+        let (lat, lon) = (lat.parse::<f64>().map_err(|_| invalid())?, lon.parse::<f64>().map_err(|_| invalid())?);
+        if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+            return Err(invalid());
+        }
+
+        let view = self.view();
+        let mut table = Table::new(&["stop_id", "stop_name", "meters", "routes"])
+            .with_style(3, |text| text.muted().to_string())
+            .with_empty_message("No stops here have coordinates");
+        for (stop, meters) in view.nearest(lat, lon, n) {
+            table.push(vec![
+                stop.stop_id.clone(),
+                stop.get_stop_name().unwrap_or("Unnamed Location").to_string(),
+                format!("{:.0}", meters),
+                view.routes_at_stop(&stop.stop_id).iter().map(|route| route.name()).collect::<Vec<_>>().join(", "),
+            ]);
+        }
+        Ok(table)
+    }
+
+    // describe lists the fields of a table, with the type the GTFS reference gives each, whether the feed gives it at
+    // all and the share of the records here which give it a value.
+    fn describe(&self, table: &str) -> Result<Table, GTFSCommandInterpreterError> {
+        let view = self.view();
+        let fill = match table.trim() {
+            "stops" => fields::fill(&stop_records::STOP_FIELDS, view.stops().map(HashMap::from)),
+            "routes" => fields::fill(&route_records::ROUTE_FIELDS, view.routes().map(HashMap::from)),
+            "trips" => fields::fill(&trip_records::TRIP_FIELDS, view.trips().map(HashMap::from)),
+            "stop_times" => fields::fill(&stop_times::STOP_TIME_FIELDS, view.stop_times().map(HashMap::from)),
+            table => return Err(GTFSCommandInterpreterError::InvalidDescribeTable(table.to_string())),
+        };
+        let mut table = Table::new(&["field", "type", "present", "fill"])
+            .with_style(1, |text| text.muted().to_string());
+        for field in fill {
+            table.push(vec![
+                field.field.to_string(),
+                fields::field_type(field.field).to_string(),
+                if field.filled > 0 { "yes" } else { "no" }.to_string(),
+                field.rate().map_or("-".to_string(), |rate| format!("{:.1}%", rate * 100.0)),
+            ]);
+        }
+        Ok(table)
+    }
+
+    // stats prints network totals, the busiest stops and routes, where trips dwell longest, and a histogram of trips by the hour they start.
+    fn stats(&self) {
+        const TOP: usize = 10;
+        const BAR_WIDTH: usize = 40;
+        let view = self.view();
+        let stats = Stats::compute(&view);
+        let heading = |heading: &str| println!("{}", heading.muted().bold());
+
+        println!("{}: {}", "Stops".muted().bold(), stats.stops);
+        println!("{}: {}", "Routes".muted().bold(), stats.routes);
+        println!("{}: {}", "Trips".muted().bold(), stats.trips);
+        println!("{}: {}", "Stop times".muted().bold(), stats.stop_times);
+
+        heading("Busiest stops (trips)");
+        for (stop_id, trips) in stats.trips_per_stop.iter().take(TOP) {
+            let name = view.stop(stop_id).and_then(|stop| stop.get_stop_name()).unwrap_or("Unnamed Location");
+            println!("{:>8}  {}: {}", trips, stop_id, name);
+        }
+
+        heading("Busiest routes (stop times)");
+        for (route_id, stop_times) in stats.stop_times_per_route.iter().take(TOP) {
+            let name = view.route(route_id).map_or(route_id.clone(), |route| route.name());
+            println!("{:>8}  {}: {}", stop_times, route_id, name);
+        }
+
+        heading("Longest dwells (stops)");
+        for (stop_id, dwell) in stats.dwell_per_stop.iter().filter(|(_, dwell)| !dwell.total.is_zero()).take(TOP) {
+            let name = view.stop(stop_id).and_then(|stop| stop.get_stop_name()).unwrap_or("Unnamed Location");
+            println!("{:>8}  {}: {} (mean {}s, max {}s)", format!("{}s", dwell.total.as_secs()), stop_id, name, dwell.mean().as_secs(), dwell.max.as_secs());
+        }
+
+        heading("Longest dwells (routes)");
+        for (route_id, dwell) in stats.dwell_per_route.iter().filter(|(_, dwell)| !dwell.total.is_zero()).take(TOP) {
+            let name = view.route(route_id).map_or(route_id.clone(), |route| route.name());
+            println!("{:>8}  {}: {} (mean {}s, max {}s)", format!("{}s", dwell.total.as_secs()), route_id, name, dwell.mean().as_secs(), dwell.max.as_secs());
+        }
+
+        heading("Trips by starting hour");
+        let busiest_hour = stats.trips_per_hour.iter().copied().max().unwrap_or(0).max(1);
+        for (hour, trips) in stats.trips_per_hour.iter().enumerate().skip_while(|(_, trips)| **trips == 0) {
+            println!("{:02}:00 {:>6} {}", hour, trips, "█".repeat(trips * BAR_WIDTH / busiest_hour));
+        }
+    }
+
+    // corridors prints the runs of stops shared by more than one route, with the routes sharing each.
+    fn corridors(&self) {
+        let view = self.view();
+        for corridor in corridors::shared_corridors(&view) {
+            println!(
+                "{}: {}",
+                corridor.route_ids.iter()
+                    .map(|route_id| view.route(route_id).map_or(route_id.clone(), |route| route.name()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+                    .bold(),
+                corridor.stop_ids.join(" → ")
+            );
+        }
+    }
+
+    // travel_times prints the minimum, median and 90th percentile scheduled travel times between two stops on a date,
+    // given as date=2025-01-31 and by default today, over the whole day and for each hour in which journeys depart.
+    fn travel_times(&self, args: &str) -> Result<(), GTFSCommandInterpreterError> {
+        let invalid = || GTFSCommandInterpreterError::InvalidTravelTimesArguments(args.to_string());
+        let (from_stop_id, to_stop_id, date) = match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [from_stop_id, to_stop_id] => (*from_stop_id, *to_stop_id, chrono::Local::now().date_naive()),
+            [from_stop_id, to_stop_id, date] => (*from_stop_id, *to_stop_id, window::date_argument(date).ok_or_else(invalid)?),
+            _ => return Err(invalid()),
+        };
+
+        let view = self.view();
+        let Some(all_day) = travel_times::travel_times(&view, from_stop_id, to_stop_id, date) else {
+            println!("No direct trips from {} to {} on {}", from_stop_id, to_stop_id, date);
+            return Ok(());
+        };
+        let minutes = |duration: std::time::Duration| format!("{:.1}", duration.as_secs_f64() / 60.0);
+        println!("{:<11} {:>8} {:>6} {:>6} {:>6}", "", "journeys", "min", "median", "p90");
+        let print_row = |label: String, times: travel_times::TravelTimes| println!(
+            "{:<11} {:>8} {:>6} {:>6} {:>6}",
+            label, times.journeys, minutes(times.min), minutes(times.median), minutes(times.p90)
+        );
+        print_row("all day".to_string(), all_day);
+        for (hour, times) in travel_times::travel_times_by_hour(&view, from_stop_id, to_stop_id, date) {
+            print_row(format!("{:02}:00", hour), times);
+        }
+        Ok(())
+    }
+
+    // through prints the trips running on a date, given as date=2025-01-31 and by default today, which pass through
+    // any of the polygons of a GeoJSON file, such as a GTFS-Flex locations.geojson.
+    fn through(&self, args: &str) -> Result<(), GTFSCommandInterpreterError> {
+        let invalid = || GTFSCommandInterpreterError::InvalidThroughArguments(args.to_string());
+        let (path, date) = match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [path] => (*path, chrono::Local::now().date_naive()),
+            [path, date] => (*path, window::date_argument(date).ok_or_else(invalid)?),
+            _ => return Err(invalid()),
+        };
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| GTFSCommandInterpreterError::AreaReadError(path.to_string(), e))?;
+        let polygons = Polygon::from_geojson(&json)
+            .map_err(|e| GTFSCommandInterpreterError::AreaParseError(path.to_string(), e))?;
+
+        let view = self.view();
+        let mut trips = polygons.iter()
+            .flat_map(|polygon| view.trips_through_area(polygon, date))
+            .collect::<Vec<_>>();
+        trips.sort_by(|a, b| a.trip_id.cmp(&b.trip_id));
+        trips.dedup_by(|a, b| a.trip_id == b.trip_id);
+        if trips.is_empty() {
+            println!("No trips pass through {} on {}", path, date);
+        }
+        for trip in trips {
+            println!(
+                "{}: {} {}",
+                trip.trip_id,
+                view.route(&trip.route_id).map_or(trip.route_id.clone(), |route| route.name()),
+                trip.trip_headsign.as_deref().unwrap_or("").muted()
+            );
+        }
+        Ok(())
+    }
+
+    // diff loads the feed at source and prints how it differs from this node's schedule, as the arguments after source
+    // drill down into it.
+    fn diff(&self, args: &str) -> Result<(), GTFSCommandInterpreterError> {
+        let args = args.split_whitespace().collect::<Vec<_>>();
+        let Some((source, drill_down)) = args.split_first() else {
+            return Err(GTFSCommandInterpreterError::DiffSourceRequired);
+        };
+        let drill_down = diff::drill_down(drill_down).map_err(GTFSCommandInterpreterError::InvalidDiffArguments)?;
+        let other = FeedSource::from(*source)
+            .load()
+            .map_err(|e| GTFSCommandInterpreterError::DiffLoadError(source.to_string(), e))?;
+        diff::print_diff(&self.view().to_schedule().diff(&other), drill_down);
+        Ok(())
+    }
+
+    // export writes the node's part of the schedule to path: as a GTFS zip or a SQLite store, which can be loaded again,
+    // as GeoJSON of its stops and shapes, or as JSON with an array for each table. The agencies and calendars are
+    // written whole.
+    fn export(&self, args: &str) -> Result<(), GTFSCommandInterpreterError> {
+        let invalid = || GTFSCommandInterpreterError::InvalidExportArguments(args.to_string());
+        let (format, path) = args.trim().split_once(char::is_whitespace).ok_or_else(invalid)?;
+        let path = path.trim();
+        let path = path.strip_prefix('"').and_then(|path| path.strip_suffix('"')).unwrap_or(path);
+        if path.is_empty() {
+            return Err(invalid());
+        }
+
+        let view = self.view();
+        let schedule = view.to_schedule();
+        let write_json = |json: serde_json::Value| std::fs::write(path, format!("{:#}\n", json))
+            .map_err(|e| GTFSCommandInterpreterError::ExportWriteError(path.to_string(), e));
+        match format {
+            "gtfs" => {
+                let file = std::fs::File::create(path)
+                    .map_err(|e| GTFSCommandInterpreterError::ExportWriteError(path.to_string(), e))?;
+                ZipWriter::new(file)
+                    .write(&schedule)
+                    .map_err(|e| GTFSCommandInterpreterError::ExportZipError(path.to_string(), e))?;
+            },
+            "sqlite" => SqliteStore::open(path)
+                .and_then(|mut store| store.save(&schedule))
+                .map_err(|e| GTFSCommandInterpreterError::ExportStoreError(path.to_string(), e))?,
+            "geojson" => write_json(json_writer::to_geojson(&schedule))?,
+            "json" => write_json(json_writer::to_json(&schedule))?,
+            _ => return Err(invalid()),
         }
+        println!(
+            "Wrote {} stops, {} routes and {} trips to {}",
+            view.stops().count(), view.routes().count(), view.trips().count(), path
+        );
+        Ok(())
     }
 }
 
-fn try_tail(s: &str) -> Option<String> {
-    let s = s.chars().skip(1).collect::<String>();
-    if s.is_empty() {
-        None
-    } else {
-        Some(s)
+// matches_glob reports whether text matches pattern, in which a * stands for any run of characters.
+fn matches_glob(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => text.strip_prefix(prefix).is_some_and(|text| {
+            text.char_indices().map(|(i, _)| i).chain([text.len()]).any(|i| matches_glob(rest, &text[i..]))
+        }),
     }
 }
@@ -0,0 +1,52 @@
+use crate::style::{Colorize, Paint};
+use std::io::{IsTerminal, Write};
+
+// Logger writes log records to stderr, so that stdout holds only the results of commands, e.g. JSON for a pipeline.
+// Progress is shown in grey, and warnings and errors stand out; records below info are labeled with their level. On a
+// terminal, each record replaces any progress bar or spinner drawn on the last line, which is drawn again beneath it.
+struct Logger;
+
+static LOGGER: Logger = Logger;
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = record.args().to_string();
+        let line = match record.level() {
+            log::Level::Error => format!("{} {}", "error:".red().bold(), message),
+            log::Level::Warn => format!("{} {}", "warning:".yellow().bold(), message),
+            log::Level::Info => message.muted().to_string(),
+            level => format!("[{}] {}", level.as_str().to_lowercase(), message).muted().to_string(),
+        };
+        let clear = if std::io::stderr().is_terminal() { "\r\x1b[2K" } else { "" };
+        let _ = writeln!(std::io::stderr(), "{}{}", clear, line);
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
+
+// verbosity gives the most detailed level logged, given how many times -v and whether -q were passed: warnings and
+// errors with -q, progress by default, the time taken to parse each table and run each command with -v, and
+// everything with -vv.
+pub fn verbosity(quiet: bool, verbose: u8) -> log::LevelFilter {
+    match (quiet, verbose) {
+        (true, _) => log::LevelFilter::Warn,
+        (false, 0) => log::LevelFilter::Info,
+        (false, 1) => log::LevelFilter::Debug,
+        (false, _) => log::LevelFilter::Trace,
+    }
+}
+
+// init makes Logger the logger for the process, logging records up to level.
+pub fn init(level: log::LevelFilter) {
+    log::set_logger(&LOGGER).expect("the logger is only set once");
+    log::set_max_level(level);
+}
@@ -0,0 +1,162 @@
+use crate::style::Colorize;
+
+// Canvas draws in the terminal with braille characters, each of which holds a grid of 2 by 4 dots, giving a
+// resolution finer than that of the characters themselves. Dots are addressed from the top left.
+pub struct Canvas {
+    // width and height are in characters.
+    width: usize,
+    height: usize,
+    // cells holds the dots set in each character, as the bits of its braille pattern.
+    cells: Vec<u8>,
+    // marked notes the characters holding a marked dot, such as a stop, which are highlighted.
+    marked: Vec<bool>,
+}
+
+// BRAILLE_BLANK is the braille character with no dots, which the others follow as the bits of their patterns.
+const BRAILLE_BLANK: u32 = 0x2800;
+// DOT_BITS gives the bit of each dot of a braille character, by its column and row.
+const DOT_BITS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+impl Canvas {
+    pub fn new(width: usize, height: usize) -> Self {
+        Canvas { width, height, cells: vec![0; width * height], marked: vec![false; width * height] }
+    }
+
+    // dots gives the width and height of the canvas in dots.
+    pub fn dots(&self) -> (usize, usize) {
+        (self.width * 2, self.height * 4)
+    }
+
+    // dot sets the dot at x, y. Dots off the canvas are ignored.
+    pub fn dot(&mut self, x: i64, y: i64) {
+        if let Some(cell) = self.cell(x, y) {
+            self.cells[cell] |= DOT_BITS[x as usize % 2][y as usize % 4];
+        }
+    }
+
+    // mark sets the dot at x, y and highlights the character holding it.
+    pub fn mark(&mut self, x: i64, y: i64) {
+        self.dot(x, y);
+        if let Some(cell) = self.cell(x, y) {
+            self.marked[cell] = true;
+        }
+    }
+
+    // line draws a straight line between two dots with Bresenham's algorithm.
+    pub fn line(&mut self, (x0, y0): (i64, i64), (x1, y1): (i64, i64)) {
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (step_x, step_y) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+        let (mut x, mut y, mut error) = (x0, y0, dx + dy);
+        loop {
+            self.dot(x, y);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    // lines renders the canvas as lines of text, with marked characters highlighted.
+    pub fn lines(&self) -> Vec<String> {
+        self.cells.chunks(self.width)
+            .zip(self.marked.chunks(self.width))
+            .map(|(cells, marked)| cells.iter()
+                .zip(marked)
+                .map(|(bits, marked)| {
+                    let braille = char::from_u32(BRAILLE_BLANK + *bits as u32).expect("braille patterns are characters").to_string();
+                    match marked {
+                        true => braille.yellow().bold().to_string(),
+                        false => braille,
+                    }
+                })
+                .collect())
+            .collect()
+    }
+
+    fn cell(&self, x: i64, y: i64) -> Option<usize> {
+        let (width, height) = self.dots();
+        (x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height)
+            .then(|| (y as usize / 4) * self.width + x as usize / 2)
+    }
+}
+
+// Projection maps coordinates onto the dots of a canvas, with north up. Longitudes are scaled by the cosine of the
+// latitude in the middle of the map, so that a dot covers the same distance either way, which is accurate enough
+// for the area covered by a feed.
+pub struct Projection {
+    max_lat: f64,
+    min_lon: f64,
+    lon_scale: f64,
+    // dots_per_degree is how many dots a degree of latitude covers.
+    dots_per_degree: f64,
+    // offset centers the map on a canvas of a different shape: x, y in dots.
+    offset: (f64, f64),
+}
+
+impl Projection {
+    // fit finds the projection fitting points, given as latitudes and longitudes, to a canvas of the given size in
+    // dots, or None if there are no points.
+    pub fn fit(points: impl Iterator<Item = (f64, f64)>, (width, height): (usize, usize)) -> Option<Projection> {
+        let (mut min_lat, mut max_lat, mut min_lon, mut max_lon) = (f64::MAX, f64::MIN, f64::MAX, f64::MIN);
+        for (lat, lon) in points {
+            (min_lat, max_lat, min_lon, max_lon) = (min_lat.min(lat), max_lat.max(lat), min_lon.min(lon), max_lon.max(lon));
+        }
+        if min_lat > max_lat {
+            return None;
+        }
+        let lon_scale = ((min_lat + max_lat) / 2.0).to_radians().cos();
+        let (span_x, span_y) = ((max_lon - min_lon) * lon_scale, max_lat - min_lat);
+        let (width, height) = ((width.max(1) - 1) as f64, (height.max(1) - 1) as f64);
+        // a single point, or points in a line, leave a span of zero, which any scale fits.
+        let dots_per_degree = match (span_x > 0.0, span_y > 0.0) {
+            (true, true) => (width / span_x).min(height / span_y),
+            (true, false) => width / span_x,
+            (false, true) => height / span_y,
+            (false, false) => 1.0,
+        };
+        Some(Projection {
+            max_lat,
+            min_lon,
+            lon_scale,
+            dots_per_degree,
+            offset: ((width - span_x * dots_per_degree) / 2.0, (height - span_y * dots_per_degree) / 2.0),
+        })
+    }
+
+    pub fn project(&self, lat: f64, lon: f64) -> (i64, i64) {
+        (
+            (self.offset.0 + (lon - self.min_lon) * self.lon_scale * self.dots_per_degree).round() as i64,
+            (self.offset.1 + (self.max_lat - lat) * self.dots_per_degree).round() as i64,
+        )
+    }
+}
+
+// draw draws shapes, as polylines, and stops, as marked dots, on a canvas of the given size in characters, fitted to
+// them. It is None if there is nothing to draw.
+pub fn draw(stops: &[(f64, f64)], shapes: &[Vec<(f64, f64)>], width: usize, height: usize) -> Option<Canvas> {
+    let mut canvas = Canvas::new(width, height);
+    let points = stops.iter().chain(shapes.iter().flatten()).copied();
+    let projection = Projection::fit(points, canvas.dots())?;
+    for shape in shapes {
+        for pair in shape.windows(2) {
+            canvas.line(projection.project(pair[0].0, pair[0].1), projection.project(pair[1].0, pair[1].1));
+        }
+        if let [(lat, lon)] = shape.as_slice() {
+            let (x, y) = projection.project(*lat, *lon);
+            canvas.dot(x, y);
+        }
+    }
+    for (lat, lon) in stops {
+        let (x, y) = projection.project(*lat, *lon);
+        canvas.mark(x, y);
+    }
+    Some(canvas)
+}
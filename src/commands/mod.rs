@@ -1,10 +1,155 @@
-pub mod gtfs;
-mod stops;
-mod routes;
-mod trips;
+use crate::style::{Colorize, Paint};
+
+pub(crate) mod agency;
+pub mod bench;
+pub(crate) mod calendar;
+pub mod config;
+pub mod diff;
+pub(crate) mod fares;
+pub(crate) mod filter;
+pub(crate) mod gtfs;
+pub mod logging;
+pub(crate) mod map;
+pub mod names;
+pub(crate) mod open;
+pub mod output;
+pub mod progress;
+pub mod realtime;
+pub(crate) mod redirect;
+pub mod repl;
+pub(crate) mod shapes;
+pub(crate) mod stops;
+pub(crate) mod table;
+pub(crate) mod routes;
+pub(crate) mod trips;
+pub mod validate;
+pub(crate) mod window;
 pub trait CommandInterpreter {
     type CommandResult;
     type CommandError: std::error::Error;
 
     fn interpret(&self, command: &str) -> Result<Self::CommandResult, Self::CommandError>;
-}
\ No newline at end of file
+
+    // complete lists, in full, the commands this interpreter accepts which begin with partial, for tab completion.
+    // Commands naming an entity, such as stops.<stop_id>.tree, are completed with the IDs of the entities there are.
+    fn complete(&self, _partial: &str) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+// Command is an entry in an interpreter's table of commands: its name, a summary of the arguments it takes,
+// what it does, and the function run to do it. Interpreters dispatch through their tables, so that help lists
+// exactly the commands which run.
+pub struct Command<F> {
+    pub name: &'static str,
+    // args summarizes what may follow the name, e.g. "[date] [time] [limit]". Commands which run a subcommand,
+    // such as stops.list, take arguments starting with a dot, e.g. ".<command>"; others are separated by a space.
+    pub args: &'static str,
+    pub description: &'static str,
+    pub run: F,
+}
+
+impl<F> Command<F> {
+    // arguments returns what follows the command's name in command, if command invokes it.
+    pub fn arguments<'c>(&self, command: &'c str) -> Option<&'c str> {
+        if self.name == "help" && is_help(command) {
+            return Some("");
+        }
+        let rest = command.strip_prefix(self.name)?;
+        if rest.is_empty() {
+            Some("")
+        } else if rest.starts_with(' ') {
+            Some(rest.trim())
+        } else if rest.starts_with('.') && self.args.starts_with('.') {
+            Some(&rest[1..])
+        } else {
+            None
+        }
+    }
+}
+
+// complete_commands lists the names of commands beginning with partial. Those which run a subcommand are followed
+// by a dot, ready for it.
+pub fn complete_commands<F>(commands: &[Command<F>], partial: &str) -> Vec<String> {
+    commands.iter()
+        .filter(|command| command.name.starts_with(partial))
+        .map(|command| if command.args.starts_with('.') { format!("{}.", command.name) } else { command.name.to_string() })
+        .collect()
+}
+
+// complete_ids lists the IDs beginning with partial, each followed by a dot, ready for a command about the entity.
+pub fn complete_ids<'a>(ids: impl Iterator<Item = &'a str>, partial: &str) -> Vec<String> {
+    ids.filter(|id| id.starts_with(partial))
+        .map(|id| format!("{}.", id))
+        .collect()
+}
+
+// is_help reports whether command asks for help, which may be given as help or ?.
+pub fn is_help(command: &str) -> bool {
+    command == "help" || command == "?"
+}
+
+// find_command returns the command in commands which command invokes, along with its arguments.
+pub fn find_command<'c, F>(commands: &'c [Command<F>], command: &'c str) -> Option<(&'c Command<F>, &'c str)> {
+    commands.iter().find_map(|candidate| Some((candidate, candidate.arguments(command)?)))
+}
+
+// help_lines gives a (usage, description) line for each command, its usage being its name preceded by prefix,
+// such as "<stop_id>.", and followed by its arguments.
+pub fn help_lines<F>(commands: &[Command<F>], prefix: &str) -> Vec<(String, &'static str)> {
+    commands.iter()
+        .map(|command| (
+            match command.args {
+                "" => format!("{}{}", prefix, command.name),
+                args if args.starts_with('.') => format!("{}{}{}", prefix, command.name, args),
+                args => format!("{}{} {}", prefix, command.name, args),
+            },
+            command.description,
+        ))
+        .collect()
+}
+
+// print_help prints help lines, with the descriptions aligned.
+pub fn print_help(lines: &[(String, &str)]) {
+    let width = lines.iter().map(|(usage, _)| usage.chars().count()).max().unwrap_or(0);
+    for (usage, description) in lines {
+        println!("{:<width$}  {}", usage, description.muted(), width = width);
+    }
+}
+
+// print_collection_help prints the help of a collection such as the stops: its own commands, the commands about
+// each of its entities, given as <id>.<command>, how to run commands on an entity's node, and how to name an entity.
+pub fn print_collection_help<F, G>(commands: &[Command<F>], entity_commands: &[Command<G>], entity: &str, id: &str) {
+    let mut lines = help_lines(commands, "");
+    lines.extend(help_lines(entity_commands, &format!("{}.", id)));
+    let node_description = format!("Run a command on the {}'s node; see {}.help", entity, id);
+    lines.push((format!("{}.<command>", id), node_description.as_str()));
+    let name_description = format!("Give the {} by name in place of {}, listing the matches if there are several", entity, id);
+    lines.push(("\"<name>\".<command>".to_string(), name_description.as_str()));
+    print_help(&lines);
+}
+
+// print_fields prints an entity's fields as a labeled block, such as a stop's info, with the values aligned.
+// Fields the feed leaves unset are left out.
+pub fn print_fields(fields: &[(&str, Option<String>)]) {
+    let width = fields.iter()
+        .filter(|(_, value)| value.is_some())
+        .map(|(label, _)| label.chars().count() + 1)
+        .max()
+        .unwrap_or(0);
+    for (label, value) in fields {
+        if let Some(value) = value {
+            println!("{} {}", format!("{:<width$}", format!("{}:", label), width = width).muted().bold(), value);
+        }
+    }
+}
+
+// swatch shows a color, such as a route's, as a block of it followed by its hex code.
+pub fn swatch(r: u8, g: u8, b: u8) -> String {
+    format!("{} #{:02X}{:02X}{:02X}", "  ".on_rgb(r, g, b), r, g, b)
+}
+
+// yes_no describes a field such as wheelchair_boarding, which the feed may leave unknown.
+pub fn yes_no(value: Option<bool>) -> Option<String> {
+    value.map(|value| if value { "yes" } else { "no" }.to_string())
+}
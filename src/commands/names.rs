@@ -0,0 +1,117 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// INTERACTIVE is whether a name which could be any of several stops, routes or trips offers a choice between them
+// rather than failing. It's set only for the REPL on a terminal, since scripts have no one to ask.
+static INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+// set_interactive chooses whether names matching more than one entity offer a choice between them from then on.
+pub fn set_interactive(interactive: bool) {
+    INTERACTIVE.store(interactive, Ordering::Relaxed);
+}
+
+// Segment is the part of a command's path which picks out a stop, route or trip: its ID, e.g. 70061, or its name in
+// quotes, e.g. "Harvard", since people rarely know the IDs a feed uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment<'a> {
+    Id(&'a str),
+    Name(&'a str),
+}
+
+// split_segment splits the first segment of path from the rest of it, which follows a dot. A quoted segment, e.g.
+// "Red Line" in "Red Line".stops.list, may hold dots and spaces.
+pub fn split_segment(path: &str) -> (Segment<'_>, &str) {
+    if let Some((name, rest)) = path.strip_prefix('"').and_then(|quoted| quoted.split_once('"')) {
+        match rest.strip_prefix('.') {
+            Some(rest) => return (Segment::Name(name), rest),
+            None if rest.is_empty() => return (Segment::Name(name), rest),
+            None => (),
+        }
+    }
+    let (first, rest) = path.split_once('.').unwrap_or((path, ""));
+    (Segment::Id(first), rest)
+}
+
+// path_end finds where the path of a command ends and its arguments begin, at the first space outside quotes.
+pub fn path_end(command: &str) -> usize {
+    let mut quoted = false;
+    for (i, c) in command.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ' ' if !quoted => return i,
+            _ => (),
+        }
+    }
+    command.len()
+}
+
+// matching finds the candidates, given as their IDs and names, called name. Names are compared ignoring case; if
+// none is called name exactly, those whose names contain it are found instead, so that "Harvard" finds
+// "Harvard Square" when nothing is called just "Harvard".
+pub fn matching<'a>(name: &str, candidates: impl IntoIterator<Item = (&'a str, &'a str)>) -> Vec<(&'a str, &'a str)> {
+    let name = name.to_lowercase();
+    let (exact, partial) = candidates.into_iter()
+        .filter(|(_, candidate)| candidate.to_lowercase().contains(&name))
+        .partition::<Vec<_>, _>(|(_, candidate)| candidate.to_lowercase() == name);
+    if exact.is_empty() { partial } else { exact }
+}
+
+// one gives the ID of the only match for the name of a stop, route or trip, the kind of entity named. If there are
+// several and the session is interactive, the one meant is picked from a list, so that the rest of the command runs
+// on it; otherwise, or if the choice is abandoned, the error lists the matches, if there are any, to choose between.
+pub fn one(kind: &'static str, name: &str, mut matches: Vec<(&str, &str)>) -> Result<String, NameError> {
+    match matches.len() {
+        0 => Err(NameError::NoMatch(kind, name.to_string())),
+        1 => Ok(matches[0].0.to_string()),
+        // a route matches once for each of its names which matches.
+        _ if matches.iter().all(|(id, _)| *id == matches[0].0) => Ok(matches[0].0.to_string()),
+        _ => {
+            matches.sort();
+            matches.dedup_by_key(|(id, _)| *id);
+            let matches = matches.into_iter().map(|(id, name)| (id.to_string(), name.to_string())).collect::<Vec<_>>();
+            if let Some(id) = INTERACTIVE.load(Ordering::Relaxed).then(|| pick(kind, name, &matches)).flatten() {
+                return Ok(id);
+            }
+            Err(NameError::Ambiguous(kind, name.to_string(), matches))
+        },
+    }
+}
+
+// pick asks which of matches, given as their IDs and names, a name was meant for, with a list which typing narrows
+// and the arrow keys move through. It gives None if the choice is abandoned with Esc, or can't be offered.
+fn pick(kind: &str, name: &str, matches: &[(String, String)]) -> Option<String> {
+    let items = matches.iter().map(|(id, name)| format!("{} ({})", name, id)).collect::<Vec<_>>();
+    dialoguer::FuzzySelect::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt(format!("\"{}\" could be any of {} {}s; which did you mean?", name, matches.len(), kind))
+        .items(&items)
+        .default(0)
+        .interact_opt()
+        .unwrap_or_else(|err| {
+            log::warn!("Couldn't offer a choice between the matches: {}", err);
+            None
+        })
+        .map(|index| matches[index].0.clone())
+}
+
+#[derive(Debug)]
+pub enum NameError {
+    NoMatch(&'static str, String),
+    // Ambiguous holds the IDs and names of the matches.
+    Ambiguous(&'static str, String, Vec<(String, String)>),
+}
+
+impl std::fmt::Display for NameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NameError::NoMatch(kind, name) => write!(f, "No {} is called \"{}\"", kind, name),
+            NameError::Ambiguous(kind, name, matches) => {
+                write!(f, "\"{}\" could be any of {} {}s; give one of their IDs instead:", name, matches.len(), kind)?;
+                for (id, name) in matches {
+                    write!(f, "\n  {}: {}", id, name)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl std::error::Error for NameError {}
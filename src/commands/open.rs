@@ -0,0 +1,68 @@
+use std::process;
+
+// PRINT_ONLY is the argument to open which prints the URL rather than opening it, e.g. over SSH.
+pub const PRINT_ONLY: &str = "--print-only";
+
+#[derive(Debug)]
+pub enum OpenError {
+    InvalidArgument(String),
+    // NoUrl names what has no URL, e.g. stop 70061.
+    NoUrl(String),
+}
+
+impl std::fmt::Display for OpenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OpenError::InvalidArgument(argument) => write!(f, "Invalid open argument: {} (expected {} or nothing)", argument, PRINT_ONLY),
+            OpenError::NoUrl(entity) => write!(f, "The feed gives no URL for {}", entity),
+        }
+    }
+}
+
+impl std::error::Error for OpenError {}
+
+// open opens the web page of an entity, such as a stop's stop_url, in the system's browser, given args of nothing or
+// --print-only. The URL is printed instead if --print-only is given, if no browser can be started, or if it isn't a
+// web address, since feeds are published by others and what's opened shouldn't be up to them.
+pub fn open(entity: &str, url: Option<&str>, args: &str) -> Result<(), OpenError> {
+    let print_only = match args {
+        "" => false,
+        PRINT_ONLY => true,
+        argument => return Err(OpenError::InvalidArgument(argument.to_string())),
+    };
+    let url = url.ok_or_else(|| OpenError::NoUrl(entity.to_string()))?;
+    let web = url.starts_with("http://") || url.starts_with("https://");
+    if !print_only && web && launch(url) {
+        println!("Opened {}", url);
+        return Ok(());
+    }
+    if !print_only && !web {
+        log::warn!("Not opening {}, which isn't a web address", url);
+    }
+    println!("{}", url);
+    Ok(())
+}
+
+// launch opens url with the system's opener, reporting whether it succeeded.
+fn launch(url: &str) -> bool {
+    let mut command = match std::env::consts::OS {
+        "macos" => process::Command::new("open"),
+        "windows" => {
+            let mut command = process::Command::new("cmd");
+            // start takes the first quoted argument as a window title, so an empty one is given.
+            command.args(["/C", "start", ""]);
+            command
+        },
+        _ => process::Command::new("xdg-open"),
+    };
+    let launched = command.arg(url)
+        .stdin(process::Stdio::null())
+        .stdout(process::Stdio::null())
+        .stderr(process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if !launched {
+        log::warn!("Couldn't start a browser; open the URL below yourself");
+    }
+    launched
+}
@@ -0,0 +1,242 @@
+use crate::commands::table::{SortKey, Table};
+use crate::style::{Colorize, Paint};
+
+// OutputFormat is how the results of commands are shown: as text for people to read, as JSON for scripts, or as CSV
+// for spreadsheets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Csv,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(()),
+        }
+    }
+}
+
+// OutputOptions are given after a command to shape the table it returns, e.g. stops.list fields=stop_id,stop_lat
+// sort=stop_name:desc limit=10.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutputOptions {
+    // fields names the fields to show, in order, in place of the table's default ones.
+    pub fields: Option<Vec<String>>,
+    // sort names the fields to sort rows by, in place of the order the command gives them in.
+    pub sort: Option<Vec<SortKey>>,
+    // offset and limit choose which of the sorted rows to show: at most limit of them, after skipping offset.
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+    // accessible=true runs the command on the wheelchair-accessible trips and stops alone, as though it were given
+    // after accessible., and accessible=false on all of them, whatever --accessible says.
+    pub accessible: Option<bool>,
+}
+
+impl OutputOptions {
+    // split separates the options at the end of a command from the command itself. Arguments the command takes
+    // itself, such as routes.list type=bus, are left with the command.
+    pub fn split(command: &str) -> Result<(&str, OutputOptions), OutputError> {
+        let mut options = OutputOptions::default();
+        let mut rest = command.trim_end();
+        let count = |option: &str, value: &str| value.parse::<usize>().map_err(|_| OutputError::InvalidOption(option.to_string()));
+        while let Some((before, last)) = rest.rsplit_once(' ') {
+            if let Some(fields) = last.strip_prefix("fields=") {
+                options.fields = Some(fields.split(',').map(|field| field.to_string()).collect());
+            } else if let Some(keys) = last.strip_prefix("sort=") {
+                options.sort = Some(keys.split(',').map(SortKey::from).collect());
+            } else if let Some(offset) = last.strip_prefix("offset=") {
+                options.offset = Some(count(last, offset)?);
+            } else if let Some(limit) = last.strip_prefix("limit=") {
+                options.limit = Some(count(last, limit)?);
+            } else if let Some(accessible) = last.strip_prefix("accessible=") {
+                options.accessible = Some(accessible.parse::<bool>().map_err(|_| OutputError::InvalidAccessible(last.to_string()))?);
+            } else {
+                break;
+            }
+            rest = before.trim_end();
+        }
+        Ok((rest, options))
+    }
+
+    // is_empty reports whether there are no options shaping the table; accessible= chooses what the command runs on
+    // instead, so applies to every command.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_none() && self.sort.is_none() && self.offset.is_none() && self.limit.is_none()
+    }
+}
+
+#[derive(Debug)]
+pub enum OutputError {
+    // NotATable is returned for options given to a command whose result isn't a table, e.g. info fields=stops.
+    NotATable,
+    UnknownField(String, Vec<String>),
+    // InvalidOption is returned for limit= and offset= options not given a number of rows.
+    InvalidOption(String),
+    InvalidAccessible(String),
+}
+
+impl std::fmt::Display for OutputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputError::NotATable => write!(f, "Options such as fields=, sort= and limit= only apply to commands which list things"),
+            OutputError::InvalidOption(option) => write!(f, "Invalid option {}: expected a number of rows, e.g. limit=10", option),
+            OutputError::InvalidAccessible(option) => write!(f, "Invalid option {}: expected accessible=true or accessible=false", option),
+            OutputError::UnknownField(field, fields) => write!(f, "Unknown field {} (expected one of {})", field, fields.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for OutputError {}
+
+// Output is the result of a command. Commands whose results are data, such as lists and counts, return it to be
+// shown in the format chosen; the others print as they go, and return Printed.
+pub enum Output {
+    Printed,
+    Table(Table),
+    Counts(Vec<(&'static str, usize)>),
+    // Sections are the results of a command run on several matches, e.g. routes.CR-*.list, labeled with the path to each.
+    Sections(Vec<(String, Output)>),
+    // Results are the results of foreach, which runs a command for each row of a listing, labeled with the row's ID.
+    Results(Vec<(String, Output)>),
+}
+
+impl Output {
+    // shape applies options to a table, which otherwise shows its default fields and default number of rows. Rows are sorted before they are
+    // paged and before fields are chosen, so they may be sorted by fields which aren't shown.
+    pub fn shape(self, options: &OutputOptions) -> Result<Output, OutputError> {
+        let mut table = match self {
+            Output::Table(table) => table,
+            Output::Sections(sections) => return sections.into_iter()
+                .map(|(label, output)| Ok((label, output.shape(options)?)))
+                .collect::<Result<_, _>>()
+                .map(Output::Sections),
+            Output::Results(results) => return results.into_iter()
+                .map(|(id, output)| Ok((id, output.shape(options)?)))
+                .collect::<Result<_, _>>()
+                .map(Output::Results),
+            output if options.is_empty() => return Ok(output),
+            _ => return Err(OutputError::NotATable),
+        };
+        if let Some(keys) = &options.sort {
+            table.sort(keys).map_err(|field| OutputError::UnknownField(field, table.headers().to_vec()))?;
+        }
+        table.page(options.offset.unwrap_or(0), options.limit.or(table.default_limit()));
+        let Some(fields) = options.fields.as_deref().or(table.default_fields()) else {
+            return Ok(Output::Table(table));
+        };
+        table.select(fields)
+            .map(Output::Table)
+            .map_err(|field| OutputError::UnknownField(field, table.headers().to_vec()))
+    }
+
+    pub fn print(&self, format: OutputFormat) {
+        match (self, format) {
+            (Output::Printed, _) => (),
+            (Output::Table(table), OutputFormat::Text) => table.print(),
+            (Output::Counts(counts), OutputFormat::Text) => {
+                for (name, count) in counts {
+                    println!("{}: {}", name.muted().bold(), count);
+                }
+            },
+            (Output::Sections(sections) | Output::Results(sections), OutputFormat::Text) => {
+                for (label, output) in sections {
+                    print_heading(label);
+                    output.print(format);
+                }
+            },
+            (output, OutputFormat::Json) => println!("{}", output.to_json()),
+            (output, OutputFormat::Csv) => print!("{}", output.to_csv()),
+        }
+    }
+
+    // to_json gives a table as an array with an object for each row, keyed by the table's headers, or with an array
+    // for each row if it has none. Counts are given as an object, keyed by the names of what they count in snake case.
+    // Sections are given as an object keyed by their labels, and results as an array of objects giving each one's ID
+    // and result.
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Output::Printed => serde_json::Value::Null,
+            Output::Table(table) => table.rows()
+                .iter()
+                .map(|row| match table.headers() {
+                    [] => serde_json::Value::from(row.clone()),
+                    headers => headers.iter().cloned()
+                        .zip(row.iter().map(|cell| serde_json::Value::from(cell.as_str())))
+                        .collect::<serde_json::Map<_, _>>()
+                        .into(),
+                })
+                .collect(),
+            Output::Counts(counts) => counts.iter()
+                .map(|(name, count)| (snake_case(name), serde_json::Value::from(*count)))
+                .collect::<serde_json::Map<_, _>>()
+                .into(),
+            Output::Sections(sections) => sections.iter()
+                .map(|(label, output)| (label.clone(), output.to_json()))
+                .collect::<serde_json::Map<_, _>>()
+                .into(),
+            Output::Results(results) => results.iter()
+                .map(|(id, output)| serde_json::json!({ "id": id, "result": output.to_json() }))
+                .collect(),
+        }
+    }
+
+    // to_csv gives a table as CSV, with its headers as the first row. Counts are given as a row of the names of what
+    // they count, in snake case, and a row of the counts. Sections are given as a single table, with a first column,
+    // match, labeling the rows of each, and results likewise, with a first column, id.
+    pub fn to_csv(&self) -> String {
+        let (headers, rows) = self.records();
+        let mut writer = csv::WriterBuilder::new().flexible(true).from_writer(Vec::new());
+        for record in headers.into_iter().chain(rows) {
+            writer.write_record(&record).expect("writing CSV to memory can't fail");
+        }
+        String::from_utf8(writer.into_inner().expect("writing CSV to memory can't fail")).expect("the CSV is built from strings")
+    }
+
+    // records gives the headers and rows of the output as a table, as to_csv writes it.
+    fn records(&self) -> (Option<Vec<String>>, Vec<Vec<String>>) {
+        match self {
+            Output::Printed => (None, Vec::new()),
+            Output::Table(table) => (
+                (!table.headers().is_empty()).then(|| table.headers().to_vec()),
+                table.rows().to_vec(),
+            ),
+            Output::Counts(counts) => (
+                Some(counts.iter().map(|(name, _)| snake_case(name)).collect()),
+                vec![counts.iter().map(|(_, count)| count.to_string()).collect()],
+            ),
+            Output::Sections(sections) => labeled_records("match", sections),
+            Output::Results(results) => labeled_records("id", results),
+        }
+    }
+}
+
+// labeled_records gives the records of sections of output as a single table, with a first column, named column,
+// labeling the rows of each. The sections are results of the same command, so the headers of the first are taken to
+// be those of all.
+fn labeled_records(column: &str, sections: &[(String, Output)]) -> (Option<Vec<String>>, Vec<Vec<String>>) {
+    let (mut headers, mut rows) = (None, Vec::new());
+    for (label, output) in sections {
+        let (section_headers, section_rows) = output.records();
+        headers = headers.or(section_headers.map(|section_headers| [vec![column.to_string()], section_headers].concat()));
+        rows.extend(section_rows.into_iter().map(|row| [vec![label.clone()], row].concat()));
+    }
+    (headers, rows)
+}
+
+fn snake_case(name: &str) -> String {
+    name.to_lowercase().replace(' ', "_")
+}
+
+// print_heading labels a section of output, such as the results of a command for one of the routes it matched.
+pub fn print_heading(label: &str) {
+    println!("{}", format!("== {} ==", label).bold());
+}
@@ -0,0 +1,163 @@
+use crate::gtfs::loaders::zip_loader::ZipLoaderEventHandler;
+use crate::style::Paint;
+use std::cell::Cell;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// BAR_WIDTH is the width of a progress bar, in characters.
+const BAR_WIDTH: usize = 30;
+// REDRAW_INTERVAL is how often a progress bar or spinner is drawn at most, so that drawing doesn't slow what it shows.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+// SPINNER_FRAMES are drawn in turn by a spinner.
+const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+// shown is whether progress is drawn: only on a terminal, since it's redrawn in place, and not with -q.
+fn shown() -> bool {
+    std::io::stderr().is_terminal() && log::max_level() >= log::LevelFilter::Info
+}
+
+// draw replaces the line progress is drawn on with text.
+fn draw(text: &str) {
+    let mut stderr = std::io::stderr().lock();
+    let _ = write!(stderr, "\r\x1b[2K{}", text);
+    let _ = stderr.flush();
+}
+
+// ProgressBar shows how far through a task of known size, such as a download, is, with its speed and, if the size is
+// known, the time left. It's drawn on stderr, and cleared when dropped.
+pub struct ProgressBar {
+    label: String,
+    started: Instant,
+    drawn: Cell<Option<Instant>>,
+}
+
+impl ProgressBar {
+    pub fn new(label: &str) -> Self {
+        ProgressBar { label: label.to_string(), started: Instant::now(), drawn: Cell::new(None) }
+    }
+
+    // update shows that done bytes of total have been received.
+    pub fn update(&self, done: usize, total: Option<usize>) {
+        let now = Instant::now();
+        let finished = total == Some(done);
+        if !shown() || self.drawn.get().is_some_and(|drawn| now - drawn < REDRAW_INTERVAL && !finished) {
+            return;
+        }
+        self.drawn.set(Some(now));
+        let speed = done as f64 / (now - self.started).as_secs_f64().max(0.001);
+        let text = match total.filter(|total| *total > 0) {
+            Some(total) => {
+                let fraction = (done as f64 / total as f64).min(1.0);
+                let filled = (fraction * BAR_WIDTH as f64) as usize;
+                let eta = Duration::from_secs_f64(total.saturating_sub(done) as f64 / speed.max(1.0));
+                format!(
+                    "{} [{}{}] {:>3}% {}/{} {}/s ETA {}",
+                    self.label,
+                    "=".repeat(filled),
+                    " ".repeat(BAR_WIDTH - filled),
+                    (fraction * 100.0) as usize,
+                    bytes(done),
+                    bytes(total),
+                    bytes(speed as usize),
+                    duration(eta),
+                )
+            },
+            None => format!("{} {} {}/s", self.label, bytes(done), bytes(speed as usize)),
+        };
+        draw(&text.muted().to_string());
+    }
+}
+
+impl Drop for ProgressBar {
+    fn drop(&mut self) {
+        if self.drawn.get().is_some() {
+            draw("");
+        }
+    }
+}
+
+// Spinner shows that a task of unknown length, such as parsing a feed, is underway, with a message saying what it's
+// doing and how long it has taken so far. It's drawn on stderr by a thread of its own, so that it keeps moving while
+// the task blocks, and cleared when dropped.
+pub struct Spinner {
+    message: Arc<Mutex<String>>,
+    done: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Spinner {
+    pub fn start(message: &str) -> Self {
+        let message = Arc::new(Mutex::new(message.to_string()));
+        let done = Arc::new(AtomicBool::new(false));
+        let thread = shown().then(|| {
+            let (message, done) = (Arc::clone(&message), Arc::clone(&done));
+            thread::spawn(move || {
+                let started = Instant::now();
+                for frame in SPINNER_FRAMES.iter().cycle() {
+                    if done.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let message = message.lock().expect("the message is never poisoned").clone();
+                    draw(&format!("{} {} ({:.1}s)", frame, message, started.elapsed().as_secs_f64()).muted().to_string());
+                    thread::park_timeout(REDRAW_INTERVAL);
+                }
+                draw("");
+            })
+        });
+        Spinner { message, done, thread }
+    }
+
+    pub fn set_message(&self, message: &str) {
+        *self.message.lock().expect("the message is never poisoned") = message.to_string();
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            // the thread is woken rather than left to finish waiting to draw the next frame.
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+// a spinner shows which table a ZipLoader is parsing.
+impl ZipLoaderEventHandler for &Spinner {
+    fn on_file_opened(&self, file_name: &str, _reader: &zip::read::ZipFile) {
+        self.set_message(&format!("Parsing {}", file_name));
+    }
+}
+
+// bytes gives a number of bytes in the largest unit of which there is at least one, e.g. 12.3 MB.
+pub(crate) fn bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if value < 1000.0 {
+            break;
+        }
+        value /= 1000.0;
+        unit = next;
+    }
+    match unit {
+        "B" => format!("{} B", bytes),
+        unit => format!("{:.1} {}", value, unit),
+    }
+}
+
+// duration gives a duration in minutes and seconds, e.g. 2m05s, in seconds if it's under a minute, or in hours,
+// minutes and seconds if it's an hour or more.
+pub(crate) fn duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{:02}m{:02}s", seconds / 3600, seconds / 60 % 60, seconds % 60),
+    }
+}
@@ -0,0 +1,212 @@
+use crate::commands::table::Table;
+use crate::gtfs::gtfs_rt::{self, alerts::{Alert, Alerts, InformedEntity}, overlay::RealtimeOverlay, trip_updates::TripUpdates};
+use crate::style::{Colorize, Paint};
+use std::sync::Arc;
+
+// Realtime is what was loaded of the realtime feeds given for a schedule: a summary of each, the predictions of
+// those which loaded, laid over the schedule, and their service alerts.
+#[derive(Debug, Default)]
+pub struct Realtime {
+    pub sources: Vec<RealtimeSource>,
+    // overlay is None if none of the feeds loaded.
+    pub overlay: Option<Arc<RealtimeOverlay>>,
+    pub alerts: Alerts,
+}
+
+// RealtimeSource is a realtime feed as it was loaded: where from, when it was created, if its header says, and how
+// many trip updates, vehicles and alerts it held, or why it couldn't be loaded.
+#[derive(Debug, Clone)]
+pub struct RealtimeSource {
+    pub source: String,
+    pub timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    pub trip_updates: usize,
+    pub vehicles: usize,
+    pub alerts: usize,
+    pub error: Option<String>,
+}
+
+impl Realtime {
+    // load fetches the realtime feeds at the URLs given, or reads them from files, combining their predictions into
+    // an overlay and gathering their alerts. Realtime data is a nicety next to the schedule, so feeds which fail to
+    // load are warned of and left out rather than stopping the tool; rt status lists them.
+    pub fn load<'a>(sources: impl IntoIterator<Item = &'a String>) -> Self {
+        let mut realtime = Realtime::default();
+        let mut trip_updates = Vec::new();
+        let mut alerts = Vec::new();
+        for source in sources {
+            let feed = match std::path::Path::new(source).is_file() {
+                true => std::fs::read(source)
+                    .map_err(|err| err.to_string())
+                    .and_then(|bytes| gtfs_rt::decode(&bytes).map_err(|err| err.to_string())),
+                false => gtfs_rt::fetch(source).map_err(|err| err.to_string()),
+            };
+            match feed {
+                Ok(feed) => {
+                    let feed_trip_updates = TripUpdates::from(&feed);
+                    let feed_alerts = Alerts::from(&feed);
+                    realtime.sources.push(RealtimeSource {
+                        source: source.clone(),
+                        timestamp: feed.header.timestamp.and_then(gtfs_rt::timestamp),
+                        trip_updates: feed_trip_updates.iter().count(),
+                        vehicles: feed_trip_updates.iter().filter(|trip_update| trip_update.vehicle.is_some()).count(),
+                        alerts: feed_alerts.alerts.len(),
+                        error: None,
+                    });
+                    trip_updates.push(feed_trip_updates);
+                    alerts.push(feed_alerts);
+                },
+                Err(err) => {
+                    log::warn!("Failed to load realtime feed {}: {}", source, err);
+                    realtime.sources.push(RealtimeSource {
+                        source: source.clone(),
+                        timestamp: None,
+                        trip_updates: 0,
+                        vehicles: 0,
+                        alerts: 0,
+                        error: Some(err),
+                    });
+                },
+            }
+        }
+        if !trip_updates.is_empty() {
+            realtime.overlay = Some(Arc::new(RealtimeOverlay::new(TripUpdates::merge(trip_updates))));
+        }
+        realtime.alerts = Alerts::merge(alerts);
+        realtime
+    }
+
+    // status lists the feeds, when each was created and how long ago, and what it held.
+    pub fn status(&self, now: chrono::DateTime<chrono::Utc>) -> Table {
+        let mut table = Table::new(&["source", "created", "age", "trip_updates", "vehicles", "alerts", "error"])
+            .with_style(0, |text| text.bold().to_string())
+            .with_style(6, |text| text.red().to_string());
+        for source in &self.sources {
+            table.push(vec![
+                source.source.clone(),
+                source.timestamp.map_or("".to_string(), local_time),
+                source.timestamp.map_or("".to_string(), |timestamp| age(now - timestamp)),
+                source.trip_updates.to_string(),
+                source.vehicles.to_string(),
+                source.alerts.to_string(),
+                source.error.clone().unwrap_or_default(),
+            ]);
+        }
+        table
+    }
+
+    // alerts lists the alerts in effect at the instant now, or every alert if all is set, most severe first, in the
+    // language of the user's locale where the feed gives it.
+    pub fn alerts(&self, now: chrono::DateTime<chrono::Utc>, all: bool) -> Table {
+        let mut table = Table::new(&["alert_id", "severity", "effect", "cause", "header", "affects", "active", "description", "url"])
+            .with_default_fields(&["alert_id", "severity", "effect", "cause", "header", "affects", "active"])
+            .with_style(0, |text| text.bold().to_string())
+            .with_style(1, severity_style)
+            .with_style(5, |text| text.muted().to_string())
+            .with_empty_message(if all { "No alerts" } else { "No alerts in effect" });
+        let mut alerts = self.alerts.iter().filter(|alert| all || alert.is_active_at(now)).collect::<Vec<_>>();
+        alerts.sort_by_key(|alert| std::cmp::Reverse(alert.severity_level));
+        let language = language();
+        for alert in alerts {
+            let text = |text: &Option<gtfs_rt::alerts::TranslatedText>| text.as_ref()
+                .and_then(|text| text.text(language.as_deref()))
+                .unwrap_or_default()
+                .to_string();
+            table.push(vec![
+                alert.id.clone(),
+                alert.severity_level.name().to_string(),
+                alert.effect.name().to_string(),
+                alert.cause.name().to_string(),
+                text(&alert.header_text),
+                alert.informed_entities.iter().map(informed).collect::<Vec<_>>().join(", "),
+                active(alert),
+                text(&alert.description_text),
+                text(&alert.url),
+            ]);
+        }
+        table
+    }
+}
+
+// delay_text shows a delay in seconds to the minute, e.g. +2 min or -1 min, or as on time if it's under a minute.
+pub fn delay_text(delay: i64) -> String {
+    match delay.abs() < 60 {
+        true => "on time".to_string(),
+        false => format!("{:+} min", (delay as f64 / 60.0).round() as i64),
+    }
+}
+
+// delay_style colors delays as delay_text shows them, and statuses, in a column of predictions: on time in green,
+// late in yellow, early in cyan, and what won't happen, canceled trips and skipped stops, in red.
+pub fn delay_style(text: &str) -> String {
+    if text.contains("canceled") || text.contains("skipped") {
+        text.red().to_string()
+    } else if text.contains('+') {
+        text.yellow().to_string()
+    } else if text.contains(" min") {
+        text.cyan().to_string()
+    } else if text.contains("on time") {
+        text.green().to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+fn severity_style(text: &str) -> String {
+    match text.trim_end() {
+        "severe" => text.red().bold().to_string(),
+        "warning" => text.yellow().to_string(),
+        "info" => text.muted().to_string(),
+        _ => text.to_string(),
+    }
+}
+
+// informed describes what an informed entity picks out, e.g. route Red at stop place-pktrm.
+fn informed(entity: &InformedEntity) -> String {
+    let parts = [
+        entity.agency_id.as_ref().map(|agency_id| format!("agency {}", agency_id)),
+        entity.route_type.map(|route_type| format!("route type {}", route_type)),
+        entity.route_id.as_ref().map(|route_id| format!("route {}", route_id)),
+        entity.direction_id.map(|direction_id| format!("direction {}", direction_id)),
+        entity.trip_id.as_ref().map(|trip_id| format!("trip {}", trip_id)),
+        entity.stop_id.as_ref().map(|stop_id| format!("stop {}", stop_id)),
+    ];
+    parts.into_iter().flatten().collect::<Vec<_>>().join(" at ")
+}
+
+// active describes when an alert is in effect, e.g. until 2025-01-31 18:00, or always if it gives no periods.
+fn active(alert: &Alert) -> String {
+    if alert.active_periods.is_empty() {
+        return "always".to_string();
+    }
+    alert.active_periods.iter()
+        .map(|period| match (period.start, period.end) {
+            (Some(start), Some(end)) => format!("{} to {}", local_time(start), local_time(end)),
+            (Some(start), None) => format!("from {}", local_time(start)),
+            (None, Some(end)) => format!("until {}", local_time(end)),
+            (None, None) => "always".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn local_time(instant: chrono::DateTime<chrono::Utc>) -> String {
+    instant.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+// age shows how long ago something happened, to the second under a minute, the minute under an hour, and else the hour.
+fn age(elapsed: chrono::Duration) -> String {
+    match elapsed.num_seconds() {
+        seconds if seconds < 60 => format!("{} s", seconds.max(0)),
+        seconds if seconds < 3600 => format!("{} min", seconds / 60),
+        seconds => format!("{} h", seconds / 3600),
+    }
+}
+
+// language is the language of the user's locale, from LC_ALL, LC_MESSAGES or LANG, e.g. en for en_US.UTF-8.
+fn language() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+        .filter_map(|variable| std::env::var(variable).ok())
+        .find(|locale| !locale.is_empty())
+        .and_then(|locale| locale.split(['_', '.', '@']).next().map(str::to_string))
+        .filter(|language| language != "C" && language != "POSIX")
+}
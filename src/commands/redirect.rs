@@ -0,0 +1,165 @@
+use std::fs;
+use std::io::{self, Seek};
+use std::process;
+
+// Redirect is where the output of a line run in the REPL goes in place of the terminal, as in a shell: a file,
+// written afresh with > or added to with >>, or the standard input of a shell command, with |.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Redirect {
+    Write(String),
+    Append(String),
+    Pipe(String),
+}
+
+#[derive(Debug)]
+pub enum RedirectError {
+    TargetRequired,
+    // OpenError names the file which couldn't be opened.
+    OpenError(String, io::Error),
+    // PipeError names the command which couldn't be run.
+    PipeError(String, io::Error),
+    StdoutError(io::Error),
+    Unsupported,
+}
+
+impl std::fmt::Display for RedirectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RedirectError::TargetRequired => write!(f, "Redirection requires a file after > or >>, or a command after |"),
+            RedirectError::OpenError(path, e) => write!(f, "Error opening {}: {}", path, e),
+            RedirectError::PipeError(command, e) => write!(f, "Error running {}: {}", command, e),
+            RedirectError::StdoutError(e) => write!(f, "Error redirecting output: {}", e),
+            RedirectError::Unsupported => write!(f, "Output can't be redirected on this platform"),
+        }
+    }
+}
+
+impl std::error::Error for RedirectError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RedirectError::OpenError(_, e) | RedirectError::PipeError(_, e) | RedirectError::StdoutError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// split separates a redirection at the end of a line from the line itself, e.g. stops.list > stops.txt or
+// stops.list | wc -l. Only a >, >> or | following a space and outside quotes begins one, so that patterns such as
+// grep "a|b" and grep a|b are left alone.
+pub fn split(line: &str) -> Result<(&str, Option<Redirect>), RedirectError> {
+    let mut quoted = false;
+    let mut previous = ' ';
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '>' | '|' if !quoted && previous == ' ' && i > 0 => {
+                let (command, rest) = (line[..i].trim_end(), &line[i..]);
+                let redirect = match rest.strip_prefix(">>") {
+                    Some(path) => Redirect::Append(unquote(path)),
+                    None if c == '>' => Redirect::Write(unquote(&rest[1..])),
+                    None => Redirect::Pipe(rest[1..].trim().to_string()),
+                };
+                return match redirect {
+                    Redirect::Write(target) | Redirect::Append(target) | Redirect::Pipe(target) if target.is_empty() => {
+                        Err(RedirectError::TargetRequired)
+                    },
+                    redirect => Ok((command, Some(redirect))),
+                };
+            },
+            _ => (),
+        }
+        previous = c;
+    }
+    Ok((line, None))
+}
+
+fn unquote(path: &str) -> String {
+    let path = path.trim();
+    path.strip_prefix('"').and_then(|path| path.strip_suffix('"')).unwrap_or(path).to_string()
+}
+
+// redirected runs run with its output sent where redirect says. A command's output is piped to it once run has
+// finished, from a temporary file, so that a command which stops reading early, such as head, can't break the REPL's
+// output.
+pub fn redirected<T>(redirect: &Redirect, run: impl FnOnce() -> T) -> Result<T, RedirectError> {
+    let open = |path: &str, options: &mut fs::OpenOptions| options.open(path)
+        .map_err(|e| RedirectError::OpenError(path.to_string(), e));
+    match redirect {
+        Redirect::Write(path) => with_stdout(&open(path, fs::OpenOptions::new().write(true).create(true).truncate(true))?, run),
+        Redirect::Append(path) => with_stdout(&open(path, fs::OpenOptions::new().append(true).create(true))?, run),
+        Redirect::Pipe(command) => {
+            let pipe_error = |e| RedirectError::PipeError(command.clone(), e);
+            let path = std::env::temp_dir().join(format!("rust-gtfs-{}-output", process::id()));
+            let mut file = open(&path.to_string_lossy(), fs::OpenOptions::new().read(true).write(true).create(true).truncate(true))?;
+            let _ = fs::remove_file(&path);
+            let result = with_stdout(&file, run)?;
+            file.rewind().map_err(pipe_error)?;
+            process::Command::new("sh")
+                .args(["-c", command])
+                .stdin(file)
+                .status()
+                .map_err(pipe_error)?;
+            Ok(result)
+        },
+    }
+}
+
+// with_stdout runs run with stdout writing to file, restoring it afterwards, and colors what's written as though
+// stdout had always been the file.
+#[cfg(unix)]
+fn with_stdout<T>(file: &fs::File, run: impl FnOnce() -> T) -> Result<T, RedirectError> {
+    use std::io::Write;
+    use std::os::fd::AsRawFd;
+    io::stdout().flush().map_err(RedirectError::StdoutError)?;
+    // SAFETY: dup and dup2 only copy file descriptors, which are checked, and stdout's is restored before returning.
+    let saved = unsafe { libc::dup(libc::STDOUT_FILENO) };
+    if saved < 0 {
+        return Err(RedirectError::StdoutError(io::Error::last_os_error()));
+    }
+    if unsafe { libc::dup2(file.as_raw_fd(), libc::STDOUT_FILENO) } < 0 {
+        let err = io::Error::last_os_error();
+        unsafe { libc::close(saved) };
+        return Err(RedirectError::StdoutError(err));
+    }
+    crate::style::reapply_color_choice();
+    let result = run();
+    let flushed = io::stdout().flush();
+    unsafe {
+        libc::dup2(saved, libc::STDOUT_FILENO);
+        libc::close(saved);
+    }
+    crate::style::reapply_color_choice();
+    flushed.map_err(RedirectError::StdoutError)?;
+    Ok(result)
+}
+
+#[cfg(not(unix))]
+fn with_stdout<T>(_file: &fs::File, _run: impl FnOnce() -> T) -> Result<T, RedirectError> {
+    Err(RedirectError::Unsupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redirections_are_split_from_the_end_of_a_line() {
+        assert_eq!(split("stops.list > stops.txt").unwrap(), ("stops.list", Some(Redirect::Write("stops.txt".to_string()))));
+        assert_eq!(split("stops.list >> stops.txt").unwrap(), ("stops.list", Some(Redirect::Append("stops.txt".to_string()))));
+        assert_eq!(split("stops.list | wc -l").unwrap(), ("stops.list", Some(Redirect::Pipe("wc -l".to_string()))));
+        assert_eq!(split("stops.list > \"my stops.txt\"").unwrap().1, Some(Redirect::Write("my stops.txt".to_string())));
+    }
+
+    #[test]
+    fn patterns_are_not_redirections() {
+        assert_eq!(split("stops.grep \"a|b\"").unwrap(), ("stops.grep \"a|b\"", None));
+        assert_eq!(split("stops.grep a|b").unwrap(), ("stops.grep a|b", None));
+        assert_eq!(split("stops.list name~\"> 1\"").unwrap(), ("stops.list name~\"> 1\"", None));
+    }
+
+    #[test]
+    fn redirections_require_a_target() {
+        assert!(matches!(split("stops.list >"), Err(RedirectError::TargetRequired)));
+        assert!(matches!(split("stops.list |  "), Err(RedirectError::TargetRequired)));
+    }
+}
@@ -0,0 +1,586 @@
+use crate::commands;
+use crate::commands::gtfs::{GTFSCommandInterpreterError, GtfsNode, NODE_COMMANDS};
+use crate::commands::CommandInterpreter;
+use crate::commands::config::{Config, ConfigError};
+use crate::commands::diff;
+use crate::commands::names;
+use crate::commands::output::{self, Output, OutputFormat, OutputOptions};
+use crate::commands::realtime::Realtime;
+use crate::commands::redirect;
+use crate::commands::table::Table;
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::loaders::multi_feed_loader::FeedSource;
+use crate::style::{self, Colorize, Paint};
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+// Repl holds the state of an interactive session: the feeds loaded, one of which is explored at a time, the node
+// commands are run on, which starts at the root of that feed and is changed with cd, up and root, and the format
+// their results are shown in, changed with set output, along with the aliases defined and the lines run. It is the line editor's helper, completing
+// commands on tab, including the IDs of the stops, routes and trips of the current node.
+pub struct Repl {
+    pub feeds: BTreeMap<String, Feed>,
+    // feed names the feed being explored.
+    pub feed: String,
+    pub node: GtfsNode,
+    pub format: OutputFormat,
+    // accessible runs every command on the wheelchair-accessible trips and stops of the node, unless it's given
+    // accessible=false; see OutputOptions::accessible.
+    pub accessible: bool,
+    // aliases maps the names of aliases, which may begin a line in place of a command, to the commands they stand for.
+    pub aliases: BTreeMap<String, String>,
+    // history holds the lines run, oldest first, including those of earlier sessions the line editor loaded.
+    pub history: Vec<String>,
+}
+
+// Feed is a schedule loaded in a session, along with where it was loaded from.
+pub struct Feed {
+    pub source: FeedSource,
+    pub schedule: Arc<GtfsSchedule>,
+    // realtime holds what was loaded of the feed's realtime feeds, if any were given.
+    pub realtime: Option<Arc<Realtime>>,
+}
+
+type ReplCommand = fn(&mut Repl, &str) -> Result<(), GTFSCommandInterpreterError>;
+
+// REPL_COMMANDS are the commands which move around the schedule or change settings, rather than printing anything
+// about it.
+const REPL_COMMANDS: &[commands::Command<ReplCommand>] = &[
+    commands::Command {
+        name: "cd",
+        args: "<path>",
+        description: "Make the node at path, e.g. routes.<route_id>, the one commands run on; cd .. goes up",
+        run: |repl, path| repl.cd(path),
+    },
+    commands::Command {
+        name: "up",
+        args: "",
+        description: "Go back to the node above this one",
+        run: |repl, _| repl.cd(".."),
+    },
+    commands::Command {
+        name: "root",
+        args: "",
+        description: "Go back to the whole schedule",
+        run: |repl, _| repl.cd(""),
+    },
+    commands::Command {
+        name: "feed",
+        args: "add <name> <url or path> | use <name> | diff <name> [stops|routes|trips|stop_times [<id>]] | list",
+        description: "Load another feed, switch to the root of a loaded one, compare this feed with one, or list those loaded",
+        run: |repl, args| repl.feed(args),
+    },
+    commands::Command {
+        name: "rt",
+        args: "status | alerts [all]",
+        description: "Show when the realtime feeds given with --realtime were created and what they hold, or the service alerts in effect, or all of them",
+        run: |repl, args| repl.rt(args),
+    },
+    commands::Command {
+        name: "reload",
+        args: "",
+        description: "Load the feed again from where it came from, staying at this node if it still exists",
+        run: |repl, _| repl.reload(),
+    },
+    commands::Command {
+        name: "alias",
+        args: "[<name> = <command>]",
+        description: "Name a command, e.g. alias rd = routes.Red.departures, saving it in the config file; alone, list the aliases",
+        run: |repl, args| repl.alias(args),
+    },
+    commands::Command {
+        name: "unalias",
+        args: "<name>",
+        description: "Remove an alias, and remove it from the config file",
+        run: |repl, name| repl.unalias(name),
+    },
+    commands::Command {
+        name: "history",
+        args: "[n]",
+        description: "List the lines run, in this session and earlier ones, or the last n; run one again with !<number>",
+        run: |repl, args| repl.list_history(args),
+    },
+    commands::Command {
+        name: "foreach",
+        args: "<listing> : <command>",
+        description: "Run a command for each row of a listing, e.g. foreach routes.list : {id}.info; {<field>} stands for any of its fields",
+        run: |repl, args| repl.foreach(args),
+    },
+    commands::Command {
+        name: "config",
+        args: "show | set <default_feed|output|color> <value> | path",
+        description: "Print the config file, change a setting in it, for this session too, or print where it's kept",
+        run: |repl, args| repl.config(args),
+    },
+    commands::Command {
+        name: "set",
+        args: "output <text|json|csv>",
+        description: "Show results as text, as JSON for scripts or as CSV for spreadsheets",
+        run: |repl, args| repl.set(args),
+    },
+];
+
+impl Repl {
+    // new starts a session exploring a single feed, named name.
+    pub fn new(name: &str, feed: Feed) -> Self {
+        let node = GtfsNode::root(Arc::clone(&feed.schedule)).with_realtime(feed.realtime.as_ref().and_then(|realtime| realtime.overlay.clone()));
+        Repl {
+            feeds: BTreeMap::from([(name.to_string(), feed)]),
+            feed: name.to_string(),
+            node,
+            format: OutputFormat::default(),
+            accessible: false,
+            aliases: BTreeMap::new(),
+            history: Vec::new(),
+        }
+    }
+
+    // prompt shows where in the schedule commands will run, e.g. gtfs/routes/Red >, beginning with the name of the
+    // feed once more than one is loaded, e.g. mbta:gtfs/routes/Red >, and marked if --accessible is given.
+    pub fn prompt(&self) -> String {
+        let accessible = if self.accessible { " (accessible)" } else { "" };
+        match self.feeds.len() {
+            1 => format!("{}{} > ", self.node.path(), accessible),
+            _ => format!("{}:{}{} > ", self.feed, self.node.path(), accessible),
+        }
+    }
+
+    // run runs a line typed at the prompt, either moving to another node or running a command on the current one,
+    // and adds it to the history. A line !<number> runs the line of that number in the history again.
+    // Options such as fields=, sort= and limit= may follow a command which lists things. IDs in the command's path
+    // may be given as patterns, e.g. routes.CR-*.list, to run it for every match; as text, the results for each are
+    // shown as they're run, beneath a heading, and otherwise together, labeled. How long commands take to run, not
+    // counting printing their results, is logged at debug level. As in a shell, what a line prints may be written to a
+    // file with > or >>, or piped to a command with |, e.g. stops.list | wc -l.
+    pub fn run(&mut self, line: &str) -> Result<(), GTFSCommandInterpreterError> {
+        let line = self.recall(line)?;
+        self.history.push(line.clone());
+        let line = self.expand(&line);
+        match redirect::split(&line).map_err(GTFSCommandInterpreterError::RedirectError)? {
+            (line, Some(redirect)) => redirect::redirected(&redirect, || self.execute(line))
+                .map_err(GTFSCommandInterpreterError::RedirectError)?,
+            (line, None) => self.execute(line),
+        }
+    }
+
+    // execute runs a line once any alias has been expanded and any redirection taken off.
+    fn execute(&mut self, line: &str) -> Result<(), GTFSCommandInterpreterError> {
+        if commands::is_help(line) {
+            let mut lines = commands::help_lines(NODE_COMMANDS, "");
+            lines.extend(commands::help_lines(REPL_COMMANDS, ""));
+            commands::print_help(&lines);
+            return Ok(());
+        }
+        match commands::find_command(REPL_COMMANDS, line) {
+            Some((repl_command, args)) => (repl_command.run)(self, args),
+            None => {
+                let (command, options) = OutputOptions::split(line).map_err(GTFSCommandInterpreterError::OutputError)?;
+                if !command[..names::path_end(command)].contains('*') {
+                    self.interpret(command, &options)?.print(self.format);
+                    return Ok(());
+                }
+                let matches = self.node.glob(command);
+                if matches.is_empty() {
+                    return Err(GTFSCommandInterpreterError::NoMatches(command.to_string()));
+                }
+                let mut sections = Vec::new();
+                for (label, command) in matches {
+                    if self.format == OutputFormat::Text {
+                        output::print_heading(&label);
+                        self.interpret(&command, &options)?.print(self.format);
+                    } else {
+                        sections.push((label, self.interpret(&command, &options)?));
+                    }
+                }
+                if !sections.is_empty() {
+                    Output::Sections(sections).print(self.format);
+                }
+                Ok(())
+            },
+        }
+    }
+
+    // interpret runs a command on the current node, or its accessible trips and stops, shaping its output with options.
+    fn interpret(&self, command: &str, options: &OutputOptions) -> Result<Output, GTFSCommandInterpreterError> {
+        let started = std::time::Instant::now();
+        let output = self.node_for(options).interpret(command)?
+            .shape(options)
+            .map_err(GTFSCommandInterpreterError::OutputError)?;
+        log::debug!("Ran {} in {:.2?}", command, started.elapsed());
+        Ok(output)
+    }
+
+    // node_for gives the node a command given options runs on: the current one, or the part of it accessible by
+    // wheelchair if accessible=true or --accessible says so.
+    fn node_for(&self, options: &OutputOptions) -> Cow<'_, GtfsNode> {
+        match options.accessible.unwrap_or(self.accessible) {
+            true => Cow::Owned(self.node.accessible()),
+            false => Cow::Borrowed(&self.node),
+        }
+    }
+
+    // recall gives the line of the history a line !<number> refers to, numbered from 1 as history lists them, echoing
+    // it so that it's clear what runs. Other lines are given as they are.
+    fn recall(&self, line: &str) -> Result<String, GTFSCommandInterpreterError> {
+        let Some(number) = line.strip_prefix('!') else {
+            return Ok(line.to_string());
+        };
+        let recalled = number.parse::<usize>().ok()
+            .and_then(|number| self.history.get(number.checked_sub(1)?))
+            .ok_or_else(|| GTFSCommandInterpreterError::NoHistoryEntry(number.to_string()))?;
+        log::info!("{}", recalled);
+        Ok(recalled.clone())
+    }
+
+    // list_history lists the lines run, numbered for !<number>, or only the last n of them if n is given.
+    fn list_history(&self, args: &str) -> Result<(), GTFSCommandInterpreterError> {
+        let count = match args {
+            "" => self.history.len(),
+            n => n.parse::<usize>().map_err(|_| GTFSCommandInterpreterError::InvalidHistoryArgument(n.to_string()))?,
+        };
+        let mut table = Table::new(&["number", "line"])
+            .with_style(0, |text| text.muted().to_string());
+        for (index, line) in self.history.iter().enumerate().skip(self.history.len().saturating_sub(count)) {
+            table.push(vec![(index + 1).to_string(), line.clone()]);
+        }
+        Output::Table(table).print(self.format);
+        Ok(())
+    }
+
+    // foreach runs a command for each row of a listing, given as <listing> : <command>, e.g.
+    // foreach routes.list : {id}.headways date=2024-06-01. In the command, {id} stands for the row's ID, its first field
+    // ending in _id, and {<field>} for any of its fields, including those the listing doesn't show. A command beginning
+    // with {id} runs on the stop, route or trip it names, as though preceded by stops., routes. or trips.; others run
+    // here. As text, each result is shown beneath a heading as it's run; otherwise they're shown together, by ID.
+    fn foreach(&self, args: &str) -> Result<(), GTFSCommandInterpreterError> {
+        let invalid = || GTFSCommandInterpreterError::InvalidForeach(args.to_string());
+        let (listing, template) = args.split_once(" : ").ok_or_else(invalid)?;
+        let template = template.trim();
+        if template.is_empty() {
+            return Err(invalid());
+        }
+        let (listing, mut options) = OutputOptions::split(listing.trim()).map_err(GTFSCommandInterpreterError::OutputError)?;
+        let not_a_listing = || GTFSCommandInterpreterError::NotAListing(listing.to_string());
+        let Output::Table(table) = self.node_for(&options).interpret(listing)? else {
+            return Err(not_a_listing());
+        };
+        // every field is kept unless others are chosen, so that the command may use those the listing doesn't show.
+        options.fields.get_or_insert_with(|| table.headers().to_vec());
+        let Output::Table(table) = Output::Table(table).shape(&options).map_err(GTFSCommandInterpreterError::OutputError)? else {
+            return Err(not_a_listing());
+        };
+
+        let headers = table.headers();
+        let id_column = headers.iter().position(|header| header.ends_with("_id")).unwrap_or(0);
+        let collection = match headers.get(id_column).map(String::as_str) {
+            Some("stop_id") if template.starts_with("{id}") => "stops.",
+            Some("route_id") if template.starts_with("{id}") => "routes.",
+            Some("trip_id") if template.starts_with("{id}") => "trips.",
+            _ => "",
+        };
+        let mut results = Vec::new();
+        for row in table.rows() {
+            let id = row.get(id_column).cloned().unwrap_or_default();
+            let mut command = collection.to_string();
+            let mut rest = template;
+            while let Some((before, after)) = rest.split_once('{') {
+                let (field, after) = after.split_once('}').ok_or_else(invalid)?;
+                let value = match field {
+                    "id" => Some(&id),
+                    field => headers.iter().position(|header| header == field).and_then(|column| row.get(column)),
+                };
+                let value = value.ok_or_else(|| GTFSCommandInterpreterError::UnknownPlaceholder(field.to_string(), headers.to_vec()))?;
+                command.push_str(before);
+                command.push_str(value);
+                rest = after;
+            }
+            command.push_str(rest);
+
+            let (command, options) = OutputOptions::split(&command).map_err(GTFSCommandInterpreterError::OutputError)?;
+            if self.format == OutputFormat::Text {
+                output::print_heading(&id);
+                self.interpret(command, &options)?.print(self.format);
+            } else {
+                results.push((id, self.interpret(command, &options)?));
+            }
+        }
+        if self.format != OutputFormat::Text {
+            Output::Results(results).print(self.format);
+        }
+        Ok(())
+    }
+
+    // history_path gives where the history is kept between sessions: in $XDG_DATA_HOME, or ~/.local/share if that
+    // isn't set.
+    pub fn history_path() -> Option<std::path::PathBuf> {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(std::path::PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".local").join("share")))
+            .map(|dir| dir.join("rust-gtfs").join("history"))
+    }
+
+    // expand replaces an alias at the start of line, ending at a space or a dot, with the command it stands for, so
+    // that with rd = routes.Red.departures, rd at=08:00 runs routes.Red.departures at=08:00. Aliases within aliases
+    // aren't expanded, so none can lead back to itself.
+    fn expand(&self, line: &str) -> String {
+        let end = line.find([' ', '.']).unwrap_or(line.len());
+        match self.aliases.get(&line[..end]) {
+            Some(command) => format!("{}{}", command, &line[end..]),
+            None => line.to_string(),
+        }
+    }
+
+    // alias defines an alias, given as <name> = <command>, and saves it in the config file, or lists the aliases if
+    // none is given. Names of commands can't be used, since the alias would hide them.
+    fn alias(&mut self, args: &str) -> Result<(), GTFSCommandInterpreterError> {
+        if args.is_empty() {
+            let mut table = Table::new(&["alias", "command"])
+                .with_style(0, |text| text.bold().to_string())
+                .with_empty_message("No aliases are defined; define one with alias <name> = <command>");
+            for (name, command) in &self.aliases {
+                table.push(vec![name.clone(), command.clone()]);
+            }
+            Output::Table(table).print(self.format);
+            return Ok(());
+        }
+        let invalid = || GTFSCommandInterpreterError::InvalidAlias(args.to_string());
+        let (name, command) = args.split_once('=').ok_or_else(invalid)?;
+        let (name, command) = (name.trim(), command.trim());
+        let is_command = REPL_COMMANDS.iter().map(|command| command.name)
+            .chain(NODE_COMMANDS.iter().map(|command| command.name))
+            .any(|command| command == name);
+        if name.is_empty() || command.is_empty() || is_command || commands::is_help(name)
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(invalid());
+        }
+        self.save_aliases(|aliases| {
+            aliases.insert(name.to_string(), command.to_string());
+        })
+    }
+
+    // unalias removes an alias, and removes it from the config file.
+    fn unalias(&mut self, name: &str) -> Result<(), GTFSCommandInterpreterError> {
+        if !self.aliases.contains_key(name) {
+            return Err(GTFSCommandInterpreterError::UnknownAlias(name.to_string()));
+        }
+        self.save_aliases(|aliases| {
+            aliases.remove(name);
+        })
+    }
+
+    // save_aliases changes the aliases of the config file as it is now, so as to keep any changes made to it since
+    // the session started, saves it, and takes up its aliases.
+    fn save_aliases(&mut self, change: impl FnOnce(&mut BTreeMap<String, String>)) -> Result<(), GTFSCommandInterpreterError> {
+        let mut config = Config::load().map_err(GTFSCommandInterpreterError::ConfigError)?;
+        change(&mut config.aliases);
+        config.save().map_err(GTFSCommandInterpreterError::ConfigError)?;
+        self.aliases = config.aliases;
+        Ok(())
+    }
+
+    // set changes a setting, given as its name and value. The only setting is output, the format of results.
+    fn set(&mut self, args: &str) -> Result<(), GTFSCommandInterpreterError> {
+        match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["output", format] => {
+                self.format = format.parse().map_err(|_| GTFSCommandInterpreterError::InvalidSetting(args.to_string()))?;
+                Ok(())
+            },
+            _ => Err(GTFSCommandInterpreterError::InvalidSetting(args.to_string())),
+        }
+    }
+
+    // config runs a config command: show prints the config file, set <key> <value> changes a setting in it, which is
+    // taken up by this session too, and path prints where it's kept.
+    fn config(&mut self, args: &str) -> Result<(), GTFSCommandInterpreterError> {
+        match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["show"] => {
+                let config = Config::load().map_err(GTFSCommandInterpreterError::ConfigError)?;
+                match config.to_toml().map_err(GTFSCommandInterpreterError::ConfigError)? {
+                    text if text.is_empty() => println!("{}", "Nothing is configured".muted()),
+                    text => print!("{}", text),
+                }
+                Ok(())
+            },
+            ["set", key, value] => {
+                let mut config = Config::load().map_err(GTFSCommandInterpreterError::ConfigError)?;
+                config.set(key, value).map_err(GTFSCommandInterpreterError::ConfigError)?;
+                config.save().map_err(GTFSCommandInterpreterError::ConfigError)?;
+                if let Some(format) = config.output.filter(|_| *key == "output") {
+                    self.format = format;
+                }
+                if let Some(color) = config.color.filter(|_| *key == "color") {
+                    style::set_color_choice(color);
+                }
+                Ok(())
+            },
+            ["path"] => {
+                let path = Config::path().ok_or(GTFSCommandInterpreterError::ConfigError(ConfigError::NoConfigDirectory))?;
+                println!("{}", path.display());
+                Ok(())
+            },
+            _ => Err(GTFSCommandInterpreterError::InvalidConfigCommand(args.to_string())),
+        }
+    }
+
+    // feed runs a feed command: add <name> <url or path> loads a feed, use <name> moves to the root of a loaded feed,
+    // diff <name> compares the whole feed being explored with a loaded feed, as diff does with one it loads, and list
+    // lists the feeds loaded.
+    fn feed(&mut self, args: &str) -> Result<(), GTFSCommandInterpreterError> {
+        match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["add", name, source] => {
+                let source = FeedSource::from(*source);
+                let schedule = source.load()
+                    .map_err(|e| GTFSCommandInterpreterError::FeedLoadError(source.to_string(), e))?;
+                self.feeds.insert(name.to_string(), Feed { source, schedule: Arc::new(schedule), realtime: None });
+                self.use_feed(name)
+            },
+            ["use", name] => self.use_feed(name),
+            ["diff", name, drill_down @ ..] => {
+                let drill_down = diff::drill_down(drill_down).map_err(GTFSCommandInterpreterError::InvalidDiffArguments)?;
+                let other = self.feeds.get(*name).ok_or_else(|| GTFSCommandInterpreterError::UnknownFeed(name.to_string()))?;
+                diff::print_diff(&self.feeds[&self.feed].schedule.diff(&other.schedule), drill_down);
+                Ok(())
+            },
+            ["list"] => {
+                let mut table = Table::new(&["feed", "source", "stops", "routes", "trips"])
+                    .with_style(0, |text| text.bold().to_string());
+                for (name, feed) in &self.feeds {
+                    let marker = if *name == self.feed { "*" } else { "" };
+                    table.push(vec![
+                        format!("{}{}", name, marker),
+                        feed.source.to_string(),
+                        feed.schedule.stops.stops.len().to_string(),
+                        feed.schedule.routes.routes.len().to_string(),
+                        feed.schedule.trips.trips.len().to_string(),
+                    ]);
+                }
+                Output::Table(table).print(self.format);
+                Ok(())
+            },
+            _ => Err(GTFSCommandInterpreterError::InvalidFeedCommand(args.to_string())),
+        }
+    }
+
+    // rt shows what the realtime feeds of the feed being explored hold.
+    fn rt(&mut self, args: &str) -> Result<(), GTFSCommandInterpreterError> {
+        let realtime = self.feeds[&self.feed].realtime.as_ref().ok_or(GTFSCommandInterpreterError::NoRealtimeFeed)?;
+        let now = chrono::Utc::now();
+        let table = match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["status"] => realtime.status(now),
+            ["alerts"] => realtime.alerts(now, false),
+            ["alerts", "all"] => realtime.alerts(now, true),
+            _ => return Err(GTFSCommandInterpreterError::InvalidRealtimeCommand(args.to_string())),
+        };
+        Output::Table(table).print(self.format);
+        Ok(())
+    }
+
+    // use_feed moves to the root of the loaded feed named name.
+    fn use_feed(&mut self, name: &str) -> Result<(), GTFSCommandInterpreterError> {
+        let feed = self.feeds.get(name).ok_or_else(|| GTFSCommandInterpreterError::UnknownFeed(name.to_string()))?;
+        self.node = GtfsNode::root(Arc::clone(&feed.schedule)).with_realtime(feed.realtime.as_ref().and_then(|realtime| realtime.overlay.clone()));
+        self.feed = name.to_string();
+        Ok(())
+    }
+
+    // reload loads the current feed again from its source, replacing the schedule of every node, and moves to the
+    // node at the same path in the new schedule. If the path no longer leads anywhere, e.g. because a route was
+    // removed, it stops at the last node along it which still exists.
+    fn reload(&mut self) -> Result<(), GTFSCommandInterpreterError> {
+        let feed = self.feeds.get_mut(&self.feed).expect("the feed being explored is loaded");
+        let schedule = feed.source.load()
+            .map_err(|e| GTFSCommandInterpreterError::FeedLoadError(feed.source.to_string(), e))?;
+        feed.schedule = Arc::new(schedule);
+
+        let mut path = Vec::new();
+        let mut node = &self.node;
+        while let Some(parent) = &node.parent {
+            path.push(node.node_id.as_str());
+            node = parent;
+        }
+        let mut node = GtfsNode::root(Arc::clone(&feed.schedule)).with_realtime(feed.realtime.as_ref().and_then(|realtime| realtime.overlay.clone()));
+        for node_id in path.into_iter().rev() {
+            match node.navigate(node_id) {
+                Ok(child) => node = child,
+                Err(_) => {
+                    println!("{} no longer exists; staying at {}", node_id, node.path());
+                    break;
+                },
+            }
+        }
+        self.node = node;
+        Ok(())
+    }
+
+    // cd moves to the node at path from the current one. As in a shell, .. is the node above, which at the root is
+    // the root itself, and the empty path is the root.
+    fn cd(&mut self, path: &str) -> Result<(), GTFSCommandInterpreterError> {
+        self.node = match path {
+            "" => {
+                let mut node = &self.node;
+                while let Some(parent) = &node.parent {
+                    node = parent;
+                }
+                node.clone()
+            },
+            ".." => match self.node.parent.take() {
+                Some(parent) => *parent,
+                None => self.node.clone(),
+            },
+            path => self.node.navigate(path)?,
+        };
+        Ok(())
+    }
+}
+
+impl Completer for Repl {
+    type Candidate = Pair;
+
+    // complete completes the command being typed, the path given to cd or the feed given to feed use or feed diff.
+    // Other arguments are paths, dates and times which the node knows nothing about, so aren't completed.
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let typed = &line[..pos];
+        let (start, completions) = match typed.strip_prefix("cd ") {
+            // only paths to nodes are offered to cd: those which can be followed by another part.
+            Some(path) if !path.contains(char::is_whitespace) => (
+                pos - path.len(),
+                self.node.complete(path).into_iter().filter(|completion| completion.ends_with('.')).collect(),
+            ),
+            _ if ["feed use ", "feed diff "].iter().any(|prefix| typed.strip_prefix(prefix).is_some_and(|name| !name.contains(' '))) => {
+                let name = typed.rsplit(' ').next().unwrap_or("");
+                (pos - name.len(), self.feeds.keys().filter(|feed| feed.starts_with(name)).cloned().collect())
+            },
+            _ if typed.contains(char::is_whitespace) => return Ok((pos, Vec::new())),
+            _ => {
+                let mut completions = commands::complete_commands(REPL_COMMANDS, typed);
+                completions.extend(self.node.complete(typed));
+                completions.extend(self.aliases.keys().filter(|name| name.starts_with(typed)).cloned());
+                (0, completions)
+            },
+        };
+        let candidates = completions.into_iter()
+            .map(|completion: String| Pair {
+                // candidates are listed by their last part, since the rest is what has already been typed.
+                display: completion.trim_end_matches('.').rsplit('.').next().unwrap_or("").to_string(),
+                replacement: completion,
+            })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for Repl {
+    type Hint = String;
+}
+
+impl Highlighter for Repl {}
+
+impl Validator for Repl {}
+
+impl Helper for Repl {}
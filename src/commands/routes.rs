@@ -1,13 +1,26 @@
+use crate::commands;
 use crate::commands::gtfs::GtfsNode;
 use crate::commands::CommandInterpreter;
+use crate::commands::fares;
+use crate::commands::filter::{self, Filter, FilterError};
+use crate::commands::names::{self, NameError, Segment};
+use crate::commands::open::{self, OpenError};
 use crate::commands::gtfs::GTFSCommandInterpreterError;
-use crate::gtfs::GtfsSchedule;
-use crate::gtfs::routes::Routes;
-use crate::gtfs::trips::Trips;
-use crate::gtfs::stops::Stops;
-use crate::gtfs::stop_times::StopTimes;
-use colored::Colorize;
-use std::collections::HashMap;
+use crate::commands::output::Output;
+use crate::commands::progress;
+use crate::commands::realtime;
+use crate::commands::window::{self, TimeWindow, WindowError};
+use crate::commands::table::{SortKey, Table};
+use crate::gtfs::analysis;
+use crate::gtfs::analysis::variants::ServiceVariant;
+use crate::gtfs::headways::Headways;
+use crate::gtfs::routes::{Route, RouteContinuityPolicy, RouteType, ROUTE_FIELDS};
+use crate::gtfs::stop_times::GtfsTime;
+use crate::gtfs::timetable::Timetable;
+use crate::gtfs::trips::Direction;
+use crate::gtfs::view::ScheduleFilter;
+use crate::style::{Colorize, Paint};
+use std::collections::{HashMap, HashSet};
 
 pub struct RoutesCommandInterpreter<'a>(pub &'a GtfsNode);
 
@@ -17,6 +30,17 @@ pub enum RoutesCommandError {
     ErrorGettingRoute(String),
     ErrorExecutingCommandForRoute(String, Box<GTFSCommandInterpreterError>),
     NoSuchRoute(String),
+    InvalidDirection(String),
+    InvalidTimetableArgument(String),
+    InvalidHeadwaysArgument(String),
+    InvalidWindow(WindowError),
+    InvalidListArgument(String),
+    InvalidDate(String),
+    TimetableExportError(String, csv::Error),
+    NoRealtimeFeed,
+    InvalidFilter(FilterError),
+    OpenError(OpenError),
+    NameError(NameError),
 }
 
 impl std::fmt::Display for RoutesCommandError {
@@ -26,110 +50,605 @@ impl std::fmt::Display for RoutesCommandError {
             RoutesCommandError::ErrorGettingRoute(route_id) => write!(f, "Error getting route: {}", route_id),
             RoutesCommandError::ErrorExecutingCommandForRoute(route_id, cause) => write!(f, "Error executing command for route {}: {}", route_id, **cause),
             RoutesCommandError::NoSuchRoute(route_id) => write!(f, "No such route: {}", route_id),
+            RoutesCommandError::InvalidDirection(direction) => write!(f, "Invalid direction: {} (expected 0 or 1)", direction),
+            RoutesCommandError::InvalidTimetableArgument(argument) => write!(
+                f,
+                "Invalid timetable argument: {} (expected a direction, 0 or 1, a date like 2025-01-31, either optionally after direction= or date=, from=07:00, to=09:30 or a path ending in .csv)",
+                argument
+            ),
+            RoutesCommandError::InvalidHeadwaysArgument(argument) => write!(
+                f,
+                "Invalid headways argument: {} (expected a direction, 0 or 1, a date like 2025-01-31, either optionally after direction= or date=, from=07:00 or to=09:30)",
+                argument
+            ),
+            RoutesCommandError::InvalidWindow(e) => write!(f, "{}", e),
+            RoutesCommandError::InvalidListArgument(argument) => write!(
+                f,
+                "Invalid list argument: {} (expected type= followed by route types, e.g. type=bus,ferry or type=rail_like)",
+                argument
+            ),
+            RoutesCommandError::InvalidDate(date) => write!(f, "Invalid date: {} (expected a date like date=2025-01-31)", date),
+            RoutesCommandError::TimetableExportError(path, e) => write!(f, "Error exporting timetable to {}: {}", path, e),
+            RoutesCommandError::NoRealtimeFeed => write!(f, "No realtime feed is loaded; give one with --realtime"),
+            RoutesCommandError::InvalidFilter(e) => write!(f, "{}", e),
+            RoutesCommandError::OpenError(e) => write!(f, "{}", e),
+            RoutesCommandError::NameError(e) => write!(f, "{}", e),
         }
     }
 }
 
-impl std::error::Error for RoutesCommandError {}
+impl std::error::Error for RoutesCommandError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RoutesCommandError::ErrorExecutingCommandForRoute(_, cause) => Some(cause.as_ref()),
+            RoutesCommandError::TimetableExportError(_, e) => Some(e),
+            RoutesCommandError::InvalidFilter(e) => Some(e),
+            RoutesCommandError::InvalidWindow(e) => Some(e),
+            RoutesCommandError::OpenError(e) => Some(e),
+            RoutesCommandError::NameError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl<'a> CommandInterpreter for RoutesCommandInterpreter<'a> {
-    type CommandResult = ();
+    type CommandResult = Output;
     type CommandError = RoutesCommandError;
 
     fn interpret(&self, command: &str) -> Result<Self::CommandResult, Self::CommandError> {
-        let (first, rest) = command.find(".").and_then(|i| command.split_at_checked(i)).unwrap_or((command, ""));
-        match first {
-            "list" => Ok(self.list()),
-            "info" => Ok(self.info()),
-            _ => match self.0.gtfs.routes.routes.get(first) {
-                None => Err(RoutesCommandError::InvalidCommand(command.to_string())),
-                Some(route) => self.route(route.route_id.as_str())
-                    .map_err(|e| RoutesCommandError::ErrorGettingRoute(e.to_string()))?
-                    .interpret(rest.chars().skip(1).collect::<String>().as_str())
-                    .map_err(|e| RoutesCommandError::ErrorExecutingCommandForRoute(route.route_id.clone(), Box::new(e)))
-            },
+        if let Some((routes_command, args)) = commands::find_command(ROUTES_COMMANDS, command) {
+            return (routes_command.run)(self, args);
         }
+        let (segment, rest) = names::split_segment(command);
+        let Some(route) = self.0.view().route(&self.route_id(segment)?) else {
+            return Err(RoutesCommandError::InvalidCommand(command.to_string()));
+        };
+        // commands about the route itself are run here; anything else is run on the route's node.
+        if let Some((route_command, args)) = commands::find_command(ROUTE_COMMANDS, rest) {
+            return (route_command.run)(self, route, args);
+        }
+        // help at a route lists the commands about the route before those of its node.
+        if commands::is_help(rest) {
+            commands::print_help(&commands::help_lines(ROUTE_COMMANDS, ""));
+        }
+        self.route(route.route_id.as_str())
+            .map_err(|e| RoutesCommandError::ErrorGettingRoute(e.to_string()))?
+            .interpret(rest)
+            .map_err(|e| RoutesCommandError::ErrorExecutingCommandForRoute(route.route_id.clone(), Box::new(e)))
+    }
+
+    fn complete(&self, partial: &str) -> Vec<String> {
+        let view = self.0.view();
+        let Some((route_id, rest)) = partial.split_once('.') else {
+            let mut completions = commands::complete_commands(ROUTES_COMMANDS, partial);
+            completions.extend(commands::complete_ids(view.routes().map(|route| route.route_id.as_str()), partial));
+            completions.sort();
+            return completions;
+        };
+        let Ok(node) = self.route(route_id) else {
+            return Vec::new();
+        };
+        let mut completions = commands::complete_commands(ROUTE_COMMANDS, rest);
+        completions.extend(node.complete(rest));
+        completions.sort();
+        completions.dedup();
+        completions.into_iter().map(|completion| format!("{}.{}", route_id, completion)).collect()
     }
 }
 
+type RoutesCommand = fn(&RoutesCommandInterpreter, &str) -> Result<Output, RoutesCommandError>;
+type RouteCommand = fn(&RoutesCommandInterpreter, &Route, &str) -> Result<Output, RoutesCommandError>;
+
+// ROUTES_COMMANDS are the commands about the routes as a whole.
+const ROUTES_COMMANDS: &[commands::Command<RoutesCommand>] = &[
+    commands::Command {
+        name: "help",
+        args: "",
+        description: "List the commands valid here (also ?)",
+        run: |_, _| {
+            commands::print_collection_help(ROUTES_COMMANDS, ROUTE_COMMANDS, "route", "<route_id>");
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "list",
+        args: "[type=<types>] [<conditions>] [fields=<fields>] [sort=<fields>] [limit=<n>] [offset=<n>]",
+        description: "List the routes, optionally of some types, e.g. type=bus,ferry or type=rail_like, meeting any conditions given, e.g. color=FFC72C",
+        run: |routes, args| routes.list(args).map(Output::Table),
+    },
+    commands::Command {
+        name: "info",
+        args: "",
+        description: "Count the routes",
+        run: |routes, _| Ok(routes.info()),
+    },
+    commands::Command {
+        name: "count",
+        args: "[type=<types>] [<conditions>]",
+        description: "Count the routes of any types and meeting any conditions given",
+        run: |routes, args| routes.list(args).map(|table| Output::Counts(vec![("Routes", table.rows().len())])),
+    },
+];
+
+// ROUTE_COMMANDS are the commands about a single route, given as <route_id>.<command>.
+const ROUTE_COMMANDS: &[commands::Command<RouteCommand>] = &[
+    // info adds the route's fields and service span to the counts the route node prints.
+    commands::Command {
+        name: "info",
+        args: "",
+        description: "Print every field of the route, count its stops and trips, and print today's service span",
+        run: |routes, route, _| {
+            routes.info_of(route);
+            println!("{}", routes.route(route.route_id.as_str())?.view());
+            routes.service_span(route.route_id.as_str());
+            Ok(Output::Printed)
+        },
+    },
+    // stops lists the route's own stops in order, which the route node's stops command can't, since it only sees a set of stops.
+    commands::Command {
+        name: "stops",
+        args: "[0|1]",
+        description: "List the route's stops in order, in each direction or the one given",
+        run: |routes, route, args| routes.stops(route.route_id.as_str(), args).map(|_| Output::Printed),
+    },
+    commands::Command {
+        name: "patterns",
+        args: "",
+        description: "List the route's journey patterns, most common first",
+        run: |routes, route, _| {
+            routes.patterns(route.route_id.as_str());
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "directions",
+        args: "",
+        description: "List the route's directions and their headsigns",
+        run: |routes, route, _| {
+            routes.directions(route);
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "profile",
+        args: "[date=<YYYY-MM-DD>]",
+        description: "Chart the route's trips per hour, today by default",
+        run: |routes, route, args| routes.profile(route.route_id.as_str(), args).map(|_| Output::Printed),
+    },
+    commands::Command {
+        name: "timetable",
+        args: "[direction=0|1] [date=<YYYY-MM-DD>] [from=<HH:MM>] [to=<HH:MM>] [path.csv]",
+        description: "Print the route's timetable of stops by trips, a page of trips at a time if it's wider than the terminal, or export it as CSV",
+        run: |routes, route, args| routes.timetable(route.route_id.as_str(), args).map(|_| Output::Printed),
+    },
+    commands::Command {
+        name: "headways",
+        args: "[direction=0|1] [date=<YYYY-MM-DD>] [from=<HH:MM>] [to=<HH:MM>]",
+        description: "Measure the intervals between the route's departures, today by default, in each direction or the one given",
+        run: |routes, route, args| routes.headways(route.route_id.as_str(), args).map(Output::Table),
+    },
+    commands::Command {
+        name: "vehicles",
+        args: "",
+        description: "List the vehicles the realtime feed has running the route, with their next stops and how late they're running",
+        run: |routes, route, _| routes.vehicles(route.route_id.as_str()).map(Output::Table),
+    },
+    commands::Command {
+        name: "fares",
+        args: "",
+        description: "List the fares charged for riding the route, cheapest first, with the zones or areas each applies between",
+        run: |routes, route, _| Ok(Output::Table(fares::route_fares(&routes.0.view(), &route.route_id))),
+    },
+    commands::Command {
+        name: "open",
+        args: "[--print-only]",
+        description: "Open the route's web page (route_url) in the browser, or print it",
+        run: |_, route, args| {
+            open::open(&format!("route {}", route.route_id), route.route_url.as_deref(), args).map_err(RoutesCommandError::OpenError)?;
+            Ok(Output::Printed)
+        },
+    },
+];
 
 impl RoutesCommandInterpreter<'_> {
-    fn list(&self) {
-        for (_, route) in &self.0.gtfs.routes.routes {
-            println!("{}: {}", route.route_id, match (route.route_long_name(), route.route_short_name()) {
-                (Some(long_name), Some(short_name)) => format!("{} ({})", long_name, short_name),
-                _ => route.name()
-            });
+    // list lists the routes with every field of routes.txt, optionally only those of certain types and meeting
+    // the conditions of a Filter. Types are given as type=bus,ferry, naming route types as RouteType's FromStr does,
+    // or the groups rail_like and road. Routes are given in the order of their route_sort_order, then of their IDs.
+    fn list(&self, args: &str) -> Result<Table, RoutesCommandError> {
+        let (types_args, conditions) = filter::split_args(args)
+            .map_err(RoutesCommandError::InvalidFilter)?
+            .into_iter()
+            .partition::<Vec<_>, _>(|arg| arg.starts_with("type="));
+        let filter = Filter::from_args(&conditions).map_err(RoutesCommandError::InvalidFilter)?;
+        let mut route_types = HashSet::new();
+        for arg in types_args {
+            let types = &arg["type=".len()..];
+            for route_type in types.split(',') {
+                match route_type {
+                    "rail_like" => route_types.extend(RouteType::RAIL_LIKE),
+                    "road" => route_types.extend(RouteType::ROAD),
+                    route_type => {
+                        route_types.insert(route_type.parse::<RouteType>().map_err(|_| RoutesCommandError::InvalidListArgument(arg.to_string()))?);
+                    },
+                }
+            }
+        }
+
+        let mut table = Table::new(&ROUTE_FIELDS).with_default_fields(&["route_id", "route_short_name", "route_long_name"]);
+        for route in self.0.view().routes().filter(|route| route_types.is_empty() || route_types.contains(&route.route_type)) {
+            table.push_record(&HashMap::from(route));
         }
+        table.sort(&[SortKey::from("route_sort_order"), SortKey::from("route_id")]).expect("both are fields of routes.txt");
+        filter.apply(table).map_err(RoutesCommandError::InvalidFilter)
     }
 
-    fn info(&self) {
-        println!("{}: {}", "Routes".truecolor(128, 128, 128).bold(), self.0.gtfs.routes.routes.len());
+    fn info(&self) -> Output {
+        Output::Counts(vec![("Routes", self.0.view().routes().count())])
     }
 
-    fn route(&self, route_id: &str) -> Result<GtfsNode, RoutesCommandError> {
-        let raw_route = self.0.gtfs.routes.routes.get(route_id)
-            .ok_or(RoutesCommandError::NoSuchRoute(route_id.to_string()))?;
+    // info_of prints every field of a route given in routes.txt, showing its colors as swatches.
+    fn info_of(&self, route: &Route) {
+        let agency = route.agency_id.as_ref().map(|agency_id| match self.0.gtfs.agencies.agencies.get(agency_id) {
+            Some(agency) => format!("{} {}", agency_id, agency.agency_name.muted()),
+            None => agency_id.clone(),
+        });
+        let continuity = |policy: &Option<RouteContinuityPolicy>| policy.as_ref().map(|policy| match policy {
+            RouteContinuityPolicy::Continuous => "anywhere along the route",
+            RouteContinuityPolicy::NotContinuous => "only at stops",
+            RouteContinuityPolicy::Prearrange => "by arrangement",
+            RouteContinuityPolicy::CoordinateWithDriver => "on request to the driver",
+        }.to_string());
+        commands::print_fields(&[
+            ("ID", Some(route.route_id.clone())),
+            ("Short name", route.route_short_name().map(|name| name.bold().to_string())),
+            ("Long name", route.route_long_name().map(|name| name.bold().to_string())),
+            ("Type", Some(route.route_type.name().to_string())),
+            ("Agency", agency),
+            ("Description", route.route_desc.clone()),
+            ("Color", route.route_color.map(|color| commands::swatch(color.r, color.g, color.b))),
+            ("Text color", route.route_text_color.map(|color| commands::swatch(color.r, color.g, color.b))),
+            ("Sort order", route.route_sort_order.map(|order| order.to_string())),
+            ("Continuous pickup", continuity(&route.continuous_pickup)),
+            ("Continuous drop-off", continuity(&route.continuous_drop_off)),
+            ("Network", route.network_id.clone()),
+            ("URL", route.route_url.clone()),
+        ]);
+    }
 
-        let routes = Routes{
-            routes: HashMap::from([(route_id.to_string(), raw_route.clone())])
+    // stops prints the stops a route visits, in order. The argument is an optional direction, 0 or 1;
+    // without one, the stops are printed for each direction the route's trips run in.
+    fn stops(&self, route_id: &str, direction: &str) -> Result<(), RoutesCommandError> {
+        let view = self.0.view();
+        let directions = match direction {
+            "" => {
+                let directions = [Direction::A, Direction::B].into_iter()
+                    .filter(|direction| view.trips_of_route(route_id).any(|trip| trip.direction_id == Some(*direction)))
+                    .map(Some)
+                    .collect::<Vec<_>>();
+                if directions.is_empty() { vec![None] } else { directions }
+            },
+            direction => vec![Some(direction.parse::<Direction>().map_err(|_| RoutesCommandError::InvalidDirection(direction.to_string()))?)],
         };
-        
-        let trips = (&self.0.gtfs.trips).into_iter()
-            .filter(
-                |trip|
-                trip.route_id == route_id
-            )
-            .map(
-                |trip|
-                (trip.trip_id.clone(), trip.clone())
-            )
-            .collect::<HashMap<_, _>>();
-
-        let stop_times_by_stop = self.0.gtfs.stop_times.iter()
-            .filter_map(
-                |stop_time|
-                stop_time.stop_id.as_ref()
-                    .and_then(
-                        |stop_id|
-                        trips.get(&stop_time.trip_id)
-                        .map(|_| (stop_id, stop_time))
-                    )
-            )
-            .fold(
-                HashMap::new(),
-                |mut acc, (stop_id, stop_time)| {
-                    acc.entry(stop_id).or_insert(Vec::new()).push(stop_time);
-                    acc
-                }
+
+        for direction in directions {
+            if let Some(direction) = direction {
+                println!("{}", format!("Direction {}", direction.code()).muted().bold());
+            }
+            for (position, stop) in view.stops_on_route(route_id, direction).into_iter().enumerate() {
+                println!("{:>3}. {}: {}", position + 1, stop.stop_id, stop.get_stop_name().unwrap_or("Unnamed Location"));
+            }
+        }
+        Ok(())
+    }
+
+    // timetable prints a route's timetable, or writes it as CSV. The arguments are an optional direction, date and
+    // path ending in .csv, in any order, the direction and date given either alone or as direction=1 and
+    // date=2025-01-31; by default, today's timetable is printed for each direction the route runs in. from= and to=
+    // keep only the trips which start within that window of the day.
+    fn timetable(&self, route_id: &str, args: &str) -> Result<(), RoutesCommandError> {
+        let (mut direction, mut date, mut window, mut path) = (None, chrono::Local::now().date_naive(), TimeWindow::default(), None);
+        for arg in args.split_whitespace() {
+            let invalid = || RoutesCommandError::InvalidTimetableArgument(arg.to_string());
+            if window.accept(arg).map_err(RoutesCommandError::InvalidWindow)? {
+                continue;
+            } else if let Some(value) = arg.strip_prefix("direction=") {
+                direction = Some(value.parse::<Direction>().map_err(|_| invalid())?);
+            } else if let Ok(arg_direction) = arg.parse::<Direction>() {
+                direction = Some(arg_direction);
+            } else if let Some(arg_date) = window::date_argument(arg) {
+                date = arg_date;
+            } else if arg.ends_with(".csv") {
+                path = Some(arg);
+            } else {
+                return Err(invalid());
+            }
+        }
+
+        let view = self.0.view();
+        let directions = match direction {
+            Some(direction) => vec![Some(direction)],
+            None => {
+                let directions = [Direction::A, Direction::B].into_iter()
+                    .filter(|direction| view.trips_of_route(route_id).any(|trip| trip.direction_id == Some(*direction)))
+                    .map(Some)
+                    .collect::<Vec<_>>();
+                if directions.is_empty() { vec![None] } else { directions }
+            },
+        };
+
+        for direction in directions {
+            let Some(timetable) = view.timetable(route_id, direction, date) else {
+                continue;
+            };
+            let timetable = match window.is_open() {
+                true => timetable,
+                false => timetable.departing_within(window.range()),
+            };
+            match path {
+                // a CSV file holds a single table, so only the first direction is exported unless one is given.
+                Some(path) => {
+                    return std::fs::File::create(path)
+                        .map_err(csv::Error::from)
+                        .and_then(|file| timetable.write_csv(file))
+                        .map_err(|e| RoutesCommandError::TimetableExportError(path.to_string(), e));
+                },
+                None => {
+                    if let Some(direction) = direction {
+                        println!("{}", format!("Direction {}", direction.code()).muted().bold());
+                    }
+                    if timetable.trip_ids.is_empty() {
+                        match (window.from, window.to) {
+                            (None, None) => println!("No trips on {}", date),
+                            (Some(from), None) => println!("No trips on {} after {}", date, from),
+                            (None, Some(to)) => println!("No trips on {} before {}", date, to),
+                            (Some(from), Some(to)) => println!("No trips on {} between {} and {}", date, from, to),
+                        }
+                    } else {
+                        print_timetable(&timetable);
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+
+    // vehicles lists the vehicles the realtime feed has running a route, by trip, with the stop each is due at next,
+    // when, at the time of day, and how late it's running there.
+    fn vehicles(&self, route_id: &str) -> Result<Table, RoutesCommandError> {
+        let overlay = self.0.realtime.as_ref().ok_or(RoutesCommandError::NoRealtimeFeed)?;
+        let view = self.0.view();
+        let mut table = Table::new(&["vehicle", "trip_id", "headsign", "next_stop", "due", "delay"])
+            .with_style(0, |text| text.bold().to_string())
+            .with_style(3, |text| text.muted().to_string())
+            .with_style(5, realtime::delay_style)
+            .with_empty_message("No vehicles running the route");
+        let vehicles = overlay.apply_to(view).vehicles(chrono::Utc::now());
+        for vehicle in vehicles.iter().filter(|vehicle| vehicle.route_id() == Some(route_id)) {
+            table.push(vec![
+                vehicle.vehicle.name().unwrap_or_default().to_string(),
+                vehicle.trip_id().to_string(),
+                vehicle.trip.and_then(|trip| trip.trip_headsign.clone()).unwrap_or_default(),
+                vehicle.next_stop_id.map_or("".to_string(), |stop_id| {
+                    view.stop(stop_id).and_then(|stop| stop.get_stop_name()).unwrap_or(stop_id).to_string()
+                }),
+                vehicle.next_time.map_or("".to_string(), |time| GtfsTime::from(time.time_of_day()).to_string()),
+                vehicle.delay.map_or("".to_string(), |delay| realtime::delay_text(delay.into())),
+            ]);
+        }
+        Ok(table)
+    }
+
+    // headways measures the intervals between a route's departures in each direction it runs in, or the one given,
+    // taking a direction and date as timetable does, and from= and to= to measure only a window of the day, e.g. the
+    // morning peak. Intervals are given in minutes and seconds, e.g. 7m30s.
+    fn headways(&self, route_id: &str, args: &str) -> Result<Table, RoutesCommandError> {
+        let (mut direction, mut date, mut window) = (None, chrono::Local::now().date_naive(), TimeWindow::default());
+        for arg in args.split_whitespace() {
+            let invalid = || RoutesCommandError::InvalidHeadwaysArgument(arg.to_string());
+            if window.accept(arg).map_err(RoutesCommandError::InvalidWindow)? {
+                continue;
+            }
+            if let Ok(arg_direction) = arg.strip_prefix("direction=").unwrap_or(arg).parse::<Direction>() {
+                direction = Some(arg_direction);
+            } else if let Some(arg_date) = window::date_argument(arg) {
+                date = arg_date;
+            } else {
+                return Err(invalid());
+            }
+        }
+
+        let view = self.0.view();
+        let mut table = Table::new(&["direction", "stop_id", "stop_name", "departures", "min", "mean", "max"])
+            .with_style(2, |text| text.muted().to_string())
+            .with_empty_message(&format!("Fewer than two departures on {} to measure", date));
+        // the mean is rarely a whole number of seconds, so it's rounded to the nearest.
+        let interval = |duration: std::time::Duration| progress::duration(std::time::Duration::from_secs_f64(duration.as_secs_f64().round()));
+        for Headways { direction_id, stop_id, departures, min, mean, max } in view.headways(route_id, direction, date, window.range()) {
+            table.push(vec![
+                direction_id.map_or("".to_string(), |direction| direction.code().to_string()),
+                stop_id.clone(),
+                view.stop(&stop_id).and_then(|stop| stop.get_stop_name()).unwrap_or_default().to_string(),
+                departures.to_string(),
+                interval(min),
+                interval(mean),
+                interval(max),
+            ]);
+        }
+        Ok(table)
+    }
+
+    // profile prints a sparkline of how many of a route's trips start in each hour of a date, given as date=2025-01-31
+    // and by default today, with the hours marked beneath it.
+    fn profile(&self, route_id: &str, args: &str) -> Result<(), RoutesCommandError> {
+        let date = match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [] => chrono::Local::now().date_naive(),
+            [arg] => window::date_argument(arg).ok_or_else(|| RoutesCommandError::InvalidDate(arg.to_string()))?,
+            _ => return Err(RoutesCommandError::InvalidDate(args.to_string())),
+        };
+        let trips_per_hour = analysis::frequency_profile(&self.0.view(), route_id, date);
+        // the peak is the earliest of the busiest hours.
+        let peak = trips_per_hour.iter()
+            .copied()
+            .enumerate()
+            .max_by_key(|(hour, trips)| (*trips, std::cmp::Reverse(*hour)))
+            .filter(|(_, trips)| *trips > 0);
+        let Some((peak_hour, peak)) = peak else {
+            println!("No trips on {}", date);
+            return Ok(());
+        };
+
+        println!("{}", format!("Trips per hour on {} (peak {} at {:02}:00)", date, peak, peak_hour).muted().bold());
+        println!("{}", sparkline(&trips_per_hour));
+        println!("{}", (0..trips_per_hour.len()).step_by(6).map(|hour| format!("{:<6}", hour)).collect::<String>().trim_end());
+        Ok(())
+    }
+
+    // service_span prints when a route runs today in each direction.
+    fn service_span(&self, route_id: &str) {
+        let today = chrono::Local::now().date_naive();
+        let spans = self.0.view().service_span(route_id, today);
+        if spans.is_empty() {
+            println!("{}: none", "Service today".muted().bold());
+        }
+        for span in spans {
+            println!(
+                "{}: {} – {} ({} {})",
+                match span.direction_id {
+                    Some(direction) => format!("Service today, direction {}", direction.code()),
+                    None => "Service today".to_string(),
+                }.muted().bold(),
+                span.first_departure,
+                span.last_arrival,
+                span.trips,
+                if span.trips == 1 { "trip" } else { "trips" },
+            );
+        }
+    }
+
+    // directions prints the directions and headsigns of a route's trips, with the number of trips sharing each.
+    fn directions(&self, route: &Route) {
+        for direction in route.directions(self.0.view().trips()) {
+            println!(
+                "{} {}{}",
+                format!("{} {}", direction.trips, if direction.trips == 1 { "trip" } else { "trips" }).bold(),
+                match direction.direction_id {
+                    Some(direction) => format!("(direction {}) ", direction.code()).muted().to_string(),
+                    None => "".to_string(),
+                },
+                direction.headsign.as_deref().unwrap_or("No headsign")
             );
-        
-        let stops = (&self.0.gtfs.stops).into_iter()
-            .filter_map(
-                |stop| stop_times_by_stop.get(&stop.stop_id).map(|_| (stop.stop_id.clone(), stop.clone()))
-            )
-            .collect::<HashMap<_, _>>();
-        
-        let stop_times = stop_times_by_stop.into_iter()
-            .map(|(stop_id, stop_times)| (stop_id.clone(), stop_times.into_iter().cloned().collect::<Vec<_>>()))
-            .collect::<HashMap<_, _>>();
-        
-        Ok(GtfsNode{
-            gtfs: GtfsSchedule{
-                routes,
-                trips: Trips{
-                    trips
+        }
+    }
+
+    // patterns prints the journey patterns of a route, with the number of trips following each, most common first.
+    fn patterns(&self, route_id: &str) {
+        let patterns = self.0.view().patterns_of_route(route_id);
+        for (pattern, trip_count) in &patterns {
+            let common = patterns.iter().find(|(common, _)| common.direction_id == pattern.direction_id).map(|(common, _)| *common);
+            println!(
+                "{} {}{}{}",
+                format!("{} {}", trip_count, if *trip_count == 1 { "trip" } else { "trips" }).bold(),
+                match pattern.direction_id {
+                    Some(direction) => format!("(direction {}) ", direction.code()).muted().to_string(),
+                    None => "".to_string(),
                 },
-                stops: Stops{
-                    stops
+                match common.map(|common| ServiceVariant::classify(common, pattern)).filter(|variant| *variant != ServiceVariant::Local) {
+                    Some(variant) => format!("[{}] ", variant.name()).yellow().to_string(),
+                    None => "".to_string(),
                 },
-                stop_times: StopTimes{
-                    stop_times
-                }
-            },
-            parent: Some(Box::new(self.0.clone())),
-            node_id: route_id.to_string(),
-            node_name: Some(raw_route.name())
+                pattern.stop_ids.join(" → ")
+            );
+        }
+    }
+
+    // navigate returns the node at path, given as <route_id> or <route_id>.<path from the route's node>, the route
+    // possibly being given by name, e.g. "Red Line".
+    pub fn navigate(&self, path: &str) -> Result<GtfsNode, RoutesCommandError> {
+        let (segment, rest) = names::split_segment(path);
+        let route_id = self.route_id(segment)?;
+        self.route(&route_id)?
+            .navigate(rest)
+            .map_err(|e| RoutesCommandError::ErrorExecutingCommandForRoute(route_id, Box::new(e)))
+    }
+
+    // route_id gives the ID of the route a segment of a path picks out, by its short or long name if it's quoted.
+    fn route_id(&self, segment: Segment) -> Result<String, RoutesCommandError> {
+        let name = match segment {
+            Segment::Id(route_id) => return Ok(route_id.to_string()),
+            Segment::Name(name) => name,
+        };
+        let view = self.0.view();
+        let candidates = view.routes()
+            .flat_map(|route| [route.route_short_name(), route.route_long_name()].into_iter()
+                .flatten()
+                .map(|route_name| (route.route_id.as_str(), route_name)));
+        names::one("route", name, names::matching(name, candidates)).map_err(RoutesCommandError::NameError)
+    }
+
+    // route creates a node for the given route, along with its trips and the stops they visit.
+    // The node shares this node's schedule rather than copying it.
+    fn route(&self, route_id: &str) -> Result<GtfsNode, RoutesCommandError> {
+        let view = self.0.view();
+        let raw_route = view.route(route_id)
+            .ok_or(RoutesCommandError::NoSuchRoute(route_id.to_string()))?;
+
+        let trip_ids = view.trips_of_route(route_id)
+            .map(|trip| trip.trip_id.clone())
+            .collect::<HashSet<_>>();
+
+        let stop_ids = trip_ids.iter()
+            .flat_map(|trip_id| view.stop_times_of_trip(trip_id))
+            .filter_map(|stop_time| stop_time.stop_id.clone())
+            .collect::<HashSet<_>>();
+
+        Ok(self.0.child(
+            &format!("routes.{}", route_id),
+            Some(raw_route.name()),
+            ScheduleFilter {
+                stop_ids,
+                route_ids: HashSet::from([route_id.to_string()]),
+                trip_ids,
+            }
+        ))
+    }
+}
+// sparkline draws counts as a row of bars of eight heights, scaled so that the largest count fills a bar.
+// A count of zero is left blank, so that hours without service stand out from quiet ones.
+fn sparkline(counts: &[usize]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = counts.iter().copied().max().unwrap_or(0).max(1);
+    counts.iter()
+        .map(|count| match count {
+            0 => ' ',
+            count => BARS[((count * BARS.len()).div_ceil(max) - 1).min(BARS.len() - 1)],
         })
+        .collect()
+}
+
+// print_timetable prints a timetable, a page of as many trips as fit across the terminal at a time if it's too wide
+// to print whole, each page headed by which trips it holds.
+fn print_timetable(timetable: &Timetable) {
+    // each trip takes a column of a time, HH:MM, after two spaces.
+    const COLUMN_WIDTH: usize = 7;
+    let label_width = timetable.rows.iter()
+        .map(|row| row.stop_name.as_ref().unwrap_or(&row.stop_id).chars().count())
+        .max()
+        .unwrap_or(0);
+    let trips = timetable.trip_ids.len();
+    let per_page = terminal_size::terminal_size()
+        .map_or(trips, |(terminal_size::Width(width), _)| (width as usize).saturating_sub(label_width) / COLUMN_WIDTH)
+        .max(1);
+    if per_page >= trips {
+        print!("{}", timetable);
+        return;
     }
-}
\ No newline at end of file
+    for start in (0..trips).step_by(per_page) {
+        let end = (start + per_page).min(trips);
+        let heading = match end - start {
+            1 => format!("Trip {} of {}", end, trips),
+            _ => format!("Trips {}–{} of {}", start + 1, end, trips),
+        };
+        println!("{}", heading.muted());
+        print!("{}", timetable.columns(start..end));
+    }
+}
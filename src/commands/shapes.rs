@@ -0,0 +1,278 @@
+use crate::commands;
+use crate::commands::gtfs::GtfsNode;
+use crate::commands::CommandInterpreter;
+use crate::commands::filter::{Filter, FilterError};
+use crate::commands::gtfs::GTFSCommandInterpreterError;
+use crate::commands::output::Output;
+use crate::commands::table::{SortKey, Table};
+use crate::gtfs::view::ScheduleFilter;
+use crate::gtfs::writers::json_writer;
+use crate::style::Colorize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+pub struct ShapesCommandInterpreter<'a>(pub &'a GtfsNode);
+
+#[derive(Debug)]
+pub enum ShapesCommandError {
+    InvalidCommand(String),
+    NoSuchShape(String),
+    ErrorExecutingCommandForShape(String, Box<GTFSCommandInterpreterError>),
+    InvalidFilter(FilterError),
+    InvalidExportArguments(String),
+    ExportWriteError(String, std::io::Error),
+}
+
+impl std::fmt::Display for ShapesCommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShapesCommandError::InvalidCommand(command) => write!(f, "Invalid command: {}", command),
+            ShapesCommandError::NoSuchShape(shape_id) => write!(f, "No such shape: {}", shape_id),
+            ShapesCommandError::ErrorExecutingCommandForShape(shape_id, cause) => write!(f, "Error executing command for shape {}: {}", shape_id, **cause),
+            ShapesCommandError::InvalidFilter(e) => write!(f, "{}", e),
+            ShapesCommandError::InvalidExportArguments(args) => write!(
+                f, "Invalid export arguments '{}': expected geojson and a path to write to", args
+            ),
+            ShapesCommandError::ExportWriteError(path, e) => write!(f, "Error writing {}: {}", path, e),
+        }
+    }
+}
+
+impl std::error::Error for ShapesCommandError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ShapesCommandError::ErrorExecutingCommandForShape(_, cause) => Some(cause.as_ref()),
+            ShapesCommandError::InvalidFilter(e) => Some(e),
+            ShapesCommandError::ExportWriteError(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> CommandInterpreter for ShapesCommandInterpreter<'a> {
+    type CommandResult = Output;
+    type CommandError = ShapesCommandError;
+
+    fn interpret(&self, command: &str) -> Result<Self::CommandResult, Self::CommandError> {
+        if let Some((shapes_command, args)) = commands::find_command(SHAPES_COMMANDS, command) {
+            return (shapes_command.run)(self, args);
+        }
+        let (shape_id, rest) = command.split_once('.').unwrap_or((command, ""));
+        if !self.0.gtfs.shapes.shapes.contains_key(shape_id) {
+            return Err(ShapesCommandError::InvalidCommand(command.to_string()));
+        }
+        // commands about the shape itself are run here; anything else is run on the shape's node.
+        if let Some((shape_command, args)) = commands::find_command(SHAPE_COMMANDS, rest) {
+            return (shape_command.run)(self, shape_id, args);
+        }
+        // help at a shape lists the commands about the shape before those of its node.
+        if commands::is_help(rest) {
+            commands::print_help(&commands::help_lines(SHAPE_COMMANDS, ""));
+        }
+        self.shape(shape_id)?
+            .interpret(rest)
+            .map_err(|e| ShapesCommandError::ErrorExecutingCommandForShape(shape_id.to_string(), Box::new(e)))
+    }
+
+    fn complete(&self, partial: &str) -> Vec<String> {
+        let Some((shape_id, rest)) = partial.split_once('.') else {
+            let mut completions = commands::complete_commands(SHAPES_COMMANDS, partial);
+            completions.extend(commands::complete_ids(self.shape_ids().into_iter(), partial));
+            completions.sort();
+            return completions;
+        };
+        let Ok(node) = self.shape(shape_id) else {
+            return Vec::new();
+        };
+        let mut completions = commands::complete_commands(SHAPE_COMMANDS, rest);
+        completions.extend(node.complete(rest));
+        completions.sort();
+        completions.dedup();
+        completions.into_iter().map(|completion| format!("{}.{}", shape_id, completion)).collect()
+    }
+}
+
+type ShapesCommand = fn(&ShapesCommandInterpreter, &str) -> Result<Output, ShapesCommandError>;
+type ShapeCommand = fn(&ShapesCommandInterpreter, &str, &str) -> Result<Output, ShapesCommandError>;
+
+// SHAPES_COMMANDS are the commands about the shapes as a whole.
+const SHAPES_COMMANDS: &[commands::Command<ShapesCommand>] = &[
+    commands::Command {
+        name: "help",
+        args: "",
+        description: "List the commands valid here (also ?)",
+        run: |_, _| {
+            let mut lines = commands::help_lines(SHAPES_COMMANDS, "");
+            lines.extend(commands::help_lines(SHAPE_COMMANDS, "<shape_id>."));
+            lines.push(("<shape_id>.<command>".to_string(), "Run a command on the shape's node; see <shape_id>.help"));
+            commands::print_help(&lines);
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "list",
+        args: "[<conditions>] [fields=<fields>] [sort=<fields>] [limit=<n>] [offset=<n>]",
+        description: "List the shapes with their points, lengths and the trips following them, meeting any conditions given, e.g. points>100",
+        run: |shapes, args| shapes.list(args).map(Output::Table),
+    },
+    commands::Command {
+        name: "info",
+        args: "",
+        description: "Count the shapes",
+        run: |shapes, _| Ok(Output::Counts(vec![("Shapes", shapes.shape_ids().len())])),
+    },
+    commands::Command {
+        name: "count",
+        args: "[<conditions>]",
+        description: "Count the shapes meeting any conditions given, e.g. points>100",
+        run: |shapes, args| shapes.list(args).map(|table| Output::Counts(vec![("Shapes", table.rows().len())])),
+    },
+];
+
+// SHAPE_COMMANDS are the commands about a single shape, given as <shape_id>.<command>.
+const SHAPE_COMMANDS: &[commands::Command<ShapeCommand>] = &[
+    // info prints the shape's points and length before the counts the shape node prints.
+    commands::Command {
+        name: "info",
+        args: "",
+        description: "Print the shape's number of points, its length and its extent, and count the trips following it",
+        run: |shapes, shape_id, _| {
+            shapes.info_of(shape_id);
+            println!("{}", shapes.shape(shape_id)?.view());
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "export",
+        args: "geojson <path>",
+        description: "Write the shape to a file as a GeoJSON LineString",
+        run: |shapes, shape_id, args| {
+            shapes.export(shape_id, args)?;
+            Ok(Output::Printed)
+        },
+    },
+];
+
+impl ShapesCommandInterpreter<'_> {
+    // shape_ids gives the IDs of the shapes of the feed, in order. Below the root, only those followed by a trip of
+    // the node are given, so that e.g. routes.Red.shapes.list gives the shapes of the Red Line.
+    fn shape_ids(&self) -> Vec<&str> {
+        let shapes = &self.0.gtfs.shapes.shapes;
+        let shape_ids = match self.0.filter {
+            None => shapes.keys().map(String::as_str).collect::<BTreeSet<_>>(),
+            Some(_) => self.0.view().trips()
+                .filter_map(|trip| trip.shape_id.as_deref())
+                .filter(|shape_id| shapes.contains_key(*shape_id))
+                .collect(),
+        };
+        shape_ids.into_iter().collect()
+    }
+
+    // trips_of_shape gives the IDs of the trips here which follow a shape, in order.
+    fn trips_of_shape(&self, shape_id: &str) -> BTreeSet<&str> {
+        self.0.view().trips()
+            .filter(|trip| trip.shape_id.as_deref() == Some(shape_id))
+            .map(|trip| trip.trip_id.as_str())
+            .collect()
+    }
+
+    // list lists the shapes meeting the conditions of a Filter given as args, in order of ID, with the number of
+    // points of each, its length in meters measured along its points, and the trips and routes here following it.
+    fn list(&self, args: &str) -> Result<Table, ShapesCommandError> {
+        let filter = Filter::parse(args).map_err(ShapesCommandError::InvalidFilter)?;
+        let view = self.0.view();
+        let mut table = Table::new(&["shape_id", "points", "meters", "trips", "routes"])
+            .with_empty_message("No shapes here; the feed may have no shapes.txt");
+        for shape_id in self.shape_ids() {
+            let trip_ids = self.trips_of_shape(shape_id);
+            let route_ids = trip_ids.iter()
+                .filter_map(|trip_id| view.trip(trip_id))
+                .map(|trip| trip.route_id.as_str())
+                .collect::<BTreeSet<_>>();
+            table.push_record(&HashMap::from([
+                ("shape_id".to_string(), shape_id.to_string()),
+                ("points".to_string(), self.0.gtfs.shapes.shapes[shape_id].len().to_string()),
+                ("meters".to_string(), self.0.gtfs.shape_polyline(shape_id).map_or(String::new(), |polyline| format!("{:.0}", polyline.length()))),
+                ("trips".to_string(), trip_ids.len().to_string()),
+                ("routes".to_string(), route_ids.into_iter().collect::<Vec<_>>().join(" ")),
+            ]));
+        }
+        table.sort(&[SortKey::from("shape_id")]).expect("shape_id is a column of the table");
+        filter.apply(table).map_err(ShapesCommandError::InvalidFilter)
+    }
+
+    // info_of prints a shape's number of points, its length measured along them and as shapes.txt gives it in
+    // shape_dist_traveled, if it does, and the corners of the box bounding it.
+    fn info_of(&self, shape_id: &str) {
+        let points = &self.0.gtfs.shapes.shapes[shape_id];
+        let length = self.0.gtfs.shape_polyline(shape_id).map(|polyline| polyline.length());
+        let dist_traveled = points.iter().filter_map(|point| point.shape_dist_traveled).reduce(f64::max);
+        let bounds = points.iter().fold(None, |bounds: Option<((f64, f64), (f64, f64))>, point| {
+            let (lat, lon) = (point.shape_pt_lat, point.shape_pt_lon);
+            Some(match bounds {
+                None => ((lat, lon), (lat, lon)),
+                Some(((min_lat, min_lon), (max_lat, max_lon))) => ((min_lat.min(lat), min_lon.min(lon)), (max_lat.max(lat), max_lon.max(lon))),
+            })
+        });
+        commands::print_fields(&[
+            ("ID", Some(shape_id.bold().to_string())),
+            ("Points", Some(points.len().to_string())),
+            ("Length", length.map(|length| format!("{:.0} m", length))),
+            ("Distance traveled", dist_traveled.map(|distance| distance.to_string())),
+            ("Bounds", bounds.map(|((min_lat, min_lon), (max_lat, max_lon))| format!("{:.6},{:.6} to {:.6},{:.6}", min_lat, min_lon, max_lat, max_lon))),
+        ]);
+    }
+
+    // export writes a shape to the path given after its format, which must be geojson, as a FeatureCollection
+    // holding its LineString.
+    fn export(&self, shape_id: &str, args: &str) -> Result<(), ShapesCommandError> {
+        let invalid = || ShapesCommandError::InvalidExportArguments(args.to_string());
+        let (format, path) = args.trim().split_once(char::is_whitespace).ok_or_else(invalid)?;
+        let path = path.trim();
+        let path = path.strip_prefix('"').and_then(|path| path.strip_suffix('"')).unwrap_or(path);
+        if format != "geojson" || path.is_empty() {
+            return Err(invalid());
+        }
+        let json = json_writer::shape_to_geojson(&self.0.gtfs, shape_id)
+            .ok_or_else(|| ShapesCommandError::NoSuchShape(shape_id.to_string()))?;
+        std::fs::write(path, format!("{:#}\n", json))
+            .map_err(|e| ShapesCommandError::ExportWriteError(path.to_string(), e))?;
+        println!("Wrote shape {} to {}", shape_id, path);
+        Ok(())
+    }
+
+    // navigate returns the node at path, given as <shape_id> or <shape_id>.<path from the shape's node>.
+    pub fn navigate(&self, path: &str) -> Result<GtfsNode, ShapesCommandError> {
+        let (shape_id, rest) = path.split_once('.').unwrap_or((path, ""));
+        self.shape(shape_id)?
+            .navigate(rest)
+            .map_err(|e| ShapesCommandError::ErrorExecutingCommandForShape(shape_id.to_string(), Box::new(e)))
+    }
+
+    // shape creates a node for the given shape, along with the trips here following it, their routes and the stops
+    // they visit. The node shares this node's schedule rather than copying it.
+    fn shape(&self, shape_id: &str) -> Result<GtfsNode, ShapesCommandError> {
+        if !self.0.gtfs.shapes.shapes.contains_key(shape_id) {
+            return Err(ShapesCommandError::NoSuchShape(shape_id.to_string()));
+        }
+        let view = self.0.view();
+
+        let trips = view.trips()
+            .filter(|trip| trip.shape_id.as_deref() == Some(shape_id))
+            .collect::<Vec<_>>();
+
+        let stop_ids = trips.iter()
+            .flat_map(|trip| view.stop_times_of_trip(&trip.trip_id))
+            .filter_map(|stop_time| stop_time.stop_id.clone())
+            .collect::<HashSet<_>>();
+
+        Ok(self.0.child(
+            &format!("shapes.{}", shape_id),
+            None,
+            ScheduleFilter {
+                stop_ids,
+                route_ids: trips.iter().map(|trip| trip.route_id.clone()).collect(),
+                trip_ids: trips.iter().map(|trip| trip.trip_id.clone()).collect(),
+            }
+        ))
+    }
+}
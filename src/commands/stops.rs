@@ -1,19 +1,36 @@
-use crate::gtfs::stop_times::StopTimes;
-use crate::{commands::gtfs::GtfsNode, gtfs::GtfsSchedule};
+use crate::commands;
+use crate::commands::gtfs::GtfsNode;
 use crate::commands::CommandInterpreter;
-use colored::Colorize;
+use crate::style::{Colorize, Paint};
+use crate::commands::filter::{Filter, FilterError};
+use crate::commands::names::{self, NameError, Segment};
+use crate::commands::open::{self, OpenError};
 use crate::commands::gtfs::GTFSCommandInterpreterError;
-use std::collections::{self, HashMap, HashSet};
-use crate::gtfs::stops::{Stops, Stop};
-use crate::gtfs::routes::Routes;
-use crate::gtfs::trips::Trips;
-pub struct StopsCommandInterpreter<'a>(pub &'a GtfsSchedule);
+use crate::commands::output::Output;
+use crate::commands::realtime;
+use crate::commands::window::{self, TimeWindow, WindowError};
+use crate::commands::table::{Colors, SortKey, Table};
+use crate::gtfs::analysis::variants::ServiceVariant;
+use crate::gtfs::gtfs_rt::overlay::{DepartureStatus, RealtimeDeparture};
+use crate::gtfs::view::ScheduleFilter;
+use crate::gtfs::stop_times::GtfsTime;
+use crate::gtfs::stops::{LocationTypeDetails, StationTree, STOP_FIELDS};
+use crate::gtfs::transfers::TransferType;
+use std::collections::{HashMap, HashSet};
+pub struct StopsCommandInterpreter<'a>(pub &'a GtfsNode);
 
 #[derive(Debug)]
 pub enum StopsCommandError {
     InvalidCommand(String),
     ErrorGettingStop(String),
     ErrorExecutingCommandForStop(String, Box<GTFSCommandInterpreterError>),
+    InvalidDeparturesArgument(String),
+    InvalidPredictionsArgument(String),
+    NoRealtimeFeed,
+    InvalidFilter(FilterError),
+    InvalidWindow(WindowError),
+    OpenError(OpenError),
+    NameError(NameError),
 }
 
 impl std::fmt::Display for StopsCommandError {
@@ -22,43 +39,195 @@ impl std::fmt::Display for StopsCommandError {
             StopsCommandError::InvalidCommand(command) => write!(f, "Invalid command: {}", command),
             StopsCommandError::ErrorGettingStop(stop_id) => write!(f, "Error getting stop: {}", stop_id),
             StopsCommandError::ErrorExecutingCommandForStop(stop_id, cause) => write!(f, "Error executing command for stop {}: {}", stop_id, **cause),
+            StopsCommandError::InvalidDeparturesArgument(argument) => write!(
+                f,
+                "Invalid departures argument: {} (expected at=08:30 or at=25:10:00, date=2025-01-31, from=07:00, to=09:30 or limit=20)",
+                argument
+            ),
+            StopsCommandError::InvalidPredictionsArgument(argument) => write!(
+                f, "Invalid predictions argument: {} (expected limit=20)", argument
+            ),
+            StopsCommandError::NoRealtimeFeed => write!(f, "No realtime feed is loaded; give one with --realtime"),
+            StopsCommandError::InvalidFilter(e) => write!(f, "{}", e),
+            StopsCommandError::InvalidWindow(e) => write!(f, "{}", e),
+            StopsCommandError::OpenError(e) => write!(f, "{}", e),
+            StopsCommandError::NameError(e) => write!(f, "{}", e),
         }
     }
 }
 
-impl std::error::Error for StopsCommandError {}
+impl std::error::Error for StopsCommandError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StopsCommandError::ErrorExecutingCommandForStop(_, cause) => Some(cause.as_ref()),
+            StopsCommandError::InvalidFilter(e) => Some(e),
+            StopsCommandError::InvalidWindow(e) => Some(e),
+            StopsCommandError::OpenError(e) => Some(e),
+            StopsCommandError::NameError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl<'a> CommandInterpreter for StopsCommandInterpreter<'a> {
-    type CommandResult = ();
+    type CommandResult = Output;
     type CommandError = StopsCommandError;
 
     fn interpret(&self, command: &str) -> Result<Self::CommandResult, Self::CommandError> {
-        let (first, rest) = command.find(".").and_then(|i| command.split_at_checked(i)).unwrap_or((command, ""));
-        match first {
-            "list" => Ok(self.list()),
-            "info" => Ok(self.info()),
-            _ => match self.0.stops.stops.get(first) {
-                None => Err(StopsCommandError::InvalidCommand(command.to_string())),
-                Some(stop) => self.stop(stop.stop_id.as_str())
-                    .map_err(|e| StopsCommandError::ErrorGettingStop(e.to_string()))?
-                    .interpret(rest.chars().skip(1).collect::<String>().as_str())
-                    .map_err(|e| StopsCommandError::ErrorExecutingCommandForStop(stop.stop_id.clone(), Box::new(e)))
-            },
+        if let Some((stops_command, args)) = commands::find_command(STOPS_COMMANDS, command) {
+            return (stops_command.run)(self, args);
+        }
+        let (segment, rest) = names::split_segment(command);
+        let Some(stop) = self.0.view().stop(&self.stop_id(segment)?) else {
+            return Err(StopsCommandError::InvalidCommand(command.to_string()));
+        };
+        // commands about the stop itself are run here; anything else is run on the stop's node.
+        if let Some((stop_command, args)) = commands::find_command(STOP_COMMANDS, rest) {
+            return (stop_command.run)(self, stop.stop_id.as_str(), args);
         }
+        let node = self.stop(stop.stop_id.as_str())
+            .map_err(|e| StopsCommandError::ErrorGettingStop(e.to_string()))?;
+        // help at a stop lists the commands about the stop before those of its node.
+        if commands::is_help(rest) {
+            commands::print_help(&commands::help_lines(STOP_COMMANDS, ""));
+        }
+        node.interpret(rest)
+            .map_err(|e| StopsCommandError::ErrorExecutingCommandForStop(stop.stop_id.clone(), Box::new(e)))
+    }
+
+    fn complete(&self, partial: &str) -> Vec<String> {
+        let view = self.0.view();
+        let Some((stop_id, rest)) = partial.split_once('.') else {
+            let mut completions = commands::complete_commands(STOPS_COMMANDS, partial);
+            completions.extend(commands::complete_ids(view.stops().map(|stop| stop.stop_id.as_str()), partial));
+            completions.sort();
+            return completions;
+        };
+        let Ok(node) = self.stop(stop_id) else {
+            return Vec::new();
+        };
+        let mut completions = commands::complete_commands(STOP_COMMANDS, rest);
+        completions.extend(node.complete(rest));
+        completions.sort();
+        completions.dedup();
+        completions.into_iter().map(|completion| format!("{}.{}", stop_id, completion)).collect()
     }
 }
 
+type StopsCommand = fn(&StopsCommandInterpreter, &str) -> Result<Output, StopsCommandError>;
+type StopCommand = fn(&StopsCommandInterpreter, &str, &str) -> Result<Output, StopsCommandError>;
+
+// STOPS_COMMANDS are the commands about the stops as a whole.
+const STOPS_COMMANDS: &[commands::Command<StopsCommand>] = &[
+    commands::Command {
+        name: "help",
+        args: "",
+        description: "List the commands valid here (also ?)",
+        run: |_, _| {
+            commands::print_collection_help(STOPS_COMMANDS, STOP_COMMANDS, "stop", "<stop_id>");
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "list",
+        args: "[<conditions>] [fields=<fields>] [sort=<fields>] [limit=<n>] [offset=<n>]",
+        description: "List the stops meeting any conditions given, e.g. name~\"Harvard\" wheelchair=true",
+        run: |stops, args| stops.list(args).map(Output::Table),
+    },
+    commands::Command {
+        name: "info",
+        args: "",
+        description: "Count the stops",
+        run: |stops, _| Ok(stops.info()),
+    },
+    commands::Command {
+        name: "count",
+        args: "[<conditions>]",
+        description: "Count the stops meeting any conditions given, e.g. wheelchair=true",
+        run: |stops, args| stops.list(args).map(|table| Output::Counts(vec![("Stops", table.rows().len())])),
+    },
+];
+
+// STOP_COMMANDS are the commands about a single stop, given as <stop_id>.<command>.
+const STOP_COMMANDS: &[commands::Command<StopCommand>] = &[
+    // info prints the stop's fields before the counts the stop node prints.
+    commands::Command {
+        name: "info",
+        args: "",
+        description: "Print every field of the stop, and count its routes and trips",
+        run: |stops, stop_id, _| {
+            stops.info_of(stop_id);
+            let node = stops.stop(stop_id).map_err(|e| StopsCommandError::ErrorGettingStop(e.to_string()))?;
+            println!("{}", node.view());
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "departures",
+        args: "[at=<HH:MM>] [date=<YYYY-MM-DD>] [from=<HH:MM>] [to=<HH:MM>] [limit=<n>]",
+        description: "List the next departures, from now by default, with routes in their colors",
+        run: |stops, stop_id, args| stops.departures(stop_id, args).map(Output::Table),
+    },
+    commands::Command {
+        name: "predictions",
+        args: "[limit=<n>]",
+        description: "List the next departures the realtime feed predicts, cancels, skips or adds, with how late they're running",
+        run: |stops, stop_id, args| stops.predictions(stop_id, args).map(Output::Table),
+    },
+    commands::Command {
+        name: "tree",
+        args: "",
+        description: "Print the stop and the stops within it",
+        run: |stops, stop_id, _| {
+            stops.tree(stop_id);
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "service",
+        args: "",
+        description: "Summarize the days and periods with service",
+        run: |stops, stop_id, _| {
+            stops.service(stop_id);
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "transfers",
+        args: "",
+        description: "List the transfers from the stop and the stops within it, with their minimum times",
+        run: |stops, stop_id, _| Ok(Output::Table(stops.transfers(stop_id))),
+    },
+    commands::Command {
+        name: "pathways",
+        args: "",
+        description: "Print the walkways, stairs, elevators and gates linking the stop and the stops within it",
+        run: |stops, stop_id, _| {
+            stops.pathways(stop_id);
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "open",
+        args: "[--print-only]",
+        description: "Open the stop's web page (stop_url) in the browser, or print it",
+        run: |stops, stop_id, args| {
+            let stop_url = stops.0.view().stop(stop_id).and_then(|stop| stop.stop_url.as_deref());
+            open::open(&format!("stop {}", stop_id), stop_url, args).map_err(StopsCommandError::OpenError)?;
+            Ok(Output::Printed)
+        },
+    },
+];
+
 #[derive(Debug)]
 pub enum StopCommandError {
     NoSuchStop(String),
-    ErrorGettingDescendants(String, Box<StopCommandError>),
 }
 
 impl std::fmt::Display for StopCommandError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             StopCommandError::NoSuchStop(stop_id) => write!(f, "No such stop: {}", stop_id),
-            StopCommandError::ErrorGettingDescendants(stop_id, cause) => write!(f, "Error getting descendants for stop {}: {}", stop_id, **cause),
         }
     }
 }
@@ -66,127 +235,419 @@ impl std::fmt::Display for StopCommandError {
 impl std::error::Error for StopCommandError {}
 
 impl StopsCommandInterpreter<'_> {
-    fn list(&self) {
-        for (_, stop) in &self.0.stops.stops {
-            match stop.get_stop_name() {
-                Some(name) => println!("{}: {}", stop.stop_id, name),
-                None => println!("{}: {}", stop.stop_id, "Unnamed Location"),
-            }
+    // list lists the stops meeting the conditions of a Filter given as args, in order of ID, with every field of
+    // stops.txt. Their IDs and names are shown unless other fields are chosen.
+    fn list(&self, args: &str) -> Result<Table, StopsCommandError> {
+        let filter = Filter::parse(args).map_err(StopsCommandError::InvalidFilter)?;
+        let mut table = Table::new(&STOP_FIELDS).with_default_fields(&["stop_id", "stop_name"]);
+        for stop in self.0.view().stops() {
+            table.push_record(&HashMap::from(stop));
         }
+        table.sort(&[SortKey::from("stop_id")]).expect("stop_id is a field of stops.txt");
+        filter.apply(table).map_err(StopsCommandError::InvalidFilter)
     }
 
-    fn info(&self) {
-        println!("{}: {}", "Stops".truecolor(128, 128, 128).bold(), self.0.stops.stops.len());
+    fn info(&self) -> Output {
+        Output::Counts(vec![("Stops", self.0.view().stops().count())])
     }
 
-    fn stop(&self, stop_id: &str) -> Result<GtfsNode, StopCommandError> {
-        let raw_stop = self.0.stops.stops.get(stop_id)
-            .ok_or(StopCommandError::NoSuchStop(stop_id.to_string()))?;
-        
-        let stops = self.clone_descendants(stop_id)?;
-
-        let stop_times = self.0.stop_times.iter()
-            .filter_map(
-                |stop_time|
-                stop_time.stop_id.as_ref().and_then(
-                    |stop_id|
-                    stops.stops.get(stop_id.as_str())
-                    .map(|_| (stop_time.trip_id.clone(), stop_time))
-                )
-            )
-            .fold(
-                HashMap::new(),
-                |mut acc, (trip_id, stop_time)| {
-                    acc.entry(trip_id).or_insert(Vec::new()).push(stop_time.clone());
-                    acc
-                }
+    // info_of prints every field of a stop given in stops.txt, naming its location type and parent station.
+    fn info_of(&self, stop_id: &str) {
+        let view = self.0.view();
+        let Some(stop) = view.stop(stop_id) else {
+            return;
+        };
+        let details = &stop.location_type_details;
+        let coordinates = stop.stop_lat().zip(stop.stop_lon()).map(|(lat, lon)| format!("{:.6}, {:.6}", lat, lon));
+        let parent_station = stop.parent_station().map(|parent_id| match view.stop(parent_id).and_then(|parent| parent.get_stop_name()) {
+            Some(name) => format!("{} {}", parent_id, name.muted()),
+            None => parent_id.to_string(),
+        });
+        commands::print_fields(&[
+            ("ID", Some(stop.stop_id.clone())),
+            ("Name", stop.get_stop_name().map(|name| name.bold().to_string())),
+            ("Code", stop.stop_code.clone()),
+            ("Spoken name", stop.tts_stop_name.clone()),
+            ("Description", stop.stop_desc.clone()),
+            ("Location type", Some(location_type_name(details).to_string())),
+            ("Coordinates", coordinates),
+            ("Parent station", parent_station),
+            ("Level", stop.level_id.clone()),
+            ("Platform", stop.platform_code.clone()),
+            ("Zone", stop.zone_id.clone()),
+            ("Timezone", stop.stop_timezone.map(|timezone| timezone.to_string())),
+            ("Wheelchair boarding", commands::yes_no(stop.wheelchair_boarding)),
+            ("URL", stop.stop_url.clone()),
+        ]);
+    }
+
+    // tree prints a stop and the stops within it, each indented beneath its parent station.
+    fn tree(&self, stop_id: &str) {
+        fn print_tree(tree: &StationTree, depth: usize) {
+            println!(
+                "{}{}: {} {}",
+                "  ".repeat(depth),
+                tree.stop.stop_id,
+                tree.stop.get_stop_name().unwrap_or("Unnamed Location"),
+                format!("({})", location_type_name(&tree.stop.location_type_details)).muted(),
             );
+            for child in &tree.children {
+                print_tree(child, depth + 1);
+            }
+        }
+        if let Some(tree) = self.0.view().station_tree(stop_id) {
+            print_tree(&tree, 0);
+        }
+    }
+
+    // stop_name gives the name of any stop in the feed with its ID, such as a transfer's destination outside the view.
+    fn stop_name(&self, stop_id: &str) -> String {
+        let name = self.0.gtfs.stops.stops.get(stop_id).and_then(|stop| stop.get_stop_name()).unwrap_or("Unnamed Location");
+        format!("{} ({})", name, stop_id)
+    }
+
+    // transfers lists the transfers given in transfers.txt from a stop or any stop within it, such as a station's
+    // platforms. Minimum transfer times are given in seconds, and only for minimum time transfers.
+    fn transfers(&self, stop_id: &str) -> Table {
+        let schedule = &self.0.gtfs;
+        let mut table = Table::new(&[
+            "from_stop_id", "to_stop_id", "to_stop_name", "type", "min_transfer_time",
+            "from_route_id", "to_route_id", "from_trip_id", "to_trip_id",
+        ])
+            .with_style(3, |text| text.yellow().to_string())
+            .with_default_fields(&["from_stop_id", "to_stop_id", "to_stop_name", "type", "min_transfer_time", "from_route_id", "to_route_id"])
+            .with_empty_message("No transfers from here; the feed may have no transfers.txt");
+        for from_stop_id in self.0.view().stops_within(stop_id) {
+            for transfer in schedule.transfers.from_stop(from_stop_id) {
+                let to_stop_name = transfer.to_stop_id.as_deref()
+                    .and_then(|to_stop_id| schedule.stops.stops.get(to_stop_id))
+                    .and_then(|stop| stop.get_stop_name())
+                    .unwrap_or_default();
+                let min_transfer_time = match transfer.transfer_type() {
+                    TransferType::MinimumTime => transfer.min_transfer_time.map(|seconds| seconds.to_string()),
+                    _ => None,
+                };
+                table.push(vec![
+                    from_stop_id.to_string(),
+                    transfer.to_stop_id.clone().unwrap_or_default(),
+                    to_stop_name.to_string(),
+                    transfer.transfer_type().name().to_string(),
+                    min_transfer_time.unwrap_or_default(),
+                    transfer.from_route_id.clone().unwrap_or_default(),
+                    transfer.to_route_id.clone().unwrap_or_default(),
+                    transfer.from_trip_id.clone().unwrap_or_default(),
+                    transfer.to_trip_id.clone().unwrap_or_default(),
+                ]);
+            }
+        }
+        table.sort(&[SortKey::from("from_stop_id"), SortKey::from("to_stop_id")]).expect("both are columns of the table");
+        table
+    }
 
-        let trips_by_route = (&self.0.trips).into_iter()
-            .filter_map(
-                |trip|
-                stop_times.get(&trip.trip_id).map(|_| (trip.route_id.clone(), trip.clone()))
-            )
-            .fold(
-                HashMap::new(),
-                |mut acc, (route_id, trip)| {
-                    acc.entry(route_id.clone()).or_insert(Vec::new()).push(trip.clone());
-                    acc
-                }
+    // pathways prints the pathways given in pathways.txt which begin or end at a stop or any stop within it, one edge
+    // per line, e.g. "PW1: Platform 1 (P1) ↔ Concourse (C1) (stairs, 12 m, 30 s, 20 steps up)". Bidirectional
+    // pathways are drawn with ↔ and the others with →, and what a pathway is signposted as is given after it.
+    fn pathways(&self, stop_id: &str) {
+        let stop_ids = self.0.view().stops_within(stop_id);
+        let mut pathways = self.0.gtfs.pathways.touching(&stop_ids).peekable();
+        if pathways.peek().is_none() {
+            println!("{}", "No pathways here; the feed may have no pathways.txt".muted());
+            return;
+        }
+        for pathway in pathways {
+            let mut details = vec![pathway.pathway_mode.name().to_string()];
+            details.extend(pathway.length.map(|length| format!("{} m", length)));
+            details.extend(pathway.traversal_time.map(|seconds| format!("{} s", seconds)));
+            // stair counts are negative for stairs going down from from_stop_id.
+            details.extend(pathway.stair_count.filter(|&count| count != 0).map(|count| match count {
+                count if count > 0 => format!("{} steps up", count),
+                count => format!("{} steps down", -count),
+            }));
+            details.extend(pathway.max_slope.map(|slope| format!("slope {}", slope)));
+            details.extend(pathway.min_width.map(|width| format!("{} m wide", width)));
+            let signs = [
+                pathway.signposted_as.as_ref().map(|sign| format!("signposted \"{}\"", sign)),
+                pathway.reversed_signposted_as.as_ref().map(|sign| format!("back \"{}\"", sign)),
+            ];
+            let signs = signs.into_iter().flatten().collect::<Vec<_>>();
+            println!(
+                "{}: {} {} {} {}{}",
+                pathway.pathway_id,
+                self.stop_name(&pathway.from_stop_id),
+                if pathway.is_bidirectional { "↔" } else { "→" },
+                self.stop_name(&pathway.to_stop_id),
+                format!("({})", details.join(", ")).muted(),
+                if signs.is_empty() { String::new() } else { format!(" {}", signs.join(", ").muted()) },
             );
+        }
+    }
 
-        let routes = (&self.0.routes).into_iter()
-            .filter_map(
-                |route|
-                trips_by_route.get(&route.route_id).map(|_| (route.route_id.clone(), route.clone()))
-            )
-            .collect::<HashMap<_, _>>();
-
-        let trips = trips_by_route.into_iter()
-            .map(|(_, trips)| trips.into_iter())
-            .flatten()
-            .map(|trip| (trip.trip_id.clone(), trip.clone()))
-            .collect::<HashMap<_, _>>();
-        
-
-        Ok(GtfsNode{
-            gtfs: GtfsSchedule{
-                stops,
-                routes: Routes{
-                    routes
-                },
-                trips: Trips{
-                    trips
+    // service prints the days of the week and periods in which a stop has service, and the trips on each type of day.
+    fn service(&self, stop_id: &str) {
+        let service_days = self.0.view().service_days_at_stop(stop_id);
+        if service_days.date_ranges.is_empty() {
+            println!("No service");
+            return;
+        }
+        println!(
+            "{}: {}",
+            "Days".muted().bold(),
+            service_days.weekdays.iter().map(|weekday| weekday.to_string()).collect::<Vec<_>>().join(", ")
+        );
+        for (first, last) in &service_days.date_ranges {
+            println!("{}: {} to {}", "Period".muted().bold(), first, last);
+        }
+        for (day_type, service) in [("Weekday", service_days.weekday), ("Saturday", service_days.saturday), ("Sunday", service_days.sunday)] {
+            match service.trips_per_date() {
+                Some(trips_per_date) => println!(
+                    "{}: {} dates, {:.1} trips per date",
+                    day_type.muted().bold(),
+                    service.dates,
+                    trips_per_date
+                ),
+                None => println!("{}: no service", day_type.muted().bold()),
+            }
+        }
+    }
+
+    // departures lists the departures from a stop, along with the stops within it if it's a station, at=<time> and
+    // date=<date> giving when to list them from; by default, those from now in the stop's timezone are listed, shown
+    // at their local times. from= and to= list those within a window of the day instead, from= standing in for at=.
+    // Only the first 10 are shown unless a limit= is given, which is usually taken as an option of the table, but is
+    // also accepted here, before other arguments.
+    fn departures(&self, stop_id: &str, args: &str) -> Result<Table, StopsCommandError> {
+        let (mut date, mut time, mut window, mut limit) = (None, None, TimeWindow::default(), 10);
+        for arg in args.split_whitespace() {
+            let invalid = || StopsCommandError::InvalidDeparturesArgument(arg.to_string());
+            if window.accept(arg).map_err(StopsCommandError::InvalidWindow)? {
+                continue;
+            }
+            match arg.split_once('=').ok_or_else(invalid)? {
+                ("date", value) => date = Some(window::parse_date(value).ok_or_else(invalid)?),
+                ("at", value) => time = Some(window::parse_time(value).ok_or_else(invalid)?),
+                ("limit", value) => limit = value.parse::<usize>().map_err(|_| invalid())?,
+                _ => return Err(invalid()),
+            }
+        }
+        let time = window.from.or(time);
+
+        let view = self.0.view();
+        let realtime = self.0.realtime.as_ref().map(|overlay| overlay.apply_to(view));
+        let table = Table::new(&["departure_time", "predicted", "route", "variant", "headsign", "from_stop_id", "continues_as"])
+            .with_style(0, |text| text.bold().to_string())
+            .with_style(1, realtime::delay_style)
+            .with_style(3, |text| text.yellow().to_string())
+            .with_style(4, |text| text.muted().to_string())
+            .with_default_limit(limit);
+        // predictions are only shown where there's a realtime feed to make them.
+        let table = match realtime {
+            Some(_) => table,
+            None => table.with_default_fields(&["departure_time", "route", "variant", "headsign", "from_stop_id", "continues_as"]),
+        };
+        // every departure is found, so that a limit= given as an option of the table can show more than the default.
+        let (mut table, departures) = match (date, time) {
+            (None, None) if window.is_open() => (
+                table.with_empty_message("No upcoming departures"),
+                match realtime {
+                    Some(realtime) => realtime.departures_now(stop_id, chrono::Utc::now(), usize::MAX),
+                    None => view.departures_now(stop_id, chrono::Utc::now(), usize::MAX)
+                        .into_iter()
+                        .map(RealtimeDeparture::scheduled)
+                        .collect(),
                 },
-                stop_times: StopTimes{
-                    stop_times
-                }
+            ),
+            (date, time) => {
+                let now = chrono::Local::now().naive_local();
+                let (date, time) = (date.unwrap_or(now.date()), time.unwrap_or(GtfsTime::from(now.time())));
+                let message = match window.to {
+                    Some(to) => format!("No departures on {} between {} and {}", date, time, to),
+                    None => format!("No departures on {} after {}", date, time),
+                };
+                let mut departures = match realtime {
+                    Some(realtime) => realtime.departures_at(stop_id, date, time, usize::MAX),
+                    None => view.departures_at(stop_id, date, time, usize::MAX)
+                        .into_iter()
+                        .map(RealtimeDeparture::scheduled)
+                        .collect(),
+                };
+                departures.retain(|departure| window.contains(departure.effective_time()));
+                (table.with_empty_message(&message), departures)
             },
-            node_id: stop_id.to_string(),
-            node_name: raw_stop.get_stop_name().map(|s| s.to_string()),
-            parent: None,
-            
-        })
-    }
-
-    fn clone_descendants(&self, stop_id: &str) -> Result<Stops, StopCommandError> {
-        let stops_and_children = self.0.stops.stops.iter().fold(
-            HashMap::new(),
-            |mut acc, (stop_id, stop)| {
-                acc.entry(stop_id.as_str()).or_insert((None, Vec::new())).0 = Some(stop);
-                stop.parent_station().map(
-                    |parent_id| {
-                        acc.entry(parent_id).or_insert((None, Vec::new())).1.push(stop_id.clone());
-                    }
-                );
-                acc
+        };
+        // departures from now may fall on two service days, so they are shown at the time of day they leave.
+        let shown_time = |departure_time: GtfsTime| match (date, time) {
+            (None, None) if window.is_open() => GtfsTime::from(departure_time.time_of_day()),
+            _ => departure_time,
+        };
+        let route_name = |route_id: &str| self.0.gtfs.routes.routes.get(route_id).map_or(route_id.to_string(), |route| route.name());
+        for departure in departures {
+            // trips the realtime feed adds aren't in the schedule, so all that's known of them is their route.
+            let scheduled = departure.scheduled;
+            let route = scheduled.and_then(|scheduled| scheduled.route)
+                .or_else(|| departure.trip_update
+                    .and_then(|trip_update| trip_update.route_id.as_deref())
+                    .and_then(|route_id| self.0.gtfs.routes.routes.get(route_id)));
+            let route_label = match (route, scheduled, departure.trip_update) {
+                (Some(route), _, _) => route.name(),
+                (None, Some(scheduled), _) => scheduled.trip.route_id.clone(),
+                (None, None, trip_update) => trip_update.and_then(|trip_update| trip_update.route_id.clone()).unwrap_or_default(),
+            };
+            // routes are shown in their colors, with black text by default as the GTFS reference specifies.
+            if let Some(route_color) = route.and_then(|route| route.route_color) {
+                table.color_value(2, &route_label, Colors {
+                    text: route
+                        .and_then(|route| route.route_text_color)
+                        .map_or((0, 0, 0), |color| (color.r, color.g, color.b)),
+                    background: (route_color.r, route_color.g, route_color.b),
+                });
             }
-        );
-        
-        let root = stops_and_children.get(stop_id)
-            .and_then(|(maybe_parent, _)| maybe_parent.clone())
+            table.push(vec![
+                departure.scheduled_time().map_or("".to_string(), |departure_time| shown_time(departure_time).to_string()),
+                predicted(&departure, shown_time),
+                route_label,
+                // local trips are the norm, so only the others are tagged.
+                scheduled.and_then(|scheduled| scheduled.variant)
+                    .filter(|variant| *variant != ServiceVariant::Local)
+                    .map_or("".to_string(), |variant| variant.name().to_string()),
+                scheduled.and_then(|scheduled| scheduled.headsign()).unwrap_or("").to_string(),
+                Some(departure.stop_id)
+                    .filter(|departure_stop_id| *departure_stop_id != stop_id)
+                    .unwrap_or_default()
+                    .to_string(),
+                // through-running is only worth pointing out when the trip changes route.
+                scheduled.and_then(|scheduled| self.0.gtfs.continues_as(&scheduled.trip.trip_id, scheduled.service_date)
+                    .filter(|next| next.route_id != scheduled.trip.route_id))
+                    .map_or("".to_string(), |next| route_name(&next.route_id)),
+            ]);
+        }
+        Ok(table)
+    }
+
+    // predictions lists the next departures from a stop which the realtime feed says something of, leaving out those
+    // it expects as scheduled, along with how late they're running and what becomes of them.
+    fn predictions(&self, stop_id: &str, args: &str) -> Result<Table, StopsCommandError> {
+        let mut limit = 10;
+        for arg in args.split_whitespace() {
+            let invalid = || StopsCommandError::InvalidPredictionsArgument(arg.to_string());
+            match arg.split_once('=').ok_or_else(invalid)? {
+                ("limit", value) => limit = value.parse::<usize>().map_err(|_| invalid())?,
+                _ => return Err(invalid()),
+            }
+        }
+        let overlay = self.0.realtime.as_ref().ok_or(StopsCommandError::NoRealtimeFeed)?;
+        let mut table = Table::new(&["trip_id", "route", "scheduled", "predicted", "delay", "status", "from_stop_id"])
+            .with_style(0, |text| text.bold().to_string())
+            .with_style(4, realtime::delay_style)
+            .with_style(5, realtime::delay_style)
+            .with_default_limit(limit)
+            .with_empty_message("No upcoming departures with predictions");
+        let departures = overlay.apply_to(self.0.view()).departures_now(stop_id, chrono::Utc::now(), usize::MAX);
+        // departures from now may fall on two service days, so they are shown at the time of day they leave.
+        let shown_time = |time: GtfsTime| GtfsTime::from(time.time_of_day()).to_string();
+        for departure in departures.iter().filter(|departure| departure.status != DepartureStatus::Scheduled) {
+            let route_id = departure.scheduled.map(|scheduled| scheduled.trip.route_id.as_str())
+                .or(departure.trip_update.and_then(|trip_update| trip_update.route_id.as_deref()));
+            table.push(vec![
+                departure.trip_id().to_string(),
+                route_id.map_or("".to_string(), |route_id| {
+                    self.0.gtfs.routes.routes.get(route_id).map_or(route_id.to_string(), |route| route.name())
+                }),
+                departure.scheduled_time().map_or("".to_string(), shown_time),
+                departure.predicted_time.map_or("".to_string(), shown_time),
+                departure.delay().map_or("".to_string(), realtime::delay_text),
+                departure.status.name().to_string(),
+                Some(departure.stop_id)
+                    .filter(|departure_stop_id| *departure_stop_id != stop_id)
+                    .unwrap_or_default()
+                    .to_string(),
+            ]);
+        }
+        Ok(table)
+    }
+
+    // navigate returns the node at path, given as <stop_id> or <stop_id>.<path from the stop's node>, the stop
+    // possibly being given by name, e.g. "Harvard".
+    pub fn navigate(&self, path: &str) -> Result<GtfsNode, StopsCommandError> {
+        let (segment, rest) = names::split_segment(path);
+        let stop_id = self.stop_id(segment)?;
+        self.stop(&stop_id)
+            .map_err(|e| StopsCommandError::ErrorGettingStop(e.to_string()))?
+            .navigate(rest)
+            .map_err(|e| StopsCommandError::ErrorExecutingCommandForStop(stop_id, Box::new(e)))
+    }
+
+    // stop_id gives the ID of the stop a segment of a path picks out. Of the stops called a name, those within a
+    // station also called it, such as its platforms, are left out, so that "Harvard" finds the station alone.
+    pub fn stop_id(&self, segment: Segment) -> Result<String, StopsCommandError> {
+        let name = match segment {
+            Segment::Id(stop_id) => return Ok(stop_id.to_string()),
+            Segment::Name(name) => name,
+        };
+        let view = self.0.view();
+        let matches = names::matching(name, view.stops().filter_map(|stop| Some((stop.stop_id.as_str(), stop.get_stop_name()?))));
+        let matched = matches.iter().map(|(stop_id, _)| *stop_id).collect::<HashSet<_>>();
+        let matches = matches.into_iter()
+            .filter(|(stop_id, _)| !view.stop(stop_id)
+                .and_then(|stop| stop.parent_station())
+                .is_some_and(|parent_station| matched.contains(parent_station)))
+            .collect();
+        names::one("stop", name, matches).map_err(StopsCommandError::NameError)
+    }
+
+    // stop creates a node for the given stop and its descendants, along with the trips which visit them
+    // and the routes those trips belong to. The node shares this node's schedule rather than copying it.
+    fn stop(&self, stop_id: &str) -> Result<GtfsNode, StopCommandError> {
+        let view = self.0.view();
+        let raw_stop = view.stop(stop_id)
             .ok_or(StopCommandError::NoSuchStop(stop_id.to_string()))?;
 
-        let mut descendants = collections::HashMap::new();
-        put_descendants(&mut descendants, &root, &stops_and_children)
-            .map_err(|e| StopCommandError::ErrorGettingDescendants(stop_id.to_string(), Box::new(e)))?;
+        let stop_ids = view.stops_within(stop_id)
+            .into_iter()
+            .map(|stop_id| stop_id.to_string())
+            .collect::<HashSet<_>>();
 
-        Ok(Stops {
-            stops: descendants,
-        })
+        let trip_ids = stop_ids.iter()
+            .flat_map(|stop_id| view.stop_times_at(stop_id))
+            .map(|stop_time| stop_time.trip_id.clone())
+            .collect::<HashSet<_>>();
+
+        let route_ids = view.routes_at_stop(stop_id)
+            .into_iter()
+            .map(|route| route.route_id.clone())
+            .collect::<HashSet<_>>();
+
+        Ok(self.0.child(
+            &format!("stops.{}", stop_id),
+            raw_stop.get_stop_name().map(|s| s.to_string()),
+            ScheduleFilter {
+                stop_ids,
+                route_ids,
+                trip_ids,
+            }
+        ))
     }
 }
 
-fn put_descendants(descendants: &mut HashMap<String, Stop>, stop: &Stop, stops_and_children: &HashMap<&str, (Option<&Stop>, Vec<String>)>) ->Result<(), StopCommandError>  {
-    descendants.insert(stop.stop_id.clone(), stop.clone());
-    if let Some(children) = stops_and_children.get(stop.stop_id.as_str()).map(|(_, children)| children) {
-        for child_id in children {
-            let child = stops_and_children.get(child_id.as_str())
-                .and_then(|(maybe_parent, _)| maybe_parent.clone())
-                .ok_or(StopCommandError::NoSuchStop(child_id.to_string()))?;
-            put_descendants(descendants, child, stops_and_children)
-                .map_err(|e| StopCommandError::ErrorGettingDescendants(child_id.clone(), Box::new(e)))?;
-        }
+fn location_type_name(location_type_details: &LocationTypeDetails) -> &'static str {
+    match location_type_details {
+        LocationTypeDetails::Stop(_) => "stop",
+        LocationTypeDetails::Station(_) => "station",
+        LocationTypeDetails::EntranceExit(_) => "entrance",
+        LocationTypeDetails::GenericNode(_) => "generic node",
+        LocationTypeDetails::BoardingArea(_) => "boarding area",
     }
-    Ok(())
-}
\ No newline at end of file
+}
+
+// predicted shows when a departure is expected as a realtime feed has it, along with how far that is from its
+// scheduled time to the minute, e.g. 08:07 +2 min, or that it's canceled, skipped or an added trip. Times are shown
+// as shown_time shows scheduled ones.
+fn predicted(departure: &RealtimeDeparture, shown_time: impl Fn(GtfsTime) -> GtfsTime) -> String {
+    let time = departure.predicted_time.map(|time| shown_time(time).to_string()).unwrap_or_default();
+    match (departure.status, departure.delay()) {
+        (DepartureStatus::Scheduled, _) => "".to_string(),
+        (DepartureStatus::Canceled | DepartureStatus::Skipped, _) => departure.status.name().to_string(),
+        (DepartureStatus::Added, _) => format!("{} added", time),
+        (DepartureStatus::Predicted, Some(delay)) => format!("{} {}", time, realtime::delay_text(delay)),
+        (DepartureStatus::Predicted, None) => time,
+    }
+}
@@ -0,0 +1,317 @@
+use crate::style::{Colorize, Paint};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+// Style colors the text of a column.
+pub type Style = fn(&str) -> String;
+
+// Colors are the colors of a cell's text and background, as RGB, such as a route's route_text_color and route_color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Colors {
+    pub text: (u8, u8, u8),
+    pub background: (u8, u8, u8),
+}
+
+// Table holds the rows found by list commands, and lays them out in aligned columns. A table may hold more fields
+// than are usually worth showing, such as every field of stops.txt, in which case it names the ones to show unless
+// others are chosen. When printed to a terminal too narrow for it, the last column is cut short to fit, as it
+// usually holds names and headsigns, which can be recognized from their beginnings.
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+    // styles color the text of each column, after it has been laid out.
+    styles: Vec<Option<Style>>,
+    // value_colors color particular values of each column in place of its style, such as route names in the route's
+    // colors. They follow the values, so that they survive the rows being sorted, paged and selected.
+    value_colors: Vec<HashMap<String, Colors>>,
+    default_fields: Option<Vec<String>>,
+    // default_limit is the most rows shown unless a limit is chosen, e.g. the next 10 departures of the many found.
+    default_limit: Option<usize>,
+    // empty_message is printed in place of a table without rows, e.g. "No upcoming departures".
+    empty_message: Option<String>,
+    // omitted counts the rows left out after the last by page, which are noted beneath them when printed.
+    omitted: usize,
+}
+
+// SortKey is a field to sort rows by, written as the field followed by :desc to sort in descending order, e.g.
+// name:desc. Fields are sorted in ascending order otherwise, or if followed by :asc.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub field: String,
+    pub descending: bool,
+}
+
+impl From<&str> for SortKey {
+    fn from(key: &str) -> Self {
+        let (field, descending) = match key.rsplit_once(':') {
+            Some((field, "desc")) => (field, true),
+            Some((field, "asc")) => (field, false),
+            _ => (key, false),
+        };
+        SortKey { field: field.to_string(), descending }
+    }
+}
+
+// MIN_LAST_COLUMN_WIDTH is the narrowest the last column is cut to; lines are cut at the terminal's edge beyond that.
+const MIN_LAST_COLUMN_WIDTH: usize = 12;
+const SEPARATOR: &str = "  ";
+
+impl Table {
+    // new creates a table with a header row naming its columns.
+    pub fn new(headers: &[&str]) -> Self {
+        Table {
+            headers: headers.iter().map(|header| header.to_string()).collect(),
+            rows: Vec::new(),
+            styles: Vec::new(),
+            value_colors: Vec::new(),
+            default_fields: None,
+            default_limit: None,
+            empty_message: None,
+            omitted: 0,
+        }
+    }
+
+    // without_headers creates a table with no header row, its columns being those of its rows.
+    pub fn without_headers() -> Self {
+        Table::new(&[])
+    }
+
+    pub fn with_style(mut self, column: usize, style: Style) -> Self {
+        if self.styles.len() <= column {
+            self.styles.resize(column + 1, None);
+        }
+        self.styles[column] = Some(style);
+        self
+    }
+
+    // with_default_fields names the fields shown unless others are chosen.
+    pub fn with_default_fields(mut self, fields: &[&str]) -> Self {
+        self.default_fields = Some(fields.iter().map(|field| field.to_string()).collect());
+        self
+    }
+
+    // with_default_limit sets the most rows shown unless a limit is chosen.
+    pub fn with_default_limit(mut self, limit: usize) -> Self {
+        self.default_limit = Some(limit);
+        self
+    }
+
+    pub fn with_empty_message(mut self, message: &str) -> Self {
+        self.empty_message = Some(message.to_string());
+        self
+    }
+
+    pub fn push(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    // push_record adds a row holding the values of a record's fields under the table's headers, such as the
+    // encoding of a stop as a row of stops.txt. Fields the record lacks are left empty.
+    pub fn push_record(&mut self, record: &HashMap<String, String>) {
+        let row = self.headers.iter().map(|header| record.get(header).cloned().unwrap_or_default()).collect();
+        self.rows.push(row);
+    }
+
+    // color_value shows value in the given colors wherever it appears in a column.
+    pub fn color_value(&mut self, column: usize, value: &str, colors: Colors) {
+        if self.value_colors.len() <= column {
+            self.value_colors.resize(column + 1, HashMap::new());
+        }
+        self.value_colors[column].insert(value.to_string(), colors);
+    }
+
+    pub fn default_fields(&self) -> Option<&[String]> {
+        self.default_fields.as_deref()
+    }
+
+    pub fn default_limit(&self) -> Option<usize> {
+        self.default_limit
+    }
+
+    // column finds the column of a field. Besides its header, a field may be given without the prefix it shares
+    // with an ID, such as name for stop_name beside stop_id, or by the first word of its header, such as wheelchair
+    // for wheelchair_boarding, as long as no other header matches as well.
+    pub fn column(&self, field: &str) -> Option<usize> {
+        let only = |matches: Vec<usize>| match matches.as_slice() {
+            [column] => Some(*column),
+            _ => None,
+        };
+        let prefixed = self.headers.iter()
+            .filter_map(|header| header.strip_suffix("id"))
+            .filter(|prefix| !prefix.is_empty())
+            .filter_map(|prefix| self.headers.iter().position(|header| *header == format!("{}{}", prefix, field)))
+            .collect();
+        let starting = self.headers.iter()
+            .enumerate()
+            .filter(|(_, header)| header.starts_with(&format!("{}_", field)))
+            .map(|(column, _)| column)
+            .collect();
+        self.headers.iter().position(|header| header == field)
+            .or_else(|| only(prefixed))
+            .or_else(|| only(starting))
+    }
+
+    // select returns a table of the given fields, in the order given, or the first field the table doesn't have.
+    pub fn select(&self, fields: &[String]) -> Result<Table, String> {
+        let columns = fields.iter()
+            .map(|field| self.column(field).ok_or_else(|| field.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Table {
+            headers: columns.iter().map(|column| self.headers[*column].clone()).collect(),
+            rows: self.rows.iter().map(|row| columns.iter().map(|column| cell(row, *column).to_string()).collect()).collect(),
+            styles: columns.iter().map(|column| self.styles.get(*column).copied().flatten()).collect(),
+            value_colors: columns.iter().map(|column| self.value_colors.get(*column).cloned().unwrap_or_default()).collect(),
+            default_fields: None,
+            default_limit: self.default_limit,
+            empty_message: self.empty_message.clone(),
+            omitted: self.omitted,
+        })
+    }
+
+    // sort sorts the rows by each key in turn, keeping the order of rows alike in all of them, or returns the first
+    // field the table doesn't have. Numbers are compared as numbers, so that 10 follows 9, and come before text,
+    // which is compared regardless of case; rows without a value come last either way.
+    pub fn sort(&mut self, keys: &[SortKey]) -> Result<(), String> {
+        let columns = keys.iter()
+            .map(|key| self.column(&key.field).map(|column| (column, key.descending)).ok_or_else(|| key.field.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.rows.sort_by(|a, b| columns.iter()
+            .map(|(column, descending)| compare(cell(a, *column), cell(b, *column), *descending))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal));
+        Ok(())
+    }
+
+    // page skips the first offset rows, then keeps at most limit of the rest if given.
+    pub fn page(&mut self, offset: usize, limit: Option<usize>) {
+        self.rows.drain(..offset.min(self.rows.len()));
+        if let Some(limit) = limit.filter(|limit| *limit < self.rows.len()) {
+            self.omitted += self.rows.len() - limit;
+            self.rows.truncate(limit);
+        }
+    }
+
+    // retain keeps the rows for which keep returns true.
+    pub fn retain(&mut self, keep: impl FnMut(&Vec<String>) -> bool) {
+        self.rows.retain(keep);
+    }
+
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    pub fn rows(&self) -> &[Vec<String>] {
+        &self.rows
+    }
+
+    // print prints the table, cut to the width of the terminal if stdout is one.
+    pub fn print(&self) {
+        if let (true, Some(message)) = (self.rows.is_empty(), &self.empty_message) {
+            println!("{}", message);
+            return;
+        }
+        let max_width = terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| width as usize);
+        let headers = (!self.headers.is_empty()).then_some(&self.headers);
+        let rows = headers.into_iter().map(|_| None).chain(self.rows.iter().map(Some));
+        for (row, cells) in rows.zip(self.layout(max_width)) {
+            let line = cells.iter()
+                .enumerate()
+                .map(|(column, text)| {
+                    // padding is left unstyled, so that colored backgrounds end with the text.
+                    let (text, padding) = text.split_at(text.trim_end_matches(' ').len());
+                    let Some(row) = row else {
+                        return format!("{}{}", text.muted().bold(), padding);
+                    };
+                    let colors = self.value_colors.get(column).and_then(|colors| colors.get(cell(row, column)));
+                    match (colors, self.styles.get(column).copied().flatten()) {
+                        (Some(Colors { text: (r, g, b), background: (br, bg, bb) }), _) => {
+                            format!("{}{}", text.rgb(*r, *g, *b).on_rgb(*br, *bg, *bb), padding)
+                        },
+                        (None, Some(style)) => format!("{}{}", style(text), padding),
+                        (None, None) => format!("{}{}", text, padding),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(SEPARATOR);
+            println!("{}", line);
+        }
+        if self.omitted > 0 {
+            println!("{}", format!("… and {} more", self.omitted).muted());
+        }
+    }
+
+    // layout pads the header and each row to the widths of the columns, cutting lines to max_width characters if
+    // given. The last column isn't padded, so that lines don't end in spaces.
+    fn layout(&self, max_width: Option<usize>) -> Vec<Vec<String>> {
+        let columns = self.rows.iter().chain(std::iter::once(&self.headers)).map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return Vec::new();
+        }
+        let mut widths = (0..columns)
+            .map(|column| self.rows.iter().chain(std::iter::once(&self.headers))
+                .map(|row| cell(row, column).chars().count())
+                .max()
+                .unwrap_or(0))
+            .collect::<Vec<_>>();
+        widths[columns - 1] = match max_width {
+            Some(max_width) => {
+                let others = widths[..columns - 1].iter().map(|width| width + SEPARATOR.len()).sum::<usize>();
+                widths[columns - 1].min(max_width.saturating_sub(others).max(MIN_LAST_COLUMN_WIDTH))
+            },
+            None => widths[columns - 1],
+        };
+
+        let headers = (!self.headers.is_empty()).then_some(&self.headers);
+        headers.into_iter().chain(self.rows.iter())
+            .map(|row| {
+                let mut remaining = max_width.unwrap_or(usize::MAX);
+                let mut cells = Vec::new();
+                for (column, width) in widths.iter().enumerate() {
+                    if remaining == 0 {
+                        break;
+                    }
+                    let text = truncate(cell(row, column), *width);
+                    let text = match column == columns - 1 {
+                        true => text,
+                        false => format!("{:<width$}", text, width = width),
+                    };
+                    let text = truncate(&text, remaining);
+                    remaining = remaining.saturating_sub(text.chars().count() + SEPARATOR.len());
+                    cells.push(text);
+                }
+                cells
+            })
+            .collect()
+    }
+}
+
+// cell returns the text of a row's column, which is empty if the row is short of it.
+fn cell(row: &[String], column: usize) -> &str {
+    row.get(column).map_or("", String::as_str)
+}
+
+// compare orders two values of a column for Table::sort.
+fn compare(a: &str, b: &str, descending: bool) -> Ordering {
+    if a.is_empty() || b.is_empty() {
+        return a.is_empty().cmp(&b.is_empty());
+    }
+    let ordering = match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.to_lowercase().cmp(&b.to_lowercase()).then_with(|| a.cmp(b)),
+    };
+    match descending {
+        true => ordering.reverse(),
+        false => ordering,
+    }
+}
+
+// truncate cuts text to at most width characters, marking where it was cut with an ellipsis.
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        text.to_string()
+    } else {
+        text.chars().take(width.saturating_sub(1)).chain(std::iter::once('…')).collect()
+    }
+}
@@ -1,25 +1,310 @@
-use crate::gtfs::GtfsSchedule;
+use crate::commands;
+use crate::commands::gtfs::GtfsNode;
 use crate::commands::CommandInterpreter;
+use crate::commands::filter::{Filter, FilterError};
+use crate::commands::gtfs::GTFSCommandInterpreterError;
+use crate::commands::names::{self, NameError, Segment};
+use crate::commands::output::Output;
+use crate::commands::table::{SortKey, Table};
+use crate::gtfs::stop_times::{GtfsTime, StopPolicy, Timepoint};
+use crate::gtfs::trips::TRIP_FIELDS;
+use crate::gtfs::view::ScheduleFilter;
+use crate::style::{Colorize, Paint};
+use std::collections::{HashMap, HashSet};
 
-pub struct TripsCommandInterpreter<'a>(pub &'a GtfsSchedule);
+pub struct TripsCommandInterpreter<'a>(pub &'a GtfsNode);
 
 #[derive(Debug)]
-pub enum TripsCommandError {}
+pub enum TripsCommandError {
+    InvalidCommand(String),
+    ErrorGettingTrip(String),
+    ErrorExecutingCommandForTrip(String, Box<GTFSCommandInterpreterError>),
+    InvalidFilter(FilterError),
+    NameError(NameError),
+}
 
 impl std::fmt::Display for TripsCommandError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        Ok(())
+        match self {
+            TripsCommandError::InvalidCommand(command) => write!(f, "Invalid command: {}", command),
+            TripsCommandError::ErrorGettingTrip(trip_id) => write!(f, "Error getting trip: {}", trip_id),
+            TripsCommandError::ErrorExecutingCommandForTrip(trip_id, cause) => write!(f, "Error executing command for trip {}: {}", trip_id, **cause),
+            TripsCommandError::InvalidFilter(e) => write!(f, "{}", e),
+            TripsCommandError::NameError(e) => write!(f, "{}", e),
+        }
     }
 }
 
-impl std::error::Error for TripsCommandError {}
+impl std::error::Error for TripsCommandError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TripsCommandError::ErrorExecutingCommandForTrip(_, cause) => Some(cause.as_ref()),
+            TripsCommandError::InvalidFilter(e) => Some(e),
+            TripsCommandError::NameError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl<'a> CommandInterpreter for TripsCommandInterpreter<'a> {
-    type CommandResult = ();
+    type CommandResult = Output;
     type CommandError = TripsCommandError;
 
     fn interpret(&self, command: &str) -> Result<Self::CommandResult, Self::CommandError> {
-        let (first, rest) = command.find(".").and_then(|i| command.split_at_checked(i)).unwrap_or((command, ""));
-        Ok(())
+        if let Some((trips_command, args)) = commands::find_command(TRIPS_COMMANDS, command) {
+            return (trips_command.run)(self, args);
+        }
+        let (segment, rest) = names::split_segment(command);
+        let Some(trip) = self.0.view().trip(&self.trip_id(segment)?) else {
+            return Err(TripsCommandError::InvalidCommand(command.to_string()));
+        };
+        // commands about the trip itself are run here; anything else is run on the trip's node.
+        if let Some((trip_command, args)) = commands::find_command(TRIP_COMMANDS, rest) {
+            return (trip_command.run)(self, trip.trip_id.as_str(), args);
+        }
+        // help at a trip lists the commands about the trip before those of its node.
+        if commands::is_help(rest) {
+            commands::print_help(&commands::help_lines(TRIP_COMMANDS, ""));
+        }
+        self.trip(trip.trip_id.as_str())
+            .map_err(|e| TripsCommandError::ErrorGettingTrip(e.to_string()))?
+            .interpret(rest)
+            .map_err(|e| TripsCommandError::ErrorExecutingCommandForTrip(trip.trip_id.clone(), Box::new(e)))
+    }
+
+    fn complete(&self, partial: &str) -> Vec<String> {
+        let view = self.0.view();
+        let Some((trip_id, rest)) = partial.split_once('.') else {
+            let mut completions = commands::complete_commands(TRIPS_COMMANDS, partial);
+            completions.extend(commands::complete_ids(view.trips().map(|trip| trip.trip_id.as_str()), partial));
+            completions.sort();
+            return completions;
+        };
+        let Ok(node) = self.trip(trip_id) else {
+            return Vec::new();
+        };
+        let mut completions = commands::complete_commands(TRIP_COMMANDS, rest);
+        completions.extend(node.complete(rest));
+        completions.sort();
+        completions.dedup();
+        completions.into_iter().map(|completion| format!("{}.{}", trip_id, completion)).collect()
+    }
+}
+
+type TripsCommand = fn(&TripsCommandInterpreter, &str) -> Result<Output, TripsCommandError>;
+type TripCommand = fn(&TripsCommandInterpreter, &str, &str) -> Result<Output, TripsCommandError>;
+
+// TRIPS_COMMANDS are the commands about the trips as a whole.
+const TRIPS_COMMANDS: &[commands::Command<TripsCommand>] = &[
+    commands::Command {
+        name: "help",
+        args: "",
+        description: "List the commands valid here (also ?)",
+        run: |_, _| {
+            commands::print_collection_help(TRIPS_COMMANDS, TRIP_COMMANDS, "trip", "<trip_id>");
+            Ok(Output::Printed)
+        },
+    },
+    commands::Command {
+        name: "list",
+        args: "[<conditions>] [fields=<fields>] [sort=<fields>] [limit=<n>] [offset=<n>]",
+        description: "List the trips with their routes and headsigns, meeting any conditions given, e.g. headsign~Ashmont",
+        run: |trips, args| trips.list(args).map(Output::Table),
+    },
+    commands::Command {
+        name: "info",
+        args: "",
+        description: "Count the trips",
+        run: |trips, _| Ok(trips.info()),
+    },
+    commands::Command {
+        name: "count",
+        args: "[<conditions>]",
+        description: "Count the trips meeting any conditions given, e.g. headsign~Ashmont",
+        run: |trips, args| trips.list(args).map(|table| Output::Counts(vec![("Trips", table.rows().len())])),
+    },
+];
+
+// TRIP_COMMANDS are the commands about a single trip, given as <trip_id>.<command>.
+const TRIP_COMMANDS: &[commands::Command<TripCommand>] = &[
+    // info prints the trip's fields before the counts the trip node prints.
+    commands::Command {
+        name: "info",
+        args: "",
+        description: "Print every field of the trip and when it runs, and count its stops",
+        run: |trips, trip_id, _| {
+            trips.info_of(trip_id);
+            let node = trips.trip(trip_id).map_err(|e| TripsCommandError::ErrorGettingTrip(e.to_string()))?;
+            println!("{}", node.view());
+            Ok(Output::Printed)
+        },
+    },
+    // schedule lists the trip's own stop times in order, which the trip node can't, since it only sees sets of records.
+    commands::Command {
+        name: "schedule",
+        args: "",
+        description: "List the trip's stop times in order",
+        run: |trips, trip_id, _| {
+            trips.schedule(trip_id);
+            Ok(Output::Printed)
+        },
+    },
+];
+
+#[derive(Debug)]
+pub enum TripCommandError {
+    NoSuchTrip(String),
+}
+
+impl std::fmt::Display for TripCommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TripCommandError::NoSuchTrip(trip_id) => write!(f, "No such trip: {}", trip_id),
+        }
+    }
+}
+
+impl std::error::Error for TripCommandError {}
+
+impl TripsCommandInterpreter<'_> {
+    // list lists the trips meeting the conditions of a Filter given as args, in order of ID, with every field of
+    // trips.txt along with the name of the route each belongs to and the time it leaves its first stop. Their IDs,
+    // routes and headsigns are shown unless other fields are chosen.
+    fn list(&self, args: &str) -> Result<Table, TripsCommandError> {
+        let filter = Filter::parse(args).map_err(TripsCommandError::InvalidFilter)?;
+        let view = self.0.view();
+        let mut table = Table::new(&[&TRIP_FIELDS[..], &["route", "departure_time"]].concat())
+            .with_default_fields(&["trip_id", "route", "trip_headsign"]);
+        for trip in view.trips() {
+            let mut record = HashMap::from(trip);
+            record.insert("route".to_string(), view.route(&trip.route_id).map_or(trip.route_id.clone(), |route| route.name()));
+            let departure_time = view.stop_times_of_trip(&trip.trip_id)
+                .min_by_key(|stop_time| stop_time.stop_sequence)
+                .and_then(|stop_time| stop_time.departure_time);
+            record.insert("departure_time".to_string(), departure_time.map_or(String::new(), |time| time.to_string()));
+            table.push_record(&record);
+        }
+        table.sort(&[SortKey::from("trip_id")]).expect("trip_id is a field of trips.txt");
+        filter.apply(table).map_err(TripsCommandError::InvalidFilter)
+    }
+
+    fn info(&self) -> Output {
+        Output::Counts(vec![("Trips", self.0.view().trips().count())])
+    }
+
+    // info_of prints every field of a trip given in trips.txt, naming its route, along with the times it leaves its
+    // first stop and reaches its last.
+    fn info_of(&self, trip_id: &str) {
+        let view = self.0.view();
+        let Some(trip) = view.trip(trip_id) else {
+            return;
+        };
+        let route = match view.route(&trip.route_id) {
+            Some(route) => format!("{} {}", trip.route_id, route.name().muted()),
+            None => trip.route_id.clone(),
+        };
+        let mut trip_stop_times = view.stop_times_of_trip(trip_id).collect::<Vec<_>>();
+        trip_stop_times.sort_by_key(|stop_time| stop_time.stop_sequence);
+        let first_departure = trip_stop_times.first().and_then(|stop_time| stop_time.departure_time);
+        let last_arrival = trip_stop_times.last().and_then(|stop_time| stop_time.arrival_time);
+        commands::print_fields(&[
+            ("ID", Some(trip.trip_id.clone())),
+            ("Route", Some(route)),
+            ("Service", Some(trip.service_id.clone())),
+            ("Headsign", trip.trip_headsign.as_ref().map(|headsign| headsign.bold().to_string())),
+            ("Short name", trip.trip_short_name.clone()),
+            ("Direction", trip.direction_id.map(|direction| direction.code().to_string())),
+            ("Block", trip.block_id.clone()),
+            ("Shape", trip.shape_id.clone()),
+            ("Wheelchair accessible", commands::yes_no(trip.wheelchair_accessible)),
+            ("Bikes allowed", commands::yes_no(trip.bikes_allowed)),
+            ("Departs", first_departure.map(|time| time.to_string())),
+            ("Arrives", last_arrival.map(|time| time.to_string())),
+        ]);
+    }
+
+    // schedule prints a trip's stop times in order of stop_sequence, with arrival and departure times, stop names and
+    // any restrictions on boarding and alighting. Times marked ~ are approximate; times of stops not marked as
+    // timepoints either way are taken to be exact if given, as the GTFS reference specifies.
+    fn schedule(&self, trip_id: &str) {
+        let view = self.0.view();
+        let mut trip_stop_times = view.stop_times_of_trip(trip_id).collect::<Vec<_>>();
+        trip_stop_times.sort_by_key(|stop_time| stop_time.stop_sequence);
+        let format_time = |time: Option<GtfsTime>| time.map_or("--:--:--".to_string(), |time| time.to_string());
+        for stop_time in trip_stop_times {
+            let approximate = matches!(stop_time.timepoint, Some(Timepoint::Approximate));
+            let flags = [("pickup", &stop_time.pickup_type), ("drop-off", &stop_time.drop_off_type)]
+                .into_iter()
+                .filter_map(|(action, policy)| Some(match policy.as_ref()? {
+                    StopPolicy::RegularlyScheduled => return None,
+                    StopPolicy::Unavailable => format!("no {}", action),
+                    StopPolicy::Prearrange => format!("{} by arrangement", action),
+                    StopPolicy::CoordinateWithDriver => format!("{} on request", action),
+                }))
+                .collect::<Vec<_>>();
+            println!(
+                "{:>3}. {}{} {} {}: {}{}",
+                stop_time.stop_sequence,
+                if approximate { "~" } else { " " },
+                format_time(stop_time.arrival_time).bold(),
+                format_time(stop_time.departure_time).bold(),
+                stop_time.stop_id.as_deref().unwrap_or("-"),
+                stop_time.stop_id.as_deref()
+                    .and_then(|stop_id| view.schedule.stops.stops.get(stop_id))
+                    .and_then(|stop| stop.get_stop_name())
+                    .unwrap_or("Unnamed Location"),
+                if flags.is_empty() {
+                    "".to_string()
+                } else {
+                    format!(" ({})", flags.join(", ")).muted().to_string()
+                }
+            );
+        }
+    }
+
+    // navigate returns the node at path, given as <trip_id> or <trip_id>.<path from the trip's node>, the trip
+    // possibly being given by name, e.g. "1712".
+    pub fn navigate(&self, path: &str) -> Result<GtfsNode, TripsCommandError> {
+        let (segment, rest) = names::split_segment(path);
+        let trip_id = self.trip_id(segment)?;
+        self.trip(&trip_id)
+            .map_err(|e| TripsCommandError::ErrorGettingTrip(e.to_string()))?
+            .navigate(rest)
+            .map_err(|e| TripsCommandError::ErrorExecutingCommandForTrip(trip_id, Box::new(e)))
+    }
+
+    // trip_id gives the ID of the trip a segment of a path picks out, by its trip_short_name, such as a train
+    // number, if it's quoted. Headsigns aren't names, since many trips share each.
+    fn trip_id(&self, segment: Segment) -> Result<String, TripsCommandError> {
+        let name = match segment {
+            Segment::Id(trip_id) => return Ok(trip_id.to_string()),
+            Segment::Name(name) => name,
+        };
+        let view = self.0.view();
+        let candidates = view.trips()
+            .filter_map(|trip| Some((trip.trip_id.as_str(), trip.trip_short_name.as_deref()?)));
+        names::one("trip", name, names::matching(name, candidates)).map_err(TripsCommandError::NameError)
+    }
+
+    // trip creates a node for the given trip, along with its route and the stops it visits.
+    // The node shares this node's schedule rather than copying it.
+    fn trip(&self, trip_id: &str) -> Result<GtfsNode, TripCommandError> {
+        let view = self.0.view();
+        let raw_trip = view.trip(trip_id)
+            .ok_or(TripCommandError::NoSuchTrip(trip_id.to_string()))?;
+
+        let stop_ids = view.stop_times_of_trip(trip_id)
+            .filter_map(|stop_time| stop_time.stop_id.clone())
+            .collect::<HashSet<_>>();
+
+        Ok(self.0.child(
+            &format!("trips.{}", trip_id),
+            raw_trip.trip_headsign.clone(),
+            ScheduleFilter {
+                stop_ids,
+                route_ids: HashSet::from([raw_trip.route_id.clone()]),
+                trip_ids: HashSet::from([trip_id.to_string()]),
+            }
+        ))
     }
 }
@@ -0,0 +1,54 @@
+use crate::gtfs::validation::{Severity, ValidationReport};
+use crate::style::{Colorize, Paint};
+
+// STRICT is the argument to validate which fails the feed for its warnings as well as its errors.
+pub const STRICT: &str = "--strict";
+
+// strict reads the arguments to validate, which are nothing or --strict, giving back the argument if it's neither.
+pub fn strict(args: &str) -> Result<bool, String> {
+    match args.trim() {
+        "" => Ok(false),
+        STRICT => Ok(true),
+        argument => Err(argument.to_string()),
+    }
+}
+
+// print_report prints the findings of validate grouped by severity, errors first, and then whether the feed passed.
+pub fn print_report(report: &ValidationReport, strict: bool) {
+    for severity in [Severity::Error, Severity::Warning, Severity::Info] {
+        let findings = report.of_severity(severity).collect::<Vec<_>>();
+        if findings.is_empty() {
+            continue;
+        }
+        let heading = format!("{} ({})", plural(severity), findings.len());
+        let heading = match severity {
+            Severity::Error => heading.red().bold().to_string(),
+            Severity::Warning => heading.yellow().bold().to_string(),
+            Severity::Info => heading.muted().bold().to_string(),
+        };
+        println!("{}", heading);
+        for finding in findings {
+            println!("  {} {}", format!("{}: {}:", finding.file, finding.record).muted(), finding.message);
+        }
+    }
+    let count = |severity, noun: &str| match report.of_severity(severity).count() {
+        1 => format!("1 {}", noun),
+        n => format!("{} {}s", n, noun),
+    };
+    let summary = format!(
+        "{}, {} and {} info",
+        count(Severity::Error, "error"), count(Severity::Warning, "warning"), report.of_severity(Severity::Info).count(),
+    );
+    match report.passes(strict) {
+        true => println!("{} {}", "Passed:".green().bold(), summary),
+        false => println!("{} {}", "Failed:".red().bold(), summary),
+    }
+}
+
+fn plural(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "Errors",
+        Severity::Warning => "Warnings",
+        Severity::Info => "Info",
+    }
+}
@@ -0,0 +1,85 @@
+use crate::gtfs::stop_times::GtfsTime;
+use std::ops;
+
+// TimeWindow is the part of a service day given to a command as from=07:00 to=09:30, either end of which may be left
+// open. Times are measured as in stop_times.txt, so to=25:30 reaches half past one on the following morning.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub from: Option<GtfsTime>,
+    // to is the last time within the window; a departure at exactly to=09:30 falls within it.
+    pub to: Option<GtfsTime>,
+}
+
+#[derive(Debug)]
+pub enum WindowError {
+    InvalidTime(String),
+    EndsBeforeStart(GtfsTime, GtfsTime),
+}
+
+impl std::fmt::Display for WindowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WindowError::InvalidTime(argument) => write!(
+                f, "Invalid time: {} (expected a time like 07:00 or 25:30:00, after from= or to=)", argument
+            ),
+            WindowError::EndsBeforeStart(from, to) => write!(f, "Invalid window: {} ends before {} begins", to, from),
+        }
+    }
+}
+
+impl std::error::Error for WindowError {}
+
+impl TimeWindow {
+    // accept takes a from= or to= argument into the window, saying whether arg was one, so that commands can read
+    // their other arguments around it.
+    pub fn accept(&mut self, arg: &str) -> Result<bool, WindowError> {
+        let Some((name, value)) = arg.split_once('=') else {
+            return Ok(false);
+        };
+        let time = || parse_time(value).ok_or_else(|| WindowError::InvalidTime(arg.to_string()));
+        match name {
+            "from" => self.from = Some(time()?),
+            "to" => self.to = Some(time()?),
+            _ => return Ok(false),
+        }
+        match (self.from, self.to) {
+            (Some(from), Some(to)) if to < from => Err(WindowError::EndsBeforeStart(from, to)),
+            _ => Ok(true),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.from.is_none() && self.to.is_none()
+    }
+
+    pub fn contains(&self, time: GtfsTime) -> bool {
+        self.from.is_none_or(|from| time >= from) && self.to.is_none_or(|to| time <= to)
+    }
+
+    // range gives the window as the range of times within it, open ends reaching from the start of the service day
+    // and as far past it as times go.
+    pub fn range(&self) -> ops::Range<GtfsTime> {
+        let start = self.from.unwrap_or(GtfsTime::from_seconds(0));
+        let end = self.to.map_or(GtfsTime::from_seconds(u32::MAX), |to| GtfsTime::from_seconds(to.seconds() + 1));
+        start..end
+    }
+}
+
+// parse_time reads a time given to a command, as HH:MM or HH:MM:SS, with hours of 24 or more for times after midnight
+// on the service day.
+pub fn parse_time(value: &str) -> Option<GtfsTime> {
+    value.parse::<GtfsTime>()
+        .or_else(|_| format!("{}:00", value).parse::<GtfsTime>())
+        .ok()
+}
+
+// parse_date reads a date given to a command, as YYYY-MM-DD.
+pub fn parse_date(value: &str) -> Option<chrono::NaiveDate> {
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+// date_argument reads an argument giving the date a command is about, e.g. date=2025-01-31. The date may also be
+// given alone, as commands took it before date= was introduced.
+pub fn date_argument(arg: &str) -> Option<chrono::NaiveDate> {
+    parse_date(arg.strip_prefix("date=").unwrap_or(arg))
+}
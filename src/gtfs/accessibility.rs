@@ -0,0 +1,50 @@
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::stops;
+use crate::gtfs::trips;
+use crate::gtfs::view::{ScheduleFilter, ScheduleView};
+
+// AccessibilityFilter selects the part of a schedule usable by wheelchair: the trips which accommodate a wheelchair
+// and the stops at which one can board. Applied to a view, it gives a ScheduleFilter, so that departures, direct trips
+// and listings of the filtered view only consider accessible trips and stops.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccessibilityFilter {
+    // include_unknown counts trips and stops which don't say whether they are accessible as accessible.
+    pub include_unknown: bool,
+}
+
+impl AccessibilityFilter {
+    // trip_is_accessible reports whether a trip can accommodate at least one wheelchair.
+    pub fn trip_is_accessible(&self, trip: &trips::Trip) -> bool {
+        trip.wheelchair_accessible.unwrap_or(self.include_unknown)
+    }
+
+    // stop_is_accessible reports whether wheelchair boarding is possible at a stop. A stop within a station which
+    // doesn't say inherits its station's wheelchair_boarding, as the GTFS reference specifies.
+    // Stations are taken at their word rather than checked for an accessible path through their pathways.
+    pub fn stop_is_accessible(&self, schedule: &GtfsSchedule, stop: &stops::Stop) -> bool {
+        stop.wheelchair_boarding
+            .or_else(|| stop.parent_station()
+                .and_then(|parent_station| schedule.stops.stops.get(parent_station))
+                .and_then(|parent| parent.wheelchair_boarding))
+            .unwrap_or(self.include_unknown)
+    }
+
+    // apply selects the accessible trips and stops of a view, along with the routes which have an accessible trip.
+    pub fn apply(&self, view: &ScheduleView) -> ScheduleFilter {
+        let trip_ids = view.trips()
+            .filter(|trip| self.trip_is_accessible(trip))
+            .map(|trip| trip.trip_id.clone())
+            .collect::<std::collections::HashSet<_>>();
+        ScheduleFilter {
+            stop_ids: view.stops()
+                .filter(|stop| self.stop_is_accessible(view.schedule, stop))
+                .map(|stop| stop.stop_id.clone())
+                .collect(),
+            route_ids: view.routes()
+                .filter(|route| view.trips_of_route(&route.route_id).any(|trip| trip_ids.contains(&trip.trip_id)))
+                .map(|route| route.route_id.clone())
+                .collect(),
+            trip_ids,
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use chrono_tz::Tz;
+use csv;
+use crate::gtfs::error::CsvError;
+use crate::gtfs::de;
+use crate::gtfs::routes::Route;
+use crate::gtfs::table;
+use serde::Deserialize;
+use std::io;
+use std::iter;
+use std::collections;
+
+// Agencies is a collection of agencies, indexed by agency_id.
+// agency_id is optional for single-agency feeds, in which case the agency is indexed by the empty string.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Agencies {
+    pub agencies: std::collections::HashMap<String, Agency>
+}
+
+impl<'a> iter::IntoIterator for &'a Agencies {
+    type Item = &'a Agency;
+    type IntoIter = std::collections::hash_map::Values<'a, String, Agency>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.agencies.values()
+    }
+}
+
+impl iter::IntoIterator for Agencies {
+    type Item = Agency;
+    type IntoIter = std::collections::hash_map::IntoValues<String, Agency>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.agencies.into_values()
+    }
+}
+
+impl Agencies {
+    // with_id_prefix prepends prefix to every agency ID.
+    pub fn with_id_prefix(self, prefix: &str) -> Self {
+        Agencies {
+            agencies: self.agencies.into_values()
+                .map(|mut agency| {
+                    let agency_id = format!("{}{}", prefix, agency.agency_id.as_deref().unwrap_or_default());
+                    agency.agency_id = Some(agency_id.clone());
+                    (agency_id, agency)
+                })
+                .collect()
+        }
+    }
+}
+
+// Agencies implements FromIterator<Agency> so that it can be assembled in memory, indexing each agency by its ID.
+impl iter::FromIterator<Agency> for Agencies {
+    fn from_iter<I: IntoIterator<Item = Agency>>(iter: I) -> Self {
+        Agencies {
+            agencies: iter.into_iter().map(|agency| (agency.agency_id.clone().unwrap_or_default(), agency)).collect()
+        }
+    }
+}
+
+// Agencies implements TryFrom<csv::Reader<R>> by loading the reader's contents as a GtfsTable.
+impl<R: io::Read> TryFrom<csv::Reader<R>> for Agencies {
+    type Error = CsvError;
+
+    fn try_from(r: csv::Reader<R>) -> Result<Self, Self::Error> {
+        table::load(r)
+    }
+}
+
+// Agencies holds the records of agency.txt, indexed by agency_id, which is empty for a feed's only agency if it has none.
+impl table::GtfsTable for Agencies {
+    type Record = Agency;
+
+    const FILE_NAME: &'static str = "agency.txt";
+
+    fn key(agency: &Agency) -> String {
+        agency.agency_id.clone().unwrap_or_default()
+    }
+
+    fn insert(&mut self, agency_id: String, agency: Agency) {
+        self.agencies.insert(agency_id, agency);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Agency {
+    #[serde(default, deserialize_with = "de::optional")]
+    pub agency_id: Option<String>,
+    #[serde(deserialize_with = "de::required")]
+    pub agency_name: String,
+    #[serde(deserialize_with = "de::required")]
+    pub agency_url: String,
+    #[serde(deserialize_with = "de::required")]
+    pub agency_timezone: Tz,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub agency_lang: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub agency_phone: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub agency_fare_url: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub agency_email: Option<String>,
+}
+
+// the fields of agency.txt, in the order they are listed by the GTFS reference.
+pub const AGENCY_FIELDS: [&str; 8] = [
+    "agency_id", "agency_name", "agency_url", "agency_timezone", "agency_lang", "agency_phone",
+    "agency_fare_url", "agency_email",
+];
+
+// collections::HashMap<String, String> implements From<&Agency> by encoding each field as it would appear in agency.txt.
+impl From<&Agency> for collections::HashMap<String, String> {
+    fn from(agency: &Agency) -> Self {
+        [
+            ("agency_id", agency.agency_id.clone()),
+            ("agency_name", Some(agency.agency_name.clone())),
+            ("agency_url", Some(agency.agency_url.clone())),
+            ("agency_timezone", Some(agency.agency_timezone.name().to_string())),
+            ("agency_lang", agency.agency_lang.clone()),
+            ("agency_phone", agency.agency_phone.clone()),
+            ("agency_fare_url", agency.agency_fare_url.clone()),
+            ("agency_email", agency.agency_email.clone()),
+        ]
+            .into_iter()
+            .filter_map(|(field, value)| value.map(|value| (field.to_string(), value)))
+            .collect()
+    }
+}
+
+impl Agencies {
+    // agency_of gives the agency running a route: the one with the route's agency_id, or, for a route without one,
+    // the feed's only agency, since the GTFS reference only lets agency_id be left out when there's just one.
+    pub fn agency_of(&self, route: &Route) -> Option<&Agency> {
+        match &route.agency_id {
+            Some(agency_id) => self.agencies.get(agency_id),
+            None if self.agencies.len() == 1 => self.agencies.values().next(),
+            None => None,
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::gtfs::view::ScheduleView;
+use std::collections;
+
+// Corridor is a run of consecutive stops which two or more routes all serve in the same order, such as a trunk
+// section shared by several bus routes before they branch apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Corridor {
+    // stop_ids lists the corridor's stops in the order the routes visit them; there are at least two.
+    pub stop_ids: Vec<String>,
+    // route_ids lists the routes running the whole length of the corridor, sorted by ID.
+    pub route_ids: Vec<String>,
+}
+
+// shared_corridors finds the corridors of the routes in the view. Each is maximal: it can't be extended at either end
+// without losing one of its routes, so a corridor shared by three routes may lie within a longer one shared by two.
+// Corridors are found from journey patterns, so each direction of travel gives its own corridor.
+// They are sorted with those shared by the most routes first, then the longest first.
+pub fn shared_corridors(view: &ScheduleView) -> Vec<Corridor> {
+    let patterns = view.routes()
+        .flat_map(|route| view.patterns_of_route(&route.route_id))
+        .map(|(pattern, _)| pattern)
+        .collect::<Vec<_>>();
+
+    // the routes travelling directly from one stop to the next.
+    let mut routes_by_edge = collections::HashMap::<(&str, &str), collections::BTreeSet<&str>>::new();
+    for pattern in &patterns {
+        for pair in pattern.stop_ids.windows(2) {
+            routes_by_edge.entry((pair[0].as_str(), pair[1].as_str())).or_default().insert(pattern.route_id.as_str());
+        }
+    }
+
+    // the routes sharing a corridor are those sharing every edge along it, so the candidates for a pattern's corridors
+    // are the sets of routes common to each run of its edges, as long as more than one route remains.
+    let mut corridors = collections::BTreeSet::<(Vec<&str>, Vec<&str>)>::new();
+    for pattern in &patterns {
+        let edges = pattern.stop_ids.windows(2)
+            .map(|pair| &routes_by_edge[&(pair[0].as_str(), pair[1].as_str())])
+            .collect::<Vec<_>>();
+        let mut route_sets = collections::BTreeSet::new();
+        for start in 0..edges.len() {
+            let mut common = edges[start].clone();
+            for routes in &edges[start..] {
+                common = common.intersection(routes).copied().collect();
+                if common.len() < 2 {
+                    break;
+                }
+                route_sets.insert(common.clone());
+            }
+        }
+        for route_set in route_sets {
+            // a run shares route_set if every edge in it is used by all of route_set's routes.
+            let mut start = None;
+            for (position, routes) in edges.iter().map(Some).chain([None]).enumerate() {
+                let shared = routes.is_some_and(|routes| route_set.is_subset(routes));
+                match (shared, start) {
+                    (true, None) => start = Some(position),
+                    (false, Some(run_start)) => {
+                        corridors.insert((
+                            pattern.stop_ids[run_start..=position].iter().map(String::as_str).collect(),
+                            route_set.iter().copied().collect(),
+                        ));
+                        start = None;
+                    },
+                    _ => {},
+                }
+            }
+        }
+    }
+
+    // a run found from one route's pattern may be only part of the same routes' corridor found from another's,
+    // and a run may be shared by more routes than the set it was found for.
+    let mut corridors = corridors.iter()
+        .filter(|(stop_ids, route_ids)| !corridors.iter().any(|(other_stop_ids, other_route_ids)| {
+            let longer = other_route_ids == route_ids
+                && other_stop_ids.len() > stop_ids.len()
+                && other_stop_ids.windows(stop_ids.len()).any(|window| window == stop_ids.as_slice());
+            let busier = other_stop_ids == stop_ids
+                && other_route_ids.len() > route_ids.len()
+                && route_ids.iter().all(|route_id| other_route_ids.contains(route_id));
+            longer || busier
+        }))
+        .map(|(stop_ids, route_ids)| Corridor {
+            stop_ids: stop_ids.iter().map(|stop_id| stop_id.to_string()).collect(),
+            route_ids: route_ids.iter().map(|route_id| route_id.to_string()).collect(),
+        })
+        .collect::<Vec<_>>();
+    corridors.sort_by(|a, b| b.route_ids.len().cmp(&a.route_ids.len())
+        .then_with(|| b.stop_ids.len().cmp(&a.stop_ids.len()))
+        .then_with(|| a.stop_ids.cmp(&b.stop_ids))
+        .then_with(|| a.route_ids.cmp(&b.route_ids)));
+    corridors
+}
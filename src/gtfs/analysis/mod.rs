@@ -0,0 +1,48 @@
+use crate::gtfs::stop_times;
+use crate::gtfs::trips;
+use crate::gtfs::view::ScheduleView;
+
+pub mod corridors;
+pub mod speed;
+pub mod stats;
+pub mod travel_times;
+pub mod variants;
+
+// frequency_profile counts the trips of a route in the view which run on date by the hour of the service day they start,
+// for charting how often the route runs. There are 24 hours, or more if trips start after midnight.
+// Trips listed in frequencies.txt are counted each time they start.
+pub fn frequency_profile(view: &ScheduleView, route_id: &str, date: chrono::NaiveDate) -> Vec<usize> {
+    let mut trips_per_hour = hour_histogram(view.trips_of_route(route_id)
+        .filter(|trip| view.schedule.service_runs_on(&trip.service_id, date))
+        .flat_map(|trip| start_times(view, trip)));
+    if trips_per_hour.len() < 24 {
+        trips_per_hour.resize(24, 0);
+    }
+    trips_per_hour
+}
+
+// start_times lists the times a trip in the view starts: once for each start time of its frequencies if it has any,
+// or else at its first stop time.
+fn start_times(view: &ScheduleView, trip: &trips::Trip) -> Vec<stop_times::GtfsTime> {
+    match view.schedule.frequencies.frequencies.get(&trip.trip_id) {
+        Some(frequencies) => frequencies.iter().flat_map(|frequency| frequency.start_times()).collect(),
+        None => view.stop_times_of_trip(&trip.trip_id)
+            .min_by_key(|stop_time| stop_time.stop_sequence)
+            .and_then(|stop_time| stop_time.departure_time.or(stop_time.arrival_time))
+            .into_iter()
+            .collect(),
+    }
+}
+
+// hour_histogram counts times by the hour they fall in, up to the last hour with any.
+fn hour_histogram(times: impl Iterator<Item = stop_times::GtfsTime>) -> Vec<usize> {
+    let mut counts = Vec::new();
+    for time in times {
+        let hour = (time.seconds() / 3600) as usize;
+        if counts.len() <= hour {
+            counts.resize(hour + 1, 0);
+        }
+        counts[hour] += 1;
+    }
+    counts
+}
@@ -0,0 +1,153 @@
+use crate::gtfs::routes;
+use crate::gtfs::spatial;
+use crate::gtfs::stop_times;
+use crate::gtfs::view::ScheduleView;
+use std::time;
+
+// TripSpeed is the distance a trip covers between its first and last timed stops, the time it takes,
+// and the speed of each segment between consecutive timed stops along the way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TripSpeed {
+    pub trip_id: String,
+    // distance is in meters.
+    pub distance: f64,
+    pub duration: time::Duration,
+    pub segments: Vec<SegmentSpeed>,
+}
+
+// SegmentSpeed is the speed a trip is scheduled to travel at between two consecutive stops which have times.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentSpeed {
+    pub from_stop_id: String,
+    pub to_stop_id: String,
+    // distance is in meters.
+    pub distance: f64,
+    // duration runs from departing the first stop to arriving at the second.
+    pub duration: time::Duration,
+    // implausible marks segments which the trip can't really travel as scheduled: faster than its route type allows,
+    // covering distance in no time at all, or going backwards along its shape.
+    pub implausible: bool,
+}
+
+impl SegmentSpeed {
+    // speed returns the segment's average speed in meters per second, or None if it takes no time.
+    pub fn speed(&self) -> Option<f64> {
+        (!self.duration.is_zero()).then(|| self.distance / self.duration.as_secs_f64())
+    }
+}
+
+impl TripSpeed {
+    // compute measures the speed of a trip in the view. Distances come from shape_dist_traveled where the trip's stop
+    // times and shape both give it, converted to meters by comparing the shape's shape_dist_traveled with its length,
+    // since feeds may use any unit. Otherwise they are measured in straight lines between stops.
+    // None is returned if the trip isn't in the view or has fewer than two stops with times and locations.
+    pub fn compute(view: &ScheduleView, trip_id: &str) -> Option<Self> {
+        let trip = view.trip(trip_id)?;
+        let max_speed = view.schedule.routes.routes.get(&trip.route_id).map_or(f64::INFINITY, |route| max_plausible_speed(&route.route_type));
+        let meters_per_unit = trip.shape_id.as_deref().and_then(|shape_id| {
+            let shape_distance = view.schedule.shapes.shapes.get(shape_id)?
+                .iter()
+                .filter_map(|point| point.shape_dist_traveled)
+                .fold(None, |max: Option<f64>, distance| Some(max.map_or(distance, |max| max.max(distance))))
+                .filter(|distance| *distance > 0.0)?;
+            Some(view.schedule.shape_polyline(shape_id)?.length() / shape_distance)
+        });
+
+        let mut trip_stop_times = view.stop_times_of_trip(trip_id).collect::<Vec<_>>();
+        trip_stop_times.sort_by_key(|stop_time| stop_time.stop_sequence);
+        let timed_stops = trip_stop_times.into_iter()
+            .filter_map(|stop_time| {
+                let stop = view.schedule.stops.stops.get(stop_time.stop_id.as_deref()?)?;
+                Some((stop_time, stop.stop_lat()?, stop.stop_lon()?))
+            })
+            .filter(|(stop_time, _, _)| stop_time.arrival_time.or(stop_time.departure_time).is_some())
+            .collect::<Vec<_>>();
+        if timed_stops.len() < 2 {
+            return None;
+        }
+        // shape_dist_traveled is only used if every timed stop gives it, so that distances aren't measured two ways at once.
+        let use_shape_distance = meters_per_unit.is_some() && timed_stops.iter().all(|(stop_time, _, _)| stop_time.shape_dist_traveled.is_some());
+
+        let segments = timed_stops.windows(2)
+            .map(|pair| {
+                let ((from, from_lat, from_lon), (to, to_lat, to_lon)) = (pair[0], pair[1]);
+                let distance = match (use_shape_distance, meters_per_unit, from.shape_dist_traveled, to.shape_dist_traveled) {
+                    (true, Some(meters_per_unit), Some(from_distance), Some(to_distance)) => (to_distance - from_distance) * meters_per_unit,
+                    _ => spatial::haversine(from_lat, from_lon, to_lat, to_lon),
+                };
+                let duration = time::Duration::from_secs(
+                    arrival(to).seconds().saturating_sub(departure(from).seconds()) as u64
+                );
+                let speed = distance / duration.as_secs_f64();
+                SegmentSpeed {
+                    from_stop_id: from.stop_id.clone().unwrap_or_default(),
+                    to_stop_id: to.stop_id.clone().unwrap_or_default(),
+                    distance,
+                    duration,
+                    implausible: distance < 0.0 || (distance > 0.0 && duration.is_zero()) || speed > max_speed,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let (first, last) = (timed_stops[0].0, timed_stops[timed_stops.len() - 1].0);
+        Some(TripSpeed {
+            trip_id: trip.trip_id.clone(),
+            distance: segments.iter().map(|segment| segment.distance).sum(),
+            duration: time::Duration::from_secs(arrival(last).seconds().saturating_sub(departure(first).seconds()) as u64),
+            segments,
+        })
+    }
+
+    // average_speed returns the trip's average speed in meters per second, including the time it waits at stops,
+    // or None if it takes no time.
+    pub fn average_speed(&self) -> Option<f64> {
+        (!self.duration.is_zero()).then(|| self.distance / self.duration.as_secs_f64())
+    }
+
+    pub fn implausible_segments(&self) -> impl Iterator<Item = &SegmentSpeed> {
+        self.segments.iter().filter(|segment| segment.implausible)
+    }
+}
+
+// implausible_segments lists the segments of every trip in the view which can't be travelled as scheduled,
+// paired with the ID of their trip, for validators to report.
+pub fn implausible_segments(view: &ScheduleView) -> Vec<(String, SegmentSpeed)> {
+    let mut segments = view.trips()
+        .filter_map(|trip| TripSpeed::compute(view, &trip.trip_id))
+        .flat_map(|trip_speed| {
+            let trip_id = trip_speed.trip_id;
+            trip_speed.segments.into_iter()
+                .filter(|segment| segment.implausible)
+                .map(move |segment| (trip_id.clone(), segment))
+        })
+        .collect::<Vec<_>>();
+    segments.sort_by(|(a, _), (b, _)| a.cmp(b));
+    segments
+}
+
+// max_plausible_speed gives the fastest a vehicle of the route type can be expected to travel, in meters per second.
+// The limits are generous, since timetables are often rounded to the minute, which exaggerates speeds over short segments.
+pub fn max_plausible_speed(route_type: &routes::RouteType) -> f64 {
+    let kilometers_per_hour = match route_type {
+        routes::RouteType::TramStreetcarLightRail => 150.0,
+        routes::RouteType::SubwayMetro => 150.0,
+        routes::RouteType::Rail => 500.0,
+        routes::RouteType::Bus => 150.0,
+        routes::RouteType::Ferry => 80.0,
+        routes::RouteType::CableTram => 50.0,
+        routes::RouteType::AerialLift => 50.0,
+        routes::RouteType::Funicular => 50.0,
+        routes::RouteType::Trolleybus => 150.0,
+        routes::RouteType::Monorail => 150.0,
+    };
+    kilometers_per_hour / 3.6
+}
+
+// arrival and departure are only used on stop times with at least one of the two times.
+fn arrival(stop_time: &stop_times::StopTime) -> stop_times::GtfsTime {
+    stop_time.arrival_time.or(stop_time.departure_time).unwrap()
+}
+
+fn departure(stop_time: &stop_times::StopTime) -> stop_times::GtfsTime {
+    stop_time.departure_time.or(stop_time.arrival_time).unwrap()
+}
@@ -0,0 +1,116 @@
+use crate::gtfs::analysis;
+use crate::gtfs::view::ScheduleView;
+use std::collections;
+use std::time;
+
+// Stats summarizes how much service a schedule, or part of one, provides.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    pub stops: usize,
+    pub routes: usize,
+    pub trips: usize,
+    pub stop_times: usize,
+    // trips_per_stop lists the number of distinct trips visiting each stop, busiest first.
+    pub trips_per_stop: Vec<(String, usize)>,
+    // stop_times_per_route lists the number of stop times of each route's trips, busiest first.
+    pub stop_times_per_route: Vec<(String, usize)>,
+    // trips_per_hour counts the trips starting in each hour of the service day, which may run past 24.
+    // Trips listed in frequencies.txt are counted each time they start.
+    pub trips_per_hour: Vec<usize>,
+    // dwell_per_stop and dwell_per_route total the time trips wait at each stop, and at the stops of each route,
+    // longest first. Long dwells are often padding added to keep trips on schedule.
+    pub dwell_per_stop: Vec<(String, Dwell)>,
+    pub dwell_per_route: Vec<(String, Dwell)>,
+}
+
+// Dwell aggregates the time trips wait at stops between arriving and departing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Dwell {
+    pub total: time::Duration,
+    pub max: time::Duration,
+    // stop_times counts the stop times measured, including those which don't wait at all.
+    pub stop_times: usize,
+}
+
+impl Dwell {
+    pub fn mean(&self) -> time::Duration {
+        if self.stop_times == 0 {
+            time::Duration::ZERO
+        } else {
+            self.total / self.stop_times as u32
+        }
+    }
+
+    fn add(&mut self, dwell: time::Duration) {
+        self.total += dwell;
+        self.max = self.max.max(dwell);
+        self.stop_times += 1;
+    }
+}
+
+impl Stats {
+    // compute gathers statistics over the records in a view.
+    pub fn compute(view: &ScheduleView) -> Self {
+        let mut trips_per_stop = collections::HashMap::<&str, collections::HashSet<&str>>::new();
+        let mut stop_times_per_route = collections::HashMap::<&str, usize>::new();
+        let mut start_times = Vec::new();
+        let mut stop_times = 0;
+        let mut dwell_per_stop = collections::HashMap::<&str, Dwell>::new();
+        let mut dwell_per_route = collections::HashMap::<&str, Dwell>::new();
+
+        for trip in view.trips() {
+            let trip_stop_times = view.stop_times_of_trip(&trip.trip_id).collect::<Vec<_>>();
+            stop_times += trip_stop_times.len();
+            *stop_times_per_route.entry(trip.route_id.as_str()).or_default() += trip_stop_times.len();
+            for stop_id in trip_stop_times.iter().filter_map(|stop_time| stop_time.stop_id.as_deref()) {
+                trips_per_stop.entry(stop_id).or_default().insert(trip.trip_id.as_str());
+            }
+
+            for (stop_id, dwell) in dwell_times(view, &trip.trip_id) {
+                dwell_per_stop.entry(stop_id).or_default().add(dwell);
+                dwell_per_route.entry(trip.route_id.as_str()).or_default().add(dwell);
+            }
+
+            start_times.extend(analysis::start_times(view, trip));
+        }
+
+        Stats {
+            stops: view.stops().count(),
+            routes: view.routes().count(),
+            trips: view.trips().count(),
+            stop_times,
+            trips_per_stop: busiest_first(trips_per_stop.into_iter().map(|(stop_id, trip_ids)| (stop_id, trip_ids.len()))),
+            stop_times_per_route: busiest_first(stop_times_per_route.into_iter()),
+            trips_per_hour: analysis::hour_histogram(start_times.into_iter()),
+            dwell_per_stop: longest_first(dwell_per_stop),
+            dwell_per_route: longest_first(dwell_per_route),
+        }
+    }
+}
+
+// dwell_times lists how long a trip in the view waits at each of its stops, in order, leaving out stop times which
+// don't give both an arrival and a departure time.
+pub fn dwell_times<'a>(view: &ScheduleView<'a>, trip_id: &str) -> Vec<(&'a str, time::Duration)> {
+    let mut trip_stop_times = view.stop_times_of_trip(trip_id).collect::<Vec<_>>();
+    trip_stop_times.sort_by_key(|stop_time| stop_time.stop_sequence);
+    trip_stop_times.into_iter()
+        .filter_map(|stop_time| Some((
+            stop_time.stop_id.as_deref()?,
+            time::Duration::from_secs(stop_time.departure_time?.seconds().saturating_sub(stop_time.arrival_time?.seconds()) as u64),
+        )))
+        .collect()
+}
+
+// longest_first sorts dwells from the longest total to the shortest, breaking ties by ID so that the order is stable.
+fn longest_first(dwells: collections::HashMap<&str, Dwell>) -> Vec<(String, Dwell)> {
+    let mut dwells = dwells.into_iter().map(|(id, dwell)| (id.to_string(), dwell)).collect::<Vec<_>>();
+    dwells.sort_by(|(a_id, a), (b_id, b)| b.total.cmp(&a.total).then_with(|| a_id.cmp(b_id)));
+    dwells
+}
+
+// busiest_first sorts counts from highest to lowest, breaking ties by ID so that the order is stable.
+fn busiest_first<'a>(counts: impl Iterator<Item = (&'a str, usize)>) -> Vec<(String, usize)> {
+    let mut counts = counts.map(|(id, count)| (id.to_string(), count)).collect::<Vec<_>>();
+    counts.sort_by(|(a_id, a), (b_id, b)| b.cmp(a).then_with(|| a_id.cmp(b_id)));
+    counts
+}
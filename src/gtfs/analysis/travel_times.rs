@@ -0,0 +1,71 @@
+use crate::gtfs::stop_times;
+use crate::gtfs::view::ScheduleView;
+use std::time;
+
+// TravelTimes summarizes the scheduled travel times of a set of journeys between two stops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TravelTimes {
+    pub journeys: usize,
+    pub min: time::Duration,
+    pub median: time::Duration,
+    pub p90: time::Duration,
+}
+
+impl TravelTimes {
+    // from_durations summarizes a set of travel times, or returns None if there are none.
+    // Percentiles are taken by nearest rank, so each is one of the given travel times.
+    pub fn from_durations(mut durations: Vec<time::Duration>) -> Option<Self> {
+        durations.sort();
+        let percentile = |p: f64| durations[((p * durations.len() as f64).ceil() as usize).clamp(1, durations.len()) - 1];
+        Some(TravelTimes {
+            journeys: durations.len(),
+            min: *durations.first()?,
+            median: percentile(0.5),
+            p90: percentile(0.9),
+        })
+    }
+}
+
+// travel_times summarizes the scheduled travel times of the trips in the view running on date which can be ridden
+// from one stop to the other without changing. See journeys for how trips are counted.
+pub fn travel_times(view: &ScheduleView, from_stop_id: &str, to_stop_id: &str, date: chrono::NaiveDate) -> Option<TravelTimes> {
+    TravelTimes::from_durations(journeys(view, from_stop_id, to_stop_id, date).into_iter().map(|(_, duration)| duration).collect())
+}
+
+// travel_times_by_hour summarizes the same travel times as travel_times, grouped by the hour of the service day
+// in which each journey departs, so that times of day can be compared. Hours without journeys are left out.
+pub fn travel_times_by_hour(view: &ScheduleView, from_stop_id: &str, to_stop_id: &str, date: chrono::NaiveDate) -> Vec<(u32, TravelTimes)> {
+    let mut durations_by_hour = std::collections::BTreeMap::<u32, Vec<time::Duration>>::new();
+    for (departure_time, duration) in journeys(view, from_stop_id, to_stop_id, date) {
+        durations_by_hour.entry(departure_time.seconds() / 3600).or_default().push(duration);
+    }
+    durations_by_hour.into_iter()
+        .filter_map(|(hour, durations)| Some((hour, TravelTimes::from_durations(durations)?)))
+        .collect()
+}
+
+// journeys lists the departure time and travel time of each direct trip between the stops which runs on date.
+// Trips listed in frequencies.txt make a journey each time they start, departing at the same offset into the trip.
+fn journeys(view: &ScheduleView, from_stop_id: &str, to_stop_id: &str, date: chrono::NaiveDate) -> Vec<(stop_times::GtfsTime, time::Duration)> {
+    let mut journeys = Vec::new();
+    for direct_trip in view.direct_trips_between(from_stop_id, to_stop_id) {
+        if !view.schedule.service_runs_on(&direct_trip.trip.service_id, date) {
+            continue;
+        }
+        let travel_time = direct_trip.travel_time();
+        match view.schedule.frequencies.frequencies.get(&direct_trip.trip.trip_id) {
+            None => journeys.push((direct_trip.departure_time, travel_time)),
+            Some(frequencies) => {
+                let first_time = view.stop_times_of_trip(&direct_trip.trip.trip_id)
+                    .filter_map(|stop_time| stop_time.departure_time.or(stop_time.arrival_time))
+                    .min()
+                    .map_or(0, |time| time.seconds());
+                let offset = direct_trip.departure_time.seconds().saturating_sub(first_time);
+                for start_time in frequencies.iter().flat_map(|frequency| frequency.start_times()) {
+                    journeys.push((stop_times::GtfsTime::from_seconds(start_time.seconds() + offset), travel_time));
+                }
+            },
+        }
+    }
+    journeys
+}
@@ -0,0 +1,110 @@
+use crate::gtfs::patterns;
+use crate::gtfs::view::ScheduleView;
+use std::collections;
+use std::fmt;
+
+// ServiceVariant classifies a trip by how many of its route's usual stops it calls at, compared with the route's most
+// common journey pattern in the same direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ServiceVariant {
+    // Local trips call at every stop of the common pattern along the part of the route they run.
+    Local,
+    // Limited trips skip some of the stops between their first and last, but fewer than half.
+    Limited,
+    // Express trips skip at least half of the stops between their first and last.
+    Express,
+}
+
+impl ServiceVariant {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ServiceVariant::Local => "local",
+            ServiceVariant::Limited => "limited",
+            ServiceVariant::Express => "express",
+        }
+    }
+
+    // classify compares a pattern with the common pattern of its route and direction. Only the stretch of the common
+    // pattern between the first and last of its stops which the pattern visits is considered, so short turns and
+    // extensions beyond the common pattern's ends are local. A pattern sharing no more than one stop with the
+    // common pattern, such as one serving another branch, can't be compared and is also local.
+    pub fn classify(common: &patterns::Pattern, pattern: &patterns::Pattern) -> Self {
+        let visited = pattern.stop_ids.iter().map(String::as_str).collect::<collections::HashSet<_>>();
+        let shared = common.stop_ids.iter()
+            .enumerate()
+            .filter(|(_, stop_id)| visited.contains(stop_id.as_str()))
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+        let (Some(first), Some(last)) = (shared.first(), shared.last()) else {
+            return ServiceVariant::Local;
+        };
+        let span = last - first + 1;
+        let skipped = span - shared.len();
+        if shared.len() < 2 || skipped == 0 {
+            ServiceVariant::Local
+        } else if skipped * 2 < span - 2 {
+            ServiceVariant::Limited
+        } else {
+            ServiceVariant::Express
+        }
+    }
+}
+
+impl fmt::Display for ServiceVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// service_variant classifies a trip in the view against the most common pattern in the view of its route and direction.
+// None is returned if the trip isn't in the view or has no stops.
+pub fn service_variant(view: &ScheduleView, trip_id: &str) -> Option<ServiceVariant> {
+    let trip = view.trip(trip_id)?;
+    let pattern = view.schedule.patterns().of_trip(&trip.trip_id)?;
+    let (common, _) = view.patterns_of_route(&trip.route_id)
+        .into_iter()
+        .find(|(common, _)| common.direction_id == pattern.direction_id)?;
+    Some(ServiceVariant::classify(common, pattern))
+}
+
+// service_variants_of classifies the given trips in the view as service_variant does, finding the patterns of each of
+// their routes once rather than once per trip. Trips which aren't in the view or have no stops are left out.
+pub fn service_variants_of<'t>(view: &ScheduleView, trip_ids: impl IntoIterator<Item = &'t str>) -> collections::HashMap<String, ServiceVariant> {
+    let mut route_patterns = collections::HashMap::new();
+    let mut variants = collections::HashMap::new();
+    for trip_id in trip_ids {
+        let Some(trip) = view.trip(trip_id) else {
+            continue;
+        };
+        let Some(pattern) = view.schedule.patterns().of_trip(&trip.trip_id) else {
+            continue;
+        };
+        let common = route_patterns.entry(trip.route_id.as_str())
+            .or_insert_with(|| view.patterns_of_route(&trip.route_id))
+            .iter()
+            .find(|(common, _)| common.direction_id == pattern.direction_id);
+        if let Some((common, _)) = common {
+            variants.insert(trip.trip_id.clone(), ServiceVariant::classify(common, pattern));
+        }
+    }
+    variants
+}
+
+// service_variants classifies every trip in the view which has stops, comparing each pattern with its route's
+// common pattern once rather than once per trip.
+pub fn service_variants(view: &ScheduleView) -> collections::HashMap<String, ServiceVariant> {
+    let mut variants = collections::HashMap::new();
+    for route in view.routes() {
+        let route_patterns = view.patterns_of_route(&route.route_id);
+        for (pattern, _) in &route_patterns {
+            let Some((common, _)) = route_patterns.iter().find(|(common, _)| common.direction_id == pattern.direction_id) else {
+                continue;
+            };
+            let variant = ServiceVariant::classify(common, pattern);
+            for trip_id in pattern.trip_ids.iter().filter(|trip_id| view.trip(trip_id).is_some()) {
+                variants.insert(trip_id.clone(), variant);
+            }
+        }
+    }
+    variants
+}
@@ -0,0 +1,70 @@
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::stop_times;
+use crate::gtfs::trips;
+
+// BlockTrip is one of the trips run in turn by the vehicle operating a block.
+#[derive(Debug, Clone, Copy)]
+pub struct BlockTrip<'a> {
+    pub trip: &'a trips::Trip,
+    // first and last are the trip's first and last stop times.
+    pub first: &'a stop_times::StopTime,
+    pub last: &'a stop_times::StopTime,
+    pub start_time: stop_times::GtfsTime,
+    pub end_time: stop_times::GtfsTime,
+    // continues_as is the next trip in the block if riders can stay on board for it: it starts from the stop
+    // this trip ends at, or another stop in the same station, no earlier than this trip ends.
+    pub continues_as: Option<&'a trips::Trip>,
+}
+
+impl GtfsSchedule {
+    // block_chain lists the trips sharing a block_id which run on date, in the order the vehicle runs them.
+    // Trips without times at their first and last stops can't be placed in the chain and are left out.
+    pub fn block_chain(&self, block_id: &str, date: chrono::NaiveDate) -> Vec<BlockTrip<'_>> {
+        let mut chain = self.trips_of_block(block_id)
+            .filter(|trip| self.service_runs_on(&trip.service_id, date))
+            .filter_map(|trip| {
+                let trip_stop_times = self.stop_times.stop_times.get(&trip.trip_id)?;
+                let first = trip_stop_times.iter().min_by_key(|stop_time| stop_time.stop_sequence)?;
+                let last = trip_stop_times.iter().max_by_key(|stop_time| stop_time.stop_sequence)?;
+                Some(BlockTrip {
+                    trip,
+                    first,
+                    last,
+                    start_time: first.departure_time.or(first.arrival_time)?,
+                    end_time: last.arrival_time.or(last.departure_time)?,
+                    continues_as: None,
+                })
+            })
+            .collect::<Vec<_>>();
+        chain.sort_by(|a, b| a.start_time.cmp(&b.start_time).then_with(|| a.trip.trip_id.cmp(&b.trip.trip_id)));
+
+        for position in 1..chain.len() {
+            let (previous, next) = (chain[position - 1], chain[position]);
+            if next.start_time >= previous.end_time && self.same_place(previous.last.stop_id.as_deref(), next.first.stop_id.as_deref()) {
+                chain[position - 1].continues_as = Some(next.trip);
+            }
+        }
+        chain
+    }
+
+    // continues_as returns the trip a trip continues as on date, if it is through-run into another trip of its block.
+    pub fn continues_as(&self, trip_id: &str, date: chrono::NaiveDate) -> Option<&trips::Trip> {
+        let block_id = self.trips.trips.get(trip_id)?.block_id.as_deref()?;
+        self.block_chain(block_id, date)
+            .into_iter()
+            .find(|block_trip| block_trip.trip.trip_id == trip_id)?
+            .continues_as
+    }
+
+    // same_place reports whether two stops are the same, or are within the same station.
+    fn same_place(&self, a: Option<&str>, b: Option<&str>) -> bool {
+        let station = |stop_id: &str| self.stops.stops.get(stop_id)
+            .and_then(|stop| stop.parent_station())
+            .unwrap_or(stop_id)
+            .to_string();
+        match (a, b) {
+            (Some(a), Some(b)) => a == b || station(a) == station(b),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,438 @@
+use csv;
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::trips;
+use crate::gtfs::error::CsvError;
+use crate::gtfs::de;
+use crate::gtfs::table;
+use crate::gtfs::view::ScheduleView;
+use serde::Deserialize;
+use std::io;
+use std::iter;
+use std::collections;
+use std::str::FromStr;
+use chrono;
+
+// Calendar is the collection of weekly service patterns from calendar.txt, indexed by service_id.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Calendar {
+    pub services: std::collections::HashMap<String, Service>
+}
+
+impl<'a> iter::IntoIterator for &'a Calendar {
+    type Item = &'a Service;
+    type IntoIter = std::collections::hash_map::Values<'a, String, Service>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.services.values()
+    }
+}
+
+impl Calendar {
+    // with_id_prefix prepends prefix to every service ID.
+    pub fn with_id_prefix(self, prefix: &str) -> Self {
+        Calendar {
+            services: self.services.into_values()
+                .map(|mut service| {
+                    service.service_id.insert_str(0, prefix);
+                    (service.service_id.clone(), service)
+                })
+                .collect()
+        }
+    }
+}
+
+// Calendar implements FromIterator<Service> so that it can be assembled in memory, indexing each service by its ID.
+impl iter::FromIterator<Service> for Calendar {
+    fn from_iter<I: IntoIterator<Item = Service>>(iter: I) -> Self {
+        Calendar {
+            services: iter.into_iter().map(|service| (service.service_id.clone(), service)).collect()
+        }
+    }
+}
+
+// CalendarDates is the collection of service exceptions from calendar_dates.txt, indexed by service_id.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CalendarDates {
+    pub calendar_dates: std::collections::HashMap<String, Vec<CalendarDate>>
+}
+
+impl CalendarDates {
+    pub fn iter(&self) -> impl Iterator<Item = &CalendarDate> {
+        self.calendar_dates.values().flatten()
+    }
+
+    // exception returns the exception made to the given service on the given date, if there is one.
+    pub fn exception(&self, service_id: &str, date: chrono::NaiveDate) -> Option<&ExceptionType> {
+        self.calendar_dates.get(service_id)?
+            .iter()
+            .find(|calendar_date| calendar_date.date == date)
+            .map(|calendar_date| &calendar_date.exception_type)
+    }
+
+    // with_id_prefix prepends prefix to every service ID.
+    pub fn with_id_prefix(self, prefix: &str) -> Self {
+        CalendarDates {
+            calendar_dates: self.calendar_dates.into_iter()
+                .map(|(service_id, mut calendar_dates)| {
+                    calendar_dates.iter_mut().for_each(|calendar_date| calendar_date.service_id.insert_str(0, prefix));
+                    (format!("{}{}", prefix, service_id), calendar_dates)
+                })
+                .collect()
+        }
+    }
+}
+
+// CalendarDates implements FromIterator<CalendarDate> so that it can be assembled in memory, grouping records by service_id in iteration order.
+impl iter::FromIterator<CalendarDate> for CalendarDates {
+    fn from_iter<I: IntoIterator<Item = CalendarDate>>(iter: I) -> Self {
+        let mut calendar_dates = collections::HashMap::new();
+        for calendar_date in iter {
+            calendar_dates.entry(calendar_date.service_id.clone()).or_insert(Vec::new()).push(calendar_date);
+        }
+        CalendarDates { calendar_dates }
+    }
+}
+
+// Calendar implements TryFrom<csv::Reader<R>> by loading the reader's contents as a GtfsTable.
+impl<R: io::Read> TryFrom<csv::Reader<R>> for Calendar {
+    type Error = CsvError;
+
+    fn try_from(r: csv::Reader<R>) -> Result<Self, Self::Error> {
+        table::load(r)
+    }
+}
+
+// Calendar holds the records of calendar.txt, indexed by service_id.
+impl table::GtfsTable for Calendar {
+    type Record = Service;
+
+    const FILE_NAME: &'static str = "calendar.txt";
+
+    fn key(service: &Service) -> String {
+        service.service_id.clone()
+    }
+
+    fn insert(&mut self, service_id: String, service: Service) {
+        self.services.insert(service_id, service);
+    }
+}
+
+// CalendarDates implements TryFrom<csv::Reader<R>> by loading the reader's contents as a GtfsTable.
+impl<R: io::Read> TryFrom<csv::Reader<R>> for CalendarDates {
+    type Error = CsvError;
+
+    fn try_from(r: csv::Reader<R>) -> Result<Self, Self::Error> {
+        table::load(r)
+    }
+}
+
+// CalendarDates holds the records of calendar_dates.txt, grouped by service_id.
+impl table::GtfsTable for CalendarDates {
+    type Record = CalendarDate;
+
+    const FILE_NAME: &'static str = "calendar_dates.txt";
+
+    fn key(calendar_date: &CalendarDate) -> String {
+        calendar_date.service_id.clone()
+    }
+
+    fn insert(&mut self, service_id: String, calendar_date: CalendarDate) {
+        self.calendar_dates.entry(service_id).or_default().push(calendar_date);
+    }
+}
+
+impl GtfsSchedule {
+    // service_runs_on reports whether the given service runs on the given date. An exception in calendar_dates.txt
+    // takes precedence over the weekly pattern in calendar.txt, and services which only appear in calendar_dates.txt
+    // run only on the dates they are added.
+    pub fn service_runs_on(&self, service_id: &str, date: chrono::NaiveDate) -> bool {
+        match self.calendar_dates.exception(service_id, date) {
+            Some(ExceptionType::Added) => true,
+            Some(ExceptionType::Removed) => false,
+            None => self.calendar.services.get(service_id).is_some_and(|service| service.runs_on(date)),
+        }
+    }
+
+    // service_ids returns the IDs of every service defined in calendar.txt or calendar_dates.txt, in order.
+    pub fn service_ids(&self) -> collections::BTreeSet<&str> {
+        self.calendar.services.keys()
+            .chain(self.calendar_dates.calendar_dates.keys())
+            .map(String::as_str)
+            .collect()
+    }
+
+    // services_on returns the IDs of the services which run on the given date, from calendar.txt and calendar_dates.txt.
+    pub fn services_on(&self, date: chrono::NaiveDate) -> collections::BTreeSet<&str> {
+        self.service_ids()
+            .into_iter()
+            .filter(|service_id| self.service_runs_on(service_id, date))
+            .collect()
+    }
+
+    // trips_on yields the trips whose service runs on the given date.
+    pub fn trips_on(&self, date: chrono::NaiveDate) -> impl Iterator<Item = &trips::Trip> {
+        self.services_on(date)
+            .into_iter()
+            .flat_map(move |service_id| self.trips_of_service(service_id))
+    }
+
+    // service_dates returns every date on which the given service runs, in order: the dates of its weekly pattern
+    // between start_date and end_date which aren't removed, along with those added in calendar_dates.txt.
+    pub fn service_dates(&self, service_id: &str) -> collections::BTreeSet<chrono::NaiveDate> {
+        let pattern_dates = self.calendar.services.get(service_id)
+            .into_iter()
+            .flat_map(|service| service.start_date.iter_days().take_while(|date| *date <= service.end_date));
+        let added_dates = self.calendar_dates.calendar_dates.get(service_id)
+            .into_iter()
+            .flatten()
+            .map(|calendar_date| calendar_date.date);
+        pattern_dates.chain(added_dates)
+            .filter(|date| self.service_runs_on(service_id, *date))
+            .collect()
+    }
+
+    // service_days_at_stop summarizes the days on which trips serve a stop. See ScheduleView::service_days_at_stop.
+    pub fn service_days_at_stop(&self, stop_id: &str) -> ServiceDays {
+        self.view().service_days_at_stop(stop_id)
+    }
+}
+
+// ServiceDays summarizes the days on which something, such as a stop, has service.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServiceDays {
+    // weekdays lists the days of the week with service on at least one date, from Monday to Sunday.
+    pub weekdays: Vec<chrono::Weekday>,
+    // date_ranges lists the periods with service, in order, as inclusive (first, last) pairs. Dates less than a week
+    // apart fall in the same period, so that a weekly pattern reads as one period rather than a period per week.
+    pub date_ranges: Vec<(chrono::NaiveDate, chrono::NaiveDate)>,
+    pub weekday: DayTypeService,
+    pub saturday: DayTypeService,
+    pub sunday: DayTypeService,
+}
+
+// DayTypeService counts the service on one type of day: Mondays to Fridays, Saturdays or Sundays.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DayTypeService {
+    // dates is the number of dates of this type with service.
+    pub dates: usize,
+    // trips is the number of trips run over all of those dates together.
+    pub trips: usize,
+}
+
+impl DayTypeService {
+    // trips_per_date returns the average number of trips on a date with service, or None if there are no such dates.
+    pub fn trips_per_date(&self) -> Option<f64> {
+        (self.dates > 0).then(|| self.trips as f64 / self.dates as f64)
+    }
+}
+
+impl<'a> ScheduleView<'a> {
+    // service_days_at_stop summarizes the days on which trips in the view serve a stop, or the stops within it if it's
+    // a station: the days of the week and periods with service, and how many dates and trips each type of day has.
+    // A trip serving the stop more than once counts once, while trips listed in frequencies.txt count once for
+    // every time they start.
+    pub fn service_days_at_stop(&self, stop_id: &str) -> ServiceDays {
+        // the number of trips of each service which serve the stop.
+        let mut trips_by_service = collections::HashMap::<&str, usize>::new();
+        let trip_ids = self.stops_within(stop_id)
+            .into_iter()
+            .flat_map(|stop_id| self.stop_times_at(stop_id))
+            .map(|stop_time| stop_time.trip_id.as_str())
+            .collect::<collections::HashSet<_>>();
+        for trip in trip_ids.into_iter().filter_map(|trip_id| self.trip(trip_id)) {
+            let starts = self.schedule.frequencies.frequencies.get(&trip.trip_id)
+                .map_or(1, |frequencies| frequencies.iter().map(|frequency| frequency.start_times().count()).sum());
+            *trips_by_service.entry(trip.service_id.as_str()).or_default() += starts;
+        }
+
+        let mut trips_by_date = collections::BTreeMap::<chrono::NaiveDate, usize>::new();
+        for (service_id, trips) in trips_by_service {
+            for date in self.schedule.service_dates(service_id) {
+                *trips_by_date.entry(date).or_default() += trips;
+            }
+        }
+
+        let mut service_days = ServiceDays::default();
+        let mut weekdays = [false; 7];
+        for (date, trips) in &trips_by_date {
+            let weekday = chrono::Datelike::weekday(date);
+            weekdays[weekday.num_days_from_monday() as usize] = true;
+            let day_type = match weekday {
+                chrono::Weekday::Sat => &mut service_days.saturday,
+                chrono::Weekday::Sun => &mut service_days.sunday,
+                _ => &mut service_days.weekday,
+            };
+            day_type.dates += 1;
+            day_type.trips += trips;
+
+            match service_days.date_ranges.last_mut() {
+                Some((_, last)) if (*date - *last).num_days() < 7 => *last = *date,
+                _ => service_days.date_ranges.push((*date, *date)),
+            }
+        }
+        service_days.weekdays = [
+            chrono::Weekday::Mon, chrono::Weekday::Tue, chrono::Weekday::Wed, chrono::Weekday::Thu,
+            chrono::Weekday::Fri, chrono::Weekday::Sat, chrono::Weekday::Sun,
+        ]
+            .into_iter()
+            .filter(|weekday| weekdays[weekday.num_days_from_monday() as usize])
+            .collect();
+        service_days
+    }
+}
+
+// Service is a weekly service pattern, valid between start_date and end_date inclusive.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Service {
+    #[serde(deserialize_with = "de::required")]
+    pub service_id: String,
+    #[serde(deserialize_with = "day")]
+    pub monday: bool,
+    #[serde(deserialize_with = "day")]
+    pub tuesday: bool,
+    #[serde(deserialize_with = "day")]
+    pub wednesday: bool,
+    #[serde(deserialize_with = "day")]
+    pub thursday: bool,
+    #[serde(deserialize_with = "day")]
+    pub friday: bool,
+    #[serde(deserialize_with = "day")]
+    pub saturday: bool,
+    #[serde(deserialize_with = "day")]
+    pub sunday: bool,
+    #[serde(deserialize_with = "date")]
+    pub start_date: chrono::NaiveDate,
+    #[serde(deserialize_with = "date")]
+    pub end_date: chrono::NaiveDate,
+}
+
+impl Service {
+    // runs_on reports whether the weekly pattern includes the given date, ignoring any exceptions in calendar_dates.txt.
+    pub fn runs_on(&self, date: chrono::NaiveDate) -> bool {
+        self.start_date <= date && date <= self.end_date && self.runs_on_weekday(chrono::Datelike::weekday(&date))
+    }
+
+    // weekdays returns the days of the week the weekly pattern includes, from Monday.
+    pub fn weekdays(&self) -> Vec<chrono::Weekday> {
+        [
+            chrono::Weekday::Mon, chrono::Weekday::Tue, chrono::Weekday::Wed, chrono::Weekday::Thu,
+            chrono::Weekday::Fri, chrono::Weekday::Sat, chrono::Weekday::Sun,
+        ]
+            .into_iter()
+            .filter(|weekday| self.runs_on_weekday(*weekday))
+            .collect()
+    }
+
+    // runs_on_weekday reports whether the weekly pattern includes the given day of the week.
+    pub fn runs_on_weekday(&self, weekday: chrono::Weekday) -> bool {
+        match weekday {
+            chrono::Weekday::Mon => self.monday,
+            chrono::Weekday::Tue => self.tuesday,
+            chrono::Weekday::Wed => self.wednesday,
+            chrono::Weekday::Thu => self.thursday,
+            chrono::Weekday::Fri => self.friday,
+            chrono::Weekday::Sat => self.saturday,
+            chrono::Weekday::Sun => self.sunday,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CalendarDate {
+    #[serde(deserialize_with = "de::required")]
+    pub service_id: String,
+    #[serde(deserialize_with = "date")]
+    pub date: chrono::NaiveDate,
+    #[serde(deserialize_with = "de::required")]
+    pub exception_type: ExceptionType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExceptionType {
+    Added,
+    Removed,
+}
+
+impl ExceptionType {
+    // code returns the value used to encode this exception type in GTFS files.
+    pub fn code(&self) -> u8 {
+        match self {
+            ExceptionType::Added => 1,
+            ExceptionType::Removed => 2,
+        }
+    }
+}
+
+impl FromStr for ExceptionType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1" => Ok(ExceptionType::Added),
+            "2" => Ok(ExceptionType::Removed),
+            _ => Err(format!("invalid exception_type '{}'", s))
+        }
+    }
+}
+
+// the fields of calendar.txt, in the order they are listed by the GTFS reference.
+pub const SERVICE_FIELDS: [&str; 10] = [
+    "service_id", "monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday",
+    "start_date", "end_date",
+];
+
+// the fields of calendar_dates.txt, in the order they are listed by the GTFS reference.
+pub const CALENDAR_DATE_FIELDS: [&str; 3] = ["service_id", "date", "exception_type"];
+
+// collections::HashMap<String, String> implements From<&Service> by encoding each field as it would appear in calendar.txt.
+impl From<&Service> for collections::HashMap<String, String> {
+    fn from(service: &Service) -> Self {
+        let day = |runs: bool| if runs { "1" } else { "0" }.to_string();
+        collections::HashMap::from([
+            ("service_id".to_string(), service.service_id.clone()),
+            ("monday".to_string(), day(service.monday)),
+            ("tuesday".to_string(), day(service.tuesday)),
+            ("wednesday".to_string(), day(service.wednesday)),
+            ("thursday".to_string(), day(service.thursday)),
+            ("friday".to_string(), day(service.friday)),
+            ("saturday".to_string(), day(service.saturday)),
+            ("sunday".to_string(), day(service.sunday)),
+            ("start_date".to_string(), format_date(service.start_date)),
+            ("end_date".to_string(), format_date(service.end_date)),
+        ])
+    }
+}
+
+// collections::HashMap<String, String> implements From<&CalendarDate> by encoding each field as it would appear in calendar_dates.txt.
+impl From<&CalendarDate> for collections::HashMap<String, String> {
+    fn from(calendar_date: &CalendarDate) -> Self {
+        collections::HashMap::from([
+            ("service_id".to_string(), calendar_date.service_id.clone()),
+            ("date".to_string(), format_date(calendar_date.date)),
+            ("exception_type".to_string(), calendar_date.exception_type.code().to_string()),
+        ])
+    }
+}
+
+// GTFS dates are encoded as YYYYMMDD.
+pub fn parse_date(s: &str) -> Result<chrono::NaiveDate, chrono::ParseError> {
+    chrono::NaiveDate::parse_from_str(s, "%Y%m%d")
+}
+
+// date reads a required date field.
+fn date<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<chrono::NaiveDate, D::Error> {
+    parse_date(de::required_str(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+// day reads one of calendar.txt's day fields, which is 1 if the service runs on that day of the week and 0 if not.
+fn day<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    match de::required_str(deserializer)? {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        s => Err(serde::de::Error::custom(format!("invalid value '{}'", s))),
+    }
+}
+
+pub fn format_date(date: chrono::NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
@@ -0,0 +1,181 @@
+use curl::easy::{Easy, List};
+use std::fmt;
+
+// the Mobility Database API, see https://mobilitydata.github.io/mobility-feed-api/
+pub const MOBILITY_DATABASE_API: &str = "https://api.mobilitydatabase.org/v1";
+
+// MobilityDatabase looks feeds up in the Mobility Database catalog.
+// The API requires an access token, which is obtained by exchanging the refresh token
+// shown on the user's Mobility Database account page.
+pub struct MobilityDatabase {
+    pub api_url: String,
+    pub access_token: String,
+}
+
+// CatalogFeed is a GTFS schedule feed as described by the catalog.
+#[derive(Debug, Clone)]
+pub struct CatalogFeed {
+    pub id: String,
+    pub provider: String,
+    pub feed_name: Option<String>,
+    pub producer_url: Option<String>,
+    pub hosted_url: Option<String>,
+}
+
+impl CatalogFeed {
+    // download_url prefers the catalog's hosted copy of the latest dataset, which stays available
+    // when the producer's URL is down, and falls back to the producer's URL.
+    pub fn download_url(&self) -> Option<&str> {
+        self.hosted_url.as_deref().or(self.producer_url.as_deref())
+    }
+}
+
+impl fmt::Display for CatalogFeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.feed_name {
+            Some(feed_name) => write!(f, "{}: {} ({})", self.id, self.provider, feed_name),
+            None => write!(f, "{}: {}", self.id, self.provider),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CatalogError {
+    TransferError(curl::Error),
+    HttpStatus(u32),
+    InvalidResponse(String),
+    NoSuchFeed(String),
+    AmbiguousProvider(String, Vec<CatalogFeed>),
+    NoDownloadUrl(String),
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TransferError(e) => write!(f, "Request to catalog failed: {}", e),
+            Self::HttpStatus(code) => write!(f, "Catalog responded with status {}", code),
+            Self::InvalidResponse(e) => write!(f, "Invalid catalog response: {}", e),
+            Self::NoSuchFeed(query) => write!(f, "No feed found for '{}'", query),
+            Self::AmbiguousProvider(query, feeds) => write!(
+                f,
+                "Several feeds match '{}': {}",
+                query,
+                feeds.iter().map(|feed| feed.id.as_str()).collect::<Vec<_>>().join(", ")
+            ),
+            Self::NoDownloadUrl(id) => write!(f, "Feed {} has no download URL", id),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::TransferError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl MobilityDatabase {
+    // connect exchanges a refresh token for an access token.
+    pub fn connect(refresh_token: &str) -> Result<Self, CatalogError> {
+        let body = serde_json::json!({ "refresh_token": refresh_token }).to_string();
+        let response = request(&format!("{}/tokens", MOBILITY_DATABASE_API), None, Some(&body))?;
+        let access_token = response.get("access_token")
+            .and_then(|token| token.as_str())
+            .ok_or(CatalogError::InvalidResponse("missing access_token".to_string()))?;
+        Ok(Self {
+            api_url: MOBILITY_DATABASE_API.to_string(),
+            access_token: access_token.to_string(),
+        })
+    }
+
+    // feed looks a feed up by its Mobility Database ID, e.g. "mdb-437".
+    pub fn feed(&self, id: &str) -> Result<CatalogFeed, CatalogError> {
+        let response = request(&format!("{}/gtfs_feeds/{}", self.api_url, url_encode(id)), Some(&self.access_token), None)
+            .map_err(|e| match e {
+                CatalogError::HttpStatus(404) => CatalogError::NoSuchFeed(id.to_string()),
+                e => e,
+            })?;
+        parse_feed(&response)
+    }
+
+    // search_provider lists the active feeds whose provider name contains the given name.
+    pub fn search_provider(&self, provider: &str) -> Result<Vec<CatalogFeed>, CatalogError> {
+        let response = request(
+            &format!("{}/gtfs_feeds?provider={}&status=active", self.api_url, url_encode(provider)),
+            Some(&self.access_token),
+            None,
+        )?;
+        response.as_array()
+            .ok_or(CatalogError::InvalidResponse("expected a list of feeds".to_string()))?
+            .iter()
+            .map(parse_feed)
+            .collect()
+    }
+
+    // resolve finds the download URL of a feed given either its Mobility Database ID or its provider's name.
+    // A provider name must identify a single feed, unless one of the matches has exactly that name.
+    pub fn resolve(&self, query: &str) -> Result<String, CatalogError> {
+        let feed = if query.starts_with("mdb-") {
+            self.feed(query)?
+        } else {
+            let mut feeds = self.search_provider(query)?;
+            match feeds.iter().position(|feed| feed.provider.eq_ignore_ascii_case(query)) {
+                Some(i) => feeds.swap_remove(i),
+                None if feeds.len() == 1 => feeds.remove(0),
+                None if feeds.is_empty() => return Err(CatalogError::NoSuchFeed(query.to_string())),
+                None => return Err(CatalogError::AmbiguousProvider(query.to_string(), feeds)),
+            }
+        };
+        feed.download_url()
+            .map(|url| url.to_string())
+            .ok_or(CatalogError::NoDownloadUrl(feed.id.clone()))
+    }
+}
+
+fn parse_feed(value: &serde_json::Value) -> Result<CatalogFeed, CatalogError> {
+    let string = |pointer: &str| value.pointer(pointer).and_then(|v| v.as_str()).map(|s| s.to_string());
+    Ok(CatalogFeed {
+        id: string("/id").ok_or(CatalogError::InvalidResponse("feed without id".to_string()))?,
+        provider: string("/provider").unwrap_or_default(),
+        feed_name: string("/feed_name").filter(|s| !s.is_empty()),
+        producer_url: string("/source_info/producer_url").filter(|s| !s.is_empty()),
+        hosted_url: string("/latest_dataset/hosted_url").filter(|s| !s.is_empty()),
+    })
+}
+
+fn url_encode(s: &str) -> String {
+    Easy::new().url_encode(s.as_bytes())
+}
+
+// request performs a GET (or a POST, if a body is given) and parses the response as JSON.
+fn request(url: &str, access_token: Option<&str>, body: Option<&str>) -> Result<serde_json::Value, CatalogError> {
+    let mut buf = Vec::new();
+    let mut handle = Easy::new();
+    handle.url(url).map_err(CatalogError::TransferError)?;
+    let mut headers = List::new();
+    headers.append("Accept: application/json").map_err(CatalogError::TransferError)?;
+    if let Some(token) = access_token {
+        headers.append(&format!("Authorization: Bearer {}", token)).map_err(CatalogError::TransferError)?;
+    }
+    if let Some(body) = body {
+        headers.append("Content-Type: application/json").map_err(CatalogError::TransferError)?;
+        handle.post(true).map_err(CatalogError::TransferError)?;
+        handle.post_fields_copy(body.as_bytes()).map_err(CatalogError::TransferError)?;
+    }
+    handle.http_headers(headers).map_err(CatalogError::TransferError)?;
+    {
+        let mut transfer = handle.transfer();
+        transfer.write_function(|data| {
+            buf.extend_from_slice(data);
+            Ok(data.len())
+        }).map_err(CatalogError::TransferError)?;
+        transfer.perform().map_err(CatalogError::TransferError)?;
+    }
+
+    match handle.response_code().map_err(CatalogError::TransferError)? {
+        200 => serde_json::from_slice(&buf).map_err(|e| CatalogError::InvalidResponse(e.to_string())),
+        code => Err(CatalogError::HttpStatus(code)),
+    }
+}
@@ -0,0 +1,107 @@
+use serde::de;
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+// These functions are used with #[serde(deserialize_with = "...")] to read fields as they are encoded in GTFS files.
+// Values are borrowed from the record being deserialized, so nothing is allocated for fields which are empty
+// or which parse to something other than a String.
+
+// optional_str reads a field which may be left empty, in which case it is None.
+pub fn optional_str<'de, D: de::Deserializer<'de>>(deserializer: D) -> Result<Option<&'de str>, D::Error> {
+    <&'de str>::deserialize(deserializer).map(|s| Some(s).filter(|s| !s.is_empty()))
+}
+
+// required_str reads a field which must not be left empty.
+pub fn required_str<'de, D: de::Deserializer<'de>>(deserializer: D) -> Result<&'de str, D::Error> {
+    optional_str(deserializer)?.ok_or_else(|| de::Error::custom("value is required"))
+}
+
+// optional reads a field which may be left empty, parsing it with FromStr otherwise.
+pub fn optional<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: de::Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    optional_str(deserializer)?.map(str::parse).transpose().map_err(de::Error::custom)
+}
+
+// required reads a field which must not be left empty, parsing it with FromStr.
+pub fn required<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: de::Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    required_str(deserializer)?.parse().map_err(de::Error::custom)
+}
+
+// optional_accessibility reads wheelchair_boarding, wheelchair_accessible or bikes_allowed, where 1 means yes, 2 means no,
+// and 0 or an empty value means there is no information.
+pub fn optional_accessibility<'de, D: de::Deserializer<'de>>(deserializer: D) -> Result<Option<bool>, D::Error> {
+    match optional_str(deserializer)? {
+        None | Some("0") => Ok(None),
+        Some("1") => Ok(Some(true)),
+        Some("2") => Ok(Some(false)),
+        Some(s) => Err(de::Error::custom(format!("invalid value '{}'", s))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Record<'a> {
+        #[serde(borrow, deserialize_with = "required_str")]
+        id: &'a str,
+        #[serde(borrow, deserialize_with = "optional_str")]
+        name: Option<&'a str>,
+        #[serde(deserialize_with = "required")]
+        count: u32,
+        #[serde(deserialize_with = "optional")]
+        price: Option<f64>,
+        #[serde(deserialize_with = "optional_accessibility")]
+        wheelchair: Option<bool>,
+    }
+
+    // parse deserializes a row of a CSV file with a header of Record's fields, passing the record, or the error's
+    // message if it fails, to check. Records borrow from the row, so they're only checked while it's in scope.
+    fn parse(row: &str, check: impl FnOnce(Result<Record, String>)) {
+        let text = format!("id,name,count,price,wheelchair\n{}\n", row);
+        let mut reader = csv::Reader::from_reader(text.as_bytes());
+        let headers = reader.headers().unwrap().clone();
+        let record = reader.records().next().unwrap().unwrap();
+        check(record.deserialize(Some(&headers)).map_err(|e| e.to_string()));
+    }
+
+    #[test]
+    fn empty_optional_fields_are_none() {
+        parse("A,,1,,", |record| assert_eq!(record, Ok(Record { id: "A", name: None, count: 1, price: None, wheelchair: None })));
+        parse("A,Name,2,2.5,1", |record| assert_eq!(
+            record,
+            Ok(Record { id: "A", name: Some("Name"), count: 2, price: Some(2.5), wheelchair: Some(true) })
+        ));
+    }
+
+    #[test]
+    fn empty_required_fields_are_rejected() {
+        parse(",,1,,", |record| assert!(record.unwrap_err().contains("value is required")));
+        parse("A,,,,", |record| assert!(record.unwrap_err().contains("value is required")));
+    }
+
+    #[test]
+    fn fields_which_fail_to_parse_are_rejected() {
+        parse("A,,x,,", |record| assert!(record.unwrap_err().contains("invalid digit")));
+        parse("A,,1,cheap,", |record| assert!(record.is_err()));
+    }
+
+    #[test]
+    fn accessibility_is_read_as_gtfs_codes_it() {
+        for (code, wheelchair) in [("", None), ("0", None), ("1", Some(true)), ("2", Some(false))] {
+            parse(&format!("A,,1,,{}", code), |record| assert_eq!(record.unwrap().wheelchair, wheelchair));
+        }
+        parse("A,,1,,3", |record| assert!(record.unwrap_err().contains("invalid value '3'")));
+    }
+}
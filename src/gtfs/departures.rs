@@ -0,0 +1,167 @@
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::analysis::variants;
+use crate::gtfs::routes;
+use crate::gtfs::stop_times;
+use crate::gtfs::trips;
+use crate::gtfs::view::ScheduleView;
+
+// Departure is a scheduled departure of a trip from a stop, as listed on a departure board.
+#[derive(Debug, Clone, Copy)]
+pub struct Departure<'a> {
+    // departure_time is measured from the start of service_date, so it may be 24:00:00 or later.
+    pub service_date: chrono::NaiveDate,
+    pub departure_time: stop_times::GtfsTime,
+    pub stop_time: &'a stop_times::StopTime,
+    pub trip: &'a trips::Trip,
+    // route is None if the trip's route isn't in the schedule.
+    pub route: Option<&'a routes::Route>,
+    // variant is how the trip's stopping pattern compares with its route's usual one; see variants::service_variant.
+    pub variant: Option<variants::ServiceVariant>,
+}
+
+impl<'a> Departure<'a> {
+    // headsign returns the stop time's headsign, which overrides the trip's for that stop, falling back to the trip's.
+    pub fn headsign(&self) -> Option<&'a str> {
+        self.stop_time.stop_headsign.as_deref().or(self.trip.trip_headsign.as_deref())
+    }
+}
+
+impl GtfsSchedule {
+    // departures_at lists the first limit departures from a stop at or after time on the given service date,
+    // in order of departure time. See ScheduleView::departures_at.
+    pub fn departures_at(&self, stop_id: &str, date: chrono::NaiveDate, time: stop_times::GtfsTime, limit: usize) -> Vec<Departure<'_>> {
+        self.view().departures_at(stop_id, date, time, limit)
+    }
+
+    // departures_now lists the first limit departures from a stop after the instant now. See ScheduleView::departures_now.
+    pub fn departures_now(&self, stop_id: &str, now: chrono::DateTime<chrono::Utc>, limit: usize) -> Vec<Departure<'_>> {
+        self.view().departures_now(stop_id, now, limit)
+    }
+}
+
+impl<'a> ScheduleView<'a> {
+    // departures_at lists the first limit departures in the view from a stop at or after time on the given service date,
+    // in order of departure time. If the stop is a station, departures from the stops within it are included.
+    // Only trips whose service runs on date are considered, and time is measured from the start of that service day,
+    // so times of 24:00:00 or later select departures after midnight.
+    // Stop times which don't allow pickup, or which end their trip, aren't departures and are left out,
+    // as are stop times with neither a departure nor an arrival time.
+    pub fn departures_at(&self, stop_id: &str, date: chrono::NaiveDate, time: stop_times::GtfsTime, limit: usize) -> Vec<Departure<'a>> {
+        let mut departures = self.stops_within(stop_id)
+            .into_iter()
+            .flat_map(|stop_id| self.stop_times_at(stop_id))
+            .filter(|stop_time| !matches!(stop_time.pickup_type, Some(stop_times::StopPolicy::Unavailable)))
+            .filter(|stop_time| !self.ends_trip(stop_time))
+            .filter_map(|stop_time| {
+                let departure_time = stop_time.departure_time.or(stop_time.arrival_time)?;
+                let trip = self.trip(&stop_time.trip_id)?;
+                (departure_time >= time && self.schedule.service_runs_on(&trip.service_id, date)).then(|| Departure {
+                    service_date: date,
+                    departure_time,
+                    stop_time,
+                    trip,
+                    route: self.schedule.routes.routes.get(&trip.route_id),
+                    variant: None,
+                })
+            })
+            .collect::<Vec<_>>();
+        departures.sort_by(|a, b| a.departure_time.cmp(&b.departure_time).then_with(|| a.trip.trip_id.cmp(&b.trip.trip_id)));
+        departures.truncate(limit);
+        // trips are only classified once the departures to list are known.
+        let variants = variants::service_variants_of(self, departures.iter().map(|departure| departure.trip.trip_id.as_str()));
+        for departure in &mut departures {
+            departure.variant = variants.get(&departure.trip.trip_id).copied();
+        }
+        departures
+    }
+
+    // departures_now lists the first limit departures in the view from a stop at or after the instant now, in order.
+    // now is converted to the stop's local time using its timezone; see ScheduleView::timezone_of_stop.
+    // Trips of the previous service day which run past midnight, with times of 24:00:00 or later, are included
+    // alongside those of the current one.
+    pub fn departures_now(&self, stop_id: &str, now: chrono::DateTime<chrono::Utc>, limit: usize) -> Vec<Departure<'a>> {
+        let local = now.with_timezone(&self.timezone_of_stop(stop_id).unwrap_or(chrono_tz::UTC)).naive_local();
+        let (date, time) = (local.date(), stop_times::GtfsTime::from(local.time()));
+        let mut departures = self.departures_at(stop_id, date, time, limit);
+        if let Some(previous_date) = date.pred_opt() {
+            let spilled_time = stop_times::GtfsTime::from_seconds(time.seconds() + 86400);
+            departures.extend(self.departures_at(stop_id, previous_date, spilled_time, limit));
+        }
+        // departures are compared by the instant they leave, measured from the start of the previous service day.
+        let instant = |departure: &Departure| departure.departure_time.seconds() + if departure.service_date == date { 86400 } else { 0 };
+        departures.sort_by(|a, b| instant(a).cmp(&instant(b)).then_with(|| a.trip.trip_id.cmp(&b.trip.trip_id)));
+        departures.truncate(limit);
+        departures
+    }
+
+    // timezone_of_stop returns the timezone of a stop: its stop_timezone, or its parent station's, or else
+    // the timezone of the agency running the routes which call there, falling back to that of any agency.
+    pub fn timezone_of_stop(&self, stop_id: &str) -> Option<chrono_tz::Tz> {
+        let stop = self.schedule.stops.stops.get(stop_id)?;
+        let agencies = &self.schedule.agencies.agencies;
+        stop.stop_timezone
+            .or_else(|| stop.parent_station()
+                .and_then(|parent_station| self.schedule.stops.stops.get(parent_station))
+                .and_then(|parent| parent.stop_timezone))
+            .or_else(|| self.routes_at_stop(stop_id)
+                .into_iter()
+                .find_map(|route| agencies.values().find(|agency| agency.agency_id.is_some() && agency.agency_id == route.agency_id))
+                .map(|agency| agency.agency_timezone))
+            .or_else(|| {
+                let mut agency_ids = agencies.keys().collect::<Vec<_>>();
+                agency_ids.sort();
+                agency_ids.first().map(|agency_id| agencies[*agency_id].agency_timezone)
+            })
+    }
+
+    // ends_trip reports whether the stop time is the last of its trip.
+    fn ends_trip(&self, stop_time: &stop_times::StopTime) -> bool {
+        self.schedule.stop_times.stop_times.get(&stop_time.trip_id)
+            .and_then(|trip_stop_times| trip_stop_times.iter().map(|other| other.stop_sequence).max())
+            .is_some_and(|last| last == stop_time.stop_sequence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gtfs::testing;
+
+    fn trip_ids<'a>(departures: &[super::Departure<'a>]) -> Vec<&'a str> {
+        departures.iter().map(|departure| departure.trip.trip_id.as_str()).collect()
+    }
+
+    #[test]
+    fn departures_at_lists_the_platforms_of_a_station_in_order() {
+        let schedule = testing::schedule();
+        let departures = schedule.departures_at("S", testing::date(2025, 3, 10), testing::time("08:13:00"), 10);
+        // T1 ends at S1, so it doesn't depart from the station.
+        assert_eq!(trip_ids(&departures), vec!["T2", "T4", "T5"]);
+        assert!(departures.iter().all(|departure| departure.variant.is_some()));
+        assert_eq!(trip_ids(&schedule.departures_at("S", testing::date(2025, 3, 10), testing::time("08:00:00"), 2)), vec!["T3", "T2"]);
+    }
+
+    #[test]
+    fn departures_at_only_lists_service_running_on_date() {
+        let schedule = testing::schedule();
+        assert!(schedule.departures_at("S", testing::date(2026, 3, 10), testing::time("08:00:00"), 10).is_empty());
+    }
+
+    #[test]
+    fn departures_now_includes_the_previous_service_day() {
+        let schedule = testing::schedule();
+        // 04:45 UTC is 00:45 in New York, during daylight saving time.
+        let now = chrono::DateTime::parse_from_rfc3339("2025-03-11T04:45:00Z").unwrap().to_utc();
+        let departures = schedule.departures_now("S", now, 3);
+        assert_eq!(trip_ids(&departures), vec!["T5", "T3", "T2"]);
+        assert_eq!(departures[0].service_date, testing::date(2025, 3, 10));
+        assert_eq!(departures[0].departure_time, testing::time("25:30:00"));
+        assert_eq!(departures[1].service_date, testing::date(2025, 3, 11));
+    }
+
+    #[test]
+    fn departures_now_leaves_out_spilled_trips_which_have_left() {
+        let schedule = testing::schedule();
+        let now = chrono::DateTime::parse_from_rfc3339("2025-03-11T05:31:00Z").unwrap().to_utc();
+        assert_eq!(trip_ids(&schedule.departures_now("S", now, 1)), vec!["T3"]);
+    }
+}
@@ -0,0 +1,318 @@
+use crate::gtfs;
+use crate::gtfs::stops;
+use crate::gtfs::routes;
+use crate::gtfs::trips;
+use crate::gtfs::stop_times;
+use crate::style::Colorize;
+use std::collections;
+use std::fmt;
+use std::str::FromStr;
+
+// FeedDiff describes how one schedule differs from another, table by table.
+// Records are matched by their IDs; stop times are matched by trip_id and stop_sequence.
+#[derive(Debug, Clone, Default)]
+pub struct FeedDiff {
+    pub stops: TableDiff<String>,
+    pub routes: TableDiff<String>,
+    pub trips: TableDiff<String>,
+    pub stop_times: TableDiff<(String, usize)>,
+}
+
+// DiffTable names one of the tables a FeedDiff compares, so that its changes can be looked at on their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffTable {
+    Stops,
+    Routes,
+    Trips,
+    StopTimes,
+}
+
+impl DiffTable {
+    pub const ALL: [DiffTable; 4] = [DiffTable::Stops, DiffTable::Routes, DiffTable::Trips, DiffTable::StopTimes];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DiffTable::Stops => "stops",
+            DiffTable::Routes => "routes",
+            DiffTable::Trips => "trips",
+            DiffTable::StopTimes => "stop_times",
+        }
+    }
+}
+
+impl FromStr for DiffTable {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DiffTable::ALL.into_iter()
+            .find(|table| table.name() == s)
+            .ok_or_else(|| format!("invalid table '{}'", s))
+    }
+}
+
+// TableDiff lists the records that were added to, removed from or changed within one table, ordered by key.
+#[derive(Debug, Clone)]
+pub struct TableDiff<K> {
+    pub added: Vec<K>,
+    pub removed: Vec<K>,
+    pub changed: Vec<RecordChange<K>>,
+}
+
+impl<K> Default for TableDiff<K> {
+    fn default() -> Self {
+        TableDiff {
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        }
+    }
+}
+
+// RecordChange lists the fields which differ between the old and new versions of a record.
+#[derive(Debug, Clone)]
+pub struct RecordChange<K> {
+    pub key: K,
+    pub fields: Vec<FieldChange>,
+}
+
+// FieldChange holds the old and new values of a field, encoded as they appear in the feed's .txt files.
+// A value of None means the field is unset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl FeedDiff {
+    // between compares the old schedule to the new one.
+    pub fn between(old: &gtfs::GtfsSchedule, new: &gtfs::GtfsSchedule) -> Self {
+        FeedDiff {
+            stops: TableDiff::between(
+                &stops::STOP_FIELDS,
+                (&old.stops).into_iter().map(|stop| (stop.stop_id.clone(), stop.into())).collect(),
+                (&new.stops).into_iter().map(|stop| (stop.stop_id.clone(), stop.into())).collect(),
+            ),
+            routes: TableDiff::between(
+                &routes::ROUTE_FIELDS,
+                (&old.routes).into_iter().map(|route| (route.route_id.clone(), route.into())).collect(),
+                (&new.routes).into_iter().map(|route| (route.route_id.clone(), route.into())).collect(),
+            ),
+            trips: TableDiff::between(
+                &trips::TRIP_FIELDS,
+                (&old.trips).into_iter().map(|trip| (trip.trip_id.clone(), trip.into())).collect(),
+                (&new.trips).into_iter().map(|trip| (trip.trip_id.clone(), trip.into())).collect(),
+            ),
+            stop_times: TableDiff::between(
+                &stop_times::STOP_TIME_FIELDS,
+                old.stop_times.iter().map(|stop_time| ((stop_time.trip_id.clone(), stop_time.stop_sequence), stop_time.into())).collect(),
+                new.stop_times.iter().map(|stop_time| ((stop_time.trip_id.clone(), stop_time.stop_sequence), stop_time.into())).collect(),
+            ),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stops.is_empty() && self.routes.is_empty() && self.trips.is_empty() && self.stop_times.is_empty()
+    }
+
+    // summary displays only how many records were added, removed and changed in each table.
+    pub fn summary(&self) -> Summary<'_> {
+        Summary(self)
+    }
+
+    // only keeps the changes to table, and if id is given, only those to the record with that ID. A trip's changes
+    // include those to its stop times, and the stop times of a trip are picked out by its trip_id.
+    pub fn only(&self, table: DiffTable, id: Option<&str>) -> FeedDiff {
+        let id_matches = |key: &String| id.is_none_or(|id| key == id);
+        let trip_matches = |(trip_id, _): &(String, usize)| id.is_none_or(|id| trip_id == id);
+        let mut diff = FeedDiff::default();
+        match table {
+            DiffTable::Stops => diff.stops = self.stops.filter(id_matches),
+            DiffTable::Routes => diff.routes = self.routes.filter(id_matches),
+            DiffTable::Trips => {
+                diff.trips = self.trips.filter(id_matches);
+                if id.is_some() {
+                    diff.stop_times = self.stop_times.filter(trip_matches);
+                }
+            },
+            DiffTable::StopTimes => diff.stop_times = self.stop_times.filter(trip_matches),
+        }
+        diff
+    }
+}
+
+impl<K: Ord + Clone> TableDiff<K> {
+    // between compares two versions of a table, each given as encoded records indexed by key.
+    // Fields are compared in the order they appear in fields.
+    pub fn between(
+        fields: &[&'static str],
+        old: collections::BTreeMap<K, collections::HashMap<String, String>>,
+        new: collections::BTreeMap<K, collections::HashMap<String, String>>,
+    ) -> Self {
+        TableDiff {
+            added: new.keys().filter(|key| !old.contains_key(key)).cloned().collect(),
+            removed: old.keys().filter(|key| !new.contains_key(key)).cloned().collect(),
+            changed: old.iter()
+                .filter_map(|(key, old_fields)| new.get(key).map(|new_fields| RecordChange {
+                    key: key.clone(),
+                    fields: fields.iter()
+                        .filter(|field| old_fields.get(**field) != new_fields.get(**field))
+                        .map(|field| FieldChange {
+                            field,
+                            old: old_fields.get(*field).cloned(),
+                            new: new_fields.get(*field).cloned(),
+                        })
+                        .collect(),
+                }))
+                .filter(|change| !change.fields.is_empty())
+                .collect(),
+        }
+    }
+}
+
+impl<K: Clone> TableDiff<K> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    // filter keeps the records whose keys satisfy predicate.
+    pub fn filter(&self, predicate: impl Fn(&K) -> bool) -> TableDiff<K> {
+        TableDiff {
+            added: self.added.iter().filter(|key| predicate(key)).cloned().collect(),
+            removed: self.removed.iter().filter(|key| predicate(key)).cloned().collect(),
+            changed: self.changed.iter().filter(|change| predicate(&change.key)).cloned().collect(),
+        }
+    }
+}
+
+impl fmt::Display for FeedDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No differences");
+        }
+        write_table(f, "Stops", &self.stops, |stop_id| stop_id.clone())?;
+        write_table(f, "Routes", &self.routes, |route_id| route_id.clone())?;
+        write_table(f, "Trips", &self.trips, |trip_id| trip_id.clone())?;
+        write_table(f, "Stop times", &self.stop_times, |(trip_id, stop_sequence)| format!("{} #{}", trip_id, stop_sequence))
+    }
+}
+
+// Summary displays how many records of each table a FeedDiff found added, removed and changed.
+pub struct Summary<'a>(&'a FeedDiff);
+
+impl fmt::Display for Summary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return writeln!(f, "No differences");
+        }
+        write_counts(f, "Stops", &self.0.stops)?;
+        write_counts(f, "Routes", &self.0.routes)?;
+        write_counts(f, "Trips", &self.0.trips)?;
+        write_counts(f, "Stop times", &self.0.stop_times)
+    }
+}
+
+fn write_counts<K>(f: &mut fmt::Formatter<'_>, name: &str, diff: &TableDiff<K>) -> fmt::Result {
+    writeln!(
+        f,
+        "{}: {} added, {} removed, {} changed",
+        name.bold(),
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len()
+    )
+}
+
+// write_table writes a summary line for the table, followed by one line per added, removed or changed record. Tables
+// without changes are left out.
+fn write_table<K: Clone>(f: &mut fmt::Formatter<'_>, name: &str, diff: &TableDiff<K>, describe: impl Fn(&K) -> String) -> fmt::Result {
+    if diff.is_empty() {
+        return Ok(());
+    }
+    write_counts(f, name, diff)?;
+    for key in &diff.added {
+        writeln!(f, "  {}", format!("+ {}", describe(key)).green())?;
+    }
+    for key in &diff.removed {
+        writeln!(f, "  {}", format!("- {}", describe(key)).red())?;
+    }
+    for change in &diff.changed {
+        writeln!(f, "  {}", format!("~ {}", describe(&change.key)).yellow())?;
+        for field in &change.fields {
+            writeln!(
+                f,
+                "      {}: {} -> {}",
+                field.field,
+                field.old.as_deref().unwrap_or("(unset)"),
+                field.new.as_deref().unwrap_or("(unset)")
+            )?;
+        }
+    }
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::testing;
+
+    // changed returns the test schedule with stop Z added, trip T5 removed and route R1 given a description.
+    fn changed() -> gtfs::GtfsSchedule {
+        let mut schedule = testing::schedule();
+        schedule.add_stop(testing::stop("Z", 40.9, -73.9, None)).unwrap();
+        schedule.remove_trip("T5").unwrap();
+        schedule.routes.routes.get_mut("R1").unwrap().route_desc = Some("Crosstown".to_string());
+        schedule
+    }
+
+    #[test]
+    fn identical_schedules_have_no_differences() {
+        assert!(FeedDiff::between(&testing::schedule(), &testing::schedule()).is_empty());
+    }
+
+    #[test]
+    fn records_are_matched_by_id() {
+        let diff = FeedDiff::between(&testing::schedule(), &changed());
+        assert_eq!(diff.stops.added, ["Z"]);
+        assert!(diff.stops.removed.is_empty() && diff.stops.changed.is_empty());
+        assert_eq!(diff.trips.removed, ["T5"]);
+        assert_eq!(diff.stop_times.removed, [("T5".to_string(), 1), ("T5".to_string(), 2)]);
+        assert_eq!(diff.routes.changed.len(), 1);
+        assert_eq!(diff.routes.changed[0].key, "R1");
+        assert_eq!(diff.routes.changed[0].fields, [FieldChange { field: "route_desc", old: None, new: Some("Crosstown".to_string()) }]);
+    }
+
+    #[test]
+    fn a_changed_field_is_compared_as_written() {
+        let mut new = testing::schedule();
+        new.stop_times.stop_times.get_mut("T2").unwrap()[0].departure_time = Some(testing::time("08:16:00"));
+        let diff = FeedDiff::between(&testing::schedule(), &new);
+        assert_eq!(diff.stop_times.changed.len(), 1);
+        assert_eq!(diff.stop_times.changed[0].key, ("T2".to_string(), 1));
+        assert_eq!(diff.stop_times.changed[0].fields, [FieldChange {
+            field: "departure_time",
+            old: Some("08:15:00".to_string()),
+            new: Some("08:16:00".to_string()),
+        }]);
+    }
+
+    #[test]
+    fn only_keeps_the_changes_to_a_table_or_record() {
+        let diff = FeedDiff::between(&testing::schedule(), &changed());
+        let stops = diff.only(DiffTable::Stops, None);
+        assert_eq!(stops.stops.added, ["Z"]);
+        assert!(stops.trips.is_empty() && stops.routes.is_empty() && stops.stop_times.is_empty());
+
+        // a trip's changes include its stop times.
+        let trip = diff.only(DiffTable::Trips, Some("T5"));
+        assert_eq!(trip.trips.removed, ["T5"]);
+        assert_eq!(trip.stop_times.removed.len(), 2);
+        assert!(diff.only(DiffTable::Trips, Some("T1")).is_empty());
+        assert!(diff.only(DiffTable::Trips, None).stop_times.is_empty());
+    }
+
+    #[test]
+    fn tables_are_named_as_their_files() {
+        assert_eq!("stop_times".parse::<DiffTable>(), Ok(DiffTable::StopTimes));
+        assert!("calendar".parse::<DiffTable>().is_err());
+    }
+}
@@ -0,0 +1,267 @@
+#[cfg(feature = "cli")]
+use crate::gtfs::catalog::CatalogError;
+use crate::gtfs::link::LinkError;
+use crate::gtfs::loaders::multi_feed_loader::{FeedLoadError, MultiFeedLoaderError};
+use crate::gtfs::loaders::tar_gz_loader::TarGzLoaderError;
+#[cfg(feature = "realtime")]
+use crate::gtfs::gtfs_rt::RealtimeError;
+#[cfg(any(feature = "cli", feature = "realtime"))]
+use crate::gtfs::loaders::url_loader::DownloadError;
+use crate::gtfs::loaders::zip_loader::ZipLoaderError;
+use crate::gtfs::mutation::MutationError;
+use crate::gtfs::store::sqlite::SqliteStoreError;
+use crate::gtfs::writers::zip_writer::ZipWriterError;
+use std::error;
+use std::fmt;
+
+// GtfsError is the crate-level error type.
+// Each module keeps its own error type for the failures particular to it, and GtfsError gathers them so that
+// callers working across modules can propagate any of them with `?`. The loaders return it, since loading a feed
+// fails either in reaching its files, with the loader's own error, or in reading one of them, with a CsvError.
+// It's transparent: it displays as the error it wraps, and source() gives that error's source in turn,
+// so that walking the chain doesn't repeat it. The chain continues down to the file, line and field
+// which caused the failure.
+#[derive(Debug)]
+pub enum GtfsError {
+    Csv(CsvError),
+    Zip(ZipLoaderError),
+    TarGz(TarGzLoaderError),
+    #[cfg(any(feature = "cli", feature = "realtime"))]
+    Download(DownloadError),
+    Feed(FeedLoadError),
+    MultiFeed(MultiFeedLoaderError),
+    #[cfg(feature = "cli")]
+    Catalog(CatalogError),
+    Store(SqliteStoreError),
+    Writer(ZipWriterError),
+    Mutation(MutationError),
+    Link(LinkError),
+    #[cfg(feature = "realtime")]
+    Realtime(RealtimeError),
+}
+
+impl fmt::Display for GtfsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Csv(e) => write!(f, "{}", e),
+            Self::Zip(e) => write!(f, "{}", e),
+            Self::TarGz(e) => write!(f, "{}", e),
+            #[cfg(any(feature = "cli", feature = "realtime"))]
+            Self::Download(e) => write!(f, "{}", e),
+            Self::Feed(e) => write!(f, "{}", e),
+            Self::MultiFeed(e) => write!(f, "{}", e),
+            #[cfg(feature = "cli")]
+            Self::Catalog(e) => write!(f, "{}", e),
+            Self::Store(e) => write!(f, "{}", e),
+            Self::Writer(e) => write!(f, "{}", e),
+            Self::Mutation(e) => write!(f, "{}", e),
+            Self::Link(e) => write!(f, "{}", e),
+            #[cfg(feature = "realtime")]
+            Self::Realtime(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for GtfsError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Csv(e) => e.source(),
+            Self::Zip(e) => e.source(),
+            Self::TarGz(e) => e.source(),
+            #[cfg(any(feature = "cli", feature = "realtime"))]
+            Self::Download(e) => e.source(),
+            Self::Feed(e) => e.source(),
+            Self::MultiFeed(e) => e.source(),
+            #[cfg(feature = "cli")]
+            Self::Catalog(e) => e.source(),
+            Self::Store(e) => e.source(),
+            Self::Writer(e) => e.source(),
+            Self::Mutation(e) => e.source(),
+            Self::Link(e) => e.source(),
+            #[cfg(feature = "realtime")]
+            Self::Realtime(e) => e.source(),
+        }
+    }
+}
+
+impl From<CsvError> for GtfsError {
+    fn from(e: CsvError) -> Self {
+        Self::Csv(e)
+    }
+}
+
+impl From<ZipLoaderError> for GtfsError {
+    fn from(e: ZipLoaderError) -> Self {
+        Self::Zip(e)
+    }
+}
+
+impl From<TarGzLoaderError> for GtfsError {
+    fn from(e: TarGzLoaderError) -> Self {
+        Self::TarGz(e)
+    }
+}
+
+#[cfg(any(feature = "cli", feature = "realtime"))]
+impl From<DownloadError> for GtfsError {
+    fn from(e: DownloadError) -> Self {
+        Self::Download(e)
+    }
+}
+
+impl From<FeedLoadError> for GtfsError {
+    fn from(e: FeedLoadError) -> Self {
+        Self::Feed(e)
+    }
+}
+
+impl From<MultiFeedLoaderError> for GtfsError {
+    fn from(e: MultiFeedLoaderError) -> Self {
+        Self::MultiFeed(e)
+    }
+}
+
+#[cfg(feature = "cli")]
+impl From<CatalogError> for GtfsError {
+    fn from(e: CatalogError) -> Self {
+        Self::Catalog(e)
+    }
+}
+
+impl From<SqliteStoreError> for GtfsError {
+    fn from(e: SqliteStoreError) -> Self {
+        Self::Store(e)
+    }
+}
+
+impl From<ZipWriterError> for GtfsError {
+    fn from(e: ZipWriterError) -> Self {
+        Self::Writer(e)
+    }
+}
+
+impl From<MutationError> for GtfsError {
+    fn from(e: MutationError) -> Self {
+        Self::Mutation(e)
+    }
+}
+
+impl From<LinkError> for GtfsError {
+    fn from(e: LinkError) -> Self {
+        Self::Link(e)
+    }
+}
+
+#[cfg(feature = "realtime")]
+impl From<RealtimeError> for GtfsError {
+    fn from(e: RealtimeError) -> Self {
+        Self::Realtime(e)
+    }
+}
+
+// CsvError is an error that occurs while reading one of a feed's CSV files.
+// It records the name of the file and, where the CSV reader knows it, the line on which the error occurred.
+#[derive(Debug)]
+pub struct CsvError {
+    pub file: &'static str,
+    pub line: Option<u64>,
+    pub kind: CsvErrorKind,
+}
+
+#[derive(Debug)]
+pub enum CsvErrorKind {
+    NoHeader,
+    ReadError(csv::Error),
+    // RecordError holds the error raised while deserializing a record's fields, e.g. for a stops.txt row whose
+    // location_type requires a stop_name it doesn't have.
+    RecordError(Box<dyn error::Error + Send + Sync>),
+}
+
+impl CsvError {
+    // no_header creates an error for a file whose header row could not be read.
+    pub fn no_header(file: &'static str) -> Self {
+        CsvError { file, line: Some(1), kind: CsvErrorKind::NoHeader }
+    }
+
+    // read creates an error for a record which could not be read, taking the line from the CSV error itself.
+    pub fn read(file: &'static str, e: csv::Error) -> Self {
+        CsvError { file, line: e.position().map(|position| position.line()), kind: CsvErrorKind::ReadError(e) }
+    }
+
+    // record creates an error for a record which was read, but whose fields could not be interpreted.
+    pub fn record(file: &'static str, record: &csv::StringRecord, e: impl error::Error + Send + Sync + 'static) -> Self {
+        CsvError { file, line: record.position().map(|position| position.line()), kind: CsvErrorKind::RecordError(Box::new(e)) }
+    }
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: ", self.file, line)?,
+            None => write!(f, "{}: ", self.file)?,
+        }
+        match &self.kind {
+            CsvErrorKind::NoHeader => write!(f, "No header found"),
+            CsvErrorKind::ReadError(e) => write!(f, "Error reading CSV: {}", e),
+            CsvErrorKind::RecordError(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+// the wrapped error's message is part of CsvError's own, so source() skips past it to its cause.
+impl error::Error for CsvError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match &self.kind {
+            CsvErrorKind::NoHeader => None,
+            CsvErrorKind::ReadError(e) => e.source(),
+            CsvErrorKind::RecordError(e) => e.source(),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::{table, transfers};
+    use crate::gtfs::loaders::multi_feed_loader::{FeedSource, MultiFeedLoader};
+    use crate::gtfs::loaders::multi_source_loader::MultiSourceLoader;
+
+    // chain gives the messages of an error and each of its sources in turn.
+    fn chain(e: &dyn error::Error) -> Vec<String> {
+        std::iter::successors(Some(e), |e| e.source()).map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn wrapped_errors_are_not_repeated_down_the_chain() {
+        let e = table::load::<transfers::Transfers, _>(csv::Reader::from_reader("from_stop_id,to_stop_id,transfer_type\nA,B,9\n".as_bytes()))
+            .unwrap_err();
+        assert_eq!(chain(&e), vec!["transfers.txt:2: invalid transfer_type '9'"]);
+        assert_eq!(chain(&GtfsError::from(e)), vec!["transfers.txt:2: invalid transfer_type '9'"]);
+    }
+
+    #[test]
+    fn loaders_report_csv_errors_once() {
+        let stops = "stop_id,stop_name,stop_lat,stop_lon\nS,Stop,40.7,-74.0\n";
+        let routes = "route_id,route_short_name,route_type\nR,1,3\n";
+        let trips = "route_id,service_id,trip_id\nR,D,T\n";
+        let stop_times = "trip_id,arrival_time,departure_time,stop_id,stop_sequence\nT,8:00:00,8:00:00,S,x\n";
+        let e = MultiSourceLoader::new(stops.as_bytes(), routes.as_bytes(), trips.as_bytes(), stop_times.as_bytes())
+            .load()
+            .unwrap_err();
+        assert!(matches!(e, GtfsError::Csv(_)));
+        assert_eq!(chain(&e).len(), 1);
+        assert!(chain(&e)[0].starts_with("stop_times.txt:2: "));
+    }
+
+    #[test]
+    fn feed_errors_are_not_repeated_down_the_chain() {
+        let e = MultiFeedLoader::new()
+            .with_feed("a", FeedSource::Path(std::path::PathBuf::from("/nonexistent/feed.zip")))
+            .load()
+            .unwrap_err();
+        let messages = chain(&e);
+        assert!(messages[0].starts_with("Failed to load feed a: Failed to read /nonexistent/feed.zip: "));
+        for (message, cause) in messages.iter().zip(messages.iter().skip(1)) {
+            assert!(!message.ends_with(cause.as_str()), "{:?} repeats {:?}", message, cause);
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use csv;
+use crate::gtfs::error::CsvError;
+use crate::gtfs::de;
+use crate::gtfs::table;
+use serde::Deserialize;
+use std::io;
+use std::iter;
+use std::collections;
+
+// FareProducts is the collection of fare products from fare_products.txt, grouped by fare_product_id, since a product
+// may be listed once for each fare media and rider category it's sold for.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FareProducts {
+    pub fare_products: std::collections::HashMap<String, Vec<FareProduct>>
+}
+
+impl FareProducts {
+    pub fn iter(&self) -> impl Iterator<Item = &FareProduct> {
+        self.fare_products.values().flatten()
+    }
+
+    // with_id_prefix prepends prefix to every ID referenced by a fare product.
+    pub fn with_id_prefix(self, prefix: &str) -> Self {
+        self.fare_products.into_values()
+            .flatten()
+            .map(|mut fare_product| {
+                fare_product.prefix_ids(prefix);
+                fare_product
+            })
+            .collect()
+    }
+
+    // of_product yields the listings of the given fare product.
+    pub fn of_product<'a>(&'a self, fare_product_id: &str) -> impl Iterator<Item = &'a FareProduct> + 'a {
+        self.fare_products.get(fare_product_id).into_iter().flatten()
+    }
+}
+
+// FareProducts implements FromIterator<FareProduct> so that it can be assembled in memory, grouping listings by fare_product_id.
+impl iter::FromIterator<FareProduct> for FareProducts {
+    fn from_iter<I: IntoIterator<Item = FareProduct>>(iter: I) -> Self {
+        let mut fare_products = FareProducts::default();
+        for fare_product in iter {
+            fare_products.fare_products.entry(fare_product.fare_product_id.clone()).or_default().push(fare_product);
+        }
+        fare_products
+    }
+}
+
+// FareProducts implements TryFrom<csv::Reader<R>> by loading the reader's contents as a GtfsTable.
+impl<R: io::Read> TryFrom<csv::Reader<R>> for FareProducts {
+    type Error = CsvError;
+
+    fn try_from(r: csv::Reader<R>) -> Result<Self, Self::Error> {
+        table::load(r)
+    }
+}
+
+// FareProducts holds the records of fare_products.txt, grouped by fare_product_id in the order they are listed.
+impl table::GtfsTable for FareProducts {
+    type Record = FareProduct;
+
+    const FILE_NAME: &'static str = "fare_products.txt";
+
+    fn key(fare_product: &FareProduct) -> String {
+        fare_product.fare_product_id.clone()
+    }
+
+    fn insert(&mut self, fare_product_id: String, fare_product: FareProduct) {
+        self.fare_products.entry(fare_product_id).or_default().push(fare_product);
+    }
+}
+
+// FareProduct is something riders may buy under GTFS fares v2, such as a single ride or a day pass, at its price for a
+// rider category paying with a fare media. Which legs it pays for is given by the rules of fare_leg_rules.txt naming it.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FareProduct {
+    #[serde(deserialize_with = "de::required")]
+    pub fare_product_id: String,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub fare_product_name: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub rider_category_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub fare_media_id: Option<String>,
+    #[serde(deserialize_with = "de::required")]
+    pub amount: f64,
+    #[serde(deserialize_with = "de::required")]
+    pub currency: String,
+}
+
+impl FareProduct {
+    // prefix_ids prepends prefix to the product's ID and the rider category and fare media IDs it references.
+    pub fn prefix_ids(&mut self, prefix: &str) {
+        self.fare_product_id.insert_str(0, prefix);
+        [&mut self.rider_category_id, &mut self.fare_media_id]
+            .into_iter()
+            .for_each(|id| if let Some(id) = id { id.insert_str(0, prefix) });
+    }
+}
+
+// FareLegRules is the collection of rules from fare_leg_rules.txt, in the order they are listed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FareLegRules {
+    pub fare_leg_rules: Vec<FareLegRule>
+}
+
+impl FareLegRules {
+    pub fn iter(&self) -> impl Iterator<Item = &FareLegRule> {
+        self.fare_leg_rules.iter()
+    }
+
+    // with_id_prefix prepends prefix to every ID referenced by a fare leg rule.
+    pub fn with_id_prefix(self, prefix: &str) -> Self {
+        self.fare_leg_rules.into_iter()
+            .map(|mut fare_leg_rule| {
+                fare_leg_rule.prefix_ids(prefix);
+                fare_leg_rule
+            })
+            .collect()
+    }
+}
+
+impl iter::FromIterator<FareLegRule> for FareLegRules {
+    fn from_iter<I: IntoIterator<Item = FareLegRule>>(iter: I) -> Self {
+        FareLegRules {
+            fare_leg_rules: iter.into_iter().collect()
+        }
+    }
+}
+
+// FareLegRules implements TryFrom<csv::Reader<R>> by loading the reader's contents as a GtfsTable.
+impl<R: io::Read> TryFrom<csv::Reader<R>> for FareLegRules {
+    type Error = CsvError;
+
+    fn try_from(r: csv::Reader<R>) -> Result<Self, Self::Error> {
+        table::load(r)
+    }
+}
+
+// FareLegRules holds the records of fare_leg_rules.txt in the order they are listed, since they have no ID of their own.
+impl table::GtfsTable for FareLegRules {
+    type Record = FareLegRule;
+
+    const FILE_NAME: &'static str = "fare_leg_rules.txt";
+
+    fn key(fare_leg_rule: &FareLegRule) -> String {
+        fare_leg_rule.fare_product_id.clone()
+    }
+
+    fn insert(&mut self, _: String, fare_leg_rule: FareLegRule) {
+        self.fare_leg_rules.push(fare_leg_rule);
+    }
+}
+
+// FareLegRule says a fare product pays for a leg on a network, from one area to another, and in timeframes, fields
+// left empty matching legs no other rule matches more closely; see ScheduleView::fares_between.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FareLegRule {
+    #[serde(default, deserialize_with = "de::optional")]
+    pub leg_group_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub network_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub from_area_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub to_area_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub from_timeframe_group_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub to_timeframe_group_id: Option<String>,
+    #[serde(deserialize_with = "de::required")]
+    pub fare_product_id: String,
+    // rule_priority ranks rules which match the same leg; only those of the highest priority apply.
+    #[serde(default, deserialize_with = "de::optional")]
+    pub rule_priority: Option<u32>,
+}
+
+impl FareLegRule {
+    // prefix_ids prepends prefix to every ID the rule references.
+    pub fn prefix_ids(&mut self, prefix: &str) {
+        self.fare_product_id.insert_str(0, prefix);
+        [
+            &mut self.leg_group_id, &mut self.network_id, &mut self.from_area_id,
+            &mut self.to_area_id, &mut self.from_timeframe_group_id, &mut self.to_timeframe_group_id,
+        ]
+            .into_iter()
+            .for_each(|id| if let Some(id) = id { id.insert_str(0, prefix) });
+    }
+}
+
+// StopAreas is the collection of the areas of stop_areas.txt which stops belong to, grouped by stop_id.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StopAreas {
+    pub stop_areas: std::collections::HashMap<String, Vec<StopArea>>
+}
+
+impl StopAreas {
+    pub fn iter(&self) -> impl Iterator<Item = &StopArea> {
+        self.stop_areas.values().flatten()
+    }
+
+    // with_id_prefix prepends prefix to the area and stop IDs of every record.
+    pub fn with_id_prefix(self, prefix: &str) -> Self {
+        self.stop_areas.into_values()
+            .flatten()
+            .map(|mut stop_area| {
+                stop_area.area_id.insert_str(0, prefix);
+                stop_area.stop_id.insert_str(0, prefix);
+                stop_area
+            })
+            .collect()
+    }
+
+    // areas_of yields the IDs of the areas the given stop belongs to.
+    pub fn areas_of<'a>(&'a self, stop_id: &str) -> impl Iterator<Item = &'a str> + 'a {
+        self.stop_areas.get(stop_id).into_iter().flatten().map(|stop_area| stop_area.area_id.as_str())
+    }
+}
+
+// StopAreas implements FromIterator<StopArea> so that it can be assembled in memory, grouping records by stop_id.
+impl iter::FromIterator<StopArea> for StopAreas {
+    fn from_iter<I: IntoIterator<Item = StopArea>>(iter: I) -> Self {
+        let mut stop_areas = StopAreas::default();
+        for stop_area in iter {
+            stop_areas.stop_areas.entry(stop_area.stop_id.clone()).or_default().push(stop_area);
+        }
+        stop_areas
+    }
+}
+
+// StopAreas implements TryFrom<csv::Reader<R>> by loading the reader's contents as a GtfsTable.
+impl<R: io::Read> TryFrom<csv::Reader<R>> for StopAreas {
+    type Error = CsvError;
+
+    fn try_from(r: csv::Reader<R>) -> Result<Self, Self::Error> {
+        table::load(r)
+    }
+}
+
+// StopAreas holds the records of stop_areas.txt, grouped by stop_id.
+impl table::GtfsTable for StopAreas {
+    type Record = StopArea;
+
+    const FILE_NAME: &'static str = "stop_areas.txt";
+
+    fn key(stop_area: &StopArea) -> String {
+        stop_area.stop_id.clone()
+    }
+
+    fn insert(&mut self, stop_id: String, stop_area: StopArea) {
+        self.stop_areas.entry(stop_id).or_default().push(stop_area);
+    }
+}
+
+// StopArea puts a stop in an area, such as a fare zone, which fare leg rules can name. A station's area includes the
+// stops within it.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct StopArea {
+    #[serde(deserialize_with = "de::required")]
+    pub area_id: String,
+    #[serde(deserialize_with = "de::required")]
+    pub stop_id: String,
+}
+
+// the fields of fare_products.txt, in the order they are listed by the GTFS reference.
+pub const FARE_PRODUCT_FIELDS: [&str; 6] = [
+    "fare_product_id", "fare_product_name", "rider_category_id", "fare_media_id", "amount", "currency",
+];
+
+// the fields of fare_leg_rules.txt, in the order they are listed by the GTFS reference.
+pub const FARE_LEG_RULE_FIELDS: [&str; 8] = [
+    "leg_group_id", "network_id", "from_area_id", "to_area_id", "from_timeframe_group_id", "to_timeframe_group_id",
+    "fare_product_id", "rule_priority",
+];
+
+// the fields of stop_areas.txt, in the order they are listed by the GTFS reference.
+pub const STOP_AREA_FIELDS: [&str; 2] = ["area_id", "stop_id"];
+
+// collections::HashMap<String, String> implements From<&FareProduct> by encoding each field as it would appear in fare_products.txt.
+impl From<&FareProduct> for collections::HashMap<String, String> {
+    fn from(fare_product: &FareProduct) -> Self {
+        [
+            ("fare_product_id", Some(fare_product.fare_product_id.clone())),
+            ("fare_product_name", fare_product.fare_product_name.clone()),
+            ("rider_category_id", fare_product.rider_category_id.clone()),
+            ("fare_media_id", fare_product.fare_media_id.clone()),
+            ("amount", Some(fare_product.amount.to_string())),
+            ("currency", Some(fare_product.currency.clone())),
+        ]
+            .into_iter()
+            .filter_map(|(field, value)| value.map(|value| (field.to_string(), value)))
+            .collect()
+    }
+}
+
+// collections::HashMap<String, String> implements From<&FareLegRule> by encoding each field as it would appear in fare_leg_rules.txt.
+impl From<&FareLegRule> for collections::HashMap<String, String> {
+    fn from(fare_leg_rule: &FareLegRule) -> Self {
+        [
+            ("leg_group_id", fare_leg_rule.leg_group_id.clone()),
+            ("network_id", fare_leg_rule.network_id.clone()),
+            ("from_area_id", fare_leg_rule.from_area_id.clone()),
+            ("to_area_id", fare_leg_rule.to_area_id.clone()),
+            ("from_timeframe_group_id", fare_leg_rule.from_timeframe_group_id.clone()),
+            ("to_timeframe_group_id", fare_leg_rule.to_timeframe_group_id.clone()),
+            ("fare_product_id", Some(fare_leg_rule.fare_product_id.clone())),
+            ("rule_priority", fare_leg_rule.rule_priority.map(|rule_priority| rule_priority.to_string())),
+        ]
+            .into_iter()
+            .filter_map(|(field, value)| value.map(|value| (field.to_string(), value)))
+            .collect()
+    }
+}
+
+// collections::HashMap<String, String> implements From<&StopArea> by encoding each field as it would appear in stop_areas.txt.
+impl From<&StopArea> for collections::HashMap<String, String> {
+    fn from(stop_area: &StopArea) -> Self {
+        collections::HashMap::from([
+            ("area_id".to_string(), stop_area.area_id.clone()),
+            ("stop_id".to_string(), stop_area.stop_id.clone()),
+        ])
+    }
+}
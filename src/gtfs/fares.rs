@@ -0,0 +1,604 @@
+use csv;
+use crate::gtfs::error::CsvError;
+use crate::gtfs::de;
+use crate::gtfs::fare_products;
+use crate::gtfs::stop_times;
+use crate::gtfs::table;
+use crate::gtfs::view::ScheduleView;
+use serde::Deserialize;
+use std::io;
+use std::iter;
+use std::collections;
+use std::fmt;
+use std::str::FromStr;
+
+// FareAttributes is the collection of fares from fare_attributes.txt, indexed by fare_id.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FareAttributes {
+    pub fare_attributes: std::collections::HashMap<String, FareAttribute>
+}
+
+impl<'a> iter::IntoIterator for &'a FareAttributes {
+    type Item = &'a FareAttribute;
+    type IntoIter = std::collections::hash_map::Values<'a, String, FareAttribute>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.fare_attributes.values()
+    }
+}
+
+impl FareAttributes {
+    // with_id_prefix prepends prefix to every fare ID and to the agency IDs fares reference.
+    pub fn with_id_prefix(self, prefix: &str) -> Self {
+        self.fare_attributes.into_values()
+            .map(|mut fare_attribute| {
+                fare_attribute.prefix_ids(prefix);
+                fare_attribute
+            })
+            .collect()
+    }
+}
+
+// FareAttributes implements FromIterator<FareAttribute> so that it can be assembled in memory, indexing each fare by its ID.
+impl iter::FromIterator<FareAttribute> for FareAttributes {
+    fn from_iter<I: IntoIterator<Item = FareAttribute>>(iter: I) -> Self {
+        FareAttributes {
+            fare_attributes: iter.into_iter().map(|fare_attribute| (fare_attribute.fare_id.clone(), fare_attribute)).collect()
+        }
+    }
+}
+
+// FareAttributes implements TryFrom<csv::Reader<R>> by loading the reader's contents as a GtfsTable.
+impl<R: io::Read> TryFrom<csv::Reader<R>> for FareAttributes {
+    type Error = CsvError;
+
+    fn try_from(r: csv::Reader<R>) -> Result<Self, Self::Error> {
+        table::load(r)
+    }
+}
+
+// FareAttributes holds the records of fare_attributes.txt, indexed by fare_id.
+impl table::GtfsTable for FareAttributes {
+    type Record = FareAttribute;
+
+    const FILE_NAME: &'static str = "fare_attributes.txt";
+
+    fn key(fare_attribute: &FareAttribute) -> String {
+        fare_attribute.fare_id.clone()
+    }
+
+    fn insert(&mut self, fare_id: String, fare_attribute: FareAttribute) {
+        self.fare_attributes.insert(fare_id, fare_attribute);
+    }
+}
+
+// FareAttribute is a fare class of GTFS fares v1: its price, how it's paid, and the transfers it allows.
+// Which journeys it applies to is given by the fare's rules in fare_rules.txt.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FareAttribute {
+    #[serde(deserialize_with = "de::required")]
+    pub fare_id: String,
+    #[serde(deserialize_with = "de::required")]
+    pub price: f64,
+    #[serde(deserialize_with = "de::required")]
+    pub currency_type: String,
+    #[serde(deserialize_with = "de::required")]
+    pub payment_method: PaymentMethod,
+    // transfers is the number of transfers the fare allows, which is unlimited if it's None.
+    #[serde(default, deserialize_with = "de::optional")]
+    pub transfers: Option<u8>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub agency_id: Option<String>,
+    // transfer_duration is in seconds.
+    #[serde(default, deserialize_with = "de::optional")]
+    pub transfer_duration: Option<u32>,
+}
+
+impl FareAttribute {
+    // prefix_ids prepends prefix to the fare's ID and the agency ID it references.
+    pub fn prefix_ids(&mut self, prefix: &str) {
+        self.fare_id.insert_str(0, prefix);
+        if let Some(agency_id) = self.agency_id.as_mut() {
+            agency_id.insert_str(0, prefix);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentMethod {
+    OnBoard,
+    BeforeBoarding,
+}
+
+#[derive(Debug)]
+pub struct PaymentMethodLoadError(String);
+
+impl fmt::Display for PaymentMethodLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid payment_method '{}'", self.0)
+    }
+}
+
+impl std::error::Error for PaymentMethodLoadError {}
+
+impl PaymentMethod {
+    // code returns the value used to encode this payment method in GTFS files.
+    pub fn code(&self) -> u8 {
+        match self {
+            PaymentMethod::OnBoard => 0,
+            PaymentMethod::BeforeBoarding => 1,
+        }
+    }
+
+    // name describes when the fare is paid.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PaymentMethod::OnBoard => "on board",
+            PaymentMethod::BeforeBoarding => "before boarding",
+        }
+    }
+}
+
+impl FromStr for PaymentMethod {
+    type Err = PaymentMethodLoadError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" => Ok(PaymentMethod::OnBoard),
+            "1" => Ok(PaymentMethod::BeforeBoarding),
+            _ => Err(PaymentMethodLoadError(s.to_string())),
+        }
+    }
+}
+
+// FareRules is the collection of rules from fare_rules.txt, grouped by fare_id in the order they are listed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FareRules {
+    pub fare_rules: std::collections::HashMap<String, Vec<FareRule>>
+}
+
+impl FareRules {
+    pub fn iter(&self) -> impl Iterator<Item = &FareRule> {
+        self.fare_rules.values().flatten()
+    }
+
+    // with_id_prefix prepends prefix to every ID referenced by a fare rule.
+    pub fn with_id_prefix(self, prefix: &str) -> Self {
+        self.fare_rules.into_values()
+            .flatten()
+            .map(|mut fare_rule| {
+                fare_rule.prefix_ids(prefix);
+                fare_rule
+            })
+            .collect()
+    }
+
+    // of_fare yields the rules of the given fare.
+    pub fn of_fare<'a>(&'a self, fare_id: &str) -> impl Iterator<Item = &'a FareRule> + 'a {
+        self.fare_rules.get(fare_id).into_iter().flatten()
+    }
+}
+
+// FareRules implements FromIterator<FareRule> so that it can be assembled in memory, grouping rules by fare_id.
+impl iter::FromIterator<FareRule> for FareRules {
+    fn from_iter<I: IntoIterator<Item = FareRule>>(iter: I) -> Self {
+        let mut fare_rules = FareRules::default();
+        for fare_rule in iter {
+            fare_rules.fare_rules.entry(fare_rule.fare_id.clone()).or_default().push(fare_rule);
+        }
+        fare_rules
+    }
+}
+
+// FareRules implements TryFrom<csv::Reader<R>> by loading the reader's contents as a GtfsTable.
+impl<R: io::Read> TryFrom<csv::Reader<R>> for FareRules {
+    type Error = CsvError;
+
+    fn try_from(r: csv::Reader<R>) -> Result<Self, Self::Error> {
+        table::load(r)
+    }
+}
+
+// FareRules holds the records of fare_rules.txt, grouped by fare_id in the order they are listed.
+impl table::GtfsTable for FareRules {
+    type Record = FareRule;
+
+    const FILE_NAME: &'static str = "fare_rules.txt";
+
+    fn key(fare_rule: &FareRule) -> String {
+        fare_rule.fare_id.clone()
+    }
+
+    fn insert(&mut self, fare_id: String, fare_rule: FareRule) {
+        self.fare_rules.entry(fare_id).or_default().push(fare_rule);
+    }
+}
+
+// FareRule says a fare applies to journeys on a route, from one zone to another, or through a set of zones. Fields
+// left empty match anything. The zones a journey passes through must be exactly those of the contains_ids of a fare's
+// rules for them to apply; see FareRule::matches.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FareRule {
+    #[serde(deserialize_with = "de::required")]
+    pub fare_id: String,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub route_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub origin_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub destination_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub contains_id: Option<String>,
+}
+
+impl FareRule {
+    // prefix_ids prepends prefix to the fare ID, route ID and zone IDs the rule references.
+    pub fn prefix_ids(&mut self, prefix: &str) {
+        self.fare_id.insert_str(0, prefix);
+        [&mut self.route_id, &mut self.origin_id, &mut self.destination_id, &mut self.contains_id]
+            .into_iter()
+            .for_each(|id| if let Some(id) = id { id.insert_str(0, prefix) });
+    }
+
+    // applies_to_route reports whether the rule covers journeys on the given route.
+    pub fn applies_to_route(&self, route_id: &str) -> bool {
+        self.route_id.as_deref().is_none_or(|rule_route_id| rule_route_id == route_id)
+    }
+}
+
+// the fields of fare_attributes.txt, in the order they are listed by the GTFS reference.
+pub const FARE_ATTRIBUTE_FIELDS: [&str; 7] = [
+    "fare_id", "price", "currency_type", "payment_method", "transfers", "agency_id", "transfer_duration",
+];
+
+// the fields of fare_rules.txt, in the order they are listed by the GTFS reference.
+pub const FARE_RULE_FIELDS: [&str; 5] = ["fare_id", "route_id", "origin_id", "destination_id", "contains_id"];
+
+// collections::HashMap<String, String> implements From<&FareAttribute> by encoding each field as it would appear in
+// fare_attributes.txt. transfers is always given, since leaving it empty means transfers are unlimited.
+impl From<&FareAttribute> for collections::HashMap<String, String> {
+    fn from(fare_attribute: &FareAttribute) -> Self {
+        [
+            ("fare_id", Some(fare_attribute.fare_id.clone())),
+            ("price", Some(fare_attribute.price.to_string())),
+            ("currency_type", Some(fare_attribute.currency_type.clone())),
+            ("payment_method", Some(fare_attribute.payment_method.code().to_string())),
+            ("transfers", Some(fare_attribute.transfers.map_or(String::new(), |transfers| transfers.to_string()))),
+            ("agency_id", fare_attribute.agency_id.clone()),
+            ("transfer_duration", fare_attribute.transfer_duration.map(|transfer_duration| transfer_duration.to_string())),
+        ]
+            .into_iter()
+            .filter_map(|(field, value)| value.map(|value| (field.to_string(), value)))
+            .collect()
+    }
+}
+
+// collections::HashMap<String, String> implements From<&FareRule> by encoding each field as it would appear in fare_rules.txt.
+impl From<&FareRule> for collections::HashMap<String, String> {
+    fn from(fare_rule: &FareRule) -> Self {
+        [
+            ("fare_id", Some(fare_rule.fare_id.clone())),
+            ("route_id", fare_rule.route_id.clone()),
+            ("origin_id", fare_rule.origin_id.clone()),
+            ("destination_id", fare_rule.destination_id.clone()),
+            ("contains_id", fare_rule.contains_id.clone()),
+        ]
+            .into_iter()
+            .filter_map(|(field, value)| value.map(|value| (field.to_string(), value)))
+            .collect()
+    }
+}
+
+// Fare is a price riders may be charged, given either by fares v1, as a fare of fare_attributes.txt, or by fares v2,
+// as a fare product of fare_products.txt.
+#[derive(Debug, Clone, Copy)]
+pub enum Fare<'a> {
+    // Attribute holds a fare and the rule of fare_rules.txt under which it applies, if the feed has rules.
+    Attribute(&'a FareAttribute, Option<&'a FareRule>),
+    // Product holds a fare product and the rule of fare_leg_rules.txt under which it's sold.
+    Product(&'a fare_products::FareProduct, &'a fare_products::FareLegRule),
+}
+
+impl<'a> Fare<'a> {
+    pub fn fare_id(&self) -> &'a str {
+        match self {
+            Fare::Attribute(fare_attribute, _) => &fare_attribute.fare_id,
+            Fare::Product(fare_product, _) => &fare_product.fare_product_id,
+        }
+    }
+
+    // name gives the fare product's name; fares of fare_attributes.txt have none.
+    pub fn name(&self) -> Option<&'a str> {
+        match self {
+            Fare::Attribute(..) => None,
+            Fare::Product(fare_product, _) => fare_product.fare_product_name.as_deref(),
+        }
+    }
+
+    pub fn price(&self) -> f64 {
+        match self {
+            Fare::Attribute(fare_attribute, _) => fare_attribute.price,
+            Fare::Product(fare_product, _) => fare_product.amount,
+        }
+    }
+
+    pub fn currency(&self) -> &'a str {
+        match self {
+            Fare::Attribute(fare_attribute, _) => &fare_attribute.currency_type,
+            Fare::Product(fare_product, _) => &fare_product.currency,
+        }
+    }
+
+    // from and to give the zones of a fare rule or the areas of a fare leg rule a fare is charged between, if it says.
+    pub fn from(&self) -> Option<&'a str> {
+        match self {
+            Fare::Attribute(_, fare_rule) => fare_rule.and_then(|fare_rule| fare_rule.origin_id.as_deref()),
+            Fare::Product(_, fare_leg_rule) => fare_leg_rule.from_area_id.as_deref(),
+        }
+    }
+
+    pub fn to(&self) -> Option<&'a str> {
+        match self {
+            Fare::Attribute(_, fare_rule) => fare_rule.and_then(|fare_rule| fare_rule.destination_id.as_deref()),
+            Fare::Product(_, fare_leg_rule) => fare_leg_rule.to_area_id.as_deref(),
+        }
+    }
+
+    // contains gives the zone a fare rule requires a journey to pass through, if it's one of a fare's contains rules.
+    pub fn contains(&self) -> Option<&'a str> {
+        match self {
+            Fare::Attribute(_, fare_rule) => fare_rule.and_then(|fare_rule| fare_rule.contains_id.as_deref()),
+            Fare::Product(..) => None,
+        }
+    }
+}
+
+// RouteFare is a fare charged for riding a route between two stops.
+#[derive(Debug, Clone, Copy)]
+pub struct RouteFare<'a> {
+    pub route_id: &'a str,
+    pub fare: Fare<'a>,
+}
+
+impl<'a> ScheduleView<'a> {
+    // fares_of_route lists the fares which may be charged for riding a route: those of fare_attributes.txt with a rule
+    // naming the route or naming none, and the fare products of the fare leg rules for the route's network. As the GTFS
+    // reference specifies, rules for any network are only used if none is for the route's own. If the feed has no
+    // fare_rules.txt, its fares apply to every route.
+    pub fn fares_of_route(&self, route_id: &str) -> Vec<Fare<'a>> {
+        let schedule = self.schedule;
+        let mut fares = Vec::new();
+        for fare_attribute in &schedule.fare_attributes {
+            if schedule.fare_rules.fare_rules.is_empty() {
+                fares.push(Fare::Attribute(fare_attribute, None));
+            }
+            fares.extend(schedule.fare_rules.of_fare(&fare_attribute.fare_id)
+                .filter(|fare_rule| fare_rule.applies_to_route(route_id))
+                .map(|fare_rule| Fare::Attribute(fare_attribute, Some(fare_rule))));
+        }
+        let network_id = self.route(route_id).and_then(|route| route.network_id.as_deref());
+        let fare_leg_rules = schedule.fare_leg_rules.iter().collect::<Vec<_>>();
+        for fare_leg_rule in most_specific(fare_leg_rules, network_id.into_iter().collect(), |rule| rule.network_id.as_deref()) {
+            fares.extend(schedule.fare_products.of_product(&fare_leg_rule.fare_product_id)
+                .map(|fare_product| Fare::Product(fare_product, fare_leg_rule)));
+        }
+        fares
+    }
+
+    // fares_between lists the fares which may be charged for riding from one stop to another without changing, on each
+    // route of a trip between them, cheapest first for each route. Fares of fare_attributes.txt apply as their rules
+    // in fare_rules.txt give; fare products as the fare leg rules for the leg's network and areas give, following the
+    // GTFS reference's matching of fare leg rules. Fares for journeys with transfers aren't worked out.
+    pub fn fares_between(&self, from_stop_id: &str, to_stop_id: &str) -> Vec<RouteFare<'a>> {
+        let mut fares = Vec::<RouteFare>::new();
+        let mut seen = collections::HashSet::new();
+        for direct_trip in self.direct_trips_between(from_stop_id, to_stop_id) {
+            let route_id = direct_trip.trip.route_id.as_str();
+            let zones = self.zones_between(&direct_trip.trip.trip_id, direct_trip.departure, direct_trip.arrival);
+            let leg_fares = self.attribute_fares(route_id, &zones)
+                .into_iter()
+                .chain(self.product_fares(route_id, direct_trip.departure, direct_trip.arrival));
+            for fare in leg_fares {
+                let rider = match fare {
+                    Fare::Product(fare_product, _) => (fare_product.rider_category_id.as_deref(), fare_product.fare_media_id.as_deref()),
+                    Fare::Attribute(..) => (None, None),
+                };
+                if seen.insert((route_id, fare.fare_id(), rider)) {
+                    fares.push(RouteFare { route_id, fare });
+                }
+            }
+        }
+        fares.sort_by(|a, b| a.route_id.cmp(b.route_id).then(a.fare.price().total_cmp(&b.fare.price())));
+        fares
+    }
+
+    // zones_between gives the zone_id of each stop a trip visits from one stop time to another, inclusive: the origin
+    // and destination zones, followed by every zone passed through.
+    fn zones_between(&self, trip_id: &str, departure: &stop_times::StopTime, arrival: &stop_times::StopTime) -> Zones<'a> {
+        let zone = |stop_time: &stop_times::StopTime| stop_time.stop_id.as_deref().and_then(|stop_id| self.zone_of(stop_id));
+        Zones {
+            origin: zone(departure),
+            destination: zone(arrival),
+            passed: self.stop_times_of_trip(trip_id)
+                .filter(|stop_time| (departure.stop_sequence..=arrival.stop_sequence).contains(&stop_time.stop_sequence))
+                .filter_map(zone)
+                .collect(),
+        }
+    }
+
+    // zone_of gives the fare zone of a stop, or of the station it's within if it has none of its own.
+    fn zone_of(&self, stop_id: &str) -> Option<&'a str> {
+        let stop = self.schedule.stops.stops.get(stop_id)?;
+        stop.zone_id.as_deref().or_else(|| {
+            let parent_station = self.schedule.stops.stops.get(stop.parent_station()?)?;
+            parent_station.zone_id.as_deref()
+        })
+    }
+
+    // attribute_fares gives the fares of fare_attributes.txt applying to a ride on a route through zones. A fare applies
+    // if any of its rules for the route names the ride's origin and destination zones, or leaves them empty, or if the
+    // zones of the contains_ids of its rules for the route are exactly those the ride passes through.
+    fn attribute_fares(&self, route_id: &str, zones: &Zones) -> Vec<Fare<'a>> {
+        let schedule = self.schedule;
+        let mut fares = Vec::new();
+        for fare_attribute in &schedule.fare_attributes {
+            if schedule.fare_rules.fare_rules.is_empty() {
+                fares.push(Fare::Attribute(fare_attribute, None));
+                continue;
+            }
+            let rules = schedule.fare_rules.of_fare(&fare_attribute.fare_id)
+                .filter(|fare_rule| fare_rule.applies_to_route(route_id))
+                .collect::<Vec<_>>();
+            let matches_zone = |rule_zone: &Option<String>, zone: Option<&str>| rule_zone.as_deref().is_none_or(|rule_zone| Some(rule_zone) == zone);
+            let by_zones = rules.iter()
+                .find(|fare_rule| fare_rule.contains_id.is_none()
+                    && matches_zone(&fare_rule.origin_id, zones.origin)
+                    && matches_zone(&fare_rule.destination_id, zones.destination));
+            let contains_ids = rules.iter()
+                .filter_map(|fare_rule| fare_rule.contains_id.as_deref())
+                .collect::<collections::HashSet<_>>();
+            let by_contains = rules.iter()
+                .find(|fare_rule| fare_rule.contains_id.is_some())
+                .filter(|_| contains_ids == zones.passed);
+            if let Some(fare_rule) = by_zones.or(by_contains) {
+                fares.push(Fare::Attribute(fare_attribute, Some(fare_rule)));
+            }
+        }
+        fares
+    }
+
+    // product_fares gives the fare products of the fare leg rules matching a ride on a route from one stop time to
+    // another. As the GTFS reference specifies, rules are matched on the route's network, then the areas of the stops
+    // boarded and left, a rule leaving a field empty only matching where no rule names the leg's own; of the rules
+    // left, only those of the highest rule_priority are used. Rules for timeframes are kept, since when the leg is
+    // ridden isn't considered.
+    fn product_fares(&self, route_id: &str, departure: &stop_times::StopTime, arrival: &stop_times::StopTime) -> Vec<Fare<'a>> {
+        let schedule = self.schedule;
+        if schedule.fare_leg_rules.fare_leg_rules.is_empty() {
+            return Vec::new();
+        }
+        // a station's areas include the stops within it.
+        let areas = |stop_time: &stop_times::StopTime| {
+            let Some(stop) = stop_time.stop_id.as_deref().and_then(|stop_id| schedule.stops.stops.get(stop_id)) else {
+                return Vec::new();
+            };
+            schedule.stop_areas.areas_of(&stop.stop_id)
+                .chain(stop.parent_station().into_iter().flat_map(|parent_station| schedule.stop_areas.areas_of(parent_station)))
+                .collect()
+        };
+        let network_id = self.route(route_id).and_then(|route| route.network_id.as_deref());
+
+        let rules = schedule.fare_leg_rules.iter().collect::<Vec<_>>();
+        let rules = most_specific(rules, network_id.into_iter().collect(), |rule| rule.network_id.as_deref());
+        let rules = most_specific(rules, areas(departure), |rule| rule.from_area_id.as_deref());
+        let rules = most_specific(rules, areas(arrival), |rule| rule.to_area_id.as_deref());
+        let priority = rules.iter().filter_map(|rule| rule.rule_priority).max();
+        rules.into_iter()
+            .filter(|rule| priority.is_none() || rule.rule_priority == priority)
+            .flat_map(|rule| schedule.fare_products.of_product(&rule.fare_product_id).map(move |fare_product| Fare::Product(fare_product, rule)))
+            .collect()
+    }
+}
+
+// Zones are the fare zones of a ride: those it starts and ends in, and every one it passes through on the way.
+struct Zones<'a> {
+    origin: Option<&'a str>,
+    destination: Option<&'a str>,
+    passed: collections::HashSet<&'a str>,
+}
+
+// most_specific keeps the rules whose field, as given by field, is one of ids, or if there are none, those which leave
+// it empty, as fare leg rules are matched.
+fn most_specific<'a>(
+    rules: Vec<&'a fare_products::FareLegRule>,
+    ids: Vec<&str>,
+    field: impl Fn(&fare_products::FareLegRule) -> Option<&str>,
+) -> Vec<&'a fare_products::FareLegRule> {
+    let (exact, empty) = rules.into_iter()
+        .filter(|rule| field(rule).is_none_or(|id| ids.contains(&id)))
+        .partition::<Vec<_>, _>(|rule| field(rule).is_some());
+    if exact.is_empty() { empty } else { exact }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::GtfsSchedule;
+    use crate::gtfs::testing;
+
+    // schedule puts X, S and Y in zones z1, z2 and z3; S's platforms are in its zone.
+    fn schedule() -> GtfsSchedule {
+        let mut schedule = testing::schedule();
+        for (stop_id, zone_id) in [("X", "z1"), ("S", "z2"), ("Y", "z3")] {
+            schedule.stops.stops.get_mut(stop_id).unwrap().zone_id = Some(zone_id.to_string());
+        }
+        schedule.fare_attributes = testing::load("fare_id,price,currency_type,payment_method,transfers\nlocal,2.00,USD,0,0\nzonal,3.50,USD,0,0\nthrough,4.00,USD,0,0\n");
+        schedule
+    }
+
+    fn fares(schedule: &GtfsSchedule, from_stop_id: &str, to_stop_id: &str) -> Vec<(String, String)> {
+        schedule.view().fares_between(from_stop_id, to_stop_id)
+            .into_iter()
+            .map(|route_fare| (route_fare.route_id.to_string(), route_fare.fare.fare_id().to_string()))
+            .collect()
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect()
+    }
+
+    #[test]
+    fn fares_apply_to_every_route_without_rules() {
+        let schedule = schedule();
+        assert_eq!(fares(&schedule, "X", "S"), pairs(&[("R1", "local"), ("R1", "zonal"), ("R1", "through")]));
+    }
+
+    #[test]
+    fn attribute_fares_follow_their_rules() {
+        let mut schedule = schedule();
+        schedule.fare_rules = testing::load("fare_id,route_id,origin_id,destination_id,contains_id\nlocal,R2,,,\nzonal,R1,z1,z2,\nthrough,R1,,,z1\nthrough,R1,,,z2\n");
+        assert_eq!(fares(&schedule, "X", "S"), pairs(&[("R1", "zonal"), ("R1", "through")]));
+        assert_eq!(fares(&schedule, "S", "Y"), pairs(&[("R2", "local")]));
+    }
+
+    #[test]
+    fn contains_rules_must_cover_every_zone_passed() {
+        let mut schedule = schedule();
+        schedule.fare_rules = testing::load("fare_id,route_id,origin_id,destination_id,contains_id\nthrough,R1,,,z1\n");
+        assert!(fares(&schedule, "X", "S").is_empty());
+    }
+
+    #[test]
+    fn product_fares_use_the_most_specific_leg_rules() {
+        let mut schedule = schedule();
+        schedule.fare_attributes = FareAttributes::default();
+        schedule.routes.routes.get_mut("R1").unwrap().network_id = Some("express".to_string());
+        schedule.fare_products = testing::load("fare_product_id,fare_product_name,amount,currency\nbase,Base,2.90,USD\nexpress,Express,6.75,USD\nairport,Airport,8.00,USD\n");
+        schedule.fare_leg_rules = testing::load("leg_group_id,network_id,from_area_id,to_area_id,fare_product_id\n,,,,base\n,express,,,express\n,,,airport,airport\n");
+        schedule.stop_areas = testing::load("area_id,stop_id\nairport,Y\n");
+        assert_eq!(fares(&schedule, "X", "S"), pairs(&[("R1", "express")]));
+        assert_eq!(fares(&schedule, "S", "Y"), pairs(&[("R2", "airport")]));
+        assert_eq!(fares(&schedule, "Y", "S"), pairs(&[]));
+    }
+
+    #[test]
+    fn product_fares_use_the_highest_rule_priority() {
+        let mut schedule = schedule();
+        schedule.fare_attributes = FareAttributes::default();
+        schedule.fare_products = testing::load("fare_product_id,amount,currency\nbase,2.90,USD\nspecial,1.00,USD\n");
+        schedule.fare_leg_rules = testing::load("leg_group_id,fare_product_id,rule_priority\n,base,1\n,special,2\n");
+        assert_eq!(fares(&schedule, "S", "Y"), pairs(&[("R2", "special")]));
+    }
+
+    #[test]
+    fn most_specific_prefers_rules_naming_an_id() {
+        let rules = testing::load::<fare_products::FareLegRules>("leg_group_id,network_id,fare_product_id\n,,any\n,bus,bus\n,rail,rail\n");
+        let products = |ids: Vec<&str>| most_specific(rules.iter().collect(), ids, |rule| rule.network_id.as_deref())
+            .into_iter()
+            .map(|rule| rule.fare_product_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(products(vec!["bus"]), vec!["bus"]);
+        assert_eq!(products(vec!["ferry"]), vec!["any"]);
+        assert_eq!(products(vec![]), vec!["any"]);
+    }
+}
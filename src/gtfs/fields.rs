@@ -0,0 +1,50 @@
+use std::collections;
+
+// FieldFill describes how well a field of a table is filled in: by how many of the table's records give it a value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldFill {
+    pub field: &'static str,
+    pub filled: usize,
+    pub records: usize,
+}
+
+impl FieldFill {
+    // rate gives the fraction of records which give the field a value, or None if there are no records.
+    pub fn rate(&self) -> Option<f64> {
+        (self.records > 0).then(|| self.filled as f64 / self.records as f64)
+    }
+}
+
+// fill counts, for each of fields, how many of records give it a value. Records are encoded as they appear in the
+// feed's .txt files, so a field left out of a file is never filled.
+pub fn fill(fields: &[&'static str], records: impl Iterator<Item = collections::HashMap<String, String>>) -> Vec<FieldFill> {
+    let mut filled = vec![0; fields.len()];
+    let mut count = 0;
+    for record in records {
+        count += 1;
+        for (field, filled) in fields.iter().zip(filled.iter_mut()) {
+            if record.get(*field).is_some_and(|value| !value.is_empty()) {
+                *filled += 1;
+            }
+        }
+    }
+    fields.iter().zip(filled).map(|(field, filled)| FieldFill { field, filled, records: count }).collect()
+}
+
+// field_type gives the type the GTFS reference gives a field of stops.txt, routes.txt, trips.txt or stop_times.txt.
+pub fn field_type(field: &str) -> &'static str {
+    match field {
+        "stop_lat" => "Latitude",
+        "stop_lon" => "Longitude",
+        "stop_url" | "route_url" => "URL",
+        "stop_timezone" => "Timezone",
+        "route_color" | "route_text_color" => "Color",
+        "arrival_time" | "departure_time" | "start_pickup_drop_off_window" | "end_pickup_drop_off_window" => "Time",
+        "stop_sequence" | "route_sort_order" => "Non-negative integer",
+        "shape_dist_traveled" => "Non-negative float",
+        "location_type" | "wheelchair_boarding" | "route_type" | "continuous_pickup" | "continuous_drop_off"
+        | "direction_id" | "wheelchair_accessible" | "bikes_allowed" | "pickup_type" | "drop_off_type" | "timepoint" => "Enum",
+        field if field.ends_with("_id") || field == "parent_station" => "ID",
+        _ => "Text",
+    }
+}
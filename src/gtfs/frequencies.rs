@@ -0,0 +1,290 @@
+use csv;
+use crate::gtfs::error::CsvError;
+use crate::gtfs::de;
+use crate::gtfs::table;
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::stop_times;
+use crate::gtfs::stop_times::GtfsTime;
+use crate::gtfs::trips;
+use serde::Deserialize;
+use std::io;
+use std::iter;
+use std::collections;
+use std::fmt;
+use std::str::FromStr;
+
+// Frequencies is a collection of frequencies, indexed by trip_id.
+// The frequencies of each trip are ordered by start_time.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Frequencies {
+    pub frequencies: std::collections::HashMap<String, Vec<Frequency>>
+}
+
+impl Frequencies {
+    pub fn iter(&self) -> impl Iterator<Item = &Frequency> {
+        self.frequencies.values().flatten()
+    }
+
+    // with_id_prefix prepends prefix to every trip ID.
+    pub fn with_id_prefix(self, prefix: &str) -> Self {
+        Frequencies {
+            frequencies: self.frequencies.into_iter()
+                .map(|(trip_id, mut frequencies)| {
+                    frequencies.iter_mut().for_each(|frequency| frequency.trip_id.insert_str(0, prefix));
+                    (format!("{}{}", prefix, trip_id), frequencies)
+                })
+                .collect()
+        }
+    }
+
+    // expand materializes the trips run by each frequency as concrete trips with their own stop times,
+    // returning only the new trips. Each copies its template trip, with an ID of the form trip_id@HH:MM:SS
+    // giving the time it starts, and its stop times are shifted so that the first stop is served at that time.
+    // Trips whose frequencies aren't exact_times=1 only run at roughly the listed headway, so their stop times
+    // are marked as approximate timepoints. Frequencies of trips missing from trips are skipped.
+    pub fn expand(&self, trips: &trips::Trips, stop_times: &stop_times::StopTimes) -> (trips::Trips, stop_times::StopTimes) {
+        let mut expanded_trips = Vec::new();
+        let mut expanded_stop_times = Vec::new();
+        for (trip_id, frequencies) in &self.frequencies {
+            let Some(trip) = trips.trips.get(trip_id) else {
+                continue;
+            };
+            let mut trip_stop_times = stop_times.stop_times.get(trip_id).into_iter().flatten().collect::<Vec<_>>();
+            trip_stop_times.sort_by_key(|stop_time| stop_time.stop_sequence);
+            let first_time = trip_stop_times.iter()
+                .find_map(|stop_time| stop_time.departure_time.or(stop_time.arrival_time))
+                .map_or(0, |time| time.seconds());
+
+            for frequency in frequencies {
+                for start_time in frequency.start_times() {
+                    let expanded_trip_id = format!("{}@{}", trip_id, start_time);
+                    let shift = |time: Option<GtfsTime>| time.map(|time| GtfsTime::from_seconds(
+                        (time.seconds() + start_time.seconds()).saturating_sub(first_time)
+                    ));
+                    expanded_stop_times.extend(trip_stop_times.iter().map(|stop_time| stop_times::StopTime {
+                        trip_id: expanded_trip_id.clone(),
+                        arrival_time: shift(stop_time.arrival_time),
+                        departure_time: shift(stop_time.departure_time),
+                        start_pickup_drop_off_window: shift(stop_time.start_pickup_drop_off_window),
+                        end_pickup_drop_off_window: shift(stop_time.end_pickup_drop_off_window),
+                        timepoint: match frequency.exact_times {
+                            Some(ExactTimes::ScheduleBased) => stop_time.timepoint.clone(),
+                            _ => Some(stop_times::Timepoint::Approximate),
+                        },
+                        ..(*stop_time).clone()
+                    }));
+                    expanded_trips.push(trips::Trip {
+                        trip_id: expanded_trip_id,
+                        ..trip.clone()
+                    });
+                }
+            }
+        }
+        (expanded_trips.into_iter().collect(), expanded_stop_times.into_iter().collect())
+    }
+}
+
+impl GtfsSchedule {
+    // expand_frequencies replaces every trip listed in frequencies.txt with the concrete trips it runs,
+    // as produced by Frequencies::expand, and clears the schedule's frequencies.
+    pub fn expand_frequencies(&mut self) {
+        let (expanded_trips, expanded_stop_times) = self.frequencies.expand(&self.trips, &self.stop_times);
+        let frequencies = std::mem::take(&mut self.frequencies);
+        self.retain_trips(|trip| !frequencies.frequencies.contains_key(&trip.trip_id));
+        self.trips.trips.extend(expanded_trips.trips);
+        self.stop_times.stop_times.extend(expanded_stop_times.stop_times);
+        self.invalidate_index();
+    }
+}
+
+// Frequencies implements FromIterator<Frequency> so that it can be assembled in memory, grouping records by trip_id ordered by start_time.
+impl iter::FromIterator<Frequency> for Frequencies {
+    fn from_iter<I: IntoIterator<Item = Frequency>>(iter: I) -> Self {
+        let mut frequencies = collections::HashMap::new();
+        for frequency in iter {
+            frequencies.entry(frequency.trip_id.clone()).or_insert(Vec::new()).push(frequency);
+        }
+        frequencies.values_mut().for_each(|frequencies: &mut Vec<Frequency>| frequencies.sort_by_key(|frequency| frequency.start_time));
+        Frequencies { frequencies }
+    }
+}
+
+// Frequencies implements TryFrom<csv::Reader<R>> by loading the reader's contents as a GtfsTable.
+impl<R: io::Read> TryFrom<csv::Reader<R>> for Frequencies {
+    type Error = CsvError;
+
+    fn try_from(r: csv::Reader<R>) -> Result<Self, Self::Error> {
+        table::load(r)
+    }
+}
+
+// Frequencies holds the records of frequencies.txt, grouped by trip_id and ordered by start_time.
+impl table::GtfsTable for Frequencies {
+    type Record = Frequency;
+
+    const FILE_NAME: &'static str = "frequencies.txt";
+
+    fn key(frequency: &Frequency) -> String {
+        frequency.trip_id.clone()
+    }
+
+    fn insert(&mut self, trip_id: String, frequency: Frequency) {
+        self.frequencies.entry(trip_id).or_default().push(frequency);
+    }
+
+    // frequencies are not required to appear in order in frequencies.txt.
+    fn finish(&mut self) {
+        self.frequencies.values_mut().for_each(|frequencies| frequencies.sort_by_key(|frequency| frequency.start_time));
+    }
+}
+
+// Frequency runs a trip repeatedly between start_time and end_time, every headway_secs seconds.
+// The trip's stop times then give the times between stops rather than the times at which the trip runs.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Frequency {
+    #[serde(deserialize_with = "de::required")]
+    pub trip_id: String,
+    #[serde(deserialize_with = "de::required")]
+    pub start_time: GtfsTime,
+    #[serde(deserialize_with = "de::required")]
+    pub end_time: GtfsTime,
+    #[serde(deserialize_with = "de::required")]
+    pub headway_secs: u32,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub exact_times: Option<ExactTimes>,
+}
+
+impl Frequency {
+    // start_times lists the times at which the trip starts under this frequency: every headway_secs from start_time,
+    // up to but excluding end_time. A headway of zero yields start_time alone.
+    pub fn start_times(&self) -> impl Iterator<Item = GtfsTime> {
+        let (end, headway_secs) = (self.end_time.seconds(), self.headway_secs);
+        iter::successors(Some(self.start_time.seconds()), move |seconds| (headway_secs > 0).then(|| seconds + headway_secs))
+            .take_while(move |seconds| *seconds < end)
+            .map(GtfsTime::from_seconds)
+    }
+}
+
+// ExactTimes describes whether a frequency's trips run at exactly the listed headway, or only roughly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExactTimes {
+    FrequencyBased,
+    ScheduleBased,
+}
+
+#[derive(Debug)]
+pub enum ExactTimesLoadError {
+    InvalidExactTimes(String),
+}
+
+impl fmt::Display for ExactTimesLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidExactTimes(s) => write!(f, "Invalid exact times: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for ExactTimesLoadError {}
+
+impl ExactTimes {
+    // code returns the value used to encode this value in GTFS files.
+    pub fn code(&self) -> u8 {
+        match self {
+            ExactTimes::FrequencyBased => 0,
+            ExactTimes::ScheduleBased => 1,
+        }
+    }
+}
+
+impl FromStr for ExactTimes {
+    type Err = ExactTimesLoadError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "0" => ExactTimes::FrequencyBased,
+            "1" => ExactTimes::ScheduleBased,
+            _ => return Err(ExactTimesLoadError::InvalidExactTimes(s.to_string())),
+        })
+    }
+}
+
+// the fields of frequencies.txt, in the order they are listed by the GTFS reference.
+pub const FREQUENCY_FIELDS: [&str; 5] = [
+    "trip_id", "start_time", "end_time", "headway_secs", "exact_times",
+];
+
+// collections::HashMap<String, String> implements From<&Frequency> by encoding each field as it would appear in frequencies.txt.
+impl From<&Frequency> for collections::HashMap<String, String> {
+    fn from(frequency: &Frequency) -> Self {
+        [
+            ("trip_id", Some(frequency.trip_id.clone())),
+            ("start_time", Some(frequency.start_time.to_string())),
+            ("end_time", Some(frequency.end_time.to_string())),
+            ("headway_secs", Some(frequency.headway_secs.to_string())),
+            ("exact_times", frequency.exact_times.map(|exact_times| exact_times.code().to_string())),
+        ]
+            .into_iter()
+            .filter_map(|(field, value)| value.map(|value| (field.to_string(), value)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::testing;
+
+    fn template() -> (trips::Trips, stop_times::StopTimes) {
+        let trips = [trips::Trip::new("F", "R", "D")].into_iter().collect();
+        let mut stop_times = testing::trip_stop_times("F", &[("A", "10:00:00"), ("B", "10:05:00"), ("C", "10:12:00")]);
+        stop_times[1].timepoint = Some(stop_times::Timepoint::Exact);
+        (trips, stop_times.into_iter().collect())
+    }
+
+    #[test]
+    fn start_times_run_until_end_time() {
+        let frequencies = testing::load::<Frequencies>("trip_id,start_time,end_time,headway_secs\nF,06:00:00,07:00:00,1200\nG,06:00:00,07:00:00,0\n");
+        let start_times = |trip_id: &str| frequencies.frequencies[trip_id][0].start_times().map(|time| time.to_string()).collect::<Vec<_>>();
+        assert_eq!(start_times("F"), vec!["06:00:00", "06:20:00", "06:40:00"]);
+        assert_eq!(start_times("G"), vec!["06:00:00"]);
+    }
+
+    #[test]
+    fn expand_shifts_stop_times_to_each_start_time() {
+        let (trips, stop_times) = template();
+        let frequencies = testing::load::<Frequencies>("trip_id,start_time,end_time,headway_secs,exact_times\nF,06:00:00,07:00:00,1200,1\n");
+        let (expanded_trips, expanded_stop_times) = frequencies.expand(&trips, &stop_times);
+        let mut trip_ids = expanded_trips.trips.keys().cloned().collect::<Vec<_>>();
+        trip_ids.sort();
+        assert_eq!(trip_ids, vec!["F@06:00:00", "F@06:20:00", "F@06:40:00"]);
+        let times = expanded_stop_times.stop_times["F@06:20:00"].iter()
+            .map(|stop_time| (stop_time.stop_id.as_deref().unwrap(), stop_time.departure_time.unwrap().to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(times, vec![("A", "06:20:00".to_string()), ("B", "06:25:00".to_string()), ("C", "06:32:00".to_string())]);
+        // schedule-based trips keep their timepoints.
+        assert!(matches!(expanded_stop_times.stop_times["F@06:20:00"][1].timepoint, Some(stop_times::Timepoint::Exact)));
+        assert!(expanded_stop_times.stop_times["F@06:20:00"][0].timepoint.is_none());
+    }
+
+    #[test]
+    fn expand_marks_frequency_based_trips_approximate() {
+        let (trips, stop_times) = template();
+        let frequencies = testing::load::<Frequencies>("trip_id,start_time,end_time,headway_secs,exact_times\nF,06:00:00,06:30:00,1200,0\nMissing,06:00:00,07:00:00,600,0\n");
+        let (expanded_trips, expanded_stop_times) = frequencies.expand(&trips, &stop_times);
+        assert_eq!(expanded_trips.trips.len(), 2);
+        assert!(expanded_stop_times.stop_times.values().flatten()
+            .all(|stop_time| matches!(stop_time.timepoint, Some(stop_times::Timepoint::Approximate))));
+    }
+
+    #[test]
+    fn expand_frequencies_replaces_template_trips() {
+        let mut schedule = testing::schedule();
+        schedule.frequencies = testing::load("trip_id,start_time,end_time,headway_secs\nT1,07:00:00,08:00:00,1800\n");
+        schedule.expand_frequencies();
+        assert!(!schedule.trips.trips.contains_key("T1"));
+        assert!(schedule.trips.trips.contains_key("T1@07:00:00") && schedule.trips.trips.contains_key("T1@07:30:00"));
+        assert_eq!(schedule.stop_times.stop_times["T1@07:30:00"][1].arrival_time, Some(testing::time("07:40:00")));
+        assert!(schedule.frequencies.frequencies.is_empty());
+    }
+}
@@ -0,0 +1,362 @@
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::spatial;
+use crate::gtfs::stop_times;
+use crate::gtfs::trips;
+use crate::gtfs::view::ScheduleView;
+use std::collections;
+use std::fmt;
+
+// Polyline is a path over the Earth's surface, such as a trip's shape, as a sequence of (lat, lon) points in degrees.
+// Distances along it are measured in meters from its first point.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Polyline {
+    pub points: Vec<(f64, f64)>,
+    // the distance along the polyline of each point.
+    distances: Vec<f64>,
+}
+
+// Projection is the closest point of a polyline to some other point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+    // distance_along is the distance along the polyline to the closest point.
+    pub distance_along: f64,
+    // distance_from is the distance from the other point to the closest point.
+    pub distance_from: f64,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl Polyline {
+    pub fn new(points: Vec<(f64, f64)>) -> Self {
+        let distances = points.iter()
+            .scan((0.0, None), |(distance, previous), &(lat, lon)| {
+                if let Some((previous_lat, previous_lon)) = *previous {
+                    *distance += spatial::haversine(previous_lat, previous_lon, lat, lon);
+                }
+                *previous = Some((lat, lon));
+                Some(*distance)
+            })
+            .collect();
+        Polyline { points, distances }
+    }
+
+    // length returns the distance along the whole polyline.
+    pub fn length(&self) -> f64 {
+        self.distances.last().copied().unwrap_or(0.0)
+    }
+
+    // point_at returns the point at the given distance along the polyline, clamped to its ends.
+    pub fn point_at(&self, distance: f64) -> Option<(f64, f64)> {
+        let first = *self.points.first()?;
+        // the first point at or beyond distance ends the segment containing it.
+        let end = self.distances.partition_point(|point_distance| *point_distance < distance);
+        if end == 0 {
+            return Some(first);
+        }
+        if end == self.points.len() {
+            return self.points.last().copied();
+        }
+        let start = end - 1;
+        let t = (distance - self.distances[start]) / (self.distances[end] - self.distances[start]);
+        Some(lerp(self.points[start], self.points[end], t))
+    }
+
+    // project finds the point of the polyline closest to (lat, lon).
+    pub fn project(&self, lat: f64, lon: f64) -> Option<Projection> {
+        self.project_from(lat, lon, 0.0)
+    }
+
+    // project_from finds the point of the polyline closest to (lat, lon), ignoring the part of it before min_distance.
+    // Projecting a sequence of points with increasing min_distance keeps them in order along polylines which
+    // double back on themselves, as loops do.
+    pub fn project_from(&self, lat: f64, lon: f64, min_distance: f64) -> Option<Projection> {
+        if self.points.len() == 1 {
+            let (point_lat, point_lon) = self.points[0];
+            return Some(Projection {
+                distance_along: 0.0,
+                distance_from: spatial::haversine(lat, lon, point_lat, point_lon),
+                lat: point_lat,
+                lon: point_lon,
+            });
+        }
+        self.points.windows(2)
+            .zip(self.distances.windows(2))
+            .filter(|(_, distances)| distances[1] >= min_distance)
+            .map(|(points, distances)| {
+                let length = distances[1] - distances[0];
+                let t_min = if length > 0.0 { ((min_distance - distances[0]) / length).clamp(0.0, 1.0) } else { 0.0 };
+                let t = project_onto_segment(points[0], points[1], (lat, lon)).max(t_min);
+                let (point_lat, point_lon) = lerp(points[0], points[1], t);
+                Projection {
+                    distance_along: distances[0] + t * length,
+                    distance_from: spatial::haversine(lat, lon, point_lat, point_lon),
+                    lat: point_lat,
+                    lon: point_lon,
+                }
+            })
+            .min_by(|a, b| a.distance_from.total_cmp(&b.distance_from))
+    }
+}
+
+// project_onto_segment returns how far along the segment from a to b the point closest to p lies, from 0 to 1.
+// Segments are short enough to be treated as flat, with longitude scaled by the cosine of a's latitude.
+fn project_onto_segment(a: (f64, f64), b: (f64, f64), p: (f64, f64)) -> f64 {
+    let scale = a.0.to_radians().cos();
+    let (dx, dy) = ((b.1 - a.1) * scale, b.0 - a.0);
+    let (px, py) = ((p.1 - a.1) * scale, p.0 - a.0);
+    let length_squared = dx * dx + dy * dy;
+    if length_squared == 0.0 {
+        0.0
+    } else {
+        ((px * dx + py * dy) / length_squared).clamp(0.0, 1.0)
+    }
+}
+
+// Polygon is an area of the Earth's surface, bounded by rings of (lat, lon) points in degrees: an outer ring,
+// followed by the rings of any holes cut out of it. Rings needn't repeat their first point at the end.
+// Edges are straight lines in latitude and longitude, which is accurate enough for areas the size of a city.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Polygon {
+    pub rings: Vec<Vec<(f64, f64)>>,
+}
+
+#[derive(Debug)]
+pub enum GeoJsonError {
+    InvalidJson(serde_json::Error),
+    InvalidGeometry(String),
+}
+
+impl fmt::Display for GeoJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidJson(e) => write!(f, "Invalid JSON: {}", e),
+            Self::InvalidGeometry(reason) => write!(f, "Invalid geometry: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for GeoJsonError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidJson(e) => Some(e),
+            Self::InvalidGeometry(_) => None,
+        }
+    }
+}
+
+impl Polygon {
+    pub fn new(outer: Vec<(f64, f64)>) -> Self {
+        Polygon { rings: vec![outer] }
+    }
+
+    // from_geojson reads the polygons of a GeoJSON document, such as the zones of a GTFS-Flex locations.geojson.
+    // The document may be a Polygon or MultiPolygon geometry, a Feature with one, or a FeatureCollection or
+    // GeometryCollection of them; a MultiPolygon gives a polygon for each of its parts. Other geometries are skipped.
+    pub fn from_geojson(json: &str) -> Result<Vec<Polygon>, GeoJsonError> {
+        let value = serde_json::from_str::<serde_json::Value>(json).map_err(GeoJsonError::InvalidJson)?;
+        let mut polygons = Vec::new();
+        collect_polygons(&value, &mut polygons)?;
+        Ok(polygons)
+    }
+
+    // contains reports whether a point lies inside the polygon and outside its holes.
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        // a ray cast from the point crosses the polygon's edges an odd number of times if it lies inside.
+        self.edges()
+            .filter(|&((lat1, lon1), (lat2, lon2))| {
+                (lat1 > lat) != (lat2 > lat) && lon < lon1 + (lat - lat1) / (lat2 - lat1) * (lon2 - lon1)
+            })
+            .count() % 2 == 1
+    }
+
+    // intersects reports whether any part of a polyline lies inside the polygon, even if none of its points do.
+    pub fn intersects(&self, polyline: &Polyline) -> bool {
+        if !self.bounds().is_some_and(|bounds| may_enter(bounds, &polyline.points)) {
+            return false;
+        }
+        polyline.points.iter().any(|&(lat, lon)| self.contains(lat, lon))
+            || polyline.points.windows(2).any(|segment| self.edges().any(|edge| segments_cross((segment[0], segment[1]), edge)))
+    }
+
+    // bounds returns the polygon's ((min lat, min lon), (max lat, max lon)), or None if it has no points.
+    pub fn bounds(&self) -> Option<((f64, f64), (f64, f64))> {
+        self.rings.iter().flatten().fold(None, |bounds, &(lat, lon)| Some(match bounds {
+            None => ((lat, lon), (lat, lon)),
+            Some(((min_lat, min_lon), (max_lat, max_lon))) => ((min_lat.min(lat), min_lon.min(lon)), (max_lat.max(lat), max_lon.max(lon))),
+        }))
+    }
+
+    // edges yields the edges of every ring, closing each ring back to its first point.
+    fn edges(&self) -> impl Iterator<Item = ((f64, f64), (f64, f64))> + '_ {
+        self.rings.iter()
+            .filter(|ring| !ring.is_empty())
+            .flat_map(|ring| ring.iter().copied().zip(ring.iter().copied().cycle().skip(1)))
+    }
+}
+
+// collect_polygons appends the polygons of a GeoJSON object to polygons. See Polygon::from_geojson.
+fn collect_polygons(value: &serde_json::Value, polygons: &mut Vec<Polygon>) -> Result<(), GeoJsonError> {
+    let ring = |ring: &serde_json::Value| -> Result<Vec<(f64, f64)>, GeoJsonError> {
+        ring.as_array()
+            .ok_or_else(|| GeoJsonError::InvalidGeometry("a ring isn't an array of positions".to_string()))?
+            .iter()
+            .map(|position| match position.as_array().map(|position| position.iter().map(serde_json::Value::as_f64).collect::<Vec<_>>()).as_deref() {
+                // GeoJSON positions are [longitude, latitude], optionally followed by an altitude.
+                Some([Some(lon), Some(lat), ..]) => Ok((*lat, *lon)),
+                _ => Err(GeoJsonError::InvalidGeometry(format!("invalid position {}", position))),
+            })
+            .collect()
+    };
+    let polygon = |coordinates: &serde_json::Value| -> Result<Polygon, GeoJsonError> {
+        Ok(Polygon {
+            rings: coordinates.as_array()
+                .ok_or_else(|| GeoJsonError::InvalidGeometry("a polygon's coordinates aren't an array of rings".to_string()))?
+                .iter()
+                .map(ring)
+                .collect::<Result<_, _>>()?,
+        })
+    };
+    let array = |field: &str| value.get(field).and_then(serde_json::Value::as_array)
+        .ok_or_else(|| GeoJsonError::InvalidGeometry(format!("missing {}", field)));
+
+    match value.get("type").and_then(serde_json::Value::as_str) {
+        Some("Polygon") => polygons.push(polygon(value.get("coordinates").unwrap_or(&serde_json::Value::Null))?),
+        Some("MultiPolygon") => {
+            for coordinates in array("coordinates")? {
+                polygons.push(polygon(coordinates)?);
+            }
+        },
+        Some("Feature") => {
+            if let Some(geometry) = value.get("geometry").filter(|geometry| !geometry.is_null()) {
+                collect_polygons(geometry, polygons)?;
+            }
+        },
+        Some("FeatureCollection") => {
+            for feature in array("features")? {
+                collect_polygons(feature, polygons)?;
+            }
+        },
+        Some("GeometryCollection") => {
+            for geometry in array("geometries")? {
+                collect_polygons(geometry, polygons)?;
+            }
+        },
+        Some(_) => {},
+        None => return Err(GeoJsonError::InvalidGeometry("missing type".to_string())),
+    }
+    Ok(())
+}
+
+// may_enter reports whether a path may pass within bounds, which it can't if all of its points lie beyond the same side.
+fn may_enter(((min_lat, min_lon), (max_lat, max_lon)): ((f64, f64), (f64, f64)), points: &[(f64, f64)]) -> bool {
+    let sides = |&(lat, lon): &(f64, f64)| (lat < min_lat) as u8 | ((lat > max_lat) as u8) << 1 | ((lon < min_lon) as u8) << 2 | ((lon > max_lon) as u8) << 3;
+    points.iter().map(sides).fold(0b1111, |common, sides| common & sides) == 0
+}
+
+// segments_cross reports whether two line segments intersect, treating (lat, lon) as plane coordinates.
+fn segments_cross((a, b): ((f64, f64), (f64, f64)), (c, d): ((f64, f64), (f64, f64))) -> bool {
+    let orientation = |p: (f64, f64), q: (f64, f64), r: (f64, f64)| ((q.0 - p.0) * (r.1 - p.1) - (q.1 - p.1) * (r.0 - p.0)).signum();
+    let (o1, o2, o3, o4) = (orientation(a, b, c), orientation(a, b, d), orientation(c, d, a), orientation(c, d, b));
+    o1 != o2 && o3 != o4
+}
+
+fn lerp(a: (f64, f64), b: (f64, f64), t: f64) -> (f64, f64) {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+impl GtfsSchedule {
+    // shape_polyline returns the polyline traced by a shape's points.
+    pub fn shape_polyline(&self, shape_id: &str) -> Option<Polyline> {
+        let points = self.shapes.shapes.get(shape_id)?;
+        Some(Polyline::new(points.iter().map(|point| (point.shape_pt_lat, point.shape_pt_lon)).collect()))
+    }
+
+    // trip_polyline returns the path a trip follows: its shape if it has one, or else the straight lines
+    // between the stops it visits. None is returned if neither gives at least one point.
+    pub fn trip_polyline(&self, trip_id: &str) -> Option<Polyline> {
+        let trip = self.trips.trips.get(trip_id)?;
+        if let Some(polyline) = trip.shape_id.as_deref().and_then(|shape_id| self.shape_polyline(shape_id)) {
+            return Some(polyline);
+        }
+        let points = self.stop_times_in_order(trip_id)
+            .into_iter()
+            .filter_map(|stop_time| self.stops.stops.get(stop_time.stop_id.as_deref()?))
+            .filter_map(|stop| Some((stop.stop_lat()?, stop.stop_lon()?)))
+            .collect::<Vec<_>>();
+        (!points.is_empty()).then(|| Polyline::new(points))
+    }
+
+    // snap_stop_to_shape returns the distance in meters along a shape to the point of it closest to a stop.
+    pub fn snap_stop_to_shape(&self, stop_id: &str, shape_id: &str) -> Option<f64> {
+        let stop = self.stops.stops.get(stop_id)?;
+        self.shape_polyline(shape_id)?
+            .project(stop.stop_lat()?, stop.stop_lon()?)
+            .map(|projection| projection.distance_along)
+    }
+
+    // trip_position_at estimates where a trip is at a time on its service day, as a (lat, lon) point on its polyline.
+    // Its stops are snapped to the polyline in order, and the trip is taken to move at a steady speed between
+    // consecutive stops with times, and to wait at a stop between arriving and departing.
+    // None is returned if time is before the trip's first stop time or after its last.
+    pub fn trip_position_at(&self, trip_id: &str, time: stop_times::GtfsTime) -> Option<(f64, f64)> {
+        let polyline = self.trip_polyline(trip_id)?;
+
+        // (arrival, departure, distance along the polyline) at each stop with a location and a time.
+        let mut min_distance = 0.0;
+        let timed_stops = self.stop_times_in_order(trip_id)
+            .into_iter()
+            .filter_map(|stop_time| {
+                let stop = self.stops.stops.get(stop_time.stop_id.as_deref()?)?;
+                let arrival_time = stop_time.arrival_time.or(stop_time.departure_time)?;
+                let departure_time = stop_time.departure_time.unwrap_or(arrival_time);
+                let projection = polyline.project_from(stop.stop_lat()?, stop.stop_lon()?, min_distance)?;
+                min_distance = projection.distance_along;
+                Some((arrival_time, departure_time, projection.distance_along))
+            })
+            .collect::<Vec<_>>();
+
+        let distance = timed_stops.iter()
+            .find(|(arrival_time, departure_time, _)| (*arrival_time..=*departure_time).contains(&time))
+            .map(|(_, _, distance)| *distance)
+            .or_else(|| timed_stops.windows(2)
+                .find(|pair| pair[0].1 < time && time < pair[1].0)
+                .map(|pair| {
+                    let (from_time, to_time) = (pair[0].1.seconds() as f64, pair[1].0.seconds() as f64);
+                    let t = (time.seconds() as f64 - from_time) / (to_time - from_time);
+                    pair[0].2 + (pair[1].2 - pair[0].2) * t
+                }))?;
+        polyline.point_at(distance)
+    }
+
+    // trips_through_area lists the trips running on date whose paths pass through an area. See ScheduleView::trips_through_area.
+    pub fn trips_through_area(&self, polygon: &Polygon, date: chrono::NaiveDate) -> Vec<&trips::Trip> {
+        self.view().trips_through_area(polygon, date)
+    }
+
+    fn stop_times_in_order(&self, trip_id: &str) -> Vec<&stop_times::StopTime> {
+        let mut trip_stop_times = self.stop_times.stop_times.get(trip_id).into_iter().flatten().collect::<Vec<_>>();
+        trip_stop_times.sort_by_key(|stop_time| stop_time.stop_sequence);
+        trip_stop_times
+    }
+}
+
+impl<'a> ScheduleView<'a> {
+    // trips_through_area lists the trips in the view running on date whose paths pass through the polygon, sorted by ID.
+    // A trip's path is its shape, or the straight lines between its stops if it has none; see GtfsSchedule::trip_polyline.
+    // Trips sharing a shape are only tested against the polygon once.
+    pub fn trips_through_area(&self, polygon: &Polygon, date: chrono::NaiveDate) -> Vec<&'a trips::Trip> {
+        let mut shapes_through = collections::HashMap::<&str, bool>::new();
+        let mut trips = self.trips()
+            .filter(|trip| self.schedule.service_runs_on(&trip.service_id, date))
+            .filter(|trip| {
+                let passes_through = || self.schedule.trip_polyline(&trip.trip_id).is_some_and(|polyline| polygon.intersects(&polyline));
+                match trip.shape_id.as_deref().filter(|shape_id| self.schedule.shapes.shapes.contains_key(*shape_id)) {
+                    Some(shape_id) => *shapes_through.entry(shape_id).or_insert_with(passes_through),
+                    None => passes_through(),
+                }
+            })
+            .collect::<Vec<_>>();
+        trips.sort_by(|a, b| a.trip_id.cmp(&b.trip_id));
+        trips
+    }
+}
@@ -0,0 +1,318 @@
+use crate::gtfs::gtfs_rt::{self, proto, RealtimeError};
+use crate::gtfs::routes;
+use crate::gtfs::view::ScheduleView;
+
+pub use proto::alert::{Cause, Effect, SeverityLevel};
+
+// Alerts holds the service alerts of a GTFS-Realtime feed, in the order the feed gives them.
+#[derive(Debug, Clone, Default)]
+pub struct Alerts {
+    // timestamp is when the feed was created, if its header says.
+    pub timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    pub alerts: Vec<Alert>,
+}
+
+impl Alerts {
+    // fetch downloads and decodes the ServiceAlerts feed at url.
+    pub fn fetch(url: &str) -> Result<Self, RealtimeError> {
+        gtfs_rt::fetch(url).map(|feed| Alerts::from(&feed))
+    }
+
+    // merge combines the alerts of several feeds, keeping the earliest of their timestamps as TripUpdates::merge does.
+    pub fn merge(feeds: impl IntoIterator<Item = Alerts>) -> Self {
+        feeds.into_iter().fold(Alerts::default(), |mut merged, feed| {
+            merged.timestamp = match (merged.timestamp, feed.timestamp) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            merged.alerts.extend(feed.alerts);
+            merged
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Alert> {
+        self.alerts.iter()
+    }
+
+    // active_at lists the alerts in effect at the instant now.
+    pub fn active_at(&self, now: chrono::DateTime<chrono::Utc>) -> impl Iterator<Item = &Alert> {
+        self.alerts.iter().filter(move |alert| alert.is_active_at(now))
+    }
+
+    // affecting lists the alerts with an informed entity covering target in view, whether or not they're in effect yet.
+    pub fn affecting(&self, view: &ScheduleView, target: AlertTarget) -> Vec<&Alert> {
+        self.alerts.iter()
+            .filter(|alert| alert.informed_entities.iter().any(|entity| entity.affects(view, target)))
+            .collect()
+    }
+}
+
+// Alerts implements From<&proto::FeedMessage>, taking the alerts of the feed's entities and skipping the rest,
+// along with any entities the feed marks as deleted.
+impl From<&proto::FeedMessage> for Alerts {
+    fn from(feed: &proto::FeedMessage) -> Self {
+        Alerts {
+            timestamp: feed.header.timestamp.and_then(gtfs_rt::timestamp),
+            alerts: feed.entity.iter()
+                .filter(|entity| !entity.is_deleted.unwrap_or(false))
+                .filter_map(|entity| entity.alert.as_ref().map(|alert| Alert::from_message(&entity.id, alert)))
+                .collect(),
+        }
+    }
+}
+
+// AlertTarget is what alerts may be fetched for: a stop, a route or a trip of the static schedule, by ID.
+#[derive(Debug, Clone, Copy)]
+pub enum AlertTarget<'a> {
+    Stop(&'a str),
+    Route(&'a str),
+    Trip(&'a str),
+}
+
+// Alert is a notice to riders of a disruption, or other news, affecting some part of the network.
+#[derive(Debug, Clone)]
+pub struct Alert {
+    // id is the ID of the feed entity the alert was given in.
+    pub id: String,
+    // active_periods are when the alert is in effect; it's always in effect if none are given.
+    pub active_periods: Vec<ActivePeriod>,
+    pub informed_entities: Vec<InformedEntity>,
+    pub cause: Cause,
+    pub effect: Effect,
+    pub severity_level: SeverityLevel,
+    pub url: Option<TranslatedText>,
+    pub header_text: Option<TranslatedText>,
+    pub description_text: Option<TranslatedText>,
+}
+
+impl Alert {
+    // from_message reads an alert from its message, taking causes, effects and severities which aren't given, or
+    // which are newer than the crate knows, to be unknown.
+    fn from_message(id: &str, alert: &proto::Alert) -> Self {
+        Alert {
+            id: id.to_string(),
+            active_periods: alert.active_period.iter().map(ActivePeriod::from).collect(),
+            informed_entities: alert.informed_entity.iter().map(InformedEntity::from).collect(),
+            cause: alert.cause.and_then(|code| Cause::try_from(code).ok()).unwrap_or(Cause::UnknownCause),
+            effect: alert.effect.and_then(|code| Effect::try_from(code).ok()).unwrap_or(Effect::UnknownEffect),
+            severity_level: alert.severity_level
+                .and_then(|code| SeverityLevel::try_from(code).ok())
+                .unwrap_or(SeverityLevel::UnknownSeverity),
+            url: alert.url.as_ref().map(TranslatedText::from),
+            header_text: alert.header_text.as_ref().map(TranslatedText::from),
+            description_text: alert.description_text.as_ref().map(TranslatedText::from),
+        }
+    }
+
+    pub fn is_active_at(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.active_periods.is_empty() || self.active_periods.iter().any(|period| period.contains(now))
+    }
+}
+
+// ActivePeriod is an interval in which an alert is in effect, open at either end where start or end isn't given.
+#[derive(Debug, Clone, Copy)]
+pub struct ActivePeriod {
+    pub start: Option<chrono::DateTime<chrono::Utc>>,
+    pub end: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ActivePeriod {
+    pub fn contains(&self, instant: chrono::DateTime<chrono::Utc>) -> bool {
+        self.start.is_none_or(|start| start <= instant) && self.end.is_none_or(|end| instant <= end)
+    }
+}
+
+impl From<&proto::TimeRange> for ActivePeriod {
+    fn from(time_range: &proto::TimeRange) -> Self {
+        ActivePeriod {
+            start: time_range.start.and_then(gtfs_rt::timestamp),
+            end: time_range.end.and_then(gtfs_rt::timestamp),
+        }
+    }
+}
+
+// InformedEntity picks out part of the network an alert is about. Every field given narrows it further, so that e.g.
+// a route_id and a stop_id together mean the route where it calls at the stop.
+#[derive(Debug, Clone)]
+pub struct InformedEntity {
+    pub agency_id: Option<String>,
+    pub route_id: Option<String>,
+    pub route_type: Option<i32>,
+    pub direction_id: Option<u32>,
+    pub trip_id: Option<String>,
+    pub stop_id: Option<String>,
+}
+
+impl InformedEntity {
+    // affects reports whether the entity covers target in view:
+    // - a stop, if it names the stop, the station the stop is in, or a stop within it, whatever else it names;
+    // - a route, if it names the route or one of its trips, or if it names only the route's agency or route type;
+    // - a trip, if it names the trip, or its route in the trip's direction without naming a stop, or if it names
+    //   only the route's agency or route type.
+    pub fn affects(&self, view: &ScheduleView, target: AlertTarget) -> bool {
+        match target {
+            AlertTarget::Stop(stop_id) => self.stop_id.as_deref().is_some_and(|informed_stop_id| {
+                informed_stop_id == stop_id
+                    || view.stops_within(informed_stop_id).contains(stop_id)
+                    || view.stops_within(stop_id).contains(informed_stop_id)
+            }),
+            AlertTarget::Route(route_id) => {
+                let Some(route) = view.route(route_id) else {
+                    return false;
+                };
+                self.route_id.as_deref() == Some(route_id)
+                    || self.trip_id.as_deref().and_then(|trip_id| view.trip(trip_id)).is_some_and(|trip| trip.route_id == route_id)
+                    || self.covers_network_of(route)
+            },
+            AlertTarget::Trip(trip_id) => {
+                let Some(trip) = view.trip(trip_id) else {
+                    return false;
+                };
+                if let Some(informed_trip_id) = &self.trip_id {
+                    return informed_trip_id == trip_id;
+                }
+                let in_direction = self.direction_id.is_none_or(|direction_id| {
+                    trip.direction_id.is_some_and(|direction| u32::from(direction.code()) == direction_id)
+                });
+                (self.route_id.as_deref() == Some(trip.route_id.as_str()) && self.stop_id.is_none() && in_direction)
+                    || view.route(&trip.route_id).is_some_and(|route| self.covers_network_of(route))
+            },
+        }
+    }
+
+    // covers_network_of reports whether the entity names only an agency or a route type, and route is of it.
+    fn covers_network_of(&self, route: &routes::Route) -> bool {
+        if self.route_id.is_some() || self.trip_id.is_some() || self.stop_id.is_some() {
+            return false;
+        }
+        if self.agency_id.is_none() && self.route_type.is_none() {
+            return false;
+        }
+        self.agency_id.as_ref().is_none_or(|agency_id| route.agency_id.as_ref() == Some(agency_id))
+            && self.route_type.is_none_or(|route_type| i32::from(route.route_type.code()) == route_type)
+    }
+}
+
+impl From<&proto::EntitySelector> for InformedEntity {
+    fn from(entity: &proto::EntitySelector) -> Self {
+        InformedEntity {
+            agency_id: entity.agency_id.clone(),
+            route_id: entity.route_id.clone().or_else(|| entity.trip.as_ref().and_then(|trip| trip.route_id.clone())),
+            route_type: entity.route_type,
+            direction_id: entity.direction_id.or_else(|| entity.trip.as_ref().and_then(|trip| trip.direction_id)),
+            trip_id: entity.trip.as_ref().and_then(|trip| trip.trip_id.clone()),
+            stop_id: entity.stop_id.clone(),
+        }
+    }
+}
+
+// TranslatedText is a piece of text given in one or more languages.
+#[derive(Debug, Clone)]
+pub struct TranslatedText {
+    // translations are pairs of text and its BCP-47 language code, which may be left out if the feed has only one.
+    pub translations: Vec<(String, Option<String>)>,
+}
+
+impl TranslatedText {
+    // text gives the text in language, matching a code such as "en" against regional ones like "en-US", or else the
+    // text whose language isn't given, or else the English text, or else the first translation.
+    pub fn text(&self, language: Option<&str>) -> Option<&str> {
+        let primary = |code: &str| code.split('-').next().unwrap_or_default().to_ascii_lowercase();
+        let in_language = |language: &str| self.translations.iter().find(|(_, code)| code.as_deref().is_some_and(|code| {
+            code.eq_ignore_ascii_case(language) || primary(code) == primary(language)
+        }));
+        language.and_then(in_language)
+            .or_else(|| self.translations.iter().find(|(_, code)| code.is_none()))
+            .or_else(|| in_language("en"))
+            .or(self.translations.first())
+            .map(|(text, _)| text.as_str())
+    }
+}
+
+impl From<&proto::TranslatedString> for TranslatedText {
+    fn from(translated: &proto::TranslatedString) -> Self {
+        TranslatedText {
+            translations: translated.translation.iter()
+                .map(|translation| (translation.text.clone(), translation.language.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl Cause {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Cause::UnknownCause => "unknown cause",
+            Cause::OtherCause => "other cause",
+            Cause::TechnicalProblem => "technical problem",
+            Cause::Strike => "strike",
+            Cause::Demonstration => "demonstration",
+            Cause::Accident => "accident",
+            Cause::Holiday => "holiday",
+            Cause::Weather => "weather",
+            Cause::Maintenance => "maintenance",
+            Cause::Construction => "construction",
+            Cause::PoliceActivity => "police activity",
+            Cause::MedicalEmergency => "medical emergency",
+        }
+    }
+}
+
+impl Effect {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Effect::NoService => "no service",
+            Effect::ReducedService => "reduced service",
+            Effect::SignificantDelays => "significant delays",
+            Effect::Detour => "detour",
+            Effect::AdditionalService => "additional service",
+            Effect::ModifiedService => "modified service",
+            Effect::OtherEffect => "other effect",
+            Effect::UnknownEffect => "unknown effect",
+            Effect::StopMoved => "stop moved",
+            Effect::NoEffect => "no effect",
+            Effect::AccessibilityIssue => "accessibility issue",
+        }
+    }
+}
+
+impl SeverityLevel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SeverityLevel::UnknownSeverity => "unknown",
+            SeverityLevel::Info => "info",
+            SeverityLevel::Warning => "warning",
+            SeverityLevel::Severe => "severe",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(translations: &[(&str, Option<&str>)]) -> TranslatedText {
+        TranslatedText {
+            translations: translations.iter().map(|(text, code)| (text.to_string(), code.map(str::to_string))).collect(),
+        }
+    }
+
+    #[test]
+    fn text_prefers_the_language_asked_for() {
+        let translated = text(&[("Umleitung", Some("de")), ("Detour", Some("en-US")), ("Déviation", Some("fr"))]);
+        assert_eq!(translated.text(Some("fr")), Some("Déviation"));
+        assert_eq!(translated.text(Some("en")), Some("Detour"));
+    }
+
+    #[test]
+    fn text_falls_back_to_untagged_then_english_then_first() {
+        let untagged = text(&[("Umleitung", Some("de")), ("Detour", Some("en")), ("Diversion", None)]);
+        assert_eq!(untagged.text(Some("es")), Some("Diversion"));
+        assert_eq!(untagged.text(None), Some("Diversion"));
+        let english = text(&[("Umleitung", Some("de")), ("Detour", Some("en"))]);
+        assert_eq!(english.text(Some("es")), Some("Detour"));
+        assert_eq!(english.text(None), Some("Detour"));
+        assert_eq!(text(&[("Umleitung", Some("de")), ("Déviation", Some("fr"))]).text(Some("es")), Some("Umleitung"));
+        assert_eq!(text(&[]).text(Some("es")), None);
+    }
+}
@@ -0,0 +1,52 @@
+use crate::gtfs::loaders::url_loader::{DownloadError, RetryPolicy, UrlLoader};
+use prost::Message;
+use std::fmt;
+
+pub mod alerts;
+pub mod overlay;
+pub mod proto;
+pub mod trip_updates;
+
+#[derive(Debug)]
+pub enum RealtimeError {
+    Download(DownloadError),
+    Decode(prost::DecodeError),
+}
+
+impl fmt::Display for RealtimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Download(e) => write!(f, "Failed to fetch realtime feed: {}", e),
+            Self::Decode(e) => write!(f, "Failed to decode realtime feed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RealtimeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Download(e) => Some(e),
+            Self::Decode(e) => Some(e),
+        }
+    }
+}
+
+// fetch downloads the GTFS-Realtime feed at url and decodes it.
+// A failed download isn't retried, since by the time a retry succeeded a fresher feed would usually be published.
+pub fn fetch(url: &str) -> Result<proto::FeedMessage, RealtimeError> {
+    let bytes = UrlLoader::new(url)
+        .with_retry_policy(RetryPolicy::no_retry())
+        .download()
+        .map_err(RealtimeError::Download)?;
+    decode(&bytes)
+}
+
+// decode decodes a GTFS-Realtime feed from the protobuf encoding it's published in.
+pub fn decode(bytes: &[u8]) -> Result<proto::FeedMessage, RealtimeError> {
+    proto::FeedMessage::decode(bytes).map_err(RealtimeError::Decode)
+}
+
+// timestamp gives the instant of a GTFS-Realtime timestamp, which counts seconds since the epoch.
+pub(crate) fn timestamp(seconds: u64) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::from_timestamp(i64::try_from(seconds).ok()?, 0)
+}
@@ -0,0 +1,486 @@
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::departures::Departure;
+use crate::gtfs::gtfs_rt::trip_updates::{TripRelationship, TripUpdate, TripUpdates, Vehicle};
+use crate::gtfs::stop_times::GtfsTime;
+use crate::gtfs::trips::Trip;
+use crate::gtfs::view::ScheduleView;
+use chrono::TimeZone;
+
+// LOOKBACK is how long before the time departures are asked for that scheduled departures are looked for, in seconds,
+// so that those running late enough to leave after it are found.
+const LOOKBACK: u32 = 3600;
+
+// RealtimeOverlay lays the predictions of a realtime feed's trip updates over a static schedule, giving the times
+// departures are now expected at, the trips canceled and the trips added.
+#[derive(Debug, Clone, Default)]
+pub struct RealtimeOverlay {
+    pub trip_updates: TripUpdates,
+}
+
+impl RealtimeOverlay {
+    pub fn new(trip_updates: TripUpdates) -> Self {
+        RealtimeOverlay { trip_updates }
+    }
+
+    // apply lays the overlay over the whole of schedule.
+    pub fn apply<'a>(&'a self, schedule: &'a GtfsSchedule) -> RealtimeSchedule<'a> {
+        self.apply_to(schedule.view())
+    }
+
+    // apply_to lays the overlay over part of a schedule. Delays still carry along the whole of each trip, including
+    // its stops outside the view.
+    pub fn apply_to<'a>(&'a self, view: ScheduleView<'a>) -> RealtimeSchedule<'a> {
+        RealtimeSchedule { view, trip_updates: &self.trip_updates }
+    }
+}
+
+// RealtimeSchedule is a schedule, or part of one, with a realtime overlay laid over it.
+#[derive(Debug, Clone, Copy)]
+pub struct RealtimeSchedule<'a> {
+    pub view: ScheduleView<'a>,
+    pub trip_updates: &'a TripUpdates,
+}
+
+impl<'a> RealtimeSchedule<'a> {
+    // canceled_trips lists the updates canceling trips of the view.
+    pub fn canceled_trips(&self) -> Vec<&'a TripUpdate> {
+        self.trip_updates.iter()
+            .filter(|trip_update| trip_update.is_canceled() && self.view.trip(&trip_update.trip_id).is_some())
+            .collect()
+    }
+
+    // added_trips lists the updates adding trips which aren't in the schedule. Added trips have no scheduled stop
+    // times, so their stop time updates give the times they call at.
+    pub fn added_trips(&self) -> Vec<&'a TripUpdate> {
+        self.trip_updates.iter()
+            .filter(|trip_update| trip_update.schedule_relationship == TripRelationship::Added)
+            .filter(|trip_update| !self.view.schedule.trips.trips.contains_key(&trip_update.trip_id))
+            .collect()
+    }
+
+    // predict gives what the overlay says of a scheduled departure: that its trip is canceled or doesn't stop there,
+    // or the time it's now expected to leave at, where the trip's update predicts a delay there; see TripUpdate::join.
+    pub fn predict(&self, departure: Departure<'a>) -> RealtimeDeparture<'a> {
+        let trip_id = &departure.trip.trip_id;
+        let Some(trip_update) = self.trip_updates.on(trip_id, departure.service_date) else {
+            return RealtimeDeparture::scheduled(departure);
+        };
+        let (status, predicted_time) = match trip_update.is_canceled() {
+            true => (DepartureStatus::Canceled, None),
+            false => {
+                let stop_times = self.view.schedule.stop_times.stop_times.get(trip_id).map_or(&[][..], Vec::as_slice);
+                let service_day = departure.stop_time.stop_id.as_deref()
+                    .and_then(|stop_id| self.view.timezone_of_stop(stop_id))
+                    .and_then(|timezone| service_day_start(departure.service_date, timezone));
+                let joined = trip_update.join(stop_times, service_day).into_iter()
+                    .find(|joined| joined.stop_time.stop_sequence == departure.stop_time.stop_sequence);
+                match joined {
+                    Some(joined) if joined.is_skipped() => (DepartureStatus::Skipped, None),
+                    Some(joined) => match joined.departure_delay {
+                        Some(delay) => (DepartureStatus::Predicted, Some(delayed(departure.departure_time, delay))),
+                        None => (DepartureStatus::Scheduled, None),
+                    },
+                    None => (DepartureStatus::Scheduled, None),
+                }
+            },
+        };
+        RealtimeDeparture {
+            service_date: departure.service_date,
+            stop_id: departure.stop_time.stop_id.as_deref().unwrap_or_default(),
+            scheduled: Some(departure),
+            trip_update: Some(trip_update),
+            status,
+            predicted_time,
+        }
+    }
+
+    // departures_at lists the first limit departures in the view from a stop which are expected to leave at or after
+    // time on the given service date, in order of when they're expected to leave, as ScheduleView::departures_at lists
+    // those scheduled. Departures of canceled trips, or from stops trips skip, are listed at their scheduled times,
+    // and the trips the overlay adds are listed alongside those of the schedule.
+    pub fn departures_at(&self, stop_id: &str, date: chrono::NaiveDate, time: GtfsTime, limit: usize) -> Vec<RealtimeDeparture<'a>> {
+        let earliest = GtfsTime::from_seconds(time.seconds().saturating_sub(LOOKBACK));
+        let mut departures = self.view.departures_at(stop_id, date, earliest, usize::MAX)
+            .into_iter()
+            .map(|departure| self.predict(departure))
+            .chain(self.added_departures_at(stop_id, date))
+            .filter(|departure| departure.effective_time() >= time)
+            .collect::<Vec<_>>();
+        departures.sort_by(|a, b| a.effective_time().cmp(&b.effective_time()).then_with(|| a.trip_id().cmp(b.trip_id())));
+        departures.truncate(limit);
+        departures
+    }
+
+    // departures_now lists the first limit departures in the view from a stop which are expected to leave at or after
+    // the instant now, in order, including those of the previous service day, as ScheduleView::departures_now does.
+    pub fn departures_now(&self, stop_id: &str, now: chrono::DateTime<chrono::Utc>, limit: usize) -> Vec<RealtimeDeparture<'a>> {
+        let local = now.with_timezone(&self.view.timezone_of_stop(stop_id).unwrap_or(chrono_tz::UTC)).naive_local();
+        let (date, time) = (local.date(), GtfsTime::from(local.time()));
+        let mut departures = self.departures_at(stop_id, date, time, limit);
+        if let Some(previous_date) = date.pred_opt() {
+            let spilled_time = GtfsTime::from_seconds(time.seconds() + 86400);
+            departures.extend(self.departures_at(stop_id, previous_date, spilled_time, limit));
+        }
+        let instant = |departure: &RealtimeDeparture| {
+            departure.effective_time().seconds() + if departure.service_date == date { 86400 } else { 0 }
+        };
+        departures.sort_by(|a, b| instant(a).cmp(&instant(b)).then_with(|| a.trip_id().cmp(b.trip_id())));
+        departures.truncate(limit);
+        departures
+    }
+
+    // vehicles lists the vehicles the overlay has running trips of the view, or trips it adds on routes of the view,
+    // along with the next stop each is due at, at or after the instant now. Vehicles of canceled trips aren't listed.
+    // Updates without a start_date are taken to be for the trip's run on the service day of now.
+    pub fn vehicles(&self, now: chrono::DateTime<chrono::Utc>) -> Vec<RealtimeVehicle<'a>> {
+        let mut vehicles = self.trip_updates.iter()
+            .filter(|trip_update| !trip_update.is_canceled())
+            .filter_map(|trip_update| {
+                let vehicle = trip_update.vehicle.as_ref()?;
+                let trip = self.view.trip(&trip_update.trip_id);
+                match trip {
+                    Some(trip) => Some(self.scheduled_vehicle(vehicle, trip_update, trip, now)),
+                    None if trip_update.schedule_relationship == TripRelationship::Added => {
+                        trip_update.route_id.as_deref().and_then(|route_id| self.view.route(route_id))?;
+                        Some(self.added_vehicle(vehicle, trip_update, now))
+                    },
+                    None => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        vehicles.sort_by(|a, b| a.trip_id().cmp(b.trip_id()));
+        vehicles
+    }
+
+    // scheduled_vehicle finds where a vehicle running a scheduled trip is due next, from the trip's stop times joined
+    // with its update.
+    fn scheduled_vehicle(&self, vehicle: &'a Vehicle, trip_update: &'a TripUpdate, trip: &'a Trip, now: chrono::DateTime<chrono::Utc>) -> RealtimeVehicle<'a> {
+        let stop_times = self.view.schedule.stop_times.stop_times.get(&trip.trip_id).map_or(&[][..], Vec::as_slice);
+        let timezone = stop_times.iter()
+            .find_map(|stop_time| stop_time.stop_id.as_deref())
+            .and_then(|stop_id| self.view.timezone_of_stop(stop_id))
+            .unwrap_or(chrono_tz::UTC);
+        let service_date = trip_update.start_date.unwrap_or(now.with_timezone(&timezone).date_naive());
+        let service_day = service_day_start(service_date, timezone);
+        let next = trip_update.join(stop_times, service_day).into_iter()
+            .filter(|joined| !joined.is_skipped())
+            .find_map(|joined| {
+                let delay = joined.arrival_delay.or(joined.departure_delay);
+                let scheduled_time = joined.stop_time.arrival_time.or(joined.stop_time.departure_time)?;
+                let time = delay.map_or(scheduled_time, |delay| delayed(scheduled_time, delay));
+                let instant = service_day? + chrono::Duration::seconds(time.seconds().into());
+                (instant >= now).then_some((joined.stop_time.stop_id.as_deref(), time, delay))
+            });
+        RealtimeVehicle {
+            vehicle,
+            trip_update,
+            trip: Some(trip),
+            service_date,
+            next_stop_id: next.and_then(|(stop_id, _, _)| stop_id),
+            next_time: next.map(|(_, time, _)| time),
+            delay: next.map_or(trip_update.delay, |(_, _, delay)| delay),
+        }
+    }
+
+    // added_vehicle finds where a vehicle running a trip the overlay adds is due next, from the times its stop time
+    // updates give.
+    fn added_vehicle(&self, vehicle: &'a Vehicle, trip_update: &'a TripUpdate, now: chrono::DateTime<chrono::Utc>) -> RealtimeVehicle<'a> {
+        let next = trip_update.stop_time_updates.iter().find_map(|stop_time_update| {
+            let event = stop_time_update.arrival.as_ref().or(stop_time_update.departure.as_ref())?;
+            let instant = event.time?;
+            (instant >= now).then_some((stop_time_update.stop_id.as_deref(), instant))
+        });
+        let timezone = next.and_then(|(stop_id, _)| self.view.timezone_of_stop(stop_id?)).unwrap_or(chrono_tz::UTC);
+        let service_date = trip_update.start_date.unwrap_or(now.with_timezone(&timezone).date_naive());
+        let next_time = next.and_then(|(_, instant)| {
+            let seconds = (instant - service_day_start(service_date, timezone)?).num_seconds();
+            Some(GtfsTime::from_seconds(u32::try_from(seconds).ok()?))
+        });
+        RealtimeVehicle {
+            vehicle,
+            trip_update,
+            trip: None,
+            service_date,
+            next_stop_id: next.and_then(|(stop_id, _)| stop_id),
+            next_time,
+            delay: None,
+        }
+    }
+
+    // added_departures_at lists the departures from a stop, or the stops within it, of the trips the overlay adds on
+    // the given service date: those dated then, and those without a date, which are taken to run on any.
+    fn added_departures_at(&self, stop_id: &str, date: chrono::NaiveDate) -> Vec<RealtimeDeparture<'a>> {
+        let stop_ids = self.view.stops_within(stop_id);
+        let Some(service_day) = self.view.timezone_of_stop(stop_id).and_then(|timezone| service_day_start(date, timezone)) else {
+            return Vec::new();
+        };
+        self.added_trips().into_iter()
+            .filter(|trip_update| trip_update.start_date.is_none_or(|start_date| start_date == date))
+            .flat_map(|trip_update| trip_update.stop_time_updates.iter().map(move |stop_time_update| (trip_update, stop_time_update)))
+            .filter_map(|(trip_update, stop_time_update)| {
+                let stop_id = stop_ids.get(stop_time_update.stop_id.as_deref()?)?;
+                let event = stop_time_update.departure.as_ref().or(stop_time_update.arrival.as_ref())?;
+                let seconds = u32::try_from((event.time? - service_day).num_seconds()).ok()?;
+                Some(RealtimeDeparture {
+                    service_date: date,
+                    stop_id,
+                    scheduled: None,
+                    trip_update: Some(trip_update),
+                    status: DepartureStatus::Added,
+                    predicted_time: Some(GtfsTime::from_seconds(seconds)),
+                })
+            })
+            .collect()
+    }
+}
+
+// RealtimeDeparture is a departure from a stop as a realtime overlay has it: a scheduled departure, along with when
+// it's now expected, or a departure of a trip the overlay adds.
+#[derive(Debug, Clone, Copy)]
+pub struct RealtimeDeparture<'a> {
+    pub service_date: chrono::NaiveDate,
+    pub stop_id: &'a str,
+    // scheduled is the departure as scheduled, or None for a trip the overlay adds.
+    pub scheduled: Option<Departure<'a>>,
+    pub trip_update: Option<&'a TripUpdate>,
+    pub status: DepartureStatus,
+    // predicted_time is when the departure is expected, measured from the start of service_date as scheduled times
+    // are; it's None where nothing is predicted, or the departure won't happen.
+    pub predicted_time: Option<GtfsTime>,
+}
+
+impl<'a> RealtimeDeparture<'a> {
+    // scheduled gives a departure the overlay says nothing of.
+    pub fn scheduled(departure: Departure<'a>) -> Self {
+        RealtimeDeparture {
+            service_date: departure.service_date,
+            stop_id: departure.stop_time.stop_id.as_deref().unwrap_or_default(),
+            scheduled: Some(departure),
+            trip_update: None,
+            status: DepartureStatus::Scheduled,
+            predicted_time: None,
+        }
+    }
+
+    pub fn trip_id(&self) -> &'a str {
+        match (self.scheduled, self.trip_update) {
+            (Some(departure), _) => &departure.trip.trip_id,
+            (None, Some(trip_update)) => &trip_update.trip_id,
+            (None, None) => "",
+        }
+    }
+
+    pub fn scheduled_time(&self) -> Option<GtfsTime> {
+        self.scheduled.map(|departure| departure.departure_time)
+    }
+
+    // effective_time is when the departure is expected: its predicted time, or else its scheduled one.
+    pub fn effective_time(&self) -> GtfsTime {
+        self.predicted_time.or(self.scheduled_time()).unwrap_or(GtfsTime::from_seconds(0))
+    }
+
+    // delay is how many seconds after its scheduled time the departure is expected, negative if early.
+    pub fn delay(&self) -> Option<i64> {
+        Some(i64::from(self.predicted_time?.seconds()) - i64::from(self.scheduled_time()?.seconds()))
+    }
+}
+
+// RealtimeVehicle is a vehicle a realtime overlay has running a trip, along with the next stop it's due at.
+#[derive(Debug, Clone, Copy)]
+pub struct RealtimeVehicle<'a> {
+    pub vehicle: &'a Vehicle,
+    pub trip_update: &'a TripUpdate,
+    // trip is the trip as scheduled, or None for a trip the overlay adds.
+    pub trip: Option<&'a Trip>,
+    pub service_date: chrono::NaiveDate,
+    // next_stop_id and next_time are the stop the vehicle is due at next and when, measured from the start of
+    // service_date; they're None once the vehicle is past its last predicted stop.
+    pub next_stop_id: Option<&'a str>,
+    pub next_time: Option<GtfsTime>,
+    // delay is how late the vehicle is expected at its next stop in seconds, negative if early, or else how late the
+    // trip as a whole is running.
+    pub delay: Option<i32>,
+}
+
+impl<'a> RealtimeVehicle<'a> {
+    pub fn trip_id(&self) -> &'a str {
+        &self.trip_update.trip_id
+    }
+
+    pub fn route_id(&self) -> Option<&'a str> {
+        self.trip.map(|trip| trip.route_id.as_str()).or(self.trip_update.route_id.as_deref())
+    }
+}
+
+// DepartureStatus is what a realtime overlay says of a departure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepartureStatus {
+    // Scheduled means there's no prediction for the departure, which is expected as scheduled.
+    Scheduled,
+    Predicted,
+    // Skipped means the trip runs, but won't stop there.
+    Skipped,
+    Canceled,
+    // Added means the departure is of a trip the overlay adds to the schedule.
+    Added,
+}
+
+impl DepartureStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DepartureStatus::Scheduled => "scheduled",
+            DepartureStatus::Predicted => "predicted",
+            DepartureStatus::Skipped => "skipped",
+            DepartureStatus::Canceled => "canceled",
+            DepartureStatus::Added => "added",
+        }
+    }
+}
+
+// service_day_start gives the instant the service day of date starts at in timezone, which GTFS takes to be noon
+// minus 12 hours so that times stay right on days the clocks change.
+pub fn service_day_start(date: chrono::NaiveDate, timezone: chrono_tz::Tz) -> Option<chrono::DateTime<chrono::Utc>> {
+    let noon = timezone.from_local_datetime(&date.and_hms_opt(12, 0, 0)?).single()?;
+    Some(noon.with_timezone(&chrono::Utc) - chrono::Duration::hours(12))
+}
+
+// delayed gives time put off by delay seconds, or brought forward if it's negative, but no earlier than the start of
+// the service day.
+fn delayed(time: GtfsTime, delay: i32) -> GtfsTime {
+    GtfsTime::from_seconds(u32::try_from(i64::from(time.seconds()) + i64::from(delay)).unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::gtfs_rt::{self, proto};
+    use crate::gtfs::testing;
+    use prost::Message;
+
+    fn trip_update(trip_id: &str, relationship: proto::trip_descriptor::ScheduleRelationship, updates: Vec<proto::trip_update::StopTimeUpdate>) -> proto::FeedEntity {
+        proto::FeedEntity {
+            id: trip_id.to_string(),
+            trip_update: Some(proto::TripUpdate {
+                trip: proto::TripDescriptor {
+                    trip_id: Some(trip_id.to_string()),
+                    start_date: Some("20250310".to_string()),
+                    route_id: Some("R2".to_string()),
+                    schedule_relationship: Some(relationship as i32),
+                    ..Default::default()
+                },
+                stop_time_update: updates,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn stop_time_update(stop_sequence: u32, departure: proto::trip_update::StopTimeEvent) -> proto::trip_update::StopTimeUpdate {
+        proto::trip_update::StopTimeUpdate {
+            stop_sequence: Some(stop_sequence),
+            departure: Some(departure),
+            ..Default::default()
+        }
+    }
+
+    fn delay(seconds: i32) -> proto::trip_update::StopTimeEvent {
+        proto::trip_update::StopTimeEvent { delay: Some(seconds), ..Default::default() }
+    }
+
+    // at gives the event of a stop time at a local time on 2025-03-10 in New York.
+    fn at(time: &str) -> proto::trip_update::StopTimeEvent {
+        let service_day = service_day_start(testing::date(2025, 3, 10), chrono_tz::America::New_York).unwrap();
+        let instant = service_day + chrono::Duration::seconds(testing::time(time).seconds().into());
+        proto::trip_update::StopTimeEvent { time: Some(instant.timestamp()), ..Default::default() }
+    }
+
+    // overlay decodes a feed delaying T2 by five minutes, canceling T3, having T4 skip S2 and adding trip N at 08:40.
+    fn overlay() -> RealtimeOverlay {
+        use proto::trip_descriptor::ScheduleRelationship;
+        let skipped = proto::trip_update::StopTimeUpdate {
+            stop_sequence: Some(1),
+            schedule_relationship: Some(proto::trip_update::stop_time_update::ScheduleRelationship::Skipped as i32),
+            ..Default::default()
+        };
+        let added = proto::trip_update::StopTimeUpdate {
+            stop_id: Some("S2".to_string()),
+            departure: Some(at("08:40:00")),
+            ..Default::default()
+        };
+        let feed = proto::FeedMessage {
+            header: proto::FeedHeader { gtfs_realtime_version: "2.0".to_string(), timestamp: Some(1741608000), ..Default::default() },
+            entity: vec![
+                trip_update("T2", ScheduleRelationship::Scheduled, vec![stop_time_update(1, delay(300))]),
+                trip_update("T3", ScheduleRelationship::Canceled, vec![]),
+                trip_update("T4", ScheduleRelationship::Scheduled, vec![skipped]),
+                trip_update("N", ScheduleRelationship::Added, vec![added]),
+            ],
+        };
+        let feed = gtfs_rt::decode(&feed.encode_to_vec()).unwrap();
+        assert_eq!(feed.header.timestamp.and_then(gtfs_rt::timestamp).map(|timestamp| timestamp.to_rfc3339()), Some("2025-03-10T12:00:00+00:00".to_string()));
+        RealtimeOverlay::new(TripUpdates::from(&feed))
+    }
+
+    #[test]
+    fn decode_rejects_what_isnt_a_feed() {
+        assert!(gtfs_rt::decode(b"not a feed").is_err());
+    }
+
+    #[test]
+    fn departures_at_lays_predictions_over_the_schedule() {
+        let schedule = testing::schedule();
+        let overlay = overlay();
+        let realtime = overlay.apply(&schedule);
+        let departures = realtime.departures_at("S", testing::date(2025, 3, 10), testing::time("08:00:00"), 10)
+            .into_iter()
+            .map(|departure| (departure.trip_id(), departure.status, departure.effective_time().to_string(), departure.delay()))
+            .collect::<Vec<_>>();
+        assert_eq!(departures, vec![
+            ("T3", DepartureStatus::Canceled, "08:12:00".to_string(), None),
+            ("T2", DepartureStatus::Predicted, "08:20:00".to_string(), Some(300)),
+            ("N", DepartureStatus::Added, "08:40:00".to_string(), None),
+            ("T4", DepartureStatus::Skipped, "09:30:00".to_string(), None),
+            ("T5", DepartureStatus::Scheduled, "25:30:00".to_string(), None),
+        ]);
+        assert_eq!(realtime.canceled_trips().len(), 1);
+        assert_eq!(realtime.added_trips().len(), 1);
+    }
+
+    #[test]
+    fn updates_only_apply_to_their_start_date() {
+        let schedule = testing::schedule();
+        let overlay = overlay();
+        let departures = overlay.apply(&schedule).departures_at("S", testing::date(2025, 3, 11), testing::time("08:00:00"), 2);
+        assert!(departures.iter().all(|departure| departure.status == DepartureStatus::Scheduled));
+        assert_eq!(departures.iter().map(RealtimeDeparture::trip_id).collect::<Vec<_>>(), vec!["T3", "T2"]);
+    }
+
+    #[test]
+    fn delays_find_departures_scheduled_before_the_time_asked_for() {
+        let schedule = testing::schedule();
+        let overlay = overlay();
+        let departures = overlay.apply(&schedule).departures_at("S", testing::date(2025, 3, 10), testing::time("08:18:00"), 1);
+        assert_eq!(departures[0].trip_id(), "T2");
+    }
+
+    #[test]
+    fn join_carries_delays_forward_and_reads_absolute_times() {
+        let schedule = testing::schedule();
+        let overlay = overlay();
+        let service_day = service_day_start(testing::date(2025, 3, 10), chrono_tz::America::New_York);
+        let stop_times = &schedule.stop_times.stop_times["T2"];
+        let update = overlay.trip_updates.on("T2", testing::date(2025, 3, 10)).unwrap();
+        let delays = update.join(stop_times, service_day).iter().map(|joined| joined.arrival_delay).collect::<Vec<_>>();
+        assert_eq!(delays, vec![Some(300), Some(300)]);
+
+        let feed = proto::FeedMessage {
+            entity: vec![trip_update("T2", proto::trip_descriptor::ScheduleRelationship::Scheduled, vec![stop_time_update(2, at("08:33:00"))])],
+            ..Default::default()
+        };
+        let trip_updates = TripUpdates::from(&feed);
+        let update = trip_updates.of_trip("T2").next().unwrap();
+        let delays = update.join(stop_times, service_day).iter().map(|joined| joined.departure_delay).collect::<Vec<_>>();
+        assert_eq!(delays, vec![None, Some(180)]);
+        assert_eq!(update.join(stop_times, None)[1].departure_delay, None);
+    }
+}
@@ -0,0 +1,347 @@
+use crate::gtfs::calendar;
+use crate::gtfs::gtfs_rt::{self, proto, RealtimeError};
+use crate::gtfs::stop_times::{GtfsTime, StopTime};
+use std::collections::HashMap;
+
+// TripUpdates holds the trip updates of a GTFS-Realtime feed, indexed by trip_id so that they join against the trips
+// of trips.txt and their stop times. A trip may have several, one for each service day it's running on.
+// Updates which don't give a trip_id, such as those for frequency-based trips given only by route and start time,
+// can't be joined and are left out.
+#[derive(Debug, Clone, Default)]
+pub struct TripUpdates {
+    // timestamp is when the feed was created, if its header says.
+    pub timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    pub trip_updates: HashMap<String, Vec<TripUpdate>>,
+}
+
+impl TripUpdates {
+    // fetch downloads and decodes the TripUpdates feed at url.
+    pub fn fetch(url: &str) -> Result<Self, RealtimeError> {
+        gtfs_rt::fetch(url).map(|feed| TripUpdates::from(&feed))
+    }
+
+    // merge combines the trip updates of several feeds, as when a producer splits them between feeds by mode.
+    // The earliest of their timestamps is kept, since the predictions are only as fresh as the stalest feed's.
+    pub fn merge(feeds: impl IntoIterator<Item = TripUpdates>) -> Self {
+        feeds.into_iter().fold(TripUpdates::default(), |mut merged, feed| {
+            merged.timestamp = match (merged.timestamp, feed.timestamp) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            for (trip_id, trip_updates) in feed.trip_updates {
+                merged.trip_updates.entry(trip_id).or_default().extend(trip_updates);
+            }
+            merged
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TripUpdate> {
+        self.trip_updates.values().flatten()
+    }
+
+    // of_trip lists the updates for a trip.
+    pub fn of_trip(&self, trip_id: &str) -> impl Iterator<Item = &TripUpdate> {
+        self.trip_updates.get(trip_id).into_iter().flatten()
+    }
+
+    // on returns the update for a trip running on the given service date: the one whose start_date is date,
+    // or else one which gives no start_date and so is taken to be for the trip's current run.
+    pub fn on(&self, trip_id: &str, date: chrono::NaiveDate) -> Option<&TripUpdate> {
+        self.of_trip(trip_id).find(|trip_update| trip_update.start_date == Some(date))
+            .or_else(|| self.of_trip(trip_id).find(|trip_update| trip_update.start_date.is_none()))
+    }
+}
+
+// TripUpdates implements From<&proto::FeedMessage>, taking the trip updates of the feed's entities and skipping
+// the rest, along with any entities the feed marks as deleted.
+impl From<&proto::FeedMessage> for TripUpdates {
+    fn from(feed: &proto::FeedMessage) -> Self {
+        let mut trip_updates = HashMap::<String, Vec<TripUpdate>>::new();
+        for entity in feed.entity.iter().filter(|entity| !entity.is_deleted.unwrap_or(false)) {
+            let Some(trip_update) = &entity.trip_update else {
+                continue;
+            };
+            match TripUpdate::from_message(trip_update) {
+                Some(trip_update) => trip_updates.entry(trip_update.trip_id.clone()).or_default().push(trip_update),
+                None => log::debug!("Skipping trip update {} which gives no trip_id", entity.id),
+            }
+        }
+        TripUpdates {
+            timestamp: feed.header.timestamp.and_then(gtfs_rt::timestamp),
+            trip_updates,
+        }
+    }
+}
+
+// TripUpdate is a prediction of how a trip will run, or a change to whether it runs at all.
+#[derive(Debug, Clone)]
+pub struct TripUpdate {
+    pub trip_id: String,
+    pub route_id: Option<String>,
+    pub direction_id: Option<u32>,
+    // start_date and start_time identify which run of the trip is updated, where the trip may be running more than once.
+    pub start_date: Option<chrono::NaiveDate>,
+    pub start_time: Option<GtfsTime>,
+    pub schedule_relationship: TripRelationship,
+    pub vehicle: Option<Vehicle>,
+    // timestamp is when the prediction was last updated.
+    pub timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    // delay is the delay of the trip as a whole in seconds, for stops which have no stop time update of their own.
+    pub delay: Option<i32>,
+    // stop_time_updates are ordered by stop_sequence, as the feed is required to give them.
+    pub stop_time_updates: Vec<StopTimeUpdate>,
+}
+
+impl TripUpdate {
+    // from_message reads a trip update from its message, giving None if it has no trip_id.
+    fn from_message(trip_update: &proto::TripUpdate) -> Option<Self> {
+        let trip = &trip_update.trip;
+        Some(TripUpdate {
+            trip_id: trip.trip_id.clone()?,
+            route_id: trip.route_id.clone(),
+            direction_id: trip.direction_id,
+            start_date: trip.start_date.as_deref().and_then(|date| calendar::parse_date(date).ok()),
+            start_time: trip.start_time.as_deref().and_then(|time| time.parse().ok()),
+            schedule_relationship: trip.schedule_relationship
+                .and_then(|code| proto::trip_descriptor::ScheduleRelationship::try_from(code).ok())
+                .map(TripRelationship::from)
+                .unwrap_or(TripRelationship::Scheduled),
+            vehicle: trip_update.vehicle.as_ref().map(Vehicle::from),
+            timestamp: trip_update.timestamp.and_then(gtfs_rt::timestamp),
+            delay: trip_update.delay,
+            stop_time_updates: trip_update.stop_time_update.iter().map(StopTimeUpdate::from).collect(),
+        })
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        matches!(self.schedule_relationship, TripRelationship::Canceled | TripRelationship::Deleted)
+    }
+
+    // update_for returns the stop time update given for a stop time of the trip, if there is one.
+    pub fn update_for(&self, stop_time: &StopTime) -> Option<&StopTimeUpdate> {
+        self.stop_time_updates.iter().find(|stop_time_update| stop_time_update.matches(stop_time))
+    }
+
+    // join pairs each of the trip's stop times, in order of stop_sequence, with the delays predicted there.
+    // A stop without an update of its own takes the delay from the last stop before it which has one, as the
+    // specification has delays carry forward, or else the trip's delay. An update saying there's no data for a
+    // stop stops the delay carrying forward, while a skipped stop passes it on to the stops after it.
+    // Where an update gives the time of arrival or departure rather than a delay, it's measured against the stop's
+    // scheduled time from service_day, the instant the trip's service day starts at (noon minus 12 hours, local time);
+    // without it, such updates give no delay.
+    pub fn join<'a>(&'a self, stop_times: &'a [StopTime], service_day: Option<chrono::DateTime<chrono::Utc>>) -> Vec<JoinedStopTime<'a>> {
+        let mut stop_times = stop_times.iter().collect::<Vec<_>>();
+        stop_times.sort_by_key(|stop_time| stop_time.stop_sequence);
+        let scheduled = |time: Option<GtfsTime>| Some(service_day? + chrono::Duration::seconds(time?.seconds().into()));
+
+        let mut carried = self.delay;
+        stop_times.into_iter().map(|stop_time| {
+            let update = self.update_for(stop_time);
+            let (arrival_delay, departure_delay) = match update {
+                None => (carried, carried),
+                Some(update) => match update.schedule_relationship {
+                    StopTimeRelationship::Scheduled => {
+                        let arrival_time = scheduled(stop_time.arrival_time.or(stop_time.departure_time));
+                        let departure_time = scheduled(stop_time.departure_time.or(stop_time.arrival_time));
+                        let arrival_delay = update.arrival.as_ref().and_then(|arrival| arrival.delay_from(arrival_time));
+                        let departure_delay = update.departure.as_ref().and_then(|departure| departure.delay_from(departure_time));
+                        let arrival_delay = arrival_delay.or(departure_delay).or(carried);
+                        let departure_delay = departure_delay.or(arrival_delay);
+                        carried = departure_delay;
+                        (arrival_delay, departure_delay)
+                    },
+                    StopTimeRelationship::Skipped => (None, None),
+                    StopTimeRelationship::NoData | StopTimeRelationship::Unscheduled => {
+                        carried = None;
+                        (None, None)
+                    },
+                },
+            };
+            JoinedStopTime { stop_time, update, arrival_delay, departure_delay }
+        }).collect()
+    }
+}
+
+// JoinedStopTime is a scheduled stop time of a trip, along with what the trip's update predicts there.
+#[derive(Debug, Clone, Copy)]
+pub struct JoinedStopTime<'a> {
+    pub stop_time: &'a StopTime,
+    // update is the stop time update given for this stop, if there's one.
+    pub update: Option<&'a StopTimeUpdate>,
+    // arrival_delay and departure_delay are the predicted delays in seconds, negative if early; see TripUpdate::join.
+    pub arrival_delay: Option<i32>,
+    pub departure_delay: Option<i32>,
+}
+
+impl JoinedStopTime<'_> {
+    pub fn is_skipped(&self) -> bool {
+        self.update.is_some_and(|update| update.schedule_relationship == StopTimeRelationship::Skipped)
+    }
+}
+
+// TripRelationship is how an updated trip relates to the static schedule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TripRelationship {
+    Scheduled,
+    Added,
+    Unscheduled,
+    Canceled,
+    Replacement,
+    Duplicated,
+    Deleted,
+}
+
+impl TripRelationship {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TripRelationship::Scheduled => "scheduled",
+            TripRelationship::Added => "added",
+            TripRelationship::Unscheduled => "unscheduled",
+            TripRelationship::Canceled => "canceled",
+            TripRelationship::Replacement => "replacement",
+            TripRelationship::Duplicated => "duplicated",
+            TripRelationship::Deleted => "deleted",
+        }
+    }
+}
+
+impl From<proto::trip_descriptor::ScheduleRelationship> for TripRelationship {
+    fn from(relationship: proto::trip_descriptor::ScheduleRelationship) -> Self {
+        use proto::trip_descriptor::ScheduleRelationship;
+        match relationship {
+            ScheduleRelationship::Scheduled => TripRelationship::Scheduled,
+            ScheduleRelationship::Added => TripRelationship::Added,
+            ScheduleRelationship::Unscheduled => TripRelationship::Unscheduled,
+            ScheduleRelationship::Canceled => TripRelationship::Canceled,
+            ScheduleRelationship::Replacement => TripRelationship::Replacement,
+            ScheduleRelationship::Duplicated => TripRelationship::Duplicated,
+            ScheduleRelationship::Deleted => TripRelationship::Deleted,
+        }
+    }
+}
+
+// Vehicle identifies the vehicle running a trip.
+#[derive(Debug, Clone)]
+pub struct Vehicle {
+    pub id: Option<String>,
+    // label is what riders see, such as a number painted on the vehicle.
+    pub label: Option<String>,
+    pub license_plate: Option<String>,
+}
+
+impl Vehicle {
+    // name gives what the vehicle is known by: its label, or else its ID.
+    pub fn name(&self) -> Option<&str> {
+        self.label.as_deref().or(self.id.as_deref())
+    }
+}
+
+impl From<&proto::VehicleDescriptor> for Vehicle {
+    fn from(vehicle: &proto::VehicleDescriptor) -> Self {
+        Vehicle {
+            id: vehicle.id.clone(),
+            label: vehicle.label.clone(),
+            license_plate: vehicle.license_plate.clone(),
+        }
+    }
+}
+
+// StopTimeUpdate is a prediction for one of the stops of an updated trip, given by stop_sequence, stop_id or both.
+#[derive(Debug, Clone)]
+pub struct StopTimeUpdate {
+    pub stop_sequence: Option<usize>,
+    pub stop_id: Option<String>,
+    pub arrival: Option<StopTimeEvent>,
+    pub departure: Option<StopTimeEvent>,
+    pub schedule_relationship: StopTimeRelationship,
+}
+
+impl StopTimeUpdate {
+    // matches reports whether the update is for stop_time: by stop_sequence where it's given, since a trip may call
+    // at the same stop more than once, and otherwise by stop_id.
+    pub fn matches(&self, stop_time: &StopTime) -> bool {
+        match (self.stop_sequence, &self.stop_id) {
+            (Some(stop_sequence), _) => stop_sequence == stop_time.stop_sequence,
+            (None, Some(stop_id)) => stop_time.stop_id.as_ref() == Some(stop_id),
+            (None, None) => false,
+        }
+    }
+}
+
+impl From<&proto::trip_update::StopTimeUpdate> for StopTimeUpdate {
+    fn from(stop_time_update: &proto::trip_update::StopTimeUpdate) -> Self {
+        StopTimeUpdate {
+            stop_sequence: stop_time_update.stop_sequence.and_then(|stop_sequence| usize::try_from(stop_sequence).ok()),
+            stop_id: stop_time_update.stop_id.clone(),
+            arrival: stop_time_update.arrival.as_ref().map(StopTimeEvent::from),
+            departure: stop_time_update.departure.as_ref().map(StopTimeEvent::from),
+            schedule_relationship: stop_time_update.schedule_relationship
+                .and_then(|code| proto::trip_update::stop_time_update::ScheduleRelationship::try_from(code).ok())
+                .map(StopTimeRelationship::from)
+                .unwrap_or(StopTimeRelationship::Scheduled),
+        }
+    }
+}
+
+// StopTimeEvent is the predicted arrival at or departure from a stop, as a delay, an absolute time, or both.
+#[derive(Debug, Clone, Copy)]
+pub struct StopTimeEvent {
+    // delay is in seconds, negative if early.
+    pub delay: Option<i32>,
+    pub time: Option<chrono::DateTime<chrono::Utc>>,
+    // uncertainty is the expected error of the prediction in seconds; 0 means it's certain, e.g. the vehicle has left.
+    pub uncertainty: Option<i32>,
+}
+
+impl StopTimeEvent {
+    // delay_from gives the delay of the event: the delay it gives, or else how long after the scheduled instant its
+    // time is.
+    pub fn delay_from(&self, scheduled: Option<chrono::DateTime<chrono::Utc>>) -> Option<i32> {
+        self.delay.or_else(|| i32::try_from((self.time? - scheduled?).num_seconds()).ok())
+    }
+}
+
+impl From<&proto::trip_update::StopTimeEvent> for StopTimeEvent {
+    fn from(event: &proto::trip_update::StopTimeEvent) -> Self {
+        StopTimeEvent {
+            delay: event.delay,
+            time: event.time.and_then(|seconds| chrono::DateTime::from_timestamp(seconds, 0)),
+            uncertainty: event.uncertainty,
+        }
+    }
+}
+
+// StopTimeRelationship is how an updated stop time relates to the static schedule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopTimeRelationship {
+    Scheduled,
+    // Skipped means the vehicle won't stop there.
+    Skipped,
+    // NoData means there's no prediction for the stop.
+    NoData,
+    // Unscheduled is for trips of frequency-based routes which don't run to a fixed schedule.
+    Unscheduled,
+}
+
+impl StopTimeRelationship {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StopTimeRelationship::Scheduled => "scheduled",
+            StopTimeRelationship::Skipped => "skipped",
+            StopTimeRelationship::NoData => "no data",
+            StopTimeRelationship::Unscheduled => "unscheduled",
+        }
+    }
+}
+
+impl From<proto::trip_update::stop_time_update::ScheduleRelationship> for StopTimeRelationship {
+    fn from(relationship: proto::trip_update::stop_time_update::ScheduleRelationship) -> Self {
+        use proto::trip_update::stop_time_update::ScheduleRelationship;
+        match relationship {
+            ScheduleRelationship::Scheduled => StopTimeRelationship::Scheduled,
+            ScheduleRelationship::Skipped => StopTimeRelationship::Skipped,
+            ScheduleRelationship::NoData => StopTimeRelationship::NoData,
+            ScheduleRelationship::Unscheduled => StopTimeRelationship::Unscheduled,
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::stop_times;
+use crate::gtfs::trips;
+use crate::gtfs::view::ScheduleView;
+use std::ops;
+use std::time;
+
+// Headways summarizes the intervals between consecutive departures of a route in one direction from a single stop.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Headways {
+    // direction_id is the direction of the trips measured, None for trips which don't give one.
+    pub direction_id: Option<trips::Direction>,
+    // stop_id is the stop the departures were measured at; see ScheduleView::headways.
+    pub stop_id: String,
+    pub departures: usize,
+    pub min: time::Duration,
+    pub mean: time::Duration,
+    pub max: time::Duration,
+}
+
+impl GtfsSchedule {
+    // headways measures the intervals between a route's departures on a service date. See ScheduleView::headways.
+    pub fn headways(
+        &self,
+        route_id: &str,
+        direction: Option<trips::Direction>,
+        date: chrono::NaiveDate,
+        window: ops::Range<stop_times::GtfsTime>,
+    ) -> Vec<Headways> {
+        self.view().headways(route_id, direction, date, window)
+    }
+}
+
+impl<'a> ScheduleView<'a> {
+    // headways measures the intervals between the departures of a route's trips in the view which run on date and
+    // depart within window, separately for each direction_id the trips give, or only for direction if there is one,
+    // since departures in opposite directions don't share a stop to be measured at. Departures are measured at the
+    // first timepoint of the direction's most common journey pattern, or its first stop if it marks no exact
+    // timepoints. Trips listed in frequencies.txt depart once per headway for each of their frequencies, rather than
+    // at their stop times. Directions in which fewer than two departures fall within window are left out.
+    pub fn headways(
+        &self,
+        route_id: &str,
+        direction: Option<trips::Direction>,
+        date: chrono::NaiveDate,
+        window: ops::Range<stop_times::GtfsTime>,
+    ) -> Vec<Headways> {
+        [Some(trips::Direction::A), Some(trips::Direction::B), None].into_iter()
+            .filter(|direction_id| direction.is_none_or(|direction| *direction_id == Some(direction)))
+            .filter_map(|direction_id| self.headways_in_direction(route_id, direction_id, date, window.clone()))
+            .collect()
+    }
+
+    // headways_in_direction measures the intervals between the departures of a route's trips with direction_id.
+    fn headways_in_direction(
+        &self,
+        route_id: &str,
+        direction_id: Option<trips::Direction>,
+        date: chrono::NaiveDate,
+        window: ops::Range<stop_times::GtfsTime>,
+    ) -> Option<Headways> {
+        let stop_id = self.timepoint_stop(route_id, direction_id)?;
+
+        let mut departure_times = self.trips_of_route(route_id)
+            .filter(|trip| trip.direction_id == direction_id)
+            .filter(|trip| self.schedule.service_runs_on(&trip.service_id, date))
+            .flat_map(|trip| self.departure_times_of_trip(&trip.trip_id, stop_id))
+            .filter(|departure_time| window.contains(departure_time))
+            .collect::<Vec<_>>();
+        departure_times.sort();
+
+        let intervals = departure_times.windows(2)
+            .map(|pair| pair[1].seconds() - pair[0].seconds())
+            .collect::<Vec<_>>();
+        (!intervals.is_empty()).then(|| Headways {
+            direction_id,
+            stop_id: stop_id.to_string(),
+            departures: departure_times.len(),
+            min: time::Duration::from_secs(*intervals.iter().min().unwrap() as u64),
+            mean: time::Duration::from_secs_f64(intervals.iter().map(|interval| *interval as f64).sum::<f64>() / intervals.len() as f64),
+            max: time::Duration::from_secs(*intervals.iter().max().unwrap() as u64),
+        })
+    }
+
+    // timepoint_stop picks the stop at which a route's headways in a direction are measured: the first stop of its most
+    // common journey pattern in that direction at which that pattern's trips keep exact times.
+    fn timepoint_stop(&self, route_id: &str, direction_id: Option<trips::Direction>) -> Option<&'a str> {
+        let pattern = self.patterns_of_route(route_id)
+            .into_iter()
+            .map(|(pattern, _)| pattern)
+            .find(|pattern| pattern.direction_id == direction_id)?;
+        let trip_stop_times = self.schedule.stop_times.stop_times.get(pattern.trip_ids.first()?)?;
+        pattern.stop_ids.iter()
+            .find(|stop_id| trip_stop_times.iter().any(|stop_time| {
+                stop_time.stop_id.as_ref() == Some(*stop_id)
+                    && stop_time.departure_time.or(stop_time.arrival_time).is_some()
+                    && !matches!(stop_time.timepoint, Some(stop_times::Timepoint::Approximate))
+            }))
+            .or(pattern.stop_ids.first())
+            .map(String::as_str)
+    }
+
+    // departure_times_of_trip lists the times a trip departs from a stop. A trip with frequencies departs once for each
+    // time it starts, offset by the time its stop times take to reach the stop.
+    fn departure_times_of_trip(&self, trip_id: &str, stop_id: &str) -> Vec<stop_times::GtfsTime> {
+        let mut trip_stop_times = self.stop_times_of_trip(trip_id).collect::<Vec<_>>();
+        trip_stop_times.sort_by_key(|stop_time| stop_time.stop_sequence);
+        let time_at = |stop_time: &stop_times::StopTime| stop_time.departure_time.or(stop_time.arrival_time);
+        let Some(departure_time) = trip_stop_times.iter()
+            .find(|stop_time| stop_time.stop_id.as_deref() == Some(stop_id))
+            .and_then(|stop_time| time_at(stop_time))
+        else {
+            return Vec::new();
+        };
+
+        match self.schedule.frequencies.frequencies.get(trip_id) {
+            None => vec![departure_time],
+            Some(frequencies) => {
+                let first_time = trip_stop_times.iter().find_map(|stop_time| time_at(stop_time)).unwrap_or(departure_time);
+                let offset = departure_time.seconds().saturating_sub(first_time.seconds());
+                frequencies.iter()
+                    .flat_map(|frequency| frequency.start_times())
+                    .map(|start_time| stop_times::GtfsTime::from_seconds(start_time.seconds() + offset))
+                    .collect()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::testing;
+    use crate::gtfs::trips::Direction;
+
+    // schedule runs route R2 in both directions: T3 and T2 from S2 to Y at 08:12 and 08:15, and T4 and T5 back from
+    // Y to S2 at 09:30 and 10:00.
+    fn schedule() -> GtfsSchedule {
+        let mut schedule = testing::schedule();
+        for (trip_id, direction) in [("T2", Direction::A), ("T3", Direction::A), ("T4", Direction::B), ("T5", Direction::B)] {
+            schedule.trips.trips.get_mut(trip_id).unwrap().direction_id = Some(direction);
+        }
+        for (trip_id, departure, arrival) in [("T4", "09:30:00", "09:45:00"), ("T5", "10:00:00", "10:15:00")] {
+            let stop_times = testing::trip_stop_times(trip_id, &[("Y", departure), ("S2", arrival)]);
+            schedule.stop_times.stop_times.insert(trip_id.to_string(), stop_times);
+        }
+        schedule
+    }
+
+    fn all_day() -> ops::Range<stop_times::GtfsTime> {
+        testing::time("00:00:00")..testing::time("48:00:00")
+    }
+
+    #[test]
+    fn headways_are_measured_in_each_direction() {
+        let headways = schedule().headways("R2", None, testing::date(2025, 6, 2), all_day());
+        assert_eq!(headways, vec![
+            Headways {
+                direction_id: Some(Direction::A),
+                stop_id: "S2".to_string(),
+                departures: 2,
+                min: time::Duration::from_secs(180),
+                mean: time::Duration::from_secs(180),
+                max: time::Duration::from_secs(180),
+            },
+            Headways {
+                direction_id: Some(Direction::B),
+                stop_id: "Y".to_string(),
+                departures: 2,
+                min: time::Duration::from_secs(1800),
+                mean: time::Duration::from_secs(1800),
+                max: time::Duration::from_secs(1800),
+            },
+        ]);
+    }
+
+    #[test]
+    fn headways_in_a_direction_given() {
+        let headways = schedule().headways("R2", Some(Direction::B), testing::date(2025, 6, 2), all_day());
+        assert_eq!(headways.iter().map(|headways| headways.direction_id).collect::<Vec<_>>(), vec![Some(Direction::B)]);
+    }
+
+    #[test]
+    fn headways_without_directions_are_measured_together() {
+        let headways = testing::schedule().headways("R2", None, testing::date(2025, 6, 2), all_day());
+        assert_eq!(headways.len(), 1);
+        assert_eq!((headways[0].direction_id, headways[0].departures), (None, 4));
+        assert_eq!(headways[0].min, time::Duration::from_secs(180));
+    }
+
+    #[test]
+    fn departures_outside_the_window_are_not_measured() {
+        let window = testing::time("08:00:00")..testing::time("09:00:00");
+        assert_eq!(schedule().headways("R2", Some(Direction::B), testing::date(2025, 6, 2), window), vec![]);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::patterns;
+use crate::gtfs::stops;
+use crate::gtfs::trips;
+use crate::gtfs::stop_times;
+use std::collections::HashMap;
+
+// GtfsIndex holds the reverse lookups which would otherwise need a scan of a whole table.
+// Records are referred to by ID, so the index can be stored alongside the schedule it was built from.
+#[derive(Debug, Clone, Default)]
+pub struct GtfsIndex {
+    // the stop times visiting each stop, as (trip_id, position) pairs, where position indexes into the trip's stop times.
+    pub stop_times_by_stop_id: HashMap<String, Vec<(String, usize)>>,
+    pub trips_by_route_id: HashMap<String, Vec<String>>,
+    pub trips_by_service_id: HashMap<String, Vec<String>>,
+    pub trips_by_block_id: HashMap<String, Vec<String>>,
+    pub children_by_parent_station: HashMap<String, Vec<String>>,
+    pub patterns: patterns::Patterns,
+}
+
+impl GtfsIndex {
+    pub fn build(schedule: &GtfsSchedule) -> Self {
+        let mut index = GtfsIndex::default();
+
+        for (trip_id, trip_stop_times) in &schedule.stop_times.stop_times {
+            for (position, stop_time) in trip_stop_times.iter().enumerate() {
+                if let Some(stop_id) = &stop_time.stop_id {
+                    index.stop_times_by_stop_id.entry(stop_id.clone()).or_default().push((trip_id.clone(), position));
+                }
+            }
+        }
+
+        for trip in &schedule.trips {
+            index.trips_by_route_id.entry(trip.route_id.clone()).or_default().push(trip.trip_id.clone());
+            index.trips_by_service_id.entry(trip.service_id.clone()).or_default().push(trip.trip_id.clone());
+            if let Some(block_id) = &trip.block_id {
+                index.trips_by_block_id.entry(block_id.clone()).or_default().push(trip.trip_id.clone());
+            }
+        }
+
+        for stop in &schedule.stops {
+            if let Some(parent_station) = stop.parent_station() {
+                index.children_by_parent_station.entry(parent_station.to_string()).or_default().push(stop.stop_id.clone());
+            }
+        }
+
+        index.patterns = patterns::Patterns::build(schedule);
+
+        index
+    }
+}
+
+// These lookups go through the schedule's index, which is built the first time one of them is used.
+impl GtfsSchedule {
+    // index returns the schedule's index, building it if it hasn't been built yet.
+    pub fn index(&self) -> &GtfsIndex {
+        self.index_cache.get_or_init(|| GtfsIndex::build(self))
+    }
+
+    // invalidate_index discards the schedule's index, along with the stops' spatial index, so that they are rebuilt on next use.
+    // The mutators call this themselves; it only needs to be called after editing the tables directly.
+    pub fn invalidate_index(&mut self) {
+        self.index_cache.take();
+        self.stops.invalidate_spatial_index();
+    }
+
+    // stop_times_at yields the stop times which visit the given stop.
+    pub fn stop_times_at<'a>(&'a self, stop_id: &str) -> impl Iterator<Item = &'a stop_times::StopTime> + 'a {
+        self.index().stop_times_by_stop_id.get(stop_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|(trip_id, position)| self.stop_times.stop_times.get(trip_id)?.get(*position))
+    }
+
+    // trips_of_route yields the trips belonging to the given route.
+    pub fn trips_of_route<'a>(&'a self, route_id: &str) -> impl Iterator<Item = &'a trips::Trip> + 'a {
+        self.index().trips_by_route_id.get(route_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|trip_id| self.trips.trips.get(trip_id))
+    }
+
+    // trips_of_service yields the trips which run on the given service.
+    pub fn trips_of_service<'a>(&'a self, service_id: &str) -> impl Iterator<Item = &'a trips::Trip> + 'a {
+        self.index().trips_by_service_id.get(service_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|trip_id| self.trips.trips.get(trip_id))
+    }
+
+    // trips_of_block yields the trips which share the given block_id, and so are run by the same vehicle.
+    pub fn trips_of_block<'a>(&'a self, block_id: &str) -> impl Iterator<Item = &'a trips::Trip> + 'a {
+        self.index().trips_by_block_id.get(block_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|trip_id| self.trips.trips.get(trip_id))
+    }
+
+    // child_stops yields the stops which name the given stop as their parent station.
+    pub fn child_stops<'a>(&'a self, stop_id: &str) -> impl Iterator<Item = &'a stops::Stop> + 'a {
+        self.index().children_by_parent_station.get(stop_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|child_id| self.stops.stops.get(child_id))
+    }
+}
@@ -0,0 +1,245 @@
+use crate::gtfs::GtfsSchedule;
+use std::collections;
+use std::fmt;
+
+// LinkPolicy decides what link does with references that don't resolve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkPolicy {
+    // Error fails the link if any reference is dangling, leaving the schedule untouched.
+    Error,
+    // Warn reports dangling references, leaving the schedule untouched.
+    Warn,
+    // Prune removes the records holding dangling references, along with any records which dangle as a result.
+    Prune,
+}
+
+// DanglingReference is a reference from one record to another which doesn't exist.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DanglingReference {
+    // the file the referring record belongs to, e.g. trips.txt.
+    pub file: &'static str,
+    // the referring record, identified by its ID; stop times are identified as "<trip_id> #<stop_sequence>".
+    pub record: String,
+    pub field: &'static str,
+    // the ID which failed to resolve.
+    pub target: String,
+}
+
+impl fmt::Display for DanglingReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} refers to missing {} {}", self.file, self.record, self.field, self.target)
+    }
+}
+
+// LinkReport lists the dangling references found by link.
+#[derive(Debug, Clone, Default)]
+pub struct LinkReport {
+    pub dangling: Vec<DanglingReference>,
+}
+
+impl LinkReport {
+    pub fn is_empty(&self) -> bool {
+        self.dangling.is_empty()
+    }
+}
+
+impl fmt::Display for LinkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} dangling references", self.dangling.len())?;
+        for reference in &self.dangling {
+            writeln!(f, "  {}", reference)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct LinkError(pub LinkReport);
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Schedule has {}", self.0)
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+impl GtfsSchedule {
+    // dangling_references checks that every trip's route_id, every stop time's trip_id and stop_id,
+    // every frequency's trip_id and every stop's parent_station refers to a record in the schedule.
+    pub fn dangling_references(&self) -> LinkReport {
+        let mut dangling = Vec::new();
+
+        for stop in &self.stops {
+            if let Some(parent_station) = stop.parent_station().filter(|parent_station| !self.stops.stops.contains_key(*parent_station)) {
+                dangling.push(DanglingReference {
+                    file: "stops.txt",
+                    record: stop.stop_id.clone(),
+                    field: "parent_station",
+                    target: parent_station.to_string(),
+                });
+            }
+        }
+
+        for trip in &self.trips {
+            if !self.routes.routes.contains_key(&trip.route_id) {
+                dangling.push(DanglingReference {
+                    file: "trips.txt",
+                    record: trip.trip_id.clone(),
+                    field: "route_id",
+                    target: trip.route_id.clone(),
+                });
+            }
+        }
+
+        for stop_time in self.stop_times.iter() {
+            let record = || format!("{} #{}", stop_time.trip_id, stop_time.stop_sequence);
+            if !self.trips.trips.contains_key(&stop_time.trip_id) {
+                dangling.push(DanglingReference {
+                    file: "stop_times.txt",
+                    record: record(),
+                    field: "trip_id",
+                    target: stop_time.trip_id.clone(),
+                });
+            }
+            if let Some(stop_id) = stop_time.stop_id.as_ref().filter(|stop_id| !self.stops.stops.contains_key(*stop_id)) {
+                dangling.push(DanglingReference {
+                    file: "stop_times.txt",
+                    record: record(),
+                    field: "stop_id",
+                    target: stop_id.clone(),
+                });
+            }
+        }
+
+        for frequency in self.frequencies.iter().filter(|frequency| !self.trips.trips.contains_key(&frequency.trip_id)) {
+            dangling.push(DanglingReference {
+                file: "frequencies.txt",
+                record: format!("{} {}", frequency.trip_id, frequency.start_time),
+                field: "trip_id",
+                target: frequency.trip_id.clone(),
+            });
+        }
+
+        LinkReport { dangling }
+    }
+
+    // link resolves the references between the schedule's tables, handling dangling references according to policy.
+    // It is intended to be run once a schedule has been loaded. With LinkPolicy::Prune, the returned report
+    // includes the references left dangling by earlier pruning, as well as the ones originally present.
+    pub fn link(&mut self, policy: LinkPolicy) -> Result<LinkReport, LinkError> {
+        match policy {
+            LinkPolicy::Error => {
+                let report = self.dangling_references();
+                if report.is_empty() {
+                    Ok(report)
+                } else {
+                    Err(LinkError(report))
+                }
+            },
+            LinkPolicy::Warn => Ok(self.dangling_references()),
+            LinkPolicy::Prune => {
+                let mut report = LinkReport::default();
+                // removing a stop can leave its children and the stop times visiting it dangling in turn,
+                // so keep pruning until no dangling references remain.
+                loop {
+                    let pass = self.dangling_references();
+                    if pass.is_empty() {
+                        return Ok(report);
+                    }
+                    self.prune(&pass);
+                    report.dangling.extend(pass.dangling);
+                }
+            },
+        }
+    }
+
+    // prune removes the records holding the given dangling references.
+    fn prune(&mut self, report: &LinkReport) {
+        let records = |file: &str| report.dangling.iter()
+            .filter(|reference| reference.file == file)
+            .map(|reference| reference.record.clone())
+            .collect::<collections::HashSet<_>>();
+
+        let stop_ids = records("stops.txt");
+        self.stops.stops.retain(|stop_id, _| !stop_ids.contains(stop_id));
+
+        let trip_ids = records("trips.txt");
+        self.retain_trips(|trip| !trip_ids.contains(&trip.trip_id));
+
+        let stop_time_records = records("stop_times.txt");
+        self.stop_times.stop_times.values_mut().for_each(|stop_times| stop_times.retain(
+            |stop_time| !stop_time_records.contains(&format!("{} #{}", stop_time.trip_id, stop_time.stop_sequence))
+        ));
+        self.stop_times.stop_times.retain(|_, stop_times| !stop_times.is_empty());
+
+        let frequency_records = records("frequencies.txt");
+        self.frequencies.frequencies.values_mut().for_each(|frequencies| frequencies.retain(
+            |frequency| !frequency_records.contains(&format!("{} {}", frequency.trip_id, frequency.start_time))
+        ));
+        self.frequencies.frequencies.retain(|_, frequencies| !frequencies.is_empty());
+        self.invalidate_index();
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::testing;
+
+    // broken returns the test schedule with station S and route R1 removed from under the records referring to them.
+    fn broken() -> GtfsSchedule {
+        let mut schedule = testing::schedule();
+        schedule.stops.stops.remove("S");
+        schedule.routes.routes.remove("R1");
+        schedule
+    }
+
+    #[test]
+    fn a_consistent_schedule_links() {
+        let mut schedule = testing::schedule();
+        assert!(schedule.link(LinkPolicy::Error).unwrap().is_empty());
+        assert_eq!(schedule, testing::schedule());
+    }
+
+    #[test]
+    fn dangling_references_are_found_in_each_table() {
+        let mut schedule = broken();
+        schedule.trips.trips.remove("T2");
+        schedule.frequencies = testing::load("trip_id,start_time,end_time,headway_secs\nT2,06:00:00,07:00:00,600\n");
+        let mut dangling = schedule.dangling_references().dangling.iter()
+            .map(|reference| format!("{} {} {} {}", reference.file, reference.record, reference.field, reference.target))
+            .collect::<Vec<_>>();
+        dangling.sort();
+        assert_eq!(dangling, [
+            "frequencies.txt T2 06:00:00 trip_id T2",
+            "stop_times.txt T2 #1 trip_id T2",
+            "stop_times.txt T2 #2 trip_id T2",
+            "stops.txt S1 parent_station S",
+            "stops.txt S2 parent_station S",
+            "trips.txt T1 route_id R1",
+        ]);
+    }
+
+    #[test]
+    fn the_error_and_warn_policies_leave_the_schedule_untouched() {
+        let mut schedule = broken();
+        let e = schedule.link(LinkPolicy::Error).unwrap_err();
+        assert_eq!(e.0.dangling.len(), 3);
+        assert_eq!(schedule.link(LinkPolicy::Warn).unwrap().dangling.len(), 3);
+        assert_eq!(schedule.stops.stops.len(), 4);
+    }
+
+    #[test]
+    fn pruning_removes_what_dangles_as_a_result() {
+        let mut schedule = broken();
+        let report = schedule.link(LinkPolicy::Prune).unwrap();
+        assert!(schedule.dangling_references().is_empty());
+        // the platforms go with their station, and with them every stop time visiting them; T1 goes with its route.
+        let mut stop_ids = schedule.stops.stops.keys().map(String::as_str).collect::<Vec<_>>();
+        stop_ids.sort();
+        assert_eq!(stop_ids, ["X", "Y"]);
+        assert!(!schedule.trips.trips.contains_key("T1"));
+        assert!(schedule.stop_times.iter().all(|stop_time| stop_time.stop_id.as_deref() == Some("Y")));
+        assert!(report.dangling.iter().any(|reference| reference.file == "stop_times.txt" && reference.target == "S2"));
+    }
+}
@@ -0,0 +1,56 @@
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+// IntegrityCheck describes what a downloaded feed must satisfy before it is parsed.
+// It is intended for pinned deployments, which must not silently ingest a truncated or unexpected feed.
+#[derive(Debug, Clone, Default)]
+pub struct IntegrityCheck {
+    // the expected SHA-256 digest of the archive, as a hex string.
+    pub sha256: Option<String>,
+    // the minimum size of the archive in bytes.
+    pub min_size: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum IntegrityError {
+    TooSmall { min_size: usize, actual: usize },
+    ChecksumMismatch { expected: String, actual: String },
+}
+
+impl fmt::Display for IntegrityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooSmall { min_size, actual } => write!(f, "Feed is {} bytes, expected at least {}", actual, min_size),
+            Self::ChecksumMismatch { expected, actual } => write!(f, "Feed has SHA-256 {}, expected {}", actual, expected),
+        }
+    }
+}
+
+impl std::error::Error for IntegrityError {}
+
+impl IntegrityCheck {
+    pub fn sha256(digest: &str) -> Self {
+        Self { sha256: Some(digest.to_string()), ..Self::default() }
+    }
+
+    pub fn min_size(min_size: usize) -> Self {
+        Self { min_size: Some(min_size), ..Self::default() }
+    }
+
+    pub fn verify(&self, buf: &[u8]) -> Result<(), IntegrityError> {
+        if let Some(min_size) = self.min_size.filter(|min_size| buf.len() < *min_size) {
+            return Err(IntegrityError::TooSmall { min_size, actual: buf.len() });
+        }
+        if let Some(expected) = &self.sha256 {
+            let actual = sha256_hex(buf);
+            if !actual.eq_ignore_ascii_case(expected.trim()) {
+                return Err(IntegrityError::ChecksumMismatch { expected: expected.clone(), actual });
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn sha256_hex(buf: &[u8]) -> String {
+    Sha256::digest(buf).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
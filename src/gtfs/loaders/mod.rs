@@ -1 +1,36 @@
-pub mod zip_loader;
\ No newline at end of file
+pub mod zip_loader;
+pub mod multi_source_loader;
+pub mod tar_gz_loader;
+#[cfg(any(feature = "cli", feature = "realtime"))]
+pub mod url_loader;
+pub mod multi_feed_loader;
+pub mod integrity;
+
+use std::cell::RefCell;
+use std::time::Duration;
+
+thread_local! {
+    // TIMINGS collects how long each table took to parse while recorded runs a load on this thread.
+    static TIMINGS: RefCell<Option<Vec<(String, Duration)>>> = const { RefCell::new(None) };
+}
+
+// timed runs parse, which parses the table in file, and logs how long it took at debug level.
+pub(crate) fn timed<T>(file: &str, parse: impl FnOnce() -> T) -> T {
+    let started = std::time::Instant::now();
+    let parsed = parse();
+    let elapsed = started.elapsed();
+    log::debug!("Parsed {} in {:.2?}", file, elapsed);
+    TIMINGS.with_borrow_mut(|timings| if let Some(timings) = timings {
+        timings.push((file.to_string(), elapsed));
+    });
+    parsed
+}
+
+// recorded runs load, which loads a feed with any of the loaders, giving back what it returned along with how long
+// each table the loader parsed took, in the order they were parsed, e.g. to benchmark loading.
+pub fn recorded<T>(load: impl FnOnce() -> T) -> (T, Vec<(String, Duration)>) {
+    let outer = TIMINGS.replace(Some(Vec::new()));
+    let loaded = load();
+    let timings = TIMINGS.replace(outer).unwrap_or_default();
+    (loaded, timings)
+}
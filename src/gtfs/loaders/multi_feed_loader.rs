@@ -0,0 +1,244 @@
+use crate::gtfs;
+use crate::gtfs::error::GtfsError;
+use crate::gtfs::loaders::multi_source_loader::MultiSourceLoader;
+use crate::gtfs::loaders::tar_gz_loader::TarGzLoader;
+#[cfg(feature = "cli")]
+use crate::gtfs::loaders::url_loader::{DownloadError, UrlLoader};
+use crate::gtfs::loaders::zip_loader::ZipLoader;
+use crate::gtfs::store::sqlite::SqliteStore;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path;
+
+// FeedSource identifies where a single feed is read from.
+// A path may point to an archive, to a directory of .txt files or to a SqliteStore's database.
+// URLs require the cli feature, which provides the downloader.
+#[derive(Debug, Clone)]
+pub enum FeedSource {
+    #[cfg(feature = "cli")]
+    Url(String),
+    Path(path::PathBuf),
+}
+
+// FeedLoadError is an error in reaching a feed's files, before any of them is loaded.
+// Errors in the files themselves are reported by the loader which reads them.
+#[derive(Debug)]
+pub enum FeedLoadError {
+    #[cfg(feature = "cli")]
+    DownloadError(DownloadError),
+    ReadError(path::PathBuf, io::Error),
+    ZipError(zip::result::ZipError),
+}
+
+impl fmt::Display for FeedLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "cli")]
+            Self::DownloadError(e) => write!(f, "Failed to download feed: {}", e),
+            Self::ReadError(path, e) => write!(f, "Failed to read {}: {}", path.display(), e),
+            Self::ZipError(e) => write!(f, "Failed to open zip archive: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FeedLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "cli")]
+            Self::DownloadError(e) => e.source(),
+            Self::ReadError(_, e) => e.source(),
+            Self::ZipError(e) => e.source(),
+        }
+    }
+}
+
+impl FeedSource {
+    pub fn load(&self) -> Result<gtfs::GtfsSchedule, GtfsError> {
+        match self {
+            #[cfg(feature = "cli")]
+            FeedSource::Url(url) => load_archive(
+                UrlLoader::new(url).download().map_err(FeedLoadError::DownloadError)?
+            ),
+            FeedSource::Path(path) if path.is_dir() => {
+                let open = |file: &str| fs::File::open(path.join(file))
+                    .map_err(|e| FeedLoadError::ReadError(path.join(file), e));
+                let mut loader = MultiSourceLoader::new(
+                    open("stops.txt")?,
+                    open("routes.txt")?,
+                    open("trips.txt")?,
+                    open("stop_times.txt")?,
+                );
+                if path.join("agency.txt").exists() {
+                    loader = loader.with_agency(open("agency.txt")?);
+                }
+                if path.join("calendar.txt").exists() {
+                    loader = loader.with_calendar(open("calendar.txt")?);
+                }
+                if path.join("calendar_dates.txt").exists() {
+                    loader = loader.with_calendar_dates(open("calendar_dates.txt")?);
+                }
+                if path.join("shapes.txt").exists() {
+                    loader = loader.with_shapes(open("shapes.txt")?);
+                }
+                if path.join("frequencies.txt").exists() {
+                    loader = loader.with_frequencies(open("frequencies.txt")?);
+                }
+                if path.join("transfers.txt").exists() {
+                    loader = loader.with_transfers(open("transfers.txt")?);
+                }
+                if path.join("pathways.txt").exists() {
+                    loader = loader.with_pathways(open("pathways.txt")?);
+                }
+                if path.join("fare_attributes.txt").exists() {
+                    loader = loader.with_fare_attributes(open("fare_attributes.txt")?);
+                }
+                if path.join("fare_rules.txt").exists() {
+                    loader = loader.with_fare_rules(open("fare_rules.txt")?);
+                }
+                if path.join("fare_products.txt").exists() {
+                    loader = loader.with_fare_products(open("fare_products.txt")?);
+                }
+                if path.join("fare_leg_rules.txt").exists() {
+                    loader = loader.with_fare_leg_rules(open("fare_leg_rules.txt")?);
+                }
+                if path.join("stop_areas.txt").exists() {
+                    loader = loader.with_stop_areas(open("stop_areas.txt")?);
+                }
+                loader.load()
+            },
+            FeedSource::Path(path) if SqliteStore::is_store(path) => SqliteStore::open(path)
+                .and_then(|store| store.load())
+                .map_err(GtfsError::from),
+            FeedSource::Path(path) => load_archive(
+                fs::read(path).map_err(|e| FeedLoadError::ReadError(path.clone(), e))?
+            ),
+        }
+    }
+}
+
+// FeedSource implements From<&str> by treating http:// and https:// URLs as URLs, and anything else as a path.
+// Without the cli feature, everything is treated as a path.
+impl From<&str> for FeedSource {
+    fn from(s: &str) -> Self {
+        #[cfg(feature = "cli")]
+        if s.starts_with("http://") || s.starts_with("https://") {
+            return FeedSource::Url(s.to_string());
+        }
+        FeedSource::Path(path::PathBuf::from(s))
+    }
+}
+
+impl fmt::Display for FeedSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "cli")]
+            FeedSource::Url(url) => write!(f, "{}", url),
+            FeedSource::Path(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+// load_archive interprets an in-memory feed as a tarball if it starts with the gzip magic number,
+// and as a zip archive otherwise.
+pub fn load_archive(buf: Vec<u8>) -> Result<gtfs::GtfsSchedule, GtfsError> {
+    if buf.starts_with(&[0x1f, 0x8b]) {
+        TarGzLoader::new(io::Cursor::new(buf)).load()
+    } else {
+        ZipLoader::new(zip::ZipArchive::new(io::Cursor::new(buf)).map_err(FeedLoadError::ZipError)?).load()
+    }
+}
+
+// MultiFeedLoader loads several feeds and merges them into a single schedule.
+// Each feed's IDs are prefixed with "<name>:" so that feeds from different agencies can't collide.
+#[derive(Default)]
+pub struct MultiFeedLoader {
+    pub feeds: Vec<(String, FeedSource)>,
+}
+
+#[derive(Debug)]
+pub struct MultiFeedLoaderError(pub String, pub Box<GtfsError>);
+
+impl fmt::Display for MultiFeedLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to load feed {}: {}", self.0, self.1)
+    }
+}
+
+impl std::error::Error for MultiFeedLoaderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.1.source()
+    }
+}
+
+impl MultiFeedLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_feed(mut self, name: &str, source: FeedSource) -> Self {
+        self.feeds.push((name.to_string(), source));
+        self
+    }
+
+    pub fn load(&self) -> Result<gtfs::GtfsSchedule, MultiFeedLoaderError> {
+        Ok(gtfs::GtfsSchedule::merge(
+            self.feeds.iter()
+                .map(|(name, source)| source.load()
+                    .map(|schedule| schedule.with_id_prefix(&format!("{}:", name)))
+                    .map_err(|e| MultiFeedLoaderError(name.clone(), Box::new(e))))
+                .collect::<Result<Vec<_>, _>>()?
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::testing;
+    use crate::gtfs::writers::zip_writer::{self, ZipWriter};
+
+    #[test]
+    fn a_feed_is_read_from_each_kind_of_path() {
+        let dir = testing::temp_dir("feed_source");
+        let feed_dir = dir.join("feed");
+        zip_writer::write_dir(&feed_dir, &testing::feed()).unwrap();
+        ZipWriter::new(fs::File::create(dir.join("feed.zip")).unwrap()).write(&testing::feed()).unwrap();
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default()));
+        builder.append_dir_all("feed", &feed_dir).unwrap();
+        fs::write(dir.join("feed.tar.gz"), builder.into_inner().unwrap().finish().unwrap()).unwrap();
+        SqliteStore::open(dir.join("feed.db")).unwrap().save(&testing::feed()).unwrap();
+
+        for path in [feed_dir, dir.join("feed.zip"), dir.join("feed.tar.gz"), dir.join("feed.db")] {
+            assert_eq!(FeedSource::Path(path.clone()).load().unwrap(), testing::feed(), "{}", path.display());
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn feeds_are_merged_with_their_ids_prefixed() {
+        let dir = testing::temp_dir("multi_feed");
+        zip_writer::write_dir(&dir, &testing::schedule()).unwrap();
+        let schedule = MultiFeedLoader::new()
+            .with_feed("a", FeedSource::Path(dir.clone()))
+            .with_feed("b", FeedSource::Path(dir.clone()))
+            .load()
+            .unwrap();
+        assert_eq!(schedule.trips.trips.len(), 10);
+        let trip = &schedule.trips.trips["b:T1"];
+        assert_eq!((trip.route_id.as_str(), trip.service_id.as_str()), ("b:R1", "b:D"));
+        let stop_ids = schedule.stop_times.stop_times["a:T1"].iter().map(|stop_time| stop_time.stop_id.as_deref().unwrap()).collect::<Vec<_>>();
+        assert_eq!(stop_ids, ["a:X", "a:S1"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_missing_feed_is_named_in_the_error() {
+        let e = MultiFeedLoader::new()
+            .with_feed("a", FeedSource::Path(path::PathBuf::from("/nonexistent/feed.zip")))
+            .load()
+            .unwrap_err();
+        assert_eq!(e.0, "a");
+        assert!(matches!(*e.1, GtfsError::Feed(FeedLoadError::ReadError(..))), "{:?}", e);
+    }
+}
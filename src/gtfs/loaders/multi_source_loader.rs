@@ -0,0 +1,233 @@
+use crate::gtfs;
+use crate::gtfs::error::GtfsError;
+use crate::gtfs::loaders;
+use crate::gtfs::agency;
+use crate::gtfs::calendar;
+use crate::gtfs::stops;
+use crate::gtfs::routes;
+use crate::gtfs::trips;
+use crate::gtfs::stop_times;
+use crate::gtfs::shapes;
+use crate::gtfs::frequencies;
+use crate::gtfs::transfers;
+use crate::gtfs::pathways;
+use crate::gtfs::fares;
+use crate::gtfs::fare_products;
+use std::io;
+
+// MultiSourceLoader loads a GTFS schedule from a separate reader for each table.
+// This covers feeds that are published as plain .txt files rather than as an archive,
+// and is the common backend for archive formats which can be unpacked into per-table readers.
+// Tables which the GTFS reference doesn't require may be left unset, in which case they load as empty.
+pub struct MultiSourceLoader {
+    pub agency: Option<Box<dyn io::Read>>,
+    pub stops: Box<dyn io::Read>,
+    pub routes: Box<dyn io::Read>,
+    pub trips: Box<dyn io::Read>,
+    pub stop_times: Box<dyn io::Read>,
+    pub calendar: Option<Box<dyn io::Read>>,
+    pub calendar_dates: Option<Box<dyn io::Read>>,
+    pub shapes: Option<Box<dyn io::Read>>,
+    pub frequencies: Option<Box<dyn io::Read>>,
+    pub transfers: Option<Box<dyn io::Read>>,
+    pub pathways: Option<Box<dyn io::Read>>,
+    pub fare_attributes: Option<Box<dyn io::Read>>,
+    pub fare_rules: Option<Box<dyn io::Read>>,
+    pub fare_products: Option<Box<dyn io::Read>>,
+    pub fare_leg_rules: Option<Box<dyn io::Read>>,
+    pub stop_areas: Option<Box<dyn io::Read>>,
+}
+
+impl MultiSourceLoader {
+    pub fn new(
+        stops: impl io::Read + 'static,
+        routes: impl io::Read + 'static,
+        trips: impl io::Read + 'static,
+        stop_times: impl io::Read + 'static,
+    ) -> Self {
+        Self {
+            agency: None,
+            stops: Box::new(stops),
+            routes: Box::new(routes),
+            trips: Box::new(trips),
+            stop_times: Box::new(stop_times),
+            calendar: None,
+            calendar_dates: None,
+            shapes: None,
+            frequencies: None,
+            transfers: None,
+            pathways: None,
+            fare_attributes: None,
+            fare_rules: None,
+            fare_products: None,
+            fare_leg_rules: None,
+            stop_areas: None,
+        }
+    }
+
+    pub fn with_agency(self, agency: impl io::Read + 'static) -> Self {
+        Self { agency: Some(Box::new(agency)), ..self }
+    }
+
+    pub fn with_calendar(self, calendar: impl io::Read + 'static) -> Self {
+        Self { calendar: Some(Box::new(calendar)), ..self }
+    }
+
+    pub fn with_calendar_dates(self, calendar_dates: impl io::Read + 'static) -> Self {
+        Self { calendar_dates: Some(Box::new(calendar_dates)), ..self }
+    }
+
+    pub fn with_shapes(self, shapes: impl io::Read + 'static) -> Self {
+        Self { shapes: Some(Box::new(shapes)), ..self }
+    }
+
+    pub fn with_frequencies(self, frequencies: impl io::Read + 'static) -> Self {
+        Self { frequencies: Some(Box::new(frequencies)), ..self }
+    }
+
+    pub fn with_transfers(self, transfers: impl io::Read + 'static) -> Self {
+        Self { transfers: Some(Box::new(transfers)), ..self }
+    }
+
+    pub fn with_pathways(self, pathways: impl io::Read + 'static) -> Self {
+        Self { pathways: Some(Box::new(pathways)), ..self }
+    }
+
+    pub fn with_fare_attributes(self, fare_attributes: impl io::Read + 'static) -> Self {
+        Self { fare_attributes: Some(Box::new(fare_attributes)), ..self }
+    }
+
+    pub fn with_fare_rules(self, fare_rules: impl io::Read + 'static) -> Self {
+        Self { fare_rules: Some(Box::new(fare_rules)), ..self }
+    }
+
+    pub fn with_fare_products(self, fare_products: impl io::Read + 'static) -> Self {
+        Self { fare_products: Some(Box::new(fare_products)), ..self }
+    }
+
+    pub fn with_fare_leg_rules(self, fare_leg_rules: impl io::Read + 'static) -> Self {
+        Self { fare_leg_rules: Some(Box::new(fare_leg_rules)), ..self }
+    }
+
+    pub fn with_stop_areas(self, stop_areas: impl io::Read + 'static) -> Self {
+        Self { stop_areas: Some(Box::new(stop_areas)), ..self }
+    }
+
+    pub fn load(self) -> Result<gtfs::GtfsSchedule, GtfsError> {
+        let agencies = self.agency
+            .map(|r| loaders::timed("agency.txt", || agency::Agencies::try_from(csv::Reader::from_reader(r))))
+            .transpose()?
+            .unwrap_or_default();
+        let stops = loaders::timed("stops.txt", || stops::Stops::try_from(csv::Reader::from_reader(self.stops)))?;
+        let routes = loaders::timed("routes.txt", || routes::Routes::try_from(csv::Reader::from_reader(self.routes)))?;
+        let trips = loaders::timed("trips.txt", || trips::Trips::try_from(csv::Reader::from_reader(self.trips)))?;
+        let stop_times = loaders::timed("stop_times.txt", || stop_times::StopTimes::try_from(csv::Reader::from_reader(self.stop_times)))?;
+        let calendar = self.calendar
+            .map(|r| loaders::timed("calendar.txt", || calendar::Calendar::try_from(csv::Reader::from_reader(r))))
+            .transpose()?
+            .unwrap_or_default();
+        let calendar_dates = self.calendar_dates
+            .map(|r| loaders::timed("calendar_dates.txt", || calendar::CalendarDates::try_from(csv::Reader::from_reader(r))))
+            .transpose()?
+            .unwrap_or_default();
+        let shapes = self.shapes
+            .map(|r| loaders::timed("shapes.txt", || shapes::Shapes::try_from(csv::Reader::from_reader(r))))
+            .transpose()?
+            .unwrap_or_default();
+        let frequencies = self.frequencies
+            .map(|r| loaders::timed("frequencies.txt", || frequencies::Frequencies::try_from(csv::Reader::from_reader(r))))
+            .transpose()?
+            .unwrap_or_default();
+        let transfers = self.transfers
+            .map(|r| loaders::timed("transfers.txt", || transfers::Transfers::try_from(csv::Reader::from_reader(r))))
+            .transpose()?
+            .unwrap_or_default();
+        let pathways = self.pathways
+            .map(|r| loaders::timed("pathways.txt", || pathways::Pathways::try_from(csv::Reader::from_reader(r))))
+            .transpose()?
+            .unwrap_or_default();
+        let fare_attributes = self.fare_attributes
+            .map(|r| loaders::timed("fare_attributes.txt", || fares::FareAttributes::try_from(csv::Reader::from_reader(r))))
+            .transpose()?
+            .unwrap_or_default();
+        let fare_rules = self.fare_rules
+            .map(|r| loaders::timed("fare_rules.txt", || fares::FareRules::try_from(csv::Reader::from_reader(r))))
+            .transpose()?
+            .unwrap_or_default();
+        let fare_products = self.fare_products
+            .map(|r| loaders::timed("fare_products.txt", || fare_products::FareProducts::try_from(csv::Reader::from_reader(r))))
+            .transpose()?
+            .unwrap_or_default();
+        let fare_leg_rules = self.fare_leg_rules
+            .map(|r| loaders::timed("fare_leg_rules.txt", || fare_products::FareLegRules::try_from(csv::Reader::from_reader(r))))
+            .transpose()?
+            .unwrap_or_default();
+        let stop_areas = self.stop_areas
+            .map(|r| loaders::timed("stop_areas.txt", || fare_products::StopAreas::try_from(csv::Reader::from_reader(r))))
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(gtfs::GtfsSchedule {
+            agencies,
+            stops,
+            routes,
+            trips,
+            stop_times,
+            calendar,
+            calendar_dates,
+            shapes,
+            frequencies,
+            transfers,
+            pathways,
+            fare_attributes,
+            fare_rules,
+            fare_products,
+            fare_leg_rules,
+            stop_areas,
+            index_cache: Default::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STOPS: &str = "stop_id,stop_name,stop_lat,stop_lon\nX,X,40.7,-74.0\nY,Y,40.8,-73.95\n";
+    const ROUTES: &str = "route_id,route_short_name,route_type\nR1,1,3\n";
+    const TRIPS: &str = "route_id,service_id,trip_id\nR1,D,T1\n";
+    const STOP_TIMES: &str = "trip_id,arrival_time,departure_time,stop_id,stop_sequence\nT1,08:00:00,08:00:00,X,1\nT1,08:10:00,08:10:00,Y,2\n";
+
+    fn loader() -> MultiSourceLoader {
+        MultiSourceLoader::new(STOPS.as_bytes(), ROUTES.as_bytes(), TRIPS.as_bytes(), STOP_TIMES.as_bytes())
+    }
+
+    #[test]
+    fn tables_which_are_not_given_load_empty() {
+        let schedule = loader().load().unwrap();
+        assert_eq!((schedule.stops.stops.len(), schedule.routes.routes.len(), schedule.trips.trips.len()), (2, 1, 1));
+        assert_eq!(schedule.stop_times.iter().count(), 2);
+        assert!(schedule.agencies.agencies.is_empty());
+        assert!(schedule.calendar.services.is_empty());
+        assert!(schedule.frequencies.frequencies.is_empty());
+    }
+
+    #[test]
+    fn optional_tables_given_are_loaded() {
+        let schedule = loader()
+            .with_calendar("service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\nD,1,1,1,1,1,0,0,20250101,20251231\n".as_bytes())
+            .with_frequencies("trip_id,start_time,end_time,headway_secs\nT1,06:00:00,09:00:00,600\n".as_bytes())
+            .load()
+            .unwrap();
+        assert!(schedule.calendar.services.contains_key("D"));
+        assert_eq!(schedule.frequencies.iter().map(|frequency| frequency.headway_secs).collect::<Vec<_>>(), [600]);
+    }
+
+    #[test]
+    fn the_first_invalid_table_fails_the_load() {
+        let e = MultiSourceLoader::new(STOPS.as_bytes(), "route_id\nR1\n".as_bytes(), TRIPS.as_bytes(), "".as_bytes())
+            .load()
+            .unwrap_err();
+        assert!(e.to_string().starts_with("routes.txt:2: "), "{}", e);
+    }
+}
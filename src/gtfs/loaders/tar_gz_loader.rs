@@ -0,0 +1,153 @@
+use crate::gtfs;
+use crate::gtfs::error::GtfsError;
+use crate::gtfs::loaders::multi_source_loader::MultiSourceLoader;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::io::Read;
+
+// the tables which must be present in the tarball for it to be loaded.
+const REQUIRED_FILES: [&str; 4] = ["stops.txt", "routes.txt", "trips.txt", "stop_times.txt"];
+// the tables which are loaded if present.
+const OPTIONAL_FILES: [&str; 12] = [
+    "agency.txt", "calendar.txt", "calendar_dates.txt", "shapes.txt", "frequencies.txt", "transfers.txt", "pathways.txt",
+    "fare_attributes.txt", "fare_rules.txt", "fare_products.txt", "fare_leg_rules.txt", "stop_areas.txt",
+];
+
+// TarGzLoader loads a GTFS schedule from a gzip-compressed tarball.
+// Files are matched by name regardless of the directory they are nested in,
+// since tarballs are commonly rooted at a top-level folder.
+pub struct TarGzLoader<R: io::Read> {
+    pub archive: tar::Archive<flate2::read::GzDecoder<R>>,
+}
+
+#[derive(Debug)]
+pub enum TarGzLoaderError {
+    FailedToReadArchive(io::Error),
+    MissingFile(String),
+}
+
+impl fmt::Display for TarGzLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FailedToReadArchive(e) => write!(f, "Failed to read archive: {}", e),
+            Self::MissingFile(file) => write!(f, "Archive does not contain {}", file),
+        }
+    }
+}
+
+impl std::error::Error for TarGzLoaderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::FailedToReadArchive(e) => e.source(),
+            Self::MissingFile(_) => None,
+        }
+    }
+}
+
+impl<R: io::Read> TarGzLoader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            archive: tar::Archive::new(flate2::read::GzDecoder::new(reader)),
+        }
+    }
+
+    pub fn load(&mut self) -> Result<gtfs::GtfsSchedule, GtfsError> {
+        // tar entries can only be read sequentially, so buffer each table we are interested in.
+        let mut files = HashMap::new();
+        for entry in self.archive.entries().map_err(TarGzLoaderError::FailedToReadArchive)? {
+            let mut entry = entry.map_err(TarGzLoaderError::FailedToReadArchive)?;
+            let name = entry.path()
+                .map_err(TarGzLoaderError::FailedToReadArchive)?
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| name.to_string());
+            if let Some(name) = name.filter(|name| REQUIRED_FILES.contains(&name.as_str()) || OPTIONAL_FILES.contains(&name.as_str())) {
+                let mut buf = Vec::new();
+                entry.read_to_end(&mut buf).map_err(TarGzLoaderError::FailedToReadArchive)?;
+                files.insert(name, buf);
+            }
+        }
+
+        let mut take = |file: &str| files.remove(file)
+            .map(io::Cursor::new)
+            .ok_or(TarGzLoaderError::MissingFile(file.to_string()));
+
+        let mut loader = MultiSourceLoader::new(
+            take("stops.txt")?,
+            take("routes.txt")?,
+            take("trips.txt")?,
+            take("stop_times.txt")?,
+        );
+        if let Ok(agency) = take("agency.txt") {
+            loader = loader.with_agency(agency);
+        }
+        if let Ok(calendar) = take("calendar.txt") {
+            loader = loader.with_calendar(calendar);
+        }
+        if let Ok(calendar_dates) = take("calendar_dates.txt") {
+            loader = loader.with_calendar_dates(calendar_dates);
+        }
+        if let Ok(shapes) = take("shapes.txt") {
+            loader = loader.with_shapes(shapes);
+        }
+        if let Ok(frequencies) = take("frequencies.txt") {
+            loader = loader.with_frequencies(frequencies);
+        }
+        if let Ok(transfers) = take("transfers.txt") {
+            loader = loader.with_transfers(transfers);
+        }
+        if let Ok(pathways) = take("pathways.txt") {
+            loader = loader.with_pathways(pathways);
+        }
+        if let Ok(fare_attributes) = take("fare_attributes.txt") {
+            loader = loader.with_fare_attributes(fare_attributes);
+        }
+        if let Ok(fare_rules) = take("fare_rules.txt") {
+            loader = loader.with_fare_rules(fare_rules);
+        }
+        if let Ok(fare_products) = take("fare_products.txt") {
+            loader = loader.with_fare_products(fare_products);
+        }
+        if let Ok(fare_leg_rules) = take("fare_leg_rules.txt") {
+            loader = loader.with_fare_leg_rules(fare_leg_rules);
+        }
+        if let Ok(stop_areas) = take("stop_areas.txt") {
+            loader = loader.with_stop_areas(stop_areas);
+        }
+        loader.load()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::testing;
+    use crate::gtfs::writers::zip_writer;
+    use std::fs;
+
+    // tarball packs the files of dir into a gzip-compressed tarball, under a top-level folder as feeds often are.
+    fn tarball(dir: &std::path::Path) -> Vec<u8> {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default()));
+        builder.append_dir_all("feed", dir).unwrap();
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn a_tarball_loads_unchanged() {
+        let dir = testing::temp_dir("tarball");
+        zip_writer::write_dir(&dir, &testing::feed()).unwrap();
+        assert_eq!(TarGzLoader::new(io::Cursor::new(tarball(&dir))).load().unwrap(), testing::feed());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_tarball_without_a_required_table_fails() {
+        let dir = testing::temp_dir("tarball_without_stops");
+        zip_writer::write_dir(&dir, &testing::schedule()).unwrap();
+        fs::remove_file(dir.join("stops.txt")).unwrap();
+        let e = TarGzLoader::new(io::Cursor::new(tarball(&dir))).load().unwrap_err();
+        assert!(matches!(&e, GtfsError::TarGz(TarGzLoaderError::MissingFile(file)) if file == "stops.txt"), "{:?}", e);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,247 @@
+use crate::gtfs::loaders::integrity::{IntegrityCheck, IntegrityError};
+use curl::easy::Easy;
+use std::cell;
+use std::fmt;
+use std::thread;
+use std::time;
+
+// RetryPolicy controls how many times a download is attempted, and how long to wait between attempts.
+// The wait grows exponentially from initial_backoff by multiplier, capped at max_backoff, and is
+// randomized by up to +/- jitter (a fraction of the wait) so that many clients don't retry in lockstep.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: time::Duration,
+    pub max_backoff: time::Duration,
+    pub multiplier: f64,
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: time::Duration::from_millis(500),
+            max_backoff: time::Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    // no_retry is a policy that attempts the download exactly once.
+    pub fn no_retry() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    // backoff returns how long to wait after the given (1-based) failed attempt.
+    pub fn backoff(&self, attempt: u32) -> time::Duration {
+        let base = self.initial_backoff.as_secs_f64() * self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        let capped = base.min(self.max_backoff.as_secs_f64());
+        // the subsecond part of the clock is a good enough source of randomness for spreading out retries.
+        let noise = time::SystemTime::now()
+            .duration_since(time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos() as f64 / 1_000_000_000.0)
+            .unwrap_or(0.5);
+        let jittered = capped * (1.0 + self.jitter * (2.0 * noise - 1.0));
+        time::Duration::from_secs_f64(jittered.max(0.0))
+    }
+}
+
+#[derive(Debug)]
+pub enum DownloadError {
+    TransferError(curl::Error),
+    HttpStatus(u32),
+    RetriesExhausted(u32, Box<DownloadError>),
+    IntegrityError(IntegrityError),
+}
+
+impl DownloadError {
+    // is_transient reports whether the failure is worth retrying.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::TransferError(e) => e.is_couldnt_connect()
+                || e.is_couldnt_resolve_host()
+                || e.is_operation_timedout()
+                || e.is_recv_error()
+                || e.is_send_error()
+                || e.is_partial_file()
+                || e.is_got_nothing(),
+            Self::HttpStatus(code) => *code == 408 || *code == 429 || (500..600).contains(code),
+            Self::RetriesExhausted(_, _) => false,
+            Self::IntegrityError(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TransferError(e) => write!(f, "Transfer failed: {}", e),
+            Self::HttpStatus(code) => write!(f, "Server responded with status {}", code),
+            Self::RetriesExhausted(attempts, e) => write!(f, "Giving up after {} attempts: {}", attempts, e),
+            Self::IntegrityError(e) => write!(f, "Integrity check failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::TransferError(e) => Some(e),
+            Self::HttpStatus(_) => None,
+            Self::RetriesExhausted(_, e) => Some(e.as_ref()),
+            Self::IntegrityError(e) => Some(e),
+        }
+    }
+}
+
+// DownloadProgress describes the state of a download in progress.
+#[derive(Debug, Clone)]
+pub struct DownloadProgress {
+    // the number of bytes received so far, including bytes received by earlier attempts.
+    pub downloaded: usize,
+    // the total size of the feed, if the server reported it.
+    pub total: Option<usize>,
+    // the offset the current attempt resumed from, if it continued an interrupted attempt.
+    pub resumed_from: Option<usize>,
+    // the (1-based) attempt currently in progress.
+    pub attempt: u32,
+}
+
+// RetryHandler is called before each retry with the number of the attempt which failed, why, and how long the
+// loader will wait before the next.
+pub type RetryHandler = Box<dyn Fn(u32, &DownloadError, time::Duration)>;
+
+// UrlLoader downloads a feed over HTTP(S), retrying transient failures according to its RetryPolicy.
+// When an attempt is interrupted, the next attempt requests only the remaining bytes with a Range header
+// and appends them to what was already received; servers which ignore the Range header are handled by
+// starting over.
+pub struct UrlLoader {
+    pub url: String,
+    pub retry_policy: RetryPolicy,
+    pub on_progress: Box<dyn Fn(&DownloadProgress)>,
+    pub on_retry: RetryHandler,
+    pub integrity_check: Option<IntegrityCheck>,
+}
+
+impl UrlLoader {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            retry_policy: RetryPolicy::default(),
+            on_progress: Box::new(|_| ()),
+            on_retry: Box::new(|_, _, _| ()),
+            integrity_check: None,
+        }
+    }
+
+    pub fn with_retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        Self { retry_policy, ..self }
+    }
+
+    // with_progress_handler registers a callback which is invoked whenever more of the feed is received.
+    pub fn with_progress_handler(self, on_progress: impl Fn(&DownloadProgress) + 'static) -> Self {
+        Self { on_progress: Box::new(on_progress), ..self }
+    }
+
+    // with_retry_handler registers a callback which is invoked with the failed attempt number,
+    // the error, and the backoff before the next attempt.
+    pub fn with_retry_handler(self, on_retry: impl Fn(u32, &DownloadError, time::Duration) + 'static) -> Self {
+        Self { on_retry: Box::new(on_retry), ..self }
+    }
+
+    // with_integrity_check makes download verify the complete feed before returning it.
+    pub fn with_integrity_check(self, integrity_check: IntegrityCheck) -> Self {
+        Self { integrity_check: Some(integrity_check), ..self }
+    }
+
+    pub fn download(&self) -> Result<Vec<u8>, DownloadError> {
+        let mut buf = Vec::new();
+        let mut attempt = 1;
+        loop {
+            match self.attempt(&mut buf, attempt) {
+                Ok(()) => return match &self.integrity_check {
+                    Some(check) => check.verify(&buf).map(|_| buf).map_err(DownloadError::IntegrityError),
+                    None => Ok(buf),
+                },
+                Err(e) if !e.is_transient() => return Err(e),
+                Err(e) if attempt >= self.retry_policy.max_attempts => {
+                    return Err(DownloadError::RetriesExhausted(attempt, Box::new(e)))
+                },
+                Err(e) => {
+                    let backoff = self.retry_policy.backoff(attempt);
+                    (self.on_retry)(attempt, &e, backoff);
+                    thread::sleep(backoff);
+                    attempt += 1;
+                },
+            }
+        }
+    }
+
+    // attempt downloads the feed into buf, resuming from the end of buf if it already holds part of the feed.
+    fn attempt(&self, buf: &mut Vec<u8>, attempt: u32) -> Result<(), DownloadError> {
+        let resume_from = buf.len();
+        let mut handle = Easy::new();
+        handle.url(&self.url).map_err(DownloadError::TransferError)?;
+        handle.get(true).map_err(DownloadError::TransferError)?;
+        handle.follow_location(true).map_err(DownloadError::TransferError)?;
+        if resume_from > 0 {
+            handle.range(&format!("{}-", resume_from)).map_err(DownloadError::TransferError)?;
+        }
+
+        // the status and length of the final response, tracked from its headers,
+        // since redirects produce several responses in one transfer.
+        let status = cell::Cell::new(0);
+        let content_length = cell::Cell::new(None);
+        // whether the bytes kept from an earlier attempt are still valid.
+        let resumed = cell::Cell::new(resume_from > 0);
+        {
+            let mut transfer = handle.transfer();
+            transfer.header_function(|header| {
+                let header = String::from_utf8_lossy(header);
+                if header.starts_with("HTTP/") {
+                    status.set(header.split_whitespace().nth(1).and_then(|code| code.parse::<u32>().ok()).unwrap_or(0));
+                    content_length.set(None);
+                } else if let Some((name, value)) = header.split_once(':') {
+                    if name.trim().eq_ignore_ascii_case("content-length") {
+                        content_length.set(value.trim().parse::<usize>().ok());
+                    }
+                }
+                true
+            }).map_err(DownloadError::TransferError)?;
+            transfer.write_function(|data| {
+                // the bodies of error responses are not part of the feed.
+                if status.get() != 200 && status.get() != 206 {
+                    return Ok(data.len());
+                }
+                // a server which ignores the Range header sends the whole feed again.
+                if resumed.get() && status.get() == 200 {
+                    buf.clear();
+                    resumed.set(false);
+                }
+                buf.extend_from_slice(data);
+                let offset = if resumed.get() { resume_from } else { 0 };
+                (self.on_progress)(&DownloadProgress {
+                    downloaded: buf.len(),
+                    total: content_length.get().map(|len| len + offset),
+                    resumed_from: Some(offset).filter(|offset| *offset > 0),
+                    attempt,
+                });
+                Ok(data.len())
+            }).map_err(DownloadError::TransferError)?;
+            transfer.perform().map_err(DownloadError::TransferError)?;
+        }
+
+        match handle.response_code().map_err(DownloadError::TransferError)? {
+            200 | 206 => Ok(()),
+            // the earlier attempt already received the whole feed.
+            416 if resume_from > 0 => Ok(()),
+            code => Err(DownloadError::HttpStatus(code)),
+        }
+    }
+}
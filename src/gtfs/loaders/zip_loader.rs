@@ -1,8 +1,18 @@
 use crate::gtfs;
+use crate::gtfs::error::GtfsError;
+use crate::gtfs::loaders;
+use crate::gtfs::agency;
+use crate::gtfs::calendar;
 use crate::gtfs::stops;
 use crate::gtfs::routes;
 use crate::gtfs::trips;
 use crate::gtfs::stop_times;
+use crate::gtfs::shapes;
+use crate::gtfs::frequencies;
+use crate::gtfs::transfers;
+use crate::gtfs::pathways;
+use crate::gtfs::fares;
+use crate::gtfs::fare_products;
 use zip::read::ZipFile;
 use zip::result::ZipError;
 use std::fmt;
@@ -13,28 +23,68 @@ pub struct ZipLoader<Handler: ZipLoaderEventHandler> {
 }
 
 
+#[derive(Debug)]
 pub enum ZipLoaderError {
+    FailedToOpenAgency(String, ZipError),
     FailedToOpenStops(String, ZipError),
     FailedToOpenRoutes(String, ZipError),
     FailedToOpenTrips(String, ZipError),
     FailedToOpenStopTimes(String, ZipError),
-    FailedToLoadStops(stops::StopsCsvLoadError),
-    FailedToLoadRoutes(routes::RoutesCsvLoadError),
-    FailedToLoadTrips(trips::TripsCsvLoadError),
-    FailedToLoadStopTimes(stop_times::StopTimesCsvLoadError),
+    FailedToOpenCalendar(String, ZipError),
+    FailedToOpenCalendarDates(String, ZipError),
+    FailedToOpenShapes(String, ZipError),
+    FailedToOpenFrequencies(String, ZipError),
+    FailedToOpenTransfers(String, ZipError),
+    FailedToOpenPathways(String, ZipError),
+    FailedToOpenFareAttributes(String, ZipError),
+    FailedToOpenFareRules(String, ZipError),
+    FailedToOpenFareProducts(String, ZipError),
+    FailedToOpenFareLegRules(String, ZipError),
+    FailedToOpenStopAreas(String, ZipError),
 }
 
 impl fmt::Display for ZipLoaderError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            Self::FailedToOpenAgency(file, e) => write!(f, "Failed to open {}: {}", file, e),
             Self::FailedToOpenStops(file, e) => write!(f, "Failed to open {}: {}", file, e),
             Self::FailedToOpenRoutes(file, e) => write!(f, "Failed to open {}: {}", file, e),
             Self::FailedToOpenTrips(file, e) => write!(f, "Failed to open {}: {}", file, e),
             Self::FailedToOpenStopTimes(file, e) => write!(f, "Failed to open {}: {}", file, e),
-            Self::FailedToLoadStops(e) => write!(f, "Failed to load stops: {}", e),
-            Self::FailedToLoadRoutes(e) => write!(f, "Failed to load routes: {}", e),
-            Self::FailedToLoadTrips(e) => write!(f, "Failed to load trips: {}", e),
-            Self::FailedToLoadStopTimes(e) => write!(f, "Failed to load stop times: {}", e),
+            Self::FailedToOpenCalendar(file, e) => write!(f, "Failed to open {}: {}", file, e),
+            Self::FailedToOpenCalendarDates(file, e) => write!(f, "Failed to open {}: {}", file, e),
+            Self::FailedToOpenShapes(file, e) => write!(f, "Failed to open {}: {}", file, e),
+            Self::FailedToOpenFrequencies(file, e) => write!(f, "Failed to open {}: {}", file, e),
+            Self::FailedToOpenTransfers(file, e) => write!(f, "Failed to open {}: {}", file, e),
+            Self::FailedToOpenPathways(file, e) => write!(f, "Failed to open {}: {}", file, e),
+            Self::FailedToOpenFareAttributes(file, e) => write!(f, "Failed to open {}: {}", file, e),
+            Self::FailedToOpenFareRules(file, e) => write!(f, "Failed to open {}: {}", file, e),
+            Self::FailedToOpenFareProducts(file, e) => write!(f, "Failed to open {}: {}", file, e),
+            Self::FailedToOpenFareLegRules(file, e) => write!(f, "Failed to open {}: {}", file, e),
+            Self::FailedToOpenStopAreas(file, e) => write!(f, "Failed to open {}: {}", file, e),
+        }
+    }
+}
+
+impl std::error::Error for ZipLoaderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::FailedToOpenAgency(_, e) => e.source(),
+            Self::FailedToOpenStops(_, e) => e.source(),
+            Self::FailedToOpenRoutes(_, e) => e.source(),
+            Self::FailedToOpenTrips(_, e) => e.source(),
+            Self::FailedToOpenStopTimes(_, e) => e.source(),
+            Self::FailedToOpenCalendar(_, e) => e.source(),
+            Self::FailedToOpenCalendarDates(_, e) => e.source(),
+            Self::FailedToOpenShapes(_, e) => e.source(),
+            Self::FailedToOpenFrequencies(_, e) => e.source(),
+            Self::FailedToOpenTransfers(_, e) => e.source(),
+            Self::FailedToOpenPathways(_, e) => e.source(),
+            Self::FailedToOpenFareAttributes(_, e) => e.source(),
+            Self::FailedToOpenFareRules(_, e) => e.source(),
+            Self::FailedToOpenFareProducts(_, e) => e.source(),
+            Self::FailedToOpenFareLegRules(_, e) => e.source(),
+            Self::FailedToOpenStopAreas(_, e) => e.source(),
         }
     }
 }
@@ -56,31 +106,37 @@ impl<Handler: ZipLoaderEventHandler> ZipLoader<Handler> {
         }
     }
 
-    pub fn load(&mut self) -> Result<gtfs::GtfsSchedule, ZipLoaderError> {
+    pub fn load(&mut self) -> Result<gtfs::GtfsSchedule, GtfsError> {
+        let agencies = match open_optional(&mut self.zip, "agency.txt")
+            .map_err(|e| ZipLoaderError::FailedToOpenAgency("agency.txt".to_string(), e))?
+        {
+            Some(agency_reader) => {
+                self.event_handler.on_file_opened("agency.txt", &agency_reader);
+                self.event_handler.on_agency_file_opened(&agency_reader);
+                loaders::timed("agency.txt", || agency::Agencies::try_from(csv::Reader::from_reader(agency_reader)))?
+            },
+            None => agency::Agencies::default(),
+        };
+        self.event_handler.on_agency_loaded(&agencies);
+
         let stops_reader = self.zip.by_name("stops.txt")
             .map_err(
                 |e|
                 ZipLoaderError::FailedToOpenStops("stops.txt".to_string(), e)
             )?;
+        self.event_handler.on_file_opened("stops.txt", &stops_reader);
         self.event_handler.on_stops_file_opened(&stops_reader);
         
-        let stops = stops::Stops::try_from(csv::Reader::from_reader(stops_reader))
-        .map_err(
-            |e|
-            ZipLoaderError::FailedToLoadStops(e)
-        )?;
+        let stops = loaders::timed("stops.txt", || stops::Stops::try_from(csv::Reader::from_reader(stops_reader)))?;
         self.event_handler.on_stops_loaded(&stops);
         let routes_reader = self.zip.by_name("routes.txt")
             .map_err(
                 |e|
                 ZipLoaderError::FailedToOpenRoutes("routes.txt".to_string(), e)
             )?;
+        self.event_handler.on_file_opened("routes.txt", &routes_reader);
         self.event_handler.on_routes_file_opened(&routes_reader);
-        let routes = routes::Routes::try_from(csv::Reader::from_reader(routes_reader))
-            .map_err(
-                |e|
-                ZipLoaderError::FailedToLoadRoutes(e)
-            )?;
+        let routes = loaders::timed("routes.txt", || routes::Routes::try_from(csv::Reader::from_reader(routes_reader)))?;
         self.event_handler.on_routes_loaded(&routes);
 
         let trips_reader = self.zip.by_name("trips.txt")
@@ -88,13 +144,10 @@ impl<Handler: ZipLoaderEventHandler> ZipLoader<Handler> {
                 |e|
                 ZipLoaderError::FailedToOpenTrips("trips.txt".to_string(), e)
             )?;
+        self.event_handler.on_file_opened("trips.txt", &trips_reader);
         self.event_handler.on_trips_file_opened(&trips_reader);
 
-        let trips = trips::Trips::try_from(csv::Reader::from_reader(trips_reader))
-            .map_err(
-                |e|
-                ZipLoaderError::FailedToLoadTrips(e)
-            )?;
+        let trips = loaders::timed("trips.txt", || trips::Trips::try_from(csv::Reader::from_reader(trips_reader)))?;
         self.event_handler.on_trips_loaded(&trips);
 
         let stop_times_reader = self.zip.by_name("stop_times.txt")
@@ -102,36 +155,222 @@ impl<Handler: ZipLoaderEventHandler> ZipLoader<Handler> {
                 |e|
                 ZipLoaderError::FailedToOpenStopTimes("stop_times.txt".to_string(), e)
             )?;
+        self.event_handler.on_file_opened("stop_times.txt", &stop_times_reader);
         self.event_handler.on_stop_times_file_opened(&stop_times_reader);
 
-        let stop_times = stop_times::StopTimes::try_from(csv::Reader::from_reader(stop_times_reader))
-            .map_err(
-                |e|
-                ZipLoaderError::FailedToLoadStopTimes(e)
-            )?;
+        let stop_times = loaders::timed("stop_times.txt", || stop_times::StopTimes::try_from(csv::Reader::from_reader(stop_times_reader)))?;
         self.event_handler.on_stop_times_loaded(&stop_times);
 
+        // calendar.txt and calendar_dates.txt are each optional, as long as one of them is present.
+        let calendar = match open_optional(&mut self.zip, "calendar.txt")
+            .map_err(|e| ZipLoaderError::FailedToOpenCalendar("calendar.txt".to_string(), e))?
+        {
+            Some(calendar_reader) => {
+                self.event_handler.on_file_opened("calendar.txt", &calendar_reader);
+                self.event_handler.on_calendar_file_opened(&calendar_reader);
+                loaders::timed("calendar.txt", || calendar::Calendar::try_from(csv::Reader::from_reader(calendar_reader)))?
+            },
+            None => calendar::Calendar::default(),
+        };
+        self.event_handler.on_calendar_loaded(&calendar);
+
+        let calendar_dates = match open_optional(&mut self.zip, "calendar_dates.txt")
+            .map_err(|e| ZipLoaderError::FailedToOpenCalendarDates("calendar_dates.txt".to_string(), e))?
+        {
+            Some(calendar_dates_reader) => {
+                self.event_handler.on_file_opened("calendar_dates.txt", &calendar_dates_reader);
+                self.event_handler.on_calendar_dates_file_opened(&calendar_dates_reader);
+                loaders::timed("calendar_dates.txt", || calendar::CalendarDates::try_from(csv::Reader::from_reader(calendar_dates_reader)))?
+            },
+            None => calendar::CalendarDates::default(),
+        };
+        self.event_handler.on_calendar_dates_loaded(&calendar_dates);
+
+        let shapes = match open_optional(&mut self.zip, "shapes.txt")
+            .map_err(|e| ZipLoaderError::FailedToOpenShapes("shapes.txt".to_string(), e))?
+        {
+            Some(shapes_reader) => {
+                self.event_handler.on_file_opened("shapes.txt", &shapes_reader);
+                self.event_handler.on_shapes_file_opened(&shapes_reader);
+                loaders::timed("shapes.txt", || shapes::Shapes::try_from(csv::Reader::from_reader(shapes_reader)))?
+            },
+            None => shapes::Shapes::default(),
+        };
+        self.event_handler.on_shapes_loaded(&shapes);
+
+        let frequencies = match open_optional(&mut self.zip, "frequencies.txt")
+            .map_err(|e| ZipLoaderError::FailedToOpenFrequencies("frequencies.txt".to_string(), e))?
+        {
+            Some(frequencies_reader) => {
+                self.event_handler.on_file_opened("frequencies.txt", &frequencies_reader);
+                self.event_handler.on_frequencies_file_opened(&frequencies_reader);
+                loaders::timed("frequencies.txt", || frequencies::Frequencies::try_from(csv::Reader::from_reader(frequencies_reader)))?
+            },
+            None => frequencies::Frequencies::default(),
+        };
+        self.event_handler.on_frequencies_loaded(&frequencies);
+
+        let transfers = match open_optional(&mut self.zip, "transfers.txt")
+            .map_err(|e| ZipLoaderError::FailedToOpenTransfers("transfers.txt".to_string(), e))?
+        {
+            Some(transfers_reader) => {
+                self.event_handler.on_file_opened("transfers.txt", &transfers_reader);
+                self.event_handler.on_transfers_file_opened(&transfers_reader);
+                loaders::timed("transfers.txt", || transfers::Transfers::try_from(csv::Reader::from_reader(transfers_reader)))?
+            },
+            None => transfers::Transfers::default(),
+        };
+        self.event_handler.on_transfers_loaded(&transfers);
+
+        let pathways = match open_optional(&mut self.zip, "pathways.txt")
+            .map_err(|e| ZipLoaderError::FailedToOpenPathways("pathways.txt".to_string(), e))?
+        {
+            Some(pathways_reader) => {
+                self.event_handler.on_file_opened("pathways.txt", &pathways_reader);
+                self.event_handler.on_pathways_file_opened(&pathways_reader);
+                loaders::timed("pathways.txt", || pathways::Pathways::try_from(csv::Reader::from_reader(pathways_reader)))?
+            },
+            None => pathways::Pathways::default(),
+        };
+        self.event_handler.on_pathways_loaded(&pathways);
+
+        let fare_attributes = match open_optional(&mut self.zip, "fare_attributes.txt")
+            .map_err(|e| ZipLoaderError::FailedToOpenFareAttributes("fare_attributes.txt".to_string(), e))?
+        {
+            Some(fare_attributes_reader) => {
+                self.event_handler.on_file_opened("fare_attributes.txt", &fare_attributes_reader);
+                self.event_handler.on_fare_attributes_file_opened(&fare_attributes_reader);
+                loaders::timed("fare_attributes.txt", || fares::FareAttributes::try_from(csv::Reader::from_reader(fare_attributes_reader)))?
+            },
+            None => fares::FareAttributes::default(),
+        };
+        self.event_handler.on_fare_attributes_loaded(&fare_attributes);
+
+        let fare_rules = match open_optional(&mut self.zip, "fare_rules.txt")
+            .map_err(|e| ZipLoaderError::FailedToOpenFareRules("fare_rules.txt".to_string(), e))?
+        {
+            Some(fare_rules_reader) => {
+                self.event_handler.on_file_opened("fare_rules.txt", &fare_rules_reader);
+                self.event_handler.on_fare_rules_file_opened(&fare_rules_reader);
+                loaders::timed("fare_rules.txt", || fares::FareRules::try_from(csv::Reader::from_reader(fare_rules_reader)))?
+            },
+            None => fares::FareRules::default(),
+        };
+        self.event_handler.on_fare_rules_loaded(&fare_rules);
+
+        let fare_products = match open_optional(&mut self.zip, "fare_products.txt")
+            .map_err(|e| ZipLoaderError::FailedToOpenFareProducts("fare_products.txt".to_string(), e))?
+        {
+            Some(fare_products_reader) => {
+                self.event_handler.on_file_opened("fare_products.txt", &fare_products_reader);
+                self.event_handler.on_fare_products_file_opened(&fare_products_reader);
+                loaders::timed("fare_products.txt", || fare_products::FareProducts::try_from(csv::Reader::from_reader(fare_products_reader)))?
+            },
+            None => fare_products::FareProducts::default(),
+        };
+        self.event_handler.on_fare_products_loaded(&fare_products);
+
+        let fare_leg_rules = match open_optional(&mut self.zip, "fare_leg_rules.txt")
+            .map_err(|e| ZipLoaderError::FailedToOpenFareLegRules("fare_leg_rules.txt".to_string(), e))?
+        {
+            Some(fare_leg_rules_reader) => {
+                self.event_handler.on_file_opened("fare_leg_rules.txt", &fare_leg_rules_reader);
+                self.event_handler.on_fare_leg_rules_file_opened(&fare_leg_rules_reader);
+                loaders::timed("fare_leg_rules.txt", || fare_products::FareLegRules::try_from(csv::Reader::from_reader(fare_leg_rules_reader)))?
+            },
+            None => fare_products::FareLegRules::default(),
+        };
+        self.event_handler.on_fare_leg_rules_loaded(&fare_leg_rules);
+
+        let stop_areas = match open_optional(&mut self.zip, "stop_areas.txt")
+            .map_err(|e| ZipLoaderError::FailedToOpenStopAreas("stop_areas.txt".to_string(), e))?
+        {
+            Some(stop_areas_reader) => {
+                self.event_handler.on_file_opened("stop_areas.txt", &stop_areas_reader);
+                self.event_handler.on_stop_areas_file_opened(&stop_areas_reader);
+                loaders::timed("stop_areas.txt", || fare_products::StopAreas::try_from(csv::Reader::from_reader(stop_areas_reader)))?
+            },
+            None => fare_products::StopAreas::default(),
+        };
+        self.event_handler.on_stop_areas_loaded(&stop_areas);
+
         Ok(gtfs::GtfsSchedule {
+            agencies,
             stops,
             routes,
             trips,
             stop_times,
+            calendar,
+            calendar_dates,
+            shapes,
+            frequencies,
+            transfers,
+            pathways,
+            fare_attributes,
+            fare_rules,
+            fare_products,
+            fare_leg_rules,
+            stop_areas,
+            index_cache: Default::default(),
         })
     }
 }
 
-trait ZipLoaderEventHandler {
-    fn on_stops_file_opened(&self, stops_reader: &ZipFile);
-    fn on_stops_loaded(&self, stops: &gtfs::stops::Stops);
-    fn on_routes_file_opened(&self, routes_reader: &ZipFile);
-    fn on_routes_loaded(&self, routes: &gtfs::routes::Routes);
-    fn on_trips_file_opened(&self, trips_reader: &ZipFile);
-    fn on_trips_loaded(&self, trips: &gtfs::trips::Trips);
-    fn on_stop_times_file_opened(&self, stop_times_reader: &ZipFile);
-    fn on_stop_times_loaded(&self, stop_times: &gtfs::stop_times::StopTimes);
+// open_optional opens a file which the GTFS reference doesn't require to be present,
+// returning None rather than an error if it is absent.
+fn open_optional<'a>(zip: &'a mut zip::ZipArchive<std::io::Cursor<Vec<u8>>>, name: &str) -> Result<Option<ZipFile<'a>>, ZipError> {
+    match zip.by_name(name) {
+        Ok(file) => Ok(Some(file)),
+        Err(ZipError::FileNotFound) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// ZipLoaderEventHandler receives progress events while a ZipLoader reads a feed.
+// Every method has a no-op default, so implementors only need to handle the events they care about.
+// on_file_opened is invoked for every table in addition to the table-specific hook, so handlers
+// which are only interested in which file is being read keep working as more tables are supported.
+// Optional tables which are absent from the archive do not trigger a file-opened event, but still
+// trigger a loaded event with an empty collection.
+pub trait ZipLoaderEventHandler {
+    fn on_file_opened(&self, _file_name: &str, _reader: &ZipFile) {}
+    fn on_agency_file_opened(&self, _agency_reader: &ZipFile) {}
+    fn on_agency_loaded(&self, _agencies: &gtfs::agency::Agencies) {}
+    fn on_stops_file_opened(&self, _stops_reader: &ZipFile) {}
+    fn on_stops_loaded(&self, _stops: &gtfs::stops::Stops) {}
+    fn on_routes_file_opened(&self, _routes_reader: &ZipFile) {}
+    fn on_routes_loaded(&self, _routes: &gtfs::routes::Routes) {}
+    fn on_trips_file_opened(&self, _trips_reader: &ZipFile) {}
+    fn on_trips_loaded(&self, _trips: &gtfs::trips::Trips) {}
+    fn on_stop_times_file_opened(&self, _stop_times_reader: &ZipFile) {}
+    fn on_stop_times_loaded(&self, _stop_times: &gtfs::stop_times::StopTimes) {}
+    fn on_calendar_file_opened(&self, _calendar_reader: &ZipFile) {}
+    fn on_calendar_loaded(&self, _calendar: &gtfs::calendar::Calendar) {}
+    fn on_calendar_dates_file_opened(&self, _calendar_dates_reader: &ZipFile) {}
+    fn on_calendar_dates_loaded(&self, _calendar_dates: &gtfs::calendar::CalendarDates) {}
+    fn on_shapes_file_opened(&self, _shapes_reader: &ZipFile) {}
+    fn on_shapes_loaded(&self, _shapes: &gtfs::shapes::Shapes) {}
+    fn on_frequencies_file_opened(&self, _frequencies_reader: &ZipFile) {}
+    fn on_frequencies_loaded(&self, _frequencies: &gtfs::frequencies::Frequencies) {}
+    fn on_transfers_file_opened(&self, _transfers_reader: &ZipFile) {}
+    fn on_transfers_loaded(&self, _transfers: &gtfs::transfers::Transfers) {}
+    fn on_pathways_file_opened(&self, _pathways_reader: &ZipFile) {}
+    fn on_pathways_loaded(&self, _pathways: &gtfs::pathways::Pathways) {}
+    fn on_fare_attributes_file_opened(&self, _fare_attributes_reader: &ZipFile) {}
+    fn on_fare_attributes_loaded(&self, _fare_attributes: &gtfs::fares::FareAttributes) {}
+    fn on_fare_rules_file_opened(&self, _fare_rules_reader: &ZipFile) {}
+    fn on_fare_rules_loaded(&self, _fare_rules: &gtfs::fares::FareRules) {}
+    fn on_fare_products_file_opened(&self, _fare_products_reader: &ZipFile) {}
+    fn on_fare_products_loaded(&self, _fare_products: &gtfs::fare_products::FareProducts) {}
+    fn on_fare_leg_rules_file_opened(&self, _fare_leg_rules_reader: &ZipFile) {}
+    fn on_fare_leg_rules_loaded(&self, _fare_leg_rules: &gtfs::fare_products::FareLegRules) {}
+    fn on_stop_areas_file_opened(&self, _stop_areas_reader: &ZipFile) {}
+    fn on_stop_areas_loaded(&self, _stop_areas: &gtfs::fare_products::StopAreas) {}
 }
 
 pub struct FnZipLoaderEventHandler {
+    pub on_agency_file_opened: Box<dyn Fn(&ZipFile)>,
+    pub on_agency_loaded: Box<dyn Fn(&gtfs::agency::Agencies)>,
     pub on_stops_file_opened: Box<dyn Fn(&ZipFile)>,
     pub on_stops_loaded: Box<dyn Fn(&gtfs::stops::Stops)>,
     pub on_routes_file_opened: Box<dyn Fn(&ZipFile)>,
@@ -139,11 +378,41 @@ pub struct FnZipLoaderEventHandler {
     pub on_trips_file_opened: Box<dyn Fn(&ZipFile)>,
     pub on_trips_loaded: Box<dyn Fn(&gtfs::trips::Trips)>,
     pub on_stop_times_file_opened: Box<dyn Fn(&ZipFile)>,
-    pub on_stop_times_loaded: Box<dyn Fn(&gtfs::stop_times::StopTimes)>
+    pub on_stop_times_loaded: Box<dyn Fn(&gtfs::stop_times::StopTimes)>,
+    pub on_calendar_file_opened: Box<dyn Fn(&ZipFile)>,
+    pub on_calendar_loaded: Box<dyn Fn(&gtfs::calendar::Calendar)>,
+    pub on_calendar_dates_file_opened: Box<dyn Fn(&ZipFile)>,
+    pub on_calendar_dates_loaded: Box<dyn Fn(&gtfs::calendar::CalendarDates)>,
+    pub on_shapes_file_opened: Box<dyn Fn(&ZipFile)>,
+    pub on_shapes_loaded: Box<dyn Fn(&gtfs::shapes::Shapes)>,
+    pub on_frequencies_file_opened: Box<dyn Fn(&ZipFile)>,
+    pub on_frequencies_loaded: Box<dyn Fn(&gtfs::frequencies::Frequencies)>,
+    pub on_transfers_file_opened: Box<dyn Fn(&ZipFile)>,
+    pub on_transfers_loaded: Box<dyn Fn(&gtfs::transfers::Transfers)>,
+    pub on_pathways_file_opened: Box<dyn Fn(&ZipFile)>,
+    pub on_pathways_loaded: Box<dyn Fn(&gtfs::pathways::Pathways)>,
+    pub on_fare_attributes_file_opened: Box<dyn Fn(&ZipFile)>,
+    pub on_fare_attributes_loaded: Box<dyn Fn(&gtfs::fares::FareAttributes)>,
+    pub on_fare_rules_file_opened: Box<dyn Fn(&ZipFile)>,
+    pub on_fare_rules_loaded: Box<dyn Fn(&gtfs::fares::FareRules)>,
+    pub on_fare_products_file_opened: Box<dyn Fn(&ZipFile)>,
+    pub on_fare_products_loaded: Box<dyn Fn(&gtfs::fare_products::FareProducts)>,
+    pub on_fare_leg_rules_file_opened: Box<dyn Fn(&ZipFile)>,
+    pub on_fare_leg_rules_loaded: Box<dyn Fn(&gtfs::fare_products::FareLegRules)>,
+    pub on_stop_areas_file_opened: Box<dyn Fn(&ZipFile)>,
+    pub on_stop_areas_loaded: Box<dyn Fn(&gtfs::fare_products::StopAreas)>,
+}
+
+impl Default for FnZipLoaderEventHandler {
+    fn default() -> Self {
+        noop_handler()
+    }
 }
 
 fn noop_handler() -> FnZipLoaderEventHandler {
     FnZipLoaderEventHandler {
+        on_agency_file_opened: Box::new(|_| ()),
+        on_agency_loaded: Box::new(|_| ()),
         on_stops_file_opened: Box::new(|_| ()),
         on_stops_loaded: Box::new(|_| ()),
         on_routes_file_opened: Box::new(|_| ()),
@@ -152,10 +421,40 @@ fn noop_handler() -> FnZipLoaderEventHandler {
         on_trips_loaded: Box::new(|_| ()),
         on_stop_times_file_opened: Box::new(|_| ()),
         on_stop_times_loaded: Box::new(|_| ()),
+        on_calendar_file_opened: Box::new(|_| ()),
+        on_calendar_loaded: Box::new(|_| ()),
+        on_calendar_dates_file_opened: Box::new(|_| ()),
+        on_calendar_dates_loaded: Box::new(|_| ()),
+        on_shapes_file_opened: Box::new(|_| ()),
+        on_shapes_loaded: Box::new(|_| ()),
+        on_frequencies_file_opened: Box::new(|_| ()),
+        on_frequencies_loaded: Box::new(|_| ()),
+        on_transfers_file_opened: Box::new(|_| ()),
+        on_transfers_loaded: Box::new(|_| ()),
+        on_pathways_file_opened: Box::new(|_| ()),
+        on_pathways_loaded: Box::new(|_| ()),
+        on_fare_attributes_file_opened: Box::new(|_| ()),
+        on_fare_attributes_loaded: Box::new(|_| ()),
+        on_fare_rules_file_opened: Box::new(|_| ()),
+        on_fare_rules_loaded: Box::new(|_| ()),
+        on_fare_products_file_opened: Box::new(|_| ()),
+        on_fare_products_loaded: Box::new(|_| ()),
+        on_fare_leg_rules_file_opened: Box::new(|_| ()),
+        on_fare_leg_rules_loaded: Box::new(|_| ()),
+        on_stop_areas_file_opened: Box::new(|_| ()),
+        on_stop_areas_loaded: Box::new(|_| ()),
     }
 }
 
 impl ZipLoaderEventHandler for FnZipLoaderEventHandler {
+    fn on_agency_file_opened(&self, agency_reader: &ZipFile) {
+        (self.on_agency_file_opened)(agency_reader);
+    }
+
+    fn on_agency_loaded(&self, agencies: &gtfs::agency::Agencies) {
+        (self.on_agency_loaded)(agencies);
+    }
+
     fn on_stops_file_opened(&self, stops_reader: &ZipFile) {
         (self.on_stops_file_opened)(stops_reader);
     }
@@ -187,4 +486,159 @@ impl ZipLoaderEventHandler for FnZipLoaderEventHandler {
     fn on_stop_times_loaded(&self, stop_times: &gtfs::stop_times::StopTimes) {
         (self.on_stop_times_loaded)(stop_times);
     }
-}
\ No newline at end of file
+
+    fn on_calendar_file_opened(&self, calendar_reader: &ZipFile) {
+        (self.on_calendar_file_opened)(calendar_reader);
+    }
+
+    fn on_calendar_loaded(&self, calendar: &gtfs::calendar::Calendar) {
+        (self.on_calendar_loaded)(calendar);
+    }
+
+    fn on_calendar_dates_file_opened(&self, calendar_dates_reader: &ZipFile) {
+        (self.on_calendar_dates_file_opened)(calendar_dates_reader);
+    }
+
+    fn on_calendar_dates_loaded(&self, calendar_dates: &gtfs::calendar::CalendarDates) {
+        (self.on_calendar_dates_loaded)(calendar_dates);
+    }
+
+    fn on_shapes_file_opened(&self, shapes_reader: &ZipFile) {
+        (self.on_shapes_file_opened)(shapes_reader);
+    }
+
+    fn on_shapes_loaded(&self, shapes: &gtfs::shapes::Shapes) {
+        (self.on_shapes_loaded)(shapes);
+    }
+
+    fn on_frequencies_file_opened(&self, frequencies_reader: &ZipFile) {
+        (self.on_frequencies_file_opened)(frequencies_reader);
+    }
+
+    fn on_frequencies_loaded(&self, frequencies: &gtfs::frequencies::Frequencies) {
+        (self.on_frequencies_loaded)(frequencies);
+    }
+
+    fn on_transfers_file_opened(&self, transfers_reader: &ZipFile) {
+        (self.on_transfers_file_opened)(transfers_reader);
+    }
+
+    fn on_transfers_loaded(&self, transfers: &gtfs::transfers::Transfers) {
+        (self.on_transfers_loaded)(transfers);
+    }
+
+    fn on_pathways_file_opened(&self, pathways_reader: &ZipFile) {
+        (self.on_pathways_file_opened)(pathways_reader);
+    }
+
+    fn on_pathways_loaded(&self, pathways: &gtfs::pathways::Pathways) {
+        (self.on_pathways_loaded)(pathways);
+    }
+
+    fn on_fare_attributes_file_opened(&self, fare_attributes_reader: &ZipFile) {
+        (self.on_fare_attributes_file_opened)(fare_attributes_reader);
+    }
+
+    fn on_fare_attributes_loaded(&self, fare_attributes: &gtfs::fares::FareAttributes) {
+        (self.on_fare_attributes_loaded)(fare_attributes);
+    }
+
+    fn on_fare_rules_file_opened(&self, fare_rules_reader: &ZipFile) {
+        (self.on_fare_rules_file_opened)(fare_rules_reader);
+    }
+
+    fn on_fare_rules_loaded(&self, fare_rules: &gtfs::fares::FareRules) {
+        (self.on_fare_rules_loaded)(fare_rules);
+    }
+
+    fn on_fare_products_file_opened(&self, fare_products_reader: &ZipFile) {
+        (self.on_fare_products_file_opened)(fare_products_reader);
+    }
+
+    fn on_fare_products_loaded(&self, fare_products: &gtfs::fare_products::FareProducts) {
+        (self.on_fare_products_loaded)(fare_products);
+    }
+
+    fn on_fare_leg_rules_file_opened(&self, fare_leg_rules_reader: &ZipFile) {
+        (self.on_fare_leg_rules_file_opened)(fare_leg_rules_reader);
+    }
+
+    fn on_fare_leg_rules_loaded(&self, fare_leg_rules: &gtfs::fare_products::FareLegRules) {
+        (self.on_fare_leg_rules_loaded)(fare_leg_rules);
+    }
+
+    fn on_stop_areas_file_opened(&self, stop_areas_reader: &ZipFile) {
+        (self.on_stop_areas_file_opened)(stop_areas_reader);
+    }
+
+    fn on_stop_areas_loaded(&self, stop_areas: &gtfs::fare_products::StopAreas) {
+        (self.on_stop_areas_loaded)(stop_areas);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::testing;
+    use crate::gtfs::writers::zip_writer::ZipWriter;
+    use std::cell::RefCell;
+    use std::io;
+
+    fn archive(schedule: &gtfs::GtfsSchedule) -> zip::ZipArchive<io::Cursor<Vec<u8>>> {
+        zip::ZipArchive::new(ZipWriter::new(io::Cursor::new(Vec::new())).write(schedule).unwrap()).unwrap()
+    }
+
+    // Events records the files a ZipLoader opens and the number of shapes it loads.
+    #[derive(Default)]
+    struct Events {
+        files: RefCell<Vec<String>>,
+        shapes: RefCell<Option<usize>>,
+    }
+
+    impl ZipLoaderEventHandler for &Events {
+        fn on_file_opened(&self, file_name: &str, _reader: &ZipFile) {
+            self.files.borrow_mut().push(file_name.to_string());
+        }
+
+        fn on_shapes_loaded(&self, shapes: &gtfs::shapes::Shapes) {
+            *self.shapes.borrow_mut() = Some(shapes.shapes.len());
+        }
+    }
+
+    #[test]
+    fn absent_optional_tables_load_empty_without_being_opened() {
+        let events = Events::default();
+        let schedule = ZipLoader::new(archive(&testing::schedule())).with_event_handler(&events).load().unwrap();
+        assert_eq!(schedule, testing::schedule());
+        assert_eq!(*events.files.borrow(), ["agency.txt", "stops.txt", "routes.txt", "trips.txt", "stop_times.txt", "calendar.txt"]);
+        assert_eq!(*events.shapes.borrow(), Some(0));
+    }
+
+    // zip_of makes an archive of the given files and their text.
+    fn zip_of(files: &[(&str, &str)]) -> zip::ZipArchive<io::Cursor<Vec<u8>>> {
+        let mut writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+        for (name, text) in files {
+            writer.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+            io::Write::write_all(&mut writer, text.as_bytes()).unwrap();
+        }
+        zip::ZipArchive::new(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn an_archive_without_a_required_table_fails() {
+        let e = ZipLoader::new(zip_of(&[("routes.txt", "route_id,route_short_name,route_type\nR1,1,3\n")])).load().unwrap_err();
+        assert!(matches!(e, GtfsError::Zip(ZipLoaderError::FailedToOpenStops(..))), "{:?}", e);
+    }
+
+    #[test]
+    fn an_invalid_table_fails_with_its_file_and_line() {
+        let e = ZipLoader::new(zip_of(&[
+            ("stops.txt", "stop_id,stop_name,stop_lat,stop_lon\nX,X,40.7,-74.0\n"),
+            ("routes.txt", "route_id,route_short_name,route_type\nR1,1,3\n"),
+            ("trips.txt", "route_id,service_id,trip_id\nR1,D,T1\nR1,,T2\n"),
+            ("stop_times.txt", "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n"),
+        ])).load().unwrap_err();
+        assert!(matches!(e, GtfsError::Csv(_)), "{:?}", e);
+        assert!(e.to_string().starts_with("trips.txt:3: "), "{}", e);
+    }
+}
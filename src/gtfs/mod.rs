@@ -1,26 +1,226 @@
+pub mod accessibility;
+pub mod agency;
+pub mod analysis;
+pub mod blocks;
+pub mod calendar;
+#[cfg(feature = "cli")]
+pub mod catalog;
+pub(crate) mod de;
+pub mod departures;
+pub mod diff;
+pub mod error;
+pub mod fare_products;
+pub mod fares;
+pub mod fields;
+pub mod frequencies;
+pub mod geometry;
+#[cfg(feature = "realtime")]
+pub mod gtfs_rt;
+pub mod headways;
+pub mod index;
 pub mod stops;
 pub mod routes;
+pub mod transfers;
 pub mod trips;
 pub mod stop_times;
+pub mod timetable;
+pub mod table;
+#[cfg(test)]
+pub(crate) mod testing;
+pub mod shapes;
+pub mod spatial;
+pub mod link;
+pub mod validation;
 pub mod loaders;
-use colored::Colorize;
+pub mod mutation;
+pub mod network;
+pub mod pathways;
+pub mod patterns;
+pub mod writers;
+pub mod store;
+pub mod view;
+use std::collections;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct GtfsSchedule {
     // TODO: fill out remaining fields
+    pub agencies: agency::Agencies,
     pub stops: stops::Stops,
     pub routes: routes::Routes,
     pub trips: trips::Trips,
     pub stop_times: stop_times::StopTimes,
+    pub calendar: calendar::Calendar,
+    pub calendar_dates: calendar::CalendarDates,
+    pub shapes: shapes::Shapes,
+    pub frequencies: frequencies::Frequencies,
+    pub transfers: transfers::Transfers,
+    pub pathways: pathways::Pathways,
+    pub fare_attributes: fares::FareAttributes,
+    pub fare_rules: fares::FareRules,
+    pub fare_products: fare_products::FareProducts,
+    pub fare_leg_rules: fare_products::FareLegRules,
+    pub stop_areas: fare_products::StopAreas,
+    // index_cache holds the schedule's index once it has been built; see index.
+    pub index_cache: std::sync::OnceLock<index::GtfsIndex>,
 }
 
+// Schedules are compared by their records alone, whether or not their indexes have been built.
+impl PartialEq for GtfsSchedule {
+    fn eq(&self, other: &Self) -> bool {
+        self.agencies == other.agencies
+            && self.stops == other.stops
+            && self.routes == other.routes
+            && self.trips == other.trips
+            && self.stop_times == other.stop_times
+            && self.calendar == other.calendar
+            && self.calendar_dates == other.calendar_dates
+            && self.shapes == other.shapes
+            && self.frequencies == other.frequencies
+            && self.transfers == other.transfers
+            && self.pathways == other.pathways
+            && self.fare_attributes == other.fare_attributes
+            && self.fare_rules == other.fare_rules
+            && self.fare_products == other.fare_products
+            && self.fare_leg_rules == other.fare_leg_rules
+            && self.stop_areas == other.stop_areas
+    }
+}
+
+impl GtfsSchedule {
+    // view returns an unfiltered view of the whole schedule.
+    pub fn view(&self) -> view::ScheduleView<'_> {
+        view::ScheduleView::new(self, None)
+    }
+
+    // diff reports the stops, routes, trips and stop times which were added, removed or changed in other,
+    // treating this schedule as the older of the two.
+    pub fn diff(&self, other: &GtfsSchedule) -> diff::FeedDiff {
+        diff::FeedDiff::between(self, other)
+    }
+
+    // sub_schedule builds a schedule from a subset of this schedule's stops, routes, trips and stop times,
+    // carrying over the supporting tables those records refer to.
+    pub fn sub_schedule(
+        &self,
+        stops: stops::Stops,
+        routes: routes::Routes,
+        trips: trips::Trips,
+        stop_times: stop_times::StopTimes,
+    ) -> GtfsSchedule {
+        let shape_ids = (&trips).into_iter()
+            .filter_map(|trip| trip.shape_id.as_deref())
+            .collect::<collections::HashSet<_>>();
 
+        GtfsSchedule {
+            agencies: self.agencies.clone(),
+            calendar: self.calendar.clone(),
+            calendar_dates: self.calendar_dates.clone(),
+            shapes: shapes::Shapes {
+                shapes: self.shapes.shapes.iter()
+                    .filter(|(shape_id, _)| shape_ids.contains(shape_id.as_str()))
+                    .map(|(shape_id, points)| (shape_id.clone(), points.clone()))
+                    .collect()
+            },
+            frequencies: frequencies::Frequencies {
+                frequencies: self.frequencies.frequencies.iter()
+                    .filter(|(trip_id, _)| trips.trips.contains_key(trip_id.as_str()))
+                    .map(|(trip_id, frequencies)| (trip_id.clone(), frequencies.clone()))
+                    .collect()
+            },
+            // transfers between stops which are both kept carry over, whatever trips or routes they name.
+            transfers: self.transfers.iter()
+                .filter(|transfer| [&transfer.from_stop_id, &transfer.to_stop_id].into_iter()
+                    .all(|stop_id| stop_id.as_ref().is_none_or(|stop_id| stops.stops.contains_key(stop_id))))
+                .cloned()
+                .collect(),
+            // pathways carry over where the stops at both their ends are kept.
+            pathways: (&self.pathways).into_iter()
+                .filter(|pathway| stops.stops.contains_key(&pathway.from_stop_id) && stops.stops.contains_key(&pathway.to_stop_id))
+                .cloned()
+                .collect(),
+            fare_attributes: self.fare_attributes.clone(),
+            // fare rules carry over unless they name a route which isn't kept.
+            fare_rules: self.fare_rules.iter()
+                .filter(|fare_rule| fare_rule.route_id.as_ref().is_none_or(|route_id| routes.routes.contains_key(route_id)))
+                .cloned()
+                .collect(),
+            fare_products: self.fare_products.clone(),
+            fare_leg_rules: self.fare_leg_rules.clone(),
+            stop_areas: self.stop_areas.iter()
+                .filter(|stop_area| stops.stops.contains_key(&stop_area.stop_id))
+                .cloned()
+                .collect(),
+            stops,
+            routes,
+            trips,
+            stop_times,
+            index_cache: Default::default(),
+        }
+    }
+
+    // with_id_prefix prepends prefix to every ID in the schedule, including references between tables,
+    // so that the schedule can be merged with others without its IDs colliding.
+    pub fn with_id_prefix(self, prefix: &str) -> Self {
+        GtfsSchedule {
+            agencies: self.agencies.with_id_prefix(prefix),
+            stops: self.stops.with_id_prefix(prefix),
+            routes: self.routes.with_id_prefix(prefix),
+            trips: self.trips.with_id_prefix(prefix),
+            stop_times: self.stop_times.with_id_prefix(prefix),
+            calendar: self.calendar.with_id_prefix(prefix),
+            calendar_dates: self.calendar_dates.with_id_prefix(prefix),
+            shapes: self.shapes.with_id_prefix(prefix),
+            frequencies: self.frequencies.with_id_prefix(prefix),
+            transfers: self.transfers.with_id_prefix(prefix),
+            pathways: self.pathways.with_id_prefix(prefix),
+            fare_attributes: self.fare_attributes.with_id_prefix(prefix),
+            fare_rules: self.fare_rules.with_id_prefix(prefix),
+            fare_products: self.fare_products.with_id_prefix(prefix),
+            fare_leg_rules: self.fare_leg_rules.with_id_prefix(prefix),
+            stop_areas: self.stop_areas.with_id_prefix(prefix),
+            index_cache: Default::default(),
+        }
+    }
+
+    // merge combines several schedules into one.
+    // IDs are not rewritten, so schedules whose IDs may collide should be given distinct prefixes
+    // with with_id_prefix first; on collision, the record from the later schedule wins.
+    pub fn merge(schedules: Vec<GtfsSchedule>) -> GtfsSchedule {
+        schedules.into_iter().fold(
+            GtfsSchedule::default(),
+            |mut merged, schedule| {
+                merged.agencies.agencies.extend(schedule.agencies.agencies);
+                merged.stops.stops.extend(schedule.stops.stops);
+                merged.routes.routes.extend(schedule.routes.routes);
+                merged.trips.trips.extend(schedule.trips.trips);
+                merged.stop_times.stop_times.extend(schedule.stop_times.stop_times);
+                merged.calendar.services.extend(schedule.calendar.services);
+                merged.calendar_dates.calendar_dates.extend(schedule.calendar_dates.calendar_dates);
+                merged.shapes.shapes.extend(schedule.shapes.shapes);
+                merged.frequencies.frequencies.extend(schedule.frequencies.frequencies);
+                merged.pathways.pathways.extend(schedule.pathways.pathways);
+                merged.fare_attributes.fare_attributes.extend(schedule.fare_attributes.fare_attributes);
+                merged.fare_products.fare_products.extend(schedule.fare_products.fare_products);
+                merged.fare_leg_rules.fare_leg_rules.extend(schedule.fare_leg_rules.fare_leg_rules);
+                // transfers have no ID of their own, so those of every schedule are kept.
+                for (from_stop_id, transfers) in schedule.transfers.transfers {
+                    merged.transfers.transfers.entry(from_stop_id).or_default().extend(transfers);
+                }
+                // nor do fare rules and stop areas, which are kept likewise.
+                for (fare_id, fare_rules) in schedule.fare_rules.fare_rules {
+                    merged.fare_rules.fare_rules.entry(fare_id).or_default().extend(fare_rules);
+                }
+                for (stop_id, stop_areas) in schedule.stop_areas.stop_areas {
+                    merged.stop_areas.stop_areas.entry(stop_id).or_default().extend(stop_areas);
+                }
+                merged
+            }
+        )
+    }
+}
 
 impl std::fmt::Display for GtfsSchedule {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}: {}\n{}: {}\n{}: {}",
-        "Stops".truecolor(128, 128, 128).bold(), self.stops.stops.len(),
-        "Routes".truecolor(128, 128, 128).bold(), self.routes.routes.len(),
-        "Trips".truecolor(128, 128, 128).bold(), self.trips.trips.len())
+        write!(f, "{}", self.view())
     }
 }
\ No newline at end of file
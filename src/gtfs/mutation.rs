@@ -0,0 +1,417 @@
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::stops;
+use crate::gtfs::routes;
+use crate::gtfs::trips;
+use crate::gtfs::stop_times;
+use std::fmt;
+
+// MutationError is returned when a change would leave the schedule with a duplicate ID or a dangling reference.
+#[derive(Debug)]
+pub enum MutationError {
+    DuplicateStop(String),
+    DuplicateRoute(String),
+    DuplicateTrip(String),
+    DuplicateStopTime(String, usize),
+    NoSuchStop(String),
+    NoSuchRoute(String),
+    NoSuchTrip(String),
+    // StopInUse holds the stop's ID and a description of the first record found referring to it.
+    StopInUse(String, String),
+}
+
+impl fmt::Display for MutationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateStop(stop_id) => write!(f, "A stop with ID {} already exists", stop_id),
+            Self::DuplicateRoute(route_id) => write!(f, "A route with ID {} already exists", route_id),
+            Self::DuplicateTrip(trip_id) => write!(f, "A trip with ID {} already exists", trip_id),
+            Self::DuplicateStopTime(trip_id, stop_sequence) => write!(f, "Trip {} already has a stop time with stop_sequence {}", trip_id, stop_sequence),
+            Self::NoSuchStop(stop_id) => write!(f, "No such stop: {}", stop_id),
+            Self::NoSuchRoute(route_id) => write!(f, "No such route: {}", route_id),
+            Self::NoSuchTrip(trip_id) => write!(f, "No such trip: {}", trip_id),
+            Self::StopInUse(stop_id, user) => write!(f, "Stop {} is still referenced by {}", stop_id, user),
+        }
+    }
+}
+
+impl std::error::Error for MutationError {}
+
+// These mutators keep the schedule consistent as it is edited: records can only be added once the records
+// they refer to exist, and removing a record removes the records which depend on it.
+impl GtfsSchedule {
+    // add_stop adds a stop, whose parent station (if any) must already be present.
+    pub fn add_stop(&mut self, stop: stops::Stop) -> Result<(), MutationError> {
+        if self.stops.stops.contains_key(&stop.stop_id) {
+            return Err(MutationError::DuplicateStop(stop.stop_id));
+        }
+        if let Some(parent_station) = stop.parent_station().filter(|parent_station| !self.stops.stops.contains_key(*parent_station)) {
+            return Err(MutationError::NoSuchStop(parent_station.to_string()));
+        }
+        self.stops.stops.insert(stop.stop_id.clone(), stop);
+        self.invalidate_index();
+        Ok(())
+    }
+
+    // add_route adds a route.
+    pub fn add_route(&mut self, route: routes::Route) -> Result<(), MutationError> {
+        if self.routes.routes.contains_key(&route.route_id) {
+            return Err(MutationError::DuplicateRoute(route.route_id));
+        }
+        self.routes.routes.insert(route.route_id.clone(), route);
+        self.invalidate_index();
+        Ok(())
+    }
+
+    // add_trip adds a trip, whose route must already be present.
+    pub fn add_trip(&mut self, trip: trips::Trip) -> Result<(), MutationError> {
+        if self.trips.trips.contains_key(&trip.trip_id) {
+            return Err(MutationError::DuplicateTrip(trip.trip_id));
+        }
+        if !self.routes.routes.contains_key(&trip.route_id) {
+            return Err(MutationError::NoSuchRoute(trip.route_id));
+        }
+        self.trips.trips.insert(trip.trip_id.clone(), trip);
+        self.invalidate_index();
+        Ok(())
+    }
+
+    // add_stop_time adds a stop time to its trip, keeping the trip's stop times ordered by stop_sequence.
+    // The trip and the stop must already be present.
+    pub fn add_stop_time(&mut self, stop_time: stop_times::StopTime) -> Result<(), MutationError> {
+        if !self.trips.trips.contains_key(&stop_time.trip_id) {
+            return Err(MutationError::NoSuchTrip(stop_time.trip_id));
+        }
+        if let Some(stop_id) = stop_time.stop_id.as_ref().filter(|stop_id| !self.stops.stops.contains_key(*stop_id)) {
+            return Err(MutationError::NoSuchStop(stop_id.clone()));
+        }
+        let trip_stop_times = self.stop_times.stop_times.entry(stop_time.trip_id.clone()).or_default();
+        if trip_stop_times.iter().any(|other| other.stop_sequence == stop_time.stop_sequence) {
+            return Err(MutationError::DuplicateStopTime(stop_time.trip_id, stop_time.stop_sequence));
+        }
+        let position = trip_stop_times.partition_point(|other| other.stop_sequence < stop_time.stop_sequence);
+        trip_stop_times.insert(position, stop_time);
+        self.invalidate_index();
+        Ok(())
+    }
+
+    // remove_stop removes a stop, as long as no stop time visits it and no other stop names it as its parent station.
+    // The stop is taken out of any stop areas it's in, and transfers and pathways from or to it are removed.
+    pub fn remove_stop(&mut self, stop_id: &str) -> Result<stops::Stop, MutationError> {
+        if !self.stops.stops.contains_key(stop_id) {
+            return Err(MutationError::NoSuchStop(stop_id.to_string()));
+        }
+        if let Some(child) = self.child_stops(stop_id).next() {
+            return Err(MutationError::StopInUse(stop_id.to_string(), format!("stop {}", child.stop_id)));
+        }
+        if let Some(stop_time) = self.stop_times_at(stop_id).next() {
+            return Err(MutationError::StopInUse(stop_id.to_string(), format!("trip {}", stop_time.trip_id)));
+        }
+        self.stop_areas.stop_areas.remove(stop_id);
+        self.transfers.transfers.remove(stop_id);
+        for transfers in self.transfers.transfers.values_mut() {
+            transfers.retain(|transfer| transfer.to_stop_id.as_deref() != Some(stop_id));
+        }
+        self.transfers.transfers.retain(|_, transfers| !transfers.is_empty());
+        self.pathways.pathways.retain(|_, pathway| pathway.from_stop_id != stop_id && pathway.to_stop_id != stop_id);
+        self.invalidate_index();
+        Ok(self.stops.stops.remove(stop_id).unwrap())
+    }
+
+    // remove_route removes a route along with its trips and their stop times.
+    pub fn remove_route(&mut self, route_id: &str) -> Option<routes::Route> {
+        let route = self.routes.routes.remove(route_id)?;
+        self.retain_trips(|trip| trip.route_id != route_id);
+        Some(route)
+    }
+
+    // remove_trip removes a trip along with its stop times, frequencies and the transfers from or to it.
+    pub fn remove_trip(&mut self, trip_id: &str) -> Option<trips::Trip> {
+        let trip = self.trips.trips.remove(trip_id)?;
+        self.remove_dangling_references();
+        Some(trip)
+    }
+
+    // retain_routes keeps only the routes for which keep returns true, removing the others along with their trips,
+    // stop times, frequencies, fare rules and transfers.
+    pub fn retain_routes<F: FnMut(&routes::Route) -> bool>(&mut self, mut keep: F) {
+        self.routes.routes.retain(|_, route| keep(route));
+        let routes = &self.routes.routes;
+        self.trips.trips.retain(|_, trip| routes.contains_key(&trip.route_id));
+        self.remove_dangling_references();
+    }
+
+    // retain_trips keeps only the trips for which keep returns true, removing the others along with their stop times,
+    // frequencies and transfers.
+    pub fn retain_trips<F: FnMut(&trips::Trip) -> bool>(&mut self, mut keep: F) {
+        self.trips.trips.retain(|_, trip| keep(trip));
+        self.remove_dangling_references();
+    }
+
+    // remove_dangling_references removes the records which refer to trips or routes no longer in the schedule: the
+    // trips' stop times and frequencies, the routes' fare rules, and transfers from or to either. A fare whose rules
+    // were all for removed routes no longer applies to any route, so it's removed as well.
+    fn remove_dangling_references(&mut self) {
+        self.invalidate_index();
+        let (routes, trips) = (&self.routes.routes, &self.trips.trips);
+        self.stop_times.stop_times.retain(|trip_id, _| trips.contains_key(trip_id));
+        self.frequencies.frequencies.retain(|trip_id, _| trips.contains_key(trip_id));
+        let fare_attributes = &mut self.fare_attributes.fare_attributes;
+        self.fare_rules.fare_rules.retain(|fare_id, fare_rules| {
+            fare_rules.retain(|fare_rule| fare_rule.route_id.as_ref().is_none_or(|route_id| routes.contains_key(route_id)));
+            if fare_rules.is_empty() {
+                fare_attributes.remove(fare_id);
+            }
+            !fare_rules.is_empty()
+        });
+        let trip_exists = |trip_id: &Option<String>| trip_id.as_ref().is_none_or(|trip_id| trips.contains_key(trip_id));
+        let route_exists = |route_id: &Option<String>| route_id.as_ref().is_none_or(|route_id| routes.contains_key(route_id));
+        self.transfers.transfers.retain(|_, transfers| {
+            transfers.retain(|transfer| trip_exists(&transfer.from_trip_id) && trip_exists(&transfer.to_trip_id)
+                && route_exists(&transfer.from_route_id) && route_exists(&transfer.to_route_id));
+            !transfers.is_empty()
+        });
+    }
+
+    // rename_stop changes a stop's ID, updating the stop times, child stops, stop areas, transfers and pathways which
+    // refer to it.
+    pub fn rename_stop(&mut self, stop_id: &str, new_stop_id: &str) -> Result<(), MutationError> {
+        if self.stops.stops.contains_key(new_stop_id) {
+            return Err(MutationError::DuplicateStop(new_stop_id.to_string()));
+        }
+        let mut stop = self.stops.stops.remove(stop_id).ok_or(MutationError::NoSuchStop(stop_id.to_string()))?;
+        stop.stop_id = new_stop_id.to_string();
+        self.stops.stops.insert(stop.stop_id.clone(), stop);
+
+        self.stops.stops.values_mut()
+            .filter_map(stops::Stop::mut_parent_station)
+            .filter(|parent_station| parent_station.as_str() == stop_id)
+            .for_each(|parent_station| *parent_station = new_stop_id.to_string());
+        self.stop_times.stop_times.values_mut()
+            .flatten()
+            .filter_map(|stop_time| stop_time.stop_id.as_mut())
+            .filter(|visited_stop_id| visited_stop_id.as_str() == stop_id)
+            .for_each(|visited_stop_id| *visited_stop_id = new_stop_id.to_string());
+        if let Some(mut stop_areas) = self.stop_areas.stop_areas.remove(stop_id) {
+            stop_areas.iter_mut().for_each(|stop_area| stop_area.stop_id = new_stop_id.to_string());
+            self.stop_areas.stop_areas.insert(new_stop_id.to_string(), stop_areas);
+        }
+        if let Some(mut transfers) = self.transfers.transfers.remove(stop_id) {
+            transfers.iter_mut().for_each(|transfer| transfer.from_stop_id = Some(new_stop_id.to_string()));
+            self.transfers.transfers.insert(new_stop_id.to_string(), transfers);
+        }
+        self.transfers.transfers.values_mut()
+            .flatten()
+            .filter_map(|transfer| transfer.to_stop_id.as_mut())
+            .filter(|to_stop_id| to_stop_id.as_str() == stop_id)
+            .for_each(|to_stop_id| *to_stop_id = new_stop_id.to_string());
+        self.pathways.pathways.values_mut()
+            .flat_map(|pathway| [&mut pathway.from_stop_id, &mut pathway.to_stop_id])
+            .filter(|linked_stop_id| linked_stop_id.as_str() == stop_id)
+            .for_each(|linked_stop_id| *linked_stop_id = new_stop_id.to_string());
+        self.invalidate_index();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::testing;
+
+    // area_stops lists the stops of each area, in order.
+    fn area_stops(schedule: &GtfsSchedule) -> Vec<(String, String)> {
+        let mut area_stops = schedule.stop_areas.iter()
+            .map(|stop_area| (stop_area.area_id.clone(), stop_area.stop_id.clone()))
+            .collect::<Vec<_>>();
+        area_stops.sort();
+        area_stops
+    }
+
+    #[test]
+    fn renamed_stops_keep_their_areas() {
+        let mut schedule = testing::schedule();
+        schedule.stop_areas = testing::load("area_id,stop_id\nA1,S1\nA2,S1\nA1,S2\n");
+        schedule.rename_stop("S1", "S1N").unwrap();
+        assert_eq!(area_stops(&schedule), [("A1", "S1N"), ("A1", "S2"), ("A2", "S1N")].map(|(a, s)| (a.to_string(), s.to_string())));
+        assert!(!schedule.stop_areas.stop_areas.contains_key("S1"));
+    }
+
+    #[test]
+    fn removed_stops_leave_their_areas() {
+        let mut schedule = testing::schedule();
+        schedule.add_stop(testing::stop("Z", 40.6, -74.1, None)).unwrap();
+        schedule.stop_areas = testing::load("area_id,stop_id\nA1,Z\nA1,S2\n");
+        schedule.remove_stop("Z").unwrap();
+        assert_eq!(area_stops(&schedule), [("A1".to_string(), "S2".to_string())]);
+    }
+
+    #[test]
+    fn removed_routes_take_their_fare_rules_with_them() {
+        let mut schedule = testing::schedule();
+        schedule.fare_attributes = testing::load("fare_id,price,currency_type,payment_method,transfers\nF1,2.9,USD,0,0\nF2,1.5,USD,0,0\nF3,5,USD,0,0\n");
+        schedule.fare_rules = testing::load("fare_id,route_id\nF1,R1\nF1,R2\nF2,R2\nF3,\n");
+        schedule.remove_route("R2");
+        let mut fare_rules = schedule.fare_rules.iter()
+            .map(|fare_rule| (fare_rule.fare_id.as_str(), fare_rule.route_id.as_deref()))
+            .collect::<Vec<_>>();
+        fare_rules.sort();
+        assert_eq!(fare_rules, [("F1", Some("R1")), ("F3", None)]);
+        let mut fare_ids = schedule.fare_attributes.fare_attributes.keys().collect::<Vec<_>>();
+        fare_ids.sort();
+        assert_eq!(fare_ids, ["F1", "F3"]);
+    }
+
+    // transfers lists every transfer as its from and to stops and trips, in order.
+    fn transfers(schedule: &GtfsSchedule) -> Vec<[Option<&str>; 4]> {
+        let mut transfers = schedule.transfers.iter()
+            .map(|transfer| [
+                transfer.from_stop_id.as_deref(), transfer.to_stop_id.as_deref(),
+                transfer.from_trip_id.as_deref(), transfer.to_trip_id.as_deref(),
+            ])
+            .collect::<Vec<_>>();
+        transfers.sort();
+        transfers
+    }
+
+    #[test]
+    fn renamed_stops_keep_their_transfers() {
+        let mut schedule = testing::schedule();
+        schedule.transfers = testing::load("from_stop_id,to_stop_id,transfer_type\nS1,S2,2\nS2,S1,2\nS1,S1,1\n");
+        schedule.rename_stop("S1", "S1N").unwrap();
+        assert_eq!(transfers(&schedule), [
+            [Some("S1N"), Some("S1N"), None, None],
+            [Some("S1N"), Some("S2"), None, None],
+            [Some("S2"), Some("S1N"), None, None],
+        ]);
+        assert!(schedule.transfers.transfers.contains_key("S1N") && !schedule.transfers.transfers.contains_key("S1"));
+    }
+
+    #[test]
+    fn removed_stops_take_their_transfers_with_them() {
+        let mut schedule = testing::schedule();
+        schedule.add_stop(testing::stop("Z", 40.6, -74.1, None)).unwrap();
+        schedule.transfers = testing::load("from_stop_id,to_stop_id,transfer_type\nZ,S2,2\nS2,Z,2\nS1,S2,2\n");
+        schedule.remove_stop("Z").unwrap();
+        assert_eq!(transfers(&schedule), [[Some("S1"), Some("S2"), None, None]]);
+        assert!(!schedule.transfers.transfers.contains_key("Z"));
+    }
+
+    #[test]
+    fn removed_trips_and_routes_take_their_transfers_with_them() {
+        let mut schedule = testing::schedule();
+        schedule.transfers = testing::load(
+            "from_stop_id,to_stop_id,from_route_id,to_route_id,from_trip_id,to_trip_id,transfer_type\n\
+             S1,S2,,,T1,T3,1\nS1,S2,,,T1,T2,1\nS1,S2,R1,R2,,,2\nS1,S2,,,,,2\n",
+        );
+        schedule.remove_trip("T3");
+        assert_eq!(transfers(&schedule).len(), 3);
+        schedule.retain_routes(|route| route.route_id != "R2");
+        assert_eq!(transfers(&schedule), [[Some("S1"), Some("S2"), None, None]]);
+    }
+
+    // pathways lists every pathway as its ID and the stops it links, in order.
+    fn pathways(schedule: &GtfsSchedule) -> Vec<(&str, &str, &str)> {
+        let mut pathways = schedule.pathways.pathways.values()
+            .map(|pathway| (pathway.pathway_id.as_str(), pathway.from_stop_id.as_str(), pathway.to_stop_id.as_str()))
+            .collect::<Vec<_>>();
+        pathways.sort();
+        pathways
+    }
+
+    // station_with_entrance returns the test schedule with an entrance E to station S, linked to its platforms by pathways.
+    fn station_with_entrance() -> GtfsSchedule {
+        let mut schedule = testing::schedule();
+        let entrance = stops::LocationTypeDetails::new(2, Some("Entrance".to_string()), Some(40.7503), Some(-73.9903), Some("S".to_string()));
+        schedule.add_stop(stops::Stop::new("E", entrance.unwrap())).unwrap();
+        schedule.pathways = testing::load(
+            "pathway_id,from_stop_id,to_stop_id,pathway_mode,is_bidirectional\nP1,E,S1,1,1\nP2,S2,E,1,0\nP3,S1,S2,1,1\n",
+        );
+        schedule
+    }
+
+    #[test]
+    fn renamed_stops_keep_their_pathways() {
+        let mut schedule = station_with_entrance();
+        schedule.rename_stop("E", "EN").unwrap();
+        assert_eq!(pathways(&schedule), [("P1", "EN", "S1"), ("P2", "S2", "EN"), ("P3", "S1", "S2")]);
+    }
+
+    #[test]
+    fn removed_stops_take_their_pathways_with_them() {
+        let mut schedule = station_with_entrance();
+        schedule.remove_stop("E").unwrap();
+        assert_eq!(pathways(&schedule), [("P3", "S1", "S2")]);
+    }
+
+    #[test]
+    fn removed_trips_take_their_stop_times_and_frequencies_with_them() {
+        let mut schedule = testing::schedule();
+        schedule.frequencies = testing::load("trip_id,start_time,end_time,headway_secs\nT2,06:00:00,09:00:00,600\n");
+        assert_eq!(schedule.remove_trip("T2").map(|trip| trip.trip_id), Some("T2".to_string()));
+        assert!(!schedule.stop_times.stop_times.contains_key("T2"));
+        assert!(schedule.frequencies.frequencies.is_empty());
+        assert!(schedule.remove_trip("T2").is_none());
+    }
+
+    #[test]
+    fn records_are_only_added_once_the_records_they_refer_to_exist() {
+        let mut schedule = testing::schedule();
+        assert!(matches!(schedule.add_stop(testing::stop("X", 40.7, -74.0, None)), Err(MutationError::DuplicateStop(_))));
+        assert!(matches!(schedule.add_stop(testing::stop("Z", 40.7, -74.0, Some("Q"))), Err(MutationError::NoSuchStop(stop_id)) if stop_id == "Q"));
+        assert!(matches!(schedule.add_trip(trips::Trip::new("T9", "R9", "D")), Err(MutationError::NoSuchRoute(route_id)) if route_id == "R9"));
+        let stop_time = stop_times::StopTime::new("T1", "Z", 3, None, None);
+        assert!(matches!(schedule.add_stop_time(stop_time), Err(MutationError::NoSuchStop(stop_id)) if stop_id == "Z"));
+        let stop_time = stop_times::StopTime::new("T1", "Y", 2, None, None);
+        assert!(matches!(schedule.add_stop_time(stop_time), Err(MutationError::DuplicateStopTime(_, 2))));
+        assert_eq!(schedule, testing::schedule());
+    }
+
+    #[test]
+    fn added_stop_times_are_kept_in_order_and_indexed() {
+        let mut schedule = testing::schedule();
+        assert_eq!(schedule.stop_times_at("Y").count(), 4);
+        schedule.add_stop_time(stop_times::StopTime::new("T1", "Y", 3, Some(testing::time("08:20:00")), None)).unwrap();
+        schedule.add_stop_time(stop_times::StopTime::new("T1", "S", 0, None, Some(testing::time("07:55:00")))).unwrap();
+        let stop_ids = schedule.stop_times.stop_times["T1"].iter().map(|stop_time| stop_time.stop_id.as_deref().unwrap()).collect::<Vec<_>>();
+        assert_eq!(stop_ids, ["S", "X", "S1", "Y"]);
+        assert_eq!(schedule.stop_times_at("Y").count(), 5);
+    }
+
+    #[test]
+    fn stops_in_use_are_not_removed() {
+        let mut schedule = testing::schedule();
+        assert!(matches!(schedule.remove_stop("S"), Err(MutationError::StopInUse(_, user)) if user.starts_with("stop S")));
+        assert!(matches!(schedule.remove_stop("X"), Err(MutationError::StopInUse(_, user)) if user == "trip T1"));
+        schedule.remove_trip("T1").unwrap();
+        assert!(schedule.remove_stop("X").is_ok());
+        assert!(schedule.stop_times_at("X").next().is_none());
+    }
+
+    #[test]
+    fn renamed_stops_keep_their_stop_times_and_children() {
+        let mut schedule = testing::schedule();
+        schedule.rename_stop("S", "Central").unwrap();
+        schedule.rename_stop("X", "Origin").unwrap();
+        assert_eq!(schedule.stops.stops["S1"].parent_station(), Some("Central"));
+        assert_eq!(schedule.child_stops("Central").count(), 2);
+        assert_eq!(schedule.stop_times_at("Origin").map(|stop_time| stop_time.trip_id.as_str()).collect::<Vec<_>>(), ["T1"]);
+        assert!(schedule.stop_times_at("X").next().is_none());
+        assert!(schedule.dangling_references().is_empty());
+        assert!(matches!(schedule.rename_stop("Origin", "Y"), Err(MutationError::DuplicateStop(_))));
+    }
+
+    #[test]
+    fn removed_routes_take_their_trips_with_them() {
+        let mut schedule = testing::schedule();
+        schedule.remove_route("R2").unwrap();
+        assert_eq!(schedule.trips.trips.keys().collect::<Vec<_>>(), ["T1"]);
+        assert_eq!(schedule.stop_times.stop_times.keys().collect::<Vec<_>>(), ["T1"]);
+        assert!(schedule.stop_times_at("Y").next().is_none());
+
+        let mut schedule = testing::schedule();
+        schedule.retain_routes(|route| route.route_id == "R2");
+        assert!(!schedule.trips.trips.contains_key("T1"));
+        assert!(schedule.stop_times_at("X").next().is_none());
+        assert!(schedule.dangling_references().is_empty());
+    }
+}
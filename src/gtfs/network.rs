@@ -0,0 +1,195 @@
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::analysis::variants;
+use crate::gtfs::routes;
+use crate::gtfs::stop_times;
+use crate::gtfs::stops;
+use crate::gtfs::trips;
+use crate::gtfs::view::ScheduleView;
+use std::cmp;
+use std::collections;
+use std::time;
+
+// DirectTrip is a trip which can be ridden from one stop to another without changing.
+#[derive(Debug, Clone, Copy)]
+pub struct DirectTrip<'a> {
+    pub trip: &'a trips::Trip,
+    // route is None if the trip's route isn't in the schedule.
+    pub route: Option<&'a routes::Route>,
+    // departure and arrival are the stop times at which the trip is boarded and left.
+    pub departure: &'a stop_times::StopTime,
+    pub arrival: &'a stop_times::StopTime,
+    pub departure_time: stop_times::GtfsTime,
+    pub arrival_time: stop_times::GtfsTime,
+    // variant is how the trip's stopping pattern compares with its route's usual one; see variants::service_variant.
+    pub variant: Option<variants::ServiceVariant>,
+}
+
+// ServiceSpan is the part of a service day during which a route runs in one direction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ServiceSpan {
+    pub direction_id: Option<trips::Direction>,
+    pub first_departure: stop_times::GtfsTime,
+    pub last_arrival: stop_times::GtfsTime,
+    pub trips: usize,
+}
+
+impl DirectTrip<'_> {
+    pub fn travel_time(&self) -> time::Duration {
+        time::Duration::from_secs(self.arrival_time.seconds().saturating_sub(self.departure_time.seconds()) as u64)
+    }
+}
+
+impl GtfsSchedule {
+    // routes_at_stop lists the routes with trips visiting a stop. See ScheduleView::routes_at_stop.
+    pub fn routes_at_stop(&self, stop_id: &str) -> Vec<&routes::Route> {
+        self.view().routes_at_stop(stop_id)
+    }
+
+    // stops_on_route lists the stops a route visits in the given direction, in order. See ScheduleView::stops_on_route.
+    pub fn stops_on_route(&self, route_id: &str, direction: Option<trips::Direction>) -> Vec<&stops::Stop> {
+        self.view().stops_on_route(route_id, direction)
+    }
+
+    // service_span returns when a route runs on a service date, in each direction. See ScheduleView::service_span.
+    pub fn service_span(&self, route_id: &str, date: chrono::NaiveDate) -> Vec<ServiceSpan> {
+        self.view().service_span(route_id, date)
+    }
+
+    // direct_trips_between lists the trips which can be ridden from one stop to another. See ScheduleView::direct_trips_between.
+    pub fn direct_trips_between(&self, from_stop_id: &str, to_stop_id: &str) -> Vec<DirectTrip<'_>> {
+        self.view().direct_trips_between(from_stop_id, to_stop_id)
+    }
+}
+
+impl<'a> ScheduleView<'a> {
+    // routes_at_stop lists the routes in the view with trips visiting a stop, or any stop within it if it's a station.
+    // Each route is listed once, ordered by route_sort_order, with routes lacking one after those which have one,
+    // then by name and ID.
+    pub fn routes_at_stop(&self, stop_id: &str) -> Vec<&'a routes::Route> {
+        let route_ids = self.stops_within(stop_id)
+            .into_iter()
+            .flat_map(|stop_id| self.stop_times_at(stop_id))
+            .filter_map(|stop_time| self.trip(&stop_time.trip_id))
+            .map(|trip| trip.route_id.as_str())
+            .collect::<collections::HashSet<_>>();
+
+        let mut routes = route_ids.into_iter()
+            .filter_map(|route_id| self.route(route_id))
+            .collect::<Vec<_>>();
+        routes.sort_by(|a, b| compare_routes(a, b));
+        routes
+    }
+
+    // stops_on_route lists the stops a route in the view visits, in order, taking the journey pattern followed by
+    // the most of its trips in the view as the route's canonical one. If direction is given, only trips in that direction
+    // count; otherwise every trip of the route does. Ties are broken in favour of the longer pattern, so that
+    // the same stops are listed every time.
+    pub fn stops_on_route(&self, route_id: &str, direction: Option<trips::Direction>) -> Vec<&'a stops::Stop> {
+        self.patterns_of_route(route_id)
+            .into_iter()
+            .map(|(pattern, _)| pattern)
+            .find(|pattern| direction.is_none_or(|direction| pattern.direction_id == Some(direction)))
+            .map(|pattern| pattern.stop_ids.iter().filter_map(|stop_id| self.stop(stop_id)).collect())
+            .unwrap_or_default()
+    }
+
+    // direct_trips_between lists the trips in the view which visit one stop and then another, where they can be boarded
+    // at the first and left at the second, in order of departure time. Stops within either stop are included if it's a station.
+    // If a trip can be boarded more than once before reaching the second stop, the last chance to board it is used.
+    // Trips listed in frequencies.txt appear once, at the times of their stop times; see GtfsSchedule::expand_frequencies.
+    pub fn direct_trips_between(&self, from_stop_id: &str, to_stop_id: &str) -> Vec<DirectTrip<'a>> {
+        let (from_stop_ids, to_stop_ids) = (self.stops_within(from_stop_id), self.stops_within(to_stop_id));
+        let trip_ids = from_stop_ids.iter()
+            .flat_map(|stop_id| self.stop_times_at(stop_id))
+            .map(|stop_time| stop_time.trip_id.as_str())
+            .collect::<collections::HashSet<_>>();
+
+        let mut direct_trips = trip_ids.into_iter()
+            .filter_map(|trip_id| {
+                let mut trip_stop_times = self.stop_times_of_trip(trip_id).collect::<Vec<_>>();
+                trip_stop_times.sort_by_key(|stop_time| stop_time.stop_sequence);
+                let visits = |stop_ids: &collections::HashSet<&str>, stop_time: &stop_times::StopTime| {
+                    stop_time.stop_id.as_deref().is_some_and(|stop_id| stop_ids.contains(stop_id))
+                };
+
+                let mut boarding = None;
+                for stop_time in trip_stop_times {
+                    if let Some((departure, departure_time)) = boarding.filter(|_| visits(&to_stop_ids, stop_time)) {
+                        if !matches!(stop_time.drop_off_type, Some(stop_times::StopPolicy::Unavailable)) {
+                            let trip = self.trip(trip_id)?;
+                            return Some(DirectTrip {
+                                trip,
+                                route: self.schedule.routes.routes.get(&trip.route_id),
+                                departure,
+                                arrival: stop_time,
+                                departure_time,
+                                arrival_time: stop_time.arrival_time.or(stop_time.departure_time)?,
+                                variant: variants::service_variant(self, trip_id),
+                            });
+                        }
+                    }
+                    if visits(&from_stop_ids, stop_time) && !matches!(stop_time.pickup_type, Some(stop_times::StopPolicy::Unavailable)) {
+                        if let Some(departure_time) = stop_time.departure_time.or(stop_time.arrival_time) {
+                            boarding = Some((stop_time, departure_time));
+                        }
+                    }
+                }
+                None
+            })
+            .collect::<Vec<_>>();
+        direct_trips.sort_by(|a, b| a.departure_time.cmp(&b.departure_time).then_with(|| a.trip.trip_id.cmp(&b.trip.trip_id)));
+        direct_trips
+    }
+
+    // service_span returns, for each direction a route in the view runs in on date, the first departure and last arrival
+    // of its trips which run that day, along with the number of those trips. Spans are listed in order of direction_id,
+    // with trips lacking one last. Trips listed in frequencies.txt count once for every time they start.
+    pub fn service_span(&self, route_id: &str, date: chrono::NaiveDate) -> Vec<ServiceSpan> {
+        let mut spans = Vec::<ServiceSpan>::new();
+        for trip in self.trips_of_route(route_id).filter(|trip| self.schedule.service_runs_on(&trip.service_id, date)) {
+            let mut trip_stop_times = self.schedule.stop_times.stop_times.get(&trip.trip_id).into_iter().flatten().collect::<Vec<_>>();
+            trip_stop_times.sort_by_key(|stop_time| stop_time.stop_sequence);
+            let first_departure = trip_stop_times.iter().find_map(|stop_time| stop_time.departure_time.or(stop_time.arrival_time));
+            let last_arrival = trip_stop_times.iter().rev().find_map(|stop_time| stop_time.arrival_time.or(stop_time.departure_time));
+            let (Some(first_departure), Some(last_arrival)) = (first_departure, last_arrival) else {
+                continue;
+            };
+
+            // a trip with frequencies runs once for each time it starts, taking as long as its stop times say.
+            let duration = last_arrival.seconds().saturating_sub(first_departure.seconds());
+            let start_times = match self.schedule.frequencies.frequencies.get(&trip.trip_id) {
+                Some(frequencies) => frequencies.iter().flat_map(|frequency| frequency.start_times()).collect(),
+                None => vec![first_departure],
+            };
+            let (Some(first_start), Some(last_start)) = (start_times.iter().min(), start_times.iter().max()) else {
+                continue;
+            };
+            let last_arrival = stop_times::GtfsTime::from_seconds(last_start.seconds() + duration);
+
+            match spans.iter_mut().find(|span| span.direction_id == trip.direction_id) {
+                Some(span) => {
+                    span.first_departure = span.first_departure.min(*first_start);
+                    span.last_arrival = span.last_arrival.max(last_arrival);
+                    span.trips += start_times.len();
+                },
+                None => spans.push(ServiceSpan {
+                    direction_id: trip.direction_id,
+                    first_departure: *first_start,
+                    last_arrival,
+                    trips: start_times.len(),
+                }),
+            }
+        }
+        spans.sort_by_key(|span| (span.direction_id.is_none(), span.direction_id.map(|direction| direction.code())));
+        spans
+    }
+}
+
+// compare_routes orders routes as a rider would expect to see them listed: by route_sort_order where it is given,
+// then by name and ID.
+pub fn compare_routes(a: &routes::Route, b: &routes::Route) -> cmp::Ordering {
+    let sort_order = |route: &routes::Route| (route.route_sort_order.is_none(), route.route_sort_order);
+    sort_order(a).cmp(&sort_order(b))
+        .then_with(|| a.short_or_long_name().cmp(b.short_or_long_name()))
+        .then_with(|| a.route_id.cmp(&b.route_id))
+}
@@ -0,0 +1,229 @@
+use csv;
+use crate::gtfs::error::CsvError;
+use crate::gtfs::de;
+use crate::gtfs::table;
+use serde::Deserialize;
+use std::io;
+use std::iter;
+use std::collections;
+use std::fmt;
+use std::str::FromStr;
+
+// Pathways is a collection of pathways, the edges of the graph linking the locations within a station, indexed by
+// pathway_id.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pathways {
+    pub pathways: std::collections::HashMap<String, Pathway>
+}
+
+impl<'a> iter::IntoIterator for &'a Pathways {
+    type Item = &'a Pathway;
+    type IntoIter = std::collections::hash_map::Values<'a, String, Pathway>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pathways.values()
+    }
+}
+
+impl Pathways {
+    // with_id_prefix prepends prefix to every pathway ID and to the stop IDs each pathway links.
+    pub fn with_id_prefix(self, prefix: &str) -> Self {
+        self.pathways.into_values()
+            .map(|mut pathway| {
+                pathway.prefix_ids(prefix);
+                pathway
+            })
+            .collect()
+    }
+
+    // touching yields the pathways which begin or end at any of stop_ids, in order of pathway_id.
+    pub fn touching<'a>(&'a self, stop_ids: &'a collections::HashSet<&str>) -> impl Iterator<Item = &'a Pathway> + 'a {
+        let mut pathways = self.pathways.values()
+            .filter(|pathway| stop_ids.contains(pathway.from_stop_id.as_str()) || stop_ids.contains(pathway.to_stop_id.as_str()))
+            .collect::<Vec<_>>();
+        pathways.sort_by(|a, b| a.pathway_id.cmp(&b.pathway_id));
+        pathways.into_iter()
+    }
+}
+
+// Pathways implements FromIterator<Pathway> so that it can be assembled in memory, indexing each pathway by its ID.
+impl iter::FromIterator<Pathway> for Pathways {
+    fn from_iter<I: IntoIterator<Item = Pathway>>(iter: I) -> Self {
+        Pathways {
+            pathways: iter.into_iter().map(|pathway| (pathway.pathway_id.clone(), pathway)).collect()
+        }
+    }
+}
+
+// Pathways implements TryFrom<csv::Reader<R>> by loading the reader's contents as a GtfsTable.
+impl<R: io::Read> TryFrom<csv::Reader<R>> for Pathways {
+    type Error = CsvError;
+
+    fn try_from(r: csv::Reader<R>) -> Result<Self, Self::Error> {
+        table::load(r)
+    }
+}
+
+// Pathways holds the records of pathways.txt, indexed by pathway_id.
+impl table::GtfsTable for Pathways {
+    type Record = Pathway;
+
+    const FILE_NAME: &'static str = "pathways.txt";
+
+    fn key(pathway: &Pathway) -> String {
+        pathway.pathway_id.clone()
+    }
+
+    fn insert(&mut self, pathway_id: String, pathway: Pathway) {
+        self.pathways.insert(pathway_id, pathway);
+    }
+}
+
+// Pathway links two locations within a station, such as an entrance and a platform, by a walkway, stairs, an
+// elevator or the like, which riders may take from from_stop_id to to_stop_id, and back if it's bidirectional.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Pathway {
+    #[serde(deserialize_with = "de::required")]
+    pub pathway_id: String,
+    #[serde(deserialize_with = "de::required")]
+    pub from_stop_id: String,
+    #[serde(deserialize_with = "de::required")]
+    pub to_stop_id: String,
+    #[serde(deserialize_with = "de::required")]
+    pub pathway_mode: PathwayMode,
+    #[serde(deserialize_with = "bidirectional")]
+    pub is_bidirectional: bool,
+    // length is in meters.
+    #[serde(default, deserialize_with = "de::optional")]
+    pub length: Option<f64>,
+    // traversal_time is in seconds.
+    #[serde(default, deserialize_with = "de::optional")]
+    pub traversal_time: Option<u32>,
+    // stair_count is negative for stairs going down from from_stop_id.
+    #[serde(default, deserialize_with = "de::optional")]
+    pub stair_count: Option<i32>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub max_slope: Option<f64>,
+    // min_width is in meters.
+    #[serde(default, deserialize_with = "de::optional")]
+    pub min_width: Option<f64>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub signposted_as: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub reversed_signposted_as: Option<String>,
+}
+
+impl Pathway {
+    // prefix_ids prepends prefix to the pathway's ID and the stop IDs it links.
+    pub fn prefix_ids(&mut self, prefix: &str) {
+        [&mut self.pathway_id, &mut self.from_stop_id, &mut self.to_stop_id]
+            .into_iter()
+            .for_each(|id| id.insert_str(0, prefix));
+    }
+}
+
+// bidirectional reads is_bidirectional, which is 1 if the pathway may be taken both ways and 0 if only from
+// from_stop_id to to_stop_id.
+fn bidirectional<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    match de::required_str(deserializer)? {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        s => Err(serde::de::Error::custom(format!("invalid is_bidirectional '{}'", s))),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathwayMode {
+    Walkway,
+    Stairs,
+    MovingSidewalk,
+    Escalator,
+    Elevator,
+    FareGate,
+    ExitGate,
+}
+
+#[derive(Debug)]
+pub struct PathwayModeLoadError(String);
+
+impl fmt::Display for PathwayModeLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid pathway_mode '{}'", self.0)
+    }
+}
+
+impl std::error::Error for PathwayModeLoadError {}
+
+impl PathwayMode {
+    // code returns the value used to encode this pathway mode in GTFS files.
+    pub fn code(&self) -> u8 {
+        match self {
+            PathwayMode::Walkway => 1,
+            PathwayMode::Stairs => 2,
+            PathwayMode::MovingSidewalk => 3,
+            PathwayMode::Escalator => 4,
+            PathwayMode::Elevator => 5,
+            PathwayMode::FareGate => 6,
+            PathwayMode::ExitGate => 7,
+        }
+    }
+
+    // name gives the pathway mode as riders would call it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PathwayMode::Walkway => "walkway",
+            PathwayMode::Stairs => "stairs",
+            PathwayMode::MovingSidewalk => "moving sidewalk",
+            PathwayMode::Escalator => "escalator",
+            PathwayMode::Elevator => "elevator",
+            PathwayMode::FareGate => "fare gate",
+            PathwayMode::ExitGate => "exit gate",
+        }
+    }
+}
+
+impl FromStr for PathwayMode {
+    type Err = PathwayModeLoadError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1" => Ok(PathwayMode::Walkway),
+            "2" => Ok(PathwayMode::Stairs),
+            "3" => Ok(PathwayMode::MovingSidewalk),
+            "4" => Ok(PathwayMode::Escalator),
+            "5" => Ok(PathwayMode::Elevator),
+            "6" => Ok(PathwayMode::FareGate),
+            "7" => Ok(PathwayMode::ExitGate),
+            _ => Err(PathwayModeLoadError(s.to_string())),
+        }
+    }
+}
+
+// the fields of pathways.txt, in the order they are listed by the GTFS reference.
+pub const PATHWAY_FIELDS: [&str; 12] = [
+    "pathway_id", "from_stop_id", "to_stop_id", "pathway_mode", "is_bidirectional", "length", "traversal_time",
+    "stair_count", "max_slope", "min_width", "signposted_as", "reversed_signposted_as",
+];
+
+// collections::HashMap<String, String> implements From<&Pathway> by encoding each field as it would appear in pathways.txt.
+impl From<&Pathway> for collections::HashMap<String, String> {
+    fn from(pathway: &Pathway) -> Self {
+        [
+            ("pathway_id", Some(pathway.pathway_id.clone())),
+            ("from_stop_id", Some(pathway.from_stop_id.clone())),
+            ("to_stop_id", Some(pathway.to_stop_id.clone())),
+            ("pathway_mode", Some(pathway.pathway_mode.code().to_string())),
+            ("is_bidirectional", Some(if pathway.is_bidirectional { "1" } else { "0" }.to_string())),
+            ("length", pathway.length.map(|length| length.to_string())),
+            ("traversal_time", pathway.traversal_time.map(|traversal_time| traversal_time.to_string())),
+            ("stair_count", pathway.stair_count.map(|stair_count| stair_count.to_string())),
+            ("max_slope", pathway.max_slope.map(|max_slope| max_slope.to_string())),
+            ("min_width", pathway.min_width.map(|min_width| min_width.to_string())),
+            ("signposted_as", pathway.signposted_as.clone()),
+            ("reversed_signposted_as", pathway.reversed_signposted_as.clone()),
+        ]
+            .into_iter()
+            .filter_map(|(field, value)| value.map(|value| (field.to_string(), value)))
+            .collect()
+    }
+}
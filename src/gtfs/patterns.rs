@@ -0,0 +1,185 @@
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::trips;
+use crate::gtfs::view::ScheduleView;
+use std::collections;
+
+// Pattern is a journey pattern: a sequence of stops visited, in order, by one or more trips of a route in the same direction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub route_id: String,
+    pub direction_id: Option<trips::Direction>,
+    // stop_ids lists the stops in order of stop_sequence.
+    pub stop_ids: Vec<String>,
+    // trip_ids lists the trips following the pattern, sorted by ID.
+    pub trip_ids: Vec<String>,
+}
+
+impl Pattern {
+    pub fn trip_count(&self) -> usize {
+        self.trip_ids.len()
+    }
+}
+
+// Patterns groups a schedule's trips by journey pattern, so that work which depends only on the stops a trip visits
+// can be done once per pattern rather than once per trip.
+#[derive(Debug, Clone, Default)]
+pub struct Patterns {
+    // the patterns of each route, with those followed by the most trips first.
+    pub patterns_by_route_id: collections::HashMap<String, Vec<Pattern>>,
+    // the pattern each trip follows, as a (route_id, position) pair, where position indexes into the route's patterns.
+    pub pattern_by_trip_id: collections::HashMap<String, (String, usize)>,
+}
+
+impl Patterns {
+    // build extracts the journey patterns of every trip in the schedule. Trips without stop times follow no pattern.
+    pub fn build(schedule: &GtfsSchedule) -> Self {
+        let mut grouped = collections::HashMap::<(&str, Option<trips::Direction>, Vec<&str>), Vec<String>>::new();
+        for trip in &schedule.trips {
+            let mut trip_stop_times = schedule.stop_times.stop_times.get(&trip.trip_id)
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>();
+            trip_stop_times.sort_by_key(|stop_time| stop_time.stop_sequence);
+            let stop_ids = trip_stop_times.into_iter()
+                .filter_map(|stop_time| stop_time.stop_id.as_deref())
+                .collect::<Vec<_>>();
+            if !stop_ids.is_empty() {
+                grouped.entry((trip.route_id.as_str(), trip.direction_id, stop_ids)).or_default().push(trip.trip_id.clone());
+            }
+        }
+
+        let mut patterns = Patterns::default();
+        for ((route_id, direction_id, stop_ids), mut trip_ids) in grouped {
+            trip_ids.sort();
+            patterns.patterns_by_route_id.entry(route_id.to_string()).or_default().push(Pattern {
+                route_id: route_id.to_string(),
+                direction_id,
+                stop_ids: stop_ids.into_iter().map(|stop_id| stop_id.to_string()).collect(),
+                trip_ids,
+            });
+        }
+
+        for (route_id, route_patterns) in &mut patterns.patterns_by_route_id {
+            // patterns are ordered the same way every time, so that their positions are stable for a given schedule.
+            route_patterns.sort_by(|a, b| b.trip_count().cmp(&a.trip_count())
+                .then_with(|| b.stop_ids.len().cmp(&a.stop_ids.len()))
+                .then_with(|| a.direction_id.map(|direction| direction.code()).cmp(&b.direction_id.map(|direction| direction.code())))
+                .then_with(|| a.stop_ids.cmp(&b.stop_ids)));
+            for (position, pattern) in route_patterns.iter().enumerate() {
+                for trip_id in &pattern.trip_ids {
+                    patterns.pattern_by_trip_id.insert(trip_id.clone(), (route_id.clone(), position));
+                }
+            }
+        }
+
+        patterns
+    }
+
+    // of_route returns the patterns of a route, with those followed by the most trips first.
+    pub fn of_route(&self, route_id: &str) -> &[Pattern] {
+        self.patterns_by_route_id.get(route_id).map_or(&[], |patterns| patterns.as_slice())
+    }
+
+    // of_trip returns the pattern a trip follows.
+    pub fn of_trip(&self, trip_id: &str) -> Option<&Pattern> {
+        let (route_id, position) = self.pattern_by_trip_id.get(trip_id)?;
+        self.patterns_by_route_id.get(route_id)?.get(*position)
+    }
+}
+
+impl GtfsSchedule {
+    // patterns returns the schedule's journey patterns, which are built along with its index.
+    pub fn patterns(&self) -> &Patterns {
+        &self.index().patterns
+    }
+}
+
+impl<'a> ScheduleView<'a> {
+    // patterns_of_route lists the patterns of a route in the view, each paired with the number of its trips in the view,
+    // with those followed by the most trips first. Patterns with no trips in the view are left out.
+    pub fn patterns_of_route(&self, route_id: &str) -> Vec<(&'a Pattern, usize)> {
+        let mut patterns = self.route(route_id)
+            .map(|route| self.schedule.patterns().of_route(&route.route_id))
+            .unwrap_or_default()
+            .iter()
+            .map(|pattern| (pattern, pattern.trip_ids.iter().filter(|trip_id| self.trip(trip_id).is_some()).count()))
+            .filter(|(_, count)| *count > 0)
+            .collect::<Vec<_>>();
+        // the sort is stable, so patterns with the same count keep the schedule's order.
+        patterns.sort_by(|(_, a), (_, b)| b.cmp(a));
+        patterns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::testing;
+    use crate::gtfs::view::ScheduleFilter;
+
+    // schedule has T4 call at X on its way from S2 to Y, so route R2 has two patterns: T2, T3 and T5's, and T4's.
+    fn schedule() -> GtfsSchedule {
+        let mut schedule = testing::schedule();
+        let stop_times = testing::trip_stop_times("T4", &[("S2", "09:30:00"), ("X", "09:35:00"), ("Y", "09:45:00")]);
+        schedule.stop_times.stop_times.insert("T4".to_string(), stop_times);
+        schedule
+    }
+
+    fn pattern(stop_ids: &[&str], trip_ids: &[&str]) -> Pattern {
+        Pattern {
+            route_id: "R2".to_string(),
+            direction_id: None,
+            stop_ids: stop_ids.iter().map(|stop_id| stop_id.to_string()).collect(),
+            trip_ids: trip_ids.iter().map(|trip_id| trip_id.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn trips_visiting_the_same_stops_share_a_pattern() {
+        let schedule = schedule();
+        assert_eq!(schedule.patterns().of_route("R2"), &[
+            pattern(&["S2", "Y"], &["T2", "T3", "T5"]),
+            pattern(&["S2", "X", "Y"], &["T4"]),
+        ]);
+        assert_eq!(schedule.patterns().of_trip("T4"), Some(&pattern(&["S2", "X", "Y"], &["T4"])));
+        assert_eq!(schedule.patterns().of_route("R3"), &[]);
+    }
+
+    #[test]
+    fn trips_in_different_directions_follow_different_patterns() {
+        let mut schedule = testing::schedule();
+        schedule.trips.trips.get_mut("T5").unwrap().direction_id = Some(trips::Direction::B);
+        let patterns = Patterns::build(&schedule);
+        assert_eq!(patterns.of_route("R2").len(), 2);
+        assert_eq!(patterns.of_trip("T5").unwrap().direction_id, Some(trips::Direction::B));
+        assert_eq!(patterns.of_trip("T5").unwrap().trip_ids, vec!["T5".to_string()]);
+    }
+
+    #[test]
+    fn trips_without_stop_times_follow_no_pattern() {
+        let mut schedule = testing::schedule();
+        schedule.stop_times.stop_times.remove("T1");
+        let patterns = Patterns::build(&schedule);
+        assert_eq!(patterns.of_trip("T1"), None);
+        assert_eq!(patterns.of_route("R1"), &[]);
+    }
+
+    #[test]
+    fn patterns_of_a_route_count_the_trips_in_the_view() {
+        let schedule = schedule();
+        let filter = ScheduleFilter {
+            route_ids: ["R2".to_string()].into_iter().collect(),
+            trip_ids: ["T4".to_string(), "T5".to_string()].into_iter().collect(),
+            ..Default::default()
+        };
+        let patterns = ScheduleView::new(&schedule, Some(&filter)).patterns_of_route("R2")
+            .into_iter()
+            .map(|(pattern, count)| (pattern.stop_ids.len(), count))
+            .collect::<Vec<_>>();
+        assert_eq!(patterns, vec![(2, 1), (3, 1)]);
+
+        let filter = ScheduleFilter { trip_ids: ["T4".to_string()].into_iter().collect(), ..filter };
+        let patterns = ScheduleView::new(&schedule, Some(&filter)).patterns_of_route("R2");
+        assert_eq!(patterns.iter().map(|(pattern, count)| (pattern.stop_ids.len(), *count)).collect::<Vec<_>>(), vec![(3, 1)]);
+    }
+}
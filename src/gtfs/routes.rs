@@ -1,4 +1,9 @@
 use csv;
+use crate::gtfs::error::CsvError;
+use crate::gtfs::de;
+use crate::gtfs::table;
+use crate::gtfs::trips;
+use serde::Deserialize;
 use std::io;
 use std::iter;
 use std::collections;
@@ -8,7 +13,7 @@ use std::str::FromStr;
 use hex_color;
 
 // Routes is a collection of routes, indexed by route_id.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Routes {
     pub routes: std::collections::HashMap<String, Route>
 }
@@ -31,79 +36,66 @@ impl iter::IntoIterator for Routes {
     }
 }
 
-// RoutesCsvLoadError is an error that occurs when loading routes from a CSV file.
-pub enum RoutesCsvLoadError {
-    NoHeader,
-    RouteLoadError(RouteLoadError),
-    CSVReadError(csv::Error)
+impl Routes {
+    // with_id_prefix prepends prefix to every route ID, and to every ID referenced by a route.
+    pub fn with_id_prefix(self, prefix: &str) -> Self {
+        Routes {
+            routes: self.routes.into_values()
+                .map(|mut route| {
+                    route.prefix_ids(prefix);
+                    (route.route_id.clone(), route)
+                })
+                .collect()
+        }
+    }
+
+    // of_type yields the routes of the given type.
+    pub fn of_type(&self, route_type: RouteType) -> impl Iterator<Item = &Route> {
+        self.routes.values().filter(move |route| route.route_type == route_type)
+    }
+
+    // of_types yields the routes of any of the given types, e.g. RouteType::RAIL_LIKE.
+    pub fn of_types<'a>(&'a self, route_types: &'a [RouteType]) -> impl Iterator<Item = &'a Route> {
+        self.routes.values().filter(move |route| route_types.contains(&route.route_type))
+    }
 }
 
-impl fmt::Display for RoutesCsvLoadError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::NoHeader => write!(f, "No header found"),
-            Self::RouteLoadError(e) => write!(f, "Error loading route: {}", e),
-            Self::CSVReadError(e) => write!(f, "Error reading CSV: {}", e)
+// Routes implements FromIterator<Route> so that it can be assembled in memory, indexing each route by its ID.
+impl iter::FromIterator<Route> for Routes {
+    fn from_iter<I: IntoIterator<Item = Route>>(iter: I) -> Self {
+        Routes {
+            routes: iter.into_iter().map(|route| (route.route_id.clone(), route)).collect()
         }
     }
 }
 
-// Routes implements TryFrom<csv::Reader<R>> by attempting to consume and read from a csv::Reader<R>.
+// Routes implements TryFrom<csv::Reader<R>> by loading the reader's contents as a GtfsTable.
 impl<R: io::Read> TryFrom<csv::Reader<R>> for Routes {
-    // The error type for this function is RoutesCsvLoadError.
-    type Error = RoutesCsvLoadError;
-
-    // try_from consumes the csv::Reader<R> and returns a Result holding a Routes object, or a RoutesCsvLoadError.
-    fn try_from(mut r: csv::Reader<R>) -> Result<Self, Self::Error> {
-        // try to get the headers; if there are no headers, return a RoutesCsvLoadError::NoHeader.
-        r.headers().cloned().map_err(|_| RoutesCsvLoadError::NoHeader).and_then(
-            // if there are headers, try to create a Routes object from the remaining records.
-            |header|
-            Ok(Routes {
-                // to create the actual collection of routes, we need to iterate over the records
-                routes: r.into_records()
-                    // and fold them into an overarching result containing the collection.
-                    .fold(
-                        Ok(collections::HashMap::new()),
-                        // at each stage of the fold,
-                        |routes_result, record_result|
-                        // proceed only if the running result is Ok.
-                        routes_result
-                        .and_then(|mut routes|
-                            // if there was an error reading this record, return that error.
-                            record_result.map_err(RoutesCsvLoadError::CSVReadError)
-                                .and_then(
-                                    // otherwise, try to create a Route object from the record.
-                                    |record|
-                                    Route::try_from(
-                                        // Zip the header and record together,
-                                        iter::zip(
-                                            header.iter().map(|s| s.to_string()),
-                                            record.iter().map(|s| s.to_string())
-                                        )
-                                        // and collect the results into a HashMap.
-                                        .collect::<collections::HashMap<String, String>>()
-                                    )
-                                    // if there is an error creating the Route object from the HashMap, return that error.
-                                    .map_err(|err| RoutesCsvLoadError::RouteLoadError(err))
-                            )
-                            .map(
-                                |route| {
-                                    // insert the Route object into the HashMap.
-                                    routes.insert(route.route_id.clone(), route);
-                                    // return the updated HashMap.
-                                    routes
-                                }
-                            )
-                        )
-                    // extract the HashMap from the Result, or return the error.
-                    )?
-            })
-        )
+    type Error = CsvError;
+
+    fn try_from(r: csv::Reader<R>) -> Result<Self, Self::Error> {
+        table::load(r)
+    }
+}
+
+// Routes holds the records of routes.txt, indexed by route_id.
+impl table::GtfsTable for Routes {
+    type Record = Route;
+
+    const FILE_NAME: &'static str = "routes.txt";
+
+    fn key(route: &Route) -> String {
+        route.route_id.clone()
+    }
+
+    fn insert(&mut self, route_id: String, route: Route) {
+        self.routes.insert(route_id, route);
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+// Rows are deserialized as RouteRecords, since a route must have a route_short_name, a route_long_name or both.
+#[serde(try_from = "RouteRecord")]
 pub struct Route {
     pub route_id: String,
     pub agency_id: Option<String>,
@@ -120,6 +112,24 @@ pub struct Route {
 }
 
 impl Route {
+    // new creates a route with the given ID, name and type, leaving every optional field unset.
+    pub fn new(route_id: &str, name: RouteName, route_type: RouteType) -> Self {
+        Route {
+            route_id: route_id.to_string(),
+            agency_id: None,
+            name,
+            route_desc: None,
+            route_type,
+            route_url: None,
+            route_color: None,
+            route_text_color: None,
+            route_sort_order: None,
+            continuous_pickup: None,
+            continuous_drop_off: None,
+            network_id: None,
+        }
+    }
+
     pub fn route_long_name(&self) -> Option<&str> {
         self.name.long()
     }
@@ -144,6 +154,40 @@ impl Route {
         }
     }
 
+    // prefix_ids prepends prefix to this route's ID and to every ID it references.
+    pub fn prefix_ids(&mut self, prefix: &str) {
+        self.route_id.insert_str(0, prefix);
+        if let Some(agency_id) = self.agency_id.as_mut() {
+            agency_id.insert_str(0, prefix);
+        }
+        if let Some(network_id) = self.network_id.as_mut() {
+            network_id.insert_str(0, prefix);
+        }
+    }
+
+    // directions groups the route's trips by direction and headsign, giving the distinct ways the route can be travelled,
+    // e.g. "Inbound to South Station" and "Outbound to Forest Hills". Trips of other routes are ignored, so trips may be
+    // a whole Trips table or any part of one. Directions are ordered by direction_id, with unspecified directions last,
+    // then with those of the most trips first.
+    pub fn directions<'a>(&self, trips: impl IntoIterator<Item = &'a trips::Trip>) -> Vec<RouteDirection> {
+        let mut counts = collections::HashMap::<(Option<trips::Direction>, Option<&str>), usize>::new();
+        for trip in trips.into_iter().filter(|trip| trip.route_id == self.route_id) {
+            *counts.entry((trip.direction_id, trip.trip_headsign.as_deref())).or_default() += 1;
+        }
+        let mut directions = counts.into_iter()
+            .map(|((direction_id, headsign), trips)| RouteDirection {
+                direction_id,
+                headsign: headsign.map(|headsign| headsign.to_string()),
+                trips,
+            })
+            .collect::<Vec<_>>();
+        directions.sort_by(|a, b| a.direction_id.map(|direction| direction.code()).unwrap_or(u8::MAX)
+            .cmp(&b.direction_id.map(|direction| direction.code()).unwrap_or(u8::MAX))
+            .then_with(|| b.trips.cmp(&a.trips))
+            .then_with(|| a.headsign.cmp(&b.headsign)));
+        directions
+    }
+
     pub fn name(&self) -> String {
         match self.route_long_name() {
             Some(long_name) => match self.route_short_name() {
@@ -158,103 +202,134 @@ impl Route {
     }
 }
 
-pub enum RouteLoadError {
-    RouteIdRequired,
-    RouteNameError(String),
-    ParseRouteColorError(hex_color::ParseHexColorError),
-    ParseRouteTextColorError(hex_color::ParseHexColorError),
-    ParseRouteSortOrderError(std::num::ParseIntError),
-    RouteTypeError(String),
-    InvalidContinuousPickup(RouteContinuityPolicyLoadError),
-    InvalidContinuousDropOff(RouteContinuityPolicyLoadError),
+// RouteDirection is a distinct direction and headsign among a route's trips, with the number of trips which share them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteDirection {
+    pub direction_id: Option<trips::Direction>,
+    pub headsign: Option<String>,
+    pub trips: usize,
 }
 
-impl fmt::Display for RouteLoadError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::RouteIdRequired => write!(f, "route_id is required"),
-            Self::RouteNameError(e) => write!(f, "Error parsing route name: {}", e),
-            Self::ParseRouteColorError(e) => write!(f, "Error parsing route color: {}", e),
-            Self::ParseRouteTextColorError(e) => write!(f, "Error parsing route text color: {}", e),
-            Self::ParseRouteSortOrderError(e) => write!(f, "Error parsing route sort order: {}", e),
-            Self::RouteTypeError(e) => write!(f, "Error parsing route type: {}", e),
-            Self::InvalidContinuousPickup(e) => write!(f, "Error parsing continuous pickup: {}", e),
-            Self::InvalidContinuousDropOff(e) => write!(f, "Error parsing continuous drop off: {}", e),
-        }
+// the fields of routes.txt, in the order they are listed by the GTFS reference.
+pub const ROUTE_FIELDS: [&str; 13] = [
+    "route_id", "agency_id", "route_short_name", "route_long_name", "route_desc", "route_type", "route_url",
+    "route_color", "route_text_color", "route_sort_order", "continuous_pickup", "continuous_drop_off", "network_id",
+];
+
+// collections::HashMap<String, String> implements From<&Route> by encoding each field as it would appear in routes.txt.
+// This is the inverse of Route's Deserialize implementation.
+impl From<&Route> for collections::HashMap<String, String> {
+    fn from(route: &Route) -> Self {
+        [
+            ("route_id", Some(route.route_id.clone())),
+            ("agency_id", route.agency_id.clone()),
+            ("route_short_name", route.route_short_name().map(|s| s.to_string())),
+            ("route_long_name", route.route_long_name().map(|s| s.to_string())),
+            ("route_desc", route.route_desc.clone()),
+            ("route_type", Some(route.route_type.code().to_string())),
+            ("route_url", route.route_url.clone()),
+            ("route_color", route.route_color.map(encode_color)),
+            ("route_text_color", route.route_text_color.map(encode_color)),
+            ("route_sort_order", route.route_sort_order.map(|order| order.to_string())),
+            ("continuous_pickup", route.continuous_pickup.as_ref().map(|policy| policy.code().to_string())),
+            ("continuous_drop_off", route.continuous_drop_off.as_ref().map(|policy| policy.code().to_string())),
+            ("network_id", route.network_id.clone()),
+        ]
+            .into_iter()
+            .filter_map(|(field, value)| value.map(|value| (field.to_string(), value)))
+            .collect()
     }
 }
 
-// Route implements TryFrom<collections::HashMap<String, String>> by interpreting the keys as field names, and
-// the values as string-encoded values for those fields.
-impl TryFrom<collections::HashMap<String, String>> for Route {
-    type Error = RouteLoadError;
+// colors are encoded in GTFS as six hex digits without a leading '#'.
+fn encode_color(color: hex_color::HexColor) -> String {
+    format!("{:02X}{:02X}{:02X}", color.r, color.g, color.b)
+}
 
-    fn try_from(fields: collections::HashMap<String, String>) -> Result<Self, Self::Error> {
+// RouteRecord is a row of routes.txt, read field by field before its names are combined into a RouteName.
+#[derive(Deserialize)]
+struct RouteRecord {
+    #[serde(deserialize_with = "de::required")]
+    route_id: String,
+    #[serde(default, deserialize_with = "de::optional")]
+    agency_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    route_short_name: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    route_long_name: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    route_desc: Option<String>,
+    #[serde(deserialize_with = "route_type_code")]
+    route_type: RouteType,
+    #[serde(default, deserialize_with = "de::optional")]
+    route_url: Option<String>,
+    #[serde(default, deserialize_with = "optional_color")]
+    route_color: Option<hex_color::HexColor>,
+    #[serde(default, deserialize_with = "optional_color")]
+    route_text_color: Option<hex_color::HexColor>,
+    #[serde(default, deserialize_with = "de::optional")]
+    route_sort_order: Option<usize>,
+    #[serde(default, deserialize_with = "de::optional")]
+    continuous_pickup: Option<RouteContinuityPolicy>,
+    #[serde(default, deserialize_with = "de::optional")]
+    continuous_drop_off: Option<RouteContinuityPolicy>,
+    #[serde(default, deserialize_with = "de::optional")]
+    network_id: Option<String>,
+}
+
+// Route implements TryFrom<RouteRecord> by requiring the record to have at least one of its names.
+impl TryFrom<RouteRecord> for Route {
+    type Error = RouteNameRequiredError;
+
+    fn try_from(record: RouteRecord) -> Result<Self, Self::Error> {
         Ok(Route {
-            route_id: fields.get("route_id")
-                .filter(|s| !s.is_empty())
-                .ok_or(RouteLoadError::RouteIdRequired)?
-                .clone(),
-            agency_id: fields.get("agency_id").filter(|s| !s.is_empty()).cloned(),
-            name: RouteName::try_from(&fields).map_err(|e| RouteLoadError::RouteNameError(e))?,
-            route_desc: fields.get("route_desc").filter(|s| !s.is_empty()).cloned(),
-            route_type: RouteType::try_from(&fields).map_err(|e| RouteLoadError::RouteTypeError(e))?,
-            route_url: fields.get("route_url").filter(|s| !s.is_empty()).cloned(),
-            route_color: match
-                    fields.get("route_color")
-                        .filter(|s| !s.is_empty())
-                        .map(|s| hex_color::HexColor::from_str(&(String::from("#") + s)))
-                {
-                    Some(res) => res
-                        .map(|color| Some(color))
-                        .map_err(|e| RouteLoadError::ParseRouteColorError(e))?,
-                    None => Ok(None)?
-                },
-            route_text_color: match
-                fields.get("route_text_color")
-                    .filter(|s| !s.is_empty())
-                    .map(|s| hex_color::HexColor::from_str(&(String::from("#") + s)))
-                {
-                    Some(res) => res
-                        .map(|color| Some(color))
-                        .map_err(|e| RouteLoadError::ParseRouteTextColorError(e))?,
-                    None => Ok(None)?
-                },
-            route_sort_order: match
-                fields.get("route_sort_order")
-                    .filter(|s| !s.is_empty())
-                    .map(|s| s.parse::<usize>())
-                {
-                    Some(res) => res
-                        .map(|order| Some(order))
-                        .map_err(|e| RouteLoadError::ParseRouteSortOrderError(e)),
-                    None => Ok(None)
-                }?,
-            continuous_pickup: match fields.get("continuous_pickup")
-                .filter(|s| !s.is_empty())
-                .map(|s| s.parse::<RouteContinuityPolicy>()) {
-                    Some(res) => res
-                        .map(|policy| Some(policy))
-                        .map_err(|e| RouteLoadError::InvalidContinuousPickup(e)),
-                    None => Ok(None)
-                }?,
-            continuous_drop_off: match fields.get("continuous_drop_off")
-                .filter(|s| !s.is_empty())
-                .map(|s| s.parse::<RouteContinuityPolicy>()) {
-                    Some(res) => res
-                        .map(|policy| Some(policy))
-                        .map_err(|e| RouteLoadError::InvalidContinuousDropOff(e)),
-                    None => Ok(None)
-                }?,
-            network_id: fields.get("network_id").filter(|s| !s.is_empty()).cloned(),
+            route_id: record.route_id,
+            agency_id: record.agency_id,
+            name: RouteName::from_names(record.route_short_name, record.route_long_name).ok_or(RouteNameRequiredError)?,
+            route_desc: record.route_desc,
+            route_type: record.route_type,
+            route_url: record.route_url,
+            route_color: record.route_color,
+            route_text_color: record.route_text_color,
+            route_sort_order: record.route_sort_order,
+            continuous_pickup: record.continuous_pickup,
+            continuous_drop_off: record.continuous_drop_off,
+            network_id: record.network_id,
         })
     }
 }
 
+// route_type_code reads a route_type, which routes.txt gives by its code alone.
+fn route_type_code<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<RouteType, D::Error> {
+    let code = de::required_str(deserializer)?;
+    code.parse().ok()
+        .and_then(RouteType::from_code)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid route type '{}'", code)))
+}
+
+// optional_color reads a route_color or route_text_color, which routes.txt gives as six hex digits without a leading '#'.
+fn optional_color<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<hex_color::HexColor>, D::Error> {
+    de::optional_str(deserializer)?
+        .map(|s| hex_color::HexColor::from_str(&format!("#{}", s)))
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug)]
+pub struct RouteNameRequiredError;
+
+impl fmt::Display for RouteNameRequiredError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "route_short_name or route_long_name is required")
+    }
+}
+
+impl std::error::Error for RouteNameRequiredError {}
+
 // RouteName is a type that represents the name of a route.
 // It represents the requirement that a route must have at
 // least one of a short name or a long name.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum RouteName {
     Short(String),
     Long(String),
@@ -262,6 +337,16 @@ pub enum RouteName {
 }
 
 impl RouteName {
+    // from_names gives the name of a route with the given short and long names, or None if it has neither.
+    pub fn from_names(short_name: Option<String>, long_name: Option<String>) -> Option<Self> {
+        match (short_name, long_name) {
+            (Some(short), Some(long)) => Some(RouteName::LongAndShort(long, short)),
+            (Some(short), None) => Some(RouteName::Short(short)),
+            (None, Some(long)) => Some(RouteName::Long(long)),
+            (None, None) => None,
+        }
+    }
+
     pub fn long(&self) -> Option<&str> {
         match self {
             RouteName::Long(name) => Some(name),
@@ -279,23 +364,7 @@ impl RouteName {
     }
 }
 
-impl TryFrom<&collections::HashMap<String, String>> for RouteName {
-    type Error = String;
-
-    fn try_from(fields: &collections::HashMap<String, String>) -> Result<Self, Self::Error> {
-        let short_name = fields.get("route_short_name").filter(|s| !s.is_empty()).cloned();
-        let long_name = fields.get("route_long_name").filter(|s| !s.is_empty()).cloned();
-
-        match (short_name, long_name) {
-            (Some(short), Some(long)) => Ok(RouteName::LongAndShort(long, short)),
-            (Some(short), None) => Ok(RouteName::Short(short)),
-            (None, Some(long)) => Ok(RouteName::Long(long)),
-            (None, None) => Err(String::from("route_short_name or route_long_name is required")),
-        }
-    }
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum RouteContinuityPolicy {
     Continuous,
     NotContinuous,
@@ -303,6 +372,7 @@ pub enum RouteContinuityPolicy {
     CoordinateWithDriver,
 }
 
+#[derive(Debug)]
 pub struct RouteContinuityPolicyLoadError (String);
 
 impl fmt::Display for RouteContinuityPolicyLoadError {
@@ -311,6 +381,20 @@ impl fmt::Display for RouteContinuityPolicyLoadError {
     }
 }
 
+impl std::error::Error for RouteContinuityPolicyLoadError {}
+
+impl RouteContinuityPolicy {
+    // code returns the value used to encode this policy in GTFS files.
+    pub fn code(&self) -> u8 {
+        match self {
+            RouteContinuityPolicy::Continuous => 0,
+            RouteContinuityPolicy::NotContinuous => 1,
+            RouteContinuityPolicy::Prearrange => 2,
+            RouteContinuityPolicy::CoordinateWithDriver => 3,
+        }
+    }
+}
+
 impl FromStr for RouteContinuityPolicy {
     type Err = RouteContinuityPolicyLoadError;
 
@@ -325,7 +409,7 @@ impl FromStr for RouteContinuityPolicy {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
 pub enum RouteType {
     TramStreetcarLightRail,
     SubwayMetro,
@@ -339,31 +423,104 @@ pub enum RouteType {
     Monorail,
 }
 
-impl TryFrom<&collections::HashMap<String, String>> for RouteType {
-    type Error = String;
-
-    fn try_from(fields: &collections::HashMap<String, String>) -> Result<Self, Self::Error> {
-        let zero_str = String::from("0");
-
-        let route_type = fields.get("route_type").unwrap_or(&zero_str);
-
-        match route_type.parse::<u8>()
-            .map_err(
-                |err|
-                format!("invalid route_type '{}': {}", route_type, err)
-            )?
-        {
-            0 => Ok(RouteType::TramStreetcarLightRail),
-            1 => Ok(RouteType::SubwayMetro),
-            2 => Ok(RouteType::Rail),
-            3 => Ok(RouteType::Bus),
-            4 => Ok(RouteType::Ferry),
-            5 => Ok(RouteType::CableTram),
-            6 => Ok(RouteType::AerialLift),
-            7 => Ok(RouteType::Funicular),
-            8 => Ok(RouteType::Trolleybus),
-            9 => Ok(RouteType::Monorail),
-            _ => Err(format!("invalid route_type '{}'", route_type))
-        }.map_err(|err| format!("failed to load route type '{}': {}", route_type, err))
+impl RouteType {
+    pub const ALL: [RouteType; 10] = [
+        RouteType::TramStreetcarLightRail, RouteType::SubwayMetro, RouteType::Rail, RouteType::Bus, RouteType::Ferry,
+        RouteType::CableTram, RouteType::AerialLift, RouteType::Funicular, RouteType::Trolleybus, RouteType::Monorail,
+    ];
+    // RAIL_LIKE lists the types which run on rails.
+    pub const RAIL_LIKE: [RouteType; 6] = [
+        RouteType::TramStreetcarLightRail, RouteType::SubwayMetro, RouteType::Rail, RouteType::CableTram,
+        RouteType::Funicular, RouteType::Monorail,
+    ];
+    // ROAD lists the types which run on roads.
+    pub const ROAD: [RouteType; 2] = [RouteType::Bus, RouteType::Trolleybus];
+
+    pub fn is_rail_like(&self) -> bool {
+        RouteType::RAIL_LIKE.contains(self)
+    }
+
+    // name returns a short lowercase name for the type, which FromStr accepts along with its code.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RouteType::TramStreetcarLightRail => "tram",
+            RouteType::SubwayMetro => "subway",
+            RouteType::Rail => "rail",
+            RouteType::Bus => "bus",
+            RouteType::Ferry => "ferry",
+            RouteType::CableTram => "cable_tram",
+            RouteType::AerialLift => "aerial_lift",
+            RouteType::Funicular => "funicular",
+            RouteType::Trolleybus => "trolleybus",
+            RouteType::Monorail => "monorail",
+        }
+    }
+
+    // from_code returns the route type encoded in GTFS files by code, if there is one.
+    pub fn from_code(code: u8) -> Option<Self> {
+        RouteType::ALL.into_iter().find(|route_type| route_type.code() == code)
+    }
+
+    // code returns the value used to encode this route type in GTFS files.
+    pub fn code(&self) -> u8 {
+        match self {
+            RouteType::TramStreetcarLightRail => 0,
+            RouteType::SubwayMetro => 1,
+            RouteType::Rail => 2,
+            RouteType::Bus => 3,
+            RouteType::Ferry => 4,
+            RouteType::CableTram => 5,
+            RouteType::AerialLift => 6,
+            RouteType::Funicular => 7,
+            RouteType::Trolleybus => 8,
+            RouteType::Monorail => 9,
+        }
+    }
+}
+
+// RouteType implements FromStr by accepting either a type's name, as given by RouteType::name, or its code.
+impl FromStr for RouteType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RouteType::ALL.into_iter()
+            .find(|route_type| route_type.name() == s || route_type.code().to_string() == s)
+            .ok_or_else(|| format!("invalid route type '{}'", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::testing;
+
+    // load_error returns the message of the error loading the given routes.txt.
+    fn load_error(text: &str) -> String {
+        table::load::<Routes, _>(csv::Reader::from_reader(text.as_bytes())).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn routes_are_read_with_their_names_type_and_colors() {
+        let routes: Routes = testing::load(
+            "route_id,route_short_name,route_long_name,route_type,route_color,route_sort_order,continuous_pickup\n\
+             R1,1,Red,1,DA291C,2,1\n\
+             R2,,Blue,3,,,\n",
+        );
+        let red = &routes.routes["R1"];
+        assert_eq!((red.route_short_name(), red.route_long_name()), (Some("1"), Some("Red")));
+        assert_eq!(red.route_type, RouteType::SubwayMetro);
+        assert_eq!(red.route_color, Some(hex_color::HexColor::rgb(0xDA, 0x29, 0x1C)));
+        assert_eq!(red.route_sort_order, Some(2));
+        assert_eq!(red.continuous_pickup, Some(RouteContinuityPolicy::NotContinuous));
+        assert_eq!(routes.routes["R2"].name, RouteName::Long("Blue".to_string()));
+    }
+
+    #[test]
+    fn invalid_routes_are_rejected() {
+        assert_eq!(load_error("route_id,route_short_name,route_long_name,route_type\nR,,,3\n"),
+            "routes.txt:2: route_short_name or route_long_name is required");
+        assert_eq!(load_error("route_id,route_short_name,route_type\nR,1,bus\n"), "routes.txt:2: invalid route type 'bus'");
+        assert_eq!(load_error("route_id,route_short_name,route_type\nR,1,42\n"), "routes.txt:2: invalid route type '42'");
+        assert!(load_error("route_id,route_short_name,route_type,route_color\nR,1,3,red\n").starts_with("routes.txt:2: "));
     }
 }
@@ -0,0 +1,109 @@
+use csv;
+use crate::gtfs::error::CsvError;
+use crate::gtfs::de;
+use crate::gtfs::table;
+use serde::Deserialize;
+use std::io;
+use std::iter;
+use std::collections;
+
+// Shapes is a collection of shape points, indexed by shape_id.
+// The points of each shape are ordered by shape_pt_sequence.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Shapes {
+    pub shapes: std::collections::HashMap<String, Vec<ShapePoint>>
+}
+
+impl Shapes {
+    pub fn iter(&self) -> impl Iterator<Item = &ShapePoint> {
+        self.shapes.values().flatten()
+    }
+
+    // with_id_prefix prepends prefix to every shape ID.
+    pub fn with_id_prefix(self, prefix: &str) -> Self {
+        Shapes {
+            shapes: self.shapes.into_iter()
+                .map(|(shape_id, mut points)| {
+                    points.iter_mut().for_each(|point| point.shape_id.insert_str(0, prefix));
+                    (format!("{}{}", prefix, shape_id), points)
+                })
+                .collect()
+        }
+    }
+}
+
+// Shapes implements FromIterator<ShapePoint> so that it can be assembled in memory, grouping records by shape_id ordered by shape_pt_sequence.
+impl iter::FromIterator<ShapePoint> for Shapes {
+    fn from_iter<I: IntoIterator<Item = ShapePoint>>(iter: I) -> Self {
+        let mut shapes = collections::HashMap::new();
+        for point in iter {
+            shapes.entry(point.shape_id.clone()).or_insert(Vec::new()).push(point);
+        }
+        shapes.values_mut().for_each(|points: &mut Vec<ShapePoint>| points.sort_by_key(|point| point.shape_pt_sequence));
+        Shapes { shapes }
+    }
+}
+
+// Shapes implements TryFrom<csv::Reader<R>> by loading the reader's contents as a GtfsTable.
+impl<R: io::Read> TryFrom<csv::Reader<R>> for Shapes {
+    type Error = CsvError;
+
+    fn try_from(r: csv::Reader<R>) -> Result<Self, Self::Error> {
+        table::load(r)
+    }
+}
+
+// Shapes holds the records of shapes.txt, grouped by shape_id and ordered by shape_pt_sequence.
+impl table::GtfsTable for Shapes {
+    type Record = ShapePoint;
+
+    const FILE_NAME: &'static str = "shapes.txt";
+
+    fn key(point: &ShapePoint) -> String {
+        point.shape_id.clone()
+    }
+
+    fn insert(&mut self, shape_id: String, point: ShapePoint) {
+        self.shapes.entry(shape_id).or_default().push(point);
+    }
+
+    // shape points are not required to appear in order in shapes.txt.
+    fn finish(&mut self) {
+        self.shapes.values_mut().for_each(|points| points.sort_by_key(|point| point.shape_pt_sequence));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ShapePoint {
+    #[serde(deserialize_with = "de::required")]
+    pub shape_id: String,
+    #[serde(deserialize_with = "de::required")]
+    pub shape_pt_lat: f64,
+    #[serde(deserialize_with = "de::required")]
+    pub shape_pt_lon: f64,
+    #[serde(deserialize_with = "de::required")]
+    pub shape_pt_sequence: usize,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub shape_dist_traveled: Option<f64>,
+}
+
+// the fields of shapes.txt, in the order they are listed by the GTFS reference.
+pub const SHAPE_POINT_FIELDS: [&str; 5] = [
+    "shape_id", "shape_pt_lat", "shape_pt_lon", "shape_pt_sequence", "shape_dist_traveled",
+];
+
+// collections::HashMap<String, String> implements From<&ShapePoint> by encoding each field as it would appear in shapes.txt.
+impl From<&ShapePoint> for collections::HashMap<String, String> {
+    fn from(point: &ShapePoint) -> Self {
+        [
+            ("shape_id", Some(point.shape_id.clone())),
+            ("shape_pt_lat", Some(point.shape_pt_lat.to_string())),
+            ("shape_pt_lon", Some(point.shape_pt_lon.to_string())),
+            ("shape_pt_sequence", Some(point.shape_pt_sequence.to_string())),
+            ("shape_dist_traveled", point.shape_dist_traveled.map(|dist| dist.to_string())),
+        ]
+            .into_iter()
+            .filter_map(|(field, value)| value.map(|value| (field.to_string(), value)))
+            .collect()
+    }
+}
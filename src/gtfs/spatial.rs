@@ -0,0 +1,241 @@
+use crate::gtfs::stops;
+use std::cmp;
+use std::collections;
+use std::ops;
+
+// the mean radius of the Earth, in meters.
+pub const EARTH_RADIUS: f64 = 6_371_008.8;
+
+// haversine returns the great-circle distance in meters between two points given in degrees.
+pub fn haversine(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let (dlat, dlon) = (lat2 - lat1, (lon2 - lon1).to_radians());
+    let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * a.sqrt().asin()
+}
+
+// SpatialIndex is a kd-tree over the stops which have coordinates.
+// Points are placed on the unit sphere, where the straight-line distance between two points orders them the same way
+// as the great-circle distance does, so the tree needs no special handling of the poles or the antimeridian.
+#[derive(Debug, Clone, Default)]
+pub struct SpatialIndex {
+    // points holds the tree in implicit form: the median of each range is its root, and the ranges either side its subtrees.
+    points: Vec<Point>,
+}
+
+#[derive(Debug, Clone)]
+struct Point {
+    stop_id: String,
+    lat: f64,
+    lon: f64,
+    position: [f64; 3],
+}
+
+impl SpatialIndex {
+    pub fn build(stops: &stops::Stops) -> Self {
+        let mut points = stops.into_iter()
+            .filter_map(|stop| Some(Point {
+                stop_id: stop.stop_id.clone(),
+                lat: stop.stop_lat()?,
+                lon: stop.stop_lon()?,
+                position: to_unit_sphere(stop.stop_lat()?, stop.stop_lon()?),
+            }))
+            .collect::<Vec<_>>();
+        build_tree(&mut points, 0);
+        SpatialIndex { points }
+    }
+
+    // nearest returns the IDs of the n stops closest to the given point, with their distances in meters, closest first.
+    pub fn nearest(&self, lat: f64, lon: f64, n: usize) -> Vec<(&str, f64)> {
+        let target = to_unit_sphere(lat, lon);
+        // the heap holds the closest points found so far, with the furthest of them on top.
+        let mut closest = collections::BinaryHeap::with_capacity(n + 1);
+        let mut radius = f64::INFINITY;
+        if n > 0 {
+            self.search(0..self.points.len(), 0, &target, &mut |index, distance, closest_distance: &mut f64| {
+                closest.push(Candidate { distance, index });
+                if closest.len() > n {
+                    closest.pop();
+                }
+                if closest.len() == n {
+                    *closest_distance = closest.peek().map_or(f64::INFINITY, |candidate| candidate.distance);
+                }
+            }, &mut radius);
+        }
+        self.with_meters(lat, lon, closest.into_sorted_vec().into_iter().map(|candidate| candidate.index))
+    }
+
+    // within_radius returns the IDs of the stops within meters of the given point, with their distances in meters, closest first.
+    pub fn within_radius(&self, lat: f64, lon: f64, meters: f64) -> Vec<(&str, f64)> {
+        let target = to_unit_sphere(lat, lon);
+        // the straight-line distance through the sphere corresponding to the given distance along its surface.
+        let mut radius = 2.0 * (meters.min(EARTH_RADIUS * std::f64::consts::PI) / (2.0 * EARTH_RADIUS)).sin();
+        let mut found = Vec::new();
+        self.search(0..self.points.len(), 0, &target, &mut |index, _, _: &mut f64| found.push(index), &mut radius);
+        let mut results = self.with_meters(lat, lon, found.into_iter());
+        results.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        results
+    }
+
+    // search visits every point in the subtree held in points[range] which is no further from target than radius.
+    // visit may shrink radius as it goes, to prune the rest of the search.
+    fn search(
+        &self,
+        range: ops::Range<usize>,
+        depth: usize,
+        target: &[f64; 3],
+        visit: &mut impl FnMut(usize, f64, &mut f64),
+        radius: &mut f64,
+    ) {
+        if range.is_empty() {
+            return;
+        }
+        let median = range.start + range.len() / 2;
+        let axis = depth % 3;
+        let point = &self.points[median];
+
+        let distance = euclidean(&point.position, target);
+        if distance <= *radius {
+            visit(median, distance, radius);
+        }
+
+        let offset = target[axis] - point.position[axis];
+        let (near, far) = if offset < 0.0 {
+            (range.start..median, median + 1..range.end)
+        } else {
+            (median + 1..range.end, range.start..median)
+        };
+        self.search(near, depth + 1, target, visit, radius);
+        if offset.abs() <= *radius {
+            self.search(far, depth + 1, target, visit, radius);
+        }
+    }
+
+    fn with_meters(&self, lat: f64, lon: f64, indices: impl Iterator<Item = usize>) -> Vec<(&str, f64)> {
+        indices
+            .map(|index| &self.points[index])
+            .map(|point| (point.stop_id.as_str(), haversine(lat, lon, point.lat, point.lon)))
+            .collect()
+    }
+}
+
+// build_tree arranges points into an implicit kd-tree, splitting on x, y and z in turn.
+fn build_tree(points: &mut [Point], depth: usize) {
+    if points.len() <= 1 {
+        return;
+    }
+    let median = points.len() / 2;
+    let axis = depth % 3;
+    points.select_nth_unstable_by(median, |a, b| a.position[axis].total_cmp(&b.position[axis]));
+    let (left, right) = points.split_at_mut(median);
+    build_tree(left, depth + 1);
+    build_tree(&mut right[1..], depth + 1);
+}
+
+fn to_unit_sphere(lat: f64, lon: f64) -> [f64; 3] {
+    let (lat, lon) = (lat.to_radians(), lon.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+fn euclidean(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| (a - b).powi(2)).sum::<f64>().sqrt()
+}
+
+// Candidate is a point found by a nearest-neighbour search, ordered by its distance from the target.
+struct Candidate {
+    distance: f64,
+    index: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.distance.total_cmp(&other.distance).then(self.index.cmp(&other.index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::testing;
+
+    // stops scatters stops over a region around New York, and a few either side of the antimeridian.
+    fn stops() -> stops::Stops {
+        let mut seed = 12345u64;
+        let mut random = move || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) as f64 / (1u64 << 31) as f64
+        };
+        let mut stops = (0..500)
+            .map(|i| testing::stop(&format!("s{}", i), 40.5 + random() * 0.5, -74.2 + random() * 0.5, None))
+            .collect::<Vec<_>>();
+        stops.push(testing::stop("east", 0.0, 179.999, None));
+        stops.push(testing::stop("west", 0.0, -179.999, None));
+        stops.into_iter().collect()
+    }
+
+    // brute_force measures the distance to every stop, closest first.
+    fn brute_force(stops: &stops::Stops, lat: f64, lon: f64) -> Vec<(String, f64)> {
+        let mut distances = stops.into_iter()
+            .map(|stop| (stop.stop_id.clone(), haversine(lat, lon, stop.stop_lat().unwrap(), stop.stop_lon().unwrap())))
+            .collect::<Vec<_>>();
+        distances.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        distances
+    }
+
+    fn ids(results: Vec<(&str, f64)>) -> Vec<String> {
+        results.into_iter().map(|(stop_id, _)| stop_id.to_string()).collect()
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let stops = stops();
+        let index = SpatialIndex::build(&stops);
+        for (lat, lon) in [(40.75, -73.95), (40.5, -74.2), (41.5, -73.0)] {
+            let expected = brute_force(&stops, lat, lon).into_iter().take(10).map(|(stop_id, _)| stop_id).collect::<Vec<_>>();
+            assert_eq!(ids(index.nearest(lat, lon, 10)), expected);
+        }
+    }
+
+    #[test]
+    fn nearest_returns_at_most_the_stops_there_are() {
+        let stops = stops();
+        let index = SpatialIndex::build(&stops);
+        assert!(index.nearest(40.75, -73.95, 0).is_empty());
+        assert_eq!(index.nearest(40.75, -73.95, 1000).len(), 502);
+    }
+
+    #[test]
+    fn nearest_crosses_the_antimeridian() {
+        let index = SpatialIndex::build(&stops());
+        let nearest = index.nearest(0.0, 179.9995, 2);
+        assert_eq!(ids(nearest.clone()), vec!["east", "west"]);
+        assert!(nearest[1].1 < 200.0);
+    }
+
+    #[test]
+    fn within_radius_matches_brute_force() {
+        let stops = stops();
+        let index = SpatialIndex::build(&stops);
+        let expected = brute_force(&stops, 40.75, -73.95).into_iter()
+            .take_while(|(_, distance)| *distance <= 2000.0)
+            .map(|(stop_id, _)| stop_id)
+            .collect::<Vec<_>>();
+        assert!(!expected.is_empty());
+        assert_eq!(ids(index.within_radius(40.75, -73.95, 2000.0)), expected);
+        assert!(index.within_radius(0.0, 0.0, 1000.0).is_empty());
+    }
+}
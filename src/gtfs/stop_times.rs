@@ -1,4 +1,8 @@
 use csv;
+use crate::gtfs::error::CsvError;
+use crate::gtfs::de;
+use crate::gtfs::table;
+use serde::Deserialize;
 use std::f32::consts::E;
 use std::io;
 use std::iter;
@@ -9,8 +13,8 @@ use std::num;
 use chrono;
 use crate::gtfs::routes;
 
-// StopTimes is a collection of stop times, indexed by trip_id.
-#[derive(Debug, Clone)]
+// StopTimes is a collection of stop times, indexed by trip_id, with each trip's ordered by stop_sequence.
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct StopTimes {
     pub stop_times: std::collections::HashMap<String, Vec<StopTime>>
 }
@@ -19,103 +23,154 @@ impl StopTimes {
     pub fn iter(&self) -> impl Iterator<Item = &StopTime> {
         self.stop_times.values().map(<&Vec<StopTime>>::into_iter).flatten()
     }
-}
 
-// StopTimesCsvLoadError is an error that occurs when loading stop times from a CSV file.
-pub enum StopTimesCsvLoadError {
-    NoHeader,
-    StopTimeLoadError(StopTimeLoadError),
-    CSVReadError(csv::Error)
+    // with_id_prefix prepends prefix to every ID referenced by a stop time.
+    pub fn with_id_prefix(self, prefix: &str) -> Self {
+        StopTimes {
+            stop_times: self.stop_times.into_iter()
+                .map(|(trip_id, mut stop_times)| {
+                    stop_times.iter_mut().for_each(|stop_time| stop_time.prefix_ids(prefix));
+                    (format!("{}{}", prefix, trip_id), stop_times)
+                })
+                .collect()
+        }
+    }
+
+    // sort orders each trip's stop times by stop_sequence, which feeds needn't list them in.
+    fn sort(&mut self) {
+        self.stop_times.values_mut().for_each(|stop_times| stop_times.sort_by_key(|stop_time| stop_time.stop_sequence));
+    }
 }
 
-impl fmt::Display for StopTimesCsvLoadError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::NoHeader => write!(f, "No header found"),
-            Self::StopTimeLoadError(e) => write!(f, "Error loading stop time: {}", e),
-            Self::CSVReadError(e) => write!(f, "Error reading CSV: {}", e)
+// StopTimes implements FromIterator<StopTime> so that it can be assembled in memory, grouping records by trip_id.
+impl iter::FromIterator<StopTime> for StopTimes {
+    fn from_iter<I: IntoIterator<Item = StopTime>>(iter: I) -> Self {
+        let mut stop_times = collections::HashMap::new();
+        for stop_time in iter {
+            stop_times.entry(stop_time.trip_id.clone()).or_insert(Vec::new()).push(stop_time);
         }
+        let mut stop_times = StopTimes { stop_times };
+        stop_times.sort();
+        stop_times
     }
 }
 
-// Trips implements TryFrom<csv::Reader<R>> by attempting to consume and read from a csv::Reader<R>.
+// StopTimes implements TryFrom<csv::Reader<R>> by loading the reader's contents as a GtfsTable.
 impl<R: io::Read> TryFrom<csv::Reader<R>> for StopTimes {
-    // The error type for this function is StopTimesCsvLoadError.
-    type Error = StopTimesCsvLoadError;
-
-    // try_from consumes the csv::Reader<R> and returns a Result holding a Routes object, or a RoutesCsvLoadError.
-    fn try_from(mut r: csv::Reader<R>) -> Result<Self, Self::Error> {
-        // try to get the headers; if there are no headers, return a StopTimesCsvLoadError::NoHeader.
-        r.headers().cloned().map_err(|_| StopTimesCsvLoadError::NoHeader).and_then(
-            // if there are headers, try to create a StopTimes object from the remaining records.
-            |header|
-            Ok(StopTimes {
-                // to create the actual collection of stop times, we need to iterate over the records
-                stop_times: r.into_records()
-                    // and fold them into an overarching result containing the collection.
-                    .try_fold(
-                        collections::HashMap::new(),
-                        // at each stage of the fold,
-                        |mut stop_times, record_result|
-                            // if there was an error reading this record, return that error.
-                            record_result.map_err(StopTimesCsvLoadError::CSVReadError)
-                                .and_then(
-                                    // otherwise, try to create a StopTime object from the record.
-                                    |record|
-                                    StopTime::try_from(
-                                        // Zip the header and record together,
-                                        &(iter::zip(
-                                            header.iter().map(|s| s.to_string()),
-                                            record.iter().map(|s| s.to_string())
-                                        )
-                                        // and collect the results into a HashMap.
-                                        .collect::<collections::HashMap<String, String>>())
-                                    )
-                                    // if there is an error creating the StopTime object from the HashMap, return that error.
-                                    .map_err(|err| StopTimesCsvLoadError::StopTimeLoadError(err))
-                            )
-                            .map(
-                                |stop_time| {
-                                    // insert the StopTime object into the HashMap.
-                                    stop_times.get_mut(&stop_time.trip_id)
-                                        .map(|v: &mut Vec<StopTime>| v.push(stop_time.clone()))
-                                        .unwrap_or_else(|| {
-                                            stop_times.insert(stop_time.trip_id.clone(), vec![stop_time]);
-                                        });
-                                    // return the updated HashMap.
-                                    stop_times
-                                }
-                            )
-                    // extract the HashMap from the Result, or return the error.
-                    )?
-            })
-        )
+    type Error = CsvError;
+
+    fn try_from(r: csv::Reader<R>) -> Result<Self, Self::Error> {
+        table::load(r)
     }
 }
 
-#[derive(Debug, Clone)]
+// StopTimes holds the records of stop_times.txt, grouped by trip_id.
+impl table::GtfsTable for StopTimes {
+    type Record = StopTime;
+
+    const FILE_NAME: &'static str = "stop_times.txt";
+
+    fn key(stop_time: &StopTime) -> String {
+        stop_time.trip_id.clone()
+    }
+
+    fn insert(&mut self, trip_id: String, stop_time: StopTime) {
+        self.stop_times.entry(trip_id).or_default().push(stop_time);
+    }
+
+    fn finish(&mut self) {
+        self.sort();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct StopTime {
+    #[serde(deserialize_with = "de::required")]
     pub trip_id: String,
+    #[serde(default, deserialize_with = "de::optional")]
     pub stop_id: Option<String>,
-    pub arrival_time: Option<chrono::NaiveTime>,
-    pub departure_time: Option<chrono::NaiveTime>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub arrival_time: Option<GtfsTime>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub departure_time: Option<GtfsTime>,
+    #[serde(default, deserialize_with = "de::optional")]
     pub location_group_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
     pub location_id: Option<String>,
+    #[serde(deserialize_with = "de::required")]
     pub stop_sequence: usize,
+    #[serde(default, deserialize_with = "de::optional")]
     pub stop_headsign: Option<String>,
-    pub start_pickup_drop_off_window: Option<chrono::NaiveTime>,
-    pub end_pickup_drop_off_window: Option<chrono::NaiveTime>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub start_pickup_drop_off_window: Option<GtfsTime>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub end_pickup_drop_off_window: Option<GtfsTime>,
+    #[serde(default, deserialize_with = "de::optional")]
     pub pickup_type: Option<StopPolicy>,
+    #[serde(default, deserialize_with = "de::optional")]
     pub drop_off_type: Option<StopPolicy>,
+    #[serde(default, deserialize_with = "de::optional")]
     pub continuous_pickup: Option<routes::RouteContinuityPolicy>,
+    #[serde(default, deserialize_with = "de::optional")]
     pub continuous_drop_off: Option<routes::RouteContinuityPolicy>,
+    #[serde(default, deserialize_with = "de::optional")]
     pub shape_dist_traveled: Option<f64>,
+    #[serde(default, deserialize_with = "de::optional")]
     pub timepoint: Option<Timepoint>,
+    #[serde(default, deserialize_with = "de::optional")]
     pub pickup_booking_rule_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
     pub drop_off_booking_rule_id: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+impl StopTime {
+    // new creates a stop time visiting stop_id as the stop_sequence'th stop of trip_id,
+    // with the given arrival and departure times and every other optional field unset.
+    pub fn new(
+        trip_id: &str,
+        stop_id: &str,
+        stop_sequence: usize,
+        arrival_time: Option<GtfsTime>,
+        departure_time: Option<GtfsTime>,
+    ) -> Self {
+        StopTime {
+            trip_id: trip_id.to_string(),
+            stop_id: Some(stop_id.to_string()),
+            arrival_time,
+            departure_time,
+            location_group_id: None,
+            location_id: None,
+            stop_sequence,
+            stop_headsign: None,
+            start_pickup_drop_off_window: None,
+            end_pickup_drop_off_window: None,
+            pickup_type: None,
+            drop_off_type: None,
+            continuous_pickup: None,
+            continuous_drop_off: None,
+            shape_dist_traveled: None,
+            timepoint: None,
+            pickup_booking_rule_id: None,
+            drop_off_booking_rule_id: None,
+        }
+    }
+
+    // prefix_ids prepends prefix to every ID referenced by this stop time.
+    pub fn prefix_ids(&mut self, prefix: &str) {
+        self.trip_id.insert_str(0, prefix);
+        for id in [
+            &mut self.stop_id,
+            &mut self.location_group_id,
+            &mut self.location_id,
+            &mut self.pickup_booking_rule_id,
+            &mut self.drop_off_booking_rule_id,
+        ].into_iter().flatten() {
+            id.insert_str(0, prefix);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum StopPolicy {
     RegularlyScheduled,
     Unavailable,
@@ -136,6 +191,20 @@ impl fmt::Display for StopPolicyLoadError {
     }
 }
 
+impl std::error::Error for StopPolicyLoadError {}
+
+impl StopPolicy {
+    // code returns the value used to encode this policy in GTFS files.
+    pub fn code(&self) -> u8 {
+        match self {
+            StopPolicy::RegularlyScheduled => 0,
+            StopPolicy::Unavailable => 1,
+            StopPolicy::Prearrange => 2,
+            StopPolicy::CoordinateWithDriver => 3,
+        }
+    }
+}
+
 impl FromStr for StopPolicy {
     type Err = StopPolicyLoadError;
 
@@ -150,12 +219,13 @@ impl FromStr for StopPolicy {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Timepoint {
     Approximate,
     Exact,
 }
 
+#[derive(Debug)]
 pub enum TimepointLoadError {
     InvalidTimepoint(String),
 }
@@ -168,6 +238,18 @@ impl fmt::Display for TimepointLoadError {
     }
 }
 
+impl std::error::Error for TimepointLoadError {}
+
+impl Timepoint {
+    // code returns the value used to encode this timepoint in GTFS files.
+    pub fn code(&self) -> u8 {
+        match self {
+            Timepoint::Approximate => 0,
+            Timepoint::Exact => 1,
+        }
+    }
+}
+
 impl FromStr for Timepoint {
     type Err = TimepointLoadError;
 
@@ -180,128 +262,41 @@ impl FromStr for Timepoint {
     }
 }
 
+// the fields of stop_times.txt, in the order they are listed by the GTFS reference.
+pub const STOP_TIME_FIELDS: [&str; 18] = [
+    "trip_id", "arrival_time", "departure_time", "stop_id", "location_group_id", "location_id", "stop_sequence",
+    "stop_headsign", "start_pickup_drop_off_window", "end_pickup_drop_off_window", "pickup_type", "drop_off_type",
+    "continuous_pickup", "continuous_drop_off", "shape_dist_traveled", "timepoint", "pickup_booking_rule_id",
+    "drop_off_booking_rule_id",
+];
 
-pub enum StopTimeLoadError {
-    TripIdRequired,
-    ArrivalTimeError(ParseTimeError),
-    DepartureTimeError(ParseTimeError),
-    StopSequenceRequired,
-    StopSequenceError(num::ParseIntError),
-    StartPickupDropOffWindowError(ParseTimeError),
-    EndPickupDropOffWindowError(ParseTimeError) ,
-    PickupTypeError(StopPolicyLoadError),
-    DropOffTypeError(StopPolicyLoadError),
-    ContinuousPickupError(routes::RouteContinuityPolicyLoadError),
-    ContinuousDropOffError(routes::RouteContinuityPolicyLoadError),
-    ShapeDistTraveledError(num::ParseFloatError),
-    TimepointError(TimepointLoadError),
-}
-
-impl fmt::Display for StopTimeLoadError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::TripIdRequired => write!(f, "trip_id is required"),
-            Self::ArrivalTimeError(e) => write!(f, "Error parsing arrival time: {}", e),
-            Self::DepartureTimeError(e) => write!(f, "Error parsing departure time: {}", e),
-            Self::StopSequenceRequired => write!(f, "stop_sequence is required"),
-            Self::StopSequenceError(e) => write!(f, "Error parsing stop sequence: {}", e),
-            Self::StartPickupDropOffWindowError(e) => write!(f, "Error parsing start pickup drop off window: {}", e),
-            Self::EndPickupDropOffWindowError(e) => write!(f, "Error parsing end pickup drop off window: {}", e),
-            Self::PickupTypeError(e) => write!(f, "Error parsing pickup type: {}", e),
-            Self::DropOffTypeError(e) => write!(f, "Error parsing drop off type: {}", e),
-            Self::ContinuousPickupError(e) => write!(f, "Error parsing continuous pickup: {}", e),
-            Self::ContinuousDropOffError(e) => write!(f, "Error parsing continuous drop off: {}", e),
-            Self::ShapeDistTraveledError(e) => write!(f, "Error parsing shape dist traveled: {}", e),
-            Self::TimepointError(e) => write!(f, "Error parsing timepoint: {}", e),
-        }
-    }
-}
-
-// Route implements TryFrom<collections::HashMap<String, String>> by interpreting the keys as field names, and
-// the values as string-encoded values for those fields.
-impl TryFrom<&collections::HashMap<String, String>> for StopTime {
-    type Error = StopTimeLoadError;
-
-    fn try_from(fields: &collections::HashMap<String, String>) -> Result<Self, Self::Error> {
-        Ok(StopTime {
-            trip_id: fields.get("trip_id")
-                .filter(|s| !s.is_empty())
-                .ok_or(StopTimeLoadError::TripIdRequired)
-                .cloned()?,
-            stop_id: fields.get("stop_id")
-                .filter(|s| !s.is_empty())
-                .cloned(),
-            arrival_time: fields.get("arrival_time")
-                .filter(|s| !s.is_empty())
-                .map(|s| parse_time(&s))
-                .transpose()
-                .map_err(|e| StopTimeLoadError::ArrivalTimeError(e))?,
-            departure_time: fields.get("departure_time")
-                .filter(|s| !s.is_empty())
-                .map(|s| parse_time(&s))
-                .transpose()
-                .map_err(|e| StopTimeLoadError::DepartureTimeError(e))?,
-            location_group_id: fields.get("location_group_id")
-                .filter(|s| !s.is_empty())
-                .cloned(),
-            location_id: fields.get("location_id")
-                .filter(|s| !s.is_empty())
-                .cloned(),
-            stop_sequence: fields.get("stop_sequence")
-                .filter(|s| !s.is_empty())
-                .ok_or(StopTimeLoadError::StopSequenceRequired)?
-                .parse::<usize>()
-                .map_err(StopTimeLoadError::StopSequenceError)?,
-            stop_headsign: fields.get("stop_headsign")
-                .filter(|s| !s.is_empty())
-                .cloned(),
-            start_pickup_drop_off_window: fields.get("start_pickup_drop_off_window")
-                .filter(|s| !s.is_empty())
-                .map(|s| parse_time(&s))
-                .transpose()
-                .map_err(|e| StopTimeLoadError::StartPickupDropOffWindowError(e))?,
-            end_pickup_drop_off_window: fields.get("end_pickup_drop_off_window")
-                .filter(|s| !s.is_empty())
-                .map(|s| parse_time(&s))
-                .transpose()
-                .map_err(|e| StopTimeLoadError::EndPickupDropOffWindowError(e))?,
-            pickup_type: fields.get("pickup_type")
-                .filter(|s| !s.is_empty())
-                .map(|s| s.parse::<StopPolicy>())
-                .transpose()
-                .map_err(StopTimeLoadError::PickupTypeError)?,
-            drop_off_type: fields.get("drop_off_type")
-                .filter(|s| !s.is_empty())
-                .map(|s| s.parse::<StopPolicy>())
-                .transpose()
-                .map_err(StopTimeLoadError::DropOffTypeError)?,
-            continuous_pickup: fields.get("continuous_pickup")
-                .filter(|s| !s.is_empty())
-                .map(|s| s.parse::<routes::RouteContinuityPolicy>())
-                .transpose()
-                .map_err(StopTimeLoadError::ContinuousPickupError)?,
-            continuous_drop_off: fields.get("continuous_drop_off")
-                .filter(|s| !s.is_empty())
-                .map(|s| s.parse::<routes::RouteContinuityPolicy>())
-                .transpose()
-                .map_err(StopTimeLoadError::ContinuousDropOffError)?,
-            shape_dist_traveled: fields.get("shape_dist_traveled")
-                .filter(|s| !s.is_empty())
-                .map(|s| s.parse::<f64>())
-                .transpose()
-                .map_err(StopTimeLoadError::ShapeDistTraveledError)?,
-            pickup_booking_rule_id: fields.get("pickup_booking_rule_id")
-                .filter(|s| !s.is_empty())
-                .cloned(),
-            drop_off_booking_rule_id: fields.get("drop_off_booking_rule_id")
-                .filter(|s| !s.is_empty())
-                .cloned(),
-            timepoint: fields.get("timepoint")
-                .filter(|s| !s.is_empty())
-                .map(|s| s.parse::<Timepoint>())
-                .transpose()
-                .map_err(StopTimeLoadError::TimepointError)?,
-        })
+// collections::HashMap<String, String> implements From<&StopTime> by encoding each field as it would appear in stop_times.txt.
+// This is the inverse of StopTime's Deserialize implementation.
+impl From<&StopTime> for collections::HashMap<String, String> {
+    fn from(stop_time: &StopTime) -> Self {
+        [
+            ("trip_id", Some(stop_time.trip_id.clone())),
+            ("arrival_time", stop_time.arrival_time.map(|time| time.to_string())),
+            ("departure_time", stop_time.departure_time.map(|time| time.to_string())),
+            ("stop_id", stop_time.stop_id.clone()),
+            ("location_group_id", stop_time.location_group_id.clone()),
+            ("location_id", stop_time.location_id.clone()),
+            ("stop_sequence", Some(stop_time.stop_sequence.to_string())),
+            ("stop_headsign", stop_time.stop_headsign.clone()),
+            ("start_pickup_drop_off_window", stop_time.start_pickup_drop_off_window.map(|time| time.to_string())),
+            ("end_pickup_drop_off_window", stop_time.end_pickup_drop_off_window.map(|time| time.to_string())),
+            ("pickup_type", stop_time.pickup_type.as_ref().map(|policy| policy.code().to_string())),
+            ("drop_off_type", stop_time.drop_off_type.as_ref().map(|policy| policy.code().to_string())),
+            ("continuous_pickup", stop_time.continuous_pickup.as_ref().map(|policy| policy.code().to_string())),
+            ("continuous_drop_off", stop_time.continuous_drop_off.as_ref().map(|policy| policy.code().to_string())),
+            ("shape_dist_traveled", stop_time.shape_dist_traveled.map(|dist| dist.to_string())),
+            ("timepoint", stop_time.timepoint.as_ref().map(|timepoint| timepoint.code().to_string())),
+            ("pickup_booking_rule_id", stop_time.pickup_booking_rule_id.clone()),
+            ("drop_off_booking_rule_id", stop_time.drop_off_booking_rule_id.clone()),
+        ]
+            .into_iter()
+            .filter_map(|(field, value)| value.map(|value| (field.to_string(), value)))
+            .collect()
     }
 }
 
@@ -326,14 +321,100 @@ impl fmt::Display for ParseTimeError {
     }
 }
 
-fn parse_time(s: &str) -> Result<chrono::NaiveTime, ParseTimeError> {
-    let segments = s.split(':').collect::<Vec<&str>>();
-    if segments.len() != 3 {
-        return Err(ParseTimeError::ImproperNumberOfSegments);
+impl std::error::Error for ParseTimeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidHourSegment(e) => Some(e),
+            Self::InvalidMinuteSegment(e) => Some(e),
+            Self::InvalidSecondSegment(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// GtfsTime is a time of day as it appears in stop_times.txt, measured from noon minus 12 hours on the service day.
+// Trips which run past midnight have times of 24:00:00 or later, so unlike chrono::NaiveTime, hours aren't bounded by 24.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GtfsTime {
+    seconds: u32,
+}
+
+impl GtfsTime {
+    // from_hms returns the time the given number of hours, minutes and seconds after the start of the service day,
+    // or None if minutes or seconds isn't below 60, or if the time is too late to be held in seconds.
+    pub fn from_hms(hours: u32, minutes: u32, seconds: u32) -> Option<Self> {
+        if minutes >= 60 || seconds >= 60 {
+            return None;
+        }
+        hours.checked_mul(3600)?
+            .checked_add(minutes * 60 + seconds)
+            .map(GtfsTime::from_seconds)
+    }
+
+    pub fn from_seconds(seconds: u32) -> Self {
+        GtfsTime { seconds }
+    }
+
+    // seconds returns the number of seconds since the start of the service day.
+    pub fn seconds(&self) -> u32 {
+        self.seconds
+    }
+
+    // days returns the number of days after the service day on which this time falls.
+    pub fn days(&self) -> u32 {
+        self.seconds / 86400
+    }
+
+    // time_of_day returns the wall-clock time, discarding the number of days past the service day.
+    pub fn time_of_day(&self) -> chrono::NaiveTime {
+        chrono::NaiveTime::from_num_seconds_from_midnight_opt(self.seconds % 86400, 0).unwrap()
+    }
+}
+
+// GtfsTime implements From<chrono::NaiveTime> by treating the time as falling on the service day itself.
+impl From<chrono::NaiveTime> for GtfsTime {
+    fn from(time: chrono::NaiveTime) -> Self {
+        GtfsTime::from_seconds(chrono::Timelike::num_seconds_from_midnight(&time))
+    }
+}
+
+impl fmt::Display for GtfsTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.seconds / 3600, self.seconds / 60 % 60, self.seconds % 60)
+    }
+}
+
+impl FromStr for GtfsTime {
+    type Err = ParseTimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = s.split(':');
+        let (hours, minutes, seconds) = match (segments.next(), segments.next(), segments.next(), segments.next()) {
+            (Some(hours), Some(minutes), Some(seconds), None) => (hours, minutes, seconds),
+            _ => return Err(ParseTimeError::ImproperNumberOfSegments),
+        };
+        let hours = hours.parse::<u32>().map_err(|e| ParseTimeError::InvalidHourSegment(e))?;
+        let minutes = minutes.parse::<u32>().map_err(|e| ParseTimeError::InvalidMinuteSegment(e))?;
+        let seconds = seconds.parse::<u32>().map_err(|e| ParseTimeError::InvalidSecondSegment(e))?;
+        GtfsTime::from_hms(hours, minutes, seconds)
+            .ok_or(ParseTimeError::InvalidTime(hours, minutes, seconds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_past_midnight_are_parsed() {
+        assert_eq!("25:30:05".parse::<GtfsTime>().unwrap().seconds(), 25 * 3600 + 30 * 60 + 5);
     }
-    let hours = segments[0].parse::<u32>().map_err(|e| ParseTimeError::InvalidHourSegment(e))? % 24;
-    let minutes = segments[1].parse::<u32>().map_err(|e| ParseTimeError::InvalidMinuteSegment(e))?;
-    let seconds = segments[2].parse::<u32>().map_err(|e| ParseTimeError::InvalidSecondSegment(e))?;
-    chrono::NaiveTime::from_hms_opt(hours, minutes, seconds)
-        .ok_or(ParseTimeError::InvalidTime(hours, minutes, seconds))
-}
\ No newline at end of file
+
+    #[test]
+    fn out_of_range_times_are_rejected() {
+        assert!(matches!("08:60:00".parse::<GtfsTime>(), Err(ParseTimeError::InvalidTime(8, 60, 0))));
+        assert!(matches!("1193047:00:00".parse::<GtfsTime>(), Err(ParseTimeError::InvalidTime(1193047, 0, 0))));
+        assert!(matches!("1193046:28:16".parse::<GtfsTime>(), Err(ParseTimeError::InvalidTime(1193046, 28, 16))));
+        assert_eq!("1193046:28:15".parse::<GtfsTime>().unwrap().seconds(), u32::MAX);
+    }
+}
@@ -1,15 +1,28 @@
 use chrono_tz::Tz;
 use csv;
+use crate::gtfs::error::CsvError;
+use crate::gtfs::de;
+use crate::gtfs::spatial;
+use crate::gtfs::table;
+use serde::Deserialize;
 use std::io;
 use std::iter;
 use std::collections;
 use std::fmt;
-use std::str::FromStr;
 
 // Stops is a collection of stops, indexed by stop_id.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default)]
 pub struct Stops {
-    pub stops: std::collections::HashMap<String, Stop>
+    pub stops: std::collections::HashMap<String, Stop>,
+    // spatial_index_cache holds the stops' spatial index once it has been built; see spatial_index.
+    pub spatial_index_cache: std::sync::OnceLock<spatial::SpatialIndex>,
+}
+
+// Stops are compared by their records alone, whether or not their spatial indexes have been built.
+impl PartialEq for Stops {
+    fn eq(&self, other: &Self) -> bool {
+        self.stops == other.stops
+    }
 }
 
 impl<'a> iter::IntoIterator for &'a Stops {
@@ -30,79 +43,235 @@ impl iter::IntoIterator for Stops {
     }
 }
 
-// StopsCsvLoadError is an error that occurs when loading stops from a CSV file.
-pub enum StopsCsvLoadError {
-    NoHeader,
-    StopLoadError(String),
-    CSVReadError(csv::Error)
+impl Stops {
+    // with_id_prefix prepends prefix to every stop ID, and to every ID referenced by a stop.
+    pub fn with_id_prefix(self, prefix: &str) -> Self {
+        Stops {
+            stops: self.stops.into_values()
+                .map(|mut stop| {
+                    stop.prefix_ids(prefix);
+                    (stop.stop_id.clone(), stop)
+                })
+                .collect(),
+            spatial_index_cache: Default::default(),
+        }
+    }
+
+    // spatial_index returns the stops' spatial index, building it if it hasn't been built yet.
+    pub fn spatial_index(&self) -> &spatial::SpatialIndex {
+        self.spatial_index_cache.get_or_init(|| spatial::SpatialIndex::build(self))
+    }
+
+    // invalidate_spatial_index discards the spatial index so that it is rebuilt on next use.
+    // It only needs to be called after editing stops directly; GtfsSchedule's mutators take care of it.
+    pub fn invalidate_spatial_index(&mut self) {
+        self.spatial_index_cache.take();
+    }
+
+    // nearest returns the n stops with coordinates closest to the given point, with their distances in meters, closest first.
+    pub fn nearest(&self, lat: f64, lon: f64, n: usize) -> Vec<(&Stop, f64)> {
+        self.with_stops(self.spatial_index().nearest(lat, lon, n))
+    }
+
+    // within_radius returns the stops within meters of the given point, with their distances in meters, closest first.
+    pub fn within_radius(&self, lat: f64, lon: f64, meters: f64) -> Vec<(&Stop, f64)> {
+        self.with_stops(self.spatial_index().within_radius(lat, lon, meters))
+    }
+
+    // station_tree returns the stop with the given ID and, nested beneath it, every stop within it: a station's platforms,
+    // entrances, generic nodes and the boarding areas of its platforms. It is None if there is no such stop.
+    pub fn station_tree(&self, station_id: &str) -> Option<StationTree<'_>> {
+        let mut children_by_parent_station = collections::HashMap::<&str, Vec<&Stop>>::new();
+        for stop in self.stops.values() {
+            if let Some(parent_station) = stop.parent_station() {
+                children_by_parent_station.entry(parent_station).or_default().push(stop);
+            }
+        }
+        Some(StationTree::build(self.stops.get(station_id)?, |stop_id| {
+            children_by_parent_station.get(stop_id).cloned().unwrap_or_default()
+        }))
+    }
+
+    // cluster groups stops which are within radius_meters of each other and share a name, once differences of case,
+    // punctuation and direction words such as "northbound" are ignored, e.g. the stops on either side of a street.
+    // Only stops and stations outside any station are clustered, since a station already groups the stops within it;
+    // stops without a name or coordinates are left on their own. Every such stop belongs to exactly one cluster.
+    // Clusters are sorted by the ID of their representative.
+    pub fn cluster(&self, radius_meters: f64) -> Vec<StopCluster<'_>> {
+        let candidates = self.stops.values()
+            .filter(|stop| matches!(stop.location_type_details, LocationTypeDetails::Stop(_) | LocationTypeDetails::Station(_)))
+            .filter(|stop| stop.parent_station().is_none())
+            .collect::<Vec<_>>();
+        let position_of = candidates.iter()
+            .enumerate()
+            .map(|(position, stop)| (stop.stop_id.as_str(), position))
+            .collect::<collections::HashMap<_, _>>();
+
+        // parents is a union-find forest over candidates, joining each stop to the matching stops near it.
+        let mut parents = (0..candidates.len()).collect::<Vec<_>>();
+        fn root(parents: &mut [usize], mut position: usize) -> usize {
+            while parents[position] != position {
+                parents[position] = parents[parents[position]];
+                position = parents[position];
+            }
+            position
+        }
+        for (position, stop) in candidates.iter().enumerate() {
+            let (Some(name), Some(lat), Some(lon)) = (stop.get_stop_name().map(normalize_stop_name), stop.stop_lat(), stop.stop_lon()) else {
+                continue;
+            };
+            for (neighbour, _) in self.within_radius(lat, lon, radius_meters) {
+                let Some(&neighbour_position) = position_of.get(neighbour.stop_id.as_str()) else {
+                    continue;
+                };
+                if neighbour.get_stop_name().map(normalize_stop_name).as_ref() == Some(&name) {
+                    let (a, b) = (root(&mut parents, position), root(&mut parents, neighbour_position));
+                    parents[a.max(b)] = a.min(b);
+                }
+            }
+        }
+
+        let mut members_by_root = collections::HashMap::<usize, Vec<&Stop>>::new();
+        for (position, stop) in candidates.iter().enumerate() {
+            members_by_root.entry(root(&mut parents, position)).or_default().push(stop);
+        }
+        let mut clusters = members_by_root.into_values()
+            .map(|mut members| {
+                members.sort_by(|a, b| a.stop_id.cmp(&b.stop_id));
+                StopCluster { representative: representative(&members), members }
+            })
+            .collect::<Vec<_>>();
+        clusters.sort_by(|a, b| a.representative.stop_id.cmp(&b.representative.stop_id));
+        clusters
+    }
+
+    fn with_stops(&self, stop_ids: Vec<(&str, f64)>) -> Vec<(&Stop, f64)> {
+        stop_ids.into_iter()
+            .filter_map(|(stop_id, meters)| Some((self.stops.get(stop_id)?, meters)))
+            .collect()
+    }
 }
 
-impl fmt::Display for StopsCsvLoadError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::NoHeader => write!(f, "No header found"),
-            Self::StopLoadError(e) => write!(f, "Error loading stop: {}", e),
-            Self::CSVReadError(e) => write!(f, "Error reading CSV: {}", e)
+// StopCluster is a group of stops which are close together and share a name, as found by Stops::cluster.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StopCluster<'a> {
+    // representative is the member to show in place of the whole cluster.
+    pub representative: &'a Stop,
+    // members lists every stop in the cluster, including the representative, sorted by ID.
+    pub members: Vec<&'a Stop>,
+}
+
+// normalize_stop_name reduces a stop name to the words which identify its location, in lowercase,
+// dropping punctuation and words which only say which way vehicles are travelling.
+fn normalize_stop_name(name: &str) -> String {
+    const DIRECTION_WORDS: [&str; 14] = [
+        "northbound", "southbound", "eastbound", "westbound", "inbound", "outbound",
+        "nb", "sb", "eb", "wb", "ib", "ob", "opposite", "opp",
+    ];
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !DIRECTION_WORDS.contains(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// representative picks the member of a cluster to stand for it: a station if there is one, or else the stop
+// closest to the middle of the cluster. members must not be empty, and ties go to the earliest member.
+fn representative<'a>(members: &[&'a Stop]) -> &'a Stop {
+    if let Some(station) = members.iter().find(|stop| matches!(stop.location_type_details, LocationTypeDetails::Station(_))) {
+        return station;
+    }
+    let located = members.iter()
+        .filter_map(|stop| Some((stop.stop_lat()?, stop.stop_lon()?)))
+        .collect::<Vec<_>>();
+    if located.is_empty() {
+        return members[0];
+    }
+    let (mid_lat, mid_lon) = (
+        located.iter().map(|(lat, _)| lat).sum::<f64>() / located.len() as f64,
+        located.iter().map(|(_, lon)| lon).sum::<f64>() / located.len() as f64,
+    );
+    let distance = |stop: &Stop| match (stop.stop_lat(), stop.stop_lon()) {
+        (Some(lat), Some(lon)) => spatial::haversine(lat, lon, mid_lat, mid_lon),
+        _ => f64::INFINITY,
+    };
+    members.iter()
+        .copied()
+        .reduce(|closest, stop| if distance(stop) < distance(closest) { stop } else { closest })
+        .unwrap()
+}
+
+// StationTree is a stop with the stops within it nested beneath it, as linked by their parent_station.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StationTree<'a> {
+    pub stop: &'a Stop,
+    // children lists the stops naming this one as their parent station, sorted by ID.
+    pub children: Vec<StationTree<'a>>,
+}
+
+impl<'a> StationTree<'a> {
+    // build assembles the tree beneath stop, where children_of gives the stops naming a stop as their parent station.
+    // A stop is only placed in the tree once, so that a cycle of parent stations can't make it infinite.
+    pub fn build(stop: &'a Stop, children_of: impl Fn(&str) -> Vec<&'a Stop>) -> Self {
+        let mut visited = collections::HashSet::from([stop.stop_id.as_str()]);
+        Self::build_visiting(stop, &children_of, &mut visited)
+    }
+
+    fn build_visiting(stop: &'a Stop, children_of: &impl Fn(&str) -> Vec<&'a Stop>, visited: &mut collections::HashSet<&'a str>) -> Self {
+        let mut children = children_of(&stop.stop_id);
+        children.retain(|child| visited.insert(child.stop_id.as_str()));
+        children.sort_by(|a, b| a.stop_id.cmp(&b.stop_id));
+        StationTree {
+            stop,
+            children: children.into_iter().map(|child| Self::build_visiting(child, children_of, visited)).collect(),
         }
     }
+
+    // stops lists every stop in the tree, each before the stops within it.
+    pub fn stops(&self) -> Vec<&'a Stop> {
+        iter::once(self.stop)
+            .chain(self.children.iter().flat_map(|child| child.stops()))
+            .collect()
+    }
 }
 
-// Stops implements TryFrom<csv::Reader<R>> by attempting to consume and read from a csv::Reader<R>.
+// Stops implements FromIterator<Stop> so that it can be assembled in memory, indexing each stop by its ID.
+impl iter::FromIterator<Stop> for Stops {
+    fn from_iter<I: IntoIterator<Item = Stop>>(iter: I) -> Self {
+        Stops {
+            stops: iter.into_iter().map(|stop| (stop.stop_id.clone(), stop)).collect(),
+            spatial_index_cache: Default::default(),
+        }
+    }
+}
+
+// Stops implements TryFrom<csv::Reader<R>> by loading the reader's contents as a GtfsTable.
 impl<R: io::Read> TryFrom<csv::Reader<R>> for Stops {
-    // The error type for this function is StopsCsvLoadError.
-    type Error = StopsCsvLoadError;
-
-    // try_from consumes the csv::Reader<R> and returns a Result holding a Stops object, or a StopsCsvLoadError.
-    fn try_from(mut r: csv::Reader<R>) -> Result<Self, Self::Error> {
-        // try to get the headers; if there are no headers, return a StopsCsvLoadError::NoHeader.
-        r.headers().cloned().map_err(|_| StopsCsvLoadError::NoHeader).and_then(
-            // if there are headers, try to create a Stops object from the remaining records.
-            |header|
-            Ok(Stops {
-                // to create the actual collection of stops, we need to iterate over the records
-                stops: r.into_records()
-                    // and fold them into an overarching result containing the collection.
-                    .fold(
-                        Ok(collections::HashMap::new()),
-                        // at each stage of the fold,
-                        |stops_result, record_result|
-                        // proceed only if the running result is Ok.
-                        stops_result
-                        .and_then(|mut stops|
-                            // if there was an error reading this record, return that error.
-                            record_result.map_err(StopsCsvLoadError::CSVReadError)
-                                .and_then(
-                                    // otherwise, try to create a Stop object from the record.
-                                    |record|
-                                    Stop::try_from(
-                                        // Zip the header and record together,
-                                        iter::zip(
-                                            header.iter().map(|s| s.to_string()),
-                                            record.iter().map(|s| s.to_string())
-                                        )
-                                        // and collect the results into a HashMap.
-                                        .collect::<collections::HashMap<String, String>>()
-                                    )
-                                    // if there is an error creating the Stop object from the HashMap, return that error.
-                                    .map_err(|err| StopsCsvLoadError::StopLoadError(err))
-                            )
-                            .map(
-                                |stop| {
-                                    // insert the Stop object into the HashMap.
-                                    stops.insert(stop.stop_id.clone(), stop);
-                                    // return the updated HashMap.
-                                    stops
-                                }
-                            )
-                        )
-                    // extract the HashMap from the Result, or return the error.
-                    )?
-            })
-        )
+    type Error = CsvError;
+
+    fn try_from(r: csv::Reader<R>) -> Result<Self, Self::Error> {
+        table::load(r)
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+// Stops holds the records of stops.txt, indexed by stop_id.
+impl table::GtfsTable for Stops {
+    type Record = Stop;
+
+    const FILE_NAME: &'static str = "stops.txt";
+
+    fn key(stop: &Stop) -> String {
+        stop.stop_id.clone()
+    }
+
+    fn insert(&mut self, stop_id: String, stop: Stop) {
+        self.stops.insert(stop_id, stop);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+// Rows are deserialized as StopRecords, since which of a stop's fields are required depends on its location_type.
+#[serde(try_from = "StopRecord")]
 pub struct Stop {
     pub stop_id: String,
     pub stop_code: Option<String>,
@@ -119,6 +288,23 @@ pub struct Stop {
 }
 
 impl Stop {
+    // new creates a stop with the given ID and location type details, leaving every optional field unset.
+    pub fn new(stop_id: &str, location_type_details: LocationTypeDetails) -> Self {
+        Stop {
+            stop_id: stop_id.to_string(),
+            stop_code: None,
+            tts_stop_name: None,
+            stop_desc: None,
+            zone_id: None,
+            stop_url: None,
+            stop_timezone: None,
+            wheelchair_boarding: None,
+            level_id: None,
+            platform_code: None,
+            location_type_details,
+        }
+    }
+
     // convenience functions to access type-specific fields in a unified way
     pub fn get_stop_name(&self) -> Option<&str> {
         match &self.location_type_details {
@@ -173,6 +359,30 @@ impl Stop {
         }
     }
 
+    pub fn mut_parent_station(&mut self) -> Option<&mut String> {
+        match &mut self.location_type_details {
+            LocationTypeDetails::Stop(stop_details) => stop_details.parent_station.as_mut(),
+            LocationTypeDetails::Station(_) => None,
+            LocationTypeDetails::EntranceExit(entrance_exit_details) => Some(&mut entrance_exit_details.parent_station),
+            LocationTypeDetails::GenericNode(generic_node_details) => Some(&mut generic_node_details.parent_station),
+            LocationTypeDetails::BoardingArea(boarding_area_details) => Some(&mut boarding_area_details.parent_station)
+        }
+    }
+
+    // prefix_ids prepends prefix to this stop's ID and to every ID it references.
+    pub fn prefix_ids(&mut self, prefix: &str) {
+        self.stop_id.insert_str(0, prefix);
+        if let Some(parent_station) = self.mut_parent_station() {
+            parent_station.insert_str(0, prefix);
+        }
+        if let Some(zone_id) = self.zone_id.as_mut() {
+            zone_id.insert_str(0, prefix);
+        }
+        if let Some(level_id) = self.level_id.as_mut() {
+            level_id.insert_str(0, prefix);
+        }
+    }
+
     pub fn parent_station(&self) -> Option<&str> {
         match &self.location_type_details {
             LocationTypeDetails::Stop(stop_details) => stop_details.parent_station.as_deref(),
@@ -184,45 +394,98 @@ impl Stop {
     }
 }
 
-// Stop implements TryFrom<collections::HashMap<String, String>> by interpreting the keys as field names, and
-// the values as string-encoded values for those fields.
-impl TryFrom<collections::HashMap<String, String>> for Stop {
-    type Error = String;
+// the fields of stops.txt, in the order they are listed by the GTFS reference.
+pub const STOP_FIELDS: [&str; 15] = [
+    "stop_id", "stop_code", "stop_name", "tts_stop_name", "stop_desc", "stop_lat", "stop_lon", "zone_id",
+    "stop_url", "location_type", "parent_station", "stop_timezone", "wheelchair_boarding", "level_id", "platform_code",
+];
+
+// collections::HashMap<String, String> implements From<&Stop> by encoding each field as it would appear in stops.txt.
+// This is the inverse of Stop's Deserialize implementation.
+impl From<&Stop> for collections::HashMap<String, String> {
+    fn from(stop: &Stop) -> Self {
+        [
+            ("stop_id", Some(stop.stop_id.clone())),
+            ("stop_code", stop.stop_code.clone()),
+            ("stop_name", stop.get_stop_name().map(|s| s.to_string())),
+            ("tts_stop_name", stop.tts_stop_name.clone()),
+            ("stop_desc", stop.stop_desc.clone()),
+            ("stop_lat", stop.stop_lat().map(|lat| lat.to_string())),
+            ("stop_lon", stop.stop_lon().map(|lon| lon.to_string())),
+            ("zone_id", stop.zone_id.clone()),
+            ("stop_url", stop.stop_url.clone()),
+            ("location_type", Some(stop.location_type_details.location_type().to_string())),
+            ("parent_station", stop.parent_station().map(|s| s.to_string())),
+            ("stop_timezone", stop.stop_timezone.map(|tz| tz.name().to_string())),
+            ("wheelchair_boarding", stop.wheelchair_boarding.map(|b| if b { "1" } else { "2" }.to_string())),
+            ("level_id", stop.level_id.clone()),
+            ("platform_code", stop.platform_code.clone()),
+        ]
+            .into_iter()
+            .filter_map(|(field, value)| value.map(|value| (field.to_string(), value)))
+            .collect()
+    }
+}
 
-    fn try_from(fields: collections::HashMap<String, String>) -> Result<Self, Self::Error> {
+// StopRecord is a row of stops.txt, read field by field before the fields which depend on location_type are checked.
+#[derive(Deserialize)]
+struct StopRecord {
+    #[serde(deserialize_with = "de::required")]
+    stop_id: String,
+    #[serde(default, deserialize_with = "de::optional")]
+    stop_code: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    stop_name: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    tts_stop_name: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    stop_desc: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    stop_lat: Option<f64>,
+    #[serde(default, deserialize_with = "de::optional")]
+    stop_lon: Option<f64>,
+    #[serde(default, deserialize_with = "de::optional")]
+    zone_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    stop_url: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    location_type: Option<u8>,
+    #[serde(default, deserialize_with = "de::optional")]
+    parent_station: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    stop_timezone: Option<Tz>,
+    #[serde(default, deserialize_with = "de::optional_accessibility")]
+    wheelchair_boarding: Option<bool>,
+    #[serde(default, deserialize_with = "de::optional")]
+    level_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    platform_code: Option<String>,
+}
+
+// Stop implements TryFrom<StopRecord> by checking that the record has the fields its location_type requires.
+// A record without a location_type is a stop or platform.
+impl TryFrom<StopRecord> for Stop {
+    type Error = LocationTypeDetailsLoadError;
+
+    fn try_from(record: StopRecord) -> Result<Self, Self::Error> {
         Ok(Stop {
-            stop_id: fields.get("stop_id")
-                .filter(|s| !s.is_empty())
-                .ok_or(String::from("stop_id is required"))?
-                .clone(),
-            location_type_details: LocationTypeDetails::try_from(&fields)?,
-            stop_code: fields.get("stop_code").filter(|s| !s.is_empty()).cloned(),
-            tts_stop_name: fields.get("tts_stop_name").filter(|s| !s.is_empty()).cloned(),
-            stop_desc: fields.get("stop_desc").filter(|s| !s.is_empty()).cloned(),
-            zone_id: fields.get("zone_id").filter(|s| !s.is_empty()).cloned(),
-            stop_url: fields.get("stop_url").filter(|s| !s.is_empty()).cloned(),
-            stop_timezone: match fields.get("stop_timezone")
-                    .filter(|s| !s.is_empty())
-                    .map(
-                        |stop_timezone_string|
-                        Tz::from_str(stop_timezone_string)
-                    )
-                {
-                    Some(res) => res
-                        .map(|tz| Some(tz))
-                        .map_err(|e| format!("Invalid timezone: {}", e)),
-                    None => Ok(None)
-                }?,
-            wheelchair_boarding: Ok(fields.get("wheelchair_boarding").filter(|s| !s.is_empty()))
-                    .and_then(|wheelchair_boarding| match wheelchair_boarding.map(|s| s.as_str()) {
-                        None => Ok(None),
-                        Some("0") => Ok(None),
-                        Some("1") => Ok(Some(true)),
-                        Some("2") => Ok(Some(false)),
-                        Some(s) => Err(format!("Invalid wheelchair_boarding: {}", s))
-                    })?,
-            level_id: fields.get("level_id").filter(|s| !s.is_empty()).cloned(),
-            platform_code: fields.get("platform_code").filter(|s| !s.is_empty()).cloned()
+            stop_id: record.stop_id,
+            location_type_details: LocationTypeDetails::new(
+                record.location_type.unwrap_or(0),
+                record.stop_name,
+                record.stop_lat,
+                record.stop_lon,
+                record.parent_station,
+            )?,
+            stop_code: record.stop_code,
+            tts_stop_name: record.tts_stop_name,
+            stop_desc: record.stop_desc,
+            zone_id: record.zone_id,
+            stop_url: record.stop_url,
+            stop_timezone: record.stop_timezone,
+            wheelchair_boarding: record.wheelchair_boarding,
+            level_id: record.level_id,
+            platform_code: record.platform_code,
         })
     }
 }
@@ -236,30 +499,80 @@ pub enum LocationTypeDetails {
     BoardingArea(BoardingAreaDetails),
 }
 
-impl TryFrom<&collections::HashMap<String, String>> for LocationTypeDetails {
-    type Error = String;
+impl LocationTypeDetails {
+    // new builds the details of a location of the given location_type from the fields of stops.txt which depend on it,
+    // requiring those which that type of location must have. Fields which don't apply to the type are ignored.
+    pub fn new(
+        location_type: u8,
+        stop_name: Option<String>,
+        stop_lat: Option<f64>,
+        stop_lon: Option<f64>,
+        parent_station: Option<String>,
+    ) -> Result<Self, LocationTypeDetailsLoadError> {
+        let required = LocationTypeDetailsLoadError::FieldRequired;
+        match location_type {
+            0 => Ok(LocationTypeDetails::Stop(StopDetails {
+                stop_name: stop_name.ok_or(required("stop_name"))?,
+                stop_lat: stop_lat.ok_or(required("stop_lat"))?,
+                stop_lon: stop_lon.ok_or(required("stop_lon"))?,
+                parent_station,
+            })),
+            1 => Ok(LocationTypeDetails::Station(StationDetails {
+                stop_name: stop_name.ok_or(required("stop_name"))?,
+                stop_lat: stop_lat.ok_or(required("stop_lat"))?,
+                stop_lon: stop_lon.ok_or(required("stop_lon"))?,
+            })),
+            2 => Ok(LocationTypeDetails::EntranceExit(EntranceExitDetails {
+                stop_name: stop_name.ok_or(required("stop_name"))?,
+                stop_lat: stop_lat.ok_or(required("stop_lat"))?,
+                stop_lon: stop_lon.ok_or(required("stop_lon"))?,
+                parent_station: parent_station.ok_or(required("parent_station"))?,
+            })),
+            3 => Ok(LocationTypeDetails::GenericNode(GenericNodeDetails {
+                stop_name,
+                stop_lat,
+                stop_lon,
+                parent_station: parent_station.ok_or(required("parent_station"))?,
+            })),
+            4 => Ok(LocationTypeDetails::BoardingArea(BoardingAreaDetails {
+                stop_name,
+                stop_lat,
+                stop_lon,
+                parent_station: parent_station.ok_or(required("parent_station"))?,
+            })),
+            _ => Err(LocationTypeDetailsLoadError::InvalidLocationType(location_type.to_string())),
+        }
+    }
 
-    fn try_from(fields: &collections::HashMap<String, String>) -> Result<Self, Self::Error> {
-        let zero_str = String::from("0");
+    // location_type returns the code stops.txt gives this type of location.
+    pub fn location_type(&self) -> u8 {
+        match self {
+            LocationTypeDetails::Stop(_) => 0,
+            LocationTypeDetails::Station(_) => 1,
+            LocationTypeDetails::EntranceExit(_) => 2,
+            LocationTypeDetails::GenericNode(_) => 3,
+            LocationTypeDetails::BoardingArea(_) => 4,
+        }
+    }
+}
 
-        let location_type = fields.get("location_type").filter(|s| !s.is_empty()).unwrap_or(&zero_str);
+#[derive(Debug)]
+pub enum LocationTypeDetailsLoadError {
+    InvalidLocationType(String),
+    FieldRequired(&'static str),
+}
 
-        match location_type.parse::<u8>()
-            .map_err(
-                |err|
-                format!("invalid location_type '{}': {}", location_type, err)
-            )?
-        {
-            0 => StopDetails::try_from(fields).map(LocationTypeDetails::Stop),
-            1 => StationDetails::try_from(fields).map(LocationTypeDetails::Station),
-            2 => EntranceExitDetails::try_from(fields).map(LocationTypeDetails::EntranceExit),
-            3 => GenericNodeDetails::try_from(fields).map(LocationTypeDetails::GenericNode),
-            4 => BoardingAreaDetails::try_from(fields).map(LocationTypeDetails::BoardingArea),
-            _ => Err(format!("invalid location_type '{}'", location_type))
-        }.map_err(|err| format!("failed to load location as type '{}': {}", location_type, err))
+impl fmt::Display for LocationTypeDetailsLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLocationType(location_type) => write!(f, "invalid location_type '{}'", location_type),
+            Self::FieldRequired(field) => write!(f, "{} is required", field),
+        }
     }
 }
 
+impl std::error::Error for LocationTypeDetailsLoadError {}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct StopDetails {
     pub stop_name: String,
@@ -268,30 +581,6 @@ pub struct StopDetails {
     pub parent_station: Option<String>,
 }
 
-impl TryFrom<&collections::HashMap<String, String>> for StopDetails {
-    type Error = String;
-
-    fn try_from(fields: &collections::HashMap<String, String>) -> Result<Self, Self::Error> {
-        Ok(StopDetails {
-            stop_name: fields.get("stop_name")
-                .filter(|s| !s.is_empty())
-                .ok_or(String::from("stop_name is required"))?
-                .clone(),
-            stop_lat: fields.get("stop_lat")
-                .filter(|s| !s.is_empty())
-                .ok_or(String::from("stop_lat is required"))?
-                .parse::<f64>()
-                .map_err(|err| format!("invalid stop_lat '{}': {}", fields.get("stop_lat").unwrap(), err))?,
-            stop_lon: fields.get("stop_lon")
-                .filter(|s| !s.is_empty())
-                .ok_or(String::from("stop_lon is required"))?
-                .parse::<f64>()
-                .map_err(|err| format!("invalid stop_lon '{}': {}", fields.get("stop_lon").unwrap(), err))?,
-            parent_station: fields.get("parent_station").filter(|s| !s.is_empty()).cloned(),
-        })
-    }
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub struct StationDetails {
     pub stop_name: String,
@@ -299,29 +588,6 @@ pub struct StationDetails {
     pub stop_lon: f64,
 }
 
-impl TryFrom<&collections::HashMap<String, String>> for StationDetails {
-    type Error = String;
-
-    fn try_from(fields: &collections::HashMap<String, String>) -> Result<Self, Self::Error> {
-        Ok(StationDetails {
-            stop_name: fields.get("stop_name")
-                .filter(|s| !s.is_empty())
-                .ok_or(String::from("stop_name is required"))?
-                .clone(),
-            stop_lat: fields.get("stop_lat")
-                .filter(|s| !s.is_empty())
-                .ok_or(String::from("stop_lat is required"))?
-                .parse::<f64>()
-                .map_err(|err| format!("invalid stop_lat '{}': {}", fields.get("stop_lat").unwrap(), err))?,
-            stop_lon: fields.get("stop_lon")
-                .filter(|s| !s.is_empty())
-                .ok_or(String::from("stop_lon is required"))?
-                .parse::<f64>()
-                .map_err(|err| format!("invalid stop_lon '{}': {}", fields.get("stop_lon").unwrap(), err))?,
-        })
-    }
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub struct EntranceExitDetails {
     pub stop_name: String,
@@ -330,77 +596,14 @@ pub struct EntranceExitDetails {
     pub parent_station: String,
 }
 
-impl TryFrom<&collections::HashMap<String, String>> for EntranceExitDetails {
-    type Error = String;
-
-    fn try_from(fields: &collections::HashMap<String, String>) -> Result<Self, Self::Error> {
-        Ok(EntranceExitDetails {
-            stop_name: fields.get("stop_name")
-                .filter(|s| !s.is_empty())
-                .ok_or(String::from("stop_name is required"))?
-                .clone(),
-            stop_lat: fields.get("stop_lat")
-                .filter(|s| !s.is_empty())
-                .ok_or(String::from("stop_lat is required"))?
-                .parse::<f64>()
-                .map_err(|err| format!("invalid stop_lat '{}': {}", fields.get("stop_lat").unwrap(), err))?,
-            stop_lon: fields.get("stop_lon")
-                .filter(|s| !s.is_empty())
-                .ok_or(String::from("stop_lon is required"))?
-                .parse::<f64>()
-                .map_err(|err| format!("invalid stop_lon '{}': {}", fields.get("stop_lon").unwrap(), err))?,
-            parent_station: fields.get("parent_station")
-                .filter(|s| !s.is_empty())
-                .ok_or(String::from("parent_station is required"))?
-                .clone(),
-        })
-    }
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub struct GenericNodeDetails {
-    pub     stop_name: Option<String>,
+    pub stop_name: Option<String>,
     pub stop_lat: Option<f64>,
     pub stop_lon: Option<f64>,
     pub parent_station: String,
 }
 
-impl TryFrom<&collections::HashMap<String, String>> for GenericNodeDetails {
-    type Error = String;
-
-    fn try_from(fields: &collections::HashMap<String, String>) -> Result<Self, Self::Error> {
-        Ok(GenericNodeDetails {
-            stop_name: fields.get("stop_name").filter(|s| !s.is_empty()).cloned(),
-            stop_lat: match fields.get("stop_lat")
-                    .filter(|s| !s.is_empty())
-                    .map(|s| s.parse::<f64>()
-                    .map_err(
-                        |err|
-                        format!("invalid stop_lat '{}': {}", s, err)
-                    ))
-                {
-                    Some(res) => res.map(|lat| Some(lat)),
-                    None => Ok(None)
-                }?,
-            stop_lon: match fields.get("stop_lon")
-                    .filter(|s| !s.is_empty())
-                    .map(|s| s.parse::<f64>()
-                    .map_err(
-                        |err|
-                        format!("invalid stop_lon '{}': {}", s, err)
-                    ))
-                {
-                    Some(res) => res.map(|lon| Some(lon)),
-                    None => Ok(None)
-                }?,
-            parent_station: fields.get("parent_station")
-                .filter(|s| !s.is_empty())
-                .ok_or(String::from("parent_station is required"))?
-                .clone(),
-        })
-    }
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub struct BoardingAreaDetails {
     pub stop_name: Option<String>,
@@ -409,38 +612,49 @@ pub struct BoardingAreaDetails {
     pub parent_station: String,
 }
 
-impl TryFrom<&collections::HashMap<String, String>> for BoardingAreaDetails {
-    type Error = String;
-
-    fn try_from(fields: &collections::HashMap<String, String>) -> Result<Self, Self::Error> {
-        Ok(BoardingAreaDetails {
-            stop_name: fields.get("stop_name").filter(|s| !s.is_empty()).cloned(),
-            stop_lat: match fields.get("stop_lat")
-                    .filter(|s| !s.is_empty())
-                    .map(|s| s.parse::<f64>()
-                    .map_err(
-                        |err|
-                        format!("invalid stop_lat '{}': {}", s, err)
-                    ))
-                {
-                    Some(res) => res.map(|lat| Some(lat)),
-                    None => Ok(None)
-                }?,
-            stop_lon: match fields.get("stop_lon")
-                    .filter(|s| !s.is_empty())
-                    .map(|s| s.parse::<f64>()
-                    .map_err(
-                        |err|
-                        format!("invalid stop_lon '{}': {}", s, err)
-                    ))
-                {
-                    Some(res) => res.map(|lon| Some(lon)),
-                    None => Ok(None)
-                }?,
-            parent_station: fields.get("parent_station")
-                .filter(|s| !s.is_empty())
-                .ok_or(String::from("parent_station is required"))?
-                .clone(),
-        })
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::testing;
+
+    // load_error returns the message of the error loading the given stops.txt.
+    fn load_error(text: &str) -> String {
+        table::load::<Stops, _>(csv::Reader::from_reader(text.as_bytes())).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn stops_are_read_by_their_location_type() {
+        let stops: Stops = testing::load(
+            "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station,stop_timezone,wheelchair_boarding\n\
+             S,Station,40.75,-73.99,1,,America/New_York,1\n\
+             S1,Platform,40.7501,-73.9901,,S,,0\n\
+             N,,,,3,S,,\n",
+        );
+        let station = &stops.stops["S"];
+        assert!(matches!(station.location_type_details, LocationTypeDetails::Station(_)));
+        assert_eq!(station.stop_timezone, Some(chrono_tz::America::New_York));
+        assert_eq!(station.wheelchair_boarding, Some(true));
+        assert_eq!(stops.stops["S1"].location_type_details, LocationTypeDetails::Stop(StopDetails {
+            stop_name: "Platform".to_string(),
+            stop_lat: 40.7501,
+            stop_lon: -73.9901,
+            parent_station: Some("S".to_string()),
+        }));
+        assert_eq!(stops.stops["S1"].wheelchair_boarding, None);
+        assert_eq!(stops.stops["N"].location_type_details, LocationTypeDetails::GenericNode(GenericNodeDetails {
+            stop_name: None,
+            stop_lat: None,
+            stop_lon: None,
+            parent_station: "S".to_string(),
+        }));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn fields_required_by_the_location_type_must_be_given() {
+        assert_eq!(load_error("stop_id,stop_name,stop_lat,stop_lon\nX,,40.7,-74.0\n"), "stops.txt:2: stop_name is required");
+        assert_eq!(load_error("stop_id,stop_name,location_type\nE,Entrance,2\n"), "stops.txt:2: stop_lat is required");
+        assert_eq!(load_error("stop_id,location_type\nB,4\n"), "stops.txt:2: parent_station is required");
+        assert_eq!(load_error("stop_id,location_type\nB,7\n"), "stops.txt:2: invalid location_type '7'");
+        assert!(load_error("stop_id,stop_name,stop_lat,stop_lon\nX,X,north,-74.0\n").starts_with("stops.txt:2: "));
+    }
+}
@@ -0,0 +1 @@
+pub mod sqlite;
\ No newline at end of file
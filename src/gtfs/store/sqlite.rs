@@ -0,0 +1,1032 @@
+use crate::gtfs;
+use crate::gtfs::agency;
+use crate::gtfs::calendar;
+use crate::gtfs::shapes;
+use crate::gtfs::frequencies;
+use crate::gtfs::transfers;
+use crate::gtfs::pathways;
+use crate::gtfs::fares;
+use crate::gtfs::fare_products;
+use crate::gtfs::stops;
+use crate::gtfs::routes;
+use crate::gtfs::table;
+use crate::gtfs::trips;
+use crate::gtfs::stop_times;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path;
+use std::str::FromStr;
+
+// SqliteStore persists a GtfsSchedule into a SQLite database so that it can be reopened later
+// without reparsing the feed's CSV files.
+//
+// Each table has a column per GTFS field, typed by what it holds: counts, flags, enumerations (by their codes), times
+// (in seconds since the start of the service day) and colors (as 0xRRGGBB) are INTEGERs, coordinates, distances and
+// prices are REALs, and IDs, names, dates (as YYYY-MM-DD) and timezones are TEXT. Rows are read straight into records,
+// which are collected into their tables with the same GtfsTable implementations used by the CSV loaders.
+pub struct SqliteStore {
+    pub connection: rusqlite::Connection,
+}
+
+// VERSION is kept in the database's user_version, so that a database laid out differently is reported rather than
+// misread. Databases written before the columns were typed, which held every value as TEXT, have version 0.
+const VERSION: i64 = 1;
+
+#[derive(Debug)]
+pub enum SqliteStoreError {
+    SqliteError(rusqlite::Error),
+    UnsupportedVersion(i64),
+    // InvalidValue is a value which its record can't hold, given with the table and column it was read from.
+    InvalidValue(&'static str, &'static str, rusqlite::Error),
+}
+
+impl fmt::Display for SqliteStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SqliteError(e) => write!(f, "SQLite error: {}", e),
+            Self::UnsupportedVersion(version) => write!(
+                f, "the database has layout version {}, not {}; save the schedule to it again to replace it", version, VERSION
+            ),
+            Self::InvalidValue(table, column, e) => write!(f, "invalid {} in {}: {}", column, table, e),
+        }
+    }
+}
+
+// SqliteStoreError is displayed with the error it wraps, so its source is that error's source in turn.
+impl std::error::Error for SqliteStoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::SqliteError(e) => e.source(),
+            Self::UnsupportedVersion(_) => None,
+            Self::InvalidValue(_, _, e) => e.source(),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for SqliteStoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::SqliteError(e)
+    }
+}
+
+// the indexes created alongside the tables, as (table, column) pairs.
+const INDEXES: [(&str, &str); 16] = [
+    ("agency", "agency_id"),
+    ("stops", "stop_id"),
+    ("routes", "route_id"),
+    ("trips", "trip_id"),
+    ("trips", "route_id"),
+    ("stop_times", "trip_id"),
+    ("calendar_dates", "service_id"),
+    ("shapes", "shape_id"),
+    ("frequencies", "trip_id"),
+    ("transfers", "from_stop_id"),
+    ("pathways", "pathway_id"),
+    ("fare_attributes", "fare_id"),
+    ("fare_rules", "fare_id"),
+    ("fare_products", "fare_product_id"),
+    ("fare_leg_rules", "fare_product_id"),
+    ("stop_areas", "stop_id"),
+];
+
+impl SqliteStore {
+    pub fn open<P: AsRef<path::Path>>(path: P) -> Result<Self, SqliteStoreError> {
+        Ok(Self {
+            connection: rusqlite::Connection::open(path)?,
+        })
+    }
+
+    pub fn open_in_memory() -> Result<Self, SqliteStoreError> {
+        Ok(Self {
+            connection: rusqlite::Connection::open_in_memory()?,
+        })
+    }
+
+    // is_store reports whether the file at path is a SQLite database, such as one written by save, by its header.
+    pub fn is_store<P: AsRef<path::Path>>(path: P) -> bool {
+        let mut header = [0; 16];
+        fs::File::open(path).and_then(|mut file| file.read_exact(&mut header)).is_ok() && &header == b"SQLite format 3\0"
+    }
+
+    // save replaces the contents of the database with the given schedule.
+    pub fn save(&mut self, schedule: &gtfs::GtfsSchedule) -> Result<(), SqliteStoreError> {
+        let tx = self.connection.transaction()?;
+
+        write_table(&tx, &schedule.agencies)?;
+        write_table(&tx, &schedule.stops)?;
+        write_table(&tx, &schedule.routes)?;
+        write_table(&tx, &schedule.trips)?;
+        write_table(&tx, schedule.stop_times.iter())?;
+        write_table(&tx, &schedule.calendar)?;
+        write_table(&tx, schedule.calendar_dates.iter())?;
+        write_table(&tx, schedule.shapes.iter())?;
+        write_table(&tx, schedule.frequencies.iter())?;
+        write_table(&tx, schedule.transfers.iter())?;
+        write_table(&tx, &schedule.pathways)?;
+        write_table(&tx, &schedule.fare_attributes)?;
+        write_table(&tx, schedule.fare_rules.iter())?;
+        write_table(&tx, schedule.fare_products.iter())?;
+        write_table(&tx, schedule.fare_leg_rules.iter())?;
+        write_table(&tx, schedule.stop_areas.iter())?;
+
+        // indexes are created after the bulk insert, which is considerably faster than maintaining them row by row.
+        for (table, column) in INDEXES {
+            tx.execute(
+                &format!("CREATE INDEX IF NOT EXISTS {}_{} ON {} ({})", table, column, table, column),
+                [],
+            )?;
+        }
+        tx.pragma_update(None, "user_version", VERSION)?;
+
+        Ok(tx.commit()?)
+    }
+
+    // load reads a schedule previously written by save.
+    pub fn load(&self) -> Result<gtfs::GtfsSchedule, SqliteStoreError> {
+        let version = self.connection.pragma_query_value(None, "user_version", |row| row.get::<_, i64>(0))?;
+        if version != VERSION {
+            return Err(SqliteStoreError::UnsupportedVersion(version));
+        }
+
+        Ok(gtfs::GtfsSchedule {
+            agencies: read_table(&self.connection)?,
+            stops: read_table(&self.connection)?,
+            routes: read_table(&self.connection)?,
+            trips: read_table(&self.connection)?,
+            stop_times: read_table(&self.connection)?,
+            calendar: read_table(&self.connection)?,
+            calendar_dates: read_table(&self.connection)?,
+            shapes: read_table(&self.connection)?,
+            frequencies: read_table(&self.connection)?,
+            transfers: read_table(&self.connection)?,
+            pathways: read_table(&self.connection)?,
+            fare_attributes: read_table(&self.connection)?,
+            fare_rules: read_table(&self.connection)?,
+            fare_products: read_table(&self.connection)?,
+            fare_leg_rules: read_table(&self.connection)?,
+            stop_areas: read_table(&self.connection)?,
+            index_cache: Default::default(),
+        })
+    }
+}
+
+// Stored is implemented by the records of each table in the store, mapping them to and from the rows of an SQL table.
+trait Stored: Sized {
+    // TABLE names the SQL table, after the feed file the records come from, e.g. stop_times.
+    const TABLE: &'static str;
+
+    // COLUMNS gives each column's name and type, in the order that insert binds them and from_row reads them.
+    const COLUMNS: &'static [(&'static str, &'static str)];
+
+    // insert executes statement, an INSERT of every column in turn, for this record.
+    fn insert(&self, statement: &mut rusqlite::Statement<'_>) -> rusqlite::Result<usize>;
+
+    // from_row reads a record from a row holding every column in turn.
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self>;
+}
+
+// write_table replaces the SQL table of R with one holding records.
+fn write_table<'a, R: Stored + 'a>(
+    connection: &rusqlite::Connection,
+    records: impl IntoIterator<Item = &'a R>,
+) -> Result<(), SqliteStoreError> {
+    connection.execute(&format!("DROP TABLE IF EXISTS {}", R::TABLE), [])?;
+    connection.execute(
+        &format!(
+            "CREATE TABLE {} ({})",
+            R::TABLE,
+            R::COLUMNS.iter().map(|(column, column_type)| format!("{} {}", column, column_type)).collect::<Vec<_>>().join(", ")
+        ),
+        [],
+    )?;
+    let mut statement = connection.prepare(&format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        R::TABLE,
+        R::COLUMNS.iter().map(|(column, _)| *column).collect::<Vec<_>>().join(", "),
+        (1..=R::COLUMNS.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ")
+    ))?;
+    for record in records {
+        record.insert(&mut statement)?;
+    }
+    Ok(())
+}
+
+// read_table reads the rows of T's records into a T, in the order they were written, so that records sharing a key,
+// e.g. a service's calendar dates, keep their order. T then finishes itself as it would after loading a CSV file.
+fn read_table<T: table::GtfsTable>(connection: &rusqlite::Connection) -> Result<T, SqliteStoreError>
+where
+    T::Record: Stored,
+{
+    let columns = T::Record::COLUMNS;
+    let mut statement = connection.prepare(&format!(
+        "SELECT {} FROM {} ORDER BY rowid",
+        columns.iter().map(|(column, _)| *column).collect::<Vec<_>>().join(", "),
+        T::Record::TABLE
+    ))?;
+    let mut rows = statement.query([])?;
+    let mut table = T::default();
+    while let Some(row) = rows.next()? {
+        let record = T::Record::from_row(row).map_err(|e| match e {
+            rusqlite::Error::FromSqlConversionFailure(column, _, _)
+            | rusqlite::Error::IntegralValueOutOfRange(column, _)
+            | rusqlite::Error::InvalidColumnType(column, _, _) => {
+                SqliteStoreError::InvalidValue(T::Record::TABLE, columns[column].0, e)
+            },
+            _ => SqliteStoreError::SqliteError(e),
+        })?;
+        table.insert(T::key(&record), record);
+    }
+    table.finish();
+    Ok(table)
+}
+
+// Parsed reads a value stored as feeds give it, e.g. a timezone by its name or a route type by its code, parsing it
+// with the same FromStr implementation used by the CSV loaders.
+struct Parsed<T>(T);
+
+impl<T: FromStr> FromSql for Parsed<T>
+where
+    T::Err: fmt::Display,
+{
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let parsed = match value {
+            ValueRef::Integer(code) => code.to_string().parse(),
+            ValueRef::Text(_) => value.as_str()?.parse(),
+            _ => return Err(FromSqlError::InvalidType),
+        };
+        parsed.map(Parsed).map_err(|e: T::Err| FromSqlError::Other(e.to_string().into()))
+    }
+}
+
+fn parsed<T: FromStr>(row: &rusqlite::Row<'_>, column: usize) -> rusqlite::Result<T>
+where
+    T::Err: fmt::Display,
+{
+    row.get::<_, Parsed<T>>(column).map(|Parsed(value)| value)
+}
+
+fn optional_parsed<T: FromStr>(row: &rusqlite::Row<'_>, column: usize) -> rusqlite::Result<Option<T>>
+where
+    T::Err: fmt::Display,
+{
+    Ok(row.get::<_, Option<Parsed<T>>>(column)?.map(|Parsed(value)| value))
+}
+
+// invalid reports that the value in column can't be held by the row's record, e.g. a stop without a name.
+fn invalid(row: &rusqlite::Row<'_>, column: usize, e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> rusqlite::Error {
+    let data_type = row.get_ref(column).map_or(rusqlite::types::Type::Null, |value| value.data_type());
+    rusqlite::Error::FromSqlConversionFailure(column, data_type, e.into())
+}
+
+// stored_as_code stores enumerations as the integer codes feeds give them.
+macro_rules! stored_as_code {
+    ($($t:ty),* $(,)?) => {$(
+        impl ToSql for $t {
+            fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+                Ok(ToSqlOutput::from(self.code()))
+            }
+        }
+
+        impl FromSql for $t {
+            fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+                Parsed::column_result(value).map(|Parsed(value)| value)
+            }
+        }
+    )*};
+}
+
+stored_as_code!(
+    trips::Direction,
+    routes::RouteType,
+    routes::RouteContinuityPolicy,
+    stop_times::StopPolicy,
+    stop_times::Timepoint,
+    calendar::ExceptionType,
+    frequencies::ExactTimes,
+    transfers::TransferType,
+    pathways::PathwayMode,
+    fares::PaymentMethod,
+);
+
+// GtfsTime is stored as the number of seconds since the start of the service day.
+impl ToSql for stop_times::GtfsTime {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.seconds()))
+    }
+}
+
+impl FromSql for stop_times::GtfsTime {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        u32::column_result(value).map(stop_times::GtfsTime::from_seconds)
+    }
+}
+
+// colors are stored as 0xRRGGBB.
+fn encode_color(color: &hex_color::HexColor) -> u32 {
+    u32::from_be_bytes([0, color.r, color.g, color.b])
+}
+
+fn decode_color(rgb: u32) -> hex_color::HexColor {
+    let [_, r, g, b] = rgb.to_be_bytes();
+    hex_color::HexColor::rgb(r, g, b)
+}
+
+impl Stored for agency::Agency {
+    const TABLE: &'static str = "agency";
+    const COLUMNS: &'static [(&'static str, &'static str)] = &[
+        ("agency_id", "TEXT"),
+        ("agency_name", "TEXT NOT NULL"),
+        ("agency_url", "TEXT NOT NULL"),
+        ("agency_timezone", "TEXT NOT NULL"),
+        ("agency_lang", "TEXT"),
+        ("agency_phone", "TEXT"),
+        ("agency_fare_url", "TEXT"),
+        ("agency_email", "TEXT"),
+    ];
+
+    fn insert(&self, statement: &mut rusqlite::Statement<'_>) -> rusqlite::Result<usize> {
+        statement.execute(rusqlite::params![
+            self.agency_id,
+            self.agency_name,
+            self.agency_url,
+            self.agency_timezone.name(),
+            self.agency_lang,
+            self.agency_phone,
+            self.agency_fare_url,
+            self.agency_email,
+        ])
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(agency::Agency {
+            agency_id: row.get(0)?,
+            agency_name: row.get(1)?,
+            agency_url: row.get(2)?,
+            agency_timezone: parsed(row, 3)?,
+            agency_lang: row.get(4)?,
+            agency_phone: row.get(5)?,
+            agency_fare_url: row.get(6)?,
+            agency_email: row.get(7)?,
+        })
+    }
+}
+
+impl Stored for stops::Stop {
+    const TABLE: &'static str = "stops";
+    const COLUMNS: &'static [(&'static str, &'static str)] = &[
+        ("stop_id", "TEXT NOT NULL"),
+        ("stop_code", "TEXT"),
+        ("stop_name", "TEXT"),
+        ("tts_stop_name", "TEXT"),
+        ("stop_desc", "TEXT"),
+        ("stop_lat", "REAL"),
+        ("stop_lon", "REAL"),
+        ("zone_id", "TEXT"),
+        ("stop_url", "TEXT"),
+        ("location_type", "INTEGER NOT NULL"),
+        ("parent_station", "TEXT"),
+        ("stop_timezone", "TEXT"),
+        ("wheelchair_boarding", "INTEGER"),
+        ("level_id", "TEXT"),
+        ("platform_code", "TEXT"),
+    ];
+
+    fn insert(&self, statement: &mut rusqlite::Statement<'_>) -> rusqlite::Result<usize> {
+        statement.execute(rusqlite::params![
+            self.stop_id,
+            self.stop_code,
+            self.get_stop_name(),
+            self.tts_stop_name,
+            self.stop_desc,
+            self.stop_lat(),
+            self.stop_lon(),
+            self.zone_id,
+            self.stop_url,
+            self.location_type_details.location_type(),
+            self.parent_station(),
+            self.stop_timezone.map(|tz| tz.name()),
+            self.wheelchair_boarding,
+            self.level_id,
+            self.platform_code,
+        ])
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(stops::Stop {
+            stop_id: row.get(0)?,
+            stop_code: row.get(1)?,
+            tts_stop_name: row.get(3)?,
+            stop_desc: row.get(4)?,
+            zone_id: row.get(7)?,
+            stop_url: row.get(8)?,
+            stop_timezone: optional_parsed(row, 11)?,
+            wheelchair_boarding: row.get(12)?,
+            level_id: row.get(13)?,
+            platform_code: row.get(14)?,
+            location_type_details: stops::LocationTypeDetails::new(
+                row.get(9)?,
+                row.get(2)?,
+                row.get(5)?,
+                row.get(6)?,
+                row.get(10)?,
+            ).map_err(|e| invalid(row, 9, e))?,
+        })
+    }
+}
+
+impl Stored for routes::Route {
+    const TABLE: &'static str = "routes";
+    const COLUMNS: &'static [(&'static str, &'static str)] = &[
+        ("route_id", "TEXT NOT NULL"),
+        ("agency_id", "TEXT"),
+        ("route_short_name", "TEXT"),
+        ("route_long_name", "TEXT"),
+        ("route_desc", "TEXT"),
+        ("route_type", "INTEGER NOT NULL"),
+        ("route_url", "TEXT"),
+        ("route_color", "INTEGER"),
+        ("route_text_color", "INTEGER"),
+        ("route_sort_order", "INTEGER"),
+        ("continuous_pickup", "INTEGER"),
+        ("continuous_drop_off", "INTEGER"),
+        ("network_id", "TEXT"),
+    ];
+
+    fn insert(&self, statement: &mut rusqlite::Statement<'_>) -> rusqlite::Result<usize> {
+        statement.execute(rusqlite::params![
+            self.route_id,
+            self.agency_id,
+            self.route_short_name(),
+            self.route_long_name(),
+            self.route_desc,
+            self.route_type,
+            self.route_url,
+            self.route_color.as_ref().map(encode_color),
+            self.route_text_color.as_ref().map(encode_color),
+            self.route_sort_order,
+            self.continuous_pickup,
+            self.continuous_drop_off,
+            self.network_id,
+        ])
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        let name = routes::RouteName::from_names(row.get(2)?, row.get(3)?)
+            .ok_or_else(|| invalid(row, 2, "route_short_name or route_long_name is required"))?;
+        let mut route = routes::Route::new(&row.get::<_, String>(0)?, name, row.get(5)?);
+        route.agency_id = row.get(1)?;
+        route.route_desc = row.get(4)?;
+        route.route_url = row.get(6)?;
+        route.route_color = row.get::<_, Option<u32>>(7)?.map(decode_color);
+        route.route_text_color = row.get::<_, Option<u32>>(8)?.map(decode_color);
+        route.route_sort_order = row.get(9)?;
+        route.continuous_pickup = row.get(10)?;
+        route.continuous_drop_off = row.get(11)?;
+        route.network_id = row.get(12)?;
+        Ok(route)
+    }
+}
+
+impl Stored for trips::Trip {
+    const TABLE: &'static str = "trips";
+    const COLUMNS: &'static [(&'static str, &'static str)] = &[
+        ("route_id", "TEXT NOT NULL"),
+        ("service_id", "TEXT NOT NULL"),
+        ("trip_id", "TEXT NOT NULL"),
+        ("trip_headsign", "TEXT"),
+        ("trip_short_name", "TEXT"),
+        ("direction_id", "INTEGER"),
+        ("block_id", "TEXT"),
+        ("shape_id", "TEXT"),
+        ("wheelchair_accessible", "INTEGER"),
+        ("bikes_allowed", "INTEGER"),
+    ];
+
+    fn insert(&self, statement: &mut rusqlite::Statement<'_>) -> rusqlite::Result<usize> {
+        statement.execute(rusqlite::params![
+            self.route_id,
+            self.service_id,
+            self.trip_id,
+            self.trip_headsign,
+            self.trip_short_name,
+            self.direction_id,
+            self.block_id,
+            self.shape_id,
+            self.wheelchair_accessible,
+            self.bikes_allowed,
+        ])
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(trips::Trip {
+            route_id: row.get(0)?,
+            service_id: row.get(1)?,
+            trip_id: row.get(2)?,
+            trip_headsign: row.get(3)?,
+            trip_short_name: row.get(4)?,
+            direction_id: row.get(5)?,
+            block_id: row.get(6)?,
+            shape_id: row.get(7)?,
+            wheelchair_accessible: row.get(8)?,
+            bikes_allowed: row.get(9)?,
+        })
+    }
+}
+
+impl Stored for stop_times::StopTime {
+    const TABLE: &'static str = "stop_times";
+    const COLUMNS: &'static [(&'static str, &'static str)] = &[
+        ("trip_id", "TEXT NOT NULL"),
+        ("stop_id", "TEXT"),
+        ("arrival_time", "INTEGER"),
+        ("departure_time", "INTEGER"),
+        ("location_group_id", "TEXT"),
+        ("location_id", "TEXT"),
+        ("stop_sequence", "INTEGER NOT NULL"),
+        ("stop_headsign", "TEXT"),
+        ("start_pickup_drop_off_window", "INTEGER"),
+        ("end_pickup_drop_off_window", "INTEGER"),
+        ("pickup_type", "INTEGER"),
+        ("drop_off_type", "INTEGER"),
+        ("continuous_pickup", "INTEGER"),
+        ("continuous_drop_off", "INTEGER"),
+        ("shape_dist_traveled", "REAL"),
+        ("timepoint", "INTEGER"),
+        ("pickup_booking_rule_id", "TEXT"),
+        ("drop_off_booking_rule_id", "TEXT"),
+    ];
+
+    fn insert(&self, statement: &mut rusqlite::Statement<'_>) -> rusqlite::Result<usize> {
+        statement.execute(rusqlite::params![
+            self.trip_id,
+            self.stop_id,
+            self.arrival_time,
+            self.departure_time,
+            self.location_group_id,
+            self.location_id,
+            self.stop_sequence,
+            self.stop_headsign,
+            self.start_pickup_drop_off_window,
+            self.end_pickup_drop_off_window,
+            self.pickup_type,
+            self.drop_off_type,
+            self.continuous_pickup,
+            self.continuous_drop_off,
+            self.shape_dist_traveled,
+            self.timepoint,
+            self.pickup_booking_rule_id,
+            self.drop_off_booking_rule_id,
+        ])
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(stop_times::StopTime {
+            trip_id: row.get(0)?,
+            stop_id: row.get(1)?,
+            arrival_time: row.get(2)?,
+            departure_time: row.get(3)?,
+            location_group_id: row.get(4)?,
+            location_id: row.get(5)?,
+            stop_sequence: row.get(6)?,
+            stop_headsign: row.get(7)?,
+            start_pickup_drop_off_window: row.get(8)?,
+            end_pickup_drop_off_window: row.get(9)?,
+            pickup_type: row.get(10)?,
+            drop_off_type: row.get(11)?,
+            continuous_pickup: row.get(12)?,
+            continuous_drop_off: row.get(13)?,
+            shape_dist_traveled: row.get(14)?,
+            timepoint: row.get(15)?,
+            pickup_booking_rule_id: row.get(16)?,
+            drop_off_booking_rule_id: row.get(17)?,
+        })
+    }
+}
+
+impl Stored for calendar::Service {
+    const TABLE: &'static str = "calendar";
+    const COLUMNS: &'static [(&'static str, &'static str)] = &[
+        ("service_id", "TEXT NOT NULL"),
+        ("monday", "INTEGER NOT NULL"),
+        ("tuesday", "INTEGER NOT NULL"),
+        ("wednesday", "INTEGER NOT NULL"),
+        ("thursday", "INTEGER NOT NULL"),
+        ("friday", "INTEGER NOT NULL"),
+        ("saturday", "INTEGER NOT NULL"),
+        ("sunday", "INTEGER NOT NULL"),
+        ("start_date", "TEXT NOT NULL"),
+        ("end_date", "TEXT NOT NULL"),
+    ];
+
+    fn insert(&self, statement: &mut rusqlite::Statement<'_>) -> rusqlite::Result<usize> {
+        statement.execute(rusqlite::params![
+            self.service_id,
+            self.monday,
+            self.tuesday,
+            self.wednesday,
+            self.thursday,
+            self.friday,
+            self.saturday,
+            self.sunday,
+            self.start_date.to_string(),
+            self.end_date.to_string(),
+        ])
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(calendar::Service {
+            service_id: row.get(0)?,
+            monday: row.get(1)?,
+            tuesday: row.get(2)?,
+            wednesday: row.get(3)?,
+            thursday: row.get(4)?,
+            friday: row.get(5)?,
+            saturday: row.get(6)?,
+            sunday: row.get(7)?,
+            start_date: parsed(row, 8)?,
+            end_date: parsed(row, 9)?,
+        })
+    }
+}
+
+impl Stored for calendar::CalendarDate {
+    const TABLE: &'static str = "calendar_dates";
+    const COLUMNS: &'static [(&'static str, &'static str)] = &[
+        ("service_id", "TEXT NOT NULL"),
+        ("date", "TEXT NOT NULL"),
+        ("exception_type", "INTEGER NOT NULL"),
+    ];
+
+    fn insert(&self, statement: &mut rusqlite::Statement<'_>) -> rusqlite::Result<usize> {
+        statement.execute(rusqlite::params![self.service_id, self.date.to_string(), self.exception_type])
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(calendar::CalendarDate {
+            service_id: row.get(0)?,
+            date: parsed(row, 1)?,
+            exception_type: row.get(2)?,
+        })
+    }
+}
+
+impl Stored for shapes::ShapePoint {
+    const TABLE: &'static str = "shapes";
+    const COLUMNS: &'static [(&'static str, &'static str)] = &[
+        ("shape_id", "TEXT NOT NULL"),
+        ("shape_pt_lat", "REAL NOT NULL"),
+        ("shape_pt_lon", "REAL NOT NULL"),
+        ("shape_pt_sequence", "INTEGER NOT NULL"),
+        ("shape_dist_traveled", "REAL"),
+    ];
+
+    fn insert(&self, statement: &mut rusqlite::Statement<'_>) -> rusqlite::Result<usize> {
+        statement.execute(rusqlite::params![
+            self.shape_id,
+            self.shape_pt_lat,
+            self.shape_pt_lon,
+            self.shape_pt_sequence,
+            self.shape_dist_traveled,
+        ])
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(shapes::ShapePoint {
+            shape_id: row.get(0)?,
+            shape_pt_lat: row.get(1)?,
+            shape_pt_lon: row.get(2)?,
+            shape_pt_sequence: row.get(3)?,
+            shape_dist_traveled: row.get(4)?,
+        })
+    }
+}
+
+impl Stored for frequencies::Frequency {
+    const TABLE: &'static str = "frequencies";
+    const COLUMNS: &'static [(&'static str, &'static str)] = &[
+        ("trip_id", "TEXT NOT NULL"),
+        ("start_time", "INTEGER NOT NULL"),
+        ("end_time", "INTEGER NOT NULL"),
+        ("headway_secs", "INTEGER NOT NULL"),
+        ("exact_times", "INTEGER"),
+    ];
+
+    fn insert(&self, statement: &mut rusqlite::Statement<'_>) -> rusqlite::Result<usize> {
+        statement.execute(rusqlite::params![self.trip_id, self.start_time, self.end_time, self.headway_secs, self.exact_times])
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(frequencies::Frequency {
+            trip_id: row.get(0)?,
+            start_time: row.get(1)?,
+            end_time: row.get(2)?,
+            headway_secs: row.get(3)?,
+            exact_times: row.get(4)?,
+        })
+    }
+}
+
+impl Stored for transfers::Transfer {
+    const TABLE: &'static str = "transfers";
+    const COLUMNS: &'static [(&'static str, &'static str)] = &[
+        ("from_stop_id", "TEXT"),
+        ("to_stop_id", "TEXT"),
+        ("from_route_id", "TEXT"),
+        ("to_route_id", "TEXT"),
+        ("from_trip_id", "TEXT"),
+        ("to_trip_id", "TEXT"),
+        ("transfer_type", "INTEGER"),
+        ("min_transfer_time", "INTEGER"),
+    ];
+
+    fn insert(&self, statement: &mut rusqlite::Statement<'_>) -> rusqlite::Result<usize> {
+        statement.execute(rusqlite::params![
+            self.from_stop_id,
+            self.to_stop_id,
+            self.from_route_id,
+            self.to_route_id,
+            self.from_trip_id,
+            self.to_trip_id,
+            self.transfer_type,
+            self.min_transfer_time,
+        ])
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(transfers::Transfer {
+            from_stop_id: row.get(0)?,
+            to_stop_id: row.get(1)?,
+            from_route_id: row.get(2)?,
+            to_route_id: row.get(3)?,
+            from_trip_id: row.get(4)?,
+            to_trip_id: row.get(5)?,
+            transfer_type: row.get(6)?,
+            min_transfer_time: row.get(7)?,
+        })
+    }
+}
+
+impl Stored for pathways::Pathway {
+    const TABLE: &'static str = "pathways";
+    const COLUMNS: &'static [(&'static str, &'static str)] = &[
+        ("pathway_id", "TEXT NOT NULL"),
+        ("from_stop_id", "TEXT NOT NULL"),
+        ("to_stop_id", "TEXT NOT NULL"),
+        ("pathway_mode", "INTEGER NOT NULL"),
+        ("is_bidirectional", "INTEGER NOT NULL"),
+        ("length", "REAL"),
+        ("traversal_time", "INTEGER"),
+        ("stair_count", "INTEGER"),
+        ("max_slope", "REAL"),
+        ("min_width", "REAL"),
+        ("signposted_as", "TEXT"),
+        ("reversed_signposted_as", "TEXT"),
+    ];
+
+    fn insert(&self, statement: &mut rusqlite::Statement<'_>) -> rusqlite::Result<usize> {
+        statement.execute(rusqlite::params![
+            self.pathway_id,
+            self.from_stop_id,
+            self.to_stop_id,
+            self.pathway_mode,
+            self.is_bidirectional,
+            self.length,
+            self.traversal_time,
+            self.stair_count,
+            self.max_slope,
+            self.min_width,
+            self.signposted_as,
+            self.reversed_signposted_as,
+        ])
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(pathways::Pathway {
+            pathway_id: row.get(0)?,
+            from_stop_id: row.get(1)?,
+            to_stop_id: row.get(2)?,
+            pathway_mode: row.get(3)?,
+            is_bidirectional: row.get(4)?,
+            length: row.get(5)?,
+            traversal_time: row.get(6)?,
+            stair_count: row.get(7)?,
+            max_slope: row.get(8)?,
+            min_width: row.get(9)?,
+            signposted_as: row.get(10)?,
+            reversed_signposted_as: row.get(11)?,
+        })
+    }
+}
+
+impl Stored for fares::FareAttribute {
+    const TABLE: &'static str = "fare_attributes";
+    const COLUMNS: &'static [(&'static str, &'static str)] = &[
+        ("fare_id", "TEXT NOT NULL"),
+        ("price", "REAL NOT NULL"),
+        ("currency_type", "TEXT NOT NULL"),
+        ("payment_method", "INTEGER NOT NULL"),
+        ("transfers", "INTEGER"),
+        ("agency_id", "TEXT"),
+        ("transfer_duration", "INTEGER"),
+    ];
+
+    fn insert(&self, statement: &mut rusqlite::Statement<'_>) -> rusqlite::Result<usize> {
+        statement.execute(rusqlite::params![
+            self.fare_id,
+            self.price,
+            self.currency_type,
+            self.payment_method,
+            self.transfers,
+            self.agency_id,
+            self.transfer_duration,
+        ])
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(fares::FareAttribute {
+            fare_id: row.get(0)?,
+            price: row.get(1)?,
+            currency_type: row.get(2)?,
+            payment_method: row.get(3)?,
+            transfers: row.get(4)?,
+            agency_id: row.get(5)?,
+            transfer_duration: row.get(6)?,
+        })
+    }
+}
+
+impl Stored for fares::FareRule {
+    const TABLE: &'static str = "fare_rules";
+    const COLUMNS: &'static [(&'static str, &'static str)] = &[
+        ("fare_id", "TEXT NOT NULL"),
+        ("route_id", "TEXT"),
+        ("origin_id", "TEXT"),
+        ("destination_id", "TEXT"),
+        ("contains_id", "TEXT"),
+    ];
+
+    fn insert(&self, statement: &mut rusqlite::Statement<'_>) -> rusqlite::Result<usize> {
+        statement.execute(rusqlite::params![self.fare_id, self.route_id, self.origin_id, self.destination_id, self.contains_id])
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(fares::FareRule {
+            fare_id: row.get(0)?,
+            route_id: row.get(1)?,
+            origin_id: row.get(2)?,
+            destination_id: row.get(3)?,
+            contains_id: row.get(4)?,
+        })
+    }
+}
+
+impl Stored for fare_products::FareProduct {
+    const TABLE: &'static str = "fare_products";
+    const COLUMNS: &'static [(&'static str, &'static str)] = &[
+        ("fare_product_id", "TEXT NOT NULL"),
+        ("fare_product_name", "TEXT"),
+        ("rider_category_id", "TEXT"),
+        ("fare_media_id", "TEXT"),
+        ("amount", "REAL NOT NULL"),
+        ("currency", "TEXT NOT NULL"),
+    ];
+
+    fn insert(&self, statement: &mut rusqlite::Statement<'_>) -> rusqlite::Result<usize> {
+        statement.execute(rusqlite::params![
+            self.fare_product_id,
+            self.fare_product_name,
+            self.rider_category_id,
+            self.fare_media_id,
+            self.amount,
+            self.currency,
+        ])
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(fare_products::FareProduct {
+            fare_product_id: row.get(0)?,
+            fare_product_name: row.get(1)?,
+            rider_category_id: row.get(2)?,
+            fare_media_id: row.get(3)?,
+            amount: row.get(4)?,
+            currency: row.get(5)?,
+        })
+    }
+}
+
+impl Stored for fare_products::FareLegRule {
+    const TABLE: &'static str = "fare_leg_rules";
+    const COLUMNS: &'static [(&'static str, &'static str)] = &[
+        ("leg_group_id", "TEXT"),
+        ("network_id", "TEXT"),
+        ("from_area_id", "TEXT"),
+        ("to_area_id", "TEXT"),
+        ("from_timeframe_group_id", "TEXT"),
+        ("to_timeframe_group_id", "TEXT"),
+        ("fare_product_id", "TEXT NOT NULL"),
+        ("rule_priority", "INTEGER"),
+    ];
+
+    fn insert(&self, statement: &mut rusqlite::Statement<'_>) -> rusqlite::Result<usize> {
+        statement.execute(rusqlite::params![
+            self.leg_group_id,
+            self.network_id,
+            self.from_area_id,
+            self.to_area_id,
+            self.from_timeframe_group_id,
+            self.to_timeframe_group_id,
+            self.fare_product_id,
+            self.rule_priority,
+        ])
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(fare_products::FareLegRule {
+            leg_group_id: row.get(0)?,
+            network_id: row.get(1)?,
+            from_area_id: row.get(2)?,
+            to_area_id: row.get(3)?,
+            from_timeframe_group_id: row.get(4)?,
+            to_timeframe_group_id: row.get(5)?,
+            fare_product_id: row.get(6)?,
+            rule_priority: row.get(7)?,
+        })
+    }
+}
+
+impl Stored for fare_products::StopArea {
+    const TABLE: &'static str = "stop_areas";
+    const COLUMNS: &'static [(&'static str, &'static str)] = &[
+        ("area_id", "TEXT NOT NULL"),
+        ("stop_id", "TEXT NOT NULL"),
+    ];
+
+    fn insert(&self, statement: &mut rusqlite::Statement<'_>) -> rusqlite::Result<usize> {
+        statement.execute(rusqlite::params![self.area_id, self.stop_id])
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(fare_products::StopArea {
+            area_id: row.get(0)?,
+            stop_id: row.get(1)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::testing;
+
+    #[test]
+    fn a_saved_schedule_loads_unchanged() {
+        let schedule = testing::feed();
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        store.save(&schedule).unwrap();
+        assert_eq!(store.load().unwrap(), schedule);
+    }
+
+    #[test]
+    fn saving_again_replaces_the_schedule() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        store.save(&testing::feed()).unwrap();
+        store.save(&testing::schedule()).unwrap();
+        assert_eq!(store.load().unwrap(), testing::schedule());
+    }
+
+    #[test]
+    fn values_are_stored_typed() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        store.save(&testing::feed()).unwrap();
+        let (departure, sequence) = store.connection.query_row(
+            "SELECT departure_time, stop_sequence FROM stop_times WHERE trip_id = 'T5' ORDER BY stop_sequence",
+            [],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+        ).unwrap();
+        assert_eq!((departure, sequence), (25 * 3600 + 30 * 60, 1));
+        let color = store.connection.query_row("SELECT route_color FROM routes WHERE route_id = 'R1'", [], |row| row.get::<_, i64>(0)).unwrap();
+        assert_eq!(color, 0x00843D);
+    }
+
+    #[test]
+    fn databases_of_another_layout_are_rejected() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        store.connection.execute("CREATE TABLE stops (stop_id TEXT, stop_lat TEXT)", []).unwrap();
+        assert!(matches!(store.load(), Err(SqliteStoreError::UnsupportedVersion(0))));
+    }
+
+    #[test]
+    fn invalid_values_are_reported_with_their_column() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        store.save(&testing::feed()).unwrap();
+        store.connection.execute("UPDATE routes SET route_type = 42 WHERE route_id = 'R1'", []).unwrap();
+        assert!(matches!(store.load(), Err(SqliteStoreError::InvalidValue("routes", "route_type", _))));
+        store.connection.execute("UPDATE stops SET stop_name = NULL WHERE stop_id = 'S'", []).unwrap();
+        store.connection.execute("UPDATE routes SET route_type = 3", []).unwrap();
+        let err = store.load().unwrap_err();
+        assert!(matches!(err, SqliteStoreError::InvalidValue("stops", "location_type", _)));
+        assert!(err.to_string().contains("stop_name is required"), "{}", err);
+    }
+}
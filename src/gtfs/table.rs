@@ -0,0 +1,43 @@
+use crate::gtfs::error::CsvError;
+use serde::de::DeserializeOwned;
+use std::io;
+
+// GtfsTable is implemented by the collections holding the records of a single GTFS file,
+// describing where each row goes in the collection so that load can do the rest.
+pub trait GtfsTable: Default {
+    // Record is the type of a single row of the file, deserialized from the row by its header names.
+    type Record: DeserializeOwned;
+
+    // FILE_NAME is the name of the file within a feed, e.g. stops.txt.
+    const FILE_NAME: &'static str;
+
+    // key returns the ID the record is indexed by within the collection.
+    fn key(record: &Self::Record) -> String;
+
+    // insert adds a record to the collection under its key.
+    fn insert(&mut self, key: String, record: Self::Record);
+
+    // finish is called once every row has been inserted; by default, it does nothing.
+    fn finish(&mut self) {}
+}
+
+// load consumes a csv::Reader<R>, deserializing each row as a T::Record and inserting it into a T.
+// Rows are deserialized in place from the reader's record buffer, so their fields aren't copied unless the record keeps them.
+// Errors are reported against T::FILE_NAME, at the line of the row which caused them.
+pub fn load<T: GtfsTable, R: io::Read>(mut r: csv::Reader<R>) -> Result<T, CsvError> {
+    let header = r.headers().cloned().map_err(|_| CsvError::no_header(T::FILE_NAME))?;
+    let mut table = T::default();
+    // a single record is reused for every row, rather than allocating one per row.
+    let mut record = csv::StringRecord::new();
+    while r.read_record(&mut record).map_err(|e| CsvError::read(T::FILE_NAME, e))? {
+        let parsed = record.deserialize::<T::Record>(Some(&header))
+            .map_err(|e| match e.kind() {
+                // the error is reported against the record itself, since the record was read successfully.
+                csv::ErrorKind::Deserialize { err, .. } => CsvError::record(T::FILE_NAME, &record, err.clone()),
+                _ => CsvError::read(T::FILE_NAME, e),
+            })?;
+        table.insert(T::key(&parsed), parsed);
+    }
+    table.finish();
+    Ok(table)
+}
@@ -0,0 +1,148 @@
+// testing builds small schedules in memory for the crate's unit tests.
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::routes::{Route, RouteName, RouteType};
+use crate::gtfs::stop_times::{GtfsTime, StopTime};
+use crate::gtfs::stops::{LocationTypeDetails, StationDetails, Stop, StopDetails};
+use crate::gtfs::table;
+use crate::gtfs::trips::Trip;
+
+// stop returns a stop at the given position, within station if one is given.
+pub fn stop(stop_id: &str, lat: f64, lon: f64, station: Option<&str>) -> Stop {
+    Stop::new(stop_id, LocationTypeDetails::Stop(StopDetails {
+        stop_name: stop_id.to_string(),
+        stop_lat: lat,
+        stop_lon: lon,
+        parent_station: station.map(str::to_string),
+    }))
+}
+
+pub fn station(stop_id: &str, lat: f64, lon: f64) -> Stop {
+    Stop::new(stop_id, LocationTypeDetails::Station(StationDetails {
+        stop_name: stop_id.to_string(),
+        stop_lat: lat,
+        stop_lon: lon,
+    }))
+}
+
+// time parses a time as GTFS writes it, e.g. 25:30:00.
+pub fn time(time: &str) -> GtfsTime {
+    time.parse().unwrap()
+}
+
+pub fn date(year: i32, month: u32, day: u32) -> chrono::NaiveDate {
+    chrono::NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+// trip_stop_times returns the stop times of a trip calling at each of stops in turn, arriving and departing at once.
+pub fn trip_stop_times(trip_id: &str, stops: &[(&str, &str)]) -> Vec<StopTime> {
+    stops.iter()
+        .enumerate()
+        .map(|(i, (stop_id, at))| StopTime::new(trip_id, stop_id, i + 1, Some(time(at)), Some(time(at))))
+        .collect()
+}
+
+// load reads a table from the text of its file.
+pub fn load<T: table::GtfsTable>(text: &str) -> T {
+    table::load(csv::Reader::from_reader(text.as_bytes())).unwrap()
+}
+
+// schedule returns a schedule of two routes of an agency in New York, meeting at station S, which run every day of 2025.
+// Route R1 runs trip T1 from stop X to S's platform S1. Route R2 runs trips from S's other platform, S2, to stop Y:
+// T3 at 08:12, T2 at 08:15, T4 at 09:30, and T5 at 25:30, after midnight.
+pub fn schedule() -> GtfsSchedule {
+    let mut schedule = GtfsSchedule {
+        agencies: load("agency_id,agency_name,agency_url,agency_timezone\nA,Agency,https://example.com,America/New_York\n"),
+        stops: [
+            stop("X", 40.70, -74.00, None),
+            station("S", 40.75, -73.99),
+            stop("S1", 40.7501, -73.9901, Some("S")),
+            stop("S2", 40.7502, -73.9902, Some("S")),
+            stop("Y", 40.80, -73.95, None),
+        ].into_iter().collect(),
+        routes: [
+            Route::new("R1", RouteName::Short("1".to_string()), RouteType::Bus),
+            Route::new("R2", RouteName::Short("2".to_string()), RouteType::Bus),
+        ].into_iter().collect(),
+        trips: [
+            Trip::new("T1", "R1", "D"),
+            Trip::new("T2", "R2", "D"),
+            Trip::new("T3", "R2", "D"),
+            Trip::new("T4", "R2", "D"),
+            Trip::new("T5", "R2", "D"),
+        ].into_iter().collect(),
+        stop_times: [
+            trip_stop_times("T1", &[("X", "08:00:00"), ("S1", "08:10:00")]),
+            trip_stop_times("T2", &[("S2", "08:15:00"), ("Y", "08:30:00")]),
+            trip_stop_times("T3", &[("S2", "08:12:00"), ("Y", "08:27:00")]),
+            trip_stop_times("T4", &[("S2", "09:30:00"), ("Y", "09:45:00")]),
+            trip_stop_times("T5", &[("S2", "25:30:00"), ("Y", "25:45:00")]),
+        ].into_iter().flatten().collect(),
+        calendar: load("service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\nD,1,1,1,1,1,1,1,20250101,20251231\n"),
+        ..Default::default()
+    };
+    for route in schedule.routes.routes.values_mut() {
+        route.agency_id = Some("A".to_string());
+    }
+    schedule
+}
+
+// feed returns schedule() with a record in every table, setting fields of every type the tables hold, for checking
+// that schedules are written and read back unchanged.
+pub fn feed() -> GtfsSchedule {
+    let mut schedule = schedule();
+    schedule.stops = load(
+        "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station,stop_timezone,wheelchair_boarding,zone_id\n\
+         X,Stop X,40.7,-74.0,,,America/Chicago,1,Z1\n\
+         S,Station S,40.75,-73.99,1,,,,\n\
+         S1,Platform 1,40.7501,-73.9901,0,S,,2,\n\
+         S2,Platform 2,40.7502,-73.9902,0,S,,,\n\
+         E,Entrance,40.7503,-73.9903,2,S,,,\n\
+         N,,,,3,S,,,\n\
+         B,Boarding area,,,4,S1,,,\n\
+         Y,Stop Y,40.8,-73.95,,,,,\n",
+    );
+    schedule.routes = load(
+        "route_id,agency_id,route_short_name,route_long_name,route_type,route_color,route_text_color,route_sort_order,continuous_pickup\n\
+         R1,A,1,,3,00843D,FFFFFF,2,1\n\
+         R2,A,2,Second Avenue,1,,,,\n",
+    );
+    schedule.trips = load(
+        "route_id,service_id,trip_id,trip_headsign,direction_id,block_id,shape_id,wheelchair_accessible,bikes_allowed\n\
+         R1,D,T1,S,0,B1,SH,1,2\n\
+         R2,D,T2,Y,1,,,,\n\
+         R2,D,T3,,,,,,\n\
+         R2,D,T4,,,,,,\n\
+         R2,D,T5,,,,,,\n",
+    );
+    schedule.stop_times = load(
+        "trip_id,arrival_time,departure_time,stop_id,stop_sequence,pickup_type,drop_off_type,shape_dist_traveled,timepoint\n\
+         T1,08:00:00,08:00:30,X,1,0,1,0,1\n\
+         T1,08:10:00,08:10:00,S1,2,,,1.5,0\n\
+         T5,25:30:00,25:30:00,S2,1,,,,\n\
+         T5,25:45:00,25:45:00,Y,2,,,,\n",
+    );
+    schedule.calendar_dates = load("service_id,date,exception_type\nD,20250704,2\nD,20250101,2\n");
+    schedule.shapes = load(
+        "shape_id,shape_pt_lat,shape_pt_lon,shape_pt_sequence,shape_dist_traveled\nSH,40.7,-74.0,1,0\nSH,40.7501,-73.9901,2,1.5\n",
+    );
+    schedule.frequencies = load("trip_id,start_time,end_time,headway_secs,exact_times\nT2,06:00:00,09:00:00,600,1\n");
+    schedule.transfers = load("from_stop_id,to_stop_id,from_trip_id,to_trip_id,transfer_type,min_transfer_time\nS1,S2,,,2,120\nS1,S2,T1,T3,1,\n");
+    schedule.pathways = load(
+        "pathway_id,from_stop_id,to_stop_id,pathway_mode,is_bidirectional,length,traversal_time,stair_count,signposted_as\n\
+         P1,E,N,2,1,12.5,30,-20,Platforms\n",
+    );
+    schedule.fare_attributes = load("fare_id,price,currency_type,payment_method,transfers,transfer_duration\nF,2.9,USD,0,1,7200\n");
+    schedule.fare_rules = load("fare_id,route_id,origin_id\nF,R1,Z1\n");
+    schedule.fare_products = load("fare_product_id,fare_product_name,amount,currency\nFP,Single ride,2.9,USD\n");
+    schedule.fare_leg_rules = load("network_id,from_area_id,fare_product_id,rule_priority\nN1,AR,FP,1\n");
+    schedule.stop_areas = load("area_id,stop_id\nAR,S1\nAR,S2\n");
+    schedule
+}
+
+// temp_dir returns an empty directory for a test to write files into, named for the test.
+pub fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("rust-gtfs-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,275 @@
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::stop_times;
+use crate::gtfs::trips;
+use crate::gtfs::view::ScheduleView;
+use std::fmt;
+use std::io;
+use std::ops;
+
+// Timetable is a route's service on one date laid out as a printed timetable: a row for each stop, in the order
+// the route visits them, and a column for each trip, in order of departure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timetable {
+    pub route_id: String,
+    pub direction_id: Option<trips::Direction>,
+    pub date: chrono::NaiveDate,
+    // trip_ids identifies the trip in each column. Trips run by a frequency take a column for every time they start,
+    // identified as trip_id@HH:MM:SS, as Frequencies::expand does.
+    pub trip_ids: Vec<String>,
+    pub rows: Vec<TimetableRow>,
+}
+
+// TimetableRow is a stop's row of a timetable. A trip which doesn't serve the stop, or doesn't give a time for it,
+// has no time in the stop's row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimetableRow {
+    pub stop_id: String,
+    pub stop_name: Option<String>,
+    pub times: Vec<Option<stop_times::GtfsTime>>,
+}
+
+impl Timetable {
+    // columns gives the part of the timetable with the trips of the given columns, e.g. to print a wide timetable a
+    // page at a time.
+    pub fn columns(&self, columns: std::ops::Range<usize>) -> Timetable {
+        let columns = columns.start.min(self.trip_ids.len())..columns.end.min(self.trip_ids.len());
+        self.select(&columns.collect::<Vec<_>>())
+    }
+
+    // departing_within gives the part of the timetable with the trips whose first time falls within window, e.g. to
+    // print only the morning peak. Stops none of those trips serve are left out.
+    pub fn departing_within(&self, window: ops::Range<stop_times::GtfsTime>) -> Timetable {
+        let columns = (0..self.trip_ids.len())
+            .filter(|column| self.rows.iter()
+                .find_map(|row| row.times[*column])
+                .is_some_and(|time| window.contains(&time)))
+            .collect::<Vec<_>>();
+        let mut timetable = self.select(&columns);
+        timetable.rows.retain(|row| row.times.iter().any(Option::is_some));
+        timetable
+    }
+
+    fn select(&self, columns: &[usize]) -> Timetable {
+        Timetable {
+            route_id: self.route_id.clone(),
+            direction_id: self.direction_id,
+            date: self.date,
+            trip_ids: columns.iter().map(|column| self.trip_ids[*column].clone()).collect(),
+            rows: self.rows.iter()
+                .map(|row| TimetableRow {
+                    stop_id: row.stop_id.clone(),
+                    stop_name: row.stop_name.clone(),
+                    times: columns.iter().map(|column| row.times[*column]).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    // write_csv writes the timetable as CSV, with stop_id and stop_name columns followed by a column for each trip.
+    pub fn write_csv<W: io::Write>(&self, writer: W) -> Result<(), csv::Error> {
+        let mut csv_writer = csv::Writer::from_writer(writer);
+        csv_writer.write_record(["stop_id", "stop_name"].into_iter().chain(self.trip_ids.iter().map(String::as_str)))?;
+        for row in &self.rows {
+            csv_writer.write_record(
+                [row.stop_id.clone(), row.stop_name.clone().unwrap_or_default()]
+                    .into_iter()
+                    .chain(row.times.iter().map(|time| time.map(|time| time.to_string()).unwrap_or_default()))
+            )?;
+        }
+        Ok(csv_writer.flush()?)
+    }
+}
+
+// Timetable implements Display by rendering the timetable as text, with times to the minute and a dash where a trip
+// has no time at a stop.
+impl fmt::Display for Timetable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = |row: &TimetableRow| row.stop_name.clone().unwrap_or_else(|| row.stop_id.clone());
+        let width = self.rows.iter().map(|row| label(row).chars().count()).max().unwrap_or(0);
+        for row in &self.rows {
+            write!(f, "{:<width$}", label(row), width = width)?;
+            for time in &row.times {
+                match time {
+                    Some(time) => write!(f, "  {:02}:{:02}", time.seconds() / 3600, time.seconds() / 60 % 60)?,
+                    None => write!(f, "  {:^5}", "-")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl GtfsSchedule {
+    // timetable lays out a route's trips on a date as a timetable. See ScheduleView::timetable.
+    pub fn timetable(&self, route_id: &str, direction: Option<trips::Direction>, date: chrono::NaiveDate) -> Option<Timetable> {
+        self.view().timetable(route_id, direction, date)
+    }
+}
+
+impl<'a> ScheduleView<'a> {
+    // timetable lays out the trips of a route in the view which run on date, in the given direction if there is one,
+    // as a timetable. The rows merge the stops of the route's journey patterns, starting from the most common,
+    // so every trip's stops appear in the order it visits them. Times are departure times, or arrival times where
+    // a stop time gives no departure. None is returned if the route isn't in the view.
+    pub fn timetable(&self, route_id: &str, direction: Option<trips::Direction>, date: chrono::NaiveDate) -> Option<Timetable> {
+        let route = self.route(route_id)?;
+        let patterns = self.patterns_of_route(&route.route_id)
+            .into_iter()
+            .map(|(pattern, _)| pattern)
+            .filter(|pattern| direction.is_none_or(|direction| pattern.direction_id == Some(direction)))
+            .collect::<Vec<_>>();
+
+        let mut stop_ids = Vec::<&str>::new();
+        for pattern in &patterns {
+            let mut cursor = 0;
+            for stop_id in &pattern.stop_ids {
+                cursor = match stop_ids[cursor..].iter().position(|row_stop_id| row_stop_id == stop_id) {
+                    Some(offset) => cursor + offset,
+                    None => {
+                        stop_ids.insert(cursor, stop_id);
+                        cursor
+                    },
+                } + 1;
+            }
+        }
+
+        // (trip ID, time at each row) for every column, before they are put in order.
+        let mut columns = Vec::<(String, Vec<Option<stop_times::GtfsTime>>)>::new();
+        for pattern in &patterns {
+            // the row of each of the pattern's stops, matched in order so that stops a pattern visits twice map to different rows.
+            let mut cursor = 0;
+            let rows = pattern.stop_ids.iter()
+                .map(|stop_id| {
+                    cursor += stop_ids[cursor..].iter().position(|row_stop_id| row_stop_id == stop_id).unwrap_or(0) + 1;
+                    cursor - 1
+                })
+                .collect::<Vec<_>>();
+
+            for trip in pattern.trip_ids.iter().filter_map(|trip_id| self.trip(trip_id)) {
+                if !self.schedule.service_runs_on(&trip.service_id, date) {
+                    continue;
+                }
+                let mut trip_stop_times = self.schedule.stop_times.stop_times.get(&trip.trip_id).into_iter().flatten().collect::<Vec<_>>();
+                trip_stop_times.sort_by_key(|stop_time| stop_time.stop_sequence);
+                let mut times = vec![None; stop_ids.len()];
+                for (row, stop_time) in rows.iter().zip(trip_stop_times.iter().filter(|stop_time| stop_time.stop_id.is_some())) {
+                    times[*row] = stop_time.departure_time.or(stop_time.arrival_time);
+                }
+
+                match self.schedule.frequencies.frequencies.get(&trip.trip_id) {
+                    None => columns.push((trip.trip_id.clone(), times)),
+                    Some(frequencies) => {
+                        let first_time = times.iter().flatten().min().map_or(0, |time| time.seconds());
+                        for start_time in frequencies.iter().flat_map(|frequency| frequency.start_times()) {
+                            columns.push((
+                                format!("{}@{}", trip.trip_id, start_time),
+                                times.iter()
+                                    .map(|time| time.map(|time| stop_times::GtfsTime::from_seconds(
+                                        (time.seconds() + start_time.seconds()).saturating_sub(first_time)
+                                    )))
+                                    .collect(),
+                            ));
+                        }
+                    },
+                }
+            }
+        }
+
+        // columns are ordered by the earliest time each gives, which is normally the trip's departure from its first stop.
+        columns.sort_by(|(a_id, a), (b_id, b)| a.iter().flatten().min().cmp(&b.iter().flatten().min()).then_with(|| a_id.cmp(b_id)));
+
+        Some(Timetable {
+            route_id: route.route_id.clone(),
+            direction_id: direction,
+            date,
+            trip_ids: columns.iter().map(|(trip_id, _)| trip_id.clone()).collect(),
+            rows: stop_ids.iter()
+                .enumerate()
+                .map(|(row, stop_id)| TimetableRow {
+                    stop_id: stop_id.to_string(),
+                    stop_name: self.schedule.stops.stops.get(*stop_id)
+                        .and_then(|stop| stop.get_stop_name())
+                        .map(|stop_name| stop_name.to_string()),
+                    times: columns.iter().map(|(_, times)| times[row]).collect(),
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::testing;
+
+    fn times(times: &[Option<&str>]) -> Vec<Option<stop_times::GtfsTime>> {
+        times.iter().map(|time| time.map(testing::time)).collect()
+    }
+
+    fn timetable(schedule: &GtfsSchedule) -> Timetable {
+        schedule.timetable("R2", None, testing::date(2025, 6, 2)).unwrap()
+    }
+
+    #[test]
+    fn trips_are_laid_out_in_order_of_departure() {
+        let timetable = timetable(&testing::schedule());
+        assert_eq!(timetable.trip_ids, vec!["T3", "T2", "T4", "T5"]);
+        assert_eq!(timetable.rows, vec![
+            TimetableRow {
+                stop_id: "S2".to_string(),
+                stop_name: Some("S2".to_string()),
+                times: times(&[Some("08:12:00"), Some("08:15:00"), Some("09:30:00"), Some("25:30:00")]),
+            },
+            TimetableRow {
+                stop_id: "Y".to_string(),
+                stop_name: Some("Y".to_string()),
+                times: times(&[Some("08:27:00"), Some("08:30:00"), Some("09:45:00"), Some("25:45:00")]),
+            },
+        ]);
+    }
+
+    #[test]
+    fn rows_merge_the_stops_of_every_pattern() {
+        let mut schedule = testing::schedule();
+        let stop_times = testing::trip_stop_times("T4", &[("S2", "09:30:00"), ("X", "09:35:00"), ("Y", "09:45:00")]);
+        schedule.stop_times.stop_times.insert("T4".to_string(), stop_times);
+        let timetable = timetable(&schedule);
+        assert_eq!(timetable.rows.iter().map(|row| row.stop_id.as_str()).collect::<Vec<_>>(), vec!["S2", "X", "Y"]);
+        assert_eq!(timetable.rows[1].times, times(&[None, None, Some("09:35:00"), None]));
+    }
+
+    #[test]
+    fn trips_run_by_a_frequency_take_a_column_each_time_they_start() {
+        let mut schedule = testing::schedule();
+        schedule.frequencies = testing::load("trip_id,start_time,end_time,headway_secs\nT2,06:00:00,07:00:00,1800\n");
+        let timetable = timetable(&schedule);
+        assert_eq!(timetable.trip_ids, vec!["T2@06:00:00", "T2@06:30:00", "T3", "T4", "T5"]);
+        assert_eq!(timetable.rows[1].times[..2], times(&[Some("06:15:00"), Some("06:45:00")]));
+    }
+
+    #[test]
+    fn trips_not_running_on_the_date_are_left_out() {
+        let mut schedule = testing::schedule();
+        schedule.trips.trips.get_mut("T4").unwrap().service_id = "W".to_string();
+        assert_eq!(timetable(&schedule).trip_ids, vec!["T3", "T2", "T5"]);
+        assert_eq!(schedule.timetable("R3", None, testing::date(2025, 6, 2)), None);
+    }
+
+    #[test]
+    fn departing_within_keeps_the_trips_starting_in_the_window() {
+        let timetable = timetable(&testing::schedule()).departing_within(testing::time("08:00:00")..testing::time("09:30:00"));
+        assert_eq!(timetable.trip_ids, vec!["T3", "T2"]);
+        assert_eq!(timetable.rows[0].times, times(&[Some("08:12:00"), Some("08:15:00")]));
+        assert_eq!(timetable.columns(1..10).trip_ids, vec!["T2"]);
+    }
+
+    #[test]
+    fn timetables_are_written_as_csv() {
+        let timetable = timetable(&testing::schedule()).columns(0..2);
+        let mut text = Vec::new();
+        timetable.write_csv(&mut text).unwrap();
+        assert_eq!(String::from_utf8(text).unwrap(), "stop_id,stop_name,T3,T2\nS2,S2,08:12:00,08:15:00\nY,Y,08:27:00,08:30:00\n");
+        assert_eq!(timetable.to_string(), "S2  08:12  08:15\nY   08:27  08:30\n");
+    }
+}
@@ -0,0 +1,370 @@
+use csv;
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::departures::Departure;
+use crate::gtfs::error::CsvError;
+use crate::gtfs::de;
+use crate::gtfs::stop_times;
+use crate::gtfs::table;
+use crate::gtfs::trips;
+use crate::gtfs::view::ScheduleView;
+use serde::Deserialize;
+use std::io;
+use std::iter;
+use std::collections;
+use std::fmt;
+use std::str::FromStr;
+use std::time;
+
+// Transfers is a collection of transfers, indexed by from_stop_id.
+// Transfers without a from_stop_id, such as in-seat transfers between trips, are indexed by the empty string.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transfers {
+    pub transfers: std::collections::HashMap<String, Vec<Transfer>>
+}
+
+impl Transfers {
+    pub fn iter(&self) -> impl Iterator<Item = &Transfer> {
+        self.transfers.values().flatten()
+    }
+
+    // with_id_prefix prepends prefix to every ID referenced by a transfer.
+    pub fn with_id_prefix(self, prefix: &str) -> Self {
+        self.transfers.into_values()
+            .flatten()
+            .map(|mut transfer| {
+                transfer.prefix_ids(prefix);
+                transfer
+            })
+            .collect()
+    }
+
+    // from_stop yields the transfers listed from the given stop.
+    pub fn from_stop<'a>(&'a self, stop_id: &str) -> impl Iterator<Item = &'a Transfer> + 'a {
+        self.transfers.get(stop_id).into_iter().flatten()
+    }
+}
+
+// Transfers implements FromIterator<Transfer> so that it can be assembled in memory, grouping records by from_stop_id.
+impl iter::FromIterator<Transfer> for Transfers {
+    fn from_iter<I: IntoIterator<Item = Transfer>>(iter: I) -> Self {
+        let mut transfers = Transfers::default();
+        for transfer in iter {
+            table::GtfsTable::insert(&mut transfers, <Transfers as table::GtfsTable>::key(&transfer), transfer);
+        }
+        transfers
+    }
+}
+
+// Transfers implements TryFrom<csv::Reader<R>> by loading the reader's contents as a GtfsTable.
+impl<R: io::Read> TryFrom<csv::Reader<R>> for Transfers {
+    type Error = CsvError;
+
+    fn try_from(r: csv::Reader<R>) -> Result<Self, Self::Error> {
+        table::load(r)
+    }
+}
+
+// Transfers holds the records of transfers.txt, grouped by from_stop_id in the order they are listed.
+impl table::GtfsTable for Transfers {
+    type Record = Transfer;
+
+    const FILE_NAME: &'static str = "transfers.txt";
+
+    fn key(transfer: &Transfer) -> String {
+        transfer.from_stop_id.clone().unwrap_or_default()
+    }
+
+    fn insert(&mut self, from_stop_id: String, transfer: Transfer) {
+        self.transfers.entry(from_stop_id).or_default().push(transfer);
+    }
+}
+
+// Transfer describes how riders may change between trips, routes or stops. The more of its from_ and to_ fields are set,
+// the more specific it is; see Transfer::specificity.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Transfer {
+    #[serde(default, deserialize_with = "de::optional")]
+    pub from_stop_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub to_stop_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub from_route_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub to_route_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub from_trip_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub to_trip_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
+    pub transfer_type: Option<TransferType>,
+    // min_transfer_time is in seconds.
+    #[serde(default, deserialize_with = "de::optional")]
+    pub min_transfer_time: Option<u32>,
+}
+
+impl Transfer {
+    // transfer_type returns the kind of transfer, which is TransferType::Recommended when left empty.
+    pub fn transfer_type(&self) -> TransferType {
+        self.transfer_type.unwrap_or(TransferType::Recommended)
+    }
+
+    // specificity ranks how precisely the transfer identifies the trips it applies to, following the GTFS reference:
+    // a transfer between trips is the most specific, then between a trip and a route, between routes, and between stops.
+    pub fn specificity(&self) -> u8 {
+        let level = |trip_id: &Option<String>, route_id: &Option<String>| match (trip_id, route_id) {
+            (Some(_), _) => 2,
+            (None, Some(_)) => 1,
+            (None, None) => 0,
+        };
+        level(&self.from_trip_id, &self.from_route_id) + level(&self.to_trip_id, &self.to_route_id)
+    }
+
+    // prefix_ids prepends prefix to every ID the transfer references.
+    pub fn prefix_ids(&mut self, prefix: &str) {
+        [
+            &mut self.from_stop_id, &mut self.to_stop_id, &mut self.from_route_id,
+            &mut self.to_route_id, &mut self.from_trip_id, &mut self.to_trip_id,
+        ]
+            .into_iter()
+            .flatten()
+            .for_each(|id| id.insert_str(0, prefix));
+    }
+
+    // applies_to reports whether the transfer governs changing from one trip to another at the given stops. Stop, route
+    // and trip IDs the transfer leaves empty match any; a stop ID also matches the stops within the station it names.
+    fn applies_to(&self, from: (&trips::Trip, &[&str]), to: (&trips::Trip, &[&str])) -> bool {
+        let matches = |id: &Option<String>, candidates: &[&str]| id.as_deref().is_none_or(|id| candidates.contains(&id));
+        matches(&self.from_stop_id, from.1)
+            && matches(&self.to_stop_id, to.1)
+            && matches(&self.from_route_id, &[from.0.route_id.as_str()])
+            && matches(&self.to_route_id, &[to.0.route_id.as_str()])
+            && matches(&self.from_trip_id, &[from.0.trip_id.as_str()])
+            && matches(&self.to_trip_id, &[to.0.trip_id.as_str()])
+    }
+
+    // required_time returns the time riders need to make the transfer, or None if it isn't possible.
+    // Only minimum time transfers give a time; timed and in-seat transfers are held for riders.
+    pub fn required_time(&self) -> Option<time::Duration> {
+        match self.transfer_type() {
+            TransferType::NotPossible => None,
+            TransferType::MinimumTime => Some(time::Duration::from_secs(self.min_transfer_time.unwrap_or(0) as u64)),
+            _ => Some(time::Duration::ZERO),
+        }
+    }
+}
+
+// Connection is an onward departure which a rider arriving on another trip can make.
+#[derive(Debug, Clone, Copy)]
+pub struct Connection<'a> {
+    pub departure: Departure<'a>,
+    // transfer is the most specific transfer governing the connection, if transfers.txt gives one.
+    pub transfer: Option<&'a Transfer>,
+    // slack is the time to spare once the transfer's required time has been allowed for.
+    pub slack: time::Duration,
+}
+
+impl GtfsSchedule {
+    // feasible_connections lists the departures a rider arriving with the given stop time can make. See ScheduleView::feasible_connections.
+    pub fn feasible_connections(&self, arrival: &stop_times::StopTime, date: chrono::NaiveDate, window: time::Duration) -> Vec<Connection<'_>> {
+        self.view().feasible_connections(arrival, date, window)
+    }
+}
+
+impl<'a> ScheduleView<'a> {
+    // feasible_connections lists the departures in the view which a rider arriving with the given stop time on the
+    // given service date can make, within window of arriving, in order of departure time. Departures are taken from
+    // anywhere in the station complex of the arrival stop, and from stops transfers.txt links it to.
+    // Where transfers.txt governs a connection, the most specific applicable transfer decides how much time it needs
+    // and whether it is possible at all; otherwise a departure at or after the arrival time can be made.
+    // Nothing is listed if the stop time has no stop or time, or its trip isn't in the view.
+    pub fn feasible_connections(&self, arrival: &stop_times::StopTime, date: chrono::NaiveDate, window: time::Duration) -> Vec<Connection<'a>> {
+        let (Some(stop_id), Some(arrival_time), Some(arrival_trip)) = (
+            arrival.stop_id.as_deref(),
+            arrival.arrival_time.or(arrival.departure_time),
+            self.trip(&arrival.trip_id),
+        ) else {
+            return Vec::new();
+        };
+        let transfers = &self.schedule.transfers;
+        let stop_and_station = |stop_id: &'a str| {
+            let station = self.schedule.stops.stops.get(stop_id).and_then(|stop| stop.parent_station());
+            [Some(stop_id), station].into_iter().flatten().collect::<Vec<_>>()
+        };
+        let Some(stop_id) = self.stop(stop_id).map(|stop| stop.stop_id.as_str()) else {
+            return Vec::new();
+        };
+        let from_stop_ids = stop_and_station(stop_id);
+        let candidates = from_stop_ids.iter()
+            .flat_map(|from_stop_id| transfers.from_stop(from_stop_id))
+            .chain(transfers.from_stop(""))
+            .collect::<Vec<_>>();
+
+        // the arrival stop's station complex, plus the stops transfers lead to from it.
+        let complex = *from_stop_ids.last().unwrap();
+        let complex_stop_ids = self.stops_within(complex);
+        let mut departure_stop_ids = vec![complex];
+        departure_stop_ids.extend(
+            candidates.iter()
+                .filter_map(|transfer| transfer.to_stop_id.as_deref())
+                .filter(|to_stop_id| !complex_stop_ids.contains(to_stop_id))
+        );
+        departure_stop_ids.sort();
+        departure_stop_ids.dedup();
+
+        let latest = arrival_time.seconds() as u64 + window.as_secs();
+        let mut connections = departure_stop_ids.into_iter()
+            .flat_map(|departure_stop_id| self.departures_at(departure_stop_id, date, arrival_time, usize::MAX))
+            .filter(|departure| departure.trip.trip_id != arrival_trip.trip_id && departure.departure_time.seconds() as u64 <= latest)
+            .filter_map(|departure| {
+                let to_stop_ids = stop_and_station(departure.stop_time.stop_id.as_deref()?);
+                let transfer = candidates.iter()
+                    .filter(|transfer| transfer.applies_to((arrival_trip, &from_stop_ids), (departure.trip, &to_stop_ids)))
+                    .max_by_key(|transfer| transfer.specificity())
+                    .copied();
+                // without a transfer, only departures from the arrival stop's station complex can be made.
+                if transfer.is_none() && !complex_stop_ids.contains(to_stop_ids[0]) {
+                    return None;
+                }
+                let required_time = transfer.map_or(Some(time::Duration::ZERO), Transfer::required_time)?;
+                let available = time::Duration::from_secs((departure.departure_time.seconds() - arrival_time.seconds()) as u64);
+                Some(Connection { departure, transfer, slack: available.checked_sub(required_time)? })
+            })
+            .collect::<Vec<_>>();
+        connections.sort_by(|a, b| a.departure.departure_time.cmp(&b.departure.departure_time)
+            .then_with(|| a.departure.trip.trip_id.cmp(&b.departure.trip.trip_id)));
+        connections
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferType {
+    Recommended,
+    Timed,
+    MinimumTime,
+    NotPossible,
+    InSeat,
+    InSeatNotAllowed,
+}
+
+#[derive(Debug)]
+pub struct TransferTypeLoadError(String);
+
+impl fmt::Display for TransferTypeLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid transfer_type '{}'", self.0)
+    }
+}
+
+impl std::error::Error for TransferTypeLoadError {}
+
+impl TransferType {
+    // code returns the value used to encode this transfer type in GTFS files.
+    pub fn code(&self) -> u8 {
+        match self {
+            TransferType::Recommended => 0,
+            TransferType::Timed => 1,
+            TransferType::MinimumTime => 2,
+            TransferType::NotPossible => 3,
+            TransferType::InSeat => 4,
+            TransferType::InSeatNotAllowed => 5,
+        }
+    }
+
+    // name describes the transfer type in a few words.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransferType::Recommended => "recommended",
+            TransferType::Timed => "timed",
+            TransferType::MinimumTime => "minimum time",
+            TransferType::NotPossible => "not possible",
+            TransferType::InSeat => "in-seat",
+            TransferType::InSeatNotAllowed => "in-seat not allowed",
+        }
+    }
+}
+
+impl FromStr for TransferType {
+    type Err = TransferTypeLoadError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" => Ok(TransferType::Recommended),
+            "1" => Ok(TransferType::Timed),
+            "2" => Ok(TransferType::MinimumTime),
+            "3" => Ok(TransferType::NotPossible),
+            "4" => Ok(TransferType::InSeat),
+            "5" => Ok(TransferType::InSeatNotAllowed),
+            _ => Err(TransferTypeLoadError(s.to_string())),
+        }
+    }
+}
+
+// the fields of transfers.txt, in the order they are listed by the GTFS reference.
+pub const TRANSFER_FIELDS: [&str; 8] = [
+    "from_stop_id", "to_stop_id", "from_route_id", "to_route_id", "from_trip_id", "to_trip_id",
+    "transfer_type", "min_transfer_time",
+];
+
+// collections::HashMap<String, String> implements From<&Transfer> by encoding each field as it would appear in transfers.txt.
+impl From<&Transfer> for collections::HashMap<String, String> {
+    fn from(transfer: &Transfer) -> Self {
+        [
+            ("from_stop_id", transfer.from_stop_id.clone()),
+            ("to_stop_id", transfer.to_stop_id.clone()),
+            ("from_route_id", transfer.from_route_id.clone()),
+            ("to_route_id", transfer.to_route_id.clone()),
+            ("from_trip_id", transfer.from_trip_id.clone()),
+            ("to_trip_id", transfer.to_trip_id.clone()),
+            ("transfer_type", Some(transfer.transfer_type().code().to_string())),
+            ("min_transfer_time", transfer.min_transfer_time.map(|min_transfer_time| min_transfer_time.to_string())),
+        ]
+            .into_iter()
+            .filter_map(|(field, value)| value.map(|value| (field.to_string(), value)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::testing;
+
+    // connections lists the trips a rider arriving at S1 on T1 can connect to within half an hour, with the slack of each in minutes.
+    fn connections(schedule: &GtfsSchedule) -> Vec<(String, u64)> {
+        let arrival = &schedule.stop_times.stop_times["T1"][1];
+        schedule.feasible_connections(arrival, testing::date(2025, 3, 10), time::Duration::from_secs(1800))
+            .into_iter()
+            .map(|connection| (connection.departure.trip.trip_id.clone(), connection.slack.as_secs() / 60))
+            .collect()
+    }
+
+    #[test]
+    fn connections_within_the_station_need_no_transfer() {
+        let schedule = testing::schedule();
+        assert_eq!(connections(&schedule), vec![("T3".to_string(), 2), ("T2".to_string(), 5)]);
+    }
+
+    #[test]
+    fn minimum_time_transfers_rule_out_tight_connections() {
+        let mut schedule = testing::schedule();
+        schedule.transfers = testing::load("from_stop_id,to_stop_id,transfer_type,min_transfer_time\nS,S,2,240\n");
+        assert_eq!(connections(&schedule), vec![("T2".to_string(), 1)]);
+    }
+
+    #[test]
+    fn the_most_specific_transfer_applies() {
+        let mut schedule = testing::schedule();
+        schedule.transfers = testing::load("from_stop_id,to_stop_id,from_trip_id,to_trip_id,transfer_type\nS,S,,,3\nS1,S2,T1,T3,1\n");
+        assert_eq!(connections(&schedule), vec![("T3".to_string(), 2)]);
+    }
+
+    #[test]
+    fn transfers_lead_to_stops_outside_the_station() {
+        let mut schedule = testing::schedule();
+        schedule.stop_times.stop_times.get_mut("T4").unwrap()[0].stop_id = Some("Y".to_string());
+        schedule.stop_times.stop_times.get_mut("T4").unwrap()[0].departure_time = Some(testing::time("08:20:00"));
+        assert_eq!(connections(&schedule).len(), 2);
+        schedule.transfers = testing::load("from_stop_id,to_stop_id,transfer_type,min_transfer_time\nS1,Y,2,300\n");
+        assert_eq!(connections(&schedule), vec![("T3".to_string(), 2), ("T2".to_string(), 5), ("T4".to_string(), 5)]);
+    }
+}
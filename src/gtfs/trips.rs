@@ -1,12 +1,17 @@
 use csv;
+use crate::gtfs::error::CsvError;
+use crate::gtfs::de;
+use crate::gtfs::stop_times;
+use crate::gtfs::table;
+use serde::Deserialize;
 use std::io;
 use std::iter;
 use std::collections;
-use std::fmt;
 use std::str::FromStr;
+use std::time;
 
 // Trips is a collection of trips, indexed by trip_id.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Trips {
     pub trips: std::collections::HashMap<String, Trip>
 }
@@ -29,99 +34,155 @@ impl iter::IntoIterator for Trips {
     }
 }
 
-// TripsCsvLoadError is an error that occurs when loading trips from a CSV file.
-pub enum TripsCsvLoadError {
-    NoHeader,
-    TripLoadError(TripLoadError),
-    CSVReadError(csv::Error)
+impl Trips {
+    // with_id_prefix prepends prefix to every trip ID, and to every ID referenced by a trip.
+    pub fn with_id_prefix(self, prefix: &str) -> Self {
+        Trips {
+            trips: self.trips.into_values()
+                .map(|mut trip| {
+                    trip.prefix_ids(prefix);
+                    (trip.trip_id.clone(), trip)
+                })
+                .collect()
+        }
+    }
 }
 
-impl fmt::Display for TripsCsvLoadError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::NoHeader => write!(f, "No header found"),
-            Self::TripLoadError(e) => write!(f, "Error loading trip: {}", e),
-            Self::CSVReadError(e) => write!(f, "Error reading CSV: {}", e)
+// Trips implements FromIterator<Trip> so that it can be assembled in memory, indexing each trip by its ID.
+impl iter::FromIterator<Trip> for Trips {
+    fn from_iter<I: IntoIterator<Item = Trip>>(iter: I) -> Self {
+        Trips {
+            trips: iter.into_iter().map(|trip| (trip.trip_id.clone(), trip)).collect()
         }
     }
 }
 
-// Trips implements TryFrom<csv::Reader<R>> by attempting to consume and read from a csv::Reader<R>.
+// Trips implements TryFrom<csv::Reader<R>> by loading the reader's contents as a GtfsTable.
 impl<R: io::Read> TryFrom<csv::Reader<R>> for Trips {
-    // The error type for this function is TripsCsvLoadError.
-    type Error = TripsCsvLoadError;
-
-    // try_from consumes the csv::Reader<R> and returns a Result holding a Routes object, or a RoutesCsvLoadError.
-    fn try_from(mut r: csv::Reader<R>) -> Result<Self, Self::Error> {
-        // try to get the headers; if there are no headers, return a TripsCsvLoadError::NoHeader.
-        r.headers().cloned().map_err(|_| TripsCsvLoadError::NoHeader).and_then(
-            // if there are headers, try to create a Trips object from the remaining records.
-            |header|
-            Ok(Trips {
-                // to create the actual collection of trips, we need to iterate over the records
-                trips: r.into_records()
-                    // and fold them into an overarching result containing the collection.
-                    .fold(
-                        Ok(collections::HashMap::new()),
-                        // at each stage of the fold,
-                        |trips_result, record_result|
-                        // proceed only if the running result is Ok.
-                        trips_result
-                        .and_then(|mut trips|
-                            // if there was an error reading this record, return that error.
-                            record_result.map_err(TripsCsvLoadError::CSVReadError)
-                                .and_then(
-                                    // otherwise, try to create a Trip object from the record.
-                                    |record|
-                                    Trip::try_from(
-                                        // Zip the header and record together,
-                                        iter::zip(
-                                            header.iter().map(|s| s.to_string()),
-                                            record.iter().map(|s| s.to_string())
-                                        )
-                                        // and collect the results into a HashMap.
-                                        .collect::<collections::HashMap<String, String>>()
-                                    )
-                                    // if there is an error creating the Trip object from the HashMap, return that error.
-                                    .map_err(|err| TripsCsvLoadError::TripLoadError(err))
-                            )
-                            .map(
-                                |trip| {
-                                    // insert the Trip object into the HashMap.
-                                    trips.insert(trip.trip_id.clone(), trip);
-                                    // return the updated HashMap.
-                                    trips
-                                }
-                            )
-                        )
-                    // extract the HashMap from the Result, or return the error.
-                    )?
-            })
-        )
+    type Error = CsvError;
+
+    fn try_from(r: csv::Reader<R>) -> Result<Self, Self::Error> {
+        table::load(r)
+    }
+}
+
+// Trips holds the records of trips.txt, indexed by trip_id.
+impl table::GtfsTable for Trips {
+    type Record = Trip;
+
+    const FILE_NAME: &'static str = "trips.txt";
+
+    fn key(trip: &Trip) -> String {
+        trip.trip_id.clone()
+    }
+
+    fn insert(&mut self, trip_id: String, trip: Trip) {
+        self.trips.insert(trip_id, trip);
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct Trip {
+    #[serde(deserialize_with = "de::required")]
     pub trip_id: String,
+    #[serde(deserialize_with = "de::required")]
     pub route_id: String,
+    #[serde(deserialize_with = "de::required")]
     pub service_id: String,
+    #[serde(default, deserialize_with = "de::optional")]
     pub trip_headsign: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
     pub trip_short_name: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
     pub direction_id: Option<Direction>,
+    #[serde(default, deserialize_with = "de::optional")]
     pub block_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional")]
     pub shape_id: Option<String>,
+    #[serde(default, deserialize_with = "de::optional_accessibility")]
     pub wheelchair_accessible: Option<bool>,
+    #[serde(default, deserialize_with = "de::optional_accessibility")]
     pub bikes_allowed: Option<bool>,
 }
 
+impl Trip {
+    // new creates a trip with the given IDs, leaving every optional field unset.
+    pub fn new(trip_id: &str, route_id: &str, service_id: &str) -> Self {
+        Trip {
+            trip_id: trip_id.to_string(),
+            route_id: route_id.to_string(),
+            service_id: service_id.to_string(),
+            trip_headsign: None,
+            trip_short_name: None,
+            direction_id: None,
+            block_id: None,
+            shape_id: None,
+            wheelchair_accessible: None,
+            bikes_allowed: None,
+        }
+    }
+
+    // prefix_ids prepends prefix to this trip's ID and to every ID it references.
+    pub fn prefix_ids(&mut self, prefix: &str) {
+        self.trip_id.insert_str(0, prefix);
+        self.route_id.insert_str(0, prefix);
+        self.service_id.insert_str(0, prefix);
+        if let Some(block_id) = self.block_id.as_mut() {
+            block_id.insert_str(0, prefix);
+        }
+        if let Some(shape_id) = self.shape_id.as_mut() {
+            shape_id.insert_str(0, prefix);
+        }
+    }
+
+    // travel_time returns how long this trip takes from departing from_stop to arriving at to_stop,
+    // or None if it doesn't visit to_stop after from_stop, or either stop time lacks a time.
+    // If the trip visits from_stop more than once, the last visit before reaching to_stop is used.
+    pub fn travel_time(&self, stop_times: &stop_times::StopTimes, from_stop: &str, to_stop: &str) -> Option<time::Duration> {
+        let mut trip_stop_times = stop_times.stop_times.get(&self.trip_id)?.iter().collect::<Vec<_>>();
+        trip_stop_times.sort_by_key(|stop_time| stop_time.stop_sequence);
+
+        let mut boarding = None;
+        for stop_time in trip_stop_times {
+            match boarding {
+                Some(boarding) if stop_time.stop_id.as_deref() == Some(to_stop) => {
+                    return travel_time_between(boarding, stop_time);
+                },
+                _ if stop_time.stop_id.as_deref() == Some(from_stop) => boarding = Some(stop_time),
+                _ => (),
+            }
+        }
+        None
+    }
+}
+
+// travel_time_between returns the time taken from departing from one stop time to arriving at another,
+// falling back to the arrival and departure times respectively where the others aren't given.
+pub fn travel_time_between(from: &stop_times::StopTime, to: &stop_times::StopTime) -> Option<time::Duration> {
+    let departure_time = from.departure_time.or(from.arrival_time)?;
+    let arrival_time = to.arrival_time.or(to.departure_time)?;
+    arrival_time.seconds()
+        .checked_sub(departure_time.seconds())
+        .map(|seconds| time::Duration::from_secs(seconds as u64))
+}
+
 // represents two arbitrary opposing directions
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Direction {
     A,
     B
 }
 
+impl Direction {
+    // code returns the value used to encode this direction in GTFS files.
+    pub fn code(&self) -> u8 {
+        match self {
+            Direction::A => 0,
+            Direction::B => 1,
+        }
+    }
+}
+
 impl FromStr for Direction {
     type Err = String;
 
@@ -134,91 +195,30 @@ impl FromStr for Direction {
     }
 }
 
-pub enum TripLoadError {
-    TripIdRequired,
-    RouteIdRequired,
-    ServiceIdRequired,
-    TripHeadsignError(String),
-    TripShortNameError(String),
-    DirectionIdError(String),
-    BlockIdError(String),
-    ShapeIdRequired,
-    WheelchairAccessibleError(String),
-    BikesAllowedError(String),
-}
-
-impl fmt::Display for TripLoadError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::TripIdRequired => write!(f, "trip_id is required"),
-            Self::RouteIdRequired => write!(f, "route_id is required"),
-            Self::ServiceIdRequired => write!(f, "service_id is required"),
-            Self::TripHeadsignError(e) => write!(f, "Error parsing trip headsign: {}", e),
-            Self::TripShortNameError(e) => write!(f, "Error parsing trip short name: {}", e),
-            Self::DirectionIdError(e) => write!(f, "Error parsing direction id: {}", e),
-            Self::BlockIdError(e) => write!(f, "Error parsing block id: {}", e),
-            Self::ShapeIdRequired => write!(f, "shape_id is required"),
-            Self::WheelchairAccessibleError(e) => write!(f, "Error parsing wheelchair accessible: {}", e),
-            Self::BikesAllowedError(e) => write!(f, "Error parsing bikes allowed: {}", e),
-        }
+// the fields of trips.txt, in the order they are listed by the GTFS reference.
+pub const TRIP_FIELDS: [&str; 10] = [
+    "route_id", "service_id", "trip_id", "trip_headsign", "trip_short_name", "direction_id", "block_id",
+    "shape_id", "wheelchair_accessible", "bikes_allowed",
+];
+
+// collections::HashMap<String, String> implements From<&Trip> by encoding each field as it would appear in trips.txt.
+// This is the inverse of Trip's Deserialize implementation.
+impl From<&Trip> for collections::HashMap<String, String> {
+    fn from(trip: &Trip) -> Self {
+        [
+            ("route_id", Some(trip.route_id.clone())),
+            ("service_id", Some(trip.service_id.clone())),
+            ("trip_id", Some(trip.trip_id.clone())),
+            ("trip_headsign", trip.trip_headsign.clone()),
+            ("trip_short_name", trip.trip_short_name.clone()),
+            ("direction_id", trip.direction_id.as_ref().map(|direction| direction.code().to_string())),
+            ("block_id", trip.block_id.clone()),
+            ("shape_id", trip.shape_id.clone()),
+            ("wheelchair_accessible", trip.wheelchair_accessible.map(|b| if b { "1" } else { "2" }.to_string())),
+            ("bikes_allowed", trip.bikes_allowed.map(|b| if b { "1" } else { "2" }.to_string())),
+        ]
+            .into_iter()
+            .filter_map(|(field, value)| value.map(|value| (field.to_string(), value)))
+            .collect()
     }
 }
-
-// Route implements TryFrom<collections::HashMap<String, String>> by interpreting the keys as field names, and
-// the values as string-encoded values for those fields.
-impl TryFrom<collections::HashMap<String, String>> for Trip {
-    type Error = TripLoadError;
-
-    fn try_from(fields: collections::HashMap<String, String>) -> Result<Self, Self::Error> {
-        Ok(Trip {
-            trip_id: fields.get("trip_id")
-                .filter(|s| !s.is_empty())
-                .ok_or(TripLoadError::TripIdRequired)?
-                .clone(),
-            route_id: fields.get("route_id")
-                .filter(|s| !s.is_empty())
-                .ok_or(TripLoadError::RouteIdRequired)?
-                .clone(),
-            service_id: fields.get("service_id")
-                .filter(|s| !s.is_empty())
-                .ok_or(TripLoadError::ServiceIdRequired)?
-                .clone(),
-            trip_headsign: fields.get("trip_headsign").filter(|s| !s.is_empty()).cloned(),
-            trip_short_name: fields.get("trip_short_name").filter(|s| !s.is_empty()).cloned(),
-            direction_id: match fields.get("direction_id")
-                .filter(|s| !s.is_empty())
-                .map(|s| s.parse::<Direction>())
-            {
-                Some(Ok(direction)) => Some(direction),
-                Some(Err(e)) => return Err(TripLoadError::DirectionIdError(e)),
-                None => None
-            },
-            block_id: fields.get("block_id").filter(|s| !s.is_empty()).cloned(),
-            shape_id: fields.get("shape_id")
-                .filter(|s| !s.is_empty())
-                .cloned(),
-            wheelchair_accessible: match fields.get("wheelchair_accessible")
-                    .filter(|s| !s.is_empty())
-                {
-                    None => Ok(None),
-                    Some(s) => match s.as_str() {
-                        "0" => Ok(None),
-                        "1" => Ok(Some(true)),
-                        "2" => Ok(Some(false)),
-                        _ => Err(TripLoadError::WheelchairAccessibleError(s.clone()))
-                    }
-                }?,
-            bikes_allowed: match fields.get("bikes_allowed")
-                    .filter(|s| !s.is_empty()) 
-                {
-                    None => Ok(None),
-                    Some(s) => match s.as_str() {
-                        "0" => Ok(None),
-                        "1" => Ok(Some(true)),
-                        "2" => Ok(Some(false)),
-                        _ => Err(TripLoadError::BikesAllowedError(s.clone()))
-                    }
-                }?,
-        })
-    }
-}
\ No newline at end of file
@@ -0,0 +1,174 @@
+use crate::gtfs::stops::LocationTypeDetails;
+use crate::gtfs::GtfsSchedule;
+use std::collections;
+use std::fmt;
+
+// MIN_CONTRAST is the least contrast a route's text color should have against its color, as a WCAG contrast ratio,
+// that for large text, since route names are usually shown on signs and badges.
+const MIN_CONTRAST: f64 = 3.0;
+
+// Severity is how much a finding matters. Errors break the feed for those who consume it, or contradict the GTFS
+// reference; warnings are likely to be mistakes; info notes what's unusual but allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+}
+
+// Finding is something validate found in a feed: how much it matters, the file and record it's about, identified as
+// in DanglingReference, and what's wrong.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub file: &'static str,
+    pub record: String,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.file, self.record, self.message)
+    }
+}
+
+// ValidationReport lists the findings of validate, errors first.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub findings: Vec<Finding>,
+}
+
+impl ValidationReport {
+    pub fn of_severity(&self, severity: Severity) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(move |finding| finding.severity == severity)
+    }
+
+    // passes reports whether the feed has no errors, nor warnings if strict, as a publishing pipeline would require.
+    pub fn passes(&self, strict: bool) -> bool {
+        self.findings.iter().all(|finding| match finding.severity {
+            Severity::Error => false,
+            Severity::Warning => !strict,
+            Severity::Info => true,
+        })
+    }
+}
+
+impl GtfsSchedule {
+    // validate checks the schedule against the GTFS reference and for common mistakes. Errors are dangling
+    // references, trips whose service is defined nowhere, stop times missing the times the first and last stops
+    // require, and times which go backwards; warnings are trips with fewer than two stops, services which never run
+    // and route text colors hard to read against the route's color; info notes routes without trips and stops no
+    // trip visits.
+    pub fn validate(&self) -> ValidationReport {
+        let mut findings = Vec::new();
+        let mut find = |severity: Severity, file: &'static str, record: String, message: String| {
+            findings.push(Finding { severity, file, record, message });
+        };
+
+        for reference in self.dangling_references().dangling {
+            find(Severity::Error, reference.file, reference.record, format!("{} {} doesn't exist", reference.field, reference.target));
+        }
+
+        for trip in &self.trips {
+            let defined = self.calendar.services.contains_key(&trip.service_id)
+                || self.calendar_dates.calendar_dates.contains_key(&trip.service_id);
+            if !defined {
+                find(Severity::Error, "trips.txt", trip.trip_id.clone(), format!("service_id {} is in neither calendar.txt nor calendar_dates.txt", trip.service_id));
+            }
+            let mut stop_times = self.stop_times.stop_times.get(&trip.trip_id).map_or(Vec::new(), |stop_times| stop_times.iter().collect());
+            stop_times.sort_by_key(|stop_time| stop_time.stop_sequence);
+            if stop_times.len() < 2 {
+                let message = match stop_times.len() {
+                    0 => "has no stop times; a trip needs at least two".to_string(),
+                    _ => "has only one stop time; a trip needs at least two".to_string(),
+                };
+                find(Severity::Warning, "trips.txt", trip.trip_id.clone(), message);
+            }
+            for stop_time in stop_times.first().into_iter().chain(stop_times.last().filter(|_| stop_times.len() > 1)) {
+                if stop_time.arrival_time.is_none() && stop_time.departure_time.is_none() {
+                    find(
+                        Severity::Error,
+                        "stop_times.txt",
+                        format!("{} #{}", stop_time.trip_id, stop_time.stop_sequence),
+                        "the first and last stops of a trip must have times".to_string(),
+                    );
+                }
+            }
+            let mut latest = None;
+            for stop_time in &stop_times {
+                let record = || format!("{} #{}", stop_time.trip_id, stop_time.stop_sequence);
+                if let (Some(arrival), Some(departure)) = (stop_time.arrival_time, stop_time.departure_time) {
+                    if departure < arrival {
+                        find(Severity::Error, "stop_times.txt", record(), format!("departs at {}, before it arrives at {}", departure, arrival));
+                    }
+                }
+                if let Some(time) = stop_time.arrival_time.or(stop_time.departure_time) {
+                    if latest.is_some_and(|latest| time < latest) {
+                        find(Severity::Error, "stop_times.txt", record(), format!("time {} is earlier than the stop before", time));
+                    }
+                }
+                latest = stop_time.departure_time.or(stop_time.arrival_time).or(latest);
+            }
+        }
+
+        let service_ids = self.calendar.services.keys()
+            .chain(self.calendar_dates.calendar_dates.keys())
+            .collect::<collections::BTreeSet<_>>();
+        for service_id in service_ids {
+            if self.service_dates(service_id).is_empty() {
+                find(Severity::Warning, "calendar.txt", service_id.clone(), "runs on no dates".to_string());
+            }
+        }
+
+        let routes_with_trips = self.trips.trips.values().map(|trip| trip.route_id.as_str()).collect::<collections::HashSet<_>>();
+        for route in &self.routes {
+            if let Some(color) = route.route_color {
+                // text is black unless a route_text_color is given, as the GTFS reference specifies.
+                let text = route.route_text_color.map_or((0, 0, 0), |text| (text.r, text.g, text.b));
+                let ratio = contrast((color.r, color.g, color.b), text);
+                if ratio < MIN_CONTRAST {
+                    find(Severity::Warning, "routes.txt", route.route_id.clone(), format!("route_text_color has a contrast of only {:.1}:1 against route_color", ratio));
+                }
+            }
+            if !routes_with_trips.contains(route.route_id.as_str()) {
+                find(Severity::Info, "routes.txt", route.route_id.clone(), "has no trips".to_string());
+            }
+        }
+
+        let visited = self.stop_times.iter().filter_map(|stop_time| stop_time.stop_id.as_deref()).collect::<collections::HashSet<_>>();
+        for stop in &self.stops {
+            if matches!(stop.location_type_details, LocationTypeDetails::Stop(_)) && !visited.contains(stop.stop_id.as_str()) {
+                find(Severity::Info, "stops.txt", stop.stop_id.clone(), "no trip stops here".to_string());
+            }
+        }
+
+        findings.sort_by(|a, b| (a.severity, a.file, &a.record).cmp(&(b.severity, b.file, &b.record)));
+        ValidationReport { findings }
+    }
+}
+
+// contrast gives the WCAG contrast ratio between two colors, from 1:1 for the same color to 21:1 for black and white.
+fn contrast(a: (u8, u8, u8), b: (u8, u8, u8)) -> f64 {
+    let luminance = |(r, g, b): (u8, u8, u8)| {
+        let channel = |value: u8| {
+            let value = value as f64 / 255.0;
+            if value <= 0.03928 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+        };
+        0.2126 * channel(r) + 0.7152 * channel(g) + 0.0722 * channel(b)
+    };
+    let (lighter, darker) = match (luminance(a), luminance(b)) {
+        (a, b) if a >= b => (a, b),
+        (a, b) => (b, a),
+    };
+    (lighter + 0.05) / (darker + 0.05)
+}
@@ -0,0 +1,184 @@
+use crate::gtfs::GtfsSchedule;
+use crate::gtfs::spatial;
+use crate::gtfs::stops;
+use crate::gtfs::routes;
+use crate::gtfs::trips;
+use crate::gtfs::stop_times;
+use crate::style::{Colorize, Paint};
+use std::borrow::Cow;
+use std::collections;
+use std::fmt;
+
+// ScheduleFilter selects a subset of a schedule by ID.
+// A stop time is selected if its trip is selected and it visits a selected stop, or no stop at all.
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleFilter {
+    pub stop_ids: collections::HashSet<String>,
+    pub route_ids: collections::HashSet<String>,
+    pub trip_ids: collections::HashSet<String>,
+}
+
+// ScheduleView is a borrowed view of a schedule, optionally restricted by a ScheduleFilter.
+// It lets part of a schedule be navigated without copying any of its records.
+#[derive(Debug, Clone, Copy)]
+pub struct ScheduleView<'a> {
+    pub schedule: &'a GtfsSchedule,
+    pub filter: Option<&'a ScheduleFilter>,
+}
+
+impl<'a> ScheduleView<'a> {
+    pub fn new(schedule: &'a GtfsSchedule, filter: Option<&'a ScheduleFilter>) -> Self {
+        ScheduleView { schedule, filter }
+    }
+
+    pub fn stops(&self) -> Box<dyn Iterator<Item = &'a stops::Stop> + 'a> {
+        let stops = &self.schedule.stops.stops;
+        match self.filter {
+            None => Box::new(stops.values()),
+            Some(filter) => Box::new(filter.stop_ids.iter().filter_map(move |stop_id| stops.get(stop_id))),
+        }
+    }
+
+    pub fn routes(&self) -> Box<dyn Iterator<Item = &'a routes::Route> + 'a> {
+        let routes = &self.schedule.routes.routes;
+        match self.filter {
+            None => Box::new(routes.values()),
+            Some(filter) => Box::new(filter.route_ids.iter().filter_map(move |route_id| routes.get(route_id))),
+        }
+    }
+
+    pub fn trips(&self) -> Box<dyn Iterator<Item = &'a trips::Trip> + 'a> {
+        let trips = &self.schedule.trips.trips;
+        match self.filter {
+            None => Box::new(trips.values()),
+            Some(filter) => Box::new(filter.trip_ids.iter().filter_map(move |trip_id| trips.get(trip_id))),
+        }
+    }
+
+    pub fn stop_times(&self) -> Box<dyn Iterator<Item = &'a stop_times::StopTime> + 'a> {
+        let stop_times = &self.schedule.stop_times.stop_times;
+        match self.filter {
+            None => Box::new(self.schedule.stop_times.iter()),
+            Some(filter) => Box::new(
+                filter.trip_ids.iter()
+                    .filter_map(move |trip_id| stop_times.get(trip_id))
+                    .flatten()
+                    .filter(move |stop_time| stop_time.stop_id.as_ref().is_none_or(|stop_id| filter.stop_ids.contains(stop_id)))
+            ),
+        }
+    }
+
+    // stop_times_of_trip yields the stop times of a single trip which are part of the view, in the order they were loaded.
+    pub fn stop_times_of_trip(&self, trip_id: &str) -> impl Iterator<Item = &'a stop_times::StopTime> + 'a {
+        let filter = self.filter;
+        self.trip(trip_id)
+            .and_then(|_| self.schedule.stop_times.stop_times.get(trip_id))
+            .into_iter()
+            .flatten()
+            .filter(move |stop_time| filter.is_none_or(|filter| stop_time.stop_id.as_ref().is_none_or(|stop_id| filter.stop_ids.contains(stop_id))))
+    }
+
+    // stop_times_at yields the stop times in the view which visit the given stop, using the schedule's index.
+    pub fn stop_times_at(&self, stop_id: &str) -> impl Iterator<Item = &'a stop_times::StopTime> + 'a {
+        let view = *self;
+        self.stop(stop_id)
+            .map(|stop| self.schedule.stop_times_at(&stop.stop_id))
+            .into_iter()
+            .flatten()
+            .filter(move |stop_time| view.trip(&stop_time.trip_id).is_some())
+    }
+
+    // trips_of_route yields the trips in the view which belong to the given route, using the schedule's index.
+    pub fn trips_of_route(&self, route_id: &str) -> impl Iterator<Item = &'a trips::Trip> + 'a {
+        let view = *self;
+        self.route(route_id)
+            .map(|route| self.schedule.trips_of_route(&route.route_id))
+            .into_iter()
+            .flatten()
+            .filter(move |trip| view.trip(&trip.trip_id).is_some())
+    }
+
+    // child_stops yields the stops in the view whose parent station is the given stop, using the schedule's index.
+    pub fn child_stops(&self, stop_id: &str) -> impl Iterator<Item = &'a stops::Stop> + 'a {
+        let view = *self;
+        self.schedule.child_stops(stop_id)
+            .filter(move |stop| view.stop(&stop.stop_id).is_some())
+    }
+
+    // station_tree returns the given stop and, nested beneath it, every stop in the view within it, using the schedule's index.
+    // It is None if the stop isn't in the view.
+    pub fn station_tree(&self, stop_id: &str) -> Option<stops::StationTree<'a>> {
+        let view = *self;
+        Some(stops::StationTree::build(self.stop(stop_id)?, |stop_id| view.child_stops(stop_id).collect()))
+    }
+
+    // stops_within returns the IDs of the given stop and every stop in the view beneath it, such as a station's platforms.
+    // It is empty if the stop isn't in the view.
+    pub fn stops_within(&self, stop_id: &str) -> collections::HashSet<&'a str> {
+        self.station_tree(stop_id)
+            .map(|tree| tree.stops())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|stop| stop.stop_id.as_str())
+            .collect()
+    }
+
+    // nearest returns the n stops in the view with coordinates closest to the given point, with their distances in
+    // meters, closest first. The whole schedule is searched with its spatial index; the stops of a filtered view,
+    // which are usually few, are measured one by one.
+    pub fn nearest(&self, lat: f64, lon: f64, n: usize) -> Vec<(&'a stops::Stop, f64)> {
+        if self.filter.is_none() {
+            return self.schedule.stops.nearest(lat, lon, n);
+        }
+        let mut nearest = self.stops()
+            .filter_map(|stop| Some((stop, spatial::haversine(lat, lon, stop.stop_lat()?, stop.stop_lon()?))))
+            .collect::<Vec<_>>();
+        nearest.sort_by(|(a, a_distance), (b, b_distance)| a_distance.total_cmp(b_distance).then_with(|| a.stop_id.cmp(&b.stop_id)));
+        nearest.truncate(n);
+        nearest
+    }
+
+    pub fn stop(&self, stop_id: &str) -> Option<&'a stops::Stop> {
+        self.filter
+            .is_none_or(|filter| filter.stop_ids.contains(stop_id))
+            .then(|| self.schedule.stops.stops.get(stop_id))
+            .flatten()
+    }
+
+    pub fn route(&self, route_id: &str) -> Option<&'a routes::Route> {
+        self.filter
+            .is_none_or(|filter| filter.route_ids.contains(route_id))
+            .then(|| self.schedule.routes.routes.get(route_id))
+            .flatten()
+    }
+
+    pub fn trip(&self, trip_id: &str) -> Option<&'a trips::Trip> {
+        self.filter
+            .is_none_or(|filter| filter.trip_ids.contains(trip_id))
+            .then(|| self.schedule.trips.trips.get(trip_id))
+            .flatten()
+    }
+
+    // to_schedule materializes the view as a schedule. An unfiltered view borrows the underlying schedule,
+    // while a filtered one is copied out with sub_schedule.
+    pub fn to_schedule(&self) -> Cow<'a, GtfsSchedule> {
+        match self.filter {
+            None => Cow::Borrowed(self.schedule),
+            Some(_) => Cow::Owned(self.schedule.sub_schedule(
+                self.stops().cloned().collect(),
+                self.routes().cloned().collect(),
+                self.trips().cloned().collect(),
+                self.stop_times().cloned().collect(),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ScheduleView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}\n{}: {}\n{}: {}",
+        "Stops".muted().bold(), self.stops().count(),
+        "Routes".muted().bold(), self.routes().count(),
+        "Trips".muted().bold(), self.trips().count())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,148 @@
+use crate::gtfs;
+use crate::gtfs::shapes;
+use crate::gtfs::stops;
+use crate::gtfs::writers::zip_writer;
+use serde_json::{json, Map, Value};
+use std::collections;
+
+// to_json gives a schedule as a JSON object with a member for each table the zip writer would write, named for its
+// file without the .txt, holding an array with an object for each row. Rows hold the fields which are set, in the
+// order the GTFS reference lists them, as the text they would have in the feed.
+pub fn to_json(schedule: &gtfs::GtfsSchedule) -> Value {
+    zip_writer::tables(schedule)
+        .into_iter()
+        .map(|(file, fields, rows)| {
+            let rows = rows.iter()
+                .map(|row| Value::Object(fields.iter()
+                    .filter_map(|field| Some((field.to_string(), Value::from(row.get(*field)?.as_str()))))
+                    .collect()))
+                .collect::<Vec<_>>();
+            (file.trim_end_matches(".txt").to_string(), Value::Array(rows))
+        })
+        .collect::<Map<_, _>>()
+        .into()
+}
+
+// to_geojson gives a schedule as a GeoJSON FeatureCollection, with a Point for each stop which has coordinates, its
+// fields as properties, and a LineString for each shape, with its ID and the routes of the trips following it.
+pub fn to_geojson(schedule: &gtfs::GtfsSchedule) -> Value {
+    let mut stops = (&schedule.stops).into_iter().collect::<Vec<_>>();
+    stops.sort_by(|a, b| a.stop_id.cmp(&b.stop_id));
+    let stop_features = stops.into_iter().filter_map(|stop| {
+        let (lat, lon) = (stop.stop_lat()?, stop.stop_lon()?);
+        let record = collections::HashMap::from(stop);
+        let properties = stops::STOP_FIELDS.iter()
+            .filter_map(|field| Some((field.to_string(), Value::from(record.get(*field)?.as_str()))))
+            .collect::<Map<_, _>>();
+        Some(json!({
+            "type": "Feature",
+            "geometry": { "type": "Point", "coordinates": [lon, lat] },
+            "properties": properties,
+        }))
+    });
+
+    let mut routes_of_shapes = collections::BTreeMap::<&str, collections::BTreeSet<&str>>::new();
+    for trip in &schedule.trips {
+        if let Some(shape_id) = trip.shape_id.as_deref() {
+            routes_of_shapes.entry(shape_id).or_default().insert(trip.route_id.as_str());
+        }
+    }
+    let mut shapes = schedule.shapes.shapes.iter().collect::<Vec<_>>();
+    shapes.sort_by(|a, b| a.0.cmp(b.0));
+    let shape_features = shapes.into_iter().map(|(shape_id, points)| {
+        shape_feature(shape_id, points, routes_of_shapes.get(shape_id.as_str()).into_iter().flatten().copied().collect())
+    });
+
+    json!({
+        "type": "FeatureCollection",
+        "features": stop_features.chain(shape_features).collect::<Vec<_>>(),
+    })
+}
+
+// shape_to_geojson gives a single shape as a GeoJSON FeatureCollection holding its LineString, with its ID and the
+// routes of the trips following it, or None if the schedule has no such shape.
+pub fn shape_to_geojson(schedule: &gtfs::GtfsSchedule, shape_id: &str) -> Option<Value> {
+    let points = schedule.shapes.shapes.get(shape_id)?;
+    let route_ids = (&schedule.trips).into_iter()
+        .filter(|trip| trip.shape_id.as_deref() == Some(shape_id))
+        .map(|trip| trip.route_id.as_str())
+        .collect();
+    Some(json!({
+        "type": "FeatureCollection",
+        "features": [shape_feature(shape_id, points, route_ids)],
+    }))
+}
+
+// shape_feature gives a shape as a GeoJSON LineString Feature, its points in order of shape_pt_sequence.
+fn shape_feature(shape_id: &str, points: &[shapes::ShapePoint], route_ids: collections::BTreeSet<&str>) -> Value {
+    let mut points = points.iter().collect::<Vec<_>>();
+    points.sort_by_key(|point| point.shape_pt_sequence);
+    json!({
+        "type": "Feature",
+        "geometry": {
+            "type": "LineString",
+            "coordinates": points.iter().map(|point| [point.shape_pt_lon, point.shape_pt_lat]).collect::<Vec<_>>(),
+        },
+        "properties": {
+            "shape_id": shape_id,
+            "route_ids": route_ids,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::loaders::multi_feed_loader::FeedSource;
+    use crate::gtfs::testing;
+    use std::fs;
+
+    // write_csv writes the rows of a table in the JSON as the CSV file they came from, with a column for each field
+    // set on any row.
+    fn write_csv(dir: &std::path::Path, table: &str, rows: &Value) {
+        let rows = rows.as_array().unwrap();
+        let mut fields = Vec::<&String>::new();
+        for field in rows.iter().flat_map(|row| row.as_object().unwrap().keys()) {
+            if !fields.contains(&field) {
+                fields.push(field);
+            }
+        }
+        let mut writer = csv::Writer::from_path(dir.join(format!("{}.txt", table))).unwrap();
+        writer.write_record(&fields).unwrap();
+        for row in rows {
+            writer.write_record(fields.iter().map(|field| row[field.as_str()].as_str().unwrap_or(""))).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    #[test]
+    fn json_holds_the_records_of_each_table() {
+        let json = to_json(&testing::schedule());
+        let tables = json.as_object().unwrap().keys().map(String::as_str).collect::<Vec<_>>();
+        assert_eq!(tables, ["agency", "stops", "routes", "trips", "stop_times", "calendar"]);
+        assert_eq!(json["routes"].as_array().unwrap().len(), 2);
+        let stop_x = json["stops"].as_array().unwrap().iter().find(|stop| stop["stop_id"] == "X").unwrap();
+        assert_eq!(stop_x, &json!({ "stop_id": "X", "stop_name": "X", "stop_lat": "40.7", "stop_lon": "-74", "location_type": "0" }));
+    }
+
+    #[test]
+    fn json_loads_back_unchanged() {
+        let dir = testing::temp_dir("to_json");
+        for (table, rows) in to_json(&testing::feed()).as_object().unwrap() {
+            write_csv(&dir, table, rows);
+        }
+        assert_eq!(FeedSource::Path(dir.clone()).load().unwrap(), testing::feed());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn geojson_has_a_point_for_each_stop_and_a_line_for_each_shape() {
+        let geojson = to_geojson(&testing::feed());
+        let features = geojson["features"].as_array().unwrap();
+        let types = features.iter().map(|feature| feature["geometry"]["type"].as_str().unwrap()).collect::<Vec<_>>();
+        // the generic node and the boarding area have no coordinates.
+        assert_eq!(types, ["Point", "Point", "Point", "Point", "Point", "Point", "LineString"]);
+        assert_eq!(features[6]["properties"], json!({ "shape_id": "SH", "route_ids": ["R1"] }));
+        assert_eq!(features[6]["geometry"]["coordinates"], json!([[-74.0, 40.7], [-73.9901, 40.7501]]));
+    }
+}
@@ -0,0 +1,2 @@
+pub mod json_writer;
+pub mod zip_writer;
\ No newline at end of file
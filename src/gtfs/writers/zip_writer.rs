@@ -0,0 +1,182 @@
+use crate::gtfs;
+use crate::gtfs::agency;
+use crate::gtfs::calendar;
+use crate::gtfs::stops;
+use crate::gtfs::routes;
+use crate::gtfs::trips;
+use crate::gtfs::stop_times;
+use crate::gtfs::shapes;
+use crate::gtfs::frequencies;
+use crate::gtfs::transfers;
+use crate::gtfs::pathways;
+use crate::gtfs::fares;
+use crate::gtfs::fare_products;
+use std::collections;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path;
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+
+// ZipWriter writes a GTFS schedule as a zip archive of .txt files, in the layout ZipLoader reads.
+// The required tables are always written; the optional ones are written only if they hold any records.
+pub struct ZipWriter<W: io::Write + io::Seek> {
+    pub zip: zip::ZipWriter<W>,
+}
+
+#[derive(Debug)]
+pub enum ZipWriterError {
+    FailedToStartFile(String, ZipError),
+    FailedToCreateDirectory(path::PathBuf, io::Error),
+    FailedToCreateFile(path::PathBuf, io::Error),
+    FailedToWriteFile(String, csv::Error),
+    FailedToFinishArchive(ZipError),
+}
+
+impl fmt::Display for ZipWriterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FailedToStartFile(file, e) => write!(f, "Failed to add {} to archive: {}", file, e),
+            Self::FailedToCreateDirectory(path, e) => write!(f, "Failed to create {}: {}", path.display(), e),
+            Self::FailedToCreateFile(path, e) => write!(f, "Failed to create {}: {}", path.display(), e),
+            Self::FailedToWriteFile(file, e) => write!(f, "Failed to write {}: {}", file, e),
+            Self::FailedToFinishArchive(e) => write!(f, "Failed to finish archive: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ZipWriterError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::FailedToStartFile(_, e) => Some(e),
+            Self::FailedToCreateDirectory(_, e) => Some(e),
+            Self::FailedToCreateFile(_, e) => Some(e),
+            Self::FailedToWriteFile(_, e) => Some(e),
+            Self::FailedToFinishArchive(e) => Some(e),
+        }
+    }
+}
+
+impl<W: io::Write + io::Seek> ZipWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            zip: zip::ZipWriter::new(writer),
+        }
+    }
+
+    // write adds every table of the schedule to the archive and finishes it, returning the underlying writer.
+    pub fn write(mut self, schedule: &gtfs::GtfsSchedule) -> Result<W, ZipWriterError> {
+        for (file, fields, rows) in tables(schedule) {
+            self.zip.start_file(file, SimpleFileOptions::default())
+                .map_err(|e| ZipWriterError::FailedToStartFile(file.to_string(), e))?;
+            write_table(&mut self.zip, fields, &rows)
+                .map_err(|e| ZipWriterError::FailedToWriteFile(file.to_string(), e))?;
+        }
+        self.zip.finish().map_err(ZipWriterError::FailedToFinishArchive)
+    }
+}
+
+// write_dir writes the schedule as plain .txt files into the given directory, creating it if necessary.
+// The result can be read back with MultiSourceLoader, or as a FeedSource::Path.
+pub fn write_dir<P: AsRef<path::Path>>(dir: P, schedule: &gtfs::GtfsSchedule) -> Result<(), ZipWriterError> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir).map_err(|e| ZipWriterError::FailedToCreateDirectory(dir.to_path_buf(), e))?;
+    for (file, fields, rows) in tables(schedule) {
+        let file_path = dir.join(file);
+        let writer = fs::File::create(&file_path).map_err(|e| ZipWriterError::FailedToCreateFile(file_path, e))?;
+        write_table(writer, fields, &rows)
+            .map_err(|e| ZipWriterError::FailedToWriteFile(file.to_string(), e))?;
+    }
+    Ok(())
+}
+
+// EncodedTable is a table of a schedule encoded for writing, as (file name, fields, rows).
+pub(super) type EncodedTable = (&'static str, &'static [&'static str], Vec<collections::HashMap<String, String>>);
+
+// tables encodes each table of the schedule which should be written.
+pub(super) fn tables(schedule: &gtfs::GtfsSchedule) -> Vec<EncodedTable> {
+    let encode = |file, fields: &'static [&'static str], rows: Vec<collections::HashMap<String, String>>, required| {
+        (required || !rows.is_empty()).then_some((file, fields, rows))
+    };
+
+    [
+        encode("agency.txt", &agency::AGENCY_FIELDS, (&schedule.agencies).into_iter().map(collections::HashMap::from).collect(), false),
+        encode("stops.txt", &stops::STOP_FIELDS, (&schedule.stops).into_iter().map(collections::HashMap::from).collect(), true),
+        encode("routes.txt", &routes::ROUTE_FIELDS, (&schedule.routes).into_iter().map(collections::HashMap::from).collect(), true),
+        encode("trips.txt", &trips::TRIP_FIELDS, (&schedule.trips).into_iter().map(collections::HashMap::from).collect(), true),
+        encode("stop_times.txt", &stop_times::STOP_TIME_FIELDS, schedule.stop_times.iter().map(collections::HashMap::from).collect(), true),
+        encode("calendar.txt", &calendar::SERVICE_FIELDS, (&schedule.calendar).into_iter().map(collections::HashMap::from).collect(), false),
+        encode("calendar_dates.txt", &calendar::CALENDAR_DATE_FIELDS, schedule.calendar_dates.iter().map(collections::HashMap::from).collect(), false),
+        encode("shapes.txt", &shapes::SHAPE_POINT_FIELDS, schedule.shapes.iter().map(collections::HashMap::from).collect(), false),
+        encode("frequencies.txt", &frequencies::FREQUENCY_FIELDS, schedule.frequencies.iter().map(collections::HashMap::from).collect(), false),
+        encode("transfers.txt", &transfers::TRANSFER_FIELDS, schedule.transfers.iter().map(collections::HashMap::from).collect(), false),
+        encode("pathways.txt", &pathways::PATHWAY_FIELDS, (&schedule.pathways).into_iter().map(collections::HashMap::from).collect(), false),
+        encode("fare_attributes.txt", &fares::FARE_ATTRIBUTE_FIELDS, (&schedule.fare_attributes).into_iter().map(collections::HashMap::from).collect(), false),
+        encode("fare_rules.txt", &fares::FARE_RULE_FIELDS, schedule.fare_rules.iter().map(collections::HashMap::from).collect(), false),
+        encode("fare_products.txt", &fare_products::FARE_PRODUCT_FIELDS, schedule.fare_products.iter().map(collections::HashMap::from).collect(), false),
+        encode("fare_leg_rules.txt", &fare_products::FARE_LEG_RULE_FIELDS, schedule.fare_leg_rules.iter().map(collections::HashMap::from).collect(), false),
+        encode("stop_areas.txt", &fare_products::STOP_AREA_FIELDS, schedule.stop_areas.iter().map(collections::HashMap::from).collect(), false),
+    ]
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+// write_table writes rows as CSV, with a column for every field which is set on at least one row.
+// Fields are written in the order the GTFS reference lists them, and unset fields are left empty.
+fn write_table<W: io::Write>(writer: W, fields: &[&str], rows: &[collections::HashMap<String, String>]) -> Result<(), csv::Error> {
+    let columns = fields.iter()
+        .filter(|field| rows.is_empty() || rows.iter().any(|row| row.contains_key(**field)))
+        .collect::<Vec<_>>();
+
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record(&columns)?;
+    for row in rows {
+        csv_writer.write_record(columns.iter().map(|field| row.get(**field).map(String::as_str).unwrap_or("")))?;
+    }
+    Ok(csv_writer.flush()?)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gtfs::loaders::multi_feed_loader::FeedSource;
+    use crate::gtfs::loaders::zip_loader::ZipLoader;
+    use crate::gtfs::testing;
+
+    fn write_zip(schedule: &gtfs::GtfsSchedule) -> zip::ZipArchive<io::Cursor<Vec<u8>>> {
+        let cursor = ZipWriter::new(io::Cursor::new(Vec::new())).write(schedule).unwrap();
+        zip::ZipArchive::new(cursor).unwrap()
+    }
+
+    #[test]
+    fn a_written_archive_loads_unchanged() {
+        assert_eq!(ZipLoader::new(write_zip(&testing::feed())).load().unwrap(), testing::feed());
+    }
+
+    #[test]
+    fn a_written_directory_loads_unchanged() {
+        let dir = testing::temp_dir("write_dir");
+        write_dir(&dir, &testing::feed()).unwrap();
+        assert_eq!(FeedSource::Path(dir.clone()).load().unwrap(), testing::feed());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn optional_tables_are_only_written_if_they_hold_records() {
+        let archive = write_zip(&testing::schedule());
+        let mut files = archive.file_names().collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, ["agency.txt", "calendar.txt", "routes.txt", "stop_times.txt", "stops.txt", "trips.txt"]);
+    }
+
+    #[test]
+    fn only_the_fields_set_on_some_record_are_written() {
+        let mut archive = write_zip(&testing::schedule());
+        let mut routes = String::new();
+        io::Read::read_to_string(&mut archive.by_name("routes.txt").unwrap(), &mut routes).unwrap();
+        let mut lines = routes.lines().collect::<Vec<_>>();
+        lines[1..].sort();
+        assert_eq!(lines, ["route_id,agency_id,route_short_name,route_type", "R1,A,1,3", "R2,A,2,3"]);
+    }
+}
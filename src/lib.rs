@@ -0,0 +1,24 @@
+pub mod gtfs;
+// commands is the implementation of the rust-gtfs binary: its REPL, configuration and output. It's public only so that
+// the binary can reach it, and isn't part of the library's interface.
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod commands;
+pub mod style;
+
+// The schedule, the ways of loading one, and the queries it answers are re-exported here for library users.
+pub use gtfs::GtfsSchedule;
+pub use gtfs::error::{CsvError, GtfsError};
+pub use gtfs::loaders::multi_feed_loader::{load_archive, FeedLoadError, FeedSource, MultiFeedLoader, MultiFeedLoaderError};
+pub use gtfs::loaders::multi_source_loader::MultiSourceLoader;
+pub use gtfs::loaders::tar_gz_loader::TarGzLoader;
+pub use gtfs::loaders::zip_loader::ZipLoader;
+pub use gtfs::store::sqlite::SqliteStore;
+pub use gtfs::view::{ScheduleFilter, ScheduleView};
+pub use gtfs::departures::Departure;
+pub use gtfs::fares::{Fare, RouteFare};
+pub use gtfs::network::{DirectTrip, ServiceSpan};
+pub use gtfs::spatial::SpatialIndex;
+pub use gtfs::transfers::Connection;
+#[cfg(feature = "realtime")]
+pub use gtfs::gtfs_rt::overlay::{RealtimeDeparture, RealtimeOverlay, RealtimeSchedule};
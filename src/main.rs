@@ -1,83 +1,401 @@
-mod gtfs;
-mod commands;
-use commands::gtfs::GtfsNode;
-
-use commands::CommandInterpreter;
-use colored::Colorize;
-use curl::easy::Easy;
+use rust_gtfs::gtfs;
+use rust_gtfs::gtfs::loaders::multi_feed_loader::FeedSource;
+use rust_gtfs::gtfs::store::sqlite::SqliteStore;
+use rust_gtfs::commands::bench;
+use rust_gtfs::commands::config::Config;
+use rust_gtfs::commands::diff;
+use rust_gtfs::commands::logging;
+use rust_gtfs::commands::names;
+use rust_gtfs::commands::output::OutputFormat;
+use rust_gtfs::commands::progress::{ProgressBar, Spinner};
+use rust_gtfs::commands::realtime::Realtime;
+use rust_gtfs::commands::repl::{Feed, Repl};
+use rust_gtfs::commands::validate;
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::Editor;
+use clap::{Arg, ArgAction, ArgMatches, ValueHint};
+use clap_complete::Shell;
+use rust_gtfs::style;
 use std::io;
-use std::io::Write;
-use std::iter;
-use std::io::BufRead;
+use std::io::IsTerminal;
+use std::sync::Arc;
+use std::time::Instant;
+
+const DEFAULT_FEED_URL: &str = "https://cdn.mbta.com/MBTA_GTFS.zip";
+// OLD_HISTORY_FILE, in the home directory, is where the REPL's history was kept before it moved to the XDG data
+// directory. It's read if there's no history there yet, so that it isn't lost.
+const OLD_HISTORY_FILE: &str = ".rust_gtfs_history";
+// FEED_VARIABLE names the environment variable giving the feed to load, as a URL, a path or the name of a feed in the
+// config file, when none is chosen on the command line.
+const FEED_VARIABLE: &str = "RUST_GTFS_FEED";
+// DEFAULT_FEED_NAME is what the feed loaded at startup goes by, unless it's named in the config file.
+const DEFAULT_FEED_NAME: &str = "default";
+
+// cli describes the command line: options choosing the feed and how results are shown, which may be given before or
+// after a subcommand, and the subcommands. Without one, the REPL starts, as with repl.
+fn cli() -> clap::Command {
+    clap::Command::new("rust-gtfs")
+        .about("Explore a GTFS schedule interactively")
+        .arg(Arg::new("url").long("url").value_name("URL").global(true).help("Download the feed from URL"))
+        .arg(Arg::new("file").long("file").value_name("PATH").global(true).value_hint(ValueHint::FilePath)
+            .help("Read the feed from a zip or tar.gz archive, or a SQLite store written by export sqlite"))
+        .arg(Arg::new("dir").long("dir").value_name("PATH").global(true).value_hint(ValueHint::DirPath)
+            .help("Read the feed from a directory of .txt files"))
+        .arg(Arg::new("feed").long("feed").value_name("NAME").global(true).help("Load the feed with this name in the config file"))
+        .arg(Arg::new("provider").long("provider").value_name("NAME").global(true)
+            .help("Look the feed up in the Mobility Database by provider"))
+        .arg(Arg::new("mdb-id").long("mdb-id").value_name("ID").global(true).help("Look the feed up in the Mobility Database by ID"))
+        .group(clap::ArgGroup::new("source").args(["url", "file", "dir", "feed", "provider", "mdb-id"]))
+        .arg(Arg::new("realtime").long("realtime").value_name("URL").action(ArgAction::Append).global(true)
+            .help("Show the predictions of the GTFS-Realtime feed at URL, or in a file, alongside the schedule; may be given more than once"))
+        .arg(Arg::new("json").long("json").action(ArgAction::SetTrue).global(true).help("Show results as JSON"))
+        .arg(Arg::new("color").long("color").value_name("WHEN").value_parser(["auto", "always", "never"]).global(true)
+            .help("Color output: auto colors it on a terminal unless NO_COLOR is set; by default, as the config file says, else auto"))
+        .arg(Arg::new("accessible").long("accessible").action(ArgAction::SetTrue).global(true)
+            .help("Consider only wheelchair-accessible trips and stops, unless a command is given accessible=false"))
+        .arg(Arg::new("quiet").short('q').long("quiet").action(ArgAction::SetTrue).conflicts_with("verbose").global(true)
+            .help("Log only warnings and errors"))
+        .arg(Arg::new("verbose").short('v').long("verbose").action(ArgAction::Count).global(true)
+            .help("Log the time taken to parse each table and run each command; -vv logs everything"))
+        .arg(Arg::new("command").short('c').value_name("COMMAND").action(ArgAction::Append)
+            .help("Run a command and exit instead of starting the REPL; may be given more than once, as with query"))
+        .subcommand(clap::Command::new("repl")
+            .about("Explore the feed interactively, as happens without a subcommand"))
+        .subcommand(clap::Command::new("query")
+            .about("Run commands on the feed and exit, with a nonzero status if one fails")
+            .arg(Arg::new("commands").value_name("COMMAND").num_args(1..).required(true)
+                .help("The commands to run, in turn, e.g. 'stops.list limit=5'")))
+        .subcommand(clap::Command::new("export")
+            .about("Write the feed, or its accessible part with --accessible, to a file and exit")
+            .arg(Arg::new("format").value_name("FORMAT").value_parser(["gtfs", "sqlite", "geojson", "json"]).required(true)
+                .help("Write a GTFS zip, a SQLite store which --file reopens without parsing, GeoJSON of the stops and shapes, or JSON of every record"))
+            .arg(Arg::new("path").value_name("PATH").value_hint(ValueHint::FilePath).required(true)
+                .help("The file to write")))
+        .subcommand(clap::Command::new("validate")
+            .about("Check a feed and exit, with a nonzero status if it has errors")
+            .arg(Arg::new("checked").value_name("FEED")
+                .help("The feed to check, as a URL, a path or the name of a feed in the config file; by default, the one the feed options choose"))
+            .arg(Arg::new("strict").long("strict").action(ArgAction::SetTrue).help("Fail the feed for warnings too")))
+        .subcommand(clap::Command::new("diff")
+            .about("Compare two feeds and exit; the older may be chosen with the feed options instead, e.g. diff --dir old NEW")
+            .arg(Arg::new("old").value_name("OLD")
+                .help("The older feed, as a URL, a path or the name of a feed in the config file"))
+            .arg(Arg::new("new").value_name("NEW").help("The newer feed"))
+            .arg(Arg::new("table").value_name("TABLE")
+                .help("List the changes to this table, one of stops, routes, trips and stop_times, rather than counting them"))
+            .arg(Arg::new("id").value_name("ID").help("Show only the changes to the record of the table with this ID")))
+        .subcommand(clap::Command::new("bench")
+            .about("Time loading a feed and a set of typical queries on it, report the peak memory used and exit")
+            .arg(Arg::new("benched").value_name("FEED")
+                .help("The feed to benchmark, as a URL, a path or the name of a feed in the config file; by default, the one the feed options choose"))
+            .arg(Arg::new("samples").long("samples").value_name("N").value_parser(clap::value_parser!(usize))
+                .help("Run each query for this many stops or routes, by default 100")))
+        .subcommand(clap::Command::new("completions")
+            .about("Print a script completing rust-gtfs's arguments in a shell, to be sourced from its startup file")
+            .arg(Arg::new("shell").value_name("SHELL").value_parser(clap::value_parser!(Shell)).required(true)
+                .help("The shell to complete arguments in")))
+}
 
 fn main() {
-    let mut buf = Vec::new();
+    let args = cli().get_matches();
+    if let Some(("completions", args)) = args.subcommand() {
+        let shell = *args.get_one::<Shell>("shell").expect("the shell is required");
+        clap_complete::generate(shell, &mut cli(), "rust-gtfs", &mut io::stdout());
+        return;
+    }
+    logging::init(logging::verbosity(args.get_flag("quiet"), args.get_count("verbose")));
+    let config = Config::load().unwrap_or_else(|err| fail(&format!("Failed to load config: {}", err)));
+    style::set_color_choice(match args.get_one::<String>("color") {
+        Some(color) => color.parse().unwrap_or_else(|err: String| fail(&err)),
+        None => config.color.unwrap_or_default(),
+    });
+    match args.subcommand() {
+        Some(("validate", args)) => validate_feed(args, &config),
+        Some(("diff", args)) => diff_feeds(args, &config),
+        Some(("bench", sub_args)) => bench_feed(sub_args, &config, args.get_flag("json")),
+        _ => (),
+    }
+    let (name, source) = feed_source(&args, &config);
 
-    // open gtfs zip file
-    let mut response = Easy::new();
-    {
-        response.url("https://cdn.mbta.com/MBTA_GTFS.zip").unwrap_or_else(
-            |err| panic!("Failed to open gtfs.zip: {}", err)
-        );
-        response.get(true).unwrap();
-        let mut transfer = response.transfer();
-        transfer.write_function(|data| {
-            buf.extend_from_slice(data);
-            pre_log(&format!("Downloaded {} bytes", buf.len()));
-            Ok(data.len())
-        }).unwrap();
-        transfer.perform().unwrap_or_else(
-            |err| panic!("Failed to download gtfs.zip: {}", err)
-        );
+    let started = Instant::now();
+    let mut gtfs = load_feed(&source);
+    // dangling references are reported rather than fatal, since published feeds often contain a few.
+    if let Some(report) = gtfs.link(gtfs::link::LinkPolicy::Warn).ok().filter(|report| !report.is_empty()) {
+        log::warn!("Feed has {} dangling references", report.dangling.len());
     }
+    let gtfs = Arc::new(gtfs);
+    log::info!("Loaded gtfs feed in {:.1?}", started.elapsed());
 
-    match response.response_code() {
-        Ok(200) => (),
-        _ => panic!("Failed to download gtfs.zip: {}", response.response_code().unwrap()),
+    let realtime = args.get_many::<String>("realtime").map(|sources| Arc::new(Realtime::load(sources)));
+    let mut repl = Repl::new(&name, Feed { source, schedule: gtfs, realtime });
+    repl.aliases = config.aliases;
+    // --json shows results as JSON from the start, as set output json does, whatever the config file says.
+    repl.format = match args.get_flag("json") {
+        true => OutputFormat::Json,
+        false => config.output.unwrap_or_default(),
+    };
+    repl.accessible = args.get_flag("accessible");
+    // commands given to query or with -c, or piped to stdin, are run without the REPL, so that the tool can be used in
+    // scripts.
+    match args.subcommand() {
+        Some(("query", args)) => run_script(&mut repl, args.get_many::<String>("commands").into_iter().flatten().cloned()),
+        Some(("export", args)) => {
+            let format = args.get_one::<String>("format").expect("the format is required");
+            let path = args.get_one::<String>("path").expect("the path is required");
+            run_script(&mut repl, [format!("export {} \"{}\"", format, path)]);
+        },
+        _ => (),
+    }
+    if let Some(commands) = args.get_many::<String>("command") {
+        run_script(&mut repl, commands.cloned());
+    }
+    if !io::stdin().is_terminal() {
+        run_script(&mut repl, io::stdin().lines().map(
+            |line| line.unwrap_or_else(|err| fail(&format!("Failed to read commands from stdin: {}", err)))
+        ));
     }
-    pre_log(&format!("Downloaded GTFS feed: {} bytes", buf.len()));
 
-    // interpret as zip archive
-    let gtfs_zip = zip::ZipArchive::new(std::io::Cursor::new(buf)).unwrap_or_else(
-        |err| panic!("Failed to create zip archive: {}", err)
+    // the REPL reads lines with rustyline, giving history across sessions, Ctrl-R search and tab completion.
+    let mut editor = Editor::<Repl, DefaultHistory>::new().unwrap_or_else(
+        |err| fail(&format!("Failed to start line editor: {}", err))
     );
-    // load gtfs feed from archive
-    let mut zip_loader = gtfs::loaders::zip_loader::ZipLoader::new(gtfs_zip);
-    zip_loader = zip_loader.with_event_handler(gtfs::loaders::zip_loader::FnZipLoaderEventHandler {
-        on_stops_file_opened: Box::new(|_| pre_log("Opened stops file")),
-        on_stops_loaded: Box::new(|_| pre_log("Loaded stops")),
-        on_routes_file_opened: Box::new(|_| pre_log("Opened routes file")),
-        on_routes_loaded: Box::new(|_| pre_log("Loaded routes")),
-        on_trips_file_opened: Box::new(|_| pre_log("Opened trips file")),
-        on_trips_loaded: Box::new(|_| pre_log("Loaded trips")),
-        on_stop_times_file_opened: Box::new(|_| pre_log("Opened stop times file")),
-        on_stop_times_loaded: Box::new(|_| pre_log("Loaded stop times")),
-    });
-    let gtfs = zip_loader.load().unwrap_or_else(
-        |err| panic!("Failed to create gtfs feed: {}", err)
+    editor.set_helper(Some(repl));
+    // names matching several stops, routes or trips offer a choice between them, as there's someone at the terminal
+    // to make it.
+    names::set_interactive(io::stderr().is_terminal());
+    let history_path = Repl::history_path();
+    let old_history_path = std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(OLD_HISTORY_FILE));
+    // there is no history to load the first time the REPL runs.
+    if let Some(history_path) = history_path.iter().chain(&old_history_path).find(|path| path.exists()) {
+        let _ = editor.load_history(history_path);
+    }
+    let history = editor.history().iter().cloned().collect();
+    editor.helper_mut().expect("the REPL is the editor's helper").history = history;
+    loop {
+        let prompt = editor.helper().map_or("> ".to_string(), Repl::prompt);
+        match editor.readline(&prompt) {
+            Ok(line) if line.trim().is_empty() => continue,
+            Ok(line) => {
+                let repl = editor.helper_mut().expect("the REPL is the editor's helper");
+                let lines_run = repl.history.len();
+                let result = repl.run(line.trim());
+                // the line is added to the editor's history as the REPL added it, with any !<number> replaced by
+                // the line it ran again.
+                let added = (repl.history.len() > lines_run).then(|| repl.history[lines_run].clone());
+                if let Some(added) = added {
+                    let _ = editor.add_history_entry(added);
+                }
+                result.unwrap_or_else(|err| println!("Error interpreting command: {}", err));
+            },
+            // Ctrl-C abandons the line being typed, as in a shell; Ctrl-D quits.
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => {
+                println!("Error reading line: {}", err);
+                break;
+            },
+        }
+    }
+    if let Some(history_path) = &history_path {
+        if let Some(dir) = history_path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        editor.save_history(history_path).unwrap_or_else(
+            |err| println!("Failed to save history to {}: {}", history_path.display(), err)
+        );
+    }
+}
+
+// feed_source chooses the feed to load, along with the name it goes by in the session: the one given on the command
+// line, else the one named by RUST_GTFS_FEED, else the config file's default feed, falling back to the MBTA's feed.
+// Feeds in the config file go by their names there, and others by the name default.
+fn feed_source(args: &ArgMatches, config: &Config) -> (String, FeedSource) {
+    let named = |name: &str| (
+        name.to_string(),
+        FeedSource::from(config.feed(name).unwrap_or_else(|err| fail(&err.to_string()))),
     );
-    pre_log("Loaded gtfs feed");
-    println!();
-
-    let stdin = io::stdin();
-    print!("> ");
-    io::stdout().flush().unwrap();
-    for line in stdin.lock().lines() {
-        line.map_err(|err| format!("Error reading line: {}", err))
-            .and_then(|line| GtfsNode{
-                gtfs: gtfs.clone(),
-                parent: None,
-                node_id: "".to_string(),
-                node_name: None
-            }.interpret(line.as_str()).map_err(|err| format!("Error interpreting command: {}", err)))
-            .unwrap_or_else(|err| println!("{}", err));
-        print!("> ");
-        io::stdout().flush().unwrap();
+    let unnamed = |source: FeedSource| (DEFAULT_FEED_NAME.to_string(), source);
+    if let Some(url) = args.get_one::<String>("url") {
+        return unnamed(FeedSource::Url(url.clone()));
+    }
+    if let Some(path) = args.get_one::<String>("file").or(args.get_one::<String>("dir")) {
+        return unnamed(FeedSource::Path(path.into()));
+    }
+    if let Some(name) = args.get_one::<String>("feed") {
+        return named(name);
+    }
+    // the feed can be looked up in the Mobility Database with --provider <name> or --mdb-id <id>
+    if let Some(query) = args.get_one::<String>("provider").or(args.get_one::<String>("mdb-id")) {
+        let refresh_token = std::env::var("MOBILITY_DATABASE_REFRESH_TOKEN").unwrap_or_else(
+            |_| fail("MOBILITY_DATABASE_REFRESH_TOKEN must be set to look feeds up in the Mobility Database")
+        );
+        log::info!("Looking up {} in the Mobility Database", query);
+        return unnamed(FeedSource::Url(
+            gtfs::catalog::MobilityDatabase::connect(&refresh_token)
+                .and_then(|catalog| catalog.resolve(query))
+                .unwrap_or_else(|err| fail(&format!("Failed to resolve feed {}: {}", query, err)))
+        ));
+    }
+    if let Ok(feed) = std::env::var(FEED_VARIABLE) {
+        return match config.feeds.contains_key(&feed) {
+            true => named(&feed),
+            false => unnamed(FeedSource::from(feed.as_str())),
+        };
+    }
+    match &config.default_feed {
+        Some(name) => named(name),
+        None => unnamed(FeedSource::Url(DEFAULT_FEED_URL.to_string())),
     }
 }
 
-fn pre_log(message: &str) {
-    print!("\r{}", iter::repeat(" ").take(80).collect::<String>());
-    print!("\r{}", message.truecolor(128, 128, 128));
-    io::stdout().flush().unwrap();
-}
\ No newline at end of file
+// subcommand_source gives the source of a feed given to a subcommand, which may be named in the config file, as with
+// RUST_GTFS_FEED, or else the feed the feed options choose, as the REPL would load.
+fn subcommand_source(args: &ArgMatches, id: &str, config: &Config) -> FeedSource {
+    match args.get_one::<String>(id) {
+        Some(feed) => named_source(feed, config),
+        None => feed_source(args, config).1,
+    }
+}
+
+fn named_source(feed: &str, config: &Config) -> FeedSource {
+    FeedSource::from(config.feeds.get(feed).map_or(feed, String::as_str))
+}
+
+// has_feed_option reports whether a feed was chosen with one of the feed options, e.g. --dir, rather than left to
+// RUST_GTFS_FEED or the config file.
+fn has_feed_option(args: &ArgMatches) -> bool {
+    ["url", "file", "dir", "feed", "provider", "mdb-id"].iter().any(|id| args.get_one::<String>(id).is_some())
+}
+
+// validate_feed loads the feed given to the validate subcommand, prints what's wrong with it and exits, with a
+// nonzero status if it fails, so that agencies can check a feed in their publishing pipelines before releasing it.
+fn validate_feed(args: &ArgMatches, config: &Config) -> ! {
+    let strict = args.get_flag("strict");
+    let report = load_feed(&subcommand_source(args, "checked", config)).validate();
+    validate::print_report(&report, strict);
+    std::process::exit(if report.passes(strict) { 0 } else { 1 });
+}
+
+// diff_feeds loads the two feeds given to the diff subcommand, prints how the second differs from the first, as the
+// table and ID given drill down into it, and exits. Where a feed option chooses the older feed, the arguments are the
+// newer feed and what to drill down into.
+fn diff_feeds(args: &ArgMatches, config: &Config) -> ! {
+    let mut positional = ["old", "new", "table", "id"].iter()
+        .filter_map(|id| args.get_one::<String>(id).map(String::as_str))
+        .collect::<Vec<_>>();
+    let needed = if has_feed_option(args) { 1 } else { 2 };
+    if positional.len() < needed {
+        fail("diff requires the feeds to compare, e.g. diff OLD NEW, or diff --dir OLD NEW");
+    }
+    let old = match needed {
+        1 => feed_source(args, config).1,
+        _ => named_source(positional.remove(0), config),
+    };
+    let new = named_source(positional.remove(0), config);
+    let drill_down = diff::drill_down(&positional)
+        .unwrap_or_else(|args| fail(&format!("Invalid diff arguments '{}': expected {} after the feeds", args, diff::DRILL_DOWN)));
+    let old = load_feed(&old);
+    let new = load_feed(&new);
+    diff::print_diff(&old.diff(&new), drill_down);
+    std::process::exit(0);
+}
+
+// bench_feed loads the feed given to the bench subcommand, timing each table, runs a set of typical queries on it and
+// prints how long each took and how much memory was used, as JSON with --json, and exits.
+fn bench_feed(args: &ArgMatches, config: &Config, json: bool) -> ! {
+    let source = subcommand_source(args, "benched", config);
+    let started = Instant::now();
+    let (mut gtfs, mut load) = gtfs::loaders::recorded(|| load_feed(&source));
+    let linking = Instant::now();
+    let _ = gtfs.link(gtfs::link::LinkPolicy::Warn);
+    load.push(("linking".to_string(), linking.elapsed()));
+    let loaded = started.elapsed();
+    let loaded_memory = bench::peak_memory();
+
+    let samples = args.get_one::<usize>("samples").copied().unwrap_or(bench::SAMPLES);
+    let queries = bench::run_queries(&gtfs, samples);
+    let benchmark = bench::Benchmark {
+        load,
+        loaded,
+        queries,
+        peak_memory: vec![("load", loaded_memory), ("queries", bench::peak_memory())],
+    };
+    benchmark.output().print(if json { OutputFormat::Json } else { OutputFormat::Text });
+    std::process::exit(0);
+}
+
+// run_script runs commands in turn and exits, skipping blank lines and comments beginning with #. The first command to
+// fail ends the run with a nonzero status, as sh -e would.
+fn run_script(repl: &mut Repl, commands: impl IntoIterator<Item = String>) -> ! {
+    for command in commands {
+        let command = command.trim();
+        if command.is_empty() || command.starts_with('#') {
+            continue;
+        }
+        if let Err(err) = repl.run(command) {
+            eprintln!("Error interpreting command {}: {}", command, err);
+            std::process::exit(1);
+        }
+    }
+    std::process::exit(0);
+}
+
+// fail prints why the tool can't go on and exits with a nonzero status.
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+// load_feed loads the feed from source, downloading it if it's a URL and showing progress as it goes.
+fn load_feed(source: &FeedSource) -> gtfs::GtfsSchedule {
+    match source {
+        FeedSource::Url(url) => {
+            let started = Instant::now();
+            log::info!("Downloading {}", url);
+            let progress_bar = ProgressBar::new("Downloading");
+            let buf = gtfs::loaders::url_loader::UrlLoader::new(url)
+                .with_progress_handler(move |progress| progress_bar.update(progress.downloaded, progress.total))
+                .with_retry_handler(|attempt, err, backoff| log::warn!(
+                    "Download attempt {} failed ({}); retrying in {:.1}s", attempt, err, backoff.as_secs_f64()
+                ))
+                .download()
+                .unwrap_or_else(
+                    |err| fail(&format!("Failed to download {}: {}", url, err))
+                );
+            log::info!("Downloaded GTFS feed: {} bytes in {:.1?}", buf.len(), started.elapsed());
+            load_archive(buf)
+        },
+        FeedSource::Path(path) if path.is_dir() || SqliteStore::is_store(path) => {
+            log::info!("Reading {}", source);
+            let spinner = Spinner::start(&format!("Parsing {}", source));
+            let loaded = source.load();
+            // the spinner is cleared before any error is printed, so that it doesn't end up on the same line.
+            drop(spinner);
+            loaded.unwrap_or_else(|err| fail(&format!("Failed to load {}: {}", source, err)))
+        },
+        FeedSource::Path(path) => {
+            log::info!("Reading {}", source);
+            load_archive(std::fs::read(path).unwrap_or_else(|err| fail(&format!("Failed to read {}: {}", source, err))))
+        },
+    }
+}
+
+// load_archive loads a feed held in memory, showing which table is being parsed. Feeds published as tarballs are
+// recognized by the gzip magic number; everything else is interpreted as a zip archive.
+fn load_archive(buf: Vec<u8>) -> gtfs::GtfsSchedule {
+    let spinner = Spinner::start("Parsing feed");
+    let loaded = match buf.starts_with(&[0x1f, 0x8b]) {
+        true => gtfs::loaders::tar_gz_loader::TarGzLoader::new(std::io::Cursor::new(buf))
+            .load()
+            .map_err(|err| format!("Failed to create gtfs feed: {}", err)),
+        false => zip::ZipArchive::new(std::io::Cursor::new(buf))
+            .map_err(|err| format!("Failed to create zip archive: {}", err))
+            .and_then(|gtfs_zip| gtfs::loaders::zip_loader::ZipLoader::new(gtfs_zip)
+                .with_event_handler(&spinner)
+                .load()
+                .map_err(|err| format!("Failed to create gtfs feed: {}", err))),
+    };
+    drop(spinner);
+    loaded.unwrap_or_else(|err| fail(&err))
+}
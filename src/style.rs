@@ -0,0 +1,168 @@
+// style is where output is colored. Commands style text with the Colorize and Paint traits from here rather than
+// with colored directly, so that color can be turned off, or limited to what the terminal supports, in one place.
+
+// With the cli feature, output is colored with colored's Colorize trait.
+#[cfg(feature = "cli")]
+pub use colored::Colorize;
+
+#[cfg(feature = "cli")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "cli")]
+use std::sync::Mutex;
+
+// ColorChoice is when output is colored: by default, when it's going to a terminal and NO_COLOR isn't set.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[cfg(feature = "cli")]
+impl std::str::FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!("Invalid color choice '{}': expected auto, always or never", s)),
+        }
+    }
+}
+
+// TRUECOLOR is whether colors given as RGB are written as such. Terminals generally support them, but a pager or
+// file may not, so elsewhere they're written as the nearest of the 16 standard colors.
+#[cfg(feature = "cli")]
+static TRUECOLOR: AtomicBool = AtomicBool::new(true);
+
+// CHOICE is the choice last given to set_color_choice, so that it can be made again when stdout changes.
+#[cfg(feature = "cli")]
+static CHOICE: Mutex<ColorChoice> = Mutex::new(ColorChoice::Auto);
+
+// set_color_choice turns color on or off for everything written from then on, following
+// https://no-color.org in choosing automatically.
+#[cfg(feature = "cli")]
+pub fn set_color_choice(choice: ColorChoice) {
+    use std::io::IsTerminal;
+    *CHOICE.lock().expect("the color choice is never poisoned") = choice;
+    let terminal = std::io::stdout().is_terminal();
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => terminal && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()),
+    };
+    colored::control::set_override(enabled);
+    TRUECOLOR.store(terminal, Ordering::Relaxed);
+}
+
+// reapply_color_choice makes the last color choice again for where stdout now goes, e.g. so that output the REPL
+// redirects to a file isn't colored unless color was chosen always.
+#[cfg(feature = "cli")]
+pub fn reapply_color_choice() {
+    let choice = *CHOICE.lock().expect("the color choice is never poisoned");
+    set_color_choice(choice);
+}
+
+// Paint colors text with RGB colors, which are the ones feeds give, such as route colors, and the grey used for
+// secondary text.
+#[cfg(feature = "cli")]
+pub trait Paint: Colorize + Sized {
+    fn rgb(self, r: u8, g: u8, b: u8) -> colored::ColoredString {
+        match TRUECOLOR.load(Ordering::Relaxed) {
+            true => self.truecolor(r, g, b),
+            false => self.color(nearest_standard_color(r, g, b)),
+        }
+    }
+
+    fn on_rgb(self, r: u8, g: u8, b: u8) -> colored::ColoredString {
+        match TRUECOLOR.load(Ordering::Relaxed) {
+            true => self.on_truecolor(r, g, b),
+            false => self.on_color(nearest_standard_color(r, g, b)),
+        }
+    }
+
+    // muted shows text which is there for context, such as headings, notes and IDs beside names, in grey.
+    fn muted(self) -> colored::ColoredString {
+        self.rgb(128, 128, 128)
+    }
+}
+
+#[cfg(feature = "cli")]
+impl<T: Colorize> Paint for T {}
+
+// nearest_standard_color finds the standard color closest to an RGB color, taking the standard colors to look as
+// they do in xterm.
+#[cfg(feature = "cli")]
+fn nearest_standard_color(r: u8, g: u8, b: u8) -> colored::Color {
+    use colored::Color;
+    const STANDARD_COLORS: [(Color, (i32, i32, i32)); 16] = [
+        (Color::Black, (0, 0, 0)),
+        (Color::Red, (205, 0, 0)),
+        (Color::Green, (0, 205, 0)),
+        (Color::Yellow, (205, 205, 0)),
+        (Color::Blue, (0, 0, 238)),
+        (Color::Magenta, (205, 0, 205)),
+        (Color::Cyan, (0, 205, 205)),
+        (Color::White, (229, 229, 229)),
+        (Color::BrightBlack, (127, 127, 127)),
+        (Color::BrightRed, (255, 0, 0)),
+        (Color::BrightGreen, (0, 255, 0)),
+        (Color::BrightYellow, (255, 255, 0)),
+        (Color::BrightBlue, (92, 92, 255)),
+        (Color::BrightMagenta, (255, 0, 255)),
+        (Color::BrightCyan, (0, 255, 255)),
+        (Color::BrightWhite, (255, 255, 255)),
+    ];
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+    STANDARD_COLORS.iter()
+        .min_by_key(|(_, (sr, sg, sb))| (r - sr).pow(2) + (g - sg).pow(2) + (b - sb).pow(2))
+        .map(|(color, _)| *color)
+        .expect("there are standard colors")
+}
+
+// Without it, this stand-in for the subset of Colorize the library uses leaves text unstyled,
+// so that Display implementations don't need to know whether the feature is enabled.
+#[cfg(not(feature = "cli"))]
+pub trait Colorize: ToString + Sized {
+    fn bold(self) -> String {
+        self.to_string()
+    }
+
+    fn green(self) -> String {
+        self.to_string()
+    }
+
+    fn red(self) -> String {
+        self.to_string()
+    }
+
+    fn yellow(self) -> String {
+        self.to_string()
+    }
+}
+
+#[cfg(not(feature = "cli"))]
+impl<T: ToString> Colorize for T {}
+
+#[cfg(not(feature = "cli"))]
+pub trait Paint: ToString + Sized {
+    fn rgb(self, _r: u8, _g: u8, _b: u8) -> String {
+        self.to_string()
+    }
+
+    fn on_rgb(self, _r: u8, _g: u8, _b: u8) -> String {
+        self.to_string()
+    }
+
+    fn muted(self) -> String {
+        self.to_string()
+    }
+}
+
+#[cfg(not(feature = "cli"))]
+impl<T: ToString> Paint for T {}